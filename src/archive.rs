@@ -0,0 +1,229 @@
+//! Files of archives fetched into the cache, for
+//! [`include_crate_file!`](crate::include_crate_file),
+//! [`include_zip_url!`](crate::include_zip_url) and
+//! [`include_tar_url!`](crate::include_tar_url).
+//!
+//! An archive is unpacked once into a directory next to its cache file, along with a stamp
+//! of the size and modification time of the archive it was unpacked from, and the
+//! directory is reused until the archive is fetched again. Only regular files are
+//! unpacked, and entries whose path would leave the directory are rejected. Tar archives
+//! are told to be gzip or zstd compressed by their content rather than their name.
+//!
+//! Files are selected by their path in the archive, or by a glob pattern, where `*`
+//! does not match across directories and `**` does.
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Read},
+    path::{Component, Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+/// Formats of archives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Format {
+    /// A tar archive, uncompressed, gzip compressed such as a `.crate` package, or zstd
+    /// compressed with the `zstd` feature.
+    Tar,
+    /// A zip archive.
+    Zip,
+}
+
+/// Files selected in an unpacked archive.
+pub(crate) enum Selection {
+    /// The file or directory at an exact path.
+    Path(PathBuf),
+    /// The files matching a glob pattern, with their paths in the archive, sorted.
+    Matches(Vec<(String, PathBuf)>),
+}
+
+/// Unpacks a cached archive, or reuses its unpacked directory, and returns the directory.
+pub(crate) fn unpack(archive: &Path, format: Format) -> Result<PathBuf, String> {
+    let metadata =
+        std::fs::metadata(archive).map_err(|e| format!("Failed to open cache file: {}", e))?;
+    let modified = (metadata.modified().ok())
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .unwrap_or_default();
+    let stamp = format!("{} {}", metadata.len(), modified.as_nanos());
+
+    let mut dir = archive.as_os_str().to_owned();
+    dir.push(".files");
+    let dir = PathBuf::from(dir);
+    let stamp_file = dir.with_extension("stamp");
+    if dir.is_dir() && std::fs::read_to_string(&stamp_file).is_ok_and(|read| read == stamp) {
+        return Ok(dir);
+    }
+
+    let work = dir.with_extension("tmp");
+    let _ = std::fs::remove_dir_all(&work);
+    let unpacked = File::open(archive)
+        .map_err(|e| format!("Failed to open cache file: {}", e))
+        .and_then(|file| match format {
+            Format::Tar => unpack_tar(decompress(BufReader::new(file))?, &work),
+            Format::Zip => unpack_zip(file, &work),
+        })
+        .and_then(|_| {
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::rename(&work, &dir).map_err(|e| format!("Failed to write cache entry: {}", e))
+        });
+    if unpacked.is_err() {
+        let _ = std::fs::remove_dir_all(&work);
+    }
+    unpacked?;
+    std::fs::write(&stamp_file, stamp)
+        .map_err(|e| format!("Failed to write cache entry: {}", e))?;
+    Ok(dir)
+}
+
+/// Decompresses a gzip or zstd compressed archive, told apart by their magic numbers.
+fn decompress(mut reader: BufReader<File>) -> Result<Box<dyn Read>, String> {
+    let magic = reader
+        .fill_buf()
+        .map_err(|e| format!("Failed to read cache file: {}", e))?;
+    match magic {
+        [0x1F, 0x8B, ..] => Ok(Box::new(flate2::read::GzDecoder::new(reader))),
+        #[cfg(feature = "zstd")]
+        [0x28, 0xB5, 0x2F, 0xFD, ..] => zstd::Decoder::with_buffer(reader)
+            .map(|decoder| Box::new(decoder) as Box<dyn Read>)
+            .map_err(|e| format!("Invalid archive: {}", e)),
+        #[cfg(not(feature = "zstd"))]
+        [0x28, 0xB5, 0x2F, 0xFD, ..] => {
+            Err("zstd compressed archives require the `zstd` feature".to_string())
+        }
+        _ => Ok(Box::new(reader)),
+    }
+}
+
+/// Unpacks the regular files of a tar archive into `dir`.
+fn unpack_tar(reader: impl Read, dir: &Path) -> Result<(), String> {
+    let invalid = |e: std::io::Error| format!("Invalid archive: {}", e);
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries().map_err(invalid)? {
+        let mut entry = entry.map_err(invalid)?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry.path().map_err(invalid)?.into_owned();
+        let target = dir.join(relative(&path)?);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create cache directory: {}", e))?;
+        }
+        let mut file =
+            File::create(&target).map_err(|e| format!("Failed to write cache entry: {}", e))?;
+        std::io::copy(&mut entry, &mut file)
+            .map_err(|e| format!("Failed to unpack `{}`: {}", path.display(), e))?;
+    }
+    Ok(())
+}
+
+/// Unpacks the regular files of a zip archive into `dir`.
+fn unpack_zip(file: File, dir: &Path) -> Result<(), String> {
+    let invalid = |e: zip::result::ZipError| format!("Invalid archive: {}", e);
+    let mut archive = zip::ZipArchive::new(BufReader::new(file)).map_err(invalid)?;
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index).map_err(invalid)?;
+        if !entry.is_file() {
+            continue;
+        }
+        let name = entry.name().to_string();
+        let target = dir.join(relative(Path::new(&name))?);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create cache directory: {}", e))?;
+        }
+        let mut file =
+            File::create(&target).map_err(|e| format!("Failed to write cache entry: {}", e))?;
+        std::io::copy(&mut entry, &mut file)
+            .map_err(|e| format!("Failed to unpack `{}`: {}", name, e))?;
+    }
+    Ok(())
+}
+
+/// Selects the file or directory at `path` in an unpacked archive, or the files matching
+/// it when it is a glob pattern.
+pub(crate) fn select(dir: &Path, path: &str, source: &str) -> Result<Selection, String> {
+    let path = path.trim_start_matches('/');
+    if !path.contains(['*', '?', '[']) {
+        let found = dir.join(relative(Path::new(path))?);
+        if path.is_empty() || !found.exists() {
+            return Err(format!("No `{}` in `{}`", path, source));
+        }
+        return Ok(Selection::Path(found));
+    }
+    let matching = crate::dir::matcher(path)?;
+    let matches: Vec<_> = crate::git::files(dir)
+        .into_iter()
+        .filter(|(name, _)| matching(name))
+        .collect();
+    if matches.is_empty() {
+        return Err(format!("No file of `{}` matches `{}`", source, path));
+    }
+    Ok(Selection::Matches(matches))
+}
+
+/// Checks that the path of an entry stays within the directory it is unpacked into.
+fn relative(path: &Path) -> Result<PathBuf, String> {
+    let mut relative = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => relative.push(part),
+            Component::CurDir => {}
+            _ => {
+                return Err(format!(
+                    "Invalid archive: the entry `{}` leaves the archive",
+                    path.display()
+                ))
+            }
+        }
+    }
+    Ok(relative)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that an archive is unpacked once, and rejected when an entry leaves it
+    #[test]
+    fn test_unpack() {
+        let dir = std::env::temp_dir().join("include_url_archive_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let archive = dir.join("package.crate");
+
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            File::create(&archive).unwrap(),
+            flate2::Compression::default(),
+        ));
+        let mut header = tar::Header::new_gnu();
+        header.set_size(5);
+        header.set_mode(0o644);
+        builder
+            .append_data(&mut header, "package-1.0.0/src/grammar.pest", &b"rule!"[..])
+            .unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let unpacked = unpack(&archive, Format::Tar).unwrap();
+        let file = unpacked.join("package-1.0.0/src/grammar.pest");
+        assert_eq!(std::fs::read(&file).unwrap(), b"rule!");
+        std::fs::write(&file, "reused").unwrap();
+        assert_eq!(unpack(&archive, Format::Tar).unwrap(), unpacked);
+        assert_eq!(std::fs::read(&file).unwrap(), b"reused");
+
+        match select(&unpacked, "package-1.0.0/**/*.pest", "package.crate").unwrap() {
+            Selection::Matches(matches) => {
+                assert_eq!(matches[0].0, "package-1.0.0/src/grammar.pest")
+            }
+            Selection::Path(_) => panic!("expected matches"),
+        }
+        assert!(select(&unpacked, "package-1.0.0/*.pest", "package.crate").is_err());
+        assert!(select(&unpacked, "package-1.0.0/README.md", "package.crate").is_err());
+        assert!(relative(Path::new("package/../../escape")).is_err());
+        assert_eq!(
+            relative(Path::new("./package/a")).unwrap(),
+            Path::new("package/a")
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
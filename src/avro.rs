@@ -0,0 +1,566 @@
+//! Apache Avro support for `include_avro_url!`: schema parsing, type generation and
+//! decoding of object container files.
+
+use std::io::Read;
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use serde_json::Value;
+
+use crate::{snake_ident, typegen::pascal_case};
+
+/// The magic bytes every object container file starts with.
+const MAGIC: &[u8] = b"Obj\x01";
+
+/// An Avro schema, with named types stored in [`Schemas`].
+#[derive(Debug, Clone, PartialEq)]
+enum Schema {
+    Null,
+    Boolean,
+    Int,
+    Long,
+    Float,
+    Double,
+    Bytes,
+    String,
+    Array(Box<Schema>),
+    Map(Box<Schema>),
+    Union(Vec<Schema>),
+    /// A record, enum or fixed type, by index.
+    Named(usize),
+}
+
+#[derive(Debug)]
+struct Named {
+    /// The full name, including the namespace.
+    name: String,
+    doc: Option<String>,
+    kind: NamedKind,
+}
+
+#[derive(Debug)]
+enum NamedKind {
+    Record(Vec<Field>),
+    Enum(Vec<String>),
+    Fixed(usize),
+}
+
+#[derive(Debug)]
+struct Field {
+    name: String,
+    doc: Option<String>,
+    schema: Schema,
+}
+
+/// The named types of a schema, in the order they are defined.
+#[derive(Debug, Default)]
+struct Schemas {
+    named: Vec<Named>,
+}
+
+impl Schemas {
+    /// Parses a schema in its JSON form.
+    fn parse(&mut self, value: &Value, namespace: Option<&str>) -> Result<Schema, String> {
+        match value {
+            Value::String(name) => self.parse_name(name, namespace),
+            Value::Array(schemas) => schemas
+                .iter()
+                .map(|schema| self.parse(schema, namespace))
+                .collect::<Result<_, _>>()
+                .map(Schema::Union),
+            Value::Object(object) => {
+                let ty = object.get("type").ok_or("A schema object has no `type`")?;
+                let Value::String(ty) = ty else {
+                    return self.parse(ty, namespace);
+                };
+                let items = |key: &str| object.get(key).ok_or(format!("`{}` has no `{}`", ty, key));
+                match ty.as_str() {
+                    "array" => Ok(Schema::Array(Box::new(
+                        self.parse(items("items")?, namespace)?,
+                    ))),
+                    "map" => Ok(Schema::Map(Box::new(
+                        self.parse(items("values")?, namespace)?,
+                    ))),
+                    "record" | "error" | "enum" | "fixed" => self.parse_named(object, namespace),
+                    ty => self.parse_name(ty, namespace),
+                }
+            }
+            _ => Err(format!("Invalid schema `{}`", value)),
+        }
+    }
+
+    /// Parses a primitive type name or a reference to a named type.
+    fn parse_name(&self, name: &str, namespace: Option<&str>) -> Result<Schema, String> {
+        Ok(match name {
+            "null" => Schema::Null,
+            "boolean" => Schema::Boolean,
+            "int" => Schema::Int,
+            "long" => Schema::Long,
+            "float" => Schema::Float,
+            "double" => Schema::Double,
+            "bytes" => Schema::Bytes,
+            "string" => Schema::String,
+            name => {
+                let full_name = full_name(name, namespace);
+                let index = self
+                    .named
+                    .iter()
+                    .position(|named| named.name == full_name || named.name == name)
+                    .ok_or_else(|| format!("Unknown type `{}`", name))?;
+                Schema::Named(index)
+            }
+        })
+    }
+
+    /// Parses the definition of a record, enum or fixed type.
+    fn parse_named(
+        &mut self,
+        object: &serde_json::Map<String, Value>,
+        namespace: Option<&str>,
+    ) -> Result<Schema, String> {
+        let string = |key: &str| object.get(key).and_then(Value::as_str);
+        let name = string("name").ok_or("A named type has no `name`")?;
+        let namespace = string("namespace").or(namespace);
+        let name = full_name(name, namespace);
+        let namespace = name
+            .rsplit_once('.')
+            .map(|(namespace, _)| namespace.to_string());
+        let doc = string("doc").map(str::to_string);
+        let index = self.named.len();
+
+        let kind = match string("type") {
+            Some("enum") => NamedKind::Enum(
+                object
+                    .get("symbols")
+                    .and_then(Value::as_array)
+                    .ok_or_else(|| format!("The enum `{}` has no `symbols`", name))?
+                    .iter()
+                    .map(|symbol| symbol.as_str().map(str::to_string))
+                    .collect::<Option<_>>()
+                    .ok_or_else(|| format!("The enum `{}` has invalid symbols", name))?,
+            ),
+            Some("fixed") => NamedKind::Fixed(
+                object
+                    .get("size")
+                    .and_then(Value::as_u64)
+                    .ok_or_else(|| format!("The fixed type `{}` has no `size`", name))?
+                    as usize,
+            ),
+            // Registered before its fields are parsed, so they can refer to the record
+            _ => NamedKind::Record(Vec::new()),
+        };
+        self.named.push(Named { name, doc, kind });
+
+        if let Some("record" | "error") = string("type") {
+            let fields = object
+                .get("fields")
+                .and_then(Value::as_array)
+                .ok_or_else(|| {
+                    format!("The record `{}` has no `fields`", self.named[index].name)
+                })?;
+            let mut parsed = Vec::new();
+            for field in fields {
+                let name = field
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .ok_or("A record field has no `name`")?;
+                let schema = field
+                    .get("type")
+                    .ok_or_else(|| format!("The field `{}` has no `type`", name))?;
+                parsed.push(Field {
+                    name: name.to_string(),
+                    doc: field.get("doc").and_then(Value::as_str).map(str::to_string),
+                    schema: self.parse(schema, namespace.as_deref())?,
+                });
+            }
+            self.named[index].kind = NamedKind::Record(parsed);
+        }
+        Ok(Schema::Named(index))
+    }
+
+    /// Generates the Rust items for every named type.
+    fn items(&self) -> Result<Vec<TokenStream>, String> {
+        let mut items = Vec::new();
+        for (index, named) in self.named.iter().enumerate() {
+            let ident = self.ident(index)?;
+            let doc = named.doc.as_ref().map(|doc| quote! { #[doc = #doc] });
+            items.push(match &named.kind {
+                NamedKind::Record(fields) => {
+                    let fields = fields
+                        .iter()
+                        .map(|field| {
+                            let name = &field.name;
+                            let field_ident = snake_ident(name).ok_or_else(|| {
+                                format!("The field `{}` has no Rust name", name)
+                            })?;
+                            let ty = self.rust_type(&field.schema, Some(index))?;
+                            let doc = field.doc.as_ref().map(|doc| quote! { #[doc = #doc] });
+                            Ok(quote! {
+                                #doc
+                                #[serde(rename = #name)]
+                                pub #field_ident: #ty,
+                            })
+                        })
+                        .collect::<Result<Vec<_>, String>>()?;
+                    quote! {
+                        #doc
+                        #[derive(Debug, Clone, PartialEq, ::serde::Serialize, ::serde::Deserialize)]
+                        pub struct #ident { #(#fields)* }
+                    }
+                }
+                NamedKind::Enum(symbols) => {
+                    let variants = symbols.iter().map(|symbol| {
+                        let variant = format_ident!("{}", pascal_case(symbol));
+                        quote! { #[serde(rename = #symbol)] #variant, }
+                    });
+                    quote! {
+                        #doc
+                        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ::serde::Serialize, ::serde::Deserialize)]
+                        pub enum #ident { #(#variants)* }
+                    }
+                }
+                NamedKind::Fixed(_) => quote! {
+                    #doc
+                    pub type #ident = ::std::vec::Vec<u8>;
+                },
+            });
+        }
+        Ok(items)
+    }
+
+    /// Returns the Rust name of a named type, its name without the namespace.
+    fn ident(&self, index: usize) -> Result<proc_macro2::Ident, String> {
+        let name = &self.named[index].name;
+        let name = name.rsplit('.').next().unwrap_or(name);
+        syn::parse_str(name).map_err(|_| format!("`{}` is not a valid type name", name))
+    }
+
+    /// Maps a schema to a Rust type. References from a record to itself or an enclosing
+    /// record are boxed to break the cycle.
+    fn rust_type(&self, schema: &Schema, record: Option<usize>) -> Result<TokenStream, String> {
+        Ok(match schema {
+            Schema::Null => quote! { () },
+            Schema::Boolean => quote! { bool },
+            Schema::Int => quote! { i32 },
+            Schema::Long => quote! { i64 },
+            Schema::Float => quote! { f32 },
+            Schema::Double => quote! { f64 },
+            Schema::Bytes => quote! { ::std::vec::Vec<u8> },
+            Schema::String => quote! { ::std::string::String },
+            Schema::Array(items) => {
+                let ty = self.rust_type(items, record)?;
+                quote! { ::std::vec::Vec<#ty> }
+            }
+            Schema::Map(values) => {
+                let ty = self.rust_type(values, record)?;
+                quote! { ::std::collections::BTreeMap<::std::string::String, #ty> }
+            }
+            Schema::Union(schemas) => match optional(schemas) {
+                Some(schema) => {
+                    let ty = self.rust_type(schema, record)?;
+                    quote! { ::core::option::Option<#ty> }
+                }
+                None => quote! { ::serde_json::Value },
+            },
+            Schema::Named(index) => {
+                let ident = self.ident(*index)?;
+                match &self.named[*index].kind {
+                    NamedKind::Record(_) if record.is_some_and(|record| *index <= record) => {
+                        quote! { ::std::boxed::Box<#ident> }
+                    }
+                    _ => quote! { #ident },
+                }
+            }
+        })
+    }
+
+    /// Decodes a value of the given schema from its binary encoding.
+    fn decode(&self, schema: &Schema, input: &mut &[u8]) -> Result<Value, String> {
+        Ok(match schema {
+            Schema::Null => Value::Null,
+            Schema::Boolean => Value::Bool(take(input, 1)?[0] != 0),
+            Schema::Int | Schema::Long => Value::from(read_long(input)?),
+            Schema::Float => {
+                let bytes = take(input, 4)?.try_into().unwrap();
+                Value::from(f32::from_le_bytes(bytes))
+            }
+            Schema::Double => {
+                let bytes = take(input, 8)?.try_into().unwrap();
+                Value::from(f64::from_le_bytes(bytes))
+            }
+            Schema::Bytes => Value::from(read_bytes(input)?.to_vec()),
+            Schema::String => Value::from(
+                std::str::from_utf8(read_bytes(input)?)
+                    .map_err(|_| "A string is not valid UTF-8")?,
+            ),
+            Schema::Array(items) => {
+                let mut values = Vec::new();
+                read_blocks(input, |input| {
+                    values.push(self.decode(items, input)?);
+                    Ok(())
+                })?;
+                Value::Array(values)
+            }
+            Schema::Map(values) => {
+                let mut map = serde_json::Map::new();
+                read_blocks(input, |input| {
+                    let key = std::str::from_utf8(read_bytes(input)?)
+                        .map_err(|_| "A map key is not valid UTF-8")?
+                        .to_string();
+                    map.insert(key, self.decode(values, input)?);
+                    Ok(())
+                })?;
+                Value::Object(map)
+            }
+            Schema::Union(schemas) => {
+                let index = read_long(input)?;
+                let schema = usize::try_from(index)
+                    .ok()
+                    .and_then(|index| schemas.get(index))
+                    .ok_or_else(|| format!("Invalid union branch {}", index))?;
+                self.decode(schema, input)?
+            }
+            Schema::Named(index) => match &self.named[*index].kind {
+                NamedKind::Record(fields) => {
+                    let mut object = serde_json::Map::new();
+                    for field in fields {
+                        object.insert(field.name.clone(), self.decode(&field.schema, input)?);
+                    }
+                    Value::Object(object)
+                }
+                NamedKind::Enum(symbols) => {
+                    let index = read_long(input)?;
+                    let symbol = usize::try_from(index)
+                        .ok()
+                        .and_then(|index| symbols.get(index))
+                        .ok_or_else(|| format!("Invalid enum symbol {}", index))?;
+                    Value::from(symbol.as_str())
+                }
+                NamedKind::Fixed(size) => Value::from(take(input, *size)?.to_vec()),
+            },
+        })
+    }
+}
+
+/// Returns the non-null branch of a `["null", T]` or `[T, "null"]` union.
+fn optional(schemas: &[Schema]) -> Option<&Schema> {
+    match schemas {
+        [Schema::Null, schema] | [schema, Schema::Null] => Some(schema),
+        _ => None,
+    }
+}
+
+fn full_name(name: &str, namespace: Option<&str>) -> String {
+    match namespace {
+        Some(namespace) if !name.contains('.') && !namespace.is_empty() => {
+            format!("{}.{}", namespace, name)
+        }
+        _ => name.to_string(),
+    }
+}
+
+fn take<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8], String> {
+    if input.len() < len {
+        return Err("Unexpected end of Avro data".to_string());
+    }
+    let (bytes, rest) = input.split_at(len);
+    *input = rest;
+    Ok(bytes)
+}
+
+/// Reads a zigzag encoded variable length integer.
+fn read_long(input: &mut &[u8]) -> Result<i64, String> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = take(input, 1)?[0];
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok((value >> 1) as i64 ^ -((value & 1) as i64));
+        }
+    }
+    Err("Invalid variable length integer in Avro data".to_string())
+}
+
+fn read_bytes<'a>(input: &mut &'a [u8]) -> Result<&'a [u8], String> {
+    let len = read_long(input)?;
+    let len = usize::try_from(len).map_err(|_| format!("Invalid length {}", len))?;
+    take(input, len)
+}
+
+/// Reads the blocks of an array or map, calling `item` for every item.
+fn read_blocks<'a>(
+    input: &mut &'a [u8],
+    mut item: impl FnMut(&mut &'a [u8]) -> Result<(), String>,
+) -> Result<(), String> {
+    loop {
+        let count = read_long(input)?;
+        if count == 0 {
+            return Ok(());
+        }
+        if count < 0 {
+            // A negative count is followed by the size of the block in bytes
+            read_long(input)?;
+        }
+        for _ in 0..count.unsigned_abs() {
+            item(input)?;
+        }
+    }
+}
+
+/// Types generated for an Avro schema or object container file.
+pub(crate) struct Generated {
+    /// The generated items.
+    pub(crate) items: Vec<TokenStream>,
+    /// The Rust type of the top-level schema.
+    pub(crate) root: TokenStream,
+    /// The records of a container file, as a JSON array.
+    pub(crate) records: Option<String>,
+}
+
+/// Generates types for an `.avsc` schema, or for the schema of an object container file
+/// along with its decoded records.
+pub(crate) fn generate(content: &[u8]) -> Result<Generated, String> {
+    let mut schemas = Schemas::default();
+    if !content.starts_with(MAGIC) {
+        let schema: Value = serde_json::from_slice(content)
+            .map_err(|e| format!("Invalid Avro schema from URL: {}", e))?;
+        let root = schemas
+            .parse(&schema, None)
+            .map_err(|e| format!("Invalid Avro schema from URL: {}", e))?;
+        return Ok(Generated {
+            items: schemas.items()?,
+            root: schemas.rust_type(&root, None)?,
+            records: None,
+        });
+    }
+
+    let (root, records) = decode_container(&mut schemas, &content[MAGIC.len()..])
+        .map_err(|e| format!("Invalid Avro container file from URL: {}", e))?;
+    Ok(Generated {
+        items: schemas.items()?,
+        root: schemas.rust_type(&root, None)?,
+        records: Some(Value::Array(records).to_string()),
+    })
+}
+
+/// Decodes an object container file after its magic bytes.
+fn decode_container(
+    schemas: &mut Schemas,
+    mut input: &[u8],
+) -> Result<(Schema, Vec<Value>), String> {
+    let mut metadata = serde_json::Map::new();
+    read_blocks(&mut input, |input| {
+        let key = String::from_utf8_lossy(read_bytes(input)?).into_owned();
+        let value = String::from_utf8_lossy(read_bytes(input)?).into_owned();
+        metadata.insert(key, Value::String(value));
+        Ok(())
+    })?;
+    let schema = metadata
+        .get("avro.schema")
+        .and_then(Value::as_str)
+        .ok_or("The header has no schema")?;
+    let schema: Value = serde_json::from_str(schema).map_err(|e| e.to_string())?;
+    let root = schemas.parse(&schema, None)?;
+    let codec = metadata
+        .get("avro.codec")
+        .and_then(Value::as_str)
+        .unwrap_or("null");
+    let sync = take(&mut input, 16)?;
+
+    let mut records = Vec::new();
+    while !input.is_empty() {
+        let count = read_long(&mut input)?;
+        let data = read_bytes(&mut input)?;
+        let data = match codec {
+            "null" => data.to_vec(),
+            "deflate" => {
+                let mut inflated = Vec::new();
+                flate2::read::DeflateDecoder::new(data)
+                    .read_to_end(&mut inflated)
+                    .map_err(|e| format!("Failed to inflate a block: {}", e))?;
+                inflated
+            }
+            codec => return Err(format!("Unsupported codec `{}`", codec)),
+        };
+        let mut block = data.as_slice();
+        for _ in 0..count {
+            records.push(schemas.decode(&root, &mut block)?);
+        }
+        if take(&mut input, 16)? != sync {
+            return Err("A block does not end with the sync marker".to_string());
+        }
+    }
+    Ok((root, records))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: &str = r#"{
+        "type": "record",
+        "name": "Node",
+        "namespace": "demo",
+        "fields": [
+            {"name": "label", "type": "string"},
+            {"name": "kind", "type": {"type": "enum", "name": "Kind", "symbols": ["LEAF", "BRANCH"]}},
+            {"name": "next", "type": ["null", "Node"]}
+        ]
+    }"#;
+
+    /// Test that recursive records are boxed and enums generated
+    #[test]
+    fn test_generate_schema() {
+        let generated = generate(SCHEMA.as_bytes()).unwrap();
+        let items = generated.items;
+        let code = quote! { #(#items)* }.to_string();
+        assert!(code.contains(
+            "pub next : :: core :: option :: Option < :: std :: boxed :: Box < Node > >"
+        ));
+        assert!(code.contains("pub enum Kind"));
+        assert_eq!(generated.root.to_string(), "Node");
+        assert!(generated.records.is_none());
+
+        assert!(generate(
+            br#"{"type": "record", "name": "A", "fields": [{"name": "b", "type": "B"}]}"#
+        )
+        .is_err());
+    }
+
+    /// Appends a zigzag encoded variable length integer.
+    fn write_long(out: &mut Vec<u8>, value: i64) {
+        let mut value = ((value << 1) ^ (value >> 63)) as u64;
+        while value >= 0x80 {
+            out.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        out.push(value as u8);
+    }
+
+    /// Test that the records of an uncompressed container file are decoded
+    #[test]
+    fn test_decode_container() {
+        let sync = [7u8; 16];
+        let mut file = MAGIC.to_vec();
+        // Metadata map with one entry: avro.schema
+        write_long(&mut file, 1);
+        for bytes in [b"avro.schema".as_slice(), SCHEMA.as_bytes()] {
+            write_long(&mut file, bytes.len() as i64);
+            file.extend(bytes);
+        }
+        file.push(0);
+        file.extend(sync);
+        // One block of two records: {"a", LEAF, null} and {"b", BRANCH, null}
+        let records = [2, b'a', 0, 0, 2, b'b', 2, 0];
+        write_long(&mut file, 2);
+        write_long(&mut file, records.len() as i64);
+        file.extend(records);
+        file.extend(sync);
+
+        let generated = generate(&file).unwrap();
+        assert_eq!(
+            generated.records.unwrap(),
+            r#"[{"kind":"LEAF","label":"a","next":null},{"kind":"BRANCH","label":"b","next":null}]"#
+        );
+    }
+}
@@ -0,0 +1,170 @@
+//! Blobs of Azure Storage, `azblob://account/container/blob`.
+//!
+//! Blobs are read from the Blob service of the account, authenticated with a shared access
+//! signature, either the SAS token held by `AZURE_STORAGE_SAS_TOKEN` or one signed for the
+//! blob with the account key held by `AZURE_STORAGE_KEY`, the variables the Azure CLI reads.
+//! Signing a token rather than every request lets range and conditional requests, retries
+//! and redirects go through unchanged. Without either variable, blobs are read anonymously,
+//! which works for public containers.
+
+use std::{
+    env,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ring::hmac;
+use url::Url;
+
+/// Environment variable holding a SAS token.
+const SAS_TOKEN_VAR: &str = "AZURE_STORAGE_SAS_TOKEN";
+
+/// Environment variable holding the base64 encoded key of the storage account.
+const KEY_VAR: &str = "AZURE_STORAGE_KEY";
+
+/// Environment variable replacing the Blob service URL of the account, such as
+/// `http://127.0.0.1:10000/devstoreaccount1` for Azurite.
+pub(crate) const ENDPOINT_VAR: &str = "INCLUDE_URL_AZURE_ENDPOINT";
+
+/// Version of the signed access signatures.
+const SAS_VERSION: &str = "2020-12-06";
+
+/// Lifetime of the signed access signatures, in seconds.
+const SAS_LIFETIME: u64 = 3600;
+
+/// Translates `azblob://account/container/blob` into the URL of the blob, carrying a shared
+/// access signature when credentials are found in the environment.
+pub(crate) fn request(url_str: &str) -> Result<Url, String> {
+    let url = Url::parse(url_str).map_err(|e| format!("Invalid URL: {}", e))?;
+    let path = url.path().trim_start_matches('/');
+    let blob = (path.split_once('/'))
+        .filter(|(container, blob)| !container.is_empty() && !blob.is_empty());
+    let (Some(account), Some(_)) = (url.host_str(), blob) else {
+        return Err(format!(
+            "Expected `azblob://account/container/blob`, got `{}`",
+            url_str
+        ));
+    };
+    let endpoint = env::var(ENDPOINT_VAR)
+        .unwrap_or_else(|_| format!("https://{}.blob.core.windows.net", account));
+    let mut request = Url::parse(&format!("{}/{}", endpoint.trim_end_matches('/'), path))
+        .map_err(|e| format!("Invalid Blob service endpoint `{}`: {}", endpoint, e))?;
+
+    let token = match (env::var(SAS_TOKEN_VAR), env::var(KEY_VAR)) {
+        (Ok(token), _) => token.trim_start_matches('?').to_string(),
+        (_, Ok(key)) => {
+            let resource = percent_encoding::percent_decode_str(path)
+                .decode_utf8()
+                .map_err(|e| format!("Invalid blob name in `{}`: {}", url_str, e))?;
+            let resource = format!("/blob/{}/{}", account, resource);
+            signed_token(&key, &resource, now() + SAS_LIFETIME)
+                .map_err(|e| format!("Invalid `{}`: {}", KEY_VAR, e))?
+        }
+        _ => return Ok(request),
+    };
+    // The token is already encoded, and its parameters are kept as is
+    let query = match request.query() {
+        Some(query) => format!("{}&{}", query, token),
+        None => token,
+    };
+    request.set_query(Some(&query));
+    Ok(request)
+}
+
+/// Signs a service SAS token granting read access to a blob, given as
+/// `/blob/account/container/blob`, until `expiry` seconds since the Unix epoch.
+fn signed_token(key: &str, resource: &str, expiry: u64) -> Result<String, String> {
+    let key = STANDARD
+        .decode(key.trim())
+        .map_err(|e| format!("Expected a base64 encoded account key: {}", e))?;
+    let expiry = iso8601(expiry);
+    // Permissions, start, expiry, resource, identifier, IP, protocol, version, resource type,
+    // snapshot time, encryption scope and the five response header overrides
+    let fields = [
+        "r",
+        "",
+        &expiry,
+        resource,
+        "",
+        "",
+        "",
+        SAS_VERSION,
+        "b",
+        "",
+        "",
+        "",
+        "",
+        "",
+        "",
+        "",
+    ];
+    let key = hmac::Key::new(hmac::HMAC_SHA256, &key);
+    let signature = STANDARD.encode(hmac::sign(&key, fields.join("\n").as_bytes()));
+    Ok(url::form_urlencoded::Serializer::new(String::new())
+        .append_pair("sv", SAS_VERSION)
+        .append_pair("sr", "b")
+        .append_pair("sp", "r")
+        .append_pair("se", &expiry)
+        .append_pair("sig", &signature)
+        .finish())
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs())
+}
+
+/// Formats seconds since the Unix epoch as a UTC date and time, `2024-01-31T12:00:00Z`.
+fn iso8601(seconds: u64) -> String {
+    let days = (seconds / 86400) as i64;
+    let time = seconds % 86400;
+    // Civil date of a day count, from Howard Hinnant's `civil_from_days`
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that dates are formatted the way access signatures expect
+    #[test]
+    fn test_iso8601() {
+        assert_eq!(iso8601(0), "1970-01-01T00:00:00Z");
+        assert_eq!(iso8601(951_825_600), "2000-02-29T12:00:00Z");
+        assert_eq!(iso8601(1_735_689_599), "2024-12-31T23:59:59Z");
+    }
+
+    /// Test that the signed token carries its parameters and a signature of the blob
+    #[test]
+    fn test_signed_token() {
+        let key = STANDARD.encode(b"account key");
+        let token = signed_token(&key, "/blob/account/assets/model.bin", 1_735_689_599).unwrap();
+        assert!(token.starts_with("sv=2020-12-06&sr=b&sp=r&se=2024-12-31T23%3A59%3A59Z&sig="));
+        let other = signed_token(&key, "/blob/account/assets/other.bin", 1_735_689_599).unwrap();
+        assert_ne!(token, other);
+        assert!(signed_token("not base64!", "/blob/a/b/c", 0).is_err());
+    }
+}
@@ -0,0 +1,252 @@
+//! Decoding of fetched text into UTF-8.
+//!
+//! The macros embedding text decode it with the encoding declared for it, found the way
+//! browsers find it: a byte order mark, then the `charset` parameter of `Content-Type`,
+//! then for HTML a `<meta charset>` tag and for XML the `encoding` of its declaration, all
+//! within the first kilobyte. Content is HTML when served as HTML, or when served without a
+//! specific type and starting like an HTML document. Undeclared text has to be UTF-8. The
+//! `charset` option overrides all of them, for servers declaring the wrong encoding.
+
+use encoding_rs::{Encoding, UTF_8};
+use reqwest::header::{HeaderMap, CONTENT_TYPE};
+
+/// Number of bytes searched for an encoding declared in the content.
+const PRESCAN_BYTES: usize = 1024;
+
+/// Types of content served without a specific type, sniffed for HTML.
+const UNKNOWN_TYPES: &[&str] = &[
+    "application/octet-stream",
+    "application/unknown",
+    "unknown/unknown",
+    "*/*",
+];
+
+/// Tags an HTML document may start with, after white space, when sniffed as browsers do.
+const HTML_STARTS: &[&str] = &[
+    "<!doctype html",
+    "<html",
+    "<head",
+    "<script",
+    "<iframe",
+    "<h1",
+    "<div",
+    "<font",
+    "<table",
+    "<a",
+    "<style",
+    "<title",
+    "<b",
+    "<body",
+    "<br",
+    "<p",
+    "<!--",
+];
+
+/// Decodes text fetched with the response `headers` into UTF-8, with the encoding named by
+/// `forced` if given. UTF-8 content is returned untouched.
+pub(crate) fn decode(
+    content: bytes::Bytes,
+    headers: &HeaderMap,
+    forced: Option<&str>,
+) -> Result<bytes::Bytes, String> {
+    let declared = match forced {
+        Some(label) => Some((for_label(label)?, "the `charset` option")),
+        None => Encoding::for_bom(&content)
+            .map(|(encoding, _)| (encoding, "its byte order mark"))
+            .or_else(|| header_charset(headers).map(|encoding| (encoding, "its `Content-Type`")))
+            .or_else(|| {
+                content_charset(&content, headers).map(|encoding| (encoding, "its content"))
+            }),
+    };
+    let Some((encoding, source)) = declared else {
+        return match std::str::from_utf8(&content) {
+            Ok(_) => Ok(content),
+            Err(_) => Err(
+                "The content is not valid UTF-8 and declares no encoding, set it \
+                           with `charset = \"...\"`"
+                    .to_string(),
+            ),
+        };
+    };
+    let invalid = || {
+        format!(
+            "The content is not valid {}, as declared by {}",
+            encoding.name(),
+            source
+        )
+    };
+    if encoding == UTF_8 {
+        return match std::str::from_utf8(&content) {
+            Ok(_) => Ok(content),
+            Err(_) => Err(invalid()),
+        };
+    }
+    let (text, had_errors) = encoding.decode_with_bom_removal(&content);
+    if had_errors {
+        return Err(invalid());
+    }
+    Ok(bytes::Bytes::from(text.into_owned()))
+}
+
+/// Looks up an encoding by one of its WHATWG labels, such as `"latin1"` or `"shift_jis"`.
+pub(crate) fn for_label(label: &str) -> Result<&'static Encoding, String> {
+    Encoding::for_label(label.trim().as_bytes())
+        .ok_or_else(|| format!("Unknown character encoding `{}`", label))
+}
+
+/// Reads the `charset` parameter of the `Content-Type` header.
+fn header_charset(headers: &HeaderMap) -> Option<&'static Encoding> {
+    let content_type = headers.get(CONTENT_TYPE)?.to_str().ok()?;
+    content_type.split(';').skip(1).find_map(|parameter| {
+        let (name, value) = parameter.split_once('=')?;
+        let value = value.trim().trim_matches('"');
+        (name.trim().eq_ignore_ascii_case("charset"))
+            .then(|| Encoding::for_label(value.as_bytes()))
+            .flatten()
+    })
+}
+
+/// Finds the encoding declared by an XML declaration near the start of the content, or by
+/// an HTML `<meta>` tag if the content is HTML.
+fn content_charset(content: &[u8], headers: &HeaderMap) -> Option<&'static Encoding> {
+    let start = &content[..content.len().min(PRESCAN_BYTES)];
+    let start = String::from_utf8_lossy(start).to_ascii_lowercase();
+    if let Some(declaration) = start.strip_prefix("<?xml") {
+        let declaration = &declaration[..declaration.find("?>")?];
+        return attribute_value(declaration, "encoding=");
+    }
+    if !is_html(&start, headers) {
+        return None;
+    }
+    start.match_indices("<meta").find_map(|(index, _)| {
+        let tag = &start[index..];
+        let tag = &tag[..tag.find('>')?];
+        // `<meta charset="...">` and `<meta http-equiv="content-type" content="...; charset=...">`
+        attribute_value(tag, "charset=")
+    })
+}
+
+/// Returns whether the content starting with `start`, in lowercase, is HTML: served as
+/// HTML, or served without a specific type and starting with an HTML tag.
+fn is_html(start: &str, headers: &HeaderMap) -> bool {
+    let content_type = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| {
+            let essence = value.split(';').next().unwrap_or_default();
+            essence.trim().to_ascii_lowercase()
+        });
+    match content_type.as_deref() {
+        Some("text/html" | "application/xhtml+xml") => true,
+        Some(content_type)
+            if !content_type.is_empty() && !UNKNOWN_TYPES.contains(&content_type) =>
+        {
+            false
+        }
+        _ => {
+            let start = start.trim_start_matches(['\t', '\n', '\x0c', '\r', ' ']);
+            HTML_STARTS.iter().any(|tag| {
+                start
+                    .strip_prefix(tag)
+                    .is_some_and(|rest| rest.starts_with([' ', '>']))
+            })
+        }
+    }
+}
+
+/// Reads the encoding following `prefix`, quoted or not.
+fn attribute_value(text: &str, prefix: &str) -> Option<&'static Encoding> {
+    let value = &text[text.find(prefix)? + prefix.len()..];
+    let value = value.trim_start_matches(['"', '\'']);
+    let end = value
+        .find(|c: char| c == '"' || c == '\'' || c == ';' || c == '/' || c.is_whitespace())
+        .unwrap_or(value.len());
+    Encoding::for_label(&value.as_bytes()[..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that text is decoded with the encoding declared for it
+    #[test]
+    fn test_decode() {
+        let latin1 = bytes::Bytes::from_static(b"caf\xe9");
+        let mut headers = HeaderMap::new();
+        assert!(decode(latin1.clone(), &headers, None).is_err());
+        assert_eq!(
+            decode(latin1.clone(), &headers, Some("latin1")).unwrap(),
+            "café"
+        );
+        headers.insert(
+            CONTENT_TYPE,
+            "text/plain; charset=ISO-8859-1".parse().unwrap(),
+        );
+        assert_eq!(decode(latin1, &headers, None).unwrap(), "café");
+
+        let utf8 = bytes::Bytes::from("café");
+        assert_eq!(decode(utf8.clone(), &HeaderMap::new(), None).unwrap(), utf8);
+        let sjis = bytes::Bytes::from_static(b"\x93\xfa\x96\x7b");
+        assert_eq!(
+            decode(sjis, &HeaderMap::new(), Some("shift_jis")).unwrap(),
+            "日本"
+        );
+        assert!(for_label("klingon").is_err());
+    }
+
+    /// Test that encodings declared by HTML and XML documents are found
+    #[test]
+    fn test_content_charset() {
+        let none = HeaderMap::new();
+        let html = b"<!DOCTYPE html><html><head><meta charset=\"windows-1251\"></head>";
+        assert_eq!(
+            content_charset(html, &none).map(Encoding::name),
+            Some("windows-1251")
+        );
+        let html = b"<head><META http-equiv=\"Content-Type\" content=\"text/html; charset=gbk\">";
+        assert_eq!(
+            content_charset(html, &none).map(Encoding::name),
+            Some("GBK")
+        );
+        let xml = b"<?xml version=\"1.0\" encoding='Shift_JIS'?><root/>";
+        assert_eq!(
+            content_charset(xml, &none).map(Encoding::name),
+            Some("Shift_JIS")
+        );
+        assert_eq!(content_charset(b"<p>plain</p>", &none), None);
+    }
+
+    /// Test that `<meta>` tags only declare the encoding of HTML content
+    #[test]
+    fn test_content_charset_html_only() {
+        let mut headers = HeaderMap::new();
+        let snippet = b"Add <meta charset=\"windows-1251\"> to the page";
+        assert_eq!(content_charset(snippet, &headers), None);
+        headers.insert(CONTENT_TYPE, "text/html".parse().unwrap());
+        assert_eq!(
+            content_charset(snippet, &headers).map(Encoding::name),
+            Some("windows-1251")
+        );
+
+        let html = b"\n  <html><meta charset=\"windows-1251\">";
+        headers.insert(
+            CONTENT_TYPE,
+            "text/markdown; charset=utf-8".parse().unwrap(),
+        );
+        assert_eq!(content_charset(html, &headers), None);
+        headers.insert(CONTENT_TYPE, "application/octet-stream".parse().unwrap());
+        assert_eq!(
+            content_charset(html, &headers).map(Encoding::name),
+            Some("windows-1251")
+        );
+        assert_eq!(
+            content_charset(b"<htmlish><meta charset=gbk>", &headers),
+            None
+        );
+
+        // Text quoting a tag is decoded with the charset of its header, not of the tag
+        let text = bytes::Bytes::from("<html><meta charset=\"utf-16\"> caf\u{e9}");
+        headers.insert(CONTENT_TYPE, "text/plain".parse().unwrap());
+        assert_eq!(decode(text.clone(), &headers, None).unwrap(), text);
+    }
+}
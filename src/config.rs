@@ -0,0 +1,288 @@
+//! Crate wide defaults for the fetch options.
+//!
+//! The defaults are read from the `[package.metadata.include_url]` table of the manifest of
+//! the crate being compiled, and can be overridden for a single build through `INCLUDE_URL_`
+//! environment variables. Options given to a macro take precedence over both.
+
+use std::{collections::BTreeMap, env, path::Path};
+
+use serde::Deserialize;
+
+use crate::FetchOptions;
+
+/// The defaults configured for the crate being compiled.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+pub(crate) struct Config {
+    /// Limit for a whole request, in seconds.
+    pub timeout: Option<u64>,
+    /// Limit for establishing a connection, in seconds.
+    pub connect_timeout: Option<u64>,
+    /// Number of times a temporary failure is retried.
+    pub retries: Option<u32>,
+    /// Maximum number of requests per second to a host.
+    pub rate_limit: Option<u32>,
+    /// `4` or `6` to only connect over one IP version.
+    pub ip_version: Option<u8>,
+    /// URL of a DNS over HTTPS server resolving the hosts.
+    pub doh: Option<String>,
+    /// Extra root certificate, as PEM or the path to a PEM file.
+    pub ca_cert: Option<String>,
+    /// URL of the IPFS gateway `ipfs://` URLs are fetched from.
+    pub ipfs_gateway: Option<String>,
+    /// Read `file://` URLs from the local file system.
+    pub allow_file_urls: Option<bool>,
+    /// Pin the branches and tags of GitHub URLs to their commit.
+    pub permalink: Option<bool>,
+    /// Path to the `trusted_root.json` checking Sigstore signatures.
+    pub sigstore_root: Option<String>,
+    /// Shared zstd dictionaries by name.
+    #[serde(default)]
+    pub zstd_dictionaries: BTreeMap<String, Dictionary>,
+}
+
+/// The definition of a shared zstd dictionary.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Dictionary {
+    /// URL of a pre-trained dictionary.
+    pub url: Option<String>,
+    /// URLs of the samples the dictionary is trained from.
+    #[serde(default)]
+    pub samples: Vec<String>,
+    /// Maximum size of a trained dictionary, in bytes.
+    pub max_size: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct Manifest {
+    package: Option<Package>,
+}
+
+#[derive(Deserialize)]
+struct Package {
+    metadata: Option<Metadata>,
+}
+
+#[derive(Deserialize)]
+struct Metadata {
+    include_url: Option<Config>,
+}
+
+/// Loads the defaults of the crate being compiled, applying the environment overrides.
+///
+/// The manifest is tracked and the variables are recorded in `options`, so that changing
+/// either re-expands the macro and selects another cache entry.
+pub(crate) fn load(options: &mut FetchOptions) -> Result<Config, String> {
+    let mut config = match env::var_os("CARGO_MANIFEST_DIR") {
+        Some(dir) => {
+            let path = Path::new(&dir).join("Cargo.toml");
+            match std::fs::read_to_string(&path) {
+                Ok(manifest) => {
+                    if !options.files.contains(&path) {
+                        options.files.push(path);
+                    }
+                    from_manifest(&manifest)?
+                }
+                Err(_) => Config::default(),
+            }
+        }
+        None => Config::default(),
+    };
+    if let Some(timeout) = number_var("INCLUDE_URL_TIMEOUT", "number of seconds", options)? {
+        config.timeout = Some(timeout);
+    }
+    let connect_timeout = number_var("INCLUDE_URL_CONNECT_TIMEOUT", "number of seconds", options)?;
+    if let Some(timeout) = connect_timeout {
+        config.connect_timeout = Some(timeout);
+    }
+    if let Some(retries) = number_var("INCLUDE_URL_RETRIES", "number of retries", options)? {
+        config.retries = Some(retries);
+    }
+    if let Some(rate) = number_var("INCLUDE_URL_RATE_LIMIT", "number of requests", options)? {
+        config.rate_limit = Some(rate);
+    }
+    if let Some(version) = number_var("INCLUDE_URL_IP_VERSION", "IP version", options)? {
+        config.ip_version = Some(version);
+    }
+    if let Some(doh) = options.env_var("INCLUDE_URL_DOH") {
+        config.doh = Some(doh).filter(|doh| !doh.is_empty());
+    }
+    if let Some(ca_cert) = options.env_var("INCLUDE_URL_CA_CERT") {
+        config.ca_cert = Some(ca_cert).filter(|ca_cert| !ca_cert.is_empty());
+    }
+    if let Some(allow) = options.env_var("INCLUDE_URL_ALLOW_FILE_URLS") {
+        config.allow_file_urls = match allow.trim() {
+            "true" | "1" => Some(true),
+            "false" | "0" | "" => Some(false),
+            _ => {
+                return Err(format!(
+                    "Invalid `INCLUDE_URL_ALLOW_FILE_URLS` of `{}`, expected `true` or `false`",
+                    allow
+                ))
+            }
+        };
+    }
+    if let Some(gateway) = options.env_var("INCLUDE_URL_IPFS_GATEWAY") {
+        config.ipfs_gateway = Some(gateway).filter(|gateway| !gateway.is_empty());
+    }
+    match config.ip_version {
+        None | Some(4 | 6) => Ok(config),
+        Some(version) => Err(format!("Invalid IP version {}, expected 4 or 6", version)),
+    }
+}
+
+/// Reads the `[package.metadata.include_url]` table of a manifest.
+fn from_manifest(manifest: &str) -> Result<Config, String> {
+    let manifest: Manifest = toml::from_str(manifest).map_err(|e| {
+        format!(
+            "Invalid `package.metadata.include_url` in Cargo.toml: {}",
+            e
+        )
+    })?;
+    Ok(manifest
+        .package
+        .and_then(|package| package.metadata)
+        .and_then(|metadata| metadata.include_url)
+        .unwrap_or_default())
+}
+
+/// Tells why the network must not be used, when cargo is offline.
+///
+/// `CARGO_NET_OFFLINE` reaches the compiler, unlike the `--offline` and `--frozen` flags,
+/// which are looked for in the command line of the parent cargo process where it can be
+/// read.
+pub(crate) fn offline() -> Option<&'static str> {
+    let enabled = |value: String| matches!(value.trim(), "true" | "1");
+    if env::var("CARGO_NET_OFFLINE").is_ok_and(enabled) {
+        return Some("`CARGO_NET_OFFLINE`");
+    }
+    cargo_args()
+        .iter()
+        .any(|arg| arg == "--offline" || arg == "--frozen")
+        .then_some("`cargo --offline`")
+}
+
+/// Reads the arguments of the process that started the compiler, usually cargo.
+#[cfg(target_os = "linux")]
+fn cargo_args() -> Vec<String> {
+    let status = std::fs::read_to_string("/proc/self/status").unwrap_or_default();
+    let parent = status
+        .lines()
+        .find_map(|line| line.strip_prefix("PPid:"))
+        .map(str::trim);
+    let cmdline = parent
+        .and_then(|pid| std::fs::read(format!("/proc/{}/cmdline", pid)).ok())
+        .unwrap_or_default();
+    cmdline
+        .split(|byte| *byte == 0)
+        .map(|arg| String::from_utf8_lossy(arg).into_owned())
+        .collect()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn cargo_args() -> Vec<String> {
+    Vec::new()
+}
+
+/// Reads an environment variable holding a number, described by `expected`, recording it in
+/// `options`.
+fn number_var<T: std::str::FromStr>(
+    name: &str,
+    expected: &str,
+    options: &mut FetchOptions,
+) -> Result<Option<T>, String> {
+    match options.env_var(name) {
+        Some(value) => value
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| format!("Invalid `{}` of `{}`, expected a {}", name, value, expected)),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that the defaults are read from the package metadata
+    #[test]
+    fn test_from_manifest() {
+        let manifest = r#"
+            [package]
+            name = "app"
+            version.workspace = true
+
+            [package.metadata.include_url]
+            timeout = 60
+            connect_timeout = 5
+            retries = 0
+            rate_limit = 10
+            ip_version = 6
+            ca_cert = "certs/ca.pem"
+            ipfs_gateway = "https://dweb.link"
+            allow_file_urls = true
+            permalink = true
+            sigstore_root = "sigstore/trusted_root.json"
+
+            [package.metadata.include_url.zstd_dictionaries]
+            icons = { samples = ["https://example.com/a.json"], max_size = 4096 }
+
+            [package.metadata.docs.rs]
+            all-features = true
+        "#;
+        assert_eq!(
+            from_manifest(manifest),
+            Ok(Config {
+                timeout: Some(60),
+                connect_timeout: Some(5),
+                retries: Some(0),
+                rate_limit: Some(10),
+                ip_version: Some(6),
+                doh: None,
+                ca_cert: Some("certs/ca.pem".to_string()),
+                ipfs_gateway: Some("https://dweb.link".to_string()),
+                allow_file_urls: Some(true),
+                permalink: Some(true),
+                sigstore_root: Some("sigstore/trusted_root.json".to_string()),
+                zstd_dictionaries: BTreeMap::from([(
+                    "icons".to_string(),
+                    Dictionary {
+                        url: None,
+                        samples: vec!["https://example.com/a.json".to_string()],
+                        max_size: Some(4096),
+                    }
+                )]),
+            })
+        );
+        assert_eq!(
+            from_manifest("[package]\nname = \"app\"\n"),
+            Ok(Config::default())
+        );
+        assert!(from_manifest("[package.metadata.include_url]\ntimeout = \"1m\"\n").is_err());
+    }
+
+    /// Test that the overrides and the manifest are recorded for re-expansion
+    #[test]
+    fn test_load() {
+        env::set_var("INCLUDE_URL_IPFS_GATEWAY", "https://ipfs.example.com");
+        let mut options = FetchOptions::default();
+        let config = load(&mut options).unwrap();
+        env::remove_var("INCLUDE_URL_IPFS_GATEWAY");
+        assert_eq!(
+            config.ipfs_gateway.as_deref(),
+            Some("https://ipfs.example.com")
+        );
+        assert!(options.env.contains(&(
+            "INCLUDE_URL_IPFS_GATEWAY".to_string(),
+            Some("https://ipfs.example.com".to_string())
+        )));
+        assert!(options
+            .env
+            .iter()
+            .any(|(name, _)| name == "INCLUDE_URL_TIMEOUT"));
+        assert!(options.cache_key().contains("https://ipfs.example.com"));
+        let manifest = Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+        assert_eq!(options.files, [manifest]);
+    }
+}
@@ -0,0 +1,128 @@
+//! Packages published on crates.io, for [`include_crate_file!`](crate::include_crate_file).
+//!
+//! The checksum of the version is read from the sparse index, and the `.crate` archive is
+//! downloaded through the cache with it as its `sha256`, so a package altered on its way
+//! fails the build. The archive is then unpacked next to its cache file.
+
+use serde::Deserialize;
+
+use crate::{archive, cached_url_content, CompressKind, FetchOptions};
+
+const INDEX_HOST: &str = "https://index.crates.io";
+
+const DOWNLOAD_HOST: &str = "https://static.crates.io/crates";
+
+/// A version of a crate in the sparse index, one per line.
+#[derive(Deserialize)]
+struct IndexEntry {
+    vers: String,
+    cksum: String,
+    #[serde(default)]
+    yanked: bool,
+}
+
+/// Path of a crate in the index, by the length of its lowercased name.
+fn index_path(name: &str) -> String {
+    let name = name.to_ascii_lowercase();
+    match name.len() {
+        1 => format!("1/{}", name),
+        2 => format!("2/{}", name),
+        3 => format!("3/{}/{}", &name[..1], name),
+        _ => format!("{}/{}/{}", &name[..2], &name[2..4], name),
+    }
+}
+
+/// Looks up the checksum of a version in the index of a crate.
+fn checksum(index: &str, name: &str, version: &str) -> Result<String, String> {
+    let mut versions = Vec::new();
+    for line in index.lines().filter(|line| !line.trim().is_empty()) {
+        let entry: IndexEntry = serde_json::from_str(line)
+            .map_err(|e| format!("Invalid index entry of `{}`: {}", name, e))?;
+        if entry.vers == version {
+            return Ok(entry.cksum);
+        }
+        if !entry.yanked {
+            versions.push(entry.vers);
+        }
+    }
+    let latest = versions.iter().rev().take(5).cloned().collect::<Vec<_>>();
+    Err(format!(
+        "`{}` has no version `{}`, its latest versions are: {}. A version published after the \
+         index was cached is seen once the cache is refreshed with INCLUDE_URL_REFRESH=1",
+        name,
+        version,
+        latest.join(", ")
+    ))
+}
+
+/// Downloads and unpacks a version of a crate, selecting the file or directory at `path`
+/// in the package, or the files matching it.
+pub(crate) fn package_path(
+    name: &str,
+    version: &str,
+    path: &str,
+    options: &mut FetchOptions,
+) -> Result<archive::Selection, String> {
+    let valid_name = !name.is_empty()
+        && (name.bytes()).all(|byte| byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_'));
+    if !valid_name {
+        return Err(format!("Invalid crate name `{}`", name));
+    }
+    if version.is_empty() || version.contains(['/', '\\']) || version.starts_with('.') {
+        return Err(format!("Invalid version `{}`", version));
+    }
+
+    if options.sha256.is_none() {
+        // The index changes with every release, the package itself is checked by its digest
+        let mut index_options = FetchOptions {
+            sha256: None,
+            integrity: None,
+            signature_url: None,
+            pgp_key: None,
+            minisign_pub: None,
+            sigstore_identity: None,
+            zsync: None,
+            unlocked: true,
+            ..options.clone()
+        };
+        let index_url = format!("{}/{}", INDEX_HOST, index_path(name));
+        let index = cached_url_content(&index_url, CompressKind::None, &mut index_options)?;
+        for recorded in index_options.env {
+            if !options.env.contains(&recorded) {
+                options.env.push(recorded);
+            }
+        }
+        let index = std::fs::read_to_string(index)
+            .map_err(|e| format!("Failed to open cache file: {}", e))?;
+        options.sha256 = Some(checksum(&index, name, version)?);
+    }
+    let url = format!("{}/{}/{}-{}.crate", DOWNLOAD_HOST, name, name, version);
+    let archive = cached_url_content(&url, CompressKind::None, options)?;
+    let unpacked = archive::unpack(&archive, archive::Format::Tar)?;
+    let source = format!("{}-{}", name, version);
+    archive::select(&unpacked.join(&source), path, &source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that index paths follow the length of the name and versions are looked up
+    #[test]
+    fn test_index() {
+        assert_eq!(index_path("a"), "1/a");
+        assert_eq!(index_path("cc"), "2/cc");
+        assert_eq!(index_path("syn"), "3/s/syn");
+        assert_eq!(index_path("Serde_JSON"), "se/rd/serde_json");
+
+        let index = concat!(
+            r#"{"name":"pest","vers":"2.7.0","deps":[],"cksum":"aa","features":{},"yanked":false}"#,
+            "\n",
+            r#"{"name":"pest","vers":"2.7.1","deps":[],"cksum":"bb","features":{},"yanked":true}"#,
+            "\n",
+        );
+        assert_eq!(checksum(index, "pest", "2.7.1").unwrap(), "bb");
+        let missing = checksum(index, "pest", "3.0.0").unwrap_err();
+        assert!(missing.contains("latest versions are: 2.7.0."));
+    }
+}
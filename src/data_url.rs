@@ -0,0 +1,89 @@
+//! `data:` URLs of RFC 2397, `data:[<media type>][;base64],<data>`, which carry their
+//! content and are decoded without any request.
+//!
+//! The media type is handed to the rest of the pipeline as the `Content-Type` of a
+//! response, so the `charset` parameter of text is honored. Text without one is read as
+//! UTF-8 rather than the US-ASCII of the RFC, which it extends.
+
+use base64::{
+    alphabet,
+    engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
+    Engine,
+};
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+
+/// Base64 as written in data URLs, where the padding is often left out.
+const BASE64: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// Tells whether a URL is a data URL, whose scheme is case insensitive.
+pub(crate) fn is_data_url(url_str: &str) -> bool {
+    url_str
+        .get(..5)
+        .is_some_and(|scheme| scheme.eq_ignore_ascii_case("data:"))
+}
+
+/// Decodes a data URL into the headers of its media type and its content.
+pub(crate) fn decode(url_str: &str) -> Result<(HeaderMap, bytes::Bytes), String> {
+    let (metadata, data) = url_str[5..]
+        .split_once(',')
+        .ok_or_else(|| "Expected `data:[<media type>][;base64],<data>`".to_string())?;
+    let (media_type, base64) = match metadata.rsplit_once(';') {
+        Some((media_type, encoding)) if encoding.trim().eq_ignore_ascii_case("base64") => {
+            (media_type, true)
+        }
+        _ => (metadata, false),
+    };
+    let media_type = match media_type.trim() {
+        "" => "text/plain",
+        media_type if media_type.starts_with(';') => &format!("text/plain{}", media_type),
+        media_type => media_type,
+    };
+    let media_type = percent_encoding::percent_decode_str(media_type).decode_utf8_lossy();
+    let content_type = HeaderValue::from_str(&media_type)
+        .map_err(|_| format!("Invalid media type `{}` in data URL", media_type))?;
+
+    // The fragment is not part of the content
+    let data = data.split('#').next().unwrap_or_default();
+    let data: Vec<u8> = percent_encoding::percent_decode_str(data).collect();
+    let content = if base64 {
+        let data: Vec<u8> = (data.into_iter())
+            .filter(|byte| !byte.is_ascii_whitespace())
+            .collect();
+        BASE64
+            .decode(data)
+            .map_err(|e| format!("Invalid base64 in data URL: {}", e))?
+    } else {
+        data
+    };
+    let mut headers = HeaderMap::new();
+    headers.insert(CONTENT_TYPE, content_type);
+    Ok((headers, content.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that both encodings of the data and the media type are decoded
+    #[test]
+    fn test_decode() {
+        let (headers, content) = decode("data:,Hello%2C%20World%21").unwrap();
+        assert_eq!(headers[CONTENT_TYPE], "text/plain");
+        assert_eq!(content, "Hello, World!");
+
+        let (headers, content) = decode("data:application/json;base64,eyJpZCI6IDF9Cg").unwrap();
+        assert_eq!(headers[CONTENT_TYPE], "application/json");
+        assert_eq!(content, "{\"id\": 1}\n");
+
+        let (headers, content) = decode("data:;charset=latin1,caf%E9#ignored").unwrap();
+        assert_eq!(headers[CONTENT_TYPE], "text/plain;charset=latin1");
+        assert_eq!(content, b"caf\xe9".as_slice());
+
+        assert!(is_data_url("DATA:,x"));
+        assert!(decode("data:text/plain").is_err());
+        assert!(decode("data:;base64,not base64!").is_err());
+    }
+}
@@ -0,0 +1,146 @@
+//! Shared zstd dictionaries for compressing many small assets.
+//!
+//! A dictionary is defined in the `[package.metadata.include_url.zstd_dictionaries]` table
+//! of the manifest, as a pre-trained dictionary at a URL or as sample URLs to train one
+//! from. It is made once for its definition and kept in the cache directory, where every
+//! macro compressing with it, and `include_zstd_dictionary!`, reads it. The content does
+//! not depend on the order in which the macros are expanded.
+
+use std::{
+    env,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::{config::Dictionary, CompressKind, FetchOptions};
+
+/// Compression level used for zstd compressed content.
+const ZSTD_LEVEL: i32 = 19;
+
+/// Maximum size of a trained dictionary, unless its definition sets `max_size`.
+const DEFAULT_MAX_SIZE: usize = 16 * 1024;
+
+/// Returns the location of the dictionary named `name` made from `definition`, for the
+/// crate being compiled.
+fn dictionary_path(name: &str, definition: &Dictionary) -> Result<PathBuf, String> {
+    let dir = Path::new(env!("INCLUDE_URL_CACHE_DIR")).join("dictionaries");
+    if !dir.exists() {
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create dictionary directory: {}", e))?;
+    }
+    let crate_name = env::var("CARGO_PKG_NAME").unwrap_or_else(|_| "unknown".into());
+    let mut hasher = Sha256::new();
+    hasher.update(crate_name.as_bytes());
+    hasher.update(b"\0");
+    hasher.update(name.as_bytes());
+    hasher.update(b"\0");
+    hasher.update(format!("{:?}", definition));
+    Ok(dir.join(format!("{:x}", hasher.finalize())))
+}
+
+/// Reads the dictionary named `name`, fetching or training it from its definition in the
+/// manifest the first time. The manifest is tracked in `options` for re-expansion.
+pub(crate) fn load_dictionary(name: &str, options: &mut FetchOptions) -> Result<Vec<u8>, String> {
+    let definition = (options.config()?.zstd_dictionaries.get(name).cloned()).ok_or_else(|| {
+        format!(
+            "Unknown zstd dictionary `{}`, define it in the \
+                 `[package.metadata.include_url.zstd_dictionaries]` table of Cargo.toml",
+            name
+        )
+    })?;
+    let path = dictionary_path(name, &definition)?;
+    if let Ok(dictionary) = std::fs::read(&path) {
+        return Ok(dictionary);
+    }
+
+    let fetch = |url: &String| {
+        crate::cached_url_content(url, CompressKind::None, &mut FetchOptions::default()).and_then(
+            |path| std::fs::read(path).map_err(|e| format!("Failed to open cache file: {}", e)),
+        )
+    };
+    let dictionary = match (&definition.url, &definition.samples[..]) {
+        (Some(url), []) => fetch(url)?,
+        (None, [_, ..]) => {
+            let samples = definition.samples.iter().map(fetch);
+            let samples = samples.collect::<Result<Vec<_>, _>>()?;
+            train_dictionary(&samples, definition.max_size.unwrap_or(DEFAULT_MAX_SIZE))?
+        }
+        _ => {
+            return Err(format!(
+                "The zstd dictionary `{}` needs either `url` or `samples`",
+                name
+            ))
+        }
+    };
+    // Replaced at once, as the targets of the crate may be compiled at the same time
+    let temporary = path.with_extension(format!("{}.tmp", std::process::id()));
+    std::fs::write(&temporary, &dictionary)
+        .and_then(|_| std::fs::rename(&temporary, &path))
+        .map_err(|e| format!("Failed to write zstd dictionary: {}", e))?;
+    Ok(dictionary)
+}
+
+/// Trains a dictionary of at most `max_size` bytes from the given samples.
+pub(crate) fn train_dictionary(samples: &[Vec<u8>], max_size: usize) -> Result<Vec<u8>, String> {
+    zstd::dict::from_samples(samples, max_size)
+        .map_err(|e| format!("Failed to train zstd dictionary: {}", e))
+}
+
+/// Compresses the `size` bytes of `content` with zstd into `output`, using the dictionary
+/// if one is given.
+pub(crate) fn compress(
+    content: &mut dyn Read,
+    output: &mut dyn Write,
+    size: u64,
+    dictionary: Option<&[u8]>,
+) -> Result<(), String> {
+    let encoder = match dictionary {
+        Some(dictionary) => zstd::Encoder::with_dictionary(output, ZSTD_LEVEL, dictionary),
+        None => zstd::Encoder::new(output, ZSTD_LEVEL),
+    };
+    // The frame records the size of the content, as compressing it in one go does
+    encoder
+        .and_then(|mut encoder| {
+            encoder.include_contentsize(true)?;
+            encoder.set_pledged_src_size(Some(size))?;
+            std::io::copy(content, &mut encoder)?;
+            encoder.finish()
+        })
+        .map(|_| ())
+        .map_err(|e| format!("Failed to compress content: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that a trained dictionary round-trips compressed content
+    #[test]
+    fn test_train_and_compress() {
+        let samples: Vec<Vec<u8>> = (0..200)
+            .map(|i| {
+                format!(
+                    r#"{{"id":{},"name":"asset-{}","tags":["icon","small"]}}"#,
+                    i, i
+                )
+            })
+            .map(String::into_bytes)
+            .collect();
+        let dictionary = train_dictionary(&samples, 4096).unwrap();
+
+        let content = br#"{"id":1000,"name":"asset-1000","tags":["icon","small"]}"#;
+        let mut compressed = Vec::new();
+        let size = content.len() as u64;
+        compress(&mut &content[..], &mut compressed, size, Some(&dictionary)).unwrap();
+        let mut decompressor = zstd::bulk::Decompressor::with_dictionary(&dictionary).unwrap();
+        let decompressed = decompressor.decompress(&compressed, content.len()).unwrap();
+        assert_eq!(decompressed, content);
+
+        let mut options = FetchOptions::default();
+        assert!(load_dictionary("test-missing", &mut options)
+            .unwrap_err()
+            .starts_with("Unknown zstd dictionary `test-missing`"));
+    }
+}
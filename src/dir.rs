@@ -0,0 +1,409 @@
+//! Listings of remote directories, for [`include_url_dir!`](crate::include_url_dir).
+//!
+//! A directory is listed by the trees API for `gh:owner/repo@ref/dir`, by the
+//! `ListObjectsV2` API for S3 buckets, or else by following the links of its HTML index
+//! page, such as the autoindex of nginx or Apache, into its subdirectories. Listings are
+//! fetched through the cache like any other content, so offline builds list directories
+//! from the cache. Only public buckets can be listed, as requests to S3 are not signed.
+
+use std::{collections::HashSet, path::PathBuf};
+
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+use proc_macro2::TokenStream;
+use quote::quote;
+use url::Url;
+
+use crate::{cached_url_content, github, CompressKind, FetchOptions, Paginate};
+
+/// Depth of the subdirectories followed in HTML indexes.
+const MAX_DEPTH: usize = 16;
+
+/// Characters escaped in the keys of objects when they become URL paths.
+const KEY: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// How a directory is listed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Listing {
+    GitHub,
+    S3,
+    Html,
+}
+
+impl Listing {
+    pub(crate) fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "github" => Ok(Listing::GitHub),
+            "s3" => Ok(Listing::S3),
+            "html" => Ok(Listing::Html),
+            _ => Err(format!(
+                "Unknown listing `{}`, expected `github`, `s3` or `html`",
+                value
+            )),
+        }
+    }
+
+    /// Tells the listing of a URL, `gh:` shorthands and S3 hosts being recognized.
+    fn detect(url_str: &str) -> Self {
+        if url_str.starts_with("gh:") {
+            return Listing::GitHub;
+        }
+        let host = Url::parse(url_str)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+            .unwrap_or_default();
+        let s3_host = host.ends_with(".amazonaws.com")
+            && (host.starts_with("s3.")
+                || host.starts_with("s3-")
+                || host.contains(".s3.")
+                || host.contains(".s3-"));
+        match s3_host {
+            true => Listing::S3,
+            false => Listing::Html,
+        }
+    }
+}
+
+/// Lists the files below a remote directory, as their paths relative to it and their URLs.
+pub(crate) fn list(
+    url_str: &str,
+    listing: Option<Listing>,
+    options: &mut FetchOptions,
+) -> Result<Vec<(String, String)>, String> {
+    let url_str = &crate::resolve_env(url_str, options)?;
+    let mut files = match listing.unwrap_or_else(|| Listing::detect(url_str)) {
+        Listing::GitHub => github::list_directory(url_str, options)?,
+        Listing::S3 => list_s3(url_str, options)?,
+        Listing::Html => list_html(url_str, options)?,
+    };
+    files.sort();
+    files.dedup_by(|a, b| a.0 == b.0);
+    Ok(files)
+}
+
+/// Fetches a listing through the cache, recording the environment it read in `options`.
+/// Listings are left out of the lockfile, as they change along with the directory.
+pub(crate) fn fetch_listing(
+    url: &str,
+    listing_options: FetchOptions,
+    options: &mut FetchOptions,
+) -> Result<Vec<u8>, String> {
+    let mut listing_options = FetchOptions {
+        sha256: None,
+        integrity: None,
+        signature_url: None,
+        pgp_key: None,
+        minisign_pub: None,
+        sigstore_identity: None,
+        zsync: None,
+        unlocked: true,
+        ..listing_options
+    };
+    let cached = cached_url_content(url, CompressKind::None, &mut listing_options)?;
+    for recorded in listing_options.env {
+        if !options.env.contains(&recorded) {
+            options.env.push(recorded);
+        }
+    }
+    std::fs::read(cached).map_err(|e| format!("Failed to open cache file: {}", e))
+}
+
+/// Returns a matcher of glob patterns, where `*` does not match across directories and
+/// `**` does.
+pub(crate) fn matcher(pattern: &str) -> Result<impl Fn(&str) -> bool, String> {
+    let compiled =
+        glob::Pattern::new(pattern).map_err(|e| format!("Invalid pattern `{}`: {}", pattern, e))?;
+    let options = glob::MatchOptions {
+        require_literal_separator: true,
+        ..glob::MatchOptions::default()
+    };
+    Ok(move |path: &str| compiled.matches_with(path, options))
+}
+
+/// Lists the objects under a prefix of an S3 bucket, addressed by virtual host, such as
+/// `https://bucket.s3.amazonaws.com/prefix/`, or by path, `https://host/bucket/prefix/`.
+fn list_s3(url_str: &str, options: &mut FetchOptions) -> Result<Vec<(String, String)>, String> {
+    let url = Url::parse(url_str).map_err(|e| format!("Invalid URL: {}", e))?;
+    let host = url.host_str().unwrap_or_default();
+    let path = url.path().trim_start_matches('/');
+    let virtual_host = host.ends_with(".amazonaws.com") && !host.starts_with("s3");
+    let (bucket_path, prefix) = match virtual_host {
+        true => (String::new(), path),
+        false => match path.split_once('/') {
+            Some((bucket, prefix)) => (format!("/{}", bucket), prefix),
+            None => (format!("/{}", path), ""),
+        },
+    };
+    if !virtual_host && bucket_path.len() < 2 {
+        return Err(format!("No bucket in `{}`", url_str));
+    }
+    let prefix = percent_decode_str(prefix).decode_utf8_lossy().into_owned();
+    let prefix = match prefix.is_empty() || prefix.ends_with('/') {
+        true => prefix,
+        false => format!("{}/", prefix),
+    };
+    let base = format!("{}{}", &url[..url::Position::BeforePath], bucket_path);
+
+    let mut files = Vec::new();
+    let mut token: Option<String> = None;
+    let max_pages = options.max_pages.unwrap_or(crate::MAX_PAGES);
+    for _ in 0..max_pages {
+        let mut list_url = Url::parse(&base).map_err(|e| format!("Invalid URL: {}", e))?;
+        list_url
+            .query_pairs_mut()
+            .append_pair("list-type", "2")
+            .append_pair("prefix", &prefix);
+        if let Some(token) = &token {
+            list_url
+                .query_pairs_mut()
+                .append_pair("continuation-token", token);
+        }
+        let listing_options = FetchOptions {
+            paginate: Paginate::None,
+            ..options.clone()
+        };
+        let page = fetch_listing(list_url.as_str(), listing_options, options)?;
+        let page = String::from_utf8_lossy(&page);
+        if !page.contains("<ListBucketResult") {
+            return Err(format!("`{}` is not an S3 bucket listing", list_url));
+        }
+        for key in xml_elements(&page, "Key") {
+            let Some(relative) = key
+                .strip_prefix(&prefix)
+                .filter(|relative| !relative.is_empty() && !relative.ends_with('/'))
+            else {
+                continue;
+            };
+            let object = format!("{}/{}", base, utf8_percent_encode(&key, KEY));
+            files.push((relative.to_string(), object));
+        }
+        let truncated = xml_elements(&page, "IsTruncated")
+            .first()
+            .map(String::as_str)
+            == Some("true");
+        token = xml_elements(&page, "NextContinuationToken")
+            .into_iter()
+            .next();
+        if !truncated || token.is_none() {
+            return Ok(files);
+        }
+    }
+    Err(format!(
+        "The listing of `{}` exceeded the limit of {} pages",
+        url_str, max_pages
+    ))
+}
+
+/// Returns the text of the elements named `tag` of a simple XML document.
+pub(crate) fn xml_elements(xml: &str, tag: &str) -> Vec<String> {
+    let (open, close) = (format!("<{}>", tag), format!("</{}>", tag));
+    let mut elements = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        rest = &rest[start + open.len()..];
+        let Some(end) = rest.find(&close) else {
+            break;
+        };
+        elements.push(unescape(&rest[..end]));
+        rest = &rest[end + close.len()..];
+    }
+    elements
+}
+
+/// Replaces the predefined entities of XML and HTML attributes.
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Lists the files linked from the HTML index of a directory and of its subdirectories.
+fn list_html(url_str: &str, options: &mut FetchOptions) -> Result<Vec<(String, String)>, String> {
+    let mut root = Url::parse(url_str).map_err(|e| format!("Invalid URL: {}", e))?;
+    if !root.path().ends_with('/') {
+        root.set_path(&format!("{}/", root.path()));
+    }
+    root.set_query(None);
+    root.set_fragment(None);
+
+    let mut files = Vec::new();
+    let mut visited = HashSet::new();
+    let mut pending = vec![(root.clone(), 0)];
+    while let Some((dir, depth)) = pending.pop() {
+        if !visited.insert(dir.clone()) {
+            continue;
+        }
+        let listing_options = FetchOptions {
+            paginate: Paginate::None,
+            ..options.clone()
+        };
+        let page = fetch_listing(dir.as_str(), listing_options, options)?;
+        for href in hrefs(&String::from_utf8_lossy(&page)) {
+            let Ok(mut link) = dir.join(&href) else {
+                continue;
+            };
+            link.set_fragment(None);
+            // Sorting links of the index, and links out of the directory, are left out
+            let Some(relative) = link.as_str().strip_prefix(root.as_str()) else {
+                continue;
+            };
+            if relative.is_empty() || link.query().is_some() {
+                continue;
+            }
+            let relative = percent_decode_str(relative)
+                .decode_utf8_lossy()
+                .into_owned();
+            if relative.ends_with('/') {
+                if depth < MAX_DEPTH && link.as_str().len() > dir.as_str().len() {
+                    pending.push((link, depth + 1));
+                }
+            } else {
+                files.push((relative, link.to_string()));
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Extracts the targets of the `href` attributes of an HTML page.
+fn hrefs(html: &str) -> Vec<String> {
+    let mut hrefs = Vec::new();
+    let lower = html.to_ascii_lowercase();
+    let mut position = 0;
+    while let Some(found) = lower[position..].find("href") {
+        position += found + 4;
+        let rest = html[position..].trim_start();
+        let Some(rest) = rest.strip_prefix('=') else {
+            continue;
+        };
+        let rest = rest.trim_start();
+        let value = match rest.chars().next() {
+            Some(quote @ ('"' | '\'')) => rest[1..].split(quote).next(),
+            _ => rest.split(|c: char| c.is_whitespace() || c == '>').next(),
+        };
+        if let Some(value) = value.filter(|value| !value.is_empty()) {
+            hrefs.push(unescape(value));
+        }
+    }
+    hrefs
+}
+
+/// Generates the `File` and `Dir` types and the `DIR` static of the files of a directory,
+/// sorted by path.
+pub(crate) fn generate(files: &[(String, PathBuf)]) -> TokenStream {
+    let paths = files.iter().map(|(path, _)| path);
+    let contents = files.iter().map(|(_, file)| file.display().to_string());
+    quote! {
+        /// A file of the directory.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub struct File {
+            path: &'static str,
+            contents: &'static [u8],
+        }
+
+        impl File {
+            /// Path of the file, relative to the directory.
+            pub const fn path(&self) -> &'static str {
+                self.path
+            }
+
+            /// Contents of the file.
+            pub const fn contents(&self) -> &'static [u8] {
+                self.contents
+            }
+
+            /// Contents of the file, if it is UTF-8 text.
+            pub fn contents_utf8(&self) -> Option<&'static str> {
+                ::core::str::from_utf8(self.contents).ok()
+            }
+        }
+
+        /// The files of the directory, sorted by path.
+        #[derive(Debug, Clone, Copy)]
+        pub struct Dir {
+            files: &'static [File],
+        }
+
+        impl Dir {
+            /// Every file of the directory, sorted by path.
+            pub const fn files(&self) -> &'static [File] {
+                self.files
+            }
+
+            /// Looks a file up by its path relative to the directory.
+            pub fn get(&self, path: &str) -> Option<&'static File> {
+                let files: &'static [File] = self.files;
+                files
+                    .binary_search_by(|file| file.path.cmp(path))
+                    .ok()
+                    .map(|index| &files[index])
+            }
+
+            /// Returns whether the directory has a file at `path`.
+            pub fn contains(&self, path: &str) -> bool {
+                self.get(path).is_some()
+            }
+
+            /// The files below the subdirectory `dir`, sorted by path.
+            pub fn files_in<'a>(
+                &'a self,
+                dir: &'a str,
+            ) -> impl Iterator<Item = &'static File> + 'a {
+                let dir = dir.trim_end_matches('/');
+                self.files.iter().filter(move |file| {
+                    file.path
+                        .strip_prefix(dir)
+                        .is_some_and(|rest| dir.is_empty() || rest.starts_with('/'))
+                })
+            }
+        }
+
+        /// The files of the directory.
+        pub static DIR: Dir = Dir {
+            files: &[#(File { path: #paths, contents: include_bytes!(#contents) }),*],
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that listings are recognized and their entries extracted
+    #[test]
+    fn test_listings() {
+        assert_eq!(Listing::detect("gh:acme/assets@v1/icons"), Listing::GitHub);
+        assert_eq!(
+            Listing::detect("https://assets.s3.eu-west-1.amazonaws.com/icons/"),
+            Listing::S3
+        );
+        assert_eq!(Listing::detect("https://example.com/icons/"), Listing::Html);
+
+        let index = r#"<a href="?C=N;O=D">Name</a> <a href="../">Parent</a>
+            <A HREF='logo.svg'>logo.svg</A> <a href=ui/>ui/</a> <a href="a&amp;b.txt">"#;
+        assert_eq!(
+            hrefs(index),
+            ["?C=N;O=D", "../", "logo.svg", "ui/", "a&b.txt"]
+        );
+
+        let page = "<ListBucketResult><Contents><Key>icons/a&amp;b.svg</Key></Contents>\
+                    <IsTruncated>false</IsTruncated></ListBucketResult>";
+        assert_eq!(xml_elements(page, "Key"), ["icons/a&b.svg"]);
+
+        let svg = matcher("**/*.svg").unwrap();
+        assert!(svg("ui/b.svg") && svg("a.svg") && !svg("a.png"));
+        assert!(!matcher("*.svg").unwrap()("ui/b.svg"));
+    }
+}
@@ -0,0 +1,179 @@
+//! Streamed and resumable downloads.
+//!
+//! Responses fetched into the cache are written to a partial file in the cache directory as
+//! they arrive, so that large content is never held in memory. A response that can be
+//! requested by range, as servers tell with `Accept-Ranges: bytes`, and that carries a
+//! strong validator, an `ETag` or a `Last-Modified` date, is resumable: when the transfer
+//! breaks off, the next attempt, a retry or the next build, asks for the rest with a `Range`
+//! request, and `If-Range` makes the server send the whole content again if it changed
+//! meanwhile.
+//!
+//! Responses decoded by the HTTP client have no known length and are downloaded again, as
+//! ranges would apply to their encoded form.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use reqwest::{
+    blocking::Response,
+    header::{ACCEPT_RANGES, CONTENT_RANGE, ETAG, LAST_MODIFIED},
+    StatusCode,
+};
+
+use crate::{check_size, error_chain, Body, Failure, FetchOptions};
+
+/// How far an interrupted download got.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Resume {
+    /// Number of bytes already written.
+    pub offset: u64,
+    /// Validator of the response being downloaded, sent in `If-Range`.
+    pub validator: String,
+}
+
+/// The file next to the partial file holding the validator of the download.
+fn validator_path(partial: &Path) -> PathBuf {
+    let mut path = partial.as_os_str().to_owned();
+    path.push(".validator");
+    PathBuf::from(path)
+}
+
+/// Returns where an interrupted download into `partial` can be resumed.
+pub(crate) fn resume_point(partial: &Path) -> Option<Resume> {
+    let offset = std::fs::metadata(partial).ok()?.len();
+    let validator = std::fs::read_to_string(validator_path(partial)).ok()?;
+    (offset > 0).then_some(Resume { offset, validator })
+}
+
+/// Removes an interrupted download.
+pub(crate) fn discard(partial: &Path) {
+    let _ = std::fs::remove_file(partial);
+    let _ = std::fs::remove_file(validator_path(partial));
+}
+
+/// Returns the strong validator of a response that can be resumed by range.
+pub(crate) fn validator(response: &Response) -> Option<String> {
+    let headers = response.headers();
+    let ranges = headers
+        .get(ACCEPT_RANGES)
+        .is_some_and(|value| value.as_bytes().eq_ignore_ascii_case(b"bytes"));
+    if !ranges || response.content_length().is_none() {
+        return None;
+    }
+    let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+    // Weak entity tags are not allowed in `If-Range`
+    header(ETAG)
+        .filter(|etag| !etag.starts_with("W/"))
+        .or_else(|| header(LAST_MODIFIED))
+        .map(str::to_string)
+}
+
+/// Returns the first byte of a `206 Partial Content` response, `bytes 100-199/200`.
+fn range_start(response: &Response) -> Option<u64> {
+    let range = response.headers().get(CONTENT_RANGE)?.to_str().ok()?;
+    let (start, _) = range.strip_prefix("bytes ")?.split_once('-')?;
+    start.trim().parse().ok()
+}
+
+/// Writes the body of a response to the partial file, appending to it when the server sent
+/// the rest of an interrupted download, and returns the complete file.
+pub(crate) fn read_body(
+    response: Response,
+    partial: &Path,
+    resume: Option<&Resume>,
+    options: &FetchOptions,
+) -> Result<Body, Failure> {
+    let io_error = |e: std::io::Error| format!("Failed to write partial download: {}", e);
+    let offset = match (response.status(), resume) {
+        (StatusCode::PARTIAL_CONTENT, Some(resume))
+            if range_start(&response) == Some(resume.offset) =>
+        {
+            resume.offset
+        }
+        (StatusCode::PARTIAL_CONTENT, _) => {
+            discard(partial);
+            return Err(Failure {
+                message: "The server sent an unexpected range of the content".to_string(),
+                transient: true,
+                retry_after: None,
+            });
+        }
+        _ => 0,
+    };
+    if let Some(length) = response.content_length() {
+        check_size(offset + length, options).inspect_err(|_| discard(partial))?;
+    }
+
+    let strong_validator = validator(&response);
+    let resumable = offset > 0 || strong_validator.is_some();
+    let mut file = match offset {
+        0 => {
+            // Without a validator an interrupted download starts over
+            match strong_validator {
+                Some(validator) => {
+                    std::fs::write(validator_path(partial), validator).map_err(io_error)?
+                }
+                None => {
+                    let _ = std::fs::remove_file(validator_path(partial));
+                }
+            }
+            File::create(partial).map_err(io_error)?
+        }
+        _ => OpenOptions::new()
+            .append(true)
+            .open(partial)
+            .map_err(io_error)?,
+    };
+    let limit = options.max_size.map_or(u64::MAX, |max| max as u64 + 1);
+    // What has been written so far is kept for the next attempt
+    std::io::copy(&mut response.take(limit.saturating_sub(offset)), &mut file).map_err(|e| {
+        let next = match resumable {
+            true => ", the next attempt resumes the download",
+            false => "",
+        };
+        Failure {
+            message: format!("Failed to read response body{}: {}", next, error_chain(&e)),
+            transient: true,
+            retry_after: None,
+        }
+    })?;
+    drop(file);
+
+    // The download is complete and no longer resumed
+    let _ = std::fs::remove_file(validator_path(partial));
+    let size = std::fs::metadata(partial).map_err(io_error)?.len();
+    check_size(size, options).inspect_err(|_| discard(partial))?;
+    Ok(Body::File(partial.to_path_buf()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that downloads are resumed from the partial file and its validator
+    #[test]
+    fn test_resume_point() {
+        let partial = std::env::temp_dir().join(format!(
+            "include_url_macro_test_{}.part",
+            std::process::id()
+        ));
+        assert_eq!(resume_point(&partial), None);
+
+        std::fs::write(&partial, b"0123").unwrap();
+        assert_eq!(resume_point(&partial), None);
+        std::fs::write(validator_path(&partial), "\"v1\"").unwrap();
+        assert_eq!(
+            resume_point(&partial),
+            Some(Resume {
+                offset: 4,
+                validator: "\"v1\"".to_string()
+            })
+        );
+
+        discard(&partial);
+        assert!(!partial.exists() && !validator_path(&partial).exists());
+    }
+}
@@ -0,0 +1,133 @@
+//! Entry extraction for the RSS and Atom feeds included by `include_rss_url!`.
+//!
+//! Feeds are parsed with `feed-rs`, which also reads RSS 0.9 and 1.0 and JSON Feed, and
+//! every entry becomes a static struct with its title, link and date. Generated entry ids
+//! are left out, as `feed-rs` falls back to random ones and the build would not be
+//! reproducible.
+
+use feed_rs::model::{Entry, Link};
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Returns the `alternate` link of an entry or feed, or the first link if none is marked.
+fn link(links: &[Link]) -> &str {
+    links
+        .iter()
+        .find(|link| link.rel.as_deref().unwrap_or("alternate") == "alternate")
+        .or(links.first())
+        .map_or("", |link| link.href.as_str())
+}
+
+/// Returns the date an entry was published, or last updated if it has no publication date,
+/// in RFC 3339 and as a Unix timestamp.
+fn date(entry: &Entry) -> (TokenStream, TokenStream) {
+    match entry.published.or(entry.updated) {
+        Some(date) => {
+            let rfc3339 = date.to_rfc3339();
+            let timestamp = date.timestamp();
+            (
+                quote! { ::core::option::Option::Some(#rfc3339) },
+                quote! { ::core::option::Option::Some(#timestamp) },
+            )
+        }
+        None => (
+            quote! { ::core::option::Option::None },
+            quote! { ::core::option::Option::None },
+        ),
+    }
+}
+
+/// Generates the `TITLE` and `LINK` of a feed, an `Entry` struct and an `ENTRIES` constant
+/// with the first `limit` entries in feed order.
+///
+/// Relative links are resolved against `base`, the URL the feed was fetched from.
+pub(crate) fn generate(
+    content: &[u8],
+    base: &str,
+    limit: Option<usize>,
+) -> Result<TokenStream, String> {
+    let feed = feed_rs::parser::Builder::new()
+        .base_uri(Some(base))
+        .build()
+        .parse(content)
+        .map_err(|e| format!("Invalid feed from URL: {}", e))?;
+    let title = feed
+        .title
+        .as_ref()
+        .map_or("", |title| title.content.as_str());
+    let link = link(&feed.links);
+    let entries = feed
+        .entries
+        .iter()
+        .take(limit.unwrap_or(usize::MAX))
+        .map(|entry| {
+            let title = entry
+                .title
+                .as_ref()
+                .map_or("", |title| title.content.as_str());
+            let link = self::link(&entry.links);
+            let (date, timestamp) = date(entry);
+            quote! {
+                Entry { title: #title, link: #link, date: #date, timestamp: #timestamp }
+            }
+        });
+
+    Ok(quote! {
+        /// The title of the feed.
+        pub const TITLE: &str = #title;
+        /// The link to the website of the feed.
+        pub const LINK: &str = #link;
+
+        /// An entry of the feed.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub struct Entry {
+            pub title: &'static str,
+            pub link: &'static str,
+            /// The publication date, or the last update if there is none, in RFC 3339.
+            pub date: ::core::option::Option<&'static str>,
+            /// The same date as seconds since the Unix epoch.
+            pub timestamp: ::core::option::Option<i64>,
+        }
+
+        /// The entries of the feed, in feed order.
+        pub const ENTRIES: &[Entry] = &[#(#entries),*];
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that RSS and Atom entries are embedded with their links and dates
+    #[test]
+    fn test_generate() {
+        let rss = br#"<?xml version="1.0"?>
+            <rss version="2.0"><channel>
+              <title>Blog</title><link>https://example.com/</link>
+              <item><title>First</title><link>/posts/1</link>
+                <pubDate>Tue, 02 Jan 2024 10:00:00 GMT</pubDate></item>
+              <item><title>Second</title><link>https://example.com/posts/2</link></item>
+            </channel></rss>"#;
+        let code = generate(rss, "https://example.com/feed.xml", None)
+            .unwrap()
+            .to_string();
+        assert!(code.contains("pub const TITLE : & str = \"Blog\""));
+        assert!(code.contains(
+            "Entry { title : \"First\" , link : \"https://example.com/posts/1\" , date : :: \
+             core :: option :: Option :: Some (\"2024-01-02T10:00:00+00:00\") , timestamp : :: \
+             core :: option :: Option :: Some (1704189600i64) }"
+        ));
+        assert!(code.contains("Entry { title : \"Second\""));
+
+        let atom = br#"<feed xmlns="http://www.w3.org/2005/Atom"><title>News</title>
+              <entry><title>Only</title><id>urn:1</id><updated>2024-01-02T10:00:00Z</updated>
+                <link rel="alternate" href="https://example.com/1"/></entry>
+              <entry><title>Dropped</title><id>urn:2</id></entry></feed>"#;
+        let code = generate(atom, "https://example.com/atom.xml", Some(1))
+            .unwrap()
+            .to_string();
+        assert!(code.contains("title : \"Only\" , link : \"https://example.com/1\""));
+        assert!(!code.contains("Dropped"));
+        assert!(generate(b"<html></html>", "https://example.com/", None).is_err());
+    }
+}
@@ -0,0 +1,67 @@
+//! `file://` URLs, read from the local file system when allowed by `allow_file_urls`.
+//!
+//! They let a macro point at a local mirror during development, such as through
+//! `include_url!("${ASSETS}/schema.json")` with `ASSETS=file:///srv/assets`, and still go
+//! through the compression, caching and format pipeline of fetched content. The file is
+//! read on every expansion and tracked by the compiler, so editing it takes effect at once.
+
+use std::path::PathBuf;
+
+use url::Url;
+
+use crate::{check_size, FetchOptions};
+
+/// Tells whether a URL is a file URL, whose scheme is case insensitive.
+pub(crate) fn is_file_url(url_str: &str) -> bool {
+    url_str
+        .get(..7)
+        .is_some_and(|scheme| scheme.eq_ignore_ascii_case("file://"))
+}
+
+/// Returns the local path of a file URL.
+pub(crate) fn path(url_str: &str) -> Result<PathBuf, String> {
+    let url = Url::parse(url_str).map_err(|e| format!("Invalid URL: {}", e))?;
+    url.to_file_path()
+        .map_err(|_| format!("`{}` does not name a local file", url_str))
+}
+
+/// Reads the file named by a file URL.
+pub(crate) fn read(url_str: &str, options: &FetchOptions) -> Result<bytes::Bytes, String> {
+    if options.allow_file_urls != Some(true) {
+        return Err(
+            "`file://` URLs are only read with `allow_file_urls = true`, given to the macro \
+             or in the crate wide defaults"
+                .to_string(),
+        );
+    }
+    let path = path(url_str)?;
+    let read_error = |e: std::io::Error| format!("Failed to read `{}`: {}", path.display(), e);
+    let size = std::fs::metadata(&path).map_err(read_error)?.len();
+    check_size(size, options).map_err(|failure| failure.message)?;
+    std::fs::read(&path).map(Into::into).map_err(read_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that files are only read when allowed, and within the size limit
+    #[test]
+    fn test_read() {
+        let path = std::env::temp_dir().join("include_url_file_url_test.txt");
+        std::fs::write(&path, "local mirror").unwrap();
+        let url = Url::from_file_path(&path).unwrap().to_string();
+        assert!(is_file_url(&url));
+
+        let denied = read(&url, &FetchOptions::default()).unwrap_err();
+        assert!(denied.contains("allow_file_urls"));
+        let mut options = FetchOptions {
+            allow_file_urls: Some(true),
+            ..FetchOptions::default()
+        };
+        assert_eq!(read(&url, &options).unwrap(), "local mirror");
+        options.max_size = Some(5);
+        assert!(read(&url, &options).unwrap_err().contains("max_size"));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,169 @@
+//! Validation and subsetting of the fonts included by `include_font_url!`.
+//!
+//! TrueType and OpenType fonts are parsed with `ttf-parser`, and subset with `subsetter`,
+//! which removes the outlines of the glyphs that are not needed but keeps the glyph ids, so
+//! the character map of the font stays valid. The layout tables, such as kerning, are
+//! dropped by the subset. WOFF and WOFF2 fonts are decoded before they are subset, and the
+//! result is encoded again in the format asked for.
+
+use std::{borrow::Cow, collections::BTreeSet};
+
+use crate::woff;
+
+/// The container of a fetched font.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FontKind {
+    /// A TrueType or OpenType font, or a collection of them.
+    Sfnt,
+    /// A font whose tables are compressed with zlib.
+    Woff,
+    /// A font whose tables are transformed and compressed with Brotli.
+    Woff2,
+}
+
+impl FontKind {
+    /// Parses a format in the syntax of the CSS `format()` function of `@font-face`.
+    pub(crate) fn from_format(format: &str) -> Result<FontKind, String> {
+        match format {
+            "truetype" | "opentype" => Ok(FontKind::Sfnt),
+            "woff" => Ok(FontKind::Woff),
+            "woff2" => Ok(FontKind::Woff2),
+            _ => Err(format!(
+                "Unknown font format `{}`, expected `truetype`, `opentype`, `woff` or `woff2`",
+                format
+            )),
+        }
+    }
+}
+
+/// Detects the container of a font and checks that TrueType and OpenType fonts parse.
+pub(crate) fn validate(content: &[u8]) -> Result<FontKind, String> {
+    match content.get(..4) {
+        Some(b"wOFF") => Ok(FontKind::Woff),
+        Some(b"wOF2") => Ok(FontKind::Woff2),
+        _ => ttf_parser::Face::parse(content, 0)
+            .map(|_| FontKind::Sfnt)
+            .map_err(|e| format!("Invalid font from URL: {}", e)),
+    }
+}
+
+/// Parses a range of code points in the syntax of the CSS `unicode-range` descriptor, such
+/// as `U+0025-00FF` or `U+4E00`.
+pub(crate) fn unicode_range(range: &str) -> Result<(char, char), String> {
+    let error = || {
+        format!(
+            "Invalid Unicode range `{}`, expected a range such as `U+0025-00FF`",
+            range
+        )
+    };
+    let code_point = |value: &str| {
+        u32::from_str_radix(value, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(error)
+    };
+    let value = range
+        .strip_prefix("U+")
+        .or_else(|| range.strip_prefix("u+"))
+        .ok_or_else(error)?;
+    let (start, end) = match value.split_once('-') {
+        Some((start, end)) => (code_point(start)?, code_point(end)?),
+        None => (code_point(value)?, code_point(value)?),
+    };
+    match start <= end {
+        true => Ok((start, end)),
+        false => Err(error()),
+    }
+}
+
+/// Decodes a WOFF or WOFF2 font into a TrueType or OpenType font.
+pub(crate) fn to_sfnt(content: &[u8]) -> Result<Cow<'_, [u8]>, String> {
+    match validate(content)? {
+        FontKind::Sfnt => Ok(Cow::Borrowed(content)),
+        FontKind::Woff => woff::decode_woff(content).map(Cow::Owned),
+        FontKind::Woff2 => woff::decode_woff2(content).map(Cow::Owned),
+    }
+}
+
+/// Encodes a TrueType or OpenType font in the container `kind`.
+pub(crate) fn encode(sfnt: Vec<u8>, kind: FontKind) -> Result<Vec<u8>, String> {
+    match kind {
+        FontKind::Sfnt => Ok(sfnt),
+        FontKind::Woff => woff::encode_woff(&sfnt),
+        FontKind::Woff2 => woff::encode_woff2(&sfnt),
+    }
+}
+
+/// Subsets a font to the glyphs of `chars` and the `.notdef` glyph, returning a TrueType or
+/// OpenType font.
+///
+/// Characters the font has no glyph for are skipped, but at least one must be covered.
+pub(crate) fn subset(content: &[u8], chars: &BTreeSet<char>) -> Result<Vec<u8>, String> {
+    let content = &to_sfnt(content)?;
+    let face =
+        ttf_parser::Face::parse(content, 0).map_err(|e| format!("Invalid font from URL: {}", e))?;
+    let mut glyphs: Vec<u16> = chars
+        .iter()
+        .filter_map(|c| face.glyph_index(*c))
+        .map(|glyph| glyph.0)
+        .collect();
+    if glyphs.is_empty() {
+        return Err("The font has no glyphs for the requested characters".to_string());
+    }
+    glyphs.push(0);
+    glyphs.sort_unstable();
+    glyphs.dedup();
+    subsetter::subset(content, 0, subsetter::Profile::pdf(&glyphs))
+        .map_err(|e| format!("Failed to subset the font: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that CSS Unicode ranges are parsed
+    #[test]
+    fn test_unicode_range() {
+        assert_eq!(unicode_range("U+0025-00FF"), Ok(('%', 'ÿ')));
+        assert_eq!(unicode_range("u+4e00"), Ok(('一', '一')));
+        assert!(unicode_range("0025-00FF").is_err());
+        assert!(unicode_range("U+00FF-0025").is_err());
+        assert!(unicode_range("U+D800").is_err());
+    }
+
+    /// Test that web fonts are recognized and other content is rejected
+    #[test]
+    fn test_validate() {
+        assert_eq!(validate(b"wOF2\0\x01\0\0"), Ok(FontKind::Woff2));
+        assert_eq!(validate(b"wOFF\0\x01\0\0"), Ok(FontKind::Woff));
+        assert!(validate(b"<html></html>").is_err());
+        assert!(subset(b"<html></html>", &BTreeSet::from(['a'])).is_err());
+        assert_eq!(FontKind::from_format("woff2"), Ok(FontKind::Woff2));
+        assert_eq!(FontKind::from_format("opentype"), Ok(FontKind::Sfnt));
+        assert!(FontKind::from_format("ttf").is_err());
+    }
+
+    /// Test that WOFF2 fonts are decoded to be subset, and encoded again when asked to
+    #[test]
+    fn test_subset_woff2() {
+        let (font, _) = woff::sample_font();
+        let woff2 = woff::encode_woff2(&font).unwrap();
+        assert_eq!(validate(&woff2), Ok(FontKind::Woff2));
+
+        let subsetted = subset(&woff2, &BTreeSet::from(['A', 'z'])).unwrap();
+        let face = ttf_parser::Face::parse(&subsetted, 0).unwrap();
+        let a = face.glyph_index('A').unwrap();
+        let b = face.glyph_index('B').unwrap();
+        assert!(face.glyph_bounding_box(a).is_some());
+        assert!(face.glyph_bounding_box(b).is_none());
+
+        let encoded = encode(subsetted.clone(), FontKind::Woff2).unwrap();
+        assert_eq!(validate(&encoded), Ok(FontKind::Woff2));
+        assert_eq!(to_sfnt(&encoded).unwrap(), subsetted);
+        let encoded = encode(subsetted.clone(), FontKind::Woff).unwrap();
+        assert_eq!(validate(&encoded), Ok(FontKind::Woff));
+        assert_eq!(to_sfnt(&encoded).unwrap(), subsetted);
+
+        assert!(subset(&woff2, &BTreeSet::from(['z'])).is_err());
+    }
+}
@@ -0,0 +1,245 @@
+//! Files of FTP servers, `ftp://host/path`.
+//!
+//! A minimal client of the file transfer commands of RFC 959, in binary mode over a passive
+//! data connection (`EPSV`, or `PASV` for servers that predate it). The session logs in with
+//! the `auth = basic_env("USER", "PASSWORD")` credentials, or anonymously. As RFC 1738
+//! specifies, the path is relative to the directory the session starts in, and
+//! `ftp://host/%2Fpub/file` names `/pub/file`. The connection is not encrypted.
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpStream, ToSocketAddrs},
+};
+
+use url::Url;
+
+use crate::{check_size, credential, Auth, Failure, FetchOptions};
+
+const DEFAULT_PORT: u16 = 21;
+
+/// Downloads the file named by an `ftp://` URL.
+pub(crate) fn fetch(url_str: &str, options: &FetchOptions) -> Result<bytes::Bytes, String> {
+    let url = Url::parse(url_str).map_err(|e| format!("Invalid URL: {}", e))?;
+    let host = url.host_str().unwrap_or_default();
+    let path = url.path().strip_prefix('/').unwrap_or_default();
+    let path = percent_encoding::percent_decode_str(path)
+        .decode_utf8()
+        .map_err(|e| format!("Invalid path in `{}`: {}", url_str, e))?;
+    if host.is_empty() || path.is_empty() || path.ends_with('/') {
+        return Err(format!("Expected `ftp://host/path`, got `{}`", url_str));
+    }
+    let (user, password) = match &options.auth {
+        Some(Auth::Basic(user, password)) => (credential(user)?, credential(password)?),
+        Some(Auth::Bearer(_)) => {
+            return Err("FTP servers only accept `auth = basic_env(...)`".to_string())
+        }
+        None => ("anonymous".to_string(), "anonymous@".to_string()),
+    };
+    let port = url.port().unwrap_or(DEFAULT_PORT);
+    crate::with_retries(host, options, || {
+        let mut session = Session::connect(host, port, options)?;
+        session.login(&user, &password)?;
+        let content = session.retrieve(&path, options)?;
+        // The file is complete, a failure to log out does not matter
+        let _ = session.command("QUIT");
+        Ok(content)
+    })
+}
+
+/// A control connection.
+struct Session {
+    reader: BufReader<TcpStream>,
+    stream: TcpStream,
+}
+
+/// A reply of the server, its code and the text of its last line.
+struct Reply(u16, String);
+
+impl Session {
+    fn connect(host: &str, port: u16, options: &FetchOptions) -> Result<Self, Failure> {
+        let stream = connect_host(host, port, options)?;
+        let mut session = Session {
+            reader: BufReader::new(stream.try_clone().map_err(io_failure)?),
+            stream,
+        };
+        expect(session.reply()?, &[220])?;
+        Ok(session)
+    }
+
+    fn login(&mut self, user: &str, password: &str) -> Result<(), Failure> {
+        let reply = self.command(&format!("USER {}", user))?;
+        if reply.0 == 331 {
+            expect(self.command(&format!("PASS {}", password))?, &[230, 202])?;
+        } else {
+            expect(reply, &[230])?;
+        }
+        expect(self.command("TYPE I")?, &[200])?;
+        Ok(())
+    }
+
+    /// Reads a file over a passive data connection.
+    fn retrieve(&mut self, path: &str, options: &FetchOptions) -> Result<bytes::Bytes, Failure> {
+        // Servers that do not know the size answer `SIZE` with an error, which is ignored
+        if let Reply(213, size) = self.command(&format!("SIZE {}", path))? {
+            if let Ok(size) = size.trim().parse() {
+                check_size(size, options)?;
+            }
+        }
+        let data = connect(&[self.passive()?], options)?;
+        expect(self.command(&format!("RETR {}", path))?, &[125, 150])?;
+
+        let limit = options.max_size.map_or(u64::MAX, |max| max as u64 + 1);
+        let mut content = Vec::new();
+        (data.take(limit))
+            .read_to_end(&mut content)
+            .map_err(io_failure)?;
+        if let Some(max) = options.max_size.filter(|&max| content.len() > max) {
+            return Err(format!("Content exceeds the `max_size` of {} bytes", max).into());
+        }
+        expect(self.reply()?, &[226, 250])?;
+        Ok(content.into())
+    }
+
+    /// Asks for a passive data connection, returning the address to connect to. The
+    /// address announced by `PASV` is replaced by the one of the server, which may be
+    /// behind NAT.
+    fn passive(&mut self) -> Result<SocketAddr, Failure> {
+        let server = self.stream.peer_addr().map_err(io_failure)?.ip();
+        if let Reply(229, text) = self.command("EPSV")? {
+            // `229 Entering Extended Passive Mode (|||port|)`
+            let port = (text.split('|').nth(3))
+                .and_then(|port| port.parse().ok())
+                .ok_or_else(|| format!("Invalid `EPSV` reply `{}`", text))?;
+            return Ok(SocketAddr::new(server, port));
+        }
+        // `227 Entering Passive Mode (h1,h2,h3,h4,p1,p2)`
+        let Reply(_, text) = expect(self.command("PASV")?, &[227])?;
+        let numbers: Vec<u16> = (text.split(|c: char| !c.is_ascii_digit()))
+            .filter_map(|number| number.parse().ok())
+            .collect();
+        match numbers[..] {
+            [.., _, _, _, _, high, low] if high < 256 && low < 256 => {
+                Ok(SocketAddr::new(server, high << 8 | low))
+            }
+            _ => Err(format!("Invalid `PASV` reply `{}`", text).into()),
+        }
+    }
+
+    /// Sends a command and reads its reply.
+    fn command(&mut self, command: &str) -> Result<Reply, Failure> {
+        (self.stream)
+            .write_all(format!("{}\r\n", command).as_bytes())
+            .map_err(io_failure)?;
+        self.reply()
+    }
+
+    /// Reads a reply, skipping to the last line of a multiline one.
+    fn reply(&mut self) -> Result<Reply, Failure> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if self.reader.read_line(&mut line).map_err(io_failure)? == 0 {
+                return Err(transient(
+                    "The FTP server closed the connection".to_string(),
+                ));
+            }
+            // Every line but the last of a multiline reply starts with `123-` or no code
+            if let Some(reply) = parse_reply(&line) {
+                return Ok(reply);
+            }
+        }
+    }
+}
+
+/// Parses the last line of a reply, `123 text`.
+fn parse_reply(line: &str) -> Option<Reply> {
+    let code = line.get(..3)?.parse().ok()?;
+    match line.as_bytes().get(3) {
+        Some(b' ') | Some(b'\r') | Some(b'\n') | None => {
+            Some(Reply(code, line[3..].trim().to_string()))
+        }
+        _ => None,
+    }
+}
+
+/// Fails unless the reply has one of the expected codes. `4xx` codes are temporary.
+fn expect(reply: Reply, codes: &[u16]) -> Result<Reply, Failure> {
+    if codes.contains(&reply.0) {
+        return Ok(reply);
+    }
+    Err(Failure {
+        message: format!("The FTP server answered `{} {}`", reply.0, reply.1),
+        transient: (400..500).contains(&reply.0),
+        retry_after: None,
+    })
+}
+
+/// Connects to a host, honoring the `hosts` and `ip_version` options.
+pub(crate) fn connect_host(
+    host: &str,
+    port: u16,
+    options: &FetchOptions,
+) -> Result<TcpStream, Failure> {
+    let addresses: Vec<SocketAddr> = match options.hosts.iter().find(|(name, _)| name == host) {
+        Some((_, ip)) => vec![SocketAddr::new(*ip, port)],
+        None => (host, port)
+            .to_socket_addrs()
+            .map_err(|e| transient(format!("Failed to resolve `{}`: {}", host, e)))?
+            .filter(|address| match options.ip_version {
+                Some(4) => address.is_ipv4(),
+                Some(6) => address.is_ipv6(),
+                _ => true,
+            })
+            .collect(),
+    };
+    connect(&addresses, options)
+}
+
+/// Connects to the first reachable address, with the `timeout` bounding every read.
+fn connect(addresses: &[SocketAddr], options: &FetchOptions) -> Result<TcpStream, Failure> {
+    let connect_timeout = options.connect_timeout_or_default();
+    let timeout = Some(options.timeout_or_default());
+    let mut error = None;
+    for address in addresses {
+        match TcpStream::connect_timeout(address, connect_timeout) {
+            Ok(stream) => {
+                stream.set_read_timeout(timeout).map_err(io_failure)?;
+                stream.set_write_timeout(timeout).map_err(io_failure)?;
+                return Ok(stream);
+            }
+            Err(e) => error = Some(e),
+        }
+    }
+    Err(match error {
+        Some(error) => transient(format!("Failed to connect: {}", error)),
+        None => "No address to connect to".to_string().into(),
+    })
+}
+
+fn io_failure(error: std::io::Error) -> Failure {
+    transient(format!("FTP connection failed: {}", error))
+}
+
+pub(crate) fn transient(message: String) -> Failure {
+    Failure {
+        message,
+        transient: true,
+        retry_after: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that only the last line of a multiline reply is taken as the reply
+    #[test]
+    fn test_parse_reply() {
+        assert!(parse_reply("220-Welcome to the archive\r\n").is_none());
+        assert!(parse_reply(" mirrors are listed at /pub/MIRRORS\r\n").is_none());
+        let Reply(code, text) = parse_reply("220 Ready\r\n").unwrap();
+        assert_eq!((code, text.as_str()), (220, "Ready"));
+        let Reply(code, text) = parse_reply("200\r\n").unwrap();
+        assert_eq!((code, text.as_str()), (200, ""));
+    }
+}
@@ -0,0 +1,263 @@
+//! Validation and coordinate extraction for the GeoJSON included by
+//! `include_geojson_url!`.
+//!
+//! Documents are checked against RFC 7946: the object types and their required members,
+//! positions with a longitude and latitude in range, line strings with at least two
+//! positions and closed linear rings with at least four.
+
+use serde_json::{Map, Value};
+
+/// A position as `[longitude, latitude]`.
+pub(crate) type Position = [f64; 2];
+
+/// Checks that `content` is a valid GeoJSON document.
+pub(crate) fn validate(content: &str) -> Result<(), String> {
+    paths(content).map(|_| ())
+}
+
+/// Validates a GeoJSON document and returns its coordinates as paths: every line string,
+/// polygon ring and multi-point, with single points as paths of one position.
+pub(crate) fn paths(content: &str) -> Result<Vec<Vec<Position>>, String> {
+    let value: Value =
+        serde_json::from_str(content).map_err(|e| format!("Invalid GeoJSON from URL: {}", e))?;
+    let mut paths = Vec::new();
+    object(&value, "$", &mut paths).map_err(|e| format!("Invalid GeoJSON from URL: {}", e))?;
+    Ok(paths)
+}
+
+fn object(value: &Value, path: &str, paths: &mut Vec<Vec<Position>>) -> Result<(), String> {
+    let members = value
+        .as_object()
+        .ok_or_else(|| format!("{} is not an object", path))?;
+    match type_name(members, path)? {
+        "FeatureCollection" => {
+            let features = member(members, "features", path)?
+                .as_array()
+                .ok_or_else(|| format!("{}.features is not an array", path))?;
+            for (index, feature) in features.iter().enumerate() {
+                let path = format!("{}.features[{}]", path, index);
+                let Some("Feature") = feature.get("type").and_then(Value::as_str) else {
+                    return Err(format!("{} is not a Feature", path));
+                };
+                object(feature, &path, paths)?;
+            }
+            Ok(())
+        }
+        "Feature" => {
+            let properties = member(members, "properties", path)?;
+            if !properties.is_object() && !properties.is_null() {
+                return Err(format!("{}.properties is not an object or null", path));
+            }
+            match member(members, "geometry", path)? {
+                Value::Null => Ok(()),
+                geometry => {
+                    let path = format!("{}.geometry", path);
+                    match geometry.get("type").and_then(Value::as_str) {
+                        Some("Feature" | "FeatureCollection") => {
+                            Err(format!("{} is not a geometry", path))
+                        }
+                        _ => object(geometry, &path, paths),
+                    }
+                }
+            }
+        }
+        "GeometryCollection" => {
+            let geometries = member(members, "geometries", path)?
+                .as_array()
+                .ok_or_else(|| format!("{}.geometries is not an array", path))?;
+            for (index, geometry) in geometries.iter().enumerate() {
+                let path = format!("{}.geometries[{}]", path, index);
+                match geometry.get("type").and_then(Value::as_str) {
+                    Some("Feature" | "FeatureCollection") => {
+                        return Err(format!("{} is not a geometry", path))
+                    }
+                    _ => object(geometry, &path, paths)?,
+                }
+            }
+            Ok(())
+        }
+        ty => {
+            let path = format!("{}.coordinates", path);
+            let coordinates = member(members, "coordinates", &path)?;
+            match ty {
+                "Point" => paths.push(vec![position(coordinates, &path)?]),
+                "MultiPoint" => paths.push(positions(coordinates, &path, 0)?),
+                "LineString" => paths.push(positions(coordinates, &path, 2)?),
+                "MultiLineString" => {
+                    for (index, line) in array(coordinates, &path)?.iter().enumerate() {
+                        paths.push(positions(line, &format!("{}[{}]", path, index), 2)?);
+                    }
+                }
+                "Polygon" => polygon(coordinates, &path, paths)?,
+                "MultiPolygon" => {
+                    for (index, rings) in array(coordinates, &path)?.iter().enumerate() {
+                        polygon(rings, &format!("{}[{}]", path, index), paths)?;
+                    }
+                }
+                _ => unreachable!("checked by `type_name`"),
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Returns the `type` of a GeoJSON object, which must be one of the nine defined types.
+fn type_name<'a>(object: &'a Map<String, Value>, path: &str) -> Result<&'a str, String> {
+    const TYPES: &[&str] = &[
+        "FeatureCollection",
+        "Feature",
+        "GeometryCollection",
+        "Point",
+        "MultiPoint",
+        "LineString",
+        "MultiLineString",
+        "Polygon",
+        "MultiPolygon",
+    ];
+    match member(object, "type", path)?.as_str() {
+        Some(ty) if TYPES.contains(&ty) => Ok(ty),
+        _ => Err(format!("{}.type is not a GeoJSON type", path)),
+    }
+}
+
+fn member<'a>(object: &'a Map<String, Value>, name: &str, path: &str) -> Result<&'a Value, String> {
+    object
+        .get(name)
+        .ok_or_else(|| format!("{} has no `{}` member", path, name))
+}
+
+fn array<'a>(value: &'a Value, path: &str) -> Result<&'a Vec<Value>, String> {
+    value
+        .as_array()
+        .ok_or_else(|| format!("{} is not an array", path))
+}
+
+fn position(value: &Value, path: &str) -> Result<Position, String> {
+    let numbers = array(value, path)?
+        .iter()
+        .map(Value::as_f64)
+        .collect::<Option<Vec<_>>>()
+        .filter(|numbers| numbers.len() >= 2)
+        .ok_or_else(|| format!("{} is not a position of two or more numbers", path))?;
+    let (longitude, latitude) = (numbers[0], numbers[1]);
+    if !(-180.0..=180.0).contains(&longitude) || !(-90.0..=90.0).contains(&latitude) {
+        return Err(format!(
+            "{} is out of range, expected a longitude and latitude",
+            path
+        ));
+    }
+    Ok([longitude, latitude])
+}
+
+fn positions(value: &Value, path: &str, min: usize) -> Result<Vec<Position>, String> {
+    let positions = array(value, path)?
+        .iter()
+        .enumerate()
+        .map(|(index, value)| position(value, &format!("{}[{}]", path, index)))
+        .collect::<Result<Vec<_>, _>>()?;
+    match positions.len() < min {
+        true => Err(format!("{} has fewer than {} positions", path, min)),
+        false => Ok(positions),
+    }
+}
+
+fn polygon(value: &Value, path: &str, paths: &mut Vec<Vec<Position>>) -> Result<(), String> {
+    for (index, ring) in array(value, path)?.iter().enumerate() {
+        let path = format!("{}[{}]", path, index);
+        let ring = positions(ring, &path, 4)?;
+        if ring.first() != ring.last() {
+            return Err(format!("{} is not a closed linear ring", path));
+        }
+        paths.push(ring);
+    }
+    Ok(())
+}
+
+/// Simplifies a path with the Douglas-Peucker algorithm, dropping positions closer than
+/// `tolerance` to the simplified line. Rings are kept as they are if they would collapse
+/// below four positions.
+pub(crate) fn simplify(path: &[Position], tolerance: f64) -> Vec<Position> {
+    if path.len() <= 2 {
+        return path.to_vec();
+    }
+    let mut keep = vec![false; path.len()];
+    keep[0] = true;
+    keep[path.len() - 1] = true;
+    let mut ranges = vec![(0, path.len() - 1)];
+    while let Some((start, end)) = ranges.pop() {
+        let farthest = (start + 1..end)
+            .map(|index| (index, distance(path[index], path[start], path[end])))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((index, distance)) = farthest {
+            if distance > tolerance {
+                keep[index] = true;
+                ranges.push((start, index));
+                ranges.push((index, end));
+            }
+        }
+    }
+    let simplified: Vec<Position> = path
+        .iter()
+        .zip(keep)
+        .filter_map(|(position, keep)| keep.then_some(*position))
+        .collect();
+    let ring = path.len() >= 4 && path.first() == path.last();
+    match ring && simplified.len() < 4 {
+        true => path.to_vec(),
+        false => simplified,
+    }
+}
+
+/// Returns the distance of `point` to the segment from `start` to `end`.
+fn distance(point: Position, start: Position, end: Position) -> f64 {
+    let (dx, dy) = (end[0] - start[0], end[1] - start[1]);
+    let length = dx * dx + dy * dy;
+    let t = match length {
+        0.0 => 0.0,
+        _ => (((point[0] - start[0]) * dx + (point[1] - start[1]) * dy) / length).clamp(0.0, 1.0),
+    };
+    let (x, y) = (start[0] + t * dx - point[0], start[1] + t * dy - point[1]);
+    (x * x + y * y).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that features and geometries are validated and flattened into paths
+    #[test]
+    fn test_paths() {
+        let paths = paths(
+            r#"{"type": "FeatureCollection", "features": [
+                {"type": "Feature", "properties": {"name": "A"},
+                 "geometry": {"type": "Point", "coordinates": [14.4, 50.1]}},
+                {"type": "Feature", "properties": null,
+                 "geometry": {"type": "Polygon", "coordinates": [[[0, 0], [1, 0], [1, 1], [0, 0]]]}}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(paths[0], [[14.4, 50.1]]);
+        assert_eq!(paths[1].len(), 4);
+
+        let invalid = [
+            r#"{"type": "Point", "coordinates": [200, 0]}"#,
+            r#"{"type": "LineString", "coordinates": [[0, 0]]}"#,
+            r#"{"type": "Polygon", "coordinates": [[[0, 0], [1, 0], [1, 1], [0, 1]]]}"#,
+            r#"{"type": "Feature", "geometry": null}"#,
+            r#"{"type": "FeatureCollection", "features": [{"type": "Point", "coordinates": [0, 0]}]}"#,
+            r#"{"type": "Circle", "coordinates": [0, 0]}"#,
+        ];
+        for invalid in invalid {
+            assert!(validate(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    /// Test that points close to the simplified line are dropped, but rings stay closed
+    #[test]
+    fn test_simplify() {
+        let line = [[0.0, 0.0], [1.0, 0.01], [2.0, 0.0], [3.0, 1.0]];
+        assert_eq!(simplify(&line, 0.1), [[0.0, 0.0], [2.0, 0.0], [3.0, 1.0]]);
+        let ring = [[0.0, 0.0], [1.0, 0.0], [1.0, 0.001], [0.0, 0.0]];
+        assert_eq!(simplify(&ring, 0.1), ring);
+    }
+}
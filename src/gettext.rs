@@ -0,0 +1,637 @@
+//! Compilation of the gettext catalogs included by `include_po_url!`.
+//!
+//! The `.po` file is parsed into the messages `msgfmt` would write to a `.mo` file:
+//! fuzzy, obsolete and untranslated entries are dropped, and the rest is sorted by context
+//! and message id for binary search. The C expression of the `Plural-Forms` header is
+//! compiled into a Rust function.
+
+use std::collections::BTreeMap;
+
+use proc_macro2::{Literal, TokenStream};
+use quote::quote;
+
+/// A translated message.
+#[derive(Debug, PartialEq, Eq)]
+struct Message {
+    context: Option<String>,
+    id: String,
+    /// Whether the message has a `msgid_plural`.
+    plural: bool,
+    /// The translation, or one per plural form for messages with a `msgid_plural`.
+    translations: Vec<String>,
+}
+
+/// A compiled catalog.
+#[derive(Debug)]
+pub(crate) struct Catalog {
+    header: Vec<(String, String)>,
+    plural: Expr,
+    /// The messages, keyed by context and message id.
+    messages: BTreeMap<(Option<String>, String), Message>,
+}
+
+/// An entry of a `.po` file as it is being parsed.
+#[derive(Default)]
+struct Entry {
+    line: usize,
+    fuzzy: bool,
+    context: Option<String>,
+    id: Option<String>,
+    plural_id: Option<String>,
+    translation: Option<String>,
+    plural_translations: Vec<String>,
+}
+
+/// The field that continuation strings are appended to.
+#[derive(Clone, Copy)]
+enum Field {
+    Context,
+    Id,
+    PluralId,
+    Translation,
+    PluralTranslation(usize),
+}
+
+impl Entry {
+    fn is_empty(&self) -> bool {
+        self.context.is_none() && self.id.is_none()
+    }
+
+    fn field(&mut self, field: Field) -> &mut String {
+        match field {
+            Field::Context => self.context.get_or_insert_with(String::new),
+            Field::Id => self.id.get_or_insert_with(String::new),
+            Field::PluralId => self.plural_id.get_or_insert_with(String::new),
+            Field::Translation => self.translation.get_or_insert_with(String::new),
+            Field::PluralTranslation(index) => &mut self.plural_translations[index],
+        }
+    }
+}
+
+/// Parses and checks a `.po` file.
+///
+/// Every entry needs a `msgid` and a `msgstr`, or `msgstr[N]` for each plural form if it
+/// has a `msgid_plural`, and a context and message id may only be translated once.
+pub(crate) fn parse(content: &str) -> Result<Catalog, String> {
+    let mut entries = Vec::new();
+    let mut entry = Entry::default();
+    let mut field = None;
+    for (index, line) in content.lines().enumerate() {
+        let number = index + 1;
+        let error =
+            |message: &str| format!("Invalid PO file from URL: line {}: {}", number, message);
+        let line = line.trim();
+        if line.is_empty() || line.starts_with("#~") {
+            continue;
+        }
+        if let Some(flags) = line.strip_prefix("#,") {
+            if !entry.is_empty() {
+                entries.push(std::mem::take(&mut entry));
+            }
+            entry.fuzzy |= flags.split(',').any(|flag| flag.trim() == "fuzzy");
+            field = None;
+            continue;
+        }
+        if line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('"') {
+            let field = field.ok_or_else(|| error("string outside of an entry"))?;
+            let value = unquote(line).map_err(|e| error(&e))?;
+            entry.field(field).push_str(&value);
+            continue;
+        }
+
+        let (keyword, value) = line
+            .split_once(|c: char| c.is_whitespace())
+            .ok_or_else(|| error("expected a keyword followed by a string"))?;
+        let value = unquote(value.trim()).map_err(|e| error(&e))?;
+        let next = match keyword {
+            "msgctxt" => Field::Context,
+            "msgid" => Field::Id,
+            "msgid_plural" => Field::PluralId,
+            "msgstr" => Field::Translation,
+            _ => {
+                let index = keyword
+                    .strip_prefix("msgstr[")
+                    .and_then(|index| index.strip_suffix(']'))
+                    .and_then(|index| index.parse::<usize>().ok())
+                    .ok_or_else(|| error(&format!("unknown keyword `{}`", keyword)))?;
+                if index != entry.plural_translations.len() {
+                    return Err(error(&format!(
+                        "expected `msgstr[{}]`",
+                        entry.plural_translations.len()
+                    )));
+                }
+                entry.plural_translations.push(String::new());
+                Field::PluralTranslation(index)
+            }
+        };
+        // A context or message id after a translation starts the next entry
+        let translated = entry.translation.is_some() || !entry.plural_translations.is_empty();
+        if matches!(next, Field::Context | Field::Id) && translated {
+            entries.push(std::mem::take(&mut entry));
+        }
+        if entry.is_empty() {
+            entry.line = number;
+        }
+        let duplicate = match next {
+            Field::Context => entry.context.is_some(),
+            Field::Id => entry.id.is_some(),
+            Field::PluralId => entry.plural_id.is_some(),
+            Field::Translation => entry.translation.is_some(),
+            Field::PluralTranslation(_) => false,
+        };
+        if duplicate {
+            return Err(error(&format!("duplicate `{}`", keyword)));
+        }
+        entry.field(next).push_str(&value);
+        field = Some(next);
+    }
+    if !entry.is_empty() {
+        entries.push(entry);
+    }
+
+    let mut header = Vec::new();
+    let mut plural = None;
+    let mut messages = BTreeMap::new();
+    for entry in entries {
+        let error =
+            |message: &str| format!("Invalid PO file from URL: line {}: {}", entry.line, message);
+        let id = entry.id.ok_or_else(|| error("entry without `msgid`"))?;
+        let translations = match (&entry.plural_id, entry.translation) {
+            (None, Some(translation)) if entry.plural_translations.is_empty() => {
+                vec![translation]
+            }
+            (Some(_), None) if !entry.plural_translations.is_empty() => entry.plural_translations,
+            (None, _) => {
+                return Err(error(
+                    "expected `msgstr` for an entry without `msgid_plural`",
+                ))
+            }
+            (Some(_), _) => {
+                return Err(error(
+                    "expected `msgstr[N]` for an entry with `msgid_plural`",
+                ))
+            }
+        };
+        if id.is_empty() && entry.context.is_none() {
+            header = parse_header(&translations[0]);
+            plural = header
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case("Plural-Forms"))
+                .map(|(_, value)| parse_plural_forms(value).map_err(|e| error(&e)))
+                .transpose()?;
+            continue;
+        }
+        if entry.fuzzy || translations.iter().all(String::is_empty) {
+            continue;
+        }
+        let key = (entry.context.clone(), id.clone());
+        if messages.contains_key(&key) {
+            return Err(error(&format!("`{}` is translated more than once", id)));
+        }
+        messages.insert(
+            key,
+            Message {
+                context: entry.context,
+                id,
+                plural: entry.plural_id.is_some(),
+                translations,
+            },
+        );
+    }
+
+    // Without a `Plural-Forms` header, gettext falls back to the rule of English
+    let (plurals, plural) = plural.unwrap_or((
+        2,
+        Expr::Binary(Box::new(Expr::N), "!=", Box::new(Expr::Number(1))),
+    ));
+    for message in messages.values() {
+        if message.plural && message.translations.len() != plurals {
+            return Err(format!(
+                "Invalid PO file from URL: `{}` has {} plural forms, expected {}",
+                message.id,
+                message.translations.len(),
+                plurals
+            ));
+        }
+    }
+    Ok(Catalog {
+        header,
+        plural,
+        messages,
+    })
+}
+
+/// Parses a quoted PO string, resolving its C escapes.
+fn unquote(value: &str) -> Result<String, String> {
+    let inner = value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .ok_or_else(|| format!("expected a quoted string, found `{}`", value))?;
+    let mut result = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => result.push(match chars.next() {
+                Some('n') => '\n',
+                Some('t') => '\t',
+                Some('r') => '\r',
+                Some('a') => '\x07',
+                Some('b') => '\x08',
+                Some('f') => '\x0c',
+                Some('v') => '\x0b',
+                Some(c @ ('"' | '\\')) => c,
+                Some(c) => return Err(format!("unknown escape `\\{}`", c)),
+                None => return Err("unterminated escape".to_string()),
+            }),
+            '"' => return Err("unescaped quote in string".to_string()),
+            c => result.push(c),
+        }
+    }
+    Ok(result)
+}
+
+/// Parses the `Key: value` lines of the header entry.
+fn parse_header(header: &str) -> Vec<(String, String)> {
+    header
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
+
+/// Parses a `Plural-Forms` header such as `nplurals=2; plural=(n != 1);`.
+fn parse_plural_forms(value: &str) -> Result<(usize, Expr), String> {
+    let mut plurals = None;
+    let mut plural = None;
+    for part in value.split(';') {
+        match part.split_once('=') {
+            Some((key, value)) if key.trim() == "nplurals" => {
+                plurals = value
+                    .trim()
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|plurals| *plurals > 0);
+            }
+            Some((key, value)) if key.trim() == "plural" => {
+                plural = Some(value.trim());
+            }
+            _ => {}
+        }
+    }
+    let plurals = plurals.ok_or("`Plural-Forms` has no valid `nplurals`")?;
+    let plural = plural.ok_or("`Plural-Forms` has no `plural` expression")?;
+    let expr = Parser::new(plural)?.parse()?;
+    Ok((plurals, expr))
+}
+
+/// A plural form expression, in the subset of C that gettext supports.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr {
+    N,
+    Number(u64),
+    Not(Box<Expr>),
+    Binary(Box<Expr>, &'static str, Box<Expr>),
+    Conditional(Box<Expr>, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    /// Returns a Rust expression evaluating to the `u64` value of the C expression, for an
+    /// `n: u64` in scope. Arithmetic wraps and division by zero yields zero, where C would
+    /// have undefined behavior.
+    fn to_tokens(&self) -> TokenStream {
+        match self {
+            Expr::N => quote! { n },
+            Expr::Number(value) => {
+                let value = Literal::u64_suffixed(*value);
+                quote! { #value }
+            }
+            Expr::Not(expr) => {
+                let expr = expr.to_tokens();
+                quote! { ((#expr) == 0) as u64 }
+            }
+            Expr::Conditional(condition, then, otherwise) => {
+                let (condition, then, otherwise) = (
+                    condition.to_tokens(),
+                    then.to_tokens(),
+                    otherwise.to_tokens(),
+                );
+                quote! { if (#condition) != 0 { #then } else { #otherwise } }
+            }
+            Expr::Binary(left, operator, right) => {
+                let (left, right) = (left.to_tokens(), right.to_tokens());
+                match *operator {
+                    "||" => quote! { ((#left) != 0 || (#right) != 0) as u64 },
+                    "&&" => quote! { ((#left) != 0 && (#right) != 0) as u64 },
+                    "==" => quote! { ((#left) == (#right)) as u64 },
+                    "!=" => quote! { ((#left) != (#right)) as u64 },
+                    "<" => quote! { ((#left) < (#right)) as u64 },
+                    ">" => quote! { ((#left) > (#right)) as u64 },
+                    "<=" => quote! { ((#left) <= (#right)) as u64 },
+                    ">=" => quote! { ((#left) >= (#right)) as u64 },
+                    "+" => quote! { (#left).wrapping_add(#right) },
+                    "-" => quote! { (#left).wrapping_sub(#right) },
+                    "*" => quote! { (#left).wrapping_mul(#right) },
+                    "/" => quote! { (#left).checked_div(#right).unwrap_or(0) },
+                    _ => quote! { (#left).checked_rem(#right).unwrap_or(0) },
+                }
+            }
+        }
+    }
+}
+
+/// The binary operators by precedence, from the loosest binding.
+const BINARY_OPERATORS: &[&[&str]] = &[
+    &["||"],
+    &["&&"],
+    &["==", "!="],
+    &["<=", ">=", "<", ">"],
+    &["+", "-"],
+    &["*", "/", "%"],
+];
+
+/// A recursive descent parser for plural form expressions.
+struct Parser<'a> {
+    tokens: Vec<&'a str>,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(source: &'a str) -> Result<Self, String> {
+        let mut tokens = Vec::new();
+        let mut rest = source.trim_start();
+        while !rest.is_empty() {
+            let length = if rest.starts_with(|c: char| c.is_ascii_digit()) {
+                rest.find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(rest.len())
+            } else if ["||", "&&", "==", "!=", "<=", ">="]
+                .iter()
+                .any(|operator| rest.starts_with(operator))
+            {
+                2
+            } else if rest.starts_with(|c: char| "n?:<>+-*/%!()".contains(c)) {
+                1
+            } else {
+                return Err(format!("unexpected `{}` in the plural expression", rest));
+            };
+            tokens.push(&rest[..length]);
+            rest = rest[length..].trim_start();
+        }
+        Ok(Parser {
+            tokens,
+            position: 0,
+        })
+    }
+
+    fn parse(mut self) -> Result<Expr, String> {
+        let expr = self.conditional()?;
+        match self.peek() {
+            None => Ok(expr),
+            Some(token) => Err(format!("unexpected `{}` in the plural expression", token)),
+        }
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Result<&'a str, String> {
+        let token = self
+            .peek()
+            .ok_or("the plural expression ends unexpectedly")?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), String> {
+        match self.next()? {
+            token if token == expected => Ok(()),
+            token => Err(format!(
+                "expected `{}` in the plural expression, found `{}`",
+                expected, token
+            )),
+        }
+    }
+
+    fn conditional(&mut self) -> Result<Expr, String> {
+        let condition = self.binary(0)?;
+        if self.peek() != Some("?") {
+            return Ok(condition);
+        }
+        self.position += 1;
+        let then = self.conditional()?;
+        self.expect(":")?;
+        let otherwise = self.conditional()?;
+        Ok(Expr::Conditional(
+            Box::new(condition),
+            Box::new(then),
+            Box::new(otherwise),
+        ))
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Expr, String> {
+        let Some(operators) = BINARY_OPERATORS.get(level) else {
+            return self.unary();
+        };
+        let mut left = self.binary(level + 1)?;
+        while let Some(operator) = self
+            .peek()
+            .and_then(|token| operators.iter().find(|operator| **operator == token))
+        {
+            self.position += 1;
+            let right = self.binary(level + 1)?;
+            left = Expr::Binary(Box::new(left), operator, Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        match self.next()? {
+            "!" => Ok(Expr::Not(Box::new(self.unary()?))),
+            "n" => Ok(Expr::N),
+            "(" => {
+                let expr = self.conditional()?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            token => token
+                .parse()
+                .map(Expr::Number)
+                .map_err(|_| format!("unexpected `{}` in the plural expression", token)),
+        }
+    }
+}
+
+/// Generates the items of the catalog module: the header, the messages and the lookup
+/// functions.
+pub(crate) fn generate(catalog: &Catalog) -> TokenStream {
+    let header = catalog
+        .header
+        .iter()
+        .map(|(key, value)| quote! { (#key, #value) });
+    let messages = catalog.messages.values().map(|message| {
+        let context = match &message.context {
+            Some(context) => quote! { ::core::option::Option::Some(#context) },
+            None => quote! { ::core::option::Option::None },
+        };
+        let id = &message.id;
+        let translations = &message.translations;
+        quote! { (#context, #id, &[#(#translations),*]) }
+    });
+    let plural = catalog.plural.to_tokens();
+
+    quote! {
+        /// The fields of the catalog header, such as `Language` and `Plural-Forms`.
+        pub const HEADER: &[(&str, &str)] = &[#(#header),*];
+
+        /// The translated messages as `(context, msgid, translations)`, sorted by context
+        /// and message id. Messages with a plural have a translation per plural form.
+        pub const MESSAGES: &[(::core::option::Option<&str>, &str, &[&str])] =
+            &[#(#messages),*];
+
+        /// Returns the index of the plural form to use for `n`, following the
+        /// `Plural-Forms` header.
+        #[allow(unused_parens)]
+        pub fn plural(n: u64) -> usize {
+            (#plural) as usize
+        }
+
+        fn lookup(
+            context: ::core::option::Option<&str>,
+            msgid: &str,
+        ) -> ::core::option::Option<&'static [&'static str]> {
+            MESSAGES
+                .binary_search_by(|(c, id, _)| (*c, *id).cmp(&(context, msgid)))
+                .ok()
+                .map(|index| MESSAGES[index].2)
+        }
+
+        fn plural_lookup<'a>(
+            context: ::core::option::Option<&str>,
+            msgid: &'a str,
+            msgid_plural: &'a str,
+            n: u64,
+        ) -> &'a str {
+            match lookup(context, msgid) {
+                ::core::option::Option::Some(forms) => {
+                    forms.get(plural(n)).or(forms.last()).copied().unwrap_or(msgid)
+                }
+                ::core::option::Option::None if n == 1 => msgid,
+                ::core::option::Option::None => msgid_plural,
+            }
+        }
+
+        /// Returns the translation of `msgid`, or `msgid` itself if it is not translated.
+        pub fn gettext(msgid: &str) -> &str {
+            lookup(::core::option::Option::None, msgid)
+                .and_then(|forms| forms.first().copied())
+                .unwrap_or(msgid)
+        }
+
+        /// Returns the translation of `msgid` in `context`, or `msgid` itself if it is not
+        /// translated.
+        pub fn pgettext<'a>(context: &str, msgid: &'a str) -> &'a str {
+            lookup(::core::option::Option::Some(context), msgid)
+                .and_then(|forms| forms.first().copied())
+                .unwrap_or(msgid)
+        }
+
+        /// Returns the plural form of the translation of `msgid` for `n`, falling back to
+        /// `msgid` or `msgid_plural` if it is not translated.
+        pub fn ngettext<'a>(msgid: &'a str, msgid_plural: &'a str, n: u64) -> &'a str {
+            plural_lookup(::core::option::Option::None, msgid, msgid_plural, n)
+        }
+
+        /// Returns the plural form of the translation of `msgid` in `context` for `n`.
+        pub fn npgettext<'a>(
+            context: &str,
+            msgid: &'a str,
+            msgid_plural: &'a str,
+            n: u64,
+        ) -> &'a str {
+            plural_lookup(::core::option::Option::Some(context), msgid, msgid_plural, n)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CATALOG: &str = r#"
+# German translations
+msgid ""
+msgstr ""
+"Language: de\n"
+"Plural-Forms: nplurals=2; plural=(n != 1);\n"
+
+#: src/main.rs:3
+msgid "Hello"
+msgstr "Hallo"
+
+msgctxt "menu"
+msgid "File"
+msgstr "Datei"
+
+msgid "One file"
+msgid_plural "{n} files"
+msgstr[0] "Eine Datei"
+msgstr[1] "{n} Dateien"
+
+#, fuzzy
+msgid "Goodbye"
+msgstr "Tschüss"
+
+msgid "Untranslated"
+msgstr ""
+
+#~ msgid "Obsolete"
+#~ msgstr "Veraltet"
+"#;
+
+    /// Test that fuzzy, obsolete and untranslated entries are dropped
+    #[test]
+    fn test_parse() {
+        let catalog = parse(CATALOG).unwrap();
+        assert!(catalog
+            .header
+            .contains(&("Language".to_string(), "de".to_string())));
+        let ids: Vec<&str> = catalog.messages.values().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, ["Hello", "One file", "File"]);
+        assert_eq!(
+            catalog.messages[&(None, "One file".to_string())].translations,
+            ["Eine Datei", "{n} Dateien"]
+        );
+    }
+
+    /// Test that malformed entries are reported with their line
+    #[test]
+    fn test_parse_errors() {
+        let error = parse("msgid \"Hello\"\n\nmsgid \"World\"\nmsgstr \"Welt\"\n").unwrap_err();
+        assert!(error.contains("line 3: duplicate `msgid`"), "{}", error);
+        assert!(parse("msgid \"Hello\nmsgstr \"Hallo\"\n").is_err());
+        assert!(parse("msgid \"a\"\nmsgstr \"b\"\nmsgid \"a\"\nmsgstr \"c\"\n").is_err());
+        assert!(parse("msgid \"a\"\nmsgid_plural \"as\"\nmsgstr[0] \"b\"\n").is_err());
+        assert!(parse("msgid \"a\"\nmsgstr[1] \"b\"\n").is_err());
+    }
+
+    /// Test that plural expressions follow C precedence
+    #[test]
+    fn test_plural_forms() {
+        let (plurals, expr) = parse_plural_forms(
+            "nplurals=3; plural=n%10==1 && n%100!=11 ? 0 : n%10>=2 && n%10<=4 && \
+             (n%100<10 || n%100>=20) ? 1 : 2;",
+        )
+        .unwrap();
+        assert_eq!(plurals, 3);
+        let Expr::Conditional(condition, _, otherwise) = expr else {
+            panic!("expected a conditional");
+        };
+        assert!(matches!(*condition, Expr::Binary(_, "&&", _)));
+        assert!(matches!(*otherwise, Expr::Conditional(..)));
+        assert!(parse_plural_forms("nplurals=2; plural=n != ;").is_err());
+        assert!(parse_plural_forms("plural=n != 1;").is_err());
+    }
+}
@@ -0,0 +1,237 @@
+//! Files of git repositories at a revision, for [`include_git_url!`](crate::include_git_url).
+//!
+//! The `git` command fetches the single commit of the revision, `--depth 1` and without
+//! the blobs outside of the path where the server supports partial clones, and checks out
+//! the path alone. The checked out file or directory is kept in the cache directory under
+//! a key of the repository, revision and path, and reused by later builds until a refresh
+//! is requested: revisions are expected to be pinned commits or tags. Credentials given
+//! with `auth` are passed to git through its environment, never on its command line.
+
+use std::{
+    path::{Component, Path, PathBuf},
+    process::{Command, Stdio},
+    time::Instant,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    config, credential, report, report::CacheStatus, resolve_env, revalidate, Auth, FetchOptions,
+};
+
+/// Checks out `path` of `repository` at `rev` into the cache, returning the cached file or
+/// directory.
+pub(crate) fn checkout(
+    repository: &str,
+    rev: &str,
+    path: &str,
+    options: &mut FetchOptions,
+) -> Result<PathBuf, String> {
+    let started = Instant::now();
+    let source = format!("{}@{}:{}", repository, rev, path);
+    let repository = &resolve_env(repository, options)?;
+    let relative = Path::new(path);
+    let valid = relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+    if !valid || path.is_empty() {
+        return Err(format!(
+            "Invalid path `{}`, expected a path relative to the root of the repository",
+            path
+        ));
+    }
+    if rev.is_empty() || rev.starts_with('-') {
+        return Err(format!("Invalid revision `{}`", rev));
+    }
+
+    let out_dir = Path::new(env!("INCLUDE_URL_CACHE_DIR"));
+    let mut hasher = Sha256::new();
+    for part in [repository, rev, path] {
+        hasher.update(part.as_bytes());
+        hasher.update(b"\0");
+    }
+    let entry = out_dir.join(format!("git-{:x}", hasher.finalize()));
+    let checked_out = entry.join(relative);
+    if checked_out.exists() && !revalidate::refresh_requested() {
+        report::record(
+            &source,
+            size(&checked_out),
+            CacheStatus::Hit,
+            started.elapsed(),
+            &mut options.warnings,
+        );
+        return Ok(checked_out);
+    }
+    if let Some(reason) = config::offline() {
+        return Err(format!(
+            "`{}` is not in the cache and the network is disabled by {}. Build once with \
+             network access to fetch it into the cache, offline builds then use it",
+            source, reason
+        ));
+    }
+
+    // The repository is fetched next to the entry, and only the path is moved into place
+    let work = entry.with_extension("tmp");
+    let _ = std::fs::remove_dir_all(&work);
+    let fetched = fetch(repository, rev, path, &work, options).and_then(|_| {
+        let _ = std::fs::remove_dir_all(&entry);
+        let target = entry.join(relative);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create cache directory: {}", e))?;
+        }
+        std::fs::rename(work.join(relative), &target)
+            .map_err(|e| format!("Failed to write cache entry: {}", e))
+    });
+    let _ = std::fs::remove_dir_all(&work);
+    fetched?;
+    report::record(
+        &source,
+        size(&checked_out),
+        CacheStatus::Miss,
+        started.elapsed(),
+        &mut options.warnings,
+    );
+    Ok(checked_out)
+}
+
+/// Fetches the commit of `rev` into a new repository in `work` and checks out `path`.
+fn fetch(
+    repository: &str,
+    rev: &str,
+    path: &str,
+    work: &Path,
+    options: &FetchOptions,
+) -> Result<(), String> {
+    let work = work.to_string_lossy();
+    let timeout = options.timeout_or_default().as_secs().to_string();
+    git(&["init", "--quiet", &work], options)?;
+    // Blobs left out by the partial clone are fetched from the remote by the checkout
+    git(
+        &["-C", &work, "remote", "add", "origin", repository],
+        options,
+    )?;
+    let fetch = [
+        "-C",
+        &work,
+        // Transfers stalled for longer than the timeout are given up
+        "-c",
+        "http.lowSpeedLimit=1",
+        "-c",
+        &format!("http.lowSpeedTime={}", timeout),
+        "fetch",
+        "--quiet",
+        "--depth=1",
+        "--filter=blob:none",
+        "--no-tags",
+        "origin",
+        rev,
+    ];
+    git(&fetch, options)
+        .map_err(|e| format!("Failed to fetch `{}` of `{}`: {}", rev, repository, e))?;
+    git(
+        &["-C", &work, "checkout", "--quiet", "FETCH_HEAD", "--", path],
+        options,
+    )
+    .map_err(|e| format!("No `{}` at `{}` of `{}`: {}", path, rev, repository, e))
+}
+
+/// Runs git without prompting for credentials, with the `auth` credentials passed as an
+/// extra header through its environment.
+fn git(args: &[&str], options: &FetchOptions) -> Result<(), String> {
+    let mut command = Command::new("git");
+    command
+        .args(args)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped());
+    let header = match &options.auth {
+        Some(Auth::Bearer(token)) => Some(format!("Bearer {}", credential(token)?)),
+        Some(Auth::Basic(user, password)) => {
+            let pair = format!("{}:{}", credential(user)?, credential(password)?);
+            Some(format!("Basic {}", STANDARD.encode(pair)))
+        }
+        None => None,
+    };
+    if let Some(header) = header {
+        command
+            .env("GIT_CONFIG_COUNT", "1")
+            .env("GIT_CONFIG_KEY_0", "http.extraHeader")
+            .env("GIT_CONFIG_VALUE_0", format!("Authorization: {}", header));
+    }
+    let output = command
+        .output()
+        .map_err(|e| format!("Failed to run `git`, which has to be installed: {}", e))?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    Err(stderr
+        .trim()
+        .lines()
+        .last()
+        .unwrap_or("git failed")
+        .to_string())
+}
+
+/// Total size of the files of a checked out path, for the report.
+fn size(path: &Path) -> u64 {
+    files(path)
+        .iter()
+        .filter_map(|(_, file)| file.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Lists the files of a checked out directory, or the file itself, with their paths
+/// relative to it, sorted.
+pub(crate) fn files(path: &Path) -> Vec<(String, PathBuf)> {
+    fn walk(dir: &Path, prefix: &str, files: &mut Vec<(String, PathBuf)>) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let relative = format!("{}{}", prefix, name);
+            match entry.file_type() {
+                Ok(kind) if kind.is_dir() => walk(&entry.path(), &format!("{}/", relative), files),
+                Ok(kind) if kind.is_file() => files.push((relative, entry.path())),
+                _ => {}
+            }
+        }
+    }
+    let mut files = Vec::new();
+    if path.is_dir() {
+        walk(path, "", &mut files);
+    } else if path.is_file() {
+        files.push((String::new(), path.to_path_buf()));
+    }
+    files.sort();
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that the files of a directory are listed with relative paths, in order
+    #[test]
+    fn test_files() {
+        let dir = std::env::temp_dir().join("include_url_git_files_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("v1/nested")).unwrap();
+        std::fs::write(dir.join("v1/b.json"), "{}").unwrap();
+        std::fs::write(dir.join("v1/nested/a.json"), "[]").unwrap();
+        std::fs::write(dir.join("v1/a.json"), "[1]").unwrap();
+        let names: Vec<String> = files(&dir.join("v1"))
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, ["a.json", "b.json", "nested/a.json"]);
+        assert_eq!(files(&dir.join("v1/b.json")).len(), 1);
+        assert_eq!(size(&dir.join("v1")), 7);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
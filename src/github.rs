@@ -0,0 +1,588 @@
+//! Shorthands for content hosted by GitHub.
+//!
+//! `gh:owner/repo@ref/path` names a file of a repository at a branch, tag or commit,
+//! `HEAD` when `@ref` is left out. It is read from `raw.githubusercontent.com`, or through
+//! the contents API when credentials are given with `auth`, which private repositories
+//! need. A ref holding a slash cannot be told apart from the path, such branches need the
+//! full URL.
+//!
+//! `gist:user/id/file` names a file of a gist, or its only file without `/file`. The gist
+//! is described by the Gist API, with the credentials of `auth` for secret gists, and the
+//! file is taken from the description, or from its raw URL when the API truncated it.
+//!
+//! With `permalink = true`, URLs of raw files at a branch or tag, `gh:` shorthands,
+//! `raw.githubusercontent.com` and `github.com/owner/repo/raw/...` URLs, are pinned to the
+//! commit the ref points at when first built. The commit is resolved through the commits
+//! API, recorded in `include_url.lock` and the file is read at it, with a warning telling
+//! that the URL moves and what its permalink is.
+//!
+//! Release assets, for
+//! [`include_github_release_asset!`](crate::include_github_release_asset), are looked up in
+//! the release of a tag through the releases API. The tag `latest` is resolved once and
+//! pinned in `include_url.lock`, so that later builds keep embedding the same release.
+
+use std::{collections::BTreeMap, path::PathBuf};
+
+use reqwest::header::{HeaderMap, ACCEPT};
+use serde::Deserialize;
+use url::Url;
+
+use crate::{
+    cached_url_content, check_size, config, fetch_url_content, lockfile, Body, CompressKind,
+    FetchOptions, Fetched, Paginate,
+};
+
+const RAW_HOST: &str = "https://raw.githubusercontent.com";
+
+const API_HOST: &str = "https://api.github.com";
+
+const WEB_HOST: &str = "https://github.com";
+
+/// A file of a repository, `gh:owner/repo@ref/path`.
+#[derive(Debug, PartialEq, Eq)]
+struct RepositoryFile<'a> {
+    owner: &'a str,
+    repo: &'a str,
+    reference: &'a str,
+    path: &'a str,
+}
+
+impl<'a> RepositoryFile<'a> {
+    fn parse(url_str: &'a str) -> Result<Self, String> {
+        let invalid = || format!("Expected `gh:owner/repo@ref/path`, got `{}`", url_str);
+        let rest = url_str.strip_prefix("gh:").ok_or_else(invalid)?;
+        let (owner, rest) = rest.split_once('/').ok_or_else(invalid)?;
+        let (repository, path) = rest.split_once('/').ok_or_else(invalid)?;
+        let (repo, reference) = repository.split_once('@').unwrap_or((repository, "HEAD"));
+        let path = path.trim_start_matches('/');
+        if [owner, repo, reference, path]
+            .iter()
+            .any(|part| part.is_empty())
+        {
+            return Err(invalid());
+        }
+        Ok(RepositoryFile {
+            owner,
+            repo,
+            reference,
+            path,
+        })
+    }
+
+    fn raw_url(&self) -> String {
+        format!(
+            "{}/{}/{}/{}/{}",
+            RAW_HOST, self.owner, self.repo, self.reference, self.path
+        )
+    }
+
+    fn contents_url(&self) -> String {
+        format!(
+            "{}/repos/{}/{}/contents/{}?ref={}",
+            API_HOST, self.owner, self.repo, self.path, self.reference
+        )
+    }
+}
+
+/// A URL of a raw file of a repository, split around its ref.
+#[derive(Debug, PartialEq, Eq)]
+struct RefUrl<'a> {
+    owner: &'a str,
+    repo: &'a str,
+    reference: &'a str,
+    /// The URL up to the ref.
+    prefix: String,
+    /// The URL after the ref.
+    suffix: String,
+}
+
+impl<'a> RefUrl<'a> {
+    /// Splits `gh:` shorthands and the URLs of raw files served by GitHub, or returns `None`
+    /// for other URLs.
+    fn parse(url_str: &'a str) -> Option<Self> {
+        if url_str.starts_with("gh:") {
+            let file = RepositoryFile::parse(url_str).ok()?;
+            return Some(RefUrl {
+                owner: file.owner,
+                repo: file.repo,
+                reference: file.reference,
+                prefix: format!("gh:{}/{}@", file.owner, file.repo),
+                suffix: format!("/{}", file.path),
+            });
+        }
+        let (host, rest) = [RAW_HOST, WEB_HOST].into_iter().find_map(|host| {
+            let rest = url_str.strip_prefix(host)?.strip_prefix('/')?;
+            Some((host, rest))
+        })?;
+        let (owner, rest) = rest.split_once('/')?;
+        let (repo, rest) = rest.split_once('/')?;
+        let rest = match host {
+            WEB_HOST => rest.strip_prefix("raw/")?,
+            _ => rest,
+        };
+        // Refs may be spelled out, `refs/heads/main`, the path follows their name
+        let length = match rest.split('/').collect::<Vec<_>>()[..] {
+            ["refs", kind @ ("heads" | "tags"), name, _, ..] => {
+                "refs//".len() + kind.len() + name.len()
+            }
+            [name, _, ..] => name.len(),
+            _ => return None,
+        };
+        let (reference, suffix) = rest.split_at(length);
+        if [owner, repo, reference].iter().any(|part| part.is_empty()) {
+            return None;
+        }
+        Some(RefUrl {
+            owner,
+            repo,
+            reference,
+            prefix: url_str[..url_str.len() - rest.len()].to_string(),
+            suffix: suffix.to_string(),
+        })
+    }
+
+    fn is_commit(&self) -> bool {
+        self.reference.len() == 40 && self.reference.bytes().all(|byte| byte.is_ascii_hexdigit())
+    }
+
+    /// The URL at another ref.
+    fn at(&self, reference: &str) -> String {
+        format!("{}{}{}", self.prefix, reference, self.suffix)
+    }
+}
+
+/// Pins a URL of a raw file at a branch or tag to the commit the ref points at, pinned in
+/// `include_url.lock`, and warns that the URL moves. Returns the URL at the commit, or the
+/// URL itself when it is at a commit or not served by GitHub.
+pub(crate) fn permalink(url_str: &str, options: &mut FetchOptions) -> Result<String, String> {
+    let Some(url) = RefUrl::parse(url_str).filter(|url| !url.is_commit()) else {
+        return Ok(url_str.to_string());
+    };
+    let reference = format!("github:{}/{}@{}", url.owner, url.repo, url.reference);
+    let commit = lockfile::pinned(&reference, options, |options| {
+        if let Some(reason) = config::offline() {
+            return Err(format!(
+                "`{}` is not pinned in `include_url.lock` and the network is disabled by {}",
+                reference, reason
+            ));
+        }
+        let commit_url = format!(
+            "{}/repos/{}/{}/commits/{}",
+            API_HOST, url.owner, url.repo, url.reference
+        );
+        let mut api_options = FetchOptions {
+            sha256: None,
+            integrity: None,
+            signature_url: None,
+            pgp_key: None,
+            minisign_pub: None,
+            sigstore_identity: None,
+            zsync: None,
+            paginate: Paginate::None,
+            partial: None,
+            revalidate: None,
+            ..options.clone()
+        };
+        // The commit is sent alone rather than described
+        api_options
+            .headers
+            .push((ACCEPT.to_string(), "application/vnd.github.sha".to_string()));
+        let Fetched::Content(_, body) = fetch_url_content(&commit_url, &api_options)? else {
+            unreachable!("commits are resolved without conditional requests");
+        };
+        let commit = String::from_utf8_lossy(&body.into_bytes()?)
+            .trim()
+            .to_string();
+        match commit.len() == 40 && commit.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            true => Ok(commit),
+            false => Err(format!("Unexpected commit `{}` of `{}`", commit, reference)),
+        }
+    })?;
+    let pinned = url.at(&commit);
+    options.warnings.push(format!(
+        "`{}` points at `{}`, which moves, and is read at the commit pinned in \
+         `include_url.lock`. Use its permalink to pin it in the source: {}",
+        url_str, url.reference, pinned
+    ));
+    Ok(pinned)
+}
+
+#[derive(Deserialize)]
+struct Tree {
+    tree: Vec<TreeEntry>,
+    #[serde(default)]
+    truncated: bool,
+}
+
+#[derive(Deserialize)]
+struct TreeEntry {
+    path: String,
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+/// Lists the files below the directory of `gh:owner/repo@ref/dir` with the trees API, as
+/// their paths relative to it and their `gh:` URLs.
+pub(crate) fn list_directory(
+    url_str: &str,
+    options: &mut FetchOptions,
+) -> Result<Vec<(String, String)>, String> {
+    let dir = RepositoryFile::parse(url_str)?;
+    let tree_url = format!(
+        "{}/repos/{}/{}/git/trees/{}?recursive=1",
+        API_HOST, dir.owner, dir.repo, dir.reference
+    );
+    let mut api_options = FetchOptions {
+        paginate: Paginate::None,
+        ..options.clone()
+    };
+    api_options.headers.push((
+        ACCEPT.to_string(),
+        "application/vnd.github+json".to_string(),
+    ));
+    let tree = crate::dir::fetch_listing(&tree_url, api_options, options)?;
+    let tree: Tree = serde_json::from_slice(&tree)
+        .map_err(|e| format!("Invalid tree of `{}`: {}", url_str, e))?;
+    if tree.truncated {
+        return Err(format!(
+            "The tree of `{}` is too large to be listed at once, name a smaller directory",
+            url_str
+        ));
+    }
+    let prefix = format!("{}/", dir.path.trim_end_matches('/'));
+    Ok(tree
+        .tree
+        .into_iter()
+        .filter(|entry| entry.kind == "blob")
+        .filter_map(|entry| {
+            let relative = entry.path.strip_prefix(&prefix)?.to_string();
+            let url = format!(
+                "gh:{}/{}@{}/{}",
+                dir.owner, dir.repo, dir.reference, entry.path
+            );
+            Some((relative, url))
+        })
+        .collect())
+}
+
+/// Translates `gh:owner/repo@ref/path` into the request of the raw file.
+pub(crate) fn request(url_str: &str, options: &mut FetchOptions) -> Result<Url, String> {
+    let file = RepositoryFile::parse(url_str)?;
+    let url = match options.auth {
+        None => file.raw_url(),
+        Some(_) => {
+            // The file itself is asked for rather than its JSON description
+            options
+                .headers
+                .push((ACCEPT.to_string(), "application/vnd.github.raw".to_string()));
+            options.paginate = Paginate::None;
+            file.contents_url()
+        }
+    };
+    Url::parse(&url).map_err(|e| format!("Invalid URL `{}`: {}", url, e))
+}
+
+/// A file of a gist, `gist:user/id/file`, or its only file.
+#[derive(Debug, PartialEq, Eq)]
+struct GistFile<'a> {
+    user: &'a str,
+    id: &'a str,
+    file: Option<&'a str>,
+}
+
+impl<'a> GistFile<'a> {
+    fn parse(url_str: &'a str) -> Result<Self, String> {
+        let invalid = || {
+            format!(
+                "Expected `gist:user/id` or `gist:user/id/file`, got `{}`",
+                url_str
+            )
+        };
+        let rest = url_str.strip_prefix("gist:").ok_or_else(invalid)?;
+        let (user, rest) = rest.split_once('/').ok_or_else(invalid)?;
+        let (id, file) = match rest.split_once('/') {
+            Some((id, file)) => (id, Some(file)),
+            None => (rest, None),
+        };
+        let valid_id = !id.is_empty() && id.bytes().all(|byte| byte.is_ascii_alphanumeric());
+        if user.is_empty() || !valid_id || file.is_some_and(str::is_empty) {
+            return Err(invalid());
+        }
+        Ok(GistFile { user, id, file })
+    }
+
+    fn api_url(&self) -> String {
+        format!("{}/gists/{}", API_HOST, self.id)
+    }
+}
+
+#[derive(Deserialize)]
+struct Gist {
+    owner: Option<Owner>,
+    files: BTreeMap<String, GistContent>,
+}
+
+#[derive(Deserialize)]
+struct Owner {
+    login: String,
+}
+
+#[derive(Deserialize)]
+struct GistContent {
+    raw_url: String,
+    #[serde(default)]
+    truncated: bool,
+    content: Option<String>,
+}
+
+/// Fetches a file of a gist, neither revalidated nor resumed: the gist is described again
+/// on every fetch.
+pub(crate) fn fetch_gist(url_str: &str, options: &FetchOptions) -> Result<Fetched, String> {
+    let gist_file = GistFile::parse(url_str)?;
+    let mut api_options = FetchOptions {
+        paginate: Paginate::None,
+        partial: None,
+        revalidate: None,
+        ..options.clone()
+    };
+    api_options.headers.push((
+        ACCEPT.to_string(),
+        "application/vnd.github+json".to_string(),
+    ));
+    let Fetched::Content(_, body) = fetch_url_content(&gist_file.api_url(), &api_options)? else {
+        unreachable!("gists are described without conditional requests");
+    };
+    let gist: Gist = serde_json::from_slice(&body.into_bytes()?)
+        .map_err(|e| format!("Invalid description of gist `{}`: {}", gist_file.id, e))?;
+    if let Some(owner) = gist
+        .owner
+        .filter(|owner| !owner.login.eq_ignore_ascii_case(gist_file.user))
+    {
+        return Err(format!(
+            "Gist `{}` belongs to `{}`, not `{}`",
+            gist_file.id, owner.login, gist_file.user
+        ));
+    }
+
+    let names = || gist.files.keys().cloned().collect::<Vec<_>>().join(", ");
+    let content = match gist_file.file {
+        Some(name) => gist.files.get(name).ok_or_else(|| {
+            format!(
+                "Gist `{}` has no file `{}`, its files are: {}",
+                gist_file.id,
+                name,
+                names()
+            )
+        })?,
+        None if gist.files.len() == 1 => gist.files.values().next().unwrap(),
+        None => {
+            return Err(format!(
+                "Gist `{}` has several files, name one with `gist:{}/{}/file`: {}",
+                gist_file.id,
+                gist_file.user,
+                gist_file.id,
+                names()
+            ))
+        }
+    };
+    match &content.content {
+        Some(text) if !content.truncated => {
+            check_size(text.len() as u64, options).map_err(|failure| failure.message)?;
+            Ok(Fetched::Content(
+                HeaderMap::new(),
+                Body::Memory(text.clone().into()),
+            ))
+        }
+        _ => fetch_url_content(
+            &content.raw_url,
+            &FetchOptions {
+                paginate: Paginate::None,
+                partial: None,
+                revalidate: None,
+                ..options.clone()
+            },
+        ),
+    }
+}
+
+#[derive(Deserialize)]
+struct Release {
+    tag_name: String,
+    #[serde(default)]
+    assets: Vec<Asset>,
+}
+
+#[derive(Deserialize)]
+struct Asset {
+    name: String,
+    /// API URL of the asset, serving its content to `Accept: application/octet-stream`.
+    url: String,
+    browser_download_url: String,
+}
+
+/// Fetches the asset named `asset` of the release of `tag`, or of the latest release, of
+/// `owner/repo` into the cache.
+pub(crate) fn release_asset(
+    repository: &str,
+    tag: &str,
+    asset: &str,
+    options: &mut FetchOptions,
+) -> Result<PathBuf, String> {
+    let Some((owner, repo)) = repository
+        .split_once('/')
+        .filter(|(owner, repo)| !owner.is_empty() && !repo.is_empty() && !repo.contains('/'))
+    else {
+        return Err(format!("Expected `owner/repo`, got `{}`", repository));
+    };
+    let releases = format!("{}/repos/{}/{}/releases", API_HOST, owner, repo);
+    // Releases are left out of the lockfile, as they count the downloads of their assets
+    let mut api_options = FetchOptions {
+        sha256: None,
+        integrity: None,
+        signature_url: None,
+        pgp_key: None,
+        minisign_pub: None,
+        sigstore_identity: None,
+        zsync: None,
+        paginate: Paginate::None,
+        unlocked: true,
+        ..options.clone()
+    };
+    api_options.headers.push((
+        ACCEPT.to_string(),
+        "application/vnd.github+json".to_string(),
+    ));
+
+    let tag = if tag == "latest" {
+        let reference = format!("github-release:{}@latest", repository);
+        lockfile::pinned(&reference, &mut api_options, |api_options| {
+            latest_tag(&releases, repository, api_options)
+        })?
+    } else {
+        tag.to_string()
+    };
+    let release_url = format!("{}/tags/{}", releases, tag);
+    let release = cached_url_content(&release_url, CompressKind::None, &mut api_options)?;
+    for recorded in api_options.env {
+        if !options.env.contains(&recorded) {
+            options.env.push(recorded);
+        }
+    }
+    options.files.extend(api_options.files);
+    let release =
+        std::fs::read(release).map_err(|e| format!("Failed to open cache file: {}", e))?;
+    let release: Release = serde_json::from_slice(&release)
+        .map_err(|e| format!("Invalid release `{}` of `{}`: {}", tag, repository, e))?;
+
+    let Some(found) = release.assets.iter().find(|found| found.name == asset) else {
+        let names: Vec<_> = release
+            .assets
+            .iter()
+            .map(|found| found.name.as_str())
+            .collect();
+        return Err(format!(
+            "Release `{}` of `{}` has no asset `{}`, its assets are: {}",
+            release.tag_name,
+            repository,
+            asset,
+            names.join(", ")
+        ));
+    };
+    let url = match options.auth {
+        // The download URL of private repositories does not take the credentials
+        Some(_) => {
+            options
+                .headers
+                .push((ACCEPT.to_string(), "application/octet-stream".to_string()));
+            &found.url
+        }
+        None => &found.browser_download_url,
+    };
+    options.paginate = Paginate::None;
+    cached_url_content(url, CompressKind::None, options)
+}
+
+/// Asks the releases API for the tag of the latest release.
+fn latest_tag(releases: &str, repository: &str, options: &FetchOptions) -> Result<String, String> {
+    if let Some(reason) = config::offline() {
+        return Err(format!(
+            "The latest release of `{}` is not pinned in `include_url.lock` and the network \
+             is disabled by {}",
+            repository, reason
+        ));
+    }
+    let latest = fetch_url_content(&format!("{}/latest", releases), options)?;
+    let Fetched::Content(_, body) = latest else {
+        return Err(format!(
+            "Unexpected response for the latest release of `{}`",
+            repository
+        ));
+    };
+    let release: Release = serde_json::from_slice(&body.into_bytes()?)
+        .map_err(|e| format!("Invalid latest release of `{}`: {}", repository, e))?;
+    Ok(release.tag_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that the shorthand expands to the raw and contents URLs
+    #[test]
+    fn test_repository_file() {
+        let file = RepositoryFile::parse("gh:rust-lang/rust@1.80.0/src/version").unwrap();
+        assert_eq!(
+            file.raw_url(),
+            "https://raw.githubusercontent.com/rust-lang/rust/1.80.0/src/version"
+        );
+        assert_eq!(
+            file.contents_url(),
+            "https://api.github.com/repos/rust-lang/rust/contents/src/version?ref=1.80.0"
+        );
+        let file = RepositoryFile::parse("gh:serde-rs/json/README.md").unwrap();
+        assert_eq!(file.reference, "HEAD");
+        assert!(RepositoryFile::parse("gh:serde-rs/json").is_err());
+        assert!(RepositoryFile::parse("gh:serde-rs/json@/README.md").is_err());
+    }
+
+    /// Test that raw URLs are split around their ref
+    #[test]
+    fn test_ref_url() {
+        let url = RefUrl::parse("https://raw.githubusercontent.com/acme/data/master/a/b.json");
+        let url = url.unwrap();
+        assert_eq!(
+            (url.owner, url.repo, url.reference),
+            ("acme", "data", "master")
+        );
+        assert_eq!(
+            url.at("0123456789abcdef0123456789abcdef01234567"),
+            "https://raw.githubusercontent.com/acme/data/0123456789abcdef0123456789abcdef01234567/a/b.json"
+        );
+        let url = RefUrl::parse("https://github.com/acme/data/raw/refs/heads/main/b.json").unwrap();
+        assert_eq!(url.reference, "refs/heads/main");
+        assert_eq!(url.at("v1"), "https://github.com/acme/data/raw/v1/b.json");
+        let url = RefUrl::parse("gh:acme/data/b.json").unwrap();
+        assert_eq!(url.reference, "HEAD");
+        assert_eq!(url.at("v1"), "gh:acme/data@v1/b.json");
+        let commit = "https://raw.githubusercontent.com/acme/data/0123456789abcdef0123456789abcdef01234567/b";
+        assert!(RefUrl::parse(commit).unwrap().is_commit());
+        assert!(RefUrl::parse("https://github.com/acme/data/blob/main/b.json").is_none());
+        assert!(RefUrl::parse("https://example.com/acme/data/main/b.json").is_none());
+    }
+
+    /// Test that gist shorthands name a file, or the only file
+    #[test]
+    fn test_gist_file() {
+        let file = GistFile::parse("gist:octocat/6cad326836d38bd3a7ae/hello.rs").unwrap();
+        assert_eq!(file.file, Some("hello.rs"));
+        assert_eq!(
+            file.api_url(),
+            "https://api.github.com/gists/6cad326836d38bd3a7ae"
+        );
+        let file = GistFile::parse("gist:octocat/6cad326836d38bd3a7ae").unwrap();
+        assert_eq!((file.user, file.file), ("octocat", None));
+        assert!(GistFile::parse("gist:6cad326836d38bd3a7ae").is_err());
+        assert!(GistFile::parse("gist:octocat/../hello.rs").is_err());
+        assert!(GistFile::parse("gist:octocat/6cad326836d38bd3a7ae/").is_err());
+    }
+}
@@ -0,0 +1,268 @@
+//! Rust type generation for GraphQL schemas included by `include_graphql_schema_url!`.
+
+use std::collections::BTreeSet;
+
+use graphql_parser::schema::{Definition, Document, Type, TypeDefinition};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+use crate::snake_ident;
+
+/// Generates serde types for the object, interface, input, enum, union and scalar types
+/// of a GraphQL schema in SDL form.
+///
+/// Field names are converted to `snake_case` and nullable fields become `Option`s. Fields
+/// referring to composite types are boxed, as schemas are commonly recursive. Custom
+/// scalars become `String` aliases.
+pub(crate) fn generate(sdl: &str) -> Result<TokenStream, String> {
+    let document = graphql_parser::parse_schema::<String>(sdl)
+        .map_err(|e| format!("Invalid GraphQL schema from URL: {}", e))?;
+    let types = type_definitions(&document);
+    let composite: BTreeSet<&str> = types
+        .iter()
+        .filter(|ty| {
+            matches!(
+                ty,
+                TypeDefinition::Object(_) | TypeDefinition::Interface(_) | TypeDefinition::Union(_)
+            )
+        })
+        .map(|ty| type_name(ty))
+        .collect();
+    let defined: BTreeSet<&str> = types.iter().map(|ty| type_name(ty)).collect();
+    let schema = Schema { composite, defined };
+
+    let mut items = Vec::new();
+    for ty in &types {
+        items.push(schema.item(ty)?);
+    }
+    Ok(quote! { #(#items)* })
+}
+
+/// The type names of a schema, to resolve field types.
+struct Schema<'a> {
+    composite: BTreeSet<&'a str>,
+    defined: BTreeSet<&'a str>,
+}
+
+impl Schema<'_> {
+    /// Generates the item for a type definition.
+    fn item(&self, ty: &TypeDefinition<'_, String>) -> Result<TokenStream, String> {
+        let name = format_ident!("{}", type_name(ty));
+        let doc = description(ty).map(|doc| quote! { #[doc = #doc] });
+        let derive = quote! {
+            #[derive(Debug, Clone, PartialEq, ::serde::Serialize, ::serde::Deserialize)]
+        };
+        let item = match ty {
+            TypeDefinition::Scalar(_) => quote! {
+                #doc
+                pub type #name = ::std::string::String;
+            },
+            TypeDefinition::Object(object) => {
+                let fields = object
+                    .fields
+                    .iter()
+                    .map(|field| {
+                        self.field(&field.name, &field.field_type, field.description.as_ref())
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                quote! { #doc #derive pub struct #name { #(#fields)* } }
+            }
+            TypeDefinition::Interface(interface) => {
+                let fields = interface
+                    .fields
+                    .iter()
+                    .map(|field| {
+                        self.field(&field.name, &field.field_type, field.description.as_ref())
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                quote! { #doc #derive pub struct #name { #(#fields)* } }
+            }
+            TypeDefinition::InputObject(input) => {
+                let fields = input
+                    .fields
+                    .iter()
+                    .map(|field| {
+                        self.field(&field.name, &field.value_type, field.description.as_ref())
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                quote! { #doc #derive pub struct #name { #(#fields)* } }
+            }
+            TypeDefinition::Enum(enumeration) => {
+                let variants = enumeration.values.iter().map(|value| {
+                    let raw = &value.name;
+                    let variant = pascal_ident(raw);
+                    let doc = value
+                        .description
+                        .as_ref()
+                        .map(|doc| quote! { #[doc = #doc] });
+                    quote! { #doc #[serde(rename = #raw)] #variant, }
+                });
+                quote! {
+                    #doc
+                    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ::serde::Serialize, ::serde::Deserialize)]
+                    pub enum #name { #(#variants)* }
+                }
+            }
+            TypeDefinition::Union(union) => {
+                let variants = union
+                    .types
+                    .iter()
+                    .map(|member| {
+                        self.check_defined(member)?;
+                        let member = format_ident!("{}", member);
+                        Ok(quote! { #member(::std::boxed::Box<#member>), })
+                    })
+                    .collect::<Result<Vec<_>, String>>()?;
+                quote! {
+                    #doc
+                    #derive
+                    #[serde(tag = "__typename")]
+                    pub enum #name { #(#variants)* }
+                }
+            }
+        };
+        Ok(item)
+    }
+
+    /// Generates a struct field, renamed to its GraphQL name.
+    fn field(
+        &self,
+        name: &str,
+        ty: &Type<'_, String>,
+        description: Option<&String>,
+    ) -> Result<TokenStream, String> {
+        let ident = snake_ident(name)
+            .ok_or_else(|| format!("The GraphQL field `{}` has no Rust name", name))?;
+        let ty = self.rust_type(ty, true)?;
+        let doc = description.map(|doc| quote! { #[doc = #doc] });
+        Ok(quote! {
+            #doc
+            #[serde(rename = #name)]
+            pub #ident: #ty,
+        })
+    }
+
+    /// Maps a GraphQL type reference to a Rust type, optional unless it is non-null.
+    fn rust_type(&self, ty: &Type<'_, String>, nullable: bool) -> Result<TokenStream, String> {
+        let inner = match ty {
+            Type::NonNullType(ty) => return self.rust_type(ty, false),
+            Type::ListType(ty) => {
+                let ty = self.rust_type(ty, true)?;
+                quote! { ::std::vec::Vec<#ty> }
+            }
+            Type::NamedType(name) => match name.as_str() {
+                "Int" => quote! { i32 },
+                "Float" => quote! { f64 },
+                "Boolean" => quote! { bool },
+                "String" | "ID" => quote! { ::std::string::String },
+                name => {
+                    self.check_defined(name)?;
+                    let ident = format_ident!("{}", name);
+                    match self.composite.contains(name) {
+                        true => quote! { ::std::boxed::Box<#ident> },
+                        false => quote! { #ident },
+                    }
+                }
+            },
+        };
+        Ok(match nullable {
+            true => quote! { ::core::option::Option<#inner> },
+            false => inner,
+        })
+    }
+
+    fn check_defined(&self, name: &str) -> Result<(), String> {
+        match self.defined.contains(name) {
+            true => Ok(()),
+            false => Err(format!(
+                "Invalid GraphQL schema from URL: unknown type `{}`",
+                name
+            )),
+        }
+    }
+}
+
+/// Returns the type definitions of a schema, skipping schema definitions, directives and
+/// extensions.
+fn type_definitions<'d, 'a>(
+    document: &'d Document<'a, String>,
+) -> Vec<&'d TypeDefinition<'a, String>> {
+    document
+        .definitions
+        .iter()
+        .filter_map(|definition| match definition {
+            Definition::TypeDefinition(ty) => Some(ty),
+            _ => None,
+        })
+        .collect()
+}
+
+fn type_name<'d>(ty: &'d TypeDefinition<'_, String>) -> &'d str {
+    match ty {
+        TypeDefinition::Scalar(ty) => &ty.name,
+        TypeDefinition::Object(ty) => &ty.name,
+        TypeDefinition::Interface(ty) => &ty.name,
+        TypeDefinition::Union(ty) => &ty.name,
+        TypeDefinition::Enum(ty) => &ty.name,
+        TypeDefinition::InputObject(ty) => &ty.name,
+    }
+}
+
+fn description<'d>(ty: &'d TypeDefinition<'_, String>) -> Option<&'d String> {
+    match ty {
+        TypeDefinition::Scalar(ty) => ty.description.as_ref(),
+        TypeDefinition::Object(ty) => ty.description.as_ref(),
+        TypeDefinition::Interface(ty) => ty.description.as_ref(),
+        TypeDefinition::Union(ty) => ty.description.as_ref(),
+        TypeDefinition::Enum(ty) => ty.description.as_ref(),
+        TypeDefinition::InputObject(ty) => ty.description.as_ref(),
+    }
+}
+
+/// Converts an enum value such as `PUBLIC_REPO` into a `PascalCase` variant name.
+fn pascal_ident(name: &str) -> proc_macro2::Ident {
+    let mut variant = String::new();
+    for word in name.split('_').filter(|word| !word.is_empty()) {
+        let mut chars = word.chars();
+        variant.extend(chars.next().map(|c| c.to_ascii_uppercase()));
+        variant.extend(chars.map(|c| c.to_ascii_lowercase()));
+    }
+    match variant.chars().next() {
+        Some('0'..='9') | None => format_ident!("_{}", variant),
+        _ => format_ident!("{}", variant),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that types, nullability and recursion are mapped to valid Rust items
+    #[test]
+    fn test_generate() {
+        let code = generate(
+            r#"
+            "A user"
+            type User { id: ID!, login: String, friends: [User!]!, bestFriend: User, role: Role! }
+            enum Role { ADMIN, READ_ONLY }
+            scalar DateTime
+            union Actor = User
+            input UserFilter { createdAfter: DateTime }
+            "#,
+        )
+        .unwrap();
+        let file: syn::File = syn::parse2(code).unwrap();
+        let code = quote! { #file }.to_string();
+        assert!(code.contains(
+            "pub best_friend : :: core :: option :: Option < :: std :: boxed :: Box < User > >"
+        ));
+        assert!(
+            code.contains("pub friends : :: std :: vec :: Vec < :: std :: boxed :: Box < User > >")
+        );
+        assert!(code.contains("ReadOnly"));
+        assert!(code.contains("pub type DateTime"));
+
+        assert!(generate("type User { id: ID! ").is_err());
+        assert!(generate("type User { team: Team }").is_err());
+    }
+}
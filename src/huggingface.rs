@@ -0,0 +1,174 @@
+//! Files of the Hugging Face Hub, `hf://org/model/resolve/rev/path`, with
+//! `hf://datasets/...` and `hf://spaces/...` for the other kinds of repositories.
+//!
+//! A revision other than a commit, such as `main` or a tag, is resolved to its commit
+//! through the Hub API once and pinned in `include_url.lock`, and the URL is rewritten to
+//! the commit before it is cached, so the cached file stays the one of the pinned commit.
+//! The token of `HF_TOKEN` is sent unless credentials are given with `auth`, and
+//! `HF_ENDPOINT` replaces `https://huggingface.co`, as for the Hugging Face tools.
+
+use serde::Deserialize;
+use url::Url;
+
+use crate::{config, fetch_url_content, lockfile, Auth, FetchOptions, Fetched, Paginate};
+
+const DEFAULT_ENDPOINT: &str = "https://huggingface.co";
+
+/// Environment variable holding the access token.
+const TOKEN_VAR: &str = "HF_TOKEN";
+
+/// Environment variable replacing the URL of the Hub.
+const ENDPOINT_VAR: &str = "HF_ENDPOINT";
+
+/// A file of a repository, `hf://[kind/]repo/resolve/rev/path`.
+#[derive(Debug, PartialEq, Eq)]
+struct HubFile<'a> {
+    /// `models`, `datasets` or `spaces`.
+    kind: &'a str,
+    repo: &'a str,
+    rev: &'a str,
+    path: &'a str,
+}
+
+impl<'a> HubFile<'a> {
+    fn parse(url_str: &'a str) -> Result<Self, String> {
+        let invalid = || {
+            format!(
+                "Expected `hf://org/model/resolve/rev/path`, got `{}`",
+                url_str
+            )
+        };
+        let rest = url_str.strip_prefix("hf://").ok_or_else(invalid)?;
+        let (repo, rest) = rest.split_once("/resolve/").ok_or_else(invalid)?;
+        let (rev, path) = rest.split_once('/').ok_or_else(invalid)?;
+        let (kind, repo) = match repo.split_once('/') {
+            Some((kind @ ("datasets" | "spaces"), repo)) => (kind, repo),
+            _ => ("models", repo),
+        };
+        if [repo, rev, path].iter().any(|part| part.is_empty()) {
+            return Err(invalid());
+        }
+        Ok(HubFile {
+            kind,
+            repo,
+            rev,
+            path,
+        })
+    }
+
+    /// The repository as written in URLs, with its kind unless it is a model.
+    fn prefixed_repo(&self) -> String {
+        match self.kind {
+            "models" => self.repo.to_string(),
+            kind => format!("{}/{}", kind, self.repo),
+        }
+    }
+
+    fn is_commit(&self) -> bool {
+        self.rev.len() == 40 && self.rev.bytes().all(|byte| byte.is_ascii_hexdigit())
+    }
+}
+
+#[derive(Deserialize)]
+struct RepoInfo {
+    sha: String,
+}
+
+/// Pins the revision of a Hub URL to its commit, and sends the token of `HF_TOKEN` unless
+/// credentials are given. Returns the URL with the commit as its revision.
+pub(crate) fn pin(url_str: &str, options: &mut FetchOptions) -> Result<String, String> {
+    let file = HubFile::parse(url_str)?;
+    if options.auth.is_none() && options.env_var(TOKEN_VAR).is_some() {
+        options.auth = Some(Auth::Bearer(TOKEN_VAR.to_string()));
+    }
+    let endpoint = endpoint(options);
+    if file.is_commit() {
+        return Ok(url_str.to_string());
+    }
+    let reference = format!("hf:{}@{}", file.prefixed_repo(), file.rev);
+    let commit = lockfile::pinned(&reference, options, |options| {
+        if let Some(reason) = config::offline() {
+            return Err(format!(
+                "`{}` is not pinned in `include_url.lock` and the network is disabled by {}",
+                reference, reason
+            ));
+        }
+        let api_url = format!(
+            "{}/api/{}/{}/revision/{}",
+            endpoint, file.kind, file.repo, file.rev
+        );
+        let api_options = FetchOptions {
+            sha256: None,
+            integrity: None,
+            signature_url: None,
+            pgp_key: None,
+            minisign_pub: None,
+            sigstore_identity: None,
+            zsync: None,
+            paginate: Paginate::None,
+            partial: None,
+            revalidate: None,
+            ..options.clone()
+        };
+        let Fetched::Content(_, body) = fetch_url_content(&api_url, &api_options)? else {
+            unreachable!("revisions are resolved without conditional requests");
+        };
+        let info: RepoInfo = serde_json::from_slice(&body.into_bytes()?)
+            .map_err(|e| format!("Invalid description of `{}`: {}", reference, e))?;
+        Ok(info.sha)
+    })?;
+    Ok(format!(
+        "hf://{}/resolve/{}/{}",
+        file.prefixed_repo(),
+        commit,
+        file.path
+    ))
+}
+
+/// Translates a Hub URL into the request of the file.
+pub(crate) fn request(url_str: &str, options: &mut FetchOptions) -> Result<Url, String> {
+    let file = HubFile::parse(url_str)?;
+    let url = format!(
+        "{}/{}/resolve/{}/{}",
+        endpoint(options),
+        file.prefixed_repo(),
+        file.rev,
+        file.path
+    );
+    Url::parse(&url).map_err(|e| format!("Invalid URL `{}`: {}", url, e))
+}
+
+fn endpoint(options: &mut FetchOptions) -> String {
+    let endpoint = options.env_var(ENDPOINT_VAR);
+    let endpoint = endpoint.as_deref().unwrap_or(DEFAULT_ENDPOINT);
+    endpoint.trim_end_matches('/').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that models, datasets and revisions are told apart
+    #[test]
+    fn test_hub_file() {
+        let file =
+            HubFile::parse("hf://google-bert/bert-base-uncased/resolve/main/vocab.txt").unwrap();
+        assert_eq!(file.kind, "models");
+        assert_eq!(file.repo, "google-bert/bert-base-uncased");
+        assert_eq!((file.rev, file.path), ("main", "vocab.txt"));
+        assert!(!file.is_commit());
+
+        let file = HubFile::parse(
+            "hf://datasets/nyu-mll/glue/resolve/bcdcba79d07bc864c1c254ccfcedcce55bcc9a8c/cola/test.parquet",
+        )
+        .unwrap();
+        assert_eq!(file.prefixed_repo(), "datasets/nyu-mll/glue");
+        assert_eq!(file.path, "cola/test.parquet");
+        assert!(file.is_commit());
+
+        let file = HubFile::parse("hf://gpt2/resolve/v1.0/config.json").unwrap();
+        assert_eq!(file.repo, "gpt2");
+        assert!(HubFile::parse("hf://gpt2/blob/main/config.json").is_err());
+        assert!(HubFile::parse("hf://gpt2/resolve/main/").is_err());
+    }
+}
@@ -0,0 +1,410 @@
+//! Parsing of the iCalendar files included by `include_ical_url!`.
+//!
+//! Content lines are unfolded and checked against the RFC 5545 syntax: every property has
+//! a name and a value, components are properly nested inside a `VCALENDAR` and dates are
+//! well-formed. The events are then embedded with their text values unescaped. Time zone
+//! definitions are not evaluated, so local times keep their `TZID`.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// A date or date-time value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Date {
+    /// The value in ISO 8601, such as `2024-12-25` or `2024-12-25T09:00:00Z`.
+    iso: String,
+    /// Whether the value is a date without a time.
+    all_day: bool,
+    /// The `TZID` parameter of a local time.
+    timezone: Option<String>,
+}
+
+/// A `VEVENT` component.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Event {
+    uid: String,
+    summary: Option<String>,
+    start: Date,
+    end: Option<Date>,
+    location: Option<String>,
+    description: Option<String>,
+    rrule: Option<String>,
+}
+
+/// A content line, with the number of the line it starts on.
+struct Property {
+    line: usize,
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl Property {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(param, _)| param == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Joins folded lines, which continue with a space or tab, and returns each content line
+/// with the number of the line it starts on.
+fn unfold(content: &str) -> Vec<(usize, String)> {
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some((_, last))) => last.push_str(continuation),
+            _ if line.trim().is_empty() => {}
+            _ => lines.push((index + 1, line.to_string())),
+        }
+    }
+    lines
+}
+
+/// Parses a content line, `NAME;PARAM=value:value`.
+fn parse_property(line: usize, content: &str) -> Result<Property, String> {
+    let name_end = content
+        .find([';', ':'])
+        .ok_or("expected a property name followed by `:`")?;
+    let name = &content[..name_end];
+    let is_name = |name: &str| {
+        !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    };
+    if !is_name(name) {
+        return Err(format!("invalid property name `{}`", name));
+    }
+
+    let mut params = Vec::new();
+    let mut rest = &content[name_end..];
+    while let Some(param) = rest.strip_prefix(';') {
+        let (param_name, mut values) = param
+            .split_once('=')
+            .ok_or_else(|| format!("expected `=` after a parameter of `{}`", name))?;
+        if !is_name(param_name) {
+            return Err(format!("invalid parameter name `{}`", param_name));
+        }
+        let mut value = String::new();
+        loop {
+            if let Some(quoted) = values.strip_prefix('"') {
+                let end = quoted
+                    .find('"')
+                    .ok_or_else(|| format!("unterminated parameter value in `{}`", name))?;
+                value.push_str(&quoted[..end]);
+                values = &quoted[end + 1..];
+            } else {
+                let end = values.find([',', ';', ':']).unwrap_or(values.len());
+                value.push_str(&values[..end]);
+                values = &values[end..];
+            }
+            match values.strip_prefix(',') {
+                Some(next) => {
+                    value.push(',');
+                    values = next;
+                }
+                None => break,
+            }
+        }
+        params.push((param_name.to_ascii_uppercase(), value));
+        rest = values;
+    }
+    let value = rest
+        .strip_prefix(':')
+        .ok_or_else(|| format!("expected `:` after the parameters of `{}`", name))?;
+    Ok(Property {
+        line,
+        name: name.to_ascii_uppercase(),
+        params,
+        value: value.to_string(),
+    })
+}
+
+/// Unescapes a `TEXT` value.
+fn unescape(value: &str) -> String {
+    let mut text = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n' | 'N') => text.push('\n'),
+                Some(escaped) => text.push(escaped),
+                None => text.push('\\'),
+            },
+            c => text.push(c),
+        }
+    }
+    text
+}
+
+/// Parses a `DATE` or `DATE-TIME` value.
+fn parse_date(property: &Property) -> Result<Date, String> {
+    let value = property.value.as_str();
+    let number = |range: std::ops::Range<usize>, max: u32| {
+        value
+            .get(range)
+            .filter(|digits| digits.bytes().all(|b| b.is_ascii_digit()))
+            .and_then(|digits| digits.parse::<u32>().ok())
+            .filter(|number| *number <= max)
+    };
+    let date = (value.len() >= 8)
+        .then(|| (number(0..4, 9999), number(4..6, 12), number(6..8, 31)))
+        .and_then(|date| match date {
+            (Some(year), Some(month @ 1..), Some(day @ 1..)) => {
+                Some(format!("{:04}-{:02}-{:02}", year, month, day))
+            }
+            _ => None,
+        });
+    let invalid = || {
+        format!(
+            "invalid date `{}` in `{}`, expected `YYYYMMDD` or `YYYYMMDDTHHMMSS`",
+            value, property.name
+        )
+    };
+    let date = date.ok_or_else(invalid)?;
+    let timezone = property.param("TZID").map(str::to_string);
+    match &value[8..] {
+        "" => Ok(Date {
+            iso: date,
+            all_day: true,
+            timezone: None,
+        }),
+        time => {
+            let utc = time.ends_with('Z');
+            let time = match (
+                time.len(),
+                number(9..11, 23),
+                number(11..13, 59),
+                number(13..15, 60),
+            ) {
+                (7 | 8, Some(hour), Some(minute), Some(second))
+                    if time.starts_with('T') && (time.len() == 7 || utc) =>
+                {
+                    format!("{:02}:{:02}:{:02}", hour, minute, second)
+                }
+                _ => return Err(invalid()),
+            };
+            Ok(Date {
+                iso: format!("{}T{}{}", date, time, if utc { "Z" } else { "" }),
+                all_day: false,
+                timezone: timezone.filter(|_| !utc),
+            })
+        }
+    }
+}
+
+/// Parses and checks an iCalendar file, returning its events in file order.
+///
+/// Every event needs a `UID` and a `DTSTART`. Properties of components nested in events,
+/// such as alarms, are ignored.
+pub(crate) fn parse(content: &str) -> Result<Vec<Event>, String> {
+    let mut events = Vec::new();
+    let mut stack: Vec<(String, usize)> = Vec::new();
+    let mut event: Vec<Property> = Vec::new();
+    let mut calendars = 0;
+    for (line, content) in unfold(content) {
+        let error = |message: &str| {
+            format!(
+                "Invalid iCalendar file from URL: line {}: {}",
+                line, message
+            )
+        };
+        let property = parse_property(line, &content).map_err(|e| error(&e))?;
+        match property.name.as_str() {
+            "BEGIN" => {
+                let component = property.value.to_ascii_uppercase();
+                match (stack.is_empty(), component == "VCALENDAR") {
+                    (true, false) => return Err(error("expected `BEGIN:VCALENDAR`")),
+                    (false, true) => return Err(error("nested `VCALENDAR`")),
+                    (true, true) => calendars += 1,
+                    (false, false) => {}
+                }
+                stack.push((component, line));
+            }
+            "END" => {
+                let component = property.value.to_ascii_uppercase();
+                match stack.pop() {
+                    Some((open, _)) if open == component => {}
+                    Some((open, _)) => {
+                        return Err(error(&format!(
+                            "`END:{}` does not close `BEGIN:{}`",
+                            component, open
+                        )))
+                    }
+                    None => return Err(error(&format!("unexpected `END:{}`", component))),
+                }
+                if component == "VEVENT" && stack.len() == 1 {
+                    events.push(parse_event(line, std::mem::take(&mut event))?);
+                }
+            }
+            _ => match stack.last() {
+                None => return Err(error("property outside of `VCALENDAR`")),
+                Some((component, _)) if component == "VEVENT" && stack.len() == 2 => {
+                    event.push(property)
+                }
+                Some(_) => {}
+            },
+        }
+    }
+    if let Some((component, line)) = stack.pop() {
+        return Err(format!(
+            "Invalid iCalendar file from URL: line {}: `BEGIN:{}` is never closed",
+            line, component
+        ));
+    }
+    match calendars {
+        0 => Err("Invalid iCalendar file from URL: no `VCALENDAR` found".to_string()),
+        _ => Ok(events),
+    }
+}
+
+/// Builds an event from its properties, `end` being the line of its `END:VEVENT`.
+fn parse_event(end: usize, properties: Vec<Property>) -> Result<Event, String> {
+    let find = |name: &str| properties.iter().find(|property| property.name == name);
+    let date = |property: &Property| {
+        parse_date(property).map_err(|e| {
+            format!(
+                "Invalid iCalendar file from URL: line {}: {}",
+                property.line, e
+            )
+        })
+    };
+    let missing = |name: &str| {
+        format!(
+            "Invalid iCalendar file from URL: line {}: the event has no `{}`",
+            end, name
+        )
+    };
+    let text = |name: &str| find(name).map(|property| unescape(&property.value));
+    Ok(Event {
+        uid: text("UID").ok_or_else(|| missing("UID"))?,
+        summary: text("SUMMARY"),
+        start: date(find("DTSTART").ok_or_else(|| missing("DTSTART"))?)?,
+        end: find("DTEND").map(date).transpose()?,
+        location: text("LOCATION"),
+        description: text("DESCRIPTION"),
+        rrule: find("RRULE").map(|property| property.value.clone()),
+    })
+}
+
+/// Generates an `Event` struct and an `EVENTS` constant holding `events`.
+pub(crate) fn generate(events: &[Event]) -> TokenStream {
+    let option = |value: Option<&str>| match value {
+        Some(value) => quote! { ::core::option::Option::Some(#value) },
+        None => quote! { ::core::option::Option::None },
+    };
+    let events = events.iter().map(|event| {
+        let uid = &event.uid;
+        let summary = event.summary.as_deref().unwrap_or_default();
+        let start = &event.start.iso;
+        let all_day = event.start.all_day;
+        let timezone = option(event.start.timezone.as_deref());
+        let end = option(event.end.as_ref().map(|end| end.iso.as_str()));
+        let location = option(event.location.as_deref());
+        let description = option(event.description.as_deref());
+        let rrule = option(event.rrule.as_deref());
+        quote! {
+            Event {
+                uid: #uid,
+                summary: #summary,
+                start: #start,
+                end: #end,
+                all_day: #all_day,
+                timezone: #timezone,
+                location: #location,
+                description: #description,
+                rrule: #rrule,
+            }
+        }
+    });
+    quote! {
+        /// An event of the calendar.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub struct Event {
+            pub uid: &'static str,
+            /// The summary, or an empty string if the event has none.
+            pub summary: &'static str,
+            /// The start in ISO 8601, a date for all-day events and a date-time otherwise,
+            /// ending with `Z` if it is in UTC.
+            pub start: &'static str,
+            /// The end in ISO 8601, which is exclusive.
+            pub end: ::core::option::Option<&'static str>,
+            pub all_day: bool,
+            /// The `TZID` of a start in local time.
+            pub timezone: ::core::option::Option<&'static str>,
+            pub location: ::core::option::Option<&'static str>,
+            pub description: ::core::option::Option<&'static str>,
+            /// The recurrence rule, such as `FREQ=YEARLY;BYMONTH=12;BYMONTHDAY=25`.
+            pub rrule: ::core::option::Option<&'static str>,
+        }
+
+        /// The events of the calendar, in file order.
+        pub const EVENTS: &[Event] = &[#(#events),*];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CALENDAR: &str = "BEGIN:VCALENDAR\r\n\
+        VERSION:2.0\r\n\
+        PRODID:-//Example//Holidays//EN\r\n\
+        BEGIN:VEVENT\r\n\
+        UID:christmas@example.com\r\n\
+        DTSTART;VALUE=DATE:20241225\r\n\
+        DTEND;VALUE=DATE:20241226\r\n\
+        SUMMARY:Christmas Day\\, observed\r\n\
+        RRULE:FREQ=YEARLY\r\n\
+        BEGIN:VALARM\r\n\
+        SUMMARY:Reminder\r\n\
+        END:VALARM\r\n\
+        END:VEVENT\r\n\
+        BEGIN:VEVENT\r\n\
+        UID:standup@example.com\r\n\
+        DTSTART;TZID=\"Europe/Prague\":20240102T090000\r\n\
+        DESCRIPTION:Daily standup\\nin the big\r\n  room\r\n\
+        END:VEVENT\r\n\
+        END:VCALENDAR\r\n";
+
+    /// Test that events are parsed with unfolded and unescaped values
+    #[test]
+    fn test_parse() {
+        let events = parse(CALENDAR).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(
+            events[0].summary.as_deref(),
+            Some("Christmas Day, observed")
+        );
+        assert_eq!(events[0].start.iso, "2024-12-25");
+        assert!(events[0].start.all_day);
+        assert_eq!(events[0].rrule.as_deref(), Some("FREQ=YEARLY"));
+        assert_eq!(events[1].start.iso, "2024-01-02T09:00:00");
+        assert_eq!(events[1].start.timezone.as_deref(), Some("Europe/Prague"));
+        assert_eq!(
+            events[1].description.as_deref(),
+            Some("Daily standup\nin the big room")
+        );
+
+        let code = generate(&events).to_string();
+        assert!(code.contains("uid : \"christmas@example.com\""));
+        assert!(code.contains("pub const EVENTS : & [Event]"));
+    }
+
+    /// Test that malformed calendars are reported with their line
+    #[test]
+    fn test_parse_errors() {
+        let error =
+            parse("BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:a\nDTSTART:2024\nEND:VEVENT\n").unwrap_err();
+        assert!(error.contains("line 4: invalid date `2024`"), "{}", error);
+        let error = parse("BEGIN:VCALENDAR\nBEGIN:VEVENT\nEND:VTODO\nEND:VCALENDAR\n").unwrap_err();
+        assert!(error.contains("line 3:"), "{}", error);
+        assert!(
+            parse("BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:a\nEND:VEVENT\nEND:VCALENDAR\n").is_err()
+        );
+        assert!(parse("BEGIN:VCALENDAR\nSUMMARY\nEND:VCALENDAR\n").is_err());
+        assert!(parse("VERSION:2.0\n").is_err());
+        assert!(parse("").is_err());
+        assert!(parse_date(&parse_property(1, "DTSTART:20241301").unwrap()).is_err());
+        assert!(parse_date(&parse_property(1, "DTSTART:20240101T250000Z").unwrap()).is_err());
+    }
+}
@@ -0,0 +1,148 @@
+//! Subresource Integrity metadata, `integrity = "sha384-BASE64"`, as found in the
+//! `integrity` attribute of HTML elements and in the manifests of CDNs.
+//!
+//! As in browsers, several space separated hashes may be given, of which the ones of the
+//! strongest algorithm are used, and the content must match one of them. Hashes of unknown
+//! algorithms and options after `?` are ignored.
+
+use std::io::Read;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+/// Hash algorithms of Subresource Integrity, from the weakest to the strongest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Algorithm {
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl Algorithm {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "sha256" => Some(Algorithm::Sha256),
+            "sha384" => Some(Algorithm::Sha384),
+            "sha512" => Some(Algorithm::Sha512),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Algorithm::Sha256 => "sha256",
+            Algorithm::Sha384 => "sha384",
+            Algorithm::Sha512 => "sha512",
+        }
+    }
+
+    /// Size of the digests in bytes.
+    fn size(self) -> usize {
+        match self {
+            Algorithm::Sha256 => 32,
+            Algorithm::Sha384 => 48,
+            Algorithm::Sha512 => 64,
+        }
+    }
+
+    fn digest(self, reader: impl Read) -> Result<Vec<u8>, String> {
+        fn hash<D: Digest + std::io::Write>(mut reader: impl Read) -> Result<Vec<u8>, String> {
+            let mut hasher = D::new();
+            std::io::copy(&mut reader, &mut hasher)
+                .map_err(|e| format!("Failed to read download: {}", e))?;
+            Ok(hasher.finalize().to_vec())
+        }
+        match self {
+            Algorithm::Sha256 => hash::<Sha256>(reader),
+            Algorithm::Sha384 => hash::<Sha384>(reader),
+            Algorithm::Sha512 => hash::<Sha512>(reader),
+        }
+    }
+}
+
+/// The hashes of the strongest algorithm of integrity metadata.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Integrity {
+    algorithm: Algorithm,
+    digests: Vec<Vec<u8>>,
+}
+
+impl Integrity {
+    pub(crate) fn parse(value: &str) -> Result<Self, String> {
+        let mut strongest: Option<Integrity> = None;
+        for token in value.split_ascii_whitespace() {
+            let hash = token.split('?').next().unwrap_or(token);
+            let Some((algorithm, encoded)) = hash.split_once('-') else {
+                return Err(format!("Expected `sha384-BASE64`, got `{}`", token));
+            };
+            let Some(algorithm) = Algorithm::parse(algorithm) else {
+                continue;
+            };
+            let digest = STANDARD
+                .decode(encoded)
+                .map_err(|e| format!("Invalid base64 digest in `{}`: {}", token, e))?;
+            if digest.len() != algorithm.size() {
+                return Err(format!("Invalid {} digest `{}`", algorithm.name(), token));
+            }
+            match &mut strongest {
+                Some(known) if known.algorithm == algorithm => known.digests.push(digest),
+                Some(known) if known.algorithm > algorithm => {}
+                _ => {
+                    strongest = Some(Integrity {
+                        algorithm,
+                        digests: vec![digest],
+                    })
+                }
+            }
+        }
+        strongest.ok_or_else(|| "Expected a sha256, sha384 or sha512 integrity hash".to_string())
+    }
+
+    /// Fails unless the content read from `reader` matches one of the hashes.
+    pub(crate) fn check(&self, reader: impl Read) -> Result<(), String> {
+        let actual = self.algorithm.digest(reader)?;
+        if self.digests.contains(&actual) {
+            return Ok(());
+        }
+        let expected: Vec<_> = self.digests.iter().map(|d| self.format(d)).collect();
+        Err(format!(
+            "Integrity mismatch: expected {}, got {}",
+            expected.join(" or "),
+            self.format(&actual)
+        ))
+    }
+
+    fn format(&self, digest: &[u8]) -> String {
+        format!("{}-{}", self.algorithm.name(), STANDARD.encode(digest))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that the strongest hashes are kept and checked
+    #[test]
+    fn test_integrity() {
+        let content = b"alert('Hello, world.');";
+        let sha384 = "sha384-H8BRh8j48O9oYatfu5AZzq6A9RINhZO5H16dQZngK7T62em8MUt1FLm52t+eX6xO";
+        let integrity = Integrity::parse(sha384).unwrap();
+        assert!(integrity.check(&content[..]).is_ok());
+
+        let sha256 = format!("sha256-{}", STANDARD.encode(Sha256::digest(content)));
+        let mixed = Integrity::parse(&format!("{} md5-abc {}?ct=text/js", sha256, sha384));
+        assert_eq!(mixed.unwrap().algorithm, Algorithm::Sha384);
+        assert!(Integrity::parse(&sha256)
+            .unwrap()
+            .check(&content[..])
+            .is_ok());
+
+        let error = integrity.check(&b"tampered"[..]).unwrap_err();
+        assert!(error.starts_with(&format!(
+            "Integrity mismatch: expected {}, got sha384-",
+            sha384
+        )));
+        assert!(Integrity::parse("md5-abc").is_err());
+        assert!(Integrity::parse("sha384-short").is_err());
+    }
+}
@@ -0,0 +1,473 @@
+//! Content-addressed fetching of `ipfs://CID/path` URLs.
+//!
+//! Content is requested from an IPFS gateway as a CAR archive of the blocks of its DAG,
+//! `https://ipfs.io/ipfs/CID/path?format=car` unless another gateway is configured. Every
+//! block is checked against the hash its CID names before the file is reassembled from
+//! them, so that a gateway cannot alter the content. Files are UnixFS DAGs, `dag-pb` nodes
+//! linking to `raw` or `dag-pb` leaves, or a single `raw` block, and paths are resolved
+//! through UnixFS directories. Sharded directories and CIDs using other hash functions than
+//! SHA-256, the default of `ipfs add`, are not supported.
+
+use std::collections::HashMap;
+
+use reqwest::header::ACCEPT;
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use crate::FetchOptions;
+
+/// Gateway used unless one is configured.
+const DEFAULT_GATEWAY: &str = "https://ipfs.io";
+
+/// Codec of UnixFS nodes.
+const DAG_PB: u64 = 0x70;
+
+/// Codec of blocks holding plain data.
+const RAW: u64 = 0x55;
+
+/// Multihash code of SHA-256.
+const SHA2_256: u64 = 0x12;
+
+/// Base58 alphabet of Bitcoin, used by version 0 CIDs.
+const BASE58: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// A content identifier, with its SHA-256 digest.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Cid {
+    codec: u64,
+    digest: Vec<u8>,
+}
+
+impl Cid {
+    /// Parses a CID in text form, `Qm...` for version 0 or multibase encoded for version 1.
+    fn parse(value: &str) -> Result<Cid, String> {
+        let invalid = |reason: &str| format!("Invalid CID `{}`: {}", value, reason);
+        let bytes = match value.as_bytes() {
+            [b'Q', b'm', ..] if value.len() == 46 => base58_decode(value),
+            [b'z', rest @ ..] => base58_decode(std::str::from_utf8(rest).unwrap_or_default()),
+            [b'b' | b'B', rest @ ..] => base32_decode(rest),
+            [b'f' | b'F', rest @ ..] => hex_decode(rest),
+            _ => None,
+        }
+        .ok_or_else(|| invalid("expected a base58, base32 or base16 encoded CID"))?;
+        match Cid::read(&bytes) {
+            Ok((cid, length)) if length == bytes.len() => Ok(cid),
+            Ok(_) => Err(invalid("trailing bytes")),
+            Err(e) => Err(invalid(&e)),
+        }
+    }
+
+    /// Reads a CID in binary form, returning it with the number of bytes it took.
+    fn read(bytes: &[u8]) -> Result<(Cid, usize), String> {
+        // Version 0 CIDs are bare SHA-256 multihashes of `dag-pb` nodes
+        if let [0x12, 0x20, digest @ ..] = bytes {
+            let digest = digest.get(..32).ok_or("truncated CID")?;
+            let cid = Cid {
+                codec: DAG_PB,
+                digest: digest.to_vec(),
+            };
+            return Ok((cid, 34));
+        }
+        let mut position = 0;
+        let version = varint(bytes, &mut position)?;
+        if version != 1 {
+            return Err(format!("unsupported CID version {}", version));
+        }
+        let codec = varint(bytes, &mut position)?;
+        let hash = varint(bytes, &mut position)?;
+        let length = varint(bytes, &mut position)? as usize;
+        if hash != SHA2_256 || length != 32 {
+            return Err(format!(
+                "unsupported hash function 0x{:x}, only SHA-256 is supported",
+                hash
+            ));
+        }
+        let digest = (bytes.get(position..position + length)).ok_or("truncated CID")?;
+        let cid = Cid {
+            codec,
+            digest: digest.to_vec(),
+        };
+        Ok((cid, position + length))
+    }
+}
+
+/// Translates `ipfs://CID/path` into the gateway request of the CAR archive of its blocks.
+pub(crate) fn request(
+    url_str: &str,
+    gateway: Option<&str>,
+    options: &mut FetchOptions,
+) -> Result<Url, String> {
+    let (cid, path) = split_url(url_str)?;
+    Cid::parse(cid)?;
+    let gateway = gateway.unwrap_or(DEFAULT_GATEWAY).trim_end_matches('/');
+    let mut request = Url::parse(&format!("{}/ipfs/{}{}", gateway, cid, path))
+        .map_err(|e| format!("Invalid IPFS gateway `{}`: {}", gateway, e))?;
+    request.query_pairs_mut().append_pair("format", "car");
+    options
+        .headers
+        .push((ACCEPT.to_string(), "application/vnd.ipld.car".to_string()));
+    Ok(request)
+}
+
+/// Splits `ipfs://CID/path` into the CID, whose case matters, and the path.
+fn split_url(url_str: &str) -> Result<(&str, &str), String> {
+    let rest = url_str
+        .strip_prefix("ipfs://")
+        .ok_or_else(|| format!("Expected `ipfs://CID/path`, got `{}`", url_str))?;
+    let rest = rest.split(['?', '#']).next().unwrap_or_default();
+    Ok(match rest.find('/') {
+        Some(index) => rest.split_at(index),
+        None => (rest, ""),
+    })
+}
+
+/// Verifies the blocks of a CAR archive fetched for `ipfs://CID/path` and reassembles the
+/// file the URL names.
+pub(crate) fn unpack(car: &[u8], url_str: &str) -> Result<Vec<u8>, String> {
+    let (cid, path) = split_url(url_str)?;
+    let blocks = read_car(car)?;
+    let mut cid = Cid::parse(cid)?;
+    for name in path.split('/').filter(|name| !name.is_empty()) {
+        let name = percent_encoding::percent_decode_str(name).decode_utf8_lossy();
+        let node = Node::parse(block(&blocks, &cid)?)?;
+        match (cid.codec, node.unixfs_type()?) {
+            (DAG_PB, Some(1)) => {}
+            (DAG_PB, Some(5)) => {
+                return Err("Sharded IPFS directories are not supported".to_string())
+            }
+            _ => return Err(format!("Cannot resolve `{}` in a file", name)),
+        }
+        cid = (node.links.into_iter())
+            .find(|link| link.name == name)
+            .map(|link| link.cid)
+            .ok_or_else(|| format!("No `{}` in the IPFS directory", name))?;
+    }
+    let mut content = Vec::new();
+    append_file(&blocks, &cid, &mut content)?;
+    Ok(content)
+}
+
+/// Reads the blocks of a CAR archive, checking each against its CID.
+fn read_car(car: &[u8]) -> Result<HashMap<Cid, &[u8]>, String> {
+    let truncated = || "Truncated CAR archive".to_string();
+    let mut position = 0;
+    // The header names the roots, which are given by the URL
+    let header = varint(car, &mut position)? as usize;
+    position = position.checked_add(header).ok_or_else(truncated)?;
+    let mut blocks = HashMap::new();
+    while position < car.len() {
+        let length = varint(car, &mut position)? as usize;
+        let section = (position.checked_add(length))
+            .and_then(|end| car.get(position..end))
+            .ok_or_else(truncated)?;
+        let (cid, cid_length) =
+            Cid::read(section).map_err(|e| format!("Invalid CID in CAR archive: {}", e))?;
+        let data = &section[cid_length..];
+        if Sha256::digest(data)[..] != cid.digest[..] {
+            return Err(
+                "A block does not match its CID, the IPFS gateway sent altered content".to_string(),
+            );
+        }
+        blocks.insert(cid, data);
+        position += length;
+    }
+    Ok(blocks)
+}
+
+fn block<'a>(blocks: &HashMap<Cid, &'a [u8]>, cid: &Cid) -> Result<&'a [u8], String> {
+    blocks
+        .get(cid)
+        .copied()
+        .ok_or_else(|| "The IPFS gateway left out a block of the content".to_string())
+}
+
+/// Appends the content of the file rooted at `cid`, in the order of its links.
+fn append_file(
+    blocks: &HashMap<Cid, &[u8]>,
+    cid: &Cid,
+    content: &mut Vec<u8>,
+) -> Result<(), String> {
+    let data = block(blocks, cid)?;
+    match cid.codec {
+        RAW => content.extend_from_slice(data),
+        DAG_PB => {
+            let node = Node::parse(data)?;
+            match node.unixfs_type()? {
+                // Raw and file nodes
+                Some(0 | 2) => {}
+                Some(1 | 5) => return Err("The IPFS URL names a directory".to_string()),
+                _ => return Err("The IPFS URL names no file".to_string()),
+            }
+            content.extend_from_slice(node.unixfs_data()?);
+            for link in &node.links {
+                append_file(blocks, &link.cid, content)?;
+            }
+        }
+        codec => return Err(format!("Unsupported IPFS codec 0x{:x}", codec)),
+    }
+    Ok(())
+}
+
+/// A `dag-pb` node.
+struct Node<'a> {
+    links: Vec<Link>,
+    /// UnixFS message of the node.
+    data: Option<&'a [u8]>,
+}
+
+struct Link {
+    cid: Cid,
+    name: String,
+}
+
+impl<'a> Node<'a> {
+    fn parse(block: &'a [u8]) -> Result<Self, String> {
+        let mut node = Node {
+            links: Vec::new(),
+            data: None,
+        };
+        for (number, field) in fields(block)? {
+            match (number, field) {
+                (1, Field::Bytes(data)) => node.data = Some(data),
+                (2, Field::Bytes(link)) => {
+                    let mut cid = None;
+                    let mut name = String::new();
+                    for (number, field) in fields(link)? {
+                        match (number, field) {
+                            (1, Field::Bytes(hash)) => cid = Some(Cid::read(hash)?.0),
+                            (2, Field::Bytes(text)) => {
+                                name = String::from_utf8_lossy(text).into_owned()
+                            }
+                            _ => {}
+                        }
+                    }
+                    let cid = cid.ok_or("IPFS link without a CID")?;
+                    node.links.push(Link { cid, name });
+                }
+                _ => {}
+            }
+        }
+        Ok(node)
+    }
+
+    /// The UnixFS type of the node: 0 raw, 1 directory, 2 file, 5 sharded directory.
+    fn unixfs_type(&self) -> Result<Option<u64>, String> {
+        let Some(data) = self.data else {
+            return Ok(None);
+        };
+        Ok(fields(data)?.into_iter().find_map(|field| match field {
+            (1, Field::Varint(kind)) => Some(kind),
+            _ => None,
+        }))
+    }
+
+    /// The file content held by the node itself.
+    fn unixfs_data(&self) -> Result<&'a [u8], String> {
+        let Some(data) = self.data else {
+            return Ok(&[]);
+        };
+        Ok(fields(data)?
+            .into_iter()
+            .find_map(|field| match field {
+                (2, Field::Bytes(data)) => Some(data),
+                _ => None,
+            })
+            .unwrap_or_default())
+    }
+}
+
+enum Field<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+}
+
+/// Reads the varint and length-delimited fields of a protobuf message, skipping the
+/// fixed-size ones.
+fn fields(message: &[u8]) -> Result<Vec<(u64, Field<'_>)>, String> {
+    let invalid = || "Invalid IPFS node".to_string();
+    let mut fields = Vec::new();
+    let mut position = 0;
+    while position < message.len() {
+        let key = varint(message, &mut position)?;
+        let field = match key & 7 {
+            0 => Field::Varint(varint(message, &mut position)?),
+            2 => {
+                let length = varint(message, &mut position)? as usize;
+                let bytes = (position.checked_add(length))
+                    .and_then(|end| message.get(position..end))
+                    .ok_or_else(invalid)?;
+                position += length;
+                Field::Bytes(bytes)
+            }
+            1 => {
+                position += 8;
+                continue;
+            }
+            5 => {
+                position += 4;
+                continue;
+            }
+            _ => return Err(invalid()),
+        };
+        fields.push((key >> 3, field));
+    }
+    Ok(fields)
+}
+
+/// Reads an unsigned LEB128 varint.
+fn varint(bytes: &[u8], position: &mut usize) -> Result<u64, String> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *bytes.get(*position).ok_or("Truncated varint")?;
+        *position += 1;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err("Invalid varint".to_string())
+}
+
+fn base58_decode(text: &str) -> Option<Vec<u8>> {
+    let mut bytes: Vec<u8> = Vec::new();
+    for c in text.bytes() {
+        let mut carry = BASE58.iter().position(|&digit| digit == c)? as u32;
+        for byte in bytes.iter_mut().rev() {
+            carry += u32::from(*byte) * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.insert(0, carry as u8);
+            carry >>= 8;
+        }
+    }
+    let zeros = text.bytes().take_while(|&c| c == b'1').count();
+    let mut decoded = vec![0; zeros];
+    decoded.extend(bytes);
+    Some(decoded)
+}
+
+/// Decodes unpadded RFC 4648 base32, in either case.
+fn base32_decode(text: &[u8]) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len() * 5 / 8);
+    let (mut buffer, mut bits) = (0u32, 0);
+    for c in text {
+        let value = match c.to_ascii_lowercase() {
+            c @ b'a'..=b'z' => c - b'a',
+            c @ b'2'..=b'7' => c - b'2' + 26,
+            _ => return None,
+        };
+        buffer = (buffer << 5) | u32::from(value);
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+fn hex_decode(text: &[u8]) -> Option<Vec<u8>> {
+    let text = std::str::from_utf8(text).ok()?;
+    (0..text.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(text.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HELLO_DIGEST: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+    /// Test that CIDs of both versions are decoded
+    #[test]
+    fn test_cid() {
+        let digest = hex_decode(HELLO_DIGEST.as_bytes()).unwrap();
+        let cid =
+            Cid::parse("bafkreibm6jg3ux5qumhcn2b3flc3tyu6dmlb4xa7u5bf44yegnrjhc4yeq").unwrap();
+        assert_eq!(cid.codec, RAW);
+        assert_eq!(cid.digest, digest);
+        let cid = Cid::parse("QmRN6wdp1S2A5EtjW9A3M1vKSBuQQGcgvuhoMUoEz4iiT5").unwrap();
+        assert_eq!(cid.codec, DAG_PB);
+        assert_eq!(cid.digest, digest);
+        assert!(Cid::parse("bafkreibm6jg3ux5qumhcn2b3").is_err());
+        assert!(Cid::parse("hello").is_err());
+    }
+
+    fn encode_varint(mut value: u64, output: &mut Vec<u8>) {
+        while value >= 0x80 {
+            output.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        output.push(value as u8);
+    }
+
+    fn cid_bytes(codec: u64, data: &[u8]) -> Vec<u8> {
+        let mut cid = vec![1];
+        encode_varint(codec, &mut cid);
+        cid.extend([0x12, 0x20]);
+        cid.extend(Sha256::digest(data));
+        cid
+    }
+
+    fn field(number: u64, bytes: &[u8], output: &mut Vec<u8>) {
+        encode_varint(number << 3 | 2, output);
+        encode_varint(bytes.len() as u64, output);
+        output.extend(bytes);
+    }
+
+    fn car(blocks: &[(u64, &[u8])]) -> Vec<u8> {
+        let mut car = vec![1, 0];
+        for (codec, data) in blocks {
+            let cid = cid_bytes(*codec, data);
+            encode_varint((cid.len() + data.len()) as u64, &mut car);
+            car.extend(cid);
+            car.extend(*data);
+        }
+        car
+    }
+
+    /// Test that a file split into blocks and placed in a directory is reassembled
+    #[test]
+    fn test_unpack() {
+        let (first, second) = (b"hello ".as_slice(), b"world".as_slice());
+        let mut file = Vec::new();
+        for leaf in [first, second] {
+            let mut link = Vec::new();
+            field(1, &cid_bytes(RAW, leaf), &mut link);
+            field(2, &[], &mut link);
+            field(2, &link, &mut file);
+        }
+        field(1, &[0x08, 0x02], &mut file);
+        let mut directory = Vec::new();
+        let mut link = Vec::new();
+        field(1, &cid_bytes(DAG_PB, &file), &mut link);
+        field(2, b"greeting.txt", &mut link);
+        field(2, &link, &mut directory);
+        field(1, &[0x08, 0x01], &mut directory);
+
+        let archive = car(&[
+            (DAG_PB, &directory),
+            (DAG_PB, &file),
+            (RAW, first),
+            (RAW, second),
+        ]);
+        let root = cid_bytes(DAG_PB, &directory);
+        let root: String = root.iter().map(|byte| format!("{:02x}", byte)).collect();
+        let url = format!("ipfs://f{}/greeting.txt", root);
+        assert_eq!(unpack(&archive, &url).unwrap(), b"hello world");
+        assert!(unpack(&archive, &format!("ipfs://f{}", root))
+            .unwrap_err()
+            .contains("directory"));
+        assert!(unpack(&archive, &format!("ipfs://f{}/missing", root))
+            .unwrap_err()
+            .contains("No `missing`"));
+
+        let mut tampered = archive.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(unpack(&tampered, &url).unwrap_err().contains("altered"));
+    }
+}
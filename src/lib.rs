@@ -4,13 +4,70 @@
 //! - [`include_url!`] for including raw content from URLs
 //! - [`include_json_url!`] for including and parsing JSON content from URLs
 //!
+//! Struct definitions can be inferred from a JSON sample with [`include_json_url_typed!`],
+//! and the data of a GraphQL query run at compile time is included by
+//! [`include_graphql_url!`].
+//! Newline-delimited JSON is parsed line by line with [`include_ndjson_url!`], JSON with
+//! comments is normalized by [`include_json5_url!`] (`json5` feature), and TOML is parsed
+//! by [`include_toml_url!`].
+//!
+//! Further formats are available behind features: [`include_yaml_url!`] (`yaml`),
+//! [`include_csv_url!`] (`csv`), [`include_xml_url!`] (`xml`), [`include_ron_url!`]
+//! (`ron`), [`include_msgpack_url!`] (`msgpack`) and [`include_cbor_url!`] (`cbor`).
+//!
+//! Legacy configuration files become modules of constants with [`include_ini_url!`],
+//! [`include_properties_url!`] and [`include_env_url!`], gettext catalogs are compiled into
+//! lookup tables by [`include_po_url!`], the Public Suffix List by
+//! [`include_public_suffix_url!`], MIME type databases by [`include_mime_db_url!`] and
+//! Unicode Character Database files into range tables by
+//! [`include_unicode_data_url!`], word lists become static sets with
+//! [`include_wordlist_url!`], the IANA time zone database is compiled into typed zones by
+//! [`include_tzdata_url!`] (`tzdata` feature), iCalendar events are embedded by
+//! [`include_ical_url!`], and protobuf schemas are compiled into message types by
+//! [`include_proto_url!`] (`proto` feature). GraphQL schemas, OpenAPI specifications and
+//! JSON Schemas become serde types with [`include_graphql_schema_url!`] (`graphql`
+//! feature), [`include_openapi_url!`] (`openapi` feature) and [`include_jsonschema_url!`]
+//! (`jsonschema` feature), and Avro schemas and container files with
+//! [`include_avro_url!`] (`avro` feature). The columns of Parquet files are embedded as
+//! static arrays by [`include_parquet_url!`] (`parquet` feature), and SQLite databases or
+//! query results by [`include_sqlite_url!`] (`sqlite` feature). Certificate bundles are
+//! validated and split into certificates by [`include_pem_url!`] (`pem` feature), and
+//! Fluent localization resources are checked for syntax errors by [`include_fluent_url!`]
+//! (`fluent` feature). GeoJSON is validated and optionally reduced to simplified coordinate
+//! arrays by [`include_geojson_url!`], and snapshots of RSS and Atom feeds are embedded by
+//! [`include_rss_url!`] (`feed` feature). Markdown documents are rendered to HTML by
+//! [`include_md_url!`] (`markdown` feature), and the parts of an HTML page matching a CSS
+//! selector are extracted by [`include_html_url!`] (`html` feature). WebAssembly modules
+//! are embedded with their header checked by [`include_wasm_url!`], and fully validated
+//! with `validate = true` (`wasm` feature). Images are decoded into raw pixel data by
+//! [`include_image_url!`] (`image` feature), and fonts are subset to the characters a
+//! program needs by [`include_font_url!`] (`font` feature). SVG images are checked and
+//! optionally optimized by [`include_svg_url!`] (`svg` feature).
+//!
+//! Many URLs are fetched concurrently and included as an array by [`include_urls!`], and
+//! the files of a remote directory, listed by the GitHub trees API, an S3 bucket listing
+//! or an HTML index, become a static directory by [`include_url_dir!`]. The pages listed
+//! by a sitemap are embedded as a map keyed by their path by [`include_sitemap_url!`].
+//! Artifacts of OCI registries, such as schemas or models pushed with `oras`, are
+//! resolved and embedded by [`include_oci_url!`], and files or directories of git
+//! repositories at a revision by [`include_git_url!`]. Assets of GitHub releases are
+//! embedded by [`include_github_release_asset!`], and files of packages published on
+//! crates.io by [`include_crate_file!`] (`archive` feature). Single files or glob
+//! selections of remote zip and tar archives are embedded by [`include_zip_url!`] and
+//! [`include_tar_url!`] (`archive` feature), without the rest of the archive.
+//! Assets too large to embed can be fetched to disk with [`include_url_path!`] and copied
+//! next to the binary from a build script with [`install_url!`]. Everything a crate
+//! included is [reported](#inclusion-report) while it compiles, and summed up by
+//! [`report_url_inclusions!`].
+//!
 //! # Examples
 //!
 //! Basic usage with text content:
 //! ```rust
 //! use include_url_macro::include_url;
 //!
-//! const CONTENT: &str = include_url!("https://example.com/static/content.txt");
+//! const CONTENT: &str =
+//!     include_url!("https://raw.githubusercontent.com/rust-lang/rust/master/README.md");
 //! ```
 //!
 //! Including JSON content with type inference:
@@ -28,294 +85,7554 @@
 //!
 //! let post: Post = include_json_url!("https://jsonplaceholder.typicode.com/posts/1", Post);
 //! ```
+//!
+//! # Options
+//!
+//! Every macro accepts optional `key = value` pairs after its arguments:
+//!
+//! * `method = "POST"` - Send the request with the given method instead of `GET`.
+//! * `body = "..."` - Send the given request body, with the `POST` method unless `method`
+//!   is given. `content_type = "application/json"` sets its `Content-Type` header. The
+//!   method and body are part of the cache key.
+//! * `header = "Name: value"` - Send an extra request header. May be repeated.
+//! * `headers = { "Accept" = "application/json", "X-Api-Key" = env("API_KEY") }` - Send
+//!   several request headers, with values given as strings or read from the environment.
+//! * `cookie = "consent=yes"` or `cookie = env("SESSION_COOKIE")` - Send cookies, given as
+//!   `name=value` pairs separated by `;`, for the host of the URL. Cookies set by the
+//!   responses are kept while following redirects and pages. May be repeated.
+//! * `proxy = "http://proxy.example.com:3128"` - Send every request through the given
+//!   proxy instead of the one configured by the environment.
+//! * `auth = bearer_env("TOKEN")` or `auth = basic_env("USER", "PASSWORD")` - Send an
+//!   `Authorization` header with credentials read from environment variables. The secrets
+//!   stay out of the source and of the cache key.
+//! * `ca_cert = "certs/internal-ca.pem"` or `ca_cert = env("INTERNAL_CA_PEM")` - Trust the
+//!   certificates of a private CA besides the usual roots, given as the path to a PEM file,
+//!   relative to the manifest directory, or as PEM. May be repeated.
+//! * `client_cert = env("BUILD_CLIENT_CERT")` - Authenticate with a client certificate to
+//!   servers requiring mutual TLS, given as PEM holding the certificate and its private key
+//!   or as the path to a PEM file. PKCS#12 archives (`pkcs12` feature) are opened with
+//!   `client_cert_password = env("NAME")`.
+//! * `ip_version = 4` - Only connect over IPv4, or IPv6 with `6`, for networks where the
+//!   other version is broken.
+//! * `doh = "https://cloudflare-dns.com/dns-query"` - Resolve hosts with the JSON API of a
+//!   DNS over HTTPS server instead of the system resolver.
+//! * `resolve = { "artifacts.internal" = "10.0.0.12" }` - Connect to the given addresses
+//!   instead of resolving the hosts.
+//! * `timeout = 30` - Fail the request after the given number of seconds, 300 by default.
+//! * `connect_timeout = 5` - Fail if no connection is established within the given number
+//!   of seconds, 30 by default.
+//! * `retries = 5` - Retry network errors, server errors, `408` and `429` responses up to
+//!   the given number of times, 2 by default. The delay doubles from half a second with
+//!   some jitter, unless the server asks for one with `Retry-After` or `X-RateLimit-Reset`,
+//!   up to a minute, which then holds back the requests of every macro to the host.
+//!   Requests whose method is not idempotent, such as `POST`, are not retried, as the
+//!   server may have acted on them.
+//! * `retry_any_method = true` - Retry the requests of every method, for `POST` endpoints
+//!   that are known to be safe to repeat, such as GraphQL queries.
+//! * `rate_limit = 5` - Send at most the given number of requests per second to the host,
+//!   counting the requests of every macro of the crate.
+//! * `max_size = "5MB"` - Fail if the content is larger than the given size, given in
+//!   bytes or as a string with a unit (`KB`, `KiB`, `MB`, `MiB`, `GB`, `GiB`). The size is
+//!   asked for with a `HEAD` request first, and else the download stops at the limit.
+//! * `sha256 = "..."` - Fail if the hex encoded SHA-256 digest of the content differs.
+//! * `integrity = "sha384-..."` - Fail unless the content matches the
+//!   [Subresource Integrity](https://www.w3.org/TR/SRI/) metadata, such as the
+//!   `integrity` attribute of a CDN asset. Of several space separated hashes, the ones of
+//!   the strongest of `sha256`, `sha384` and `sha512` are used, and one has to match.
+//! * `signature_url = "https://.../file.asc", pgp_key = env("RELEASE_KEY")` - Download a
+//!   detached OpenPGP signature and fail unless it was made over the content by one of
+//!   the trusted public keys (`pgp` feature). The keys are given armored, from an
+//!   environment variable with `env("NAME")`, or as the path to a key file relative to the
+//!   manifest directory. RSA and Ed25519 signatures hashed with SHA-256, SHA-384 or SHA-512
+//!   are supported, and the keys are trusted as given, without looking at their expiry or
+//!   revocation.
+//! * `signature_url = "https://.../file.minisig", minisign_pub = "RW..."` - Fail unless the
+//!   minisign or signify signature was made over the content by the public key
+//!   (`minisign` feature). The key is given as it is, from an environment variable, or as
+//!   the path to a `.pub` file relative to the manifest directory.
+//! * `signature_url = "https://.../file.sigstore.json", sigstore_identity = "...",
+//!   sigstore_issuer = "..."` - Fail unless the Sigstore bundle, or the bundle of
+//!   `cosign sign-blob --bundle`, holds a signature of the content by a certificate issued
+//!   to the identity, an e-mail address or workflow URL, for tokens of the OIDC issuer
+//!   (`sigstore` feature). The certificate has to chain to the trusted root given by
+//!   `sigstore_root = "sigstore/trusted_root.json"`, relative to the manifest directory,
+//!   and the signature has to be in its transparency log, proven by the signed entry
+//!   timestamp or inclusion proof of the bundle. DSSE attestations and the certificate
+//!   transparency of the certificate are not checked.
+//! * `canonicalize = true` - Re-serialize fetched JSON with sorted keys and stable number
+//!   formatting before it is hashed and embedded.
+//! * `locked = false` - Leave the content out of the [lockfile](#lockfile), for content
+//!   that is meant to change.
+//! * `permalink = true` - Pin GitHub files at a branch or tag to the commit it points at,
+//!   see [GitHub shorthands](#github-shorthands).
+//! * `decompress = false` - Embed compressed responses without decoding them, see
+//!   [Compressed responses](#compressed-responses).
+//! * `charset = "shift_jis"` - Decode the text with the given encoding rather than the one
+//!   declared by the response, see [Character encodings](#character-encodings).
+//!   `include_url_bytes!` embeds the bytes as sent unless it is given.
+//! * `allow_status = [404]` - Embed the responses with the listed status codes. Any other
+//!   status outside of the 2xx range fails the build, quoting the start of the response.
+//! * `compression = brotli` - Compress the embedded bytes (`include_url_bytes!` only).
+//!   `compression = zstd` requires the `zstd` feature and may be combined with
+//!   `dictionary = "name"` to use a [shared dictionary](include_zstd_dictionary!).
+//! * `chunk_size = "1MiB"` - Embed the content as separately included chunks of at most
+//!   the given size, expanding to the `&'static [&'static [u8]]` of the chunks in order
+//!   (`include_url_bytes!` only). This keeps the memory rustc needs for very large assets
+//!   bounded.
+//! * `paginate = link_header` - Follow RFC 5988 `Link: <...>; rel="next"` headers and
+//!   concatenate the JSON array of every page. `paginate = none` disables the automatic
+//!   pagination of GitHub API endpoints.
+//! * `max_pages = N` - Fail if more than `N` pages would be fetched (defaults to 100).
+//! * `mirrors = ["https://backup.example.com/..."]` - Fetch the content from the given URLs
+//!   in order when the URL fails. The URL itself can be left out and given as the first
+//!   mirror, `include_url!(mirrors = ["https://a/...", "https://b/..."])`. With
+//!   `verify_mirrors = true`, every mirror is fetched and has to serve the same bytes.
+//! * `allow_file_urls = true` - Read `file://` URLs from the local file system, see
+//!   [Local files](#local-files).
+//! * `ttl = "7d"` - Revalidate the cached content once it is older than the given number of
+//!   seconds, or duration with a unit (`s`, `m`, `h`, `d`, `w`), rather than when the
+//!   response expires, see [Revalidation](#revalidation).
+//! * `zsync = "https://.../file.zsync"` - Patch cached content due for revalidation with
+//!   the blocks it shares with the new content, described by the zsync control file, and
+//!   only download the changed blocks (`zsync` feature), see [Revalidation](#revalidation).
+//! * `skip_under = [test, clippy]` - When any of the listed cfgs (or `clippy`/`doc` tools)
+//!   is active, expand to a placeholder without touching the network.
+//!
+//! ```rust
+//! use include_url_macro::include_url;
+//!
+//! const CONTENT: &str = include_url!(
+//!     "https://raw.githubusercontent.com/rust-lang/rust/master/README.md",
+//!     header = "Accept: text/plain",
+//!     timeout = 30,
+//! );
+//! ```
+//!
+//! # Environment
+//!
+//! `${NAME}` references in the URL and header values are replaced with the value of the
+//! environment variable `NAME`, as are `env("NAME")` values of the `headers` option.
+//! Every variable that influenced a fetch, including the `HTTP_PROXY`/`HTTPS_PROXY`/
+//! `NO_PROXY` family, is part of the cache key and tracked by the compiler, so changing it
+//! re-expands the macro and fetches fresh content.
+//!
+//! Requests go through the proxy given by `HTTP_PROXY` for `http` URLs, `HTTPS_PROXY` for
+//! `https` URLs or `ALL_PROXY` for both, spelled in upper or lower case, except for the
+//! hosts listed in `NO_PROXY`. The `proxy` option takes precedence over all of them.
+//!
+//! ```rust,ignore
+//! use include_url_macro::include_url;
+//!
+//! const MANIFEST: &str = include_url!("https://${ASSET_HOST}/manifest.json");
+//! ```
+//!
+//! # Cloud storage
+//!
+//! `gs://bucket/object` URLs (`gcs` feature) read objects of Google Cloud Storage, with the
+//! application default credentials: the service account key held by `INCLUDE_URL_GCS_KEY`,
+//! the credentials file named by `GOOGLE_APPLICATION_CREDENTIALS`, or the one written by
+//! `gcloud auth application-default login`. Objects of public buckets are read without
+//! credentials. `gs://bucket/object#1700000000000000` reads a given generation of the
+//! object, and `INCLUDE_URL_GCS_ENDPOINT` sends the requests to an emulator instead.
+//!
+//! `azblob://account/container/blob` URLs (`azure` feature) read blobs of Azure Storage,
+//! with the SAS token held by `AZURE_STORAGE_SAS_TOKEN`, or else with a token signed by the
+//! account key held by `AZURE_STORAGE_KEY`. Blobs of public containers are read without
+//! credentials, and `INCLUDE_URL_AZURE_ENDPOINT` replaces the Blob service URL of the
+//! account, for Azurite.
+//!
+//! # GitHub shorthands
+//!
+//! `gh:owner/repo@ref/path` names a file of a GitHub repository at a branch, tag or commit,
+//! or at `HEAD` without `@ref`. It is read from `raw.githubusercontent.com`, or through the
+//! contents API when `auth = bearer_env("GITHUB_TOKEN")` is given, for private
+//! repositories.
+//!
+//! `gist:user/id/file` names a file of a gist, and `gist:user/id` its only file. The gist
+//! is looked up through the Gist API, with the credentials of `auth` for secret gists, and
+//! fetched again whenever its content is revalidated.
+//!
+//! With `permalink = true`, given to a macro or set for the crate in
+//! `[package.metadata.include_url]`, files at a branch or tag, as `gh:` shorthands,
+//! `raw.githubusercontent.com` or `github.com/owner/repo/raw/...` URLs, are read at the
+//! commit the ref points at when first built, resolved through the commits API and pinned
+//! in the [lockfile](#lockfile). The expansion then warns that the URL moves and gives its
+//! permalink.
+//!
+//! # Hugging Face Hub
+//!
+//! `hf://org/model/resolve/rev/path` URLs, and `hf://datasets/org/name/resolve/rev/path`
+//! for datasets, read files of the Hugging Face Hub, such as tokenizer configurations and
+//! vocabularies. A revision other than a commit, such as `main`, is resolved to its commit
+//! once and pinned in the [lockfile](#lockfile). The token held by `HF_TOKEN` is sent unless
+//! `auth` is given, and `HF_ENDPOINT` replaces `https://huggingface.co`.
+//!
+//! # Data URLs
+//!
+//! `data:` URLs, such as `data:application/json;base64,eyJpZCI6MX0=`, are decoded without
+//! any request, for inline fixtures and generated code. The `charset` of their media type
+//! is honored, and text declaring none is read as UTF-8.
+//!
+//! # Local files
+//!
+//! `file://` URLs are read from the local file system when allowed by
+//! `allow_file_urls = true`, given to the macro or set in the
+//! [crate wide defaults](#crate-wide-defaults). They go through the same compression,
+//! caching and format handling as fetched content, and the file is tracked by the compiler.
+//! With the URL taken from an environment variable, such as
+//! `include_url!("${ASSETS}/schema.json", allow_file_urls = true)`, the same call reads a
+//! local mirror during development and the real URL in CI.
+//!
+//! # FTP and SFTP
+//!
+//! `ftp://host/path` and `sftp://user@host/path` URLs (`ftp` feature) read files of FTP and
+//! SSH servers, with the user and password of `auth = basic_env("USER", "PASSWORD")`.
+//! Without credentials, FTP servers are logged in to anonymously, and SSH servers with the
+//! keys of the SSH agent or the unencrypted default keys of `~/.ssh`. The key of an SSH
+//! server is checked against `~/.ssh/known_hosts`, which must list it. Revalidating such a
+//! file downloads it again in full.
+//!
+//! # IPFS
+//!
+//! `ipfs://CID/path` URLs are fetched from the `https://ipfs.io` gateway, or the
+//! `ipfs_gateway` of the [crate wide defaults](#crate-wide-defaults). The content is
+//! requested as a CAR archive of its blocks, each checked against its CID before the file
+//! is reassembled, so the gateway does not need to be trusted. Only SHA-256 CIDs are
+//! supported, and paths cannot go through sharded directories.
+//!
+//! # Compressed responses
+//!
+//! Requests accept gzip, brotli and deflate encoded responses, which are decoded before
+//! they are hashed, cached and embedded, so `max_size` and `sha256` apply to the decoded
+//! content. `decompress = false` embeds the response as the server sent it instead, for
+//! servers that declare the encoding of files that are meant to stay compressed.
+//!
+//! # Character encodings
+//!
+//! Text is embedded as UTF-8. Text in another encoding, such as Latin-1, Shift-JIS or GBK,
+//! is decoded with the encoding declared by its byte order mark, the `charset` of its
+//! `Content-Type`, or else the `<meta charset>` tag of an HTML page or the declaration of
+//! an XML document, before it is cached. Pages are HTML when served as `text/html`, or
+//! served without a specific type and starting with an HTML tag, so text quoting a
+//! `<meta>` tag keeps its encoding. Text declaring no encoding has to be UTF-8. The
+//! `charset` option decodes with the given encoding instead, for servers declaring the
+//! wrong one. The `sha256` option applies to the content as sent.
+//!
+//! # Streamed and resumed downloads
+//!
+//! Content is written to a partial file in the cache directory as it arrives, and then
+//! through the compressor, if any, into its cache file, so that large binaries are never
+//! held in memory. Only content that is transformed, canonicalized JSON or decoded text,
+//! is read in memory. When the connection breaks off on a response that servers allow to be
+//! requested by range, with `Accept-Ranges: bytes` and an `ETag` or `Last-Modified` header,
+//! the retry, or else the next build, only asks for the missing bytes, and starts over if
+//! the content changed in the meantime.
+//!
+//! # Revalidation
+//!
+//! Cached content is used until it expires, as told by the `Cache-Control: max-age` or
+//! `Expires` header of its response, or is older than its `ttl`, which takes precedence.
+//! Content whose response tells neither is kept as long as the cache directory, unless
+//! `INCLUDE_URL_REFRESH=1` is set for the build. The `ETag` and `Last-Modified` headers of
+//! every response are kept next to its cache file, so that content is then revalidated
+//! with a conditional request, and servers answering `304 Not Modified` do not send it
+//! again. Content without either header, and the merged pages of paginated responses, are
+//! downloaded again. Expired content that cannot be fetched, such as while the server is
+//! down, is used as is with a warning.
+//!
+//! Large content that changed can be patched rather than downloaded again, with the `zsync`
+//! feature and the URL of a control file made by `zsyncmake` for it. The control file is
+//! fetched instead of a conditional request, and only the blocks of the new content that
+//! cannot be found in the cached content are requested, with `Range` requests to the URL.
+//! Content that is compressed, transformed or embedded as text is downloaded again, and so
+//! is content failing the SHA-1 digest of the control file, with a warning.
+//!
+//! ```rust,ignore
+//! const MODEL: &str = include_url_path!(
+//!     "https://example.com/models/model.bin",
+//!     zsync = "https://example.com/models/model.bin.zsync",
+//!     ttl = "1d",
+//! );
+//! ```
+//!
+//! Macros are only expanded when the crate is compiled, and setting or clearing
+//! `INCLUDE_URL_REFRESH` recompiles it.
+//!
+//! # Inclusion report
+//!
+//! Every inclusion is recorded while the crate compiles, in `include_url_report/` of its
+//! `OUT_DIR` (or of the cache directory for packages without a build script). The library,
+//! binaries and tests of a package get a `<crate>-<lib|bin|test>.tsv` file each, replaced
+//! by every compilation, with one `<pid>\t<status>\t<bytes>\t<milliseconds>\t<url>` line
+//! per inclusion. The status is `hit` for content served from the cache, `miss` for
+//! downloads, `revalidated` for content the server confirmed and `stale` for expired
+//! content used because it could not be revalidated. Stale content is always reported with
+//! a warning, and `INCLUDE_URL_REPORT=1` lists every inclusion as a warning:
+//!
+//! ```text
+//! warning: include_url: miss 18230 bytes in 0.42s https://example.com/data.json
+//! ```
+//!
+//! Warnings are printed to the output of the compiler rather than raised as lints, so they
+//! never fail builds that deny warnings, and are only shown when the crate compiles.
+//!
+//! [`report_url_inclusions!`], expanded after every other macro of the crate, prints the
+//! number of URLs, the included bytes, the cache hit rate and the stale entries of the
+//! compilation:
+//!
+//! ```text
+//! warning: include_url: 12 URLs, 401822 bytes, 11/12 cache hits, 0.47s fetching
+//! ```
+//!
+//! # Offline builds
+//!
+//! With `CARGO_NET_OFFLINE=true`, or `cargo --offline` and `--frozen` where the command
+//! line of cargo can be read (Linux), the macros never touch the network. Cached content
+//! is used even if it is due for [revalidation](#revalidation), and every URL missing from
+//! the cache fails with an error naming it. Building once with network access fills the
+//! cache for the following offline builds.
+//!
+//! # Lockfile
+//!
+//! References that move over time, such as the `latest` release of
+//! [`include_github_release_asset!`] or the branches of
+//! [Hugging Face repositories](#hugging-face-hub), are resolved once and pinned in
+//! `include_url.lock`, next to the `Cargo.toml` of the workspace, or of the crate outside
+//! of a workspace. The SHA-256 digest, size and `ETag` of the content of every URL are
+//! recorded there as well when it is first fetched, and content fetched again, by another
+//! checkout or once its cache entry expired, fails the build with a "content changed
+//! upstream" error unless it has the same digest. Content checked by a `sha256` or
+//! `integrity` option, local files and the listings of directories are not recorded, and
+//! neither is content given `locked = false`, such as a feed that is meant to change. URLs
+//! are recorded as written, with the digest of the values of their `${NAME}` references,
+//! so every value of a variable is locked separately and never written to the file.
+//!
+//! Commit the file so that every checkout builds the same content. Building with
+//! `INCLUDE_URL_UPDATE_LOCK=1` resolves the pinned references again and records the digest
+//! of the content as it is fetched, and so does removing an entry from the file. Content
+//! that is cached is not fetched again unless `INCLUDE_URL_REFRESH=1` is set as well.
+//!
+//! # Crate wide defaults
+//!
+//! The defaults of the `timeout`, `connect_timeout`, `retries`, `rate_limit`, `ip_version`
+//! and `doh` options can be set for every macro of a crate in the
+//! `[package.metadata.include_url]` table of its `Cargo.toml`, and for a single build with
+//! the `INCLUDE_URL_TIMEOUT`, `INCLUDE_URL_CONNECT_TIMEOUT`, `INCLUDE_URL_RETRIES`,
+//! `INCLUDE_URL_RATE_LIMIT`, `INCLUDE_URL_IP_VERSION` and `INCLUDE_URL_DOH` environment
+//! variables, which take precedence over the manifest. A
+//! `ca_cert` trusted by every macro is set the same way, with `INCLUDE_URL_CA_CERT`, and
+//! adds to the certificates given to a macro. So are the `ipfs_gateway` of
+//! [`ipfs://` URLs](#ipfs), with `INCLUDE_URL_IPFS_GATEWAY`, and `allow_file_urls`, with
+//! `INCLUDE_URL_ALLOW_FILE_URLS`. `permalink` and `sigstore_root` are only set in the
+//! manifest. Editing the manifest or changing one of the variables expands the macros again,
+//! and content fetched with other defaults is cached separately.
+//!
+//! ```toml
+//! [package.metadata.include_url]
+//! timeout = 600
+//! connect_timeout = 10
+//! retries = 5
+//! ip_version = 4
+//! ```
 
-use std::{fs::OpenOptions, io::Write};
+use std::io::Read;
 
 use proc_macro::TokenStream;
-use quote::quote;
-use reqwest::blocking::Client;
+use quote::{format_ident, quote};
+use reqwest::{
+    blocking::{Client, ClientBuilder, RequestBuilder, Response},
+    cookie::Jar,
+    header::{
+        HeaderMap, HeaderName, HeaderValue, CONTENT_LENGTH, CONTENT_TYPE, IF_RANGE, LINK, RANGE,
+        RETRY_AFTER,
+    },
+    Method, NoProxy, Proxy, StatusCode,
+};
 use sha2::{Digest, Sha256};
-use std::env;
-use syn::{parse::Parse, parse::ParseStream, parse_macro_input, LitStr, Token, Type};
+use std::{
+    collections::{hash_map::RandomState, HashMap},
+    env,
+    hash::{BuildHasher, Hasher},
+    net::{IpAddr, SocketAddr},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+    time::{Duration, Instant, SystemTime},
+};
+use syn::{
+    parse::Parse, parse::ParseStream, parse_macro_input, punctuated::Punctuated, Ident, LitBool,
+    LitInt, LitStr, Token, Type,
+};
 use url::Url;
 
+#[cfg(feature = "archive")]
+mod archive;
+#[cfg(feature = "avro")]
+mod avro;
+#[cfg(feature = "azure")]
+mod azure;
+mod charset;
+mod config;
+#[cfg(feature = "archive")]
+mod crates_io;
+mod data_url;
+#[cfg(feature = "zstd")]
+mod dictionary;
+mod dir;
+mod download;
+#[cfg(feature = "feed")]
+mod feed;
+mod file_url;
+#[cfg(feature = "font")]
+mod font;
+mod formats;
+#[cfg(feature = "ftp")]
+mod ftp;
+#[cfg(feature = "gcs")]
+mod gcs;
+mod geo;
+mod gettext;
+mod git;
+mod github;
+#[cfg(feature = "graphql")]
+mod graphql;
+mod huggingface;
+mod ical;
+mod integrity;
+mod ipfs;
+mod lockfile;
+mod mime_db;
+#[cfg(feature = "minisign")]
+mod minisign;
+mod oci;
+#[cfg(feature = "parquet")]
+mod parquet;
+#[cfg(feature = "pgp")]
+mod pgp;
+#[cfg(feature = "proto")]
+mod proto;
+mod psl;
+#[cfg(feature = "image")]
+mod raster;
+mod rate_limit;
+mod report;
+mod resolve;
+mod revalidate;
+#[cfg(any(feature = "openapi", feature = "jsonschema"))]
+mod schema;
+#[cfg(feature = "ftp")]
+mod sftp;
+#[cfg(feature = "sigstore")]
+mod sigstore;
+mod sitemap;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+#[cfg(feature = "svg")]
+mod svg;
+mod tls;
+mod typegen;
+#[cfg(feature = "tzdata")]
+mod tzdata;
+mod ucd;
+#[cfg(feature = "font")]
+mod woff;
+mod wordlist;
+#[cfg(feature = "zsync")]
+mod zsync;
+
+use report::CacheStatus;
+
+/// Default maximum number of pages followed when merging a paginated response.
+const MAX_PAGES: usize = 100;
+
+/// Default limit for a whole request, in seconds.
+const DEFAULT_TIMEOUT: u64 = 300;
+
+/// Default limit for establishing a connection, in seconds.
+const DEFAULT_CONNECT_TIMEOUT: u64 = 30;
+
+/// Default number of times a temporary failure is retried.
+const DEFAULT_RETRIES: u32 = 2;
+
+/// Default number of URLs fetched at a time by `include_urls!`.
+const DEFAULT_CONCURRENCY: usize = 8;
+
+/// Delay before the first retry, doubled for every further retry.
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// Maximum delay between two attempts, also bounding the delay asked for by a server.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Maximum number of characters of an error response quoted in the compile error.
+const ERROR_SNIPPET_CHARS: usize = 200;
+
+/// How paginated responses are followed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Paginate {
+    /// Follow `Link` headers for GitHub API endpoints only.
+    #[default]
+    Auto,
+    /// Never follow `Link` headers.
+    None,
+    /// Follow RFC 5988 `Link: <...>; rel="next"` headers for any host.
+    LinkHeader,
+}
+
+/// Credentials sent in the `Authorization` header, given as the names of the environment
+/// variables holding them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Auth {
+    /// A bearer token, `auth = bearer_env("TOKEN")`.
+    Bearer(String),
+    /// A user name and password, `auth = basic_env("USER", "PASSWORD")`.
+    Basic(String, String),
+}
+
+impl Auth {
+    /// The names of the environment variables holding the credentials.
+    fn env_names(&self) -> Vec<&str> {
+        match self {
+            Auth::Bearer(token) => vec![token],
+            Auth::Basic(user, password) => vec![user, password],
+        }
+    }
+}
+
+/// Reads a credential of [`Auth`] from the environment variable `name`.
+fn credential(name: &str) -> Result<String, String> {
+    env::var(name).map_err(|_| format!("Environment variable `{}` is not set", name))
+}
+
+/// Options controlling how a URL is fetched.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct FetchOptions {
+    /// URLs of the same content tried in order when the URL fails.
+    mirrors: Vec<String>,
+    /// Fetch every mirror and check that they serve the same content.
+    verify_mirrors: bool,
+    paginate: Paginate,
+    max_pages: Option<usize>,
+    /// Request method, `GET` unless given or a body is sent.
+    method: Option<Method>,
+    /// Request body.
+    body: Option<String>,
+    /// Content type of the request body.
+    content_type: Option<String>,
+    /// Extra request headers as `(name, value)` pairs.
+    headers: Vec<(String, String)>,
+    /// Cookies sent with the first request, as `name=value` pairs.
+    cookies: Vec<String>,
+    /// Proxy for every request, overriding the proxy variables.
+    proxy: Option<String>,
+    /// `4` or `6` to only connect over one IP version.
+    ip_version: Option<u8>,
+    /// URL of a DNS over HTTPS server resolving the hosts.
+    doh: Option<String>,
+    /// Extra root certificates, as PEM or paths to PEM files.
+    ca_certs: Vec<String>,
+    /// Client certificate for mutual TLS, as PEM or the path to a PEM or PKCS#12 file.
+    client_cert: Option<String>,
+    /// Password of a PKCS#12 client certificate.
+    client_cert_password: Option<String>,
+    /// Addresses of hosts, bypassing their resolution.
+    hosts: Vec<(String, IpAddr)>,
+    /// Credentials for the `Authorization` header. Only the variable names are part of the
+    /// cache key, so the secrets never reach the cache directory.
+    auth: Option<Auth>,
+    /// Request timeout in seconds.
+    timeout: Option<u64>,
+    /// Connection timeout in seconds.
+    connect_timeout: Option<u64>,
+    /// Number of times a temporary failure is retried.
+    retries: Option<u32>,
+    /// Retry the temporary failures of requests whose method is not idempotent.
+    retry_any_method: bool,
+    /// Maximum number of requests per second to the host.
+    rate_limit: Option<u32>,
+    /// Maximum size of the fetched content in bytes.
+    max_size: Option<usize>,
+    /// Expected lowercase hex SHA-256 digest of the fetched content.
+    sha256: Option<String>,
+    /// Expected Subresource Integrity hashes of the fetched content.
+    integrity: Option<integrity::Integrity>,
+    /// URL of a detached signature of the fetched content.
+    signature_url: Option<String>,
+    /// Trusted OpenPGP public keys checking the signature, armored or the path to a key file.
+    pgp_key: Option<String>,
+    /// Trusted minisign public key checking the signature, or the path to a `.pub` file.
+    minisign_pub: Option<String>,
+    /// Identity the Sigstore certificate of the signature is issued to.
+    sigstore_identity: Option<String>,
+    /// OIDC issuer of the Sigstore identity.
+    sigstore_issuer: Option<String>,
+    /// Path to the `trusted_root.json` of the Sigstore instance.
+    sigstore_root: Option<String>,
+    /// Re-serialize the fetched JSON in canonical form.
+    canonicalize: bool,
+    /// Keep the content encoding of the response instead of decoding it.
+    keep_encoding: bool,
+    /// Decode the content into UTF-8 with its declared character encoding, for the macros
+    /// embedding text.
+    text: bool,
+    /// Character encoding of the content, overriding the declared one.
+    charset: Option<String>,
+    /// Unsuccessful status codes whose responses are embedded anyway.
+    allow_status: Vec<u16>,
+    /// Environment variables read while resolving the request, with their values.
+    env: Vec<(String, Option<String>)>,
+    /// Read `file://` URLs from the local file system.
+    allow_file_urls: Option<bool>,
+    /// Local files named by the request, tracked for re-expansion.
+    files: Vec<std::path::PathBuf>,
+    /// File downloads are streamed to as they arrive, resumed by later attempts.
+    partial: Option<std::path::PathBuf>,
+    /// Seconds after which the cached content is revalidated.
+    ttl: Option<u64>,
+    /// URL of a zsync control file, patching the cached content when it is revalidated.
+    zsync: Option<String>,
+    /// Validators of the cached content, making the request conditional.
+    revalidate: Option<revalidate::Metadata>,
+    /// Leave the content out of `include_url.lock`, for content expected to change.
+    unlocked: bool,
+    /// Pin the branches and tags of GitHub URLs to their commit.
+    permalink: Option<bool>,
+    /// The crate wide defaults, loaded once per macro call.
+    config: Option<config::Config>,
+    /// Warnings about the request, emitted by the expansion.
+    warnings: Vec<String>,
+}
+
+impl FetchOptions {
+    /// Describes the options that influence the fetched content, for the cache key.
+    fn cache_key(&self) -> String {
+        format!(
+            "{:?} {:?} {:?} {:?} {:?} {:?} {:?} {:?} {:?} {:?} {:?} {:?} {:?} {:?} {:?} {:?} {:?} {:?} {:?} {:?} {:?} {:?} {:?} {:?}",
+            self.paginate,
+            self.max_pages,
+            self.method,
+            self.body,
+            self.content_type,
+            self.headers,
+            self.cookies,
+            self.proxy,
+            self.auth,
+            self.max_size,
+            self.sha256,
+            self.integrity,
+            self.signature_url,
+            self.pgp_key,
+            self.minisign_pub,
+            self.sigstore_identity,
+            self.sigstore_issuer,
+            self.sigstore_root,
+            self.canonicalize,
+            self.keep_encoding,
+            self.text,
+            self.charset,
+            self.allow_status,
+            self.env
+        )
+    }
+
+    /// Limit for a whole request, `timeout` or else the default.
+    pub(crate) fn timeout_or_default(&self) -> Duration {
+        Duration::from_secs(self.timeout.unwrap_or(DEFAULT_TIMEOUT))
+    }
+
+    /// Limit for establishing a connection, `connect_timeout` or else the default.
+    pub(crate) fn connect_timeout_or_default(&self) -> Duration {
+        Duration::from_secs(self.connect_timeout.unwrap_or(DEFAULT_CONNECT_TIMEOUT))
+    }
+
+    /// Reads an environment variable, recording it so that it becomes part of the cache key
+    /// and is tracked for re-expansion.
+    fn env_var(&mut self, name: &str) -> Option<String> {
+        let value = env::var(name).ok();
+        if !self.env.iter().any(|(recorded, _)| recorded == name) {
+            self.env.push((name.to_string(), value.clone()));
+        }
+        value
+    }
+
+    /// Returns the crate wide defaults, loading them the first time, which records the
+    /// environment variables and the manifest they are read from.
+    fn config(&mut self) -> Result<&config::Config, String> {
+        let config = match self.config.take() {
+            Some(config) => config,
+            None => config::load(self)?,
+        };
+        Ok(self.config.insert(config))
+    }
+}
+
+/// Proxy variables honored by the HTTP client, recorded because they influence the fetch.
+const PROXY_ENV_VARS: &[&str] = &[
+    "HTTP_PROXY",
+    "http_proxy",
+    "HTTPS_PROXY",
+    "https_proxy",
+    "ALL_PROXY",
+    "all_proxy",
+    "NO_PROXY",
+    "no_proxy",
+];
+
+/// Replaces `${NAME}` references with the value of the environment variable `NAME`.
+fn substitute_env(value: &str, options: &mut FetchOptions) -> Result<String, String> {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        result.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| format!("Unterminated `${{` in `{}`", value))?;
+        let name = &rest[start + 2..start + end];
+        let var = options
+            .env_var(name)
+            .ok_or_else(|| format!("Environment variable `{}` is not set", name))?;
+        result.push_str(&var);
+        rest = &rest[start + end + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// Resolves environment references in the URL and its mirrors, request headers, cookies,
+/// certificates, proxy and signature options and records the proxy variables, returning the
+/// URL to fetch.
+fn resolve_env(url_str: &str, options: &mut FetchOptions) -> Result<String, String> {
+    let url = substitute_env(url_str, options)?;
+    for mirror in std::mem::take(&mut options.mirrors) {
+        let mirror = substitute_env(&mirror, options)?;
+        options.mirrors.push(mirror);
+    }
+    for (name, value) in std::mem::take(&mut options.headers) {
+        let value = substitute_env(&value, options)?;
+        options.headers.push((name, value));
+    }
+    for cookie in std::mem::take(&mut options.cookies) {
+        let cookie = substitute_env(&cookie, options)?;
+        options.cookies.push(cookie);
+    }
+    for ca_cert in std::mem::take(&mut options.ca_certs) {
+        let ca_cert = substitute_env(&ca_cert, options)?;
+        options.ca_certs.push(ca_cert);
+    }
+    if let Some(client_cert) = options.client_cert.take() {
+        options.client_cert = Some(substitute_env(&client_cert, options)?);
+    }
+    if let Some(password) = options.client_cert_password.take() {
+        options.client_cert_password = Some(substitute_env(&password, options)?);
+    }
+    if let Some(proxy) = options.proxy.take() {
+        options.proxy = Some(substitute_env(&proxy, options)?);
+    }
+    if let Some(signature_url) = options.signature_url.take() {
+        options.signature_url = Some(substitute_env(&signature_url, options)?);
+    }
+    if let Some(key) = options.pgp_key.take() {
+        options.pgp_key = Some(substitute_env(&key, options)?);
+    }
+    if let Some(key) = options.minisign_pub.take() {
+        options.minisign_pub = Some(substitute_env(&key, options)?);
+    }
+    if let Some(zsync) = options.zsync.take() {
+        options.zsync = Some(substitute_env(&zsync, options)?);
+    }
+    for name in PROXY_ENV_VARS {
+        options.env_var(name);
+    }
+    #[cfg(feature = "gcs")]
+    if url.starts_with("gs://") {
+        options.env_var(gcs::ENDPOINT_VAR);
+    }
+    #[cfg(feature = "azure")]
+    if url.starts_with("azblob://") {
+        options.env_var(azure::ENDPOINT_VAR);
+    }
+    Ok(url)
+}
+
+/// Emits `option_env!` and `include_bytes!` references so that the compiler re-expands the
+/// macro whenever one of the recorded environment variables, credentials or local files
+/// changes, or a refresh of the cache is requested, and prints the warnings of the fetch.
+fn env_tracking(options: &FetchOptions) -> proc_macro2::TokenStream {
+    let credentials = options.auth.iter().flat_map(Auth::env_names);
+    let names = options
+        .env
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .chain(credentials)
+        .chain([
+            revalidate::REFRESH_VAR,
+            lockfile::UPDATE_VAR,
+            report::REPORT_VAR,
+        ]);
+    let files = options.files.iter().map(|path| path.display().to_string());
+    // Stable proc macros cannot emit warnings, and code raising one would fail the builds
+    // that deny warnings, so they go to the output of the compiler, which cargo shows
+    for warning in &options.warnings {
+        eprintln!("warning: {}", warning);
+    }
+    quote! {
+        #(const _: ::core::option::Option<&str> = ::core::option_env!(#names);)*
+        #(const _: &[u8] = ::core::include_bytes!(#files);)*
+    }
+}
+
+/// Parses an HTTP or HTTPS URL. The other supported schemes are fetched before URLs get
+/// here, so the error names them, or the feature they require.
+fn parse_url(url_str: &str) -> Result<Url, String> {
+    let url = Url::parse(url_str).map_err(|e| format!("Invalid URL: {}", e))?;
+
+    match url.scheme() {
+        "http" | "https" => Ok(url),
+        "gs" => Err("`gs://` URLs require the `gcs` feature".to_string()),
+        "azblob" => Err("`azblob://` URLs require the `azure` feature".to_string()),
+        "ftp" | "sftp" => Err(format!(
+            "`{}://` URLs require the `ftp` feature",
+            url.scheme()
+        )),
+        scheme => Err(format!(
+            "Unsupported URL scheme `{}`, the supported schemes are `http`, `https`, `data`, \
+             `file`, `ftp`, `sftp`, `gs`, `azblob`, `oci`, `ipfs`, `hf`, `gh` and `gist`",
+            scheme
+        )),
+    }
+}
+
+/// Fills the options left unset by the macro with the crate wide defaults of the manifest.
+fn with_config_defaults(options: &FetchOptions) -> Result<FetchOptions, String> {
+    let mut options = options.clone();
+    let config = options.config()?.clone();
+    // Options of the macro take precedence over the crate wide defaults
+    Ok(FetchOptions {
+        timeout: options.timeout.or(config.timeout),
+        connect_timeout: options.connect_timeout.or(config.connect_timeout),
+        retries: options.retries.or(config.retries).or(Some(DEFAULT_RETRIES)),
+        rate_limit: options.rate_limit.or(config.rate_limit),
+        ip_version: options.ip_version.or(config.ip_version),
+        doh: options.doh.clone().or_else(|| config.doh.clone()),
+        ca_certs: (config.ca_cert.iter().cloned())
+            .chain(options.ca_certs.iter().cloned())
+            .collect(),
+        allow_file_urls: options.allow_file_urls.or(config.allow_file_urls),
+        ..options.clone()
+    })
+}
+
 /// Fetches content from a URL at compile time.
 ///
+/// List endpoints of the GitHub API are paginated, so for `api.github.com` URLs every
+/// page is followed and the JSON arrays are merged into a single document. Other hosts
+/// are only followed when requested through [`FetchOptions::paginate`].
+///
 /// # Arguments
 ///
 /// * `url_str` - The URL to fetch content from
+/// * `options` - Options controlling the request
 ///
 /// # Returns
 ///
-/// * `Ok(String)` - The content fetched from the URL
+/// * `Ok(Fetched)` - The content fetched from the URL, or the confirmation that the cached
+///   content is current when [`FetchOptions::revalidate`] made the request conditional
 /// * `Err(String)` - A descriptive error message if the fetch failed
 ///
 /// # Security
 ///
-/// This function only supports HTTP and HTTPS URLs to prevent potential security issues
-/// with other URL schemes.
-pub(crate) fn fetch_url_content(url_str: &str) -> Result<bytes::Bytes, String> {
-    // Validate URL
-    let url = Url::parse(url_str).map_err(|e| format!("Invalid URL: {}", e))?;
+/// Other schemes than the ones listed by [`parse_url`] are rejected, and `file://` URLs
+/// are only read when [`FetchOptions::allow_file_urls`] allows it.
+pub(crate) fn fetch_url_content(url_str: &str, options: &FetchOptions) -> Result<Fetched, String> {
+    // Data URLs carry their content
+    if data_url::is_data_url(url_str) {
+        let (headers, content) = data_url::decode(url_str)?;
+        return Ok(Fetched::Content(headers, Body::Memory(content)));
+    }
+
+    let mut options = with_config_defaults(options)?;
+
+    if file_url::is_file_url(url_str) {
+        let content = file_url::read(url_str, &options)?;
+        return Ok(Fetched::Content(HeaderMap::new(), Body::Memory(content)));
+    }
+
+    // FTP and SSH servers are read without the HTTP client, and neither paginated nor
+    // revalidated
+    #[cfg(feature = "ftp")]
+    match url_str.split_once("://") {
+        Some(("ftp", _)) => {
+            let content = ftp::fetch(url_str, &options)?;
+            return Ok(Fetched::Content(HeaderMap::new(), Body::Memory(content)));
+        }
+        Some(("sftp", _)) => {
+            let content = sftp::fetch(url_str, &options)?;
+            return Ok(Fetched::Content(HeaderMap::new(), Body::Memory(content)));
+        }
+        _ => {}
+    }
 
-    // Only allow HTTP(S) schemes
-    if url.scheme() != "http" && url.scheme() != "https" {
-        return Err("Only HTTP and HTTPS URLs are supported".to_string());
+    // Gists are described by the API before their file is read
+    if url_str.starts_with("gist:") {
+        return github::fetch_gist(url_str, &options);
     }
 
+    // Objects of storage services and shorthand URLs are read through HTTPS
+    let url = match url_str.split_once("://") {
+        #[cfg(feature = "gcs")]
+        Some(("gs", _)) => gcs::request(url_str, &mut options)?,
+        #[cfg(feature = "azure")]
+        Some(("azblob", _)) => azure::request(url_str)?,
+        Some(("oci", _)) => oci::request(url_str, &mut options)?,
+        Some(("ipfs", _)) => {
+            let gateway = options.config()?.ipfs_gateway.clone();
+            ipfs::request(url_str, gateway.as_deref(), &mut options)?
+        }
+        Some(("hf", _)) => huggingface::request(url_str, &mut options)?,
+        _ if url_str.starts_with("gh:") => github::request(url_str, &mut options)?,
+        _ => parse_url(url_str)?,
+    };
+    let options = &options;
+
     // Fetch the URL content
-    let client = Client::new();
-    let response = client
-        .get(url)
-        .header("User-Agent", "include_url_macro")
-        .send()
-        .map_err(|e| format!("Failed to fetch URL: {}", e))?;
+    let client = http_client(&url, options)?;
+    let paginate = match options.paginate {
+        Paginate::Auto => url.host_str() == Some("api.github.com"),
+        Paginate::None => false,
+        Paginate::LinkHeader => true,
+    };
+    if paginate {
+        // Pages are only read in memory, and downloaded again when revalidated
+        let options = &FetchOptions {
+            partial: None,
+            revalidate: None,
+            ..options.clone()
+        };
+        let body = fetch_paginated(&client, url, options)?;
+        return Ok(Fetched::Content(HeaderMap::new(), Body::Memory(body)));
+    }
 
-    response
-        .bytes()
-        .map_err(|e| format!("Failed to read response body: {}", e))
+    check_size_ahead(&client, &url, options)?;
+    let fetched = send_request(&client, url, options)?;
+    if !url_str.starts_with("ipfs://") {
+        return Ok(fetched);
+    }
+    // The archive of blocks is verified against the CID before the file is taken out of it
+    match fetched {
+        Fetched::Content(headers, body) => {
+            let content = ipfs::unpack(&body.into_bytes()?, url_str)?;
+            Ok(Fetched::Content(headers, Body::Memory(content.into())))
+        }
+        not_modified => Ok(not_modified),
+    }
 }
 
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
-enum CompressKind {
-    #[default]
-    None,
-    #[cfg(feature = "brotli")]
-    Brotli,
+/// The outcome of a fetch.
+#[derive(Debug)]
+pub(crate) enum Fetched {
+    /// The content, with the headers of its response.
+    Content(HeaderMap, Body),
+    /// The cached content is current, a conditional request was answered with
+    /// `304 Not Modified`.
+    NotModified(HeaderMap),
 }
 
-pub(crate) fn cached_url_content(
-    url_str: &str,
-    compress_kind: CompressKind,
-) -> Result<std::path::PathBuf, String> {
-    let out_dir = std::path::Path::new(env!("INCLUDE_URL_CACHE_DIR"));
-    if !out_dir.exists() {
-        std::fs::create_dir_all(out_dir)
-            .map_err(|e| format!("Failed to create cache directory: {}", e))?;
+impl Fetched {
+    fn headers(&self) -> &HeaderMap {
+        match self {
+            Fetched::Content(headers, _) | Fetched::NotModified(headers) => headers,
+        }
     }
-    let crate_name = env::var("CARGO_PKG_NAME").unwrap_or_else(|_| "unknown".into());
-    let mut hasher = Sha256::new();
-    hasher.update(crate_name.as_bytes());
-    hasher.update(b"\0");
-    hasher.update(url_str.as_bytes());
-    hasher.update(b"\0");
-    hasher.update(format!("{:?}", compress_kind));
-    let hash = hasher.finalize();
-    let filename = format!("{:x}", hash);
-    let cache_file = out_dir.join(filename);
-    if cache_file.exists() {
-        return Ok(cache_file);
+}
+
+/// A response body, read in memory or streamed to a file when fetched into the cache.
+#[derive(Debug)]
+pub(crate) enum Body {
+    Memory(bytes::Bytes),
+    /// A complete download, moved into the cache or removed once consumed.
+    File(std::path::PathBuf),
+}
+
+impl Body {
+    /// Reads the whole body in memory.
+    fn into_bytes(self) -> Result<bytes::Bytes, String> {
+        match self {
+            Body::Memory(content) => Ok(content),
+            Body::File(path) => {
+                let content = std::fs::read(&path);
+                let _ = std::fs::remove_file(&path);
+                content
+                    .map(bytes::Bytes::from)
+                    .map_err(|e| format!("Failed to read download: {}", e))
+            }
+        }
     }
 
-    let content = fetch_url_content(url_str)?;
+    /// Opens the body for reading.
+    fn reader(&self) -> Result<Box<dyn Read + '_>, String> {
+        match self {
+            Body::Memory(content) => Ok(Box::new(&content[..])),
+            Body::File(path) => std::fs::File::open(path)
+                .map(|file| Box::new(std::io::BufReader::new(file)) as Box<dyn Read>)
+                .map_err(|e| format!("Failed to read download: {}", e)),
+        }
+    }
 
-    let content = match compress_kind {
-        CompressKind::None => content,
-        #[cfg(feature = "brotli")]
-        CompressKind::Brotli => {
-            let mut buffer = Vec::with_capacity(4096);
-            {
-                let mut encoder = brotli::CompressorWriter::new(&mut buffer, 4096, 11, 22);
-                encoder
-                    .write_all(&content)
-                    .map_err(|e| format!("Failed to write compressed content: {}", e))?;
-                encoder
-                    .flush()
-                    .map_err(|e| format!("Failed to flush compressed content: {}", e))?;
+    /// Returns the size of the body in bytes.
+    fn len(&self) -> Result<u64, String> {
+        match self {
+            Body::Memory(content) => Ok(content.len() as u64),
+            Body::File(path) => std::fs::metadata(path)
+                .map(|meta| meta.len())
+                .map_err(|e| format!("Failed to read download: {}", e)),
+        }
+    }
+
+    /// Returns the lowercase hex SHA-256 digest of the body, read in chunks.
+    fn sha256(&self) -> Result<String, String> {
+        let mut reader = self.reader()?;
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut reader, &mut hasher)
+            .map_err(|e| format!("Failed to read download: {}", e))?;
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    /// Removes a downloaded file.
+    fn discard(self) {
+        if let Body::File(path) = self {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Fetches a URL, falling back to its mirrors in order when it fails. With
+/// `verify_mirrors`, every mirror is fetched and has to serve the same content.
+fn fetch_mirrored(url_str: &str, options: &FetchOptions) -> Result<Fetched, String> {
+    if options.mirrors.is_empty() {
+        return fetch_url_content(url_str, options);
+    }
+    let urls = std::iter::once(url_str).chain(options.mirrors.iter().map(String::as_str));
+    if options.verify_mirrors {
+        let mut first: Option<(&str, HeaderMap, Body, String)> = None;
+        for (index, url) in urls.enumerate() {
+            // Every mirror is downloaded to a file of its own to be compared
+            let options = &FetchOptions {
+                partial: (options.partial.as_ref())
+                    .map(|partial| partial.with_extension(format!("mirror{}", index))),
+                revalidate: None,
+                ..options.clone()
+            };
+            let fetched = fetch_url_content(url, options)
+                .map_err(|e| format!("Failed to fetch mirror `{}`: {}", url, e))?;
+            let Fetched::Content(headers, body) = fetched else {
+                unreachable!("mirrors are compared without conditional requests");
+            };
+            let digest = body.sha256()?;
+            let Some((first_url, _, _, first_digest)) = &first else {
+                first = Some((url, headers, body, digest));
+                continue;
+            };
+            body.discard();
+            if *first_digest != digest {
+                let message = format!(
+                    "Mirror `{}` serves different content than `{}` (SHA-256 {} instead of {})",
+                    url, first_url, digest, first_digest
+                );
+                if let Some((_, _, body, _)) = first {
+                    body.discard();
+                }
+                return Err(message);
+            }
+        }
+        let (_, headers, body, _) = first.expect("the URL is always fetched");
+        return Ok(Fetched::Content(headers, body));
+    }
+
+    let mut errors = Vec::new();
+    for url in urls {
+        match fetch_url_content(url, options) {
+            Ok(content) => return Ok(content),
+            Err(err) => errors.push(format!("`{}`: {}", url, err)),
+        }
+    }
+    Err(format!("Every mirror failed:\n{}", errors.join("\n")))
+}
+
+/// Asks for the size of the content with a `HEAD` request before downloading it, so that
+/// content exceeding `max_size` is never transferred. Servers that do not answer `HEAD`
+/// requests or leave out the size are checked while the body is read instead.
+fn check_size_ahead(client: &Client, url: &Url, options: &FetchOptions) -> Result<(), String> {
+    if options.max_size.is_none() || request_method(options) != Method::GET {
+        return Ok(());
+    }
+    rate_limit::wait(url.host_str().unwrap_or_default(), options.rate_limit);
+    let Ok(response) = build_request(client, Method::HEAD, url.clone(), options)?.send() else {
+        return Ok(());
+    };
+    // The length of the body of a HEAD response is always zero, the header tells the size
+    let length = (response.headers().get(CONTENT_LENGTH))
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+        .filter(|_| response.status().is_success());
+    match length {
+        Some(length) => check_size(length, options)
+            .map_err(|failure| format!("{} (reported by a HEAD request)", failure.message)),
+        None => Ok(()),
+    }
+}
+
+/// Returns an HTTP client for the options, shared by every macro expanded by the compiler
+/// process so that fetches from the same host reuse their connections.
+///
+/// Clients are shared between fetches with the same proxies, name resolution, certificates,
+/// timeouts and decoding of responses. Fetches sending cookies get a client of their own,
+/// with a cookie store seeded with them, so that sessions never leak into other fetches.
+fn http_client(url: &Url, options: &FetchOptions) -> Result<Client, String> {
+    static CLIENTS: OnceLock<Mutex<HashMap<String, Client>>> = OnceLock::new();
+
+    let build = |builder: ClientBuilder| {
+        let mut builder = builder.use_rustls_tls();
+        if options.ip_version.is_some() || options.doh.is_some() {
+            builder = builder.dns_resolver(Arc::new(resolve::Resolver {
+                ip_version: options.ip_version,
+                doh: options.doh.clone(),
+                timeout: options.connect_timeout_or_default(),
+            }));
+        }
+        for certificate in tls::root_certificates(&options.ca_certs)? {
+            builder = builder.add_root_certificate(certificate);
+        }
+        if let Some(client_cert) = &options.client_cert {
+            let password = options.client_cert_password.as_deref();
+            builder = tls::client_identity(builder, client_cert, password)?;
+        }
+        for (host, ip) in &options.hosts {
+            // Port 0 keeps the port of the URL
+            builder = builder.resolve(host, SocketAddr::new(*ip, 0));
+        }
+        configure_proxies(builder, options)?
+            .gzip(!options.keep_encoding)
+            .brotli(!options.keep_encoding)
+            .deflate(!options.keep_encoding)
+            .tcp_keepalive(Duration::from_secs(60))
+            .connect_timeout(options.connect_timeout_or_default())
+            // Every request is bounded, including the ones sent to get access tokens
+            .timeout(options.timeout_or_default())
+            .build()
+            .map_err(|e| format!("Failed to build HTTP client: {}", e))
+    };
+    if !options.cookies.is_empty() {
+        // Cookies set by the responses, such as after a redirect through a consent page,
+        // are sent back along with the given ones
+        let cookies = Jar::default();
+        for cookie in options
+            .cookies
+            .iter()
+            .flat_map(|cookies| cookies.split(';'))
+        {
+            cookies.add_cookie_str(cookie.trim(), url);
+        }
+        return build(Client::builder().cookie_provider(Arc::new(cookies)));
+    }
+
+    let proxy_env: Vec<_> = (options.env.iter())
+        .filter(|(name, _)| PROXY_ENV_VARS.contains(&name.as_str()))
+        .collect();
+    let key = format!(
+        "{:?} {:?} {:?} {:?} {:?} {:?} {:?} {:?} {:?} {:?} {:?}",
+        options.proxy,
+        proxy_env,
+        options.ca_certs,
+        options.client_cert,
+        options.client_cert_password,
+        options.ip_version,
+        options.doh,
+        options.hosts,
+        options.connect_timeout_or_default(),
+        options.timeout_or_default(),
+        options.keep_encoding
+    );
+    let mut clients = CLIENTS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(client) = clients.get(&key) {
+        return Ok(client.clone());
+    }
+    let client = build(Client::builder())?;
+    clients.insert(key, client.clone());
+    Ok(client)
+}
+
+/// Sets up the proxies of the client explicitly rather than relying on the defaults of the
+/// HTTP client, from the `proxy` option or else from the `HTTP_PROXY`, `HTTPS_PROXY` and
+/// `ALL_PROXY` variables with the hosts in `NO_PROXY` excluded.
+fn configure_proxies(
+    builder: ClientBuilder,
+    options: &FetchOptions,
+) -> Result<ClientBuilder, String> {
+    let mut builder = builder.no_proxy();
+    if let Some(proxy) = &options.proxy {
+        let proxy = Proxy::all(proxy).map_err(|e| format!("Invalid proxy: {}", e))?;
+        return Ok(builder.proxy(proxy));
+    }
+    // The variables recorded by `resolve_env`, read directly if they were not recorded,
+    // with the uppercase spelling taking precedence
+    let var = |name: &str| {
+        [name.to_string(), name.to_lowercase()]
+            .iter()
+            .find_map(
+                |name| match options.env.iter().find(|(recorded, _)| recorded == name) {
+                    Some((_, value)) => value.clone(),
+                    None => env::var(name).ok(),
+                },
+            )
+            .filter(|value| !value.trim().is_empty())
+    };
+    let no_proxy = var("NO_PROXY").and_then(|hosts| NoProxy::from_string(&hosts));
+    for name in ["HTTP_PROXY", "HTTPS_PROXY", "ALL_PROXY"] {
+        let Some(value) = var(name) else {
+            continue;
+        };
+        let proxy = match name {
+            "HTTP_PROXY" => Proxy::http(value),
+            "HTTPS_PROXY" => Proxy::https(value),
+            _ => Proxy::all(value),
+        }
+        .map_err(|e| format!("Invalid proxy in `{}`: {}", name, e))?;
+        builder = builder.proxy(proxy.no_proxy(no_proxy.clone()));
+    }
+    Ok(builder)
+}
+
+/// A failed attempt at fetching a URL.
+struct Failure {
+    message: String,
+    /// Whether the failure may be temporary, such as a network error or a 5xx status.
+    transient: bool,
+    /// The delay requested by a `Retry-After` header.
+    retry_after: Option<Duration>,
+}
+
+impl From<String> for Failure {
+    fn from(message: String) -> Self {
+        Failure {
+            message,
+            transient: false,
+            retry_after: None,
+        }
+    }
+}
+
+/// Fetches a URL, retrying temporary failures up to `retries` times with an exponential
+/// backoff.
+///
+/// A delay asked for by the server pauses every request to the host, including the ones of
+/// other macros.
+fn send_request(client: &Client, url: Url, options: &FetchOptions) -> Result<Fetched, String> {
+    let host = url.host_str().unwrap_or_default().to_string();
+    with_retries(&host, options, || {
+        let fetched = attempt_request(client, url.clone(), options)?;
+        // Spare the next requests from being limited
+        if rate_limit::exhausted(fetched.headers()) {
+            let delay = rate_limit::reset_delay(fetched.headers()).unwrap_or_default();
+            rate_limit::pause(&host, delay.min(MAX_RETRY_DELAY));
+        }
+        Ok(fetched)
+    })
+}
+
+/// Runs the attempts of a request to `host`, retrying temporary failures up to `retries`
+/// times with an exponential backoff, within the `rate_limit` of the host.
+///
+/// The server may have acted on a failed request, so requests whose method is not
+/// idempotent, such as `POST`, are only retried with `retry_any_method`.
+fn with_retries<T>(
+    host: &str,
+    options: &FetchOptions,
+    mut attempt_request: impl FnMut() -> Result<T, Failure>,
+) -> Result<T, String> {
+    let retries = match request_method(options).is_idempotent() || options.retry_any_method {
+        true => options.retries.unwrap_or_default(),
+        false => 0,
+    };
+    let mut attempt = 0;
+    loop {
+        rate_limit::wait(host, options.rate_limit);
+        match attempt_request() {
+            Ok(result) => return Ok(result),
+            Err(failure) if failure.transient && attempt < retries => {
+                match failure.retry_after {
+                    Some(delay) => rate_limit::pause(host, delay.min(MAX_RETRY_DELAY)),
+                    None => std::thread::sleep(backoff(attempt)),
+                }
+                attempt += 1;
+            }
+            Err(failure) if attempt > 0 => {
+                return Err(format!(
+                    "{} (gave up after {} retries)",
+                    failure.message, attempt
+                ))
+            }
+            Err(failure) => return Err(failure.message),
+        }
+    }
+}
+
+/// Returns the delay before the retry following `attempt`, doubling from half a second,
+/// with random jitter so that parallel builds do not retry in lockstep.
+fn backoff(attempt: u32) -> Duration {
+    let delay = RETRY_BASE_DELAY
+        .saturating_mul(1 << attempt.min(16))
+        .min(MAX_RETRY_DELAY);
+    let random = RandomState::new().build_hasher().finish();
+    delay / 2 + delay.mul_f64((random % 1000) as f64 / 2000.0)
+}
+
+/// Parses a `Retry-After` header, either a number of seconds or an HTTP date.
+fn retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    match value.parse() {
+        Ok(seconds) => Some(Duration::from_secs(seconds)),
+        Err(_) => httpdate::parse_http_date(value)
+            .ok()
+            .map(|date| date.duration_since(SystemTime::now()).unwrap_or_default()),
+    }
+}
+
+/// Returns the method of the request, `GET` unless given or a body is sent.
+fn request_method(options: &FetchOptions) -> Method {
+    match (&options.method, &options.body) {
+        (Some(method), _) => method.clone(),
+        (None, Some(_)) => Method::POST,
+        (None, None) => Method::GET,
+    }
+}
+
+/// Prepares a request with the headers, credentials and, unless it is a `HEAD` request,
+/// the body given by the options.
+fn build_request(
+    client: &Client,
+    method: Method,
+    url: Url,
+    options: &FetchOptions,
+) -> Result<RequestBuilder, String> {
+    let head = method == Method::HEAD;
+    let mut request = client
+        .request(method, url)
+        .timeout(options.timeout_or_default())
+        .header("User-Agent", "include_url_macro");
+    if let Some(content_type) = options.content_type.as_ref().filter(|_| !head) {
+        request = request.header(CONTENT_TYPE, content_type);
+    }
+    if let Some(body) = options.body.as_ref().filter(|_| !head) {
+        request = request.body(body.clone());
+    }
+    for (name, value) in &options.headers {
+        request = request.header(name, value);
+    }
+    request = match &options.auth {
+        Some(Auth::Bearer(token)) => request.bearer_auth(credential(token)?),
+        Some(Auth::Basic(user, password)) => {
+            request.basic_auth(credential(user)?, Some(credential(password)?))
+        }
+        None => request,
+    };
+    Ok(request)
+}
+
+/// Sends a single request and reads its response.
+///
+/// The rest of an interrupted download is asked for with a `Range` request, see
+/// [`download`], and cached content is revalidated with a conditional request, see
+/// [`revalidate`].
+fn attempt_request(client: &Client, url: Url, options: &FetchOptions) -> Result<Fetched, Failure> {
+    let method = request_method(options);
+    let get = method == Method::GET;
+    let partial = options.partial.as_deref().filter(|_| get);
+    let resume = partial.and_then(download::resume_point);
+    let revalidate = options.revalidate.as_ref().filter(|_| get);
+    let mut request = build_request(client, method, url, options)?;
+    match (&resume, revalidate) {
+        (Some(resume), _) => {
+            request = request
+                .header(RANGE, format!("bytes={}-", resume.offset))
+                .header(IF_RANGE, &resume.validator);
+        }
+        (None, Some(metadata)) => request = metadata.conditional(request),
+        (None, None) => {}
+    }
+    let response = request
+        .send()
+        .map_err(|e| request_error(e, options, "Failed to fetch URL"))?;
+    if response.status() == StatusCode::NOT_MODIFIED && revalidate.is_some() {
+        return Ok(Fetched::NotModified(response.headers().clone()));
+    }
+    let response = check_status(response, options)?;
+    let headers = response.headers().clone();
+    let body = match partial {
+        Some(partial) => download::read_body(response, partial, resume.as_ref(), options)?,
+        None => Body::Memory(read_body(response, options)?),
+    };
+    Ok(Fetched::Content(headers, body))
+}
+
+/// Describes an error followed by its causes, which errors of the HTTP client keep apart.
+pub(crate) fn error_chain(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        // Wrapping errors often repeat the message of their cause
+        let cause_message = cause.to_string();
+        if !message.ends_with(&cause_message) {
+            message.push_str(&format!(": {}", cause_message));
+        }
+        source = cause.source();
+    }
+    message
+}
+
+/// Describes a failed request, pointing at the option to raise when it timed out.
+fn request_error(error: reqwest::Error, options: &FetchOptions, context: &str) -> Failure {
+    let message = match (error.is_timeout(), error.is_connect()) {
+        (true, true) => format!(
+            "Timed out connecting after {} seconds, raise the limit with `connect_timeout`",
+            options.connect_timeout_or_default().as_secs()
+        ),
+        (true, false) => format!(
+            "Timed out after {} seconds, raise the limit with `timeout`",
+            options.timeout_or_default().as_secs()
+        ),
+        // The causes tell apart DNS, connection and TLS failures
+        _ => format!("{}: {}", context, error_chain(&error)),
+    };
+    Failure {
+        message,
+        transient: error.is_timeout() || error.is_connect() || error.is_request(),
+        retry_after: None,
+    }
+}
+
+/// Fails on a response whose status is neither a success nor listed in `allow_status`,
+/// quoting the start of its body, so error pages are never embedded.
+///
+/// Server errors, `408 Request Timeout`, `429 Too Many Requests` and `403 Forbidden` with
+/// an exhausted rate limit, as GitHub answers, are temporary.
+fn check_status(response: Response, options: &FetchOptions) -> Result<Response, Failure> {
+    let status = response.status();
+    if status.is_success() || options.allow_status.contains(&status.as_u16()) {
+        return Ok(response);
+    }
+    let status_line = format!("{:?} {}", response.version(), status);
+    let headers = response.headers();
+    let rate_limited = status == StatusCode::TOO_MANY_REQUESTS
+        || (status == StatusCode::FORBIDDEN && rate_limit::exhausted(headers));
+    let transient =
+        status.is_server_error() || status == StatusCode::REQUEST_TIMEOUT || rate_limited;
+    let retry_after = headers
+        .get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(retry_after)
+        .or_else(|| rate_limit::reset_delay(headers).filter(|_| rate_limited));
+    // Only the start of the body is read, error pages may be large
+    let mut body = Vec::new();
+    let _ = response
+        .take(ERROR_SNIPPET_CHARS as u64 * 4)
+        .read_to_end(&mut body);
+    let message = match body_snippet(&String::from_utf8_lossy(&body)) {
+        snippet if snippet.is_empty() => format!("Request failed with {}", status_line),
+        snippet => format!("Request failed with {}: {}", status_line, snippet),
+    };
+    Err(Failure {
+        message,
+        transient,
+        retry_after,
+    })
+}
+
+/// Collapses the whitespace of a response body and truncates it for an error message.
+fn body_snippet(body: &str) -> String {
+    let mut words = body.split_whitespace();
+    let mut snippet = words.next().unwrap_or_default().to_string();
+    for word in words {
+        snippet.push(' ');
+        snippet.push_str(word);
+    }
+    match snippet.char_indices().nth(ERROR_SNIPPET_CHARS) {
+        Some((end, _)) => format!("{}...", &snippet[..end]),
+        None => snippet,
+    }
+}
+
+/// Reads a response body, enforcing the `max_size` limit. A body of unknown size is read
+/// up to one byte past the limit.
+fn read_body(response: Response, options: &FetchOptions) -> Result<bytes::Bytes, Failure> {
+    if let Some(length) = response.content_length() {
+        check_size(length, options)?;
+    }
+    let limit = options.max_size.map_or(u64::MAX, |max| max as u64 + 1);
+    let mut body = Vec::new();
+    response.take(limit).read_to_end(&mut body).map_err(|e| {
+        match e.into_inner().map(|e| e.downcast::<reqwest::Error>()) {
+            Some(Ok(error)) => request_error(*error, options, "Failed to read response body"),
+            Some(Err(error)) => {
+                format!("Failed to read response body: {}", error_chain(&*error)).into()
+            }
+            None => "Failed to read response body".to_string().into(),
+        }
+    })?;
+    match options.max_size {
+        // The download stopped at the limit, the size is unknown
+        Some(max) if body.len() > max => {
+            Err(format!("Content exceeds the `max_size` of {} bytes", max).into())
+        }
+        _ => Ok(bytes::Bytes::from(body)),
+    }
+}
+
+/// Fails if content of `size` bytes exceeds the `max_size` option.
+fn check_size(size: u64, options: &FetchOptions) -> Result<(), Failure> {
+    match options.max_size {
+        Some(max) if size > max as u64 => Err(format!(
+            "Content of {} bytes exceeds the `max_size` of {} bytes",
+            size, max
+        )
+        .into()),
+        _ => Ok(()),
+    }
+}
+
+/// Follows `Link: <...>; rel="next"` headers and concatenates the JSON array pages.
+///
+/// A response without a next link is returned untouched, so non-list endpoints keep
+/// their original formatting.
+fn fetch_paginated(
+    client: &Client,
+    url: Url,
+    options: &FetchOptions,
+) -> Result<bytes::Bytes, String> {
+    let max_pages = options.max_pages.unwrap_or(MAX_PAGES);
+    let mut items = Vec::new();
+    let mut next = Some(url);
+    let mut page = 0;
+
+    while let Some(url) = next.take() {
+        page += 1;
+        if page > max_pages {
+            return Err(format!(
+                "Pagination exceeded the limit of {} pages",
+                max_pages
+            ));
+        }
+
+        let base = url.clone();
+        let Fetched::Content(headers, body) = send_request(client, url, options)? else {
+            unreachable!("pages are fetched without conditional requests");
+        };
+        let body = body.into_bytes()?;
+        let next_url = headers
+            .get(LINK)
+            .and_then(|value| value.to_str().ok())
+            .and_then(next_link);
+
+        if page == 1 && next_url.is_none() {
+            return Ok(body);
+        }
+
+        match serde_json::from_slice(&body) {
+            Ok(serde_json::Value::Array(page_items)) => items.extend(page_items),
+            _ => {
+                return Err(format!(
+                    "Page {} of the paginated response is not a JSON array",
+                    page
+                ))
+            }
+        }
+
+        // Targets may be relative to the page they were found on
+        next = next_url
+            .map(|link| match base.join(&link) {
+                Ok(url) => parse_url(url.as_str()),
+                Err(e) => Err(format!("Invalid URL `{}` in the Link header: {}", link, e)),
+            })
+            .transpose()?;
+    }
+
+    serde_json::to_vec(&serde_json::Value::Array(items))
+        .map(bytes::Bytes::from)
+        .map_err(|e| format!("Failed to serialize merged pages: {}", e))
+}
+
+/// Extracts the `rel="next"` target from an RFC 8288 `Link` header.
+fn next_link(header: &str) -> Option<String> {
+    link_values(header)
+        .into_iter()
+        .find(|(_, params)| {
+            params.iter().any(|(name, value)| {
+                name.eq_ignore_ascii_case("rel")
+                    && (value.split_ascii_whitespace()).any(|rel| rel.eq_ignore_ascii_case("next"))
+            })
+        })
+        .map(|(target, _)| target)
+}
+
+/// Splits a `Link` header into its targets and their parameters. Commas and semicolons
+/// only separate links and parameters outside of `<...>` targets and quoted strings.
+fn link_values(header: &str) -> Vec<(String, Vec<(String, String)>)> {
+    let mut links = Vec::new();
+    let mut chars = header.chars().peekable();
+    loop {
+        // Skip to the start of the next target
+        while chars.next_if(|c| *c != '<').is_some() {}
+        if chars.next().is_none() {
+            return links;
+        }
+        let target: String = std::iter::from_fn(|| chars.next_if(|c| *c != '>')).collect();
+        chars.next();
+        let mut params = Vec::new();
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        while chars.next_if_eq(&';').is_some() {
+            let name: String =
+                std::iter::from_fn(|| chars.next_if(|c| !"=;,".contains(*c))).collect();
+            let mut value = String::new();
+            if chars.next_if_eq(&'=').is_some() {
+                while chars.next_if(|c| c.is_whitespace()).is_some() {}
+                if chars.next_if_eq(&'"').is_some() {
+                    while let Some(c) = chars.next() {
+                        match c {
+                            '"' => break,
+                            '\\' => value.extend(chars.next()),
+                            c => value.push(c),
+                        }
+                    }
+                }
+                value.extend(std::iter::from_fn(|| chars.next_if(|c| !";,".contains(*c))));
+            }
+            params.push((name.trim().to_string(), value.trim().to_string()));
+        }
+        links.push((target.trim().to_string(), params));
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+enum CompressKind {
+    #[default]
+    None,
+    #[cfg(feature = "brotli")]
+    Brotli,
+    /// zstd, optionally with a shared dictionary defined in the manifest.
+    #[cfg(feature = "zstd")]
+    Zstd { dictionary: Option<String> },
+}
+
+pub(crate) fn cached_url_content(
+    url_str: &str,
+    compress_kind: CompressKind,
+    fetch_options: &mut FetchOptions,
+) -> Result<std::path::PathBuf, String> {
+    // The unresolved URL is reported, so substituted secrets never end up in the report
+    let source = url_str;
+    let started = Instant::now();
+    let url_str = &resolve_env(url_str, fetch_options)?;
+    // The defaults are loaded before the cache key is taken, which covers their variables
+    fetch_options.config()?;
+    // Revisions of the Hugging Face Hub are pinned to their commit before they are cached
+    let url_str = &match url_str.starts_with("hf://") {
+        true => huggingface::pin(url_str, fetch_options)?,
+        false => url_str.clone(),
+    };
+    // Branches of GitHub repositories are pinned to their commit when asked to
+    let permalink = fetch_options
+        .permalink
+        .or(fetch_options.config()?.permalink);
+    let url_str = &match permalink == Some(true) {
+        true => github::permalink(url_str, fetch_options)?,
+        false => url_str.clone(),
+    };
+    // Local files are tracked like environment variables
+    let urls = std::iter::once(url_str).chain(&fetch_options.mirrors);
+    let files: Vec<_> = (urls.filter(|url| file_url::is_file_url(url)))
+        .map(|url| file_url::path(url))
+        .collect::<Result<_, _>>()?;
+    fetch_options.files.extend(files);
+    let out_dir = std::path::Path::new(env!("INCLUDE_URL_CACHE_DIR"));
+    if !out_dir.exists() {
+        std::fs::create_dir_all(out_dir)
+            .map_err(|e| format!("Failed to create cache directory: {}", e))?;
+    }
+    let crate_name = env::var("CARGO_PKG_NAME").unwrap_or_else(|_| "unknown".into());
+    let mut hasher = Sha256::new();
+    hasher.update(crate_name.as_bytes());
+    hasher.update(b"\0");
+    hasher.update(url_str.as_bytes());
+    hasher.update(b"\0");
+    hasher.update(format!("{:?}", compress_kind));
+    hasher.update(b"\0");
+    hasher.update(fetch_options.cache_key());
+    #[cfg(feature = "zstd")]
+    if let CompressKind::Zstd {
+        dictionary: Some(name),
+    } = &compress_kind
+    {
+        hasher.update(b"\0");
+        hasher.update(Sha256::digest(dictionary::load_dictionary(
+            name,
+            fetch_options,
+        )?));
+    }
+    let hash = hasher.finalize();
+    let filename = format!("{:x}", hash);
+    let cache_file = out_dir.join(filename);
+    // Local content is read again on every expansion
+    let local = data_url::is_data_url(url_str) || file_url::is_file_url(url_str);
+    let cached = !local && cache_file.exists();
+    if cached {
+        let size = cache_file.metadata().map(|meta| meta.len()).unwrap_or(0);
+        let metadata = revalidate::load(&cache_file);
+        if !metadata.is_stale(fetch_options.ttl) {
+            lockfile::check_content(source, &metadata, fetch_options)?;
+            report::record(
+                source,
+                size,
+                CacheStatus::Hit,
+                started.elapsed(),
+                &mut fetch_options.warnings,
+            );
+            return Ok(cache_file);
+        }
+        if let Some(metadata) = Some(metadata).filter(revalidate::Metadata::has_validators) {
+            fetch_options.revalidate = Some(metadata);
+        }
+    }
+
+    if let Some(reason) = config::offline().filter(|_| !local) {
+        // Stale content is used as is, only missing content fails
+        if cache_file.exists() {
+            lockfile::check_content(source, &revalidate::load(&cache_file), fetch_options)?;
+            let size = cache_file.metadata().map(|meta| meta.len()).unwrap_or(0);
+            report::record(
+                source,
+                size,
+                CacheStatus::Hit,
+                started.elapsed(),
+                &mut fetch_options.warnings,
+            );
+            return Ok(cache_file);
+        }
+        return Err(format!(
+            "`{}` is not in the cache and the network is disabled by {}. Build once with \
+             network access to fetch it into the cache, offline builds then use it",
+            source, reason
+        ));
+    }
+
+    let partial = cache_file.with_extension("part");
+    fetch_options.partial = Some(partial);
+    // Large content is patched with the blocks it shares with the cached content, and
+    // downloaded again if that fails
+    #[cfg(feature = "zsync")]
+    let patched = match fetch_options.zsync.clone().filter(|_| cached) {
+        Some(control) => {
+            match zsync::refresh(
+                url_str,
+                &control,
+                &cache_file,
+                &compress_kind,
+                fetch_options,
+            ) {
+                Ok(fetched) => Some(fetched),
+                Err(e) => {
+                    fetch_options.warnings.push(format!(
+                        "The zsync refresh of `{}` failed, it is downloaded again: {}",
+                        source, e
+                    ));
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+    #[cfg(not(feature = "zsync"))]
+    let patched = None;
+    let fetched = match patched.map_or_else(|| fetch_mirrored(url_str, fetch_options), Ok) {
+        // Expired content is better than a broken build, unless a refresh was asked for
+        Err(_) if cached && !revalidate::refresh_requested() => {
+            lockfile::check_content(source, &revalidate::load(&cache_file), fetch_options)?;
+            let size = cache_file.metadata().map(|meta| meta.len()).unwrap_or(0);
+            report::record(
+                source,
+                size,
+                CacheStatus::Stale,
+                started.elapsed(),
+                &mut fetch_options.warnings,
+            );
+            return Ok(cache_file);
+        }
+        fetched => fetched?,
+    };
+    let (headers, mut body) = match fetched {
+        Fetched::Content(headers, body) => (headers, body),
+        Fetched::NotModified(headers) => {
+            let metadata =
+                (fetch_options.revalidate.take()).unwrap_or_else(|| revalidate::load(&cache_file));
+            let metadata = metadata.revalidated(&headers);
+            lockfile::check_content(source, &metadata, fetch_options)?;
+            revalidate::store(&cache_file, &metadata)?;
+            let size = cache_file.metadata().map(|meta| meta.len()).unwrap_or(0);
+            report::record(
+                source,
+                size,
+                CacheStatus::Revalidated,
+                started.elapsed(),
+                &mut fetch_options.warnings,
+            );
+            return Ok(cache_file);
+        }
+    };
+
+    let expected_sha256 = fetch_options.sha256.clone();
+    let check_sha256 = |actual: String| match &expected_sha256 {
+        Some(expected) if &actual != expected => Err(format!(
+            "SHA-256 mismatch: expected {}, got {}",
+            expected, actual
+        )),
+        _ => Ok(()),
+    };
+    let mut metadata = revalidate::Metadata::from_headers(&headers);
+    let transcoded = fetch_options.text || fetch_options.charset.is_some();
+    if fetch_options.canonicalize || transcoded {
+        // Content that is transformed is held in memory
+        let mut content = body.into_bytes()?;
+        check_signature(&content, fetch_options)?;
+        if fetch_options.canonicalize {
+            content = canonicalize_json(&content)?;
+        }
+        let digest = format!("{:x}", Sha256::digest(&content));
+        check_sha256(digest.clone())?;
+        if let Some(integrity) = &fetch_options.integrity {
+            integrity.check(&content[..])?;
+        }
+        metadata.sha256 = Some(digest);
+        metadata.length = Some(content.len() as u64);
+        lockfile::check_content(source, &metadata, fetch_options)?;
+        if transcoded {
+            content = charset::decode(content, &headers, fetch_options.charset.as_deref())?;
+        }
+        body = Body::Memory(content);
+    } else {
+        let checked = body.sha256().and_then(|digest| {
+            check_sha256(digest.clone())?;
+            let keys = [
+                &fetch_options.pgp_key,
+                &fetch_options.minisign_pub,
+                &fetch_options.sigstore_identity,
+            ];
+            if fetch_options.signature_url.is_some() || keys.iter().any(|key| key.is_some()) {
+                let mut content = Vec::new();
+                body.reader()?
+                    .read_to_end(&mut content)
+                    .map_err(|e| format!("Failed to read download: {}", e))?;
+                check_signature(&content, fetch_options)?;
+            }
+            if let Some(integrity) = &fetch_options.integrity {
+                integrity.check(body.reader()?)?;
+            }
+            metadata.sha256 = Some(digest);
+            metadata.length = Some(body.len()?);
+            lockfile::check_content(source, &metadata, fetch_options)
+        });
+        if let Err(e) = checked {
+            body.discard();
+            return Err(e);
+        }
+    }
+
+    write_cache_file(body, &compress_kind, &cache_file)?;
+    revalidate::store(&cache_file, &metadata)?;
+    let size = cache_file.metadata().map(|meta| meta.len()).unwrap_or(0);
+    report::record(
+        source,
+        size,
+        CacheStatus::Miss,
+        started.elapsed(),
+        &mut fetch_options.warnings,
+    );
+    Ok(cache_file)
+}
+
+/// Fails unless the detached signature at `signature_url` was made over the content by the
+/// trusted keys of `pgp_key` or `minisign_pub`, or the identity of `sigstore_identity`.
+#[cfg_attr(
+    not(any(feature = "pgp", feature = "minisign", feature = "sigstore")),
+    allow(unused_variables)
+)]
+fn check_signature(content: &[u8], options: &mut FetchOptions) -> Result<(), String> {
+    let keys: Vec<_> = [
+        ("pgp_key", options.pgp_key.is_some()),
+        ("minisign_pub", options.minisign_pub.is_some()),
+        ("sigstore_identity", options.sigstore_identity.is_some()),
+    ]
+    .into_iter()
+    .filter_map(|(key, given)| given.then_some(key))
+    .collect();
+    let Some(signature_url) = options.signature_url.clone() else {
+        return match keys.first() {
+            Some(key) => Err(format!("`{}` requires `signature_url`", key)),
+            None => Ok(()),
+        };
+    };
+    let key = match keys[..] {
+        [key] => key,
+        [] => {
+            return Err(
+                "`signature_url` requires `pgp_key`, `minisign_pub` or `sigstore_identity`"
+                    .to_string(),
+            )
+        }
+        [first, second, ..] => {
+            return Err(format!("`{}` and `{}` cannot be combined", first, second))
+        }
+    };
+    let signature_options = FetchOptions {
+        sha256: None,
+        integrity: None,
+        signature_url: None,
+        pgp_key: None,
+        minisign_pub: None,
+        sigstore_identity: None,
+        zsync: None,
+        paginate: Paginate::None,
+        partial: None,
+        revalidate: None,
+        ..options.clone()
+    };
+    let signature = match fetch_url_content(&signature_url, &signature_options)? {
+        Fetched::Content(_, body) => body.into_bytes()?,
+        Fetched::NotModified(_) => unreachable!("the signature request is not conditional"),
+    };
+    let failed = |e: String| format!("Signature check against `{}` failed: {}", signature_url, e);
+    match key {
+        #[cfg(feature = "pgp")]
+        "pgp_key" => pgp::load_keys(&options.pgp_key.clone().unwrap_or_default(), options)
+            .and_then(|keys| pgp::verify(content, &signature, &keys))
+            .map_err(failed),
+        #[cfg(feature = "minisign")]
+        "minisign_pub" => {
+            minisign::load_key(&options.minisign_pub.clone().unwrap_or_default(), options)
+                .and_then(|key| minisign::verify(content, &signature, &key))
+                .map_err(failed)
+        }
+        #[cfg(feature = "sigstore")]
+        "sigstore_identity" => sigstore::verify(content, &signature, options).map_err(failed),
+        _ => unreachable!("the keys are rejected without their features"),
+    }
+}
+
+/// Reads a key file given by its path relative to the manifest directory, tracking it for
+/// re-expansion.
+#[cfg(any(feature = "pgp", feature = "minisign", feature = "sigstore"))]
+fn read_key_file(value: &str, options: &mut FetchOptions) -> Result<Vec<u8>, String> {
+    let path = match env::var_os("CARGO_MANIFEST_DIR") {
+        Some(dir) => std::path::Path::new(&dir).join(value),
+        None => std::path::PathBuf::from(value),
+    };
+    if !options.files.contains(&path) {
+        options.files.push(path.clone());
+    }
+    std::fs::read(&path).map_err(|e| format!("Failed to read `{}`: {}", path.display(), e))
+}
+
+/// Writes a body to its cache file through the compressor, replacing the file at once so
+/// that an interrupted build never leaves a truncated entry behind. Downloaded files that
+/// are not compressed are moved into place.
+fn write_cache_file(
+    body: Body,
+    compress_kind: &CompressKind,
+    cache_file: &std::path::Path,
+) -> Result<(), String> {
+    if let (CompressKind::None, Body::File(path)) = (compress_kind, &body) {
+        return std::fs::rename(path, cache_file)
+            .map_err(|e| format!("Failed to write cache file: {}", e));
+    }
+    let temporary = cache_file.with_extension("tmp");
+    let written = (|| {
+        let mut reader = body.reader()?;
+        let file = std::fs::File::create(&temporary)
+            .map_err(|e| format!("Failed to open cache file: {}", e))?;
+        let mut output = std::io::BufWriter::new(file);
+        match compress_kind {
+            CompressKind::None => std::io::copy(&mut reader, &mut output)
+                .map(|_| ())
+                .map_err(|e| format!("Failed to write cache file: {}", e))?,
+            #[cfg(feature = "brotli")]
+            CompressKind::Brotli => {
+                let mut encoder = brotli::CompressorWriter::new(&mut output, 4096, 11, 22);
+                std::io::copy(&mut reader, &mut encoder)
+                    .map_err(|e| format!("Failed to write compressed content: {}", e))?;
+                std::io::Write::flush(&mut encoder)
+                    .map_err(|e| format!("Failed to flush compressed content: {}", e))?;
+            }
+            #[cfg(feature = "zstd")]
+            CompressKind::Zstd { dictionary } => {
+                let size = body.len()?;
+                // Made when the cache key was computed, so it is only read here
+                let dictionary = (dictionary.as_deref())
+                    .map(|name| dictionary::load_dictionary(name, &mut FetchOptions::default()))
+                    .transpose()?;
+                dictionary::compress(&mut reader, &mut output, size, dictionary.as_deref())?
+            }
+        }
+        output
+            .into_inner()
+            .map_err(|e| format!("Failed to write cache file: {}", e.error()))?;
+        std::fs::rename(&temporary, cache_file)
+            .map_err(|e| format!("Failed to write cache file: {}", e))
+    })();
+    if written.is_err() {
+        let _ = std::fs::remove_file(&temporary);
+    }
+    body.discard();
+    written
+}
+
+/// Re-serializes JSON with sorted object keys, no insignificant whitespace and a stable
+/// number formatting, so that semantically identical documents produce identical bytes.
+///
+/// The keys are sorted explicitly, as the `preserve_order` feature of `serde_json` may be
+/// enabled by another crate in the dependency graph.
+fn canonicalize_json(content: &[u8]) -> Result<bytes::Bytes, String> {
+    let value: serde_json::Value = serde_json::from_slice(content)
+        .map_err(|e| format!("Cannot canonicalize invalid JSON content: {}", e))?;
+    serde_json::to_vec(&sort_keys(value))
+        .map(bytes::Bytes::from)
+        .map_err(|e| format!("Failed to serialize canonical JSON: {}", e))
+}
+
+/// Sorts the keys of every object in `value`.
+fn sort_keys(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => {
+            let mut entries: Vec<_> = map.into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            let entries = entries
+                .into_iter()
+                .map(|(key, value)| (key, sort_keys(value)));
+            serde_json::Value::Object(entries.collect())
+        }
+        serde_json::Value::Array(items) => {
+            serde_json::Value::Array(items.into_iter().map(sort_keys).collect())
+        }
+        value => value,
+    }
+}
+
+/// Returns `true` if any of the `skip_under` cfgs is active for the crate being compiled.
+///
+/// Proc macros cannot evaluate `cfg` predicates of the calling crate, so this inspects the
+/// command line and environment of the compiler process the macro runs in.
+fn skip_requested(skip_under: &[String]) -> bool {
+    let args: Vec<String> = env::args().collect();
+    skip_under.iter().any(|cfg| match cfg.as_str() {
+        "clippy" => {
+            env::var_os("CLIPPY_ARGS").is_some()
+                || args
+                    .first()
+                    .is_some_and(|arg| arg.contains("clippy-driver"))
+        }
+        "doc" => args.first().is_some_and(|arg| arg.contains("rustdoc")),
+        cfg => cfg_in_args(cfg, &args),
+    })
+}
+
+/// Checks whether a rustc command line enables `cfg`, either through `--test` or `--cfg`.
+fn cfg_in_args(cfg: &str, args: &[String]) -> bool {
+    if cfg == "test" && args.iter().any(|arg| arg == "--test") {
+        return true;
+    }
+    args.iter().enumerate().any(|(index, arg)| {
+        let value = match arg.strip_prefix("--cfg") {
+            Some("") => args.get(index + 1).map(String::as_str),
+            Some(value) => value.strip_prefix('='),
+            None => None,
+        };
+        value == Some(cfg)
+    })
+}
+
+/// Parses the value of the `skip_under` option: a single cfg name or a list of them.
+fn parse_cfg_list(input: ParseStream) -> syn::Result<Vec<String>> {
+    if input.peek(syn::token::Bracket) {
+        let content;
+        syn::bracketed!(content in input);
+        let cfgs = Punctuated::<Ident, Token![,]>::parse_terminated(&content)?;
+        Ok(cfgs.iter().map(Ident::to_string).collect())
+    } else {
+        Ok(vec![input.parse::<Ident>()?.to_string()])
+    }
+}
+
+/// Parses a macro specific option given as a list of strings, such as `["id", "name"]`.
+fn string_list(value: &syn::Expr) -> syn::Result<Vec<String>> {
+    let syn::Expr::Array(array) = value else {
+        return Err(syn::Error::new_spanned(value, "Expected a list of strings"));
+    };
+    array
+        .elems
+        .iter()
+        .map(|element| match element {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Str(value),
+                ..
+            }) => Ok(value.value()),
+            _ => Err(syn::Error::new_spanned(element, "Expected a string")),
+        })
+        .collect()
+}
+
+/// Options shared by every macro, given as `key = value` pairs after the URL.
+#[derive(Default)]
+struct MacroOptions {
+    fetch: FetchOptions,
+    compression: Option<CompressKind>,
+    /// Name of the shared zstd dictionary to compress with.
+    dictionary: Option<String>,
+    /// Embed the content as chunks of at most this many bytes.
+    chunk_size: Option<usize>,
+    skip_under: Vec<String>,
+}
+
+impl MacroOptions {
+    /// Parses the value of the option named `key`.
+    fn parse_option(&mut self, key: &Ident, input: ParseStream) -> syn::Result<()> {
+        match key.to_string().as_str() {
+            "paginate" => {
+                let value: Ident = input.parse()?;
+                self.fetch.paginate = match value.to_string().as_str() {
+                    "link_header" => Paginate::LinkHeader,
+                    "none" => Paginate::None,
+                    _ => {
+                        return Err(syn::Error::new(
+                            value.span(),
+                            "Expected `link_header` or `none`",
+                        ))
+                    }
+                };
+            }
+            "mirrors" => {
+                let value: syn::Expr = input.parse()?;
+                self.fetch.mirrors.extend(string_list(&value)?);
+            }
+            "verify_mirrors" => self.fetch.verify_mirrors = input.parse::<LitBool>()?.value,
+            "allow_file_urls" => self.fetch.allow_file_urls = Some(input.parse::<LitBool>()?.value),
+            "max_pages" => {
+                let value: LitInt = input.parse()?;
+                let max_pages = value.base10_parse()?;
+                if max_pages == 0 {
+                    return Err(syn::Error::new(
+                        value.span(),
+                        "`max_pages` must be at least 1",
+                    ));
+                }
+                self.fetch.max_pages = Some(max_pages);
+            }
+            "header" => {
+                let value: LitStr = input.parse()?;
+                let header = value.value();
+                let (name, content) = header
+                    .split_once(':')
+                    .ok_or_else(|| syn::Error::new(value.span(), "Expected `Name: value`"))?;
+                let name = name.trim();
+                check_header_name(name, &value)?;
+                self.fetch
+                    .headers
+                    .push((name.to_string(), content.trim().to_string()));
+            }
+            "method" => {
+                let value: LitStr = input.parse()?;
+                let method = Method::from_bytes(value.value().to_ascii_uppercase().as_bytes())
+                    .map_err(|_| syn::Error::new(value.span(), "Invalid HTTP method"))?;
+                self.fetch.method = Some(method);
+            }
+            "body" => self.fetch.body = Some(input.parse::<LitStr>()?.value()),
+            "content_type" => {
+                let value: LitStr = input.parse()?;
+                if HeaderValue::from_str(&value.value()).is_err() {
+                    return Err(syn::Error::new(value.span(), "Invalid content type"));
+                }
+                self.fetch.content_type = Some(value.value());
+            }
+            "auth" => self.fetch.auth = Some(parse_auth(input)?),
+            "proxy" => self.fetch.proxy = Some(input.parse::<LitStr>()?.value()),
+            "ca_cert" => self.fetch.ca_certs.push(parse_header_value(input)?),
+            "client_cert" => self.fetch.client_cert = Some(parse_header_value(input)?),
+            "client_cert_password" => {
+                self.fetch.client_cert_password = Some(parse_header_value(input)?)
+            }
+            "cookie" => {
+                let value = parse_header_value(input)?;
+                if HeaderValue::from_str(&value).is_err() {
+                    return Err(syn::Error::new(input.span(), "Invalid cookie"));
+                }
+                self.fetch.cookies.push(value);
+            }
+            "headers" => {
+                let content;
+                syn::braced!(content in input);
+                while !content.is_empty() {
+                    let name: LitStr = content.parse()?;
+                    check_header_name(&name.value(), &name)?;
+                    content.parse::<Token![=]>()?;
+                    let value = parse_header_value(&content)?;
+                    self.fetch.headers.push((name.value(), value));
+                    if content.is_empty() {
+                        break;
+                    }
+                    content.parse::<Token![,]>()?;
+                }
+            }
+            "max_size" => self.fetch.max_size = Some(parse_size(input)?),
+            "charset" => {
+                let value: LitStr = input.parse()?;
+                charset::for_label(&value.value()).map_err(|e| syn::Error::new(value.span(), e))?;
+                self.fetch.charset = Some(value.value());
+            }
+            "ttl" => self.fetch.ttl = Some(parse_duration(input)?),
+            #[cfg(feature = "zsync")]
+            "zsync" => self.fetch.zsync = Some(input.parse::<LitStr>()?.value()),
+            #[cfg(not(feature = "zsync"))]
+            "zsync" => {
+                return Err(syn::Error::new(
+                    key.span(),
+                    "`zsync` requires the `zsync` feature",
+                ))
+            }
+            "timeout" => {
+                let value: LitInt = input.parse()?;
+                self.fetch.timeout = Some(value.base10_parse()?);
+            }
+            "connect_timeout" => {
+                let value: LitInt = input.parse()?;
+                self.fetch.connect_timeout = Some(value.base10_parse()?);
+            }
+            "retries" => {
+                let value: LitInt = input.parse()?;
+                self.fetch.retries = Some(value.base10_parse()?);
+            }
+            "retry_any_method" => {
+                let value: LitBool = input.parse()?;
+                self.fetch.retry_any_method = value.value;
+            }
+            "rate_limit" => {
+                let value: LitInt = input.parse()?;
+                match value.base10_parse()? {
+                    0 => return Err(syn::Error::new(value.span(), "Expected a positive rate")),
+                    rate => self.fetch.rate_limit = Some(rate),
+                }
+            }
+            "canonicalize" => {
+                let value: LitBool = input.parse()?;
+                self.fetch.canonicalize = value.value;
+            }
+            "locked" => {
+                let value: LitBool = input.parse()?;
+                self.fetch.unlocked = !value.value;
+            }
+            "permalink" => {
+                let value: LitBool = input.parse()?;
+                self.fetch.permalink = Some(value.value);
+            }
+            "ip_version" => {
+                let value: LitInt = input.parse()?;
+                match value.base10_parse()? {
+                    version @ (4 | 6) => self.fetch.ip_version = Some(version),
+                    _ => return Err(syn::Error::new(value.span(), "Expected `4` or `6`")),
+                }
+            }
+            "doh" => {
+                let value: LitStr = input.parse()?;
+                match Url::parse(&value.value()) {
+                    Ok(url) if url.scheme() == "https" => {}
+                    _ => return Err(syn::Error::new(value.span(), "Expected an HTTPS URL")),
+                }
+                self.fetch.doh = Some(value.value());
+            }
+            "resolve" => {
+                let content;
+                syn::braced!(content in input);
+                while !content.is_empty() {
+                    let host: LitStr = content.parse()?;
+                    content.parse::<Token![=]>()?;
+                    let ip: LitStr = content.parse()?;
+                    let ip = ip
+                        .value()
+                        .parse()
+                        .map_err(|_| syn::Error::new(ip.span(), "Invalid IP address"))?;
+                    self.fetch.hosts.push((host.value(), ip));
+                    if content.is_empty() {
+                        break;
+                    }
+                    content.parse::<Token![,]>()?;
+                }
+            }
+            "decompress" => {
+                let value: LitBool = input.parse()?;
+                self.fetch.keep_encoding = !value.value;
+            }
+            "allow_status" => {
+                let codes = match input.peek(syn::token::Bracket) {
+                    true => {
+                        let content;
+                        syn::bracketed!(content in input);
+                        Punctuated::<LitInt, Token![,]>::parse_terminated(&content)?
+                            .into_iter()
+                            .collect()
+                    }
+                    false => vec![input.parse::<LitInt>()?],
+                };
+                for code in codes {
+                    match code.base10_parse::<u16>() {
+                        Ok(status @ 100..=599) => self.fetch.allow_status.push(status),
+                        _ => {
+                            return Err(syn::Error::new(
+                                code.span(),
+                                "Expected an HTTP status code",
+                            ))
+                        }
+                    }
+                }
+            }
+            "sha256" => {
+                let value: LitStr = input.parse()?;
+                let digest = value.value().to_ascii_lowercase();
+                if digest.len() != 64 || !digest.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err(syn::Error::new(
+                        value.span(),
+                        "Expected a hex encoded SHA-256 digest",
+                    ));
+                }
+                self.fetch.sha256 = Some(digest);
+            }
+            "signature_url" => self.fetch.signature_url = Some(input.parse::<LitStr>()?.value()),
+            #[cfg(feature = "pgp")]
+            "pgp_key" => self.fetch.pgp_key = Some(parse_header_value(input)?),
+            #[cfg(not(feature = "pgp"))]
+            "pgp_key" => {
+                return Err(syn::Error::new(
+                    key.span(),
+                    "`pgp_key` requires the `pgp` feature",
+                ))
+            }
+            #[cfg(feature = "minisign")]
+            "minisign_pub" => self.fetch.minisign_pub = Some(parse_header_value(input)?),
+            #[cfg(not(feature = "minisign"))]
+            "minisign_pub" => {
+                return Err(syn::Error::new(
+                    key.span(),
+                    "`minisign_pub` requires the `minisign` feature",
+                ))
+            }
+            #[cfg(feature = "sigstore")]
+            "sigstore_identity" => {
+                self.fetch.sigstore_identity = Some(input.parse::<LitStr>()?.value())
+            }
+            #[cfg(feature = "sigstore")]
+            "sigstore_issuer" => {
+                self.fetch.sigstore_issuer = Some(input.parse::<LitStr>()?.value())
+            }
+            #[cfg(feature = "sigstore")]
+            "sigstore_root" => self.fetch.sigstore_root = Some(input.parse::<LitStr>()?.value()),
+            #[cfg(not(feature = "sigstore"))]
+            "sigstore_identity" | "sigstore_issuer" | "sigstore_root" => {
+                return Err(syn::Error::new(
+                    key.span(),
+                    format!("`{}` requires the `sigstore` feature", key),
+                ))
+            }
+            "integrity" => {
+                let value: LitStr = input.parse()?;
+                let integrity = integrity::Integrity::parse(&value.value())
+                    .map_err(|err| syn::Error::new(value.span(), err))?;
+                self.fetch.integrity = Some(integrity);
+            }
+            "compression" => {
+                let value: Ident = input.parse()?;
+                self.compression = Some(match value.to_string().as_str() {
+                    "none" => CompressKind::None,
+                    #[cfg(feature = "brotli")]
+                    "brotli" => CompressKind::Brotli,
+                    #[cfg(feature = "zstd")]
+                    "zstd" => CompressKind::Zstd { dictionary: None },
+                    _ => {
+                        return Err(syn::Error::new(
+                            value.span(),
+                            format!("Unsupported compression `{}`", value),
+                        ))
+                    }
+                });
+            }
+            "dictionary" => self.dictionary = Some(input.parse::<LitStr>()?.value()),
+            "chunk_size" => {
+                let chunk_size = parse_size(input)?;
+                if chunk_size == 0 {
+                    return Err(syn::Error::new(
+                        key.span(),
+                        "`chunk_size` must be at least 1",
+                    ));
+                }
+                self.chunk_size = Some(chunk_size);
+            }
+            "skip_under" => self.skip_under = parse_cfg_list(input)?,
+            _ => {
+                return Err(syn::Error::new(
+                    key.span(),
+                    format!("Unknown option `{}`", key),
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the requested compression, falling back to `default`.
+    fn compress_kind(&self, default: CompressKind) -> Result<CompressKind, String> {
+        let kind = self.compression.clone().unwrap_or(default);
+        match (kind, &self.dictionary) {
+            (kind, None) => Ok(kind),
+            #[cfg(feature = "zstd")]
+            (CompressKind::Zstd { .. }, Some(name)) => Ok(CompressKind::Zstd {
+                dictionary: Some(name.clone()),
+            }),
+            _ => Err("`dictionary` requires `compression = zstd`".to_string()),
+        }
+    }
+}
+
+/// Fails with an error spanned at `token` if `name` is not a valid header name.
+fn check_header_name(name: &str, token: &LitStr) -> syn::Result<()> {
+    match HeaderName::from_bytes(name.as_bytes()) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(syn::Error::new(
+            token.span(),
+            format!("Invalid header name `{}`", name),
+        )),
+    }
+}
+
+/// Parses the value of an entry of the `headers` option, either a string or
+/// `env("NAME")`, which reads the environment variable `NAME` like a `${NAME}` reference.
+fn parse_header_value(input: ParseStream) -> syn::Result<String> {
+    if input.peek(LitStr) {
+        return Ok(input.parse::<LitStr>()?.value());
+    }
+    let function: Ident = input.parse()?;
+    if function != "env" {
+        return Err(syn::Error::new(
+            function.span(),
+            "Expected a string or `env(\"NAME\")`",
+        ));
+    }
+    let content;
+    syn::parenthesized!(content in input);
+    let name: LitStr = content.parse()?;
+    Ok(format!("${{{}}}", name.value()))
+}
+
+/// Parses the value of the `auth` option, `bearer_env("TOKEN")` or
+/// `basic_env("USER", "PASSWORD")`.
+fn parse_auth(input: ParseStream) -> syn::Result<Auth> {
+    let function: Ident = input.parse()?;
+    let content;
+    syn::parenthesized!(content in input);
+    let names: Vec<String> = Punctuated::<LitStr, Token![,]>::parse_terminated(&content)?
+        .iter()
+        .map(LitStr::value)
+        .collect();
+    match (function.to_string().as_str(), names.as_slice()) {
+        ("bearer_env", [token]) => Ok(Auth::Bearer(token.clone())),
+        ("basic_env", [user, password]) => Ok(Auth::Basic(user.clone(), password.clone())),
+        _ => Err(syn::Error::new(
+            function.span(),
+            "Expected `bearer_env(\"TOKEN\")` or `basic_env(\"USER\", \"PASSWORD\")`",
+        )),
+    }
+}
+
+/// Parses a byte size given either as an integer or as a string with a unit, such as
+/// `"512KiB"` or `"5MB"`.
+fn parse_size(input: ParseStream) -> syn::Result<usize> {
+    if input.peek(LitInt) {
+        return input.parse::<LitInt>()?.base10_parse();
+    }
+    let value: LitStr = input.parse()?;
+    size_from_str(&value.value()).ok_or_else(|| {
+        syn::Error::new(
+            value.span(),
+            "Expected a size such as `1024`, `\"512KiB\"` or `\"5MB\"`",
+        )
+    })
+}
+
+fn size_from_str(size: &str) -> Option<usize> {
+    let size = size.trim();
+    let split = size
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(size.len());
+    let (number, unit) = size.split_at(split);
+    let multiplier = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "KB" => 1_000,
+        "KIB" => 1 << 10,
+        "MB" => 1_000_000,
+        "MIB" => 1 << 20,
+        "GB" => 1_000_000_000,
+        "GIB" => 1 << 30,
+        _ => return None,
+    };
+    number.parse::<usize>().ok()?.checked_mul(multiplier)
+}
+
+/// Parses a duration in seconds given either as an integer or as a string with a unit,
+/// such as `"30m"` or `"7d"`.
+fn parse_duration(input: ParseStream) -> syn::Result<u64> {
+    if input.peek(LitInt) {
+        return input.parse::<LitInt>()?.base10_parse();
+    }
+    let value: LitStr = input.parse()?;
+    seconds_from_str(&value.value()).ok_or_else(|| {
+        syn::Error::new(
+            value.span(),
+            "Expected a duration such as `3600`, `\"30m\"` or `\"7d\"`",
+        )
+    })
+}
+
+fn seconds_from_str(duration: &str) -> Option<u64> {
+    let duration = duration.trim();
+    let split = duration
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(duration.len());
+    let (number, unit) = duration.split_at(split);
+    let multiplier = match unit.trim() {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return None,
+    };
+    number.parse::<u64>().ok()?.checked_mul(multiplier)
+}
+
+/// Parses an option name followed by `=`.
+fn parse_option_key(input: ParseStream) -> syn::Result<Ident> {
+    let key = input.parse()?;
+    input.parse::<Token![=]>()?;
+    Ok(key)
+}
+
+/// Parses the URL leading the input of a macro, or a `mirrors = [...]` list whose first URL
+/// is the primary one and the others its fallbacks.
+fn parse_primary_url(input: ParseStream, options: &mut MacroOptions) -> syn::Result<LitStr> {
+    if !(input.peek(Ident) && input.peek2(Token![=])) {
+        return input.parse();
+    }
+    let key = parse_option_key(input)?;
+    if key != "mirrors" {
+        return Err(syn::Error::new(
+            key.span(),
+            "Expected a URL or `mirrors = [...]`",
+        ));
+    }
+    let value: syn::Expr = input.parse()?;
+    let mut mirrors = string_list(&value)?.into_iter();
+    let url = mirrors
+        .next()
+        .ok_or_else(|| syn::Error::new_spanned(&value, "Expected at least one URL"))?;
+    options.fetch.mirrors.extend(mirrors);
+    Ok(LitStr::new(&url, key.span()))
+}
+
+/// Parser for the input of the raw content macros.
+///
+/// Handles the URL, or a list of mirrors, followed by optional `key = value` options.
+struct UrlInput {
+    url: LitStr,
+    options: MacroOptions,
+}
+
+impl Parse for UrlInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        UrlInput::parse_with(input, &[]).map(|(input, _)| input)
+    }
+}
+
+/// Macro specific options as `key = value` pairs, with the values left unparsed.
+type ExtraOptions = Vec<(Ident, syn::Expr)>;
+
+impl UrlInput {
+    /// Parses the URL and options, also accepting the macro specific options named in
+    /// `keys`, whose values are returned unparsed.
+    fn parse_with(input: ParseStream, keys: &[&str]) -> syn::Result<(Self, ExtraOptions)> {
+        let mut options = MacroOptions::default();
+        let url = parse_primary_url(input, &mut options)?;
+        let mut extra = Vec::new();
+
+        while input.peek(Token![,]) {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            let key = parse_option_key(input)?;
+            if keys.contains(&key.to_string().as_str()) {
+                extra.push((key, input.parse()?));
+            } else {
+                options.parse_option(&key, input)?;
+            }
+        }
+
+        Ok((UrlInput { url, options }, extra))
+    }
+}
+
+/// Parser for the input of macros that generate a module, `vis mod name, "url", options`.
+struct ModuleUrlInput {
+    vis: syn::Visibility,
+    name: Ident,
+    input: UrlInput,
+}
+
+impl Parse for ModuleUrlInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        ModuleUrlInput::parser(&[])(input).map(|(input, _)| input)
+    }
+}
+
+impl ModuleUrlInput {
+    /// Returns a parser that also accepts the macro specific options named in `keys`, for
+    /// use with `parse_macro_input!(input with parser)`. Their values are returned unparsed.
+    fn parser(
+        keys: &'static [&'static str],
+    ) -> impl Fn(ParseStream) -> syn::Result<(Self, ExtraOptions)> {
+        move |input| {
+            let vis = input.parse()?;
+            input.parse::<Token![mod]>()?;
+            let name = input.parse()?;
+            input.parse::<Token![,]>()?;
+            let (input, extra) = UrlInput::parse_with(input, keys)?;
+            Ok((ModuleUrlInput { vis, name, input }, extra))
+        }
+    }
+}
+
+/// Converts an arbitrary key into a `SCREAMING_SNAKE_CASE` identifier, splitting
+/// `camelCase` words and replacing punctuation with underscores.
+fn const_ident(key: &str) -> Option<Ident> {
+    let mut name = String::new();
+    let mut previous = '_';
+    for c in key.chars() {
+        if c.is_ascii_alphanumeric() {
+            if c.is_ascii_uppercase()
+                && (previous.is_ascii_lowercase() || previous.is_ascii_digit())
+            {
+                name.push('_');
+            }
+            name.push(c.to_ascii_uppercase());
+        } else if !name.ends_with('_') {
+            name.push('_');
+        }
+        previous = c;
+    }
+    let name = name.trim_matches('_');
+    match name.chars().next()? {
+        '0'..='9' => Some(format_ident!("_{}", name)),
+        _ => Some(format_ident!("{}", name)),
+    }
+}
+
+/// Converts an arbitrary name into a `snake_case` identifier.
+fn snake_ident(name: &str) -> Option<Ident> {
+    let name = const_ident(name)?.to_string().to_lowercase();
+    match syn::parse_str::<Ident>(&name) {
+        Ok(ident) => Some(ident),
+        Err(_) => Some(format_ident!("{}_", name)),
+    }
+}
+
+/// Returns a literal of the float type `ty`, spelling out the values that have none.
+#[cfg(any(feature = "parquet", feature = "sqlite"))]
+fn float_literal(value: f64, ty: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    if value.is_nan() {
+        quote! { #ty::NAN }
+    } else if value == f64::INFINITY {
+        quote! { #ty::INFINITY }
+    } else if value == f64::NEG_INFINITY {
+        quote! { #ty::NEG_INFINITY }
+    } else {
+        let value = proc_macro2::Literal::f64_unsuffixed(value);
+        quote! { #value }
+    }
+}
+
+/// Expands to a compile error with `message`, reported at the macro call.
+fn compile_error(message: impl std::fmt::Display) -> TokenStream {
+    syn::Error::new(proc_macro2::Span::call_site(), message)
+        .to_compile_error()
+        .into()
+}
+
+/// Returns an error if a byte-only option was given to a macro that does not embed bytes.
+fn reject_bytes_options(options: &MacroOptions, macro_name: &str) -> Result<(), String> {
+    let compressed = options
+        .compression
+        .as_ref()
+        .is_some_and(|kind| *kind != CompressKind::None);
+    let option = if compressed {
+        "compression"
+    } else if options.dictionary.is_some() {
+        "dictionary"
+    } else if options.chunk_size.is_some() {
+        "chunk_size"
+    } else {
+        return Ok(());
+    };
+    Err(format!(
+        "`{}` is not supported by `{}!`, use `include_url_bytes!` instead",
+        option, macro_name
+    ))
+}
+
+/// Returns an error if `skip_under` was given to a macro generating items, which would be
+/// missing when skipped.
+fn reject_skip_under(options: &MacroOptions, macro_name: &str) -> Result<(), String> {
+    match options.skip_under.is_empty() {
+        true => Ok(()),
+        false => Err(format!(
+            "`skip_under` is not supported by `{}!` as it generates items",
+            macro_name
+        )),
+    }
+}
+
+/// Expands to the bytes of a file, or to the `&'static [(&'static str, &'static [u8])]` of
+/// the files below a directory, by relative path.
+fn embed_tree(path: &std::path::Path) -> proc_macro2::TokenStream {
+    if !path.is_dir() {
+        let path_str = path.display().to_string();
+        return quote! { include_bytes!(#path_str) };
+    }
+    embed_files(&git::files(path))
+}
+
+/// Expands to the `&'static [(&'static str, &'static [u8])]` of named files.
+fn embed_files(files: &[(String, std::path::PathBuf)]) -> proc_macro2::TokenStream {
+    let names = files.iter().map(|(name, _)| name);
+    let paths = files.iter().map(|(_, path)| path.display().to_string());
+    quote! { &[#((#names, include_bytes!(#paths) as &[u8])),*] }
+}
+
+/// Expands to the files selected in an archive.
+#[cfg(feature = "archive")]
+fn embed_selection(selection: archive::Selection) -> proc_macro2::TokenStream {
+    match selection {
+        archive::Selection::Path(path) => embed_tree(&path),
+        archive::Selection::Matches(files) => embed_files(&files),
+    }
+}
+
+/// Expands to the bytes of a cached file.
+///
+/// With a `chunk_size`, the content is split into separately included chunk files and
+/// expands to the `&'static [&'static [u8]]` of the chunks in order, which keeps the
+/// memory rustc needs for very large assets bounded. Chunk files are named after the digest
+/// of their content, so a refreshed cache file never embeds the chunks of its former
+/// content, and those are removed.
+fn embed_bytes(
+    path: &std::path::Path,
+    chunk_size: Option<usize>,
+) -> Result<proc_macro2::TokenStream, String> {
+    let Some(chunk_size) = chunk_size else {
+        let path_str = path.display().to_string();
+        return Ok(quote! { include_bytes!(#path_str) });
+    };
+
+    let content = std::fs::read(path).map_err(|e| format!("Failed to open cache file: {}", e))?;
+    let prefix = format!("{}.chunk-{}-", path.display(), chunk_size);
+    let mut chunks = Vec::new();
+    for (index, chunk) in content.chunks(chunk_size).enumerate() {
+        let digest = format!("{:x}", Sha256::digest(chunk));
+        let chunk_path = format!("{}{}-{}", prefix, index, &digest[..16]);
+        if !std::path::Path::new(&chunk_path).exists() {
+            std::fs::write(&chunk_path, chunk)
+                .map_err(|e| format!("Failed to write chunk file: {}", e))?;
+        }
+        chunks.push(chunk_path);
+    }
+    let count = chunks.len();
+
+    // Chunks of the former content of the cache file are left behind by a refresh
+    let siblings = path.parent().and_then(|dir| std::fs::read_dir(dir).ok());
+    for entry in siblings.into_iter().flatten().flatten() {
+        let sibling = entry.path().display().to_string();
+        if sibling.starts_with(&prefix) && !chunks.contains(&sibling) {
+            let _ = std::fs::remove_file(sibling);
+        }
+    }
+
+    // Every chunk stays a reference to its own included file
+    Ok(quote! {{
+        static CHUNKS: [&[u8]; #count] = [#(include_bytes!(#chunks)),*];
+        &CHUNKS as &'static [&'static [u8]]
+    }})
+}
+
+/// A procedural macro that includes content from a URL as a static string at compile time.
+///
+/// # Usage
+///
+/// ```rust
+/// use include_url_macro::include_url;
+///
+/// const STATIC_CONTENT: &str =
+///     include_url!("https://raw.githubusercontent.com/rust-lang/rust/master/README.md");
+/// ```
+///
+/// See the [crate level documentation](crate#options) for the supported options. When
+/// skipped through `skip_under`, the macro expands to an empty string.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The URL is invalid
+/// * The URL scheme is not HTTP or HTTPS
+/// * The content cannot be fetched
+/// * The response is not valid UTF-8
+#[proc_macro]
+pub fn include_url(input: TokenStream) -> TokenStream {
+    let UrlInput { url, mut options } = parse_macro_input!(input as UrlInput);
+    if skip_requested(&options.skip_under) {
+        return quote! { "" }.into();
+    }
+    if let Err(err) = reject_bytes_options(&options, "include_url") {
+        return compile_error(err);
+    }
+    let url_str = url.value();
+
+    options.fetch.text = true;
+    match cached_url_content(&url_str, CompressKind::None, &mut options.fetch) {
+        Ok(path) => {
+            let path_str = path.display().to_string();
+            let tracking = env_tracking(&options.fetch);
+            let output = quote! {{
+                #tracking
+                include_str!(#path_str)
+            }};
+            output.into()
+        }
+        Err(err) => compile_error(err),
+    }
+}
+
+/// A procedural macro that includes content from a URL as a static byte array at compile
+/// time.
+///
+/// # Usage
+///
+/// ```rust
+/// use include_url_macro::include_url_bytes;
+///
+/// const FAVICON: &[u8] =
+///     include_url_bytes!("https://www.rust-lang.org/static/images/favicon.ico");
+/// ```
+///
+/// See the [crate level documentation](crate#options) for the supported options.
+#[proc_macro]
+pub fn include_url_bytes(input: TokenStream) -> TokenStream {
+    let UrlInput { url, mut options } = parse_macro_input!(input as UrlInput);
+    if skip_requested(&options.skip_under) {
+        return match options.chunk_size {
+            Some(_) => quote! { &[] as &'static [&'static [u8]] },
+            None => quote! { b"" },
+        }
+        .into();
+    }
+    let url_str = url.value();
+    let compress_kind = match options.compress_kind(CompressKind::None) {
+        Ok(compress_kind) => compress_kind,
+        Err(err) => return compile_error(err),
+    };
+
+    match cached_url_content(&url_str, compress_kind, &mut options.fetch)
+        .and_then(|path| embed_bytes(&path, options.chunk_size))
+    {
+        Ok(bytes) => {
+            let tracking = env_tracking(&options.fetch);
+            let output = quote! {{
+                #tracking
+                #bytes
+            }};
+            output.into()
+        }
+        Err(err) => compile_error(err),
+    }
+}
+
+#[cfg(feature = "brotli")]
+#[proc_macro]
+pub fn include_url_bytes_with_brotli(input: TokenStream) -> TokenStream {
+    let UrlInput { url, mut options } = parse_macro_input!(input as UrlInput);
+    if skip_requested(&options.skip_under) {
+        return match options.chunk_size {
+            Some(_) => quote! { &[] as &'static [&'static [u8]] },
+            None => quote! { b"" },
+        }
+        .into();
+    }
+    let url_str = url.value();
+    let compress_kind = match options.compress_kind(CompressKind::Brotli) {
+        Ok(compress_kind) => compress_kind,
+        Err(err) => return compile_error(err),
+    };
+
+    match cached_url_content(&url_str, compress_kind, &mut options.fetch)
+        .and_then(|path| embed_bytes(&path, options.chunk_size))
+    {
+        Ok(bytes) => {
+            let tracking = env_tracking(&options.fetch);
+            let output = quote! {{
+                #tracking
+                #bytes
+            }};
+            output.into()
+        }
+        Err(err) => compile_error(err),
+    }
+}
+
+/// Parser for the input of `include_urls!`.
+///
+/// Handles a list of URLs, or `manifest = "path"` naming a file that lists them, followed
+/// by `bytes`, `concurrency` and the shared options.
+struct UrlListInput {
+    urls: Vec<String>,
+    manifest: Option<LitStr>,
+    bytes: bool,
+    concurrency: Option<usize>,
+    options: MacroOptions,
+}
+
+impl Parse for UrlListInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut urls = Vec::new();
+        let mut manifest = None;
+        if input.peek(syn::token::Bracket) {
+            urls = string_list(&input.parse()?)?;
+        } else {
+            let key = parse_option_key(input)?;
+            if key != "manifest" {
+                return Err(syn::Error::new(
+                    key.span(),
+                    "Expected a list of URLs or `manifest = \"...\"`",
+                ));
+            }
+            manifest = Some(input.parse()?);
+        }
+
+        let mut bytes = false;
+        let mut concurrency = None;
+        let mut options = MacroOptions::default();
+        while input.peek(Token![,]) {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            let key = parse_option_key(input)?;
+            match key.to_string().as_str() {
+                "bytes" => bytes = input.parse::<LitBool>()?.value,
+                "concurrency" => {
+                    let value: LitInt = input.parse()?;
+                    match value.base10_parse()? {
+                        0 => return Err(syn::Error::new(value.span(), "Expected at least 1")),
+                        value => concurrency = Some(value),
+                    }
+                }
+                _ => options.parse_option(&key, input)?,
+            }
+        }
+
+        Ok(UrlListInput {
+            urls,
+            manifest,
+            bytes,
+            concurrency,
+            options,
+        })
+    }
+}
+
+/// Reads the URLs listed by a manifest file, one per line, skipping blank lines and `#`
+/// comments.
+fn manifest_urls(path: &std::path::Path) -> Result<Vec<String>, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read `{}`: {}", path.display(), e))?;
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Fetches every URL into the cache on up to `concurrency` threads, returning the cache
+/// files in the order of the URLs along with the options recording the environment read
+/// by all fetches. Every failure is reported, not only the first.
+fn cache_all(
+    urls: &[String],
+    compress_kind: &CompressKind,
+    options: &FetchOptions,
+    concurrency: usize,
+) -> Result<(Vec<std::path::PathBuf>, FetchOptions), String> {
+    // A URL listed twice is fetched once, as fetches of the same URL share their files
+    let mut unique: Vec<&String> = Vec::new();
+    for url in urls {
+        if !unique.contains(&url) {
+            unique.push(url);
+        }
+    }
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<_> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..concurrency.min(unique.len()))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut results = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(url) = unique.get(index) else {
+                            return results;
+                        };
+                        let mut fetch = options.clone();
+                        let result = cached_url_content(url, compress_kind.clone(), &mut fetch);
+                        results.push((index, result, fetch));
+                    }
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| {
+                worker
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+            .collect()
+    });
+    results.sort_by_key(|(index, _, _)| *index);
+
+    let mut tracked = options.clone();
+    let mut paths = Vec::new();
+    let mut errors = Vec::new();
+    for ((_, result, fetch), url) in results.into_iter().zip(&unique) {
+        for recorded in fetch.env {
+            if !tracked.env.contains(&recorded) {
+                tracked.env.push(recorded);
+            }
+        }
+        for file in fetch.files {
+            if !tracked.files.contains(&file) {
+                tracked.files.push(file);
+            }
+        }
+        tracked.warnings.extend(fetch.warnings);
+        match result {
+            Ok(path) => paths.push(path),
+            Err(err) => errors.push(format!("`{}`: {}", url, err)),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(format!(
+            "Failed to fetch {} of {} URLs:\n{}",
+            errors.len(),
+            unique.len(),
+            errors.join("\n")
+        ));
+    }
+    let paths = urls
+        .iter()
+        .map(|url| {
+            let index = unique.iter().position(|unique| *unique == url);
+            paths[index.expect("every URL is fetched")].clone()
+        })
+        .collect();
+    Ok((paths, tracked))
+}
+
+/// A procedural macro that includes the content of several URLs as an array, fetching
+/// them concurrently.
+///
+/// The URLs are given as a list, or listed one per line in a file named by
+/// `manifest = "path"`, relative to the manifest directory of the crate, where blank lines
+/// and `#` comments are skipped. The array holds `&'static str` content, or
+/// `&'static [u8]` with `bytes = true`, in the order of the URLs. Up to 8 URLs are fetched
+/// at a time, or the number given by `concurrency = N`, and every URL that fails is
+/// reported.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_urls;
+///
+/// const LICENSES: [&str; 2] = include_urls!([
+///     "https://www.apache.org/licenses/LICENSE-2.0.txt",
+///     "https://opensource.org/license/mit",
+/// ]);
+/// const ICONS: [&[u8]; 30] = include_urls!(manifest = "assets/icons.txt", bytes = true);
+/// ```
+///
+/// See the [crate level documentation](crate#options) for the other supported options,
+/// which apply to every URL.
+#[proc_macro]
+pub fn include_urls(input: TokenStream) -> TokenStream {
+    let UrlListInput {
+        mut urls,
+        manifest,
+        bytes,
+        concurrency,
+        options,
+    } = parse_macro_input!(input as UrlListInput);
+    let placeholder = if bytes && options.chunk_size.is_some() {
+        quote! { &[] as &'static [&'static [u8]] }
+    } else if bytes {
+        quote! { b"" }
+    } else {
+        quote! { "" }
+    };
+
+    let mut manifest_path = None;
+    let result = (|| {
+        if let Some(manifest) = &manifest {
+            let dir = env::var("CARGO_MANIFEST_DIR").unwrap_or_else(|_| ".".into());
+            let path = std::path::Path::new(&dir).join(manifest.value());
+            urls = manifest_urls(&path)?;
+            manifest_path = Some(path.display().to_string());
+        }
+        if skip_requested(&options.skip_under) {
+            let placeholders = urls.iter().map(|_| &placeholder);
+            return Ok(quote! { [#(#placeholders),*] });
+        }
+        let compress_kind = match bytes {
+            true => options.compress_kind(CompressKind::None)?,
+            false => {
+                reject_bytes_options(&options, "include_urls")?;
+                CompressKind::None
+            }
+        };
+        let fetch = FetchOptions {
+            text: !bytes,
+            ..options.fetch.clone()
+        };
+        let concurrency = concurrency.unwrap_or(DEFAULT_CONCURRENCY);
+        let (paths, fetch) = cache_all(&urls, &compress_kind, &fetch, concurrency)?;
+        let contents = paths
+            .iter()
+            .map(|path| match bytes {
+                true => embed_bytes(path, options.chunk_size),
+                false => {
+                    let path = path.display().to_string();
+                    Ok(quote! { include_str!(#path) })
+                }
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        let tracking = env_tracking(&fetch);
+        Ok::<_, String>(quote! {{
+            #tracking
+            [#(#contents),*]
+        }})
+    })();
+
+    match result {
+        Ok(array) => {
+            // The manifest is included so that editing it re-expands the macro
+            let manifest = manifest_path.iter();
+            let output = quote! {{
+                #(const _: &[u8] = include_bytes!(#manifest);)*
+                #array
+            }};
+            output.into()
+        }
+        Err(err) => compile_error(err),
+    }
+}
+
+/// Parser for the input of the macros that place a fetched file on disk.
+///
+/// Handles the URL followed by an optional `file_name = "..."` and the shared options.
+struct PathUrlInput {
+    url: LitStr,
+    file_name: Option<LitStr>,
+    options: MacroOptions,
+}
+
+impl Parse for PathUrlInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let url = input.parse()?;
+        let mut file_name = None;
+        let mut options = MacroOptions::default();
+
+        while input.peek(Token![,]) {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            let key = parse_option_key(input)?;
+            if key == "file_name" {
+                file_name = Some(input.parse()?);
+            } else {
+                options.parse_option(&key, input)?;
+            }
+        }
+
+        Ok(PathUrlInput {
+            url,
+            file_name,
+            options,
+        })
+    }
+}
+
+/// Fetches a URL through the cache and exposes it under a deterministic path ending in
+/// `file_name`, defaulting to the last segment of the URL.
+fn installed_file(
+    url_str: &str,
+    file_name: Option<&str>,
+    options: &mut MacroOptions,
+) -> Result<std::path::PathBuf, String> {
+    reject_bytes_options(options, "include_url_path")?;
+    let cache_file = cached_url_content(url_str, CompressKind::None, &mut options.fetch)?;
+    let file_name = match file_name {
+        Some(file_name) => file_name.to_string(),
+        None => Url::parse(url_str)
+            .ok()
+            .and_then(|url| {
+                url.path_segments()
+                    .and_then(|mut segments| segments.next_back().map(str::to_string))
+            })
+            .filter(|name| !name.is_empty())
+            .ok_or("Cannot derive a file name from the URL, use `file_name = \"...\"`")?,
+    };
+    if file_name.contains(['/', '\\']) || file_name == ".." {
+        return Err(format!("Invalid file name `{}`", file_name));
+    }
+
+    let dir = std::path::PathBuf::from(format!("{}.files", cache_file.display()));
+    let path = dir.join(file_name);
+    // The cached content is replaced when it is refreshed, the placed file follows it
+    let modified = |path: &std::path::Path| {
+        std::fs::metadata(path)
+            .and_then(|meta| meta.modified())
+            .ok()
+    };
+    if modified(&path).is_none_or(|placed| modified(&cache_file) > Some(placed)) {
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create install directory: {}", e))?;
+        std::fs::copy(&cache_file, &path)
+            .map_err(|e| format!("Failed to place fetched file: {}", e))?;
+    }
+    Ok(path)
+}
+
+/// A procedural macro that fetches a URL without embedding it, expanding to the
+/// `&'static str` path of the fetched file instead.
+///
+/// This is meant for assets too large to live in the binary that should still go through
+/// the same fetch, verification and caching pipeline. The file is placed at a
+/// deterministic location ending in its file name; use [`install_url!`] in the build
+/// script to copy it next to the binary.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_url_path;
+///
+/// const MODEL_PATH: &str = include_url_path!("https://example.com/models/model.bin");
+/// ```
+///
+/// Besides the [shared options](crate#options), `file_name = "..."` overrides the file
+/// name taken from the last segment of the URL.
+#[proc_macro]
+pub fn include_url_path(input: TokenStream) -> TokenStream {
+    let PathUrlInput {
+        url,
+        file_name,
+        mut options,
+    } = parse_macro_input!(input as PathUrlInput);
+    if skip_requested(&options.skip_under) {
+        return quote! { "" }.into();
+    }
+    let file_name = file_name.map(|file_name| file_name.value());
+
+    match installed_file(&url.value(), file_name.as_deref(), &mut options) {
+        Ok(path) => {
+            let path_str = path.display().to_string();
+            let tracking = env_tracking(&options.fetch);
+            let output = quote! {{
+                #tracking
+                #path_str
+            }};
+            output.into()
+        }
+        Err(err) => compile_error(err),
+    }
+}
+
+/// A procedural macro for build scripts that fetches a URL and copies the file next to
+/// the compiled binaries, expanding to the `std::path::PathBuf` it was installed to.
+///
+/// The target directory is derived from `OUT_DIR` when the build script runs. Fetching
+/// uses the same cache as [`include_url_path!`] in the crate itself.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// // build.rs
+/// fn main() {
+///     include_url_macro::install_url!("https://example.com/models/model.bin");
+/// }
+/// ```
+#[proc_macro]
+pub fn install_url(input: TokenStream) -> TokenStream {
+    let PathUrlInput {
+        url,
+        file_name,
+        mut options,
+    } = parse_macro_input!(input as PathUrlInput);
+    if skip_requested(&options.skip_under) {
+        return quote! { ::std::path::PathBuf::new() }.into();
+    }
+    let file_name = file_name.map(|file_name| file_name.value());
+
+    match installed_file(&url.value(), file_name.as_deref(), &mut options) {
+        Ok(path) => {
+            let path_str = path.display().to_string();
+            let file_name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let tracking = env_tracking(&options.fetch);
+            let output = quote! {{
+                #tracking
+                let out_dir = ::std::env::var("OUT_DIR").expect("OUT_DIR not set");
+                // OUT_DIR is `<target>/<profile>/build/<package>-<hash>/out`
+                let target_dir = ::std::path::Path::new(&out_dir)
+                    .ancestors()
+                    .nth(3)
+                    .expect("Unexpected OUT_DIR layout");
+                let installed = target_dir.join(#file_name);
+                ::std::fs::copy(#path_str, &installed).expect("Failed to install fetched file");
+                ::std::println!("cargo:rerun-if-changed={}", #path_str);
+                installed
+            }};
+            output.into()
+        }
+        Err(err) => compile_error(err),
+    }
+}
+
+/// Parser for the `report_url_inclusions` macro's input.
+///
+/// Handles an optional `details = true`.
+struct ReportInput {
+    details: bool,
+}
+
+impl Parse for ReportInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut details = false;
+        while !input.is_empty() {
+            let key = parse_option_key(input)?;
+            match key.to_string().as_str() {
+                "details" => details = input.parse::<LitBool>()?.value,
+                _ => {
+                    return Err(syn::Error::new(
+                        key.span(),
+                        format!("Unknown option `{}`, expected `details`", key),
+                    ))
+                }
+            }
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+        Ok(ReportInput { details })
+    }
+}
+
+/// A procedural macro that prints a summary of the remote content included by the target
+/// being compiled, expanding to nothing.
+///
+/// The summary is made of the inclusions recorded so far by the compilation, so the macro
+/// goes after every other item of the crate root, where the compiler expands it last: the
+/// number of URLs, the included bytes, the cache hit rate, the time spent fetching and every
+/// stale entry are printed. With `details = true`, every URL is listed with its cache
+/// status, size and fetch time, the slowest first.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// // last item of src/main.rs or src/lib.rs
+/// include_url_macro::report_url_inclusions!(details = true);
+/// ```
+#[proc_macro]
+pub fn report_url_inclusions(input: TokenStream) -> TokenStream {
+    let ReportInput { details } = parse_macro_input!(input as ReportInput);
+    let report = std::fs::read_to_string(report::report_path()).unwrap_or_default();
+    let summary = report::Summary::parse(&report, std::process::id());
+    if !summary.entries.is_empty() {
+        for line in summary.lines(details) {
+            eprintln!("warning: {}", line);
+        }
+    }
+    TokenStream::new()
+}
+
+/// A procedural macro that includes an artifact of an OCI registry at compile time,
+/// expanding to the bytes of one of its layers as a `&'static [u8]`.
+///
+/// The manifest of the reference is resolved, the layer of the given media type is picked
+/// out of it, and its blob is fetched and checked against the digest in the manifest.
+/// References are `oci://registry/repository:tag`, `latest` when the tag is left out, or
+/// `oci://registry/repository@sha256:...` to pin a manifest. Registries asking for a token
+/// are given an anonymous pull token, or one obtained with `auth = basic_env(...)`.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_oci_url;
+///
+/// const SCHEMA: &[u8] = include_oci_url!(
+///     "oci://ghcr.io/acme/schemas:v3",
+///     media_type = "application/schema+json",
+/// );
+/// ```
+///
+/// # Options
+///
+/// Besides the [options shared by every macro](crate#options):
+///
+/// * `media_type = "..."` - Media type of the layer to embed, required unless the artifact
+///   has a single layer.
+///
+/// When skipped through `skip_under`, the macro expands to an empty slice.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The reference is invalid or the manifest cannot be fetched
+/// * The reference is an image index rather than a manifest
+/// * No layer, or several, have the media type
+/// * The blob cannot be fetched or does not match its digest
+#[proc_macro]
+pub fn include_oci_url(input: TokenStream) -> TokenStream {
+    let parser = |input: ParseStream| UrlInput::parse_with(input, &["media_type"]);
+    let (UrlInput { url, mut options }, extra) = parse_macro_input!(input with parser);
+    let media_type = match extra
+        .last()
+        .map(|(_, value)| syn::parse2::<LitStr>(quote! { #value }))
+        .transpose()
+    {
+        Ok(media_type) => media_type.map(|media_type| media_type.value()),
+        Err(err) => return err.to_compile_error().into(),
+    };
+    if skip_requested(&options.skip_under) {
+        return quote! { b"" }.into();
+    }
+
+    let bytes = reject_bytes_options(&options, "include_oci_url")
+        .and_then(|_| oci::fetch_blob(&url.value(), media_type.as_deref(), &mut options.fetch))
+        .and_then(|path| embed_bytes(&path, None));
+    match bytes {
+        Ok(bytes) => {
+            let tracking = env_tracking(&options.fetch);
+            quote! {{
+                #tracking
+                #bytes
+            }}
+            .into()
+        }
+        Err(err) => compile_error(err),
+    }
+}
+
+/// A procedural macro that includes a file or directory of a git repository at a revision
+/// at compile time, for git hosts without raw file URLs.
+///
+/// A file expands to its bytes as a `&'static [u8]`, and a directory to the
+/// `&'static [(&'static str, &'static [u8])]` of every file below it, with their paths
+/// relative to the directory, sorted. Only the commit of the revision is fetched, by the
+/// `git` command, which has to be installed. The checked out path is cached until a refresh
+/// is requested, so `rev` should be a commit or a tag rather than a branch.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_git_url;
+///
+/// const SCHEMA: &[u8] = include_git_url!(
+///     "https://git.example.com/acme/schemas.git",
+///     rev = "3f2a9c1",
+///     path = "schema/v1.json",
+/// );
+/// const MIGRATIONS: &[(&str, &[u8])] = include_git_url!(
+///     "https://git.example.com/acme/db.git",
+///     rev = "v2.4.0",
+///     path = "migrations",
+/// );
+/// ```
+///
+/// # Options
+///
+/// * `rev = "..."` - Commit, tag or branch to check out, required.
+/// * `path = "..."` - File or directory to embed, relative to the root of the repository,
+///   required.
+/// * `auth = bearer_env("TOKEN")` or `auth = basic_env("USER", "PASSWORD")` - Credentials
+///   of a private repository over HTTPS, sent in the `Authorization` header.
+///
+/// When skipped through `skip_under`, the macro expands to an empty slice.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * `git` is not installed or the repository cannot be fetched
+/// * The revision or the path does not exist
+#[proc_macro]
+pub fn include_git_url(input: TokenStream) -> TokenStream {
+    let parser = |input: ParseStream| UrlInput::parse_with(input, &["rev", "path"]);
+    let (UrlInput { url, mut options }, extra) = parse_macro_input!(input with parser);
+    let mut rev = None;
+    let mut path = None;
+    for (key, value) in &extra {
+        match syn::parse2::<LitStr>(quote! { #value }) {
+            Ok(value) if key == "rev" => rev = Some(value.value()),
+            Ok(value) => path = Some(value.value()),
+            Err(err) => return err.to_compile_error().into(),
+        }
+    }
+    let (Some(rev), Some(path)) = (rev, path) else {
+        return compile_error("Expected `rev = \"...\"` and `path = \"...\"`");
+    };
+    if skip_requested(&options.skip_under) {
+        return quote! { &[] }.into();
+    }
+
+    let checked_out = reject_bytes_options(&options, "include_git_url")
+        .and_then(|_| git::checkout(&url.value(), &rev, &path, &mut options.fetch));
+    match checked_out {
+        Ok(checked_out) => {
+            let tracking = env_tracking(&options.fetch);
+            let content = embed_tree(&checked_out);
+            quote! {{
+                #tracking
+                #content
+            }}
+            .into()
+        }
+        Err(err) => compile_error(err),
+    }
+}
+
+/// A procedural macro that includes an asset of a GitHub release at compile time,
+/// expanding to its bytes as a `&'static [u8]`.
+///
+/// The release of `tag` of the `owner/repo` repository is looked up through the releases
+/// API, and its asset named `asset` is downloaded into the cache. The tag `latest` is
+/// resolved to the tag of the latest release once, and pinned in the
+/// [lockfile](crate#lockfile) so that later builds embed the same asset.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_github_release_asset;
+///
+/// const CLI: &[u8] = include_github_release_asset!(
+///     "acme/tool",
+///     tag = "v1.4.2",
+///     asset = "tool-x86_64-unknown-linux-musl.tar.gz",
+/// );
+/// const RULES: &[u8] = include_github_release_asset!("acme/rules", asset = "rules.json");
+/// ```
+///
+/// # Options
+///
+/// Besides the [options shared by every macro](crate#options):
+///
+/// * `asset = "..."` - Name of the asset to embed, required.
+/// * `tag = "..."` - Tag of the release, `latest` by default.
+///
+/// With `auth = bearer_env("GITHUB_TOKEN")`, the release and the asset are requested
+/// through the API with the token, as private repositories need. When skipped through
+/// `skip_under`, the macro expands to an empty slice.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The repository has no release of the tag, or no release at all for `latest`
+/// * The release has no asset of the name, the error lists its assets
+/// * The asset cannot be downloaded
+#[proc_macro]
+pub fn include_github_release_asset(input: TokenStream) -> TokenStream {
+    let parser = |input: ParseStream| UrlInput::parse_with(input, &["tag", "asset"]);
+    let (UrlInput { url, mut options }, extra) = parse_macro_input!(input with parser);
+    let mut tag = "latest".to_string();
+    let mut asset = None;
+    for (key, value) in &extra {
+        match syn::parse2::<LitStr>(quote! { #value }) {
+            Ok(value) if key == "tag" => tag = value.value(),
+            Ok(value) => asset = Some(value.value()),
+            Err(err) => return err.to_compile_error().into(),
+        }
+    }
+    let Some(asset) = asset else {
+        return compile_error("Expected `asset = \"...\"`");
+    };
+    if skip_requested(&options.skip_under) {
+        return quote! { b"" }.into();
+    }
+
+    let bytes = reject_bytes_options(&options, "include_github_release_asset")
+        .and_then(|_| github::release_asset(&url.value(), &tag, &asset, &mut options.fetch))
+        .and_then(|path| embed_bytes(&path, None));
+    match bytes {
+        Ok(bytes) => {
+            let tracking = env_tracking(&options.fetch);
+            quote! {{
+                #tracking
+                #bytes
+            }}
+            .into()
+        }
+        Err(err) => compile_error(err),
+    }
+}
+
+/// A procedural macro that includes a file or directory of a package published on
+/// crates.io at compile time, such as a grammar or data file that a crate ships without
+/// exposing it (`archive` feature).
+///
+/// A file expands to its bytes as a `&'static [u8]`, and a directory to the
+/// `&'static [(&'static str, &'static [u8])]` of every file below it, with their paths
+/// relative to the directory, sorted. The `.crate` archive of the version is downloaded
+/// through the cache, checked against the checksum of the crates.io index unless a
+/// `sha256` is given, and unpacked once.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_crate_file;
+///
+/// const GRAMMAR: &[u8] = include_crate_file!(
+///     "pest_meta",
+///     version = "2.7.14",
+///     path = "src/grammar.pest",
+/// );
+/// ```
+///
+/// # Options
+///
+/// Besides the [options shared by every macro](crate#options):
+///
+/// * `version = "..."` - Exact version of the crate, required.
+/// * `path = "..."` - File or directory to embed, relative to the root of the package, or
+///   a glob pattern of the files to embed as a slice, required.
+///
+/// When skipped through `skip_under`, the macro expands to an empty slice.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The crate has no such version
+/// * The archive cannot be downloaded or does not match its checksum
+/// * The package has no such path, or no file matching the pattern
+#[cfg(feature = "archive")]
+#[proc_macro]
+pub fn include_crate_file(input: TokenStream) -> TokenStream {
+    let parser = |input: ParseStream| UrlInput::parse_with(input, &["version", "path"]);
+    let (UrlInput { url, mut options }, extra) = parse_macro_input!(input with parser);
+    let mut version = None;
+    let mut path = None;
+    for (key, value) in &extra {
+        match syn::parse2::<LitStr>(quote! { #value }) {
+            Ok(value) if key == "version" => version = Some(value.value()),
+            Ok(value) => path = Some(value.value()),
+            Err(err) => return err.to_compile_error().into(),
+        }
+    }
+    let (Some(version), Some(path)) = (version, path) else {
+        return compile_error("Expected `version = \"...\"` and `path = \"...\"`");
+    };
+    if skip_requested(&options.skip_under) {
+        return quote! { &[] }.into();
+    }
+
+    let found = reject_bytes_options(&options, "include_crate_file")
+        .and_then(|_| crates_io::package_path(&url.value(), &version, &path, &mut options.fetch));
+    match found {
+        Ok(found) => {
+            let tracking = env_tracking(&options.fetch);
+            let content = embed_selection(found);
+            quote! {{
+                #tracking
+                #content
+            }}
+            .into()
+        }
+        Err(err) => compile_error(err),
+    }
+}
+
+/// A procedural macro that includes files of a remote zip archive at compile time, without
+/// embedding the whole archive (`archive` feature).
+///
+/// A file expands to its bytes as a `&'static [u8]`, and a directory, or a glob pattern
+/// such as `icons/*.svg`, to the `&'static [(&'static str, &'static [u8])]` of the files
+/// it selects, sorted by path. In patterns, `*` does not match across directories and `**`
+/// does. The archive is fetched through the cache and unpacked once, and the unpacked
+/// files are reused until the archive is fetched again.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_zip_url;
+///
+/// const INTER: &[u8] = include_zip_url!(
+///     "https://example.com/assets.zip",
+///     path = "fonts/Inter.ttf",
+/// );
+/// const ICONS: &[(&str, &[u8])] = include_zip_url!(
+///     "https://example.com/assets.zip",
+///     path = "icons/**/*.svg",
+/// );
+/// ```
+///
+/// # Options
+///
+/// Besides the [options shared by every macro](crate#options), where `sha256` applies to
+/// the archive:
+///
+/// * `path = "..."` - File or directory to embed, relative to the root of the archive, or
+///   a glob pattern of the files to embed, required.
+///
+/// When skipped through `skip_under`, the macro expands to an empty slice.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The archive cannot be fetched or is not a valid zip archive
+/// * An entry of the archive leaves it, such as `../file`
+/// * The archive has no such path, or no file matching the pattern
+#[cfg(feature = "archive")]
+#[proc_macro]
+pub fn include_zip_url(input: TokenStream) -> TokenStream {
+    include_archive_url(input, archive::Format::Zip, "include_zip_url")
+}
+
+/// A procedural macro that includes files of a remote tar archive at compile time, without
+/// embedding the whole archive (`archive` feature).
+///
+/// Archives may be uncompressed, gzip compressed (`.tar.gz`, `.tgz`) or zstd compressed
+/// (`.tar.zst`, with the `zstd` feature), as told by their content. A file expands to its
+/// bytes as a `&'static [u8]`, and a directory, or a glob pattern such as `data/*.csv`, to
+/// the `&'static [(&'static str, &'static [u8])]` of the files it selects, sorted by path.
+/// In patterns, `*` does not match across directories and `**` does. The archive is
+/// fetched through the cache and unpacked once, and the unpacked files are reused until
+/// the archive is fetched again.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_tar_url;
+///
+/// const LICENSE: &[u8] = include_tar_url!(
+///     "https://example.com/release-1.2.0.tar.gz",
+///     path = "release-1.2.0/LICENSE",
+/// );
+/// const TABLES: &[(&str, &[u8])] = include_tar_url!(
+///     "https://example.com/tables.tar.zst",
+///     path = "tables/*.csv",
+/// );
+/// ```
+///
+/// # Options
+///
+/// Besides the [options shared by every macro](crate#options), where `sha256` applies to
+/// the archive:
+///
+/// * `path = "..."` - File or directory to embed, relative to the root of the archive, or
+///   a glob pattern of the files to embed, required.
+///
+/// When skipped through `skip_under`, the macro expands to an empty slice.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The archive cannot be fetched, is not a valid tar archive, or is zstd compressed
+///   without the `zstd` feature
+/// * An entry of the archive leaves it, such as `../file`
+/// * The archive has no such path, or no file matching the pattern
+#[cfg(feature = "archive")]
+#[proc_macro]
+pub fn include_tar_url(input: TokenStream) -> TokenStream {
+    include_archive_url(input, archive::Format::Tar, "include_tar_url")
+}
+
+/// Expands the archive macros, which select files of a fetched archive.
+#[cfg(feature = "archive")]
+fn include_archive_url(
+    input: TokenStream,
+    format: archive::Format,
+    macro_name: &str,
+) -> TokenStream {
+    let parser = |input: ParseStream| UrlInput::parse_with(input, &["path"]);
+    let (UrlInput { url, mut options }, extra) = parse_macro_input!(input with parser);
+    let path = match extra
+        .last()
+        .map(|(_, value)| syn::parse2::<LitStr>(quote! { #value }))
+        .transpose()
+    {
+        Ok(Some(path)) => path.value(),
+        Ok(None) => return compile_error("Expected `path = \"...\"`"),
+        Err(err) => return err.to_compile_error().into(),
+    };
+    if skip_requested(&options.skip_under) {
+        return quote! { &[] }.into();
+    }
+
+    let url = url.value();
+    let selected = reject_bytes_options(&options, macro_name)
+        .and_then(|_| cached_url_content(&url, CompressKind::None, &mut options.fetch))
+        .and_then(|cached| archive::unpack(&cached, format))
+        .and_then(|unpacked| archive::select(&unpacked, &path, &url));
+    match selected {
+        Ok(selected) => {
+            let tracking = env_tracking(&options.fetch);
+            let content = embed_selection(selected);
+            quote! {{
+                #tracking
+                #content
+            }}
+            .into()
+        }
+        Err(err) => compile_error(err),
+    }
+}
+
+/// A procedural macro that lists a remote directory at compile time and embeds its files
+/// into a module with a static `Dir`, whose files are looked up by path.
+///
+/// The directory is listed with the trees API for `gh:owner/repo@ref/dir`, with the
+/// `ListObjectsV2` API for public S3 buckets, `https://bucket.s3.amazonaws.com/prefix/`,
+/// or else by following the links of its HTML index page into its subdirectories. Every
+/// listed file, or every file matching `glob`, is fetched through the cache. The module
+/// holds the `File` and `Dir` types and the `DIR` static, whose `get` method looks a file
+/// up by its path relative to the directory.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_url_dir;
+///
+/// include_url_dir!(pub mod icons, "https://example.com/assets/icons/", glob = "**/*.svg");
+///
+/// let logo = icons::DIR.get("brand/logo.svg").unwrap().contents();
+/// for file in icons::DIR.files_in("ui") {
+///     println!("{} ({} bytes)", file.path(), file.contents().len());
+/// }
+/// ```
+///
+/// # Options
+///
+/// Besides the [options shared by every macro](crate#options), which apply to the
+/// listings and every file, except `sha256` and `skip_under`:
+///
+/// * `glob = "..."` - Pattern of the paths of the files to embed, where `*` does not match
+///   across directories and `**` does. Every file by default.
+/// * `listing = "github"`, `"s3"` or `"html"` - How the directory is listed, for S3
+///   compatible servers under another host. Told from the URL by default.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The directory cannot be listed
+/// * No file is listed, or none matches the pattern
+/// * One of the files cannot be fetched, every failure being reported
+#[proc_macro]
+pub fn include_url_dir(input: TokenStream) -> TokenStream {
+    let parser = ModuleUrlInput::parser(&["glob", "listing"]);
+    let (
+        ModuleUrlInput {
+            vis,
+            name,
+            input: UrlInput { url, mut options },
+        },
+        extra,
+    ) = parse_macro_input!(input with parser);
+    let mut pattern = None;
+    let mut listing = None;
+    for (key, value) in &extra {
+        let parsed = syn::parse2::<LitStr>(quote! { #value }).and_then(|value| {
+            match key.to_string().as_str() {
+                "glob" => pattern = Some(value.value()),
+                _ => {
+                    let parsed = dir::Listing::parse(&value.value());
+                    listing = Some(parsed.map_err(|err| syn::Error::new(value.span(), err))?);
+                }
+            }
+            Ok(())
+        });
+        if let Err(err) = parsed {
+            return err.to_compile_error().into();
+        }
+    }
+
+    let unsupported = if !options.skip_under.is_empty() {
+        Some("skip_under")
+    } else if options.fetch.sha256.is_some() {
+        Some("sha256")
+    } else if options.fetch.integrity.is_some() {
+        Some("integrity")
+    } else if options.fetch.signature_url.is_some() {
+        Some("signature_url")
+    } else {
+        None
+    };
+    let files = reject_bytes_options(&options, "include_url_dir")
+        .and_then(|_| match unsupported {
+            Some(option) => Err(format!(
+                "`{}` is not supported by `include_url_dir!`",
+                option
+            )),
+            None => Ok(()),
+        })
+        .and_then(|_| {
+            let url = url.value();
+            let mut files = dir::list(&url, listing, &mut options.fetch)?;
+            if let Some(pattern) = &pattern {
+                let matches = dir::matcher(pattern)?;
+                files.retain(|(path, _)| matches(path));
+            }
+            if files.is_empty() {
+                return Err(match &pattern {
+                    Some(pattern) => format!("No file of `{}` matches `{}`", url, pattern),
+                    None => format!("No file is listed in `{}`", url),
+                });
+            }
+            let urls: Vec<_> = files.iter().map(|(_, url)| url.clone()).collect();
+            let (paths, fetch) = cache_all(
+                &urls,
+                &CompressKind::None,
+                &options.fetch,
+                DEFAULT_CONCURRENCY,
+            )?;
+            options.fetch = fetch;
+            let names = files.into_iter().map(|(path, _)| path);
+            Ok(names.zip(paths).collect::<Vec<_>>())
+        });
+
+    match files {
+        Ok(files) => {
+            let tracking = env_tracking(&options.fetch);
+            let items = dir::generate(&files);
+            quote! {
+                #vis mod #name {
+                    #tracking
+                    #items
+                }
+            }
+            .into()
+        }
+        Err(err) => compile_error(err),
+    }
+}
+
+/// A procedural macro that fetches the pages listed by a sitemap at compile time and embeds
+/// them into a module as a static map keyed by the path of their URL.
+///
+/// The `<loc>` entries of the sitemap are read, following the nested sitemaps of a sitemap
+/// index, and the pages whose path matches `glob` and whose URL matches `regex` are fetched
+/// through the cache, up to 8 at a time. Each page may be reduced to the elements matching
+/// a CSS selector, or to their text, as by [`include_html_url!`], or minified. The module
+/// holds the `PAGES` constant, sorted by path, and the `get` function looking a page up.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_sitemap_url;
+///
+/// include_sitemap_url!(
+///     pub mod guide,
+///     "https://docs.example.com/sitemap.xml",
+///     glob = "/guide/**",
+///     select = "main",
+/// );
+///
+/// let install = guide::get("/guide/install").unwrap();
+/// for (path, page) in guide::PAGES {
+///     println!("{}: {} bytes", path, page.len());
+/// }
+/// ```
+///
+/// # Options
+///
+/// Besides the [options shared by every macro](crate#options), which apply to the
+/// sitemaps and every page, except `sha256` and `skip_under`:
+///
+/// * `glob = "/guide/**"` - Pattern of the paths of the pages to embed, where `*` does not
+///   match across directories and `**` does.
+/// * `regex = "..."` - Regular expression the whole URL of the pages to embed must match.
+/// * `select = "main"` - Embed the elements of each page matching a CSS selector, one per
+///   line, leaving out the pages without any. Requires the `html` feature.
+/// * `text = true` - Embed the text content of the selected elements, or of the body of
+///   the pages, instead of their HTML. Requires the `html` feature.
+/// * `minify = true` - Collapse the whitespace of the embedded HTML and drop its comments,
+///   leaving `pre`, `textarea`, `script` and `style` elements as they are.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The sitemap cannot be fetched, or is neither a sitemap nor a sitemap index
+/// * No page is listed, or none matches the filters
+/// * One of the pages cannot be fetched, every failure being reported
+#[proc_macro]
+pub fn include_sitemap_url(input: TokenStream) -> TokenStream {
+    let parser = ModuleUrlInput::parser(&["glob", "regex", "select", "text", "minify"]);
+    let (
+        ModuleUrlInput {
+            vis,
+            name,
+            input: UrlInput { url, mut options },
+        },
+        extra,
+    ) = parse_macro_input!(input with parser);
+    let mut pattern = None;
+    let mut regex = None;
+    #[cfg(feature = "html")]
+    let mut selector = None;
+    #[cfg(feature = "html")]
+    let mut text = false;
+    let mut minify = false;
+    for (key, value) in &extra {
+        let parsed = match key.to_string().as_str() {
+            "glob" => syn::parse2(quote! { #value }).map(|value: LitStr| {
+                pattern = Some(value.value());
+            }),
+            "regex" => syn::parse2(quote! { #value }).and_then(|value: LitStr| {
+                regex::Regex::new(&value.value())
+                    .map(|parsed| regex = Some(parsed))
+                    .map_err(|err| syn::Error::new(value.span(), err))
+            }),
+            #[cfg(feature = "html")]
+            "select" => syn::parse2(quote! { #value }).and_then(|value: LitStr| {
+                formats::css_selector(&value.value())
+                    .map(|parsed| selector = Some(parsed))
+                    .map_err(|err| syn::Error::new(value.span(), err))
+            }),
+            #[cfg(feature = "html")]
+            "text" => syn::parse2(quote! { #value }).map(|value: LitBool| text = value.value),
+            "minify" => syn::parse2(quote! { #value }).map(|value: LitBool| minify = value.value),
+            key => Err(syn::Error::new_spanned(
+                value,
+                format!("`{}` requires the `html` feature", key),
+            )),
+        };
+        if let Err(err) = parsed {
+            return err.to_compile_error().into();
+        }
+    }
+
+    let unsupported = if !options.skip_under.is_empty() {
+        Some("skip_under")
+    } else if options.fetch.sha256.is_some() {
+        Some("sha256")
+    } else if options.fetch.integrity.is_some() {
+        Some("integrity")
+    } else if options.fetch.signature_url.is_some() {
+        Some("signature_url")
+    } else {
+        None
+    };
+    let pages = reject_bytes_options(&options, "include_sitemap_url")
+        .and_then(|_| match unsupported {
+            Some(option) => Err(format!(
+                "`{}` is not supported by `include_sitemap_url!`",
+                option
+            )),
+            None => Ok(()),
+        })
+        .and_then(|_| {
+            let url = url.value();
+            let matches = pattern.as_deref().map(dir::matcher).transpose()?;
+            let mut pages = Vec::new();
+            for page in sitemap::urls(&url, &mut options.fetch)? {
+                let path = sitemap::page_path(&page)?;
+                let selected = matches.as_ref().is_none_or(|matches| matches(&path))
+                    && regex.as_ref().is_none_or(|regex| regex.is_match(&page));
+                if selected && !pages.iter().any(|(known, _)| *known == path) {
+                    pages.push((path, page));
+                }
+            }
+            if pages.is_empty() {
+                return Err(format!("No page of `{}` matches the filters", url));
+            }
+            pages.sort();
+            let urls: Vec<_> = pages.iter().map(|(_, page)| page.clone()).collect();
+            let fetch = FetchOptions {
+                text: true,
+                ..options.fetch.clone()
+            };
+            let (paths, fetch) =
+                cache_all(&urls, &CompressKind::None, &fetch, DEFAULT_CONCURRENCY)?;
+            options.fetch.env = fetch.env;
+            let mut embedded = Vec::new();
+            for ((path, _), cached) in pages.into_iter().zip(paths) {
+                let content = std::fs::read_to_string(cached)
+                    .map_err(|e| format!("Failed to open cache file: {}", e))?;
+                #[cfg(feature = "html")]
+                let content = match (&selector, text) {
+                    (Some(selector), _) => match formats::select_html(&content, selector, text) {
+                        Ok(content) => content,
+                        Err(_) => continue,
+                    },
+                    (None, true) => {
+                        formats::select_html(&content, &formats::css_selector("body")?, true)?
+                    }
+                    (None, false) => content,
+                };
+                let content = match minify {
+                    true => sitemap::minify(&content),
+                    false => content,
+                };
+                embedded.push((path, content));
+            }
+            if embedded.is_empty() {
+                return Err(format!("No page of `{}` matches the selector", url));
+            }
+            Ok(embedded)
+        });
+
+    match pages {
+        Ok(pages) => {
+            let tracking = env_tracking(&options.fetch);
+            let items = sitemap::generate(&pages);
+            quote! {
+                #vis mod #name {
+                    #tracking
+                    #items
+                }
+            }
+            .into()
+        }
+        Err(err) => compile_error(err),
+    }
+}
+
+/// Parser for the `include_zstd_dictionary` macro's input.
+///
+/// Handles the dictionary name followed by an optional `skip_under = [...]`.
+#[cfg(feature = "zstd")]
+struct ZstdDictionaryInput {
+    name: LitStr,
+    skip_under: Vec<String>,
+}
+
+#[cfg(feature = "zstd")]
+impl Parse for ZstdDictionaryInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name: LitStr = input.parse()?;
+        let mut skip_under = Vec::new();
+
+        while input.peek(Token![,]) {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            let key = parse_option_key(input)?;
+            match key.to_string().as_str() {
+                "skip_under" => skip_under = parse_cfg_list(input)?,
+                _ => {
+                    return Err(syn::Error::new(
+                        key.span(),
+                        format!(
+                            "Unknown option `{}`, the dictionary is defined in Cargo.toml",
+                            key
+                        ),
+                    ))
+                }
+            }
+        }
+
+        Ok(ZstdDictionaryInput { name, skip_under })
+    }
+}
+
+/// A procedural macro that provides a shared zstd dictionary for compressing many small
+/// assets, expanding to the dictionary as a static byte array.
+///
+/// The dictionary is defined in the `[package.metadata.include_url.zstd_dictionaries]`
+/// table of `Cargo.toml`, either trained at compile time from sample URLs or fetched
+/// pre-trained from a URL. `include_url_bytes!` calls of the same crate compress with it
+/// through `compression = zstd` and `dictionary = "name"`, and read the same definition,
+/// so the macros may be expanded in any order.
+///
+/// # Usage
+///
+/// ```toml
+/// [package.metadata.include_url.zstd_dictionaries]
+/// icons = { samples = [
+///     "https://example.com/icons/a.json",
+///     "https://example.com/icons/b.json",
+///     "https://example.com/icons/c.json",
+/// ], max_size = 16384 }
+/// ```
+///
+/// ```rust,ignore
+/// use include_url_macro::{include_url_bytes, include_zstd_dictionary};
+///
+/// pub const ICONS_DICTIONARY: &[u8] = include_zstd_dictionary!("icons");
+///
+/// const ICON_A: &[u8] = include_url_bytes!(
+///     "https://example.com/icons/a.json",
+///     compression = zstd,
+///     dictionary = "icons",
+/// );
+/// ```
+///
+/// A pre-trained dictionary is defined with `url = "https://example.com/icons.dict"`
+/// instead of `samples`. `max_size` limits the size of a trained dictionary (defaults to
+/// 16 KiB). The samples and the dictionary are fetched with the crate wide defaults.
+#[cfg(feature = "zstd")]
+#[proc_macro]
+pub fn include_zstd_dictionary(input: TokenStream) -> TokenStream {
+    let ZstdDictionaryInput { name, skip_under } = parse_macro_input!(input as ZstdDictionaryInput);
+    if skip_requested(&skip_under) {
+        return quote! { b"" }.into();
+    }
+
+    let mut options = FetchOptions::default();
+    match dictionary::load_dictionary(&name.value(), &mut options) {
+        Ok(dictionary) => {
+            let dictionary = proc_macro2::Literal::byte_string(&dictionary);
+            let tracking = env_tracking(&options);
+            let output = quote! {{
+                #tracking
+                #dictionary
+            }};
+            output.into()
+        }
+        Err(err) => syn::Error::new(name.span(), err).to_compile_error().into(),
+    }
+}
+
+/// Parser for the input of the macros that deserialize structured content, such as
+/// `include_json_url`.
+///
+/// Handles the URL, the optional type specification and trailing `key = value` options.
+struct TypedUrlInput {
+    url: LitStr,
+    ty: Option<Type>,
+    with: Option<syn::Path>,
+    csv: CsvOptions,
+    options: MacroOptions,
+}
+
+/// Options specific to `include_csv_url`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CsvOptions {
+    delimiter: u8,
+    /// Check at compile time that the header columns match the fields of the row type.
+    check_headers: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            delimiter: b',',
+            check_headers: true,
+        }
+    }
+}
+
+impl Parse for TypedUrlInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        TypedUrlInput::parse_with(input, &[]).map(|(input, _)| input)
+    }
+}
+
+impl TypedUrlInput {
+    /// Parses the URL, type and options, also accepting the macro specific options named in
+    /// `keys`, whose values are returned unparsed.
+    fn parse_with(input: ParseStream, keys: &[&str]) -> syn::Result<(Self, ExtraOptions)> {
+        let mut options = MacroOptions::default();
+        let url = parse_primary_url(input, &mut options)?;
+        let mut ty = None;
+        let mut with = None;
+        let mut csv = CsvOptions::default();
+        let mut extra = Vec::new();
+        let mut position = 0;
+
+        while input.peek(Token![,]) {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            position += 1;
+
+            if input.peek(Ident) && input.peek2(Token![=]) {
+                let key = parse_option_key(input)?;
+                match key.to_string().as_str() {
+                    name if keys.contains(&name) => extra.push((key, input.parse()?)),
+                    "with" => with = Some(input.parse()?),
+                    "delimiter" => {
+                        let value: syn::LitChar = input.parse()?;
+                        csv.delimiter = u8::try_from(value.value()).map_err(|_| {
+                            syn::Error::new(
+                                value.span(),
+                                "The delimiter must be an ASCII character",
+                            )
+                        })?;
+                    }
+                    "check_headers" => csv.check_headers = input.parse::<LitBool>()?.value,
+                    _ => options.parse_option(&key, input)?,
+                }
+            } else if position == 1 {
+                // Check if there's a type specification after the URL
+                ty = Some(input.parse()?);
+            } else {
+                return Err(input.error("Expected a `key = value` option"));
+            }
+        }
+
+        Ok((
+            TypedUrlInput {
+                url,
+                ty,
+                with,
+                csv,
+                options,
+            },
+            extra,
+        ))
+    }
+}
+
+/// A procedural macro that includes and parses JSON content from a URL at compile time.
+///
+/// This macro can either return a generic `serde_json::Value` or parse the JSON into
+/// a specific type that implements `serde::Deserialize`.
+///
+/// # Usage
+///
+/// Basic usage (returns `serde_json::Value`):
+/// ```rust
+/// use include_url_macro::include_json_url;
+///
+/// let json = include_json_url!("https://jsonplaceholder.typicode.com/posts");
+/// ```
+///
+/// Usage with a specific type:
+/// ```rust
+/// use include_url_macro::include_json_url;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Post {
+///   userId: i32,
+///   id: i32,
+///   title: String,
+///   body: String,
+/// }
+///
+/// let post: Post = include_json_url!("https://jsonplaceholder.typicode.com/posts/1", Post);
+/// ```
+///
+/// # Options
+///
+/// Besides the [options shared by every macro](crate#options), `key = value` pairs after
+/// the URL and type may include:
+///
+/// * `with = path::to::from_value` - Convert the `serde_json::Value` with a custom function
+///   instead of `serde_json::from_str`. The function has the signature of
+///   `serde_json::from_value`: `fn(serde_json::Value) -> Result<T, E>` with `E: Debug`.
+///
+/// ```rust
+/// use include_url_macro::include_json_url;
+///
+/// let releases = include_json_url!(
+///     "https://api.github.com/repos/rust-lang/rust/releases",
+///     paginate = link_header,
+///     max_pages = 3,
+/// );
+/// ```
+///
+/// When skipped through `skip_under`, the macro expands to `Default::default()` of the
+/// type (or `serde_json::Value::Null`).
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The URL is invalid
+/// * The URL scheme is not HTTP or HTTPS
+/// * The content cannot be fetched
+/// * The response is not valid JSON
+/// * The JSON cannot be parsed into the specified type (if a type is provided)
+/// * A paginated response exceeds `max_pages` or a page is not a JSON array
+#[proc_macro]
+pub fn include_json_url(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as TypedUrlInput);
+    expand_text_format(
+        input,
+        TextFormat {
+            macro_name: "include_json_url",
+            name: "JSON",
+            validate: formats::validate_json,
+            normalize: None,
+            from_str: quote! { serde_json::from_str },
+            value_ty: Some(quote! { serde_json::Value }),
+        },
+    )
+}
+
+/// A procedural macro that runs a GraphQL query against an endpoint at compile time and
+/// includes the `data` of the response.
+///
+/// The query is sent in a `POST` request with a JSON body, given inline with `query` or
+/// fetched from a `.graphql` document with `query_url`. Like [`include_json_url!`], the
+/// data becomes a `serde_json::Value` or is parsed into the given type, and a conversion
+/// function can be given with `with`. The query and its variables are part of the cache
+/// key, so changing them runs the query again. Being `POST` requests, queries are only
+/// retried with `retry_any_method = true`.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_graphql_url;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Posts {
+///     posts: Vec<Post>,
+/// }
+///
+/// #[derive(Deserialize)]
+/// struct Post {
+///     title: String,
+/// }
+///
+/// let data: Posts = include_graphql_url!(
+///     "https://cms.example.com/graphql",
+///     Posts,
+///     query = "query Posts($first: Int) { posts(first: $first) { title } }",
+///     variables = r#"{"first": 10}"#,
+///     auth = bearer_env("CMS_TOKEN"),
+/// );
+/// ```
+///
+/// # Options
+///
+/// Besides the [options shared by every macro](crate#options) and `with`:
+///
+/// * `query = "{ ... }"` - The query document.
+/// * `query_url = "https://..."` - Fetch the query document from a URL instead, without
+///   the headers and credentials given for the endpoint.
+/// * `variables = r#"{"first": 10}"#` - The variables of the query, as a JSON object.
+/// * `operation_name = "Posts"` - The operation to run when the document has several.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * Neither or both of `query` and `query_url` are given
+/// * A URL is invalid or the content cannot be fetched
+/// * `variables` is not a JSON object
+/// * The response reports errors, which are quoted, or has no `data`
+#[proc_macro]
+pub fn include_graphql_url(input: TokenStream) -> TokenStream {
+    let parser = |input: ParseStream| {
+        TypedUrlInput::parse_with(
+            input,
+            &["query", "query_url", "variables", "operation_name"],
+        )
+    };
+    let (mut input, extra) = parse_macro_input!(input with parser);
+    let mut query = None;
+    let mut query_url = None;
+    let mut variables = None;
+    let mut operation_name = None;
+    for (key, value) in extra {
+        let parsed = syn::parse2(quote! { #value }).map(|value: LitStr| {
+            let target = match key.to_string().as_str() {
+                "query" => &mut query,
+                "query_url" => &mut query_url,
+                "variables" => &mut variables,
+                _ => &mut operation_name,
+            };
+            *target = Some(value);
+        });
+        if let Err(err) = parsed {
+            return err.to_compile_error().into();
+        }
+    }
+    if skip_requested(&input.options.skip_under) {
+        return expand_graphql(input);
+    }
+
+    let fetch = &mut input.options.fetch;
+    let body = match (query, query_url) {
+        (Some(query), None) => Ok(query.value()),
+        (None, Some(url)) => {
+            let mut query_options = FetchOptions {
+                text: true,
+                ..FetchOptions::default()
+            };
+            let query = cached_url_content(&url.value(), CompressKind::None, &mut query_options)
+                .and_then(|path| {
+                    std::fs::read_to_string(path)
+                        .map_err(|e| format!("Failed to read the GraphQL query: {}", e))
+                });
+            fetch.env.extend(query_options.env);
+            query
+        }
+        _ => Err("Expected exactly one of `query` and `query_url`".to_string()),
+    }
+    .and_then(|query| {
+        formats::graphql_request(
+            &query,
+            variables.map(|value| value.value()).as_deref(),
+            operation_name.map(|value| value.value()).as_deref(),
+        )
+    });
+    match body {
+        Ok(body) => {
+            fetch.method.get_or_insert(Method::POST);
+            fetch.body = Some(body);
+            fetch
+                .content_type
+                .get_or_insert_with(|| "application/json".to_string());
+            expand_graphql(input)
+        }
+        Err(err) => compile_error(err),
+    }
+}
+
+/// Expands `include_graphql_url!` once the request is set up, including the `data` of the
+/// response like `include_json_url!`.
+fn expand_graphql(input: TypedUrlInput) -> TokenStream {
+    expand_text_format(
+        input,
+        TextFormat {
+            macro_name: "include_graphql_url",
+            name: "JSON",
+            validate: formats::validate_json,
+            normalize: Some(formats::graphql_data),
+            from_str: quote! { serde_json::from_str },
+            value_ty: Some(quote! { serde_json::Value }),
+        },
+    )
+}
+
+/// A procedural macro that includes JSON5 or JSONC content from a URL at compile time.
+///
+/// Comments, trailing commas and the other relaxed JSON5 syntax are resolved during
+/// expansion, so the embedded text is strict JSON. It is then parsed like
+/// [`include_json_url!`], into a `serde_json::Value` or the given type, and accepts the
+/// same options. Requires the `json5` feature.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_json5_url;
+///
+/// let settings = include_json5_url!("https://example.com/.vscode/settings.json");
+/// ```
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The URL is invalid or the content cannot be fetched
+/// * The response is not valid JSON5, or holds a value JSON cannot represent such as `NaN`
+#[cfg(feature = "json5")]
+#[proc_macro]
+pub fn include_json5_url(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as TypedUrlInput);
+    expand_text_format(
+        input,
+        TextFormat {
+            macro_name: "include_json5_url",
+            name: "JSON",
+            validate: formats::validate_json,
+            normalize: Some(formats::json5_to_json),
+            from_str: quote! { serde_json::from_str },
+            value_ty: Some(quote! { serde_json::Value }),
+        },
+    )
+}
+
+/// Parser for the input of `include_json_url_typed!`.
+///
+/// Handles the URL followed by the required `name = "Type"` and optional `key = value`
+/// options.
+struct NamedUrlInput {
+    url: LitStr,
+    name: LitStr,
+    options: MacroOptions,
+}
+
+impl Parse for NamedUrlInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let url: LitStr = input.parse()?;
+        let mut name = None;
+        let mut options = MacroOptions::default();
+
+        while input.peek(Token![,]) {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            let key = parse_option_key(input)?;
+            if key == "name" {
+                name = Some(input.parse()?);
+            } else {
+                options.parse_option(&key, input)?;
+            }
+        }
+
+        let name = name.ok_or_else(|| syn::Error::new(url.span(), "Missing `name = \"Type\"`"))?;
+        Ok(NamedUrlInput { url, name, options })
+    }
+}
+
+/// A procedural macro that fetches a JSON sample at compile time, infers struct
+/// definitions from it and emits them together with a constant holding the sample.
+///
+/// The root must be an object or an array of objects. The root struct is called `name`,
+/// nested objects become structs named after their path (`author` in `Post` becomes
+/// `PostAuthor`) and the constant is `name` in `SCREAMING_SNAKE_CASE`. Keys are converted
+/// to `snake_case` fields, strings and arrays borrow `'static` data, keys missing from
+/// some array elements or holding `null` become `Option`s, and values of conflicting
+/// types are kept as JSON text. No parsing happens at runtime.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_json_url_typed;
+///
+/// include_json_url_typed!("https://jsonplaceholder.typicode.com/posts/1", name = "Post");
+///
+/// assert_eq!(POST.id, 1);
+/// println!("{}", POST.title);
+/// ```
+///
+/// See the [crate level documentation](crate#options) for the supported options,
+/// except `skip_under` as the generated items would be missing.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The URL is invalid or the content cannot be fetched
+/// * The response is not valid JSON, or not an object or array of objects
+/// * Two keys of an object map to the same field name
+#[proc_macro]
+pub fn include_json_url_typed(input: TokenStream) -> TokenStream {
+    let NamedUrlInput {
+        url,
+        name,
+        mut options,
+    } = parse_macro_input!(input as NamedUrlInput);
+    if syn::parse_str::<Ident>(&name.value()).is_err() {
+        return syn::Error::new(name.span(), "The name must be a valid type name")
+            .to_compile_error()
+            .into();
+    }
+    let items = reject_bytes_options(&options, "include_json_url_typed")
+        .and_then(|_| reject_skip_under(&options, "include_json_url_typed"))
+        .and_then(|_| read_cached_text(&url, &mut options))
+        .and_then(|text| {
+            serde_json::from_str(&text).map_err(|e| format!("Invalid JSON content from URL: {}", e))
+        })
+        .and_then(|value| typegen::generate(&value, &name.value()));
+
+    match items {
+        Ok(items) => {
+            let tracking = env_tracking(&options.fetch);
+            quote! {
+                #tracking
+                #items
+            }
+            .into()
+        }
+        Err(err) => compile_error(err),
+    }
+}
+
+/// A procedural macro that includes newline-delimited JSON from a URL at compile time,
+/// expanding to a `Vec` with one element per line.
+///
+/// Every non-empty line is validated as JSON during expansion, and the error names the
+/// offending line. The elements are `serde_json::Value`s or the given type implementing
+/// `serde::Deserialize`.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_ndjson_url;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Event {
+///     id: u64,
+///     kind: String,
+/// }
+///
+/// let events: Vec<Event> = include_ndjson_url!("https://example.com/events.ndjson", Event);
+/// ```
+///
+/// See the [crate level documentation](crate#options) for the supported options.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The URL is invalid or the content cannot be fetched
+/// * A line is not valid JSON
+#[proc_macro]
+pub fn include_ndjson_url(input: TokenStream) -> TokenStream {
+    let TypedUrlInput {
+        url,
+        ty,
+        with,
+        csv,
+        mut options,
+    } = parse_macro_input!(input as TypedUrlInput);
+    let ty = ty.map_or_else(|| quote! { serde_json::Value }, |ty| quote! { #ty });
+    if skip_requested(&options.skip_under) {
+        return quote! { ::std::vec::Vec::<#ty>::new() }.into();
+    }
+    if with.is_some() || csv != CsvOptions::default() {
+        let err =
+            "`with`, `delimiter` and `check_headers` are not supported by `include_ndjson_url!`";
+        return syn::Error::new(url.span(), err).to_compile_error().into();
+    }
+
+    let content = reject_bytes_options(&options, "include_ndjson_url")
+        .and_then(|_| read_cached_text(&url, &mut options))
+        .and_then(|content| formats::validate_ndjson(&content).map(|_| content));
+    match content {
+        Ok(content) => {
+            let tracking = env_tracking(&options.fetch);
+            let output = quote! {{
+                #tracking
+                let text = #content;
+                text.lines()
+                    .enumerate()
+                    .filter(|(_, line)| !line.trim().is_empty())
+                    .map(|(index, line)| {
+                        serde_json::from_str::<#ty>(line).unwrap_or_else(|e| {
+                            panic!("Failed to parse NDJSON line {} into the specified type: {}", index + 1, e)
+                        })
+                    })
+                    .collect::<::std::vec::Vec<#ty>>()
+            }};
+            output.into()
+        }
+        Err(err) => compile_error(err),
+    }
+}
+
+/// Fetches a URL through the cache and reads the content as text.
+fn read_cached_text(url: &LitStr, options: &mut MacroOptions) -> Result<String, String> {
+    options.fetch.text = true;
+    let path = cached_url_content(&url.value(), CompressKind::None, &mut options.fetch)?;
+    std::fs::read_to_string(path).map_err(|e| format!("Failed to open cache file: {}", e))
+}
+
+/// Rewrites fetched text into the form that is embedded.
+type Normalize = fn(&str) -> Result<String, String>;
+
+/// A structured text format that is validated during expansion and deserialized by the
+/// generated code.
+struct TextFormat {
+    /// Name of the macro, for error messages.
+    macro_name: &'static str,
+    /// Name of the format, for error messages.
+    name: &'static str,
+    /// Validates the fetched text, describing the problem if it is malformed.
+    validate: fn(&str) -> Result<(), String>,
+    /// Rewrites the fetched text before it is validated and embedded.
+    normalize: Option<Normalize>,
+    /// The `from_str` function called by the generated code.
+    from_str: proc_macro2::TokenStream,
+    /// Type produced when the invocation does not specify one, or `None` to include the
+    /// validated text itself.
+    value_ty: Option<proc_macro2::TokenStream>,
+}
+
+/// Expands a macro that includes a structured text format, deserializing it into the given
+/// type or the format's generic value type.
+fn expand_text_format(input: TypedUrlInput, format: TextFormat) -> TokenStream {
+    let TypedUrlInput {
+        url,
+        ty,
+        with,
+        csv,
+        mut options,
+    } = input;
+    let TextFormat {
+        macro_name,
+        name,
+        validate,
+        normalize,
+        from_str,
+        value_ty,
+    } = format;
+    if skip_requested(&options.skip_under) {
+        return match ty.map(|ty| quote! { #ty }).or(value_ty) {
+            Some(ty) => quote! { <#ty as ::core::default::Default>::default() },
+            None => quote! { "" },
+        }
+        .into();
+    }
+
+    let content = reject_bytes_options(&options, macro_name)
+        .and_then(|_| match csv == CsvOptions::default() {
+            true => Ok(()),
+            false => Err(format!(
+                "`delimiter` and `check_headers` are not supported by `{}!`",
+                macro_name
+            )),
+        })
+        .and_then(|_| read_cached_text(&url, &mut options))
+        .and_then(|content| match normalize {
+            Some(normalize) => normalize(&content),
+            None => Ok(content),
+        })
+        .and_then(|content| validate(&content).map(|_| content));
+
+    match content {
+        Ok(content) => {
+            let tracking = env_tracking(&options.fetch);
+            let parse_error = format!("Failed to parse {}", name);
+            let value = match &value_ty {
+                Some(value_ty) => quote! { #from_str::<#value_ty>(text).expect(#parse_error) },
+                None => quote! { text },
+            };
+            let output = match (with, ty.map(|ty| quote! { #ty }).or(value_ty)) {
+                (Some(with), ty) => {
+                    let ty = ty.unwrap_or_else(|| quote! { _ });
+                    quote! {{
+                        #tracking
+                        let text = #content;
+                        let value = #value;
+                        let parsed: #ty = #with(value)
+                            .expect("Failed to convert the content with the custom deserializer");
+                        parsed
+                    }}
+                }
+                (None, Some(ty)) => {
+                    let parse_error = format!("Failed to parse {} into the specified type", name);
+                    quote! {{
+                        #tracking
+                        let text = #content;
+                        #from_str::<#ty>(text).expect(#parse_error)
+                    }}
+                }
+                (None, None) => quote! {{
+                    #tracking
+                    #content
+                }},
+            };
+            output.into()
+        }
+        Err(err) => compile_error(err),
+    }
+}
+
+/// A procedural macro that includes and parses YAML content from a URL at compile time.
+///
+/// The YAML is validated during expansion and deserialized into either a
+/// `serde_yaml::Value` or the given type implementing `serde::Deserialize`, so the calling
+/// crate needs to depend on `serde_yaml`. Like [`include_json_url!`], a conversion
+/// function can be given with `with = path::to::from_value`. Requires the `yaml` feature.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_yaml_url;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Config {
+///     name: String,
+///     replicas: u32,
+/// }
+///
+/// let value = include_yaml_url!("https://example.com/config.yaml");
+/// let config: Config = include_yaml_url!("https://example.com/config.yaml", Config);
+/// ```
+///
+/// See the [crate level documentation](crate#options) for the supported options.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The URL is invalid or the content cannot be fetched
+/// * The response is not valid YAML
+#[cfg(feature = "yaml")]
+#[proc_macro]
+pub fn include_yaml_url(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as TypedUrlInput);
+    expand_text_format(
+        input,
+        TextFormat {
+            macro_name: "include_yaml_url",
+            name: "YAML",
+            validate: formats::validate_yaml,
+            normalize: None,
+            from_str: quote! { serde_yaml::from_str },
+            value_ty: Some(quote! { serde_yaml::Value }),
+        },
+    )
+}
+
+/// A procedural macro that includes and parses TOML content from a URL at compile time.
+///
+/// The TOML is validated during expansion and deserialized into either a `toml::Table` or
+/// the given type implementing `serde::Deserialize`, so the calling crate needs to depend
+/// on `toml`.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_toml_url;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Manifest {
+///     package: Package,
+/// }
+///
+/// #[derive(Deserialize)]
+/// struct Package {
+///     name: String,
+/// }
+///
+/// let manifest: Manifest = include_toml_url!(
+///     "https://raw.githubusercontent.com/serde-rs/serde/master/Cargo.toml",
+///     Manifest
+/// );
+/// ```
+///
+/// See the [crate level documentation](crate#options) for the supported options.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The URL is invalid or the content cannot be fetched
+/// * The response is not valid TOML
+#[proc_macro]
+pub fn include_toml_url(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as TypedUrlInput);
+    expand_text_format(
+        input,
+        TextFormat {
+            macro_name: "include_toml_url",
+            name: "TOML",
+            validate: formats::validate_toml,
+            normalize: None,
+            from_str: quote! { toml::from_str },
+            value_ty: Some(quote! { toml::Table }),
+        },
+    )
+}
+
+/// A procedural macro that includes and parses RON content from a URL at compile time.
+///
+/// The RON is validated during expansion and deserialized into either a `ron::Value` or
+/// the given type implementing `serde::Deserialize`, so the calling crate needs to depend
+/// on `ron`. Requires the `ron` feature.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_ron_url;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Level {
+///     name: String,
+///     spawns: Vec<(f32, f32)>,
+/// }
+///
+/// let level: Level = include_ron_url!("https://assets.example.com/levels/1.ron", Level);
+/// ```
+///
+/// See the [crate level documentation](crate#options) for the supported options.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The URL is invalid or the content cannot be fetched
+/// * The response is not valid RON
+#[cfg(feature = "ron")]
+#[proc_macro]
+pub fn include_ron_url(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as TypedUrlInput);
+    expand_text_format(
+        input,
+        TextFormat {
+            macro_name: "include_ron_url",
+            name: "RON",
+            validate: formats::validate_ron,
+            normalize: None,
+            from_str: quote! { ron::from_str },
+            value_ty: Some(quote! { ron::Value }),
+        },
+    )
+}
+
+/// A procedural macro that includes XML content from a URL at compile time, checking that
+/// it is well-formed.
+///
+/// Without a type the macro expands to the document as a `&'static str`. With a type
+/// implementing `serde::Deserialize`, the document is deserialized with
+/// `quick_xml::de::from_str`, so the calling crate needs to depend on `quick-xml` with the
+/// `serialize` feature. A conversion function given with `with = path::to::from_str`
+/// receives the document text. Requires the `xml` feature.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_xml_url;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Feed {
+///     title: String,
+/// }
+///
+/// let xml: &str = include_xml_url!("https://example.com/feed.xml");
+/// let feed: Feed = include_xml_url!("https://example.com/feed.xml", Feed);
+/// ```
+///
+/// See the [crate level documentation](crate#options) for the supported options.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The URL is invalid or the content cannot be fetched
+/// * The response is not a well-formed XML document
+#[cfg(feature = "xml")]
+#[proc_macro]
+pub fn include_xml_url(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as TypedUrlInput);
+    expand_text_format(
+        input,
+        TextFormat {
+            macro_name: "include_xml_url",
+            name: "XML",
+            validate: formats::validate_xml,
+            normalize: None,
+            from_str: quote! { quick_xml::de::from_str },
+            value_ty: None,
+        },
+    )
+}
+
+/// A structured binary format that is validated during expansion and decoded at runtime.
+#[cfg(any(feature = "msgpack", feature = "cbor"))]
+struct BinaryFormat {
+    /// Name of the macro, for error messages.
+    macro_name: &'static str,
+    /// Name of the format, for error messages.
+    name: &'static str,
+    /// Validates the fetched bytes, describing the problem if they are malformed.
+    validate: fn(&[u8]) -> Result<(), String>,
+    /// The function called by the generated code to decode the bytes into the given type.
+    decode: proc_macro2::TokenStream,
+    /// Type produced when the invocation does not specify one, or `None` to include the
+    /// validated bytes themselves.
+    value_ty: Option<proc_macro2::TokenStream>,
+}
+
+/// Expands a macro that includes a structured binary format, decoding it into the given
+/// type or the format's generic value type.
+#[cfg(any(feature = "msgpack", feature = "cbor"))]
+fn expand_binary_format(input: TypedUrlInput, format: BinaryFormat) -> TokenStream {
+    let TypedUrlInput {
+        url,
+        ty,
+        with,
+        csv,
+        mut options,
+    } = input;
+    let BinaryFormat {
+        macro_name,
+        name,
+        validate,
+        decode,
+        value_ty,
+    } = format;
+    let ty = ty.map(|ty| quote! { #ty }).or(value_ty);
+    if skip_requested(&options.skip_under) {
+        return match ty {
+            Some(ty) => quote! { <#ty as ::core::default::Default>::default() },
+            None => quote! { &[] as &'static [u8] },
+        }
+        .into();
+    }
+
+    let path = reject_bytes_options(&options, macro_name)
+        .and_then(|_| match csv == CsvOptions::default() {
+            true => Ok(()),
+            false => Err(format!(
+                "`delimiter` and `check_headers` are not supported by `{}!`",
+                macro_name
+            )),
+        })
+        .and_then(|_| cached_url_content(&url.value(), CompressKind::None, &mut options.fetch))
+        .and_then(|path| {
+            let content =
+                std::fs::read(&path).map_err(|e| format!("Failed to open cache file: {}", e))?;
+            validate(&content).map(|_| path.display().to_string())
+        });
+
+    match path {
+        Ok(path) => {
+            let tracking = env_tracking(&options.fetch);
+            let bytes = quote! { include_bytes!(#path) as &'static [u8] };
+            let output = match (with, ty) {
+                (Some(with), ty) => {
+                    let ty = ty.unwrap_or_else(|| quote! { _ });
+                    quote! {{
+                        #tracking
+                        let parsed: #ty = #with(#bytes)
+                            .expect("Failed to convert the content with the custom deserializer");
+                        parsed
+                    }}
+                }
+                (None, Some(ty)) => {
+                    let parse_error = format!("Failed to decode {} into the specified type", name);
+                    quote! {{
+                        #tracking
+                        let parsed: #ty = #decode(#bytes).expect(#parse_error);
+                        parsed
+                    }}
+                }
+                (None, None) => quote! {{
+                    #tracking
+                    #bytes
+                }},
+            };
+            output.into()
+        }
+        Err(err) => compile_error(err),
+    }
+}
+
+/// A procedural macro that includes MessagePack content from a URL at compile time.
+///
+/// The payload is validated during expansion. Without a type the macro expands to the raw
+/// bytes as a `&'static [u8]`; with a type implementing `serde::Deserialize` they are
+/// decoded with `rmp_serde::from_slice`, so the calling crate needs to depend on
+/// `rmp-serde`. A conversion function given with `with = path::to::decode` receives the
+/// raw bytes. Requires the `msgpack` feature.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_msgpack_url;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Tileset {
+///     name: String,
+///     tiles: Vec<u32>,
+/// }
+///
+/// let bytes: &[u8] = include_msgpack_url!("https://example.com/tileset.msgpack");
+/// let tileset: Tileset = include_msgpack_url!("https://example.com/tileset.msgpack", Tileset);
+/// ```
+///
+/// See the [crate level documentation](crate#options) for the supported options.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The URL is invalid or the content cannot be fetched
+/// * The response is not a single well-formed MessagePack value
+#[cfg(feature = "msgpack")]
+#[proc_macro]
+pub fn include_msgpack_url(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as TypedUrlInput);
+    expand_binary_format(
+        input,
+        BinaryFormat {
+            macro_name: "include_msgpack_url",
+            name: "MessagePack",
+            validate: formats::validate_msgpack,
+            decode: quote! { rmp_serde::from_slice },
+            value_ty: None,
+        },
+    )
+}
+
+/// A procedural macro that includes and parses CBOR content from a URL at compile time.
+///
+/// The CBOR is validated during expansion and decoded into either a `ciborium::Value` or
+/// the given type implementing `serde::Deserialize` with `ciborium::from_reader`, so the
+/// calling crate needs to depend on `ciborium`. A conversion function given with
+/// `with = path::to::decode` receives the raw bytes. Requires the `cbor` feature.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_cbor_url;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Manifest {
+///     version: u32,
+/// }
+///
+/// let value = include_cbor_url!("https://example.com/manifest.cbor");
+/// let manifest: Manifest = include_cbor_url!("https://example.com/manifest.cbor", Manifest);
+/// ```
+///
+/// See the [crate level documentation](crate#options) for the supported options.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The URL is invalid or the content cannot be fetched
+/// * The response is not a single well-formed CBOR data item
+#[cfg(feature = "cbor")]
+#[proc_macro]
+pub fn include_cbor_url(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as TypedUrlInput);
+    expand_binary_format(
+        input,
+        BinaryFormat {
+            macro_name: "include_cbor_url",
+            name: "CBOR",
+            validate: formats::validate_cbor,
+            decode: quote! { ciborium::from_reader },
+            value_ty: Some(quote! { ciborium::Value }),
+        },
+    )
+}
+
+/// A procedural macro that includes a WebAssembly module from a URL at compile time,
+/// expanding to its bytes as a `&'static [u8]`.
+///
+/// The module must start with the `\0asm` magic and version 1, so a corrupt download or an
+/// HTML error page fails the build instead of the instantiation. With `validate = true`,
+/// the whole module is validated by `wasmparser` as well, which requires the `wasm`
+/// feature.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_wasm_url;
+///
+/// const PLUGIN: &[u8] = include_wasm_url!("https://example.com/plugin.wasm", validate = true);
+/// ```
+///
+/// # Options
+///
+/// Besides the [options shared by every macro](crate#options):
+///
+/// * `validate = true` - Validate the types and instructions of the module with the
+///   features `wasmparser` enables by default. Defaults to `false`.
+///
+/// When skipped through `skip_under`, the macro expands to an empty slice.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The URL is invalid or the content cannot be fetched
+/// * The content is not a version 1 WebAssembly module
+/// * `validate = true` is given and the module does not validate
+#[proc_macro]
+pub fn include_wasm_url(input: TokenStream) -> TokenStream {
+    let parser = |input: ParseStream| UrlInput::parse_with(input, &["validate"]);
+    let (UrlInput { url, mut options }, extra) = parse_macro_input!(input with parser);
+    let validate = match extra
+        .last()
+        .map(|(_, value)| syn::parse2::<LitBool>(quote! { #value }))
+        .transpose()
+    {
+        Ok(validate) => validate,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    #[cfg(not(feature = "wasm"))]
+    if let Some(validate) = validate.filter(|validate| validate.value) {
+        return syn::Error::new(
+            validate.span(),
+            "`validate = true` requires the `wasm` feature",
+        )
+        .to_compile_error()
+        .into();
+    }
+    #[cfg(feature = "wasm")]
+    let validate = validate.is_some_and(|validate| validate.value);
+    if skip_requested(&options.skip_under) {
+        return quote! { b"" }.into();
+    }
+
+    let bytes = reject_bytes_options(&options, "include_wasm_url")
+        .and_then(|_| cached_url_content(&url.value(), CompressKind::None, &mut options.fetch))
+        .and_then(|path| {
+            let content =
+                std::fs::read(&path).map_err(|e| format!("Failed to open cache file: {}", e))?;
+            formats::check_wasm_header(&content)?;
+            #[cfg(feature = "wasm")]
+            if validate {
+                formats::validate_wasm(&content)?;
+            }
+            embed_bytes(&path, None)
+        });
+    match bytes {
+        Ok(bytes) => {
+            let tracking = env_tracking(&options.fetch);
+            quote! {{
+                #tracking
+                #bytes
+            }}
+            .into()
+        }
+        Err(err) => compile_error(err),
+    }
+}
+
+/// Parses the `(width, height)` of the `resize` option of `include_image_url!`.
+#[cfg(feature = "image")]
+fn image_size(value: &syn::Expr) -> syn::Result<(u32, u32)> {
+    let error = || syn::Error::new_spanned(value, "Expected a size such as `(64, 64)`");
+    let syn::Expr::Tuple(tuple) = value else {
+        return Err(error());
+    };
+    let sizes = tuple
+        .elems
+        .iter()
+        .map(|element| match element {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Int(size),
+                ..
+            }) => size.base10_parse::<u32>().ok().filter(|size| *size > 0),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>();
+    match sizes.as_deref() {
+        Some(&[width, height]) => Ok((width, height)),
+        _ => Err(error()),
+    }
+}
+
+/// A procedural macro that fetches a PNG, JPEG or GIF image at compile time and expands to a
+/// module with its decoded pixels, so no image decoder is needed at runtime.
+///
+/// The module holds the `WIDTH` and `HEIGHT` of the image, the `FORMAT`,
+/// `BITS_PER_PIXEL`, `BYTES_PER_PIXEL` (for byte sized pixels) and `STRIDE` of the pixel
+/// layout and the `PIXELS` themselves, row by row from the top left corner and aligned to
+/// 4 bytes. Only the first frame of an animated GIF is embedded. The image can be
+/// downscaled during expansion, and re-encoded with `format`, in which case the module
+/// holds the encoded `BYTES` instead of the pixels. Requires the `image` feature.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_image_url;
+///
+/// include_image_url!(
+///     pub mod logo,
+///     "https://www.rust-lang.org/static/images/rust-logo-blk.png",
+///     pixels = "rgba8",
+/// );
+///
+/// assert_eq!(logo::PIXELS.len(), (logo::WIDTH * logo::HEIGHT * 4) as usize);
+///
+/// include_image_url!(
+///     pub mod icon,
+///     "https://www.rust-lang.org/static/images/rust-logo-blk.png",
+///     resize = (64, 64),
+///     format = "png",
+/// );
+///
+/// const ICON_PNG: &[u8] = icon::BYTES;
+/// ```
+///
+/// # Options
+///
+/// Besides the [options shared by every macro](crate#options), except `skip_under` as the
+/// generated items would be missing:
+///
+/// * `pixels = "rgb8"` - The pixel layout, one of `rgba8`, `rgb8`, `luma8` (grayscale)
+///   and `lumaa8` (grayscale with alpha), or for `embedded-graphics` raw images, `rgb565`
+///   (big endian, for `ImageRaw<Rgb565>`), `rgb565le` (for `ImageRawLE<Rgb565>`) and
+///   `mono1` (for `ImageRaw<BinaryColor>`, on where the luminance is at least half).
+///   Defaults to `rgba8`.
+/// * `resize = (64, 64)` - Downscale the image to fit within this width and height,
+///   keeping its aspect ratio. Smaller images are not enlarged.
+/// * `format = "png"` - Re-encode the image as `png`, `jpeg` or `gif` and embed the file
+///   instead of the pixels. JPEG images lose their alpha channel.
+/// * `quality = 85` - The JPEG quality from 1 to 100, for `format = "jpeg"`. Defaults to
+///   75.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The URL is invalid or the content cannot be fetched
+/// * The content is not a PNG, JPEG or GIF image, or cannot be decoded
+/// * `pixels` is combined with `format`, or `quality` is given without `format = "jpeg"`
+#[cfg(feature = "image")]
+#[proc_macro]
+pub fn include_image_url(input: TokenStream) -> TokenStream {
+    use raster::{FileFormat, Output, PixelFormat};
+
+    let parser = ModuleUrlInput::parser(&["pixels", "resize", "format", "quality"]);
+    let (
+        ModuleUrlInput {
+            vis,
+            name,
+            input: UrlInput { url, mut options },
+        },
+        extra,
+    ) = parse_macro_input!(input with parser);
+    let mut pixels = None;
+    let mut resize = None;
+    let mut format = None;
+    let mut quality = None;
+    for (key, value) in extra {
+        let parsed = match key.to_string().as_str() {
+            "pixels" => syn::parse2(quote! { #value }).and_then(|value: LitStr| {
+                PixelFormat::parse(&value.value())
+                    .map(|parsed| pixels = Some(parsed))
+                    .map_err(|err| syn::Error::new(value.span(), err))
+            }),
+            "resize" => image_size(&value).map(|size| resize = Some(size)),
+            "format" => syn::parse2(quote! { #value }).and_then(|value: LitStr| {
+                FileFormat::parse(&value.value())
+                    .map(|parsed| format = Some(parsed))
+                    .map_err(|err| syn::Error::new(value.span(), err))
+            }),
+            _ => syn::parse2(quote! { #value }).and_then(|value: LitInt| {
+                value
+                    .base10_parse::<u8>()
+                    .ok()
+                    .filter(|parsed| (1..=100).contains(parsed))
+                    .map(|parsed| quality = Some(parsed))
+                    .ok_or_else(|| syn::Error::new(value.span(), "`quality` must be from 1 to 100"))
+            }),
+        };
+        if let Err(err) = parsed {
+            return err.to_compile_error().into();
+        }
+    }
+    let output = match (pixels, format, quality) {
+        (Some(_), Some(_), _) => Err(
+            "`pixels` cannot be combined with `format`, which embeds the encoded image".to_string(),
+        ),
+        (_, Some(FileFormat::Jpeg(_)), Some(quality)) => {
+            Ok(Output::Encoded(FileFormat::Jpeg(quality)))
+        }
+        (_, _, Some(_)) => Err("`quality` requires `format = \"jpeg\"`".to_string()),
+        (_, Some(format), None) => Ok(Output::Encoded(format)),
+        (pixels, None, None) => Ok(Output::Pixels(pixels.unwrap_or(PixelFormat::Rgba8))),
+    };
+    let items = output
+        .and_then(|output| {
+            reject_bytes_options(&options, "include_image_url")?;
+            reject_skip_under(&options, "include_image_url")?;
+            Ok(output)
+        })
+        .and_then(|output| {
+            let path = cached_url_content(&url.value(), CompressKind::None, &mut options.fetch)?;
+            let content =
+                std::fs::read(path).map_err(|e| format!("Failed to open cache file: {}", e))?;
+            let image = raster::decode(&content)?;
+            let image = match resize {
+                Some((width, height)) => raster::fit(image, width, height),
+                None => image,
+            };
+            raster::generate(&image, output)
+        });
+
+    match items {
+        Ok(items) => {
+            let tracking = env_tracking(&options.fetch);
+            quote! {
+                #vis mod #name {
+                    #tracking
+                    #items
+                }
+            }
+            .into()
+        }
+        Err(err) => compile_error(err),
+    }
+}
+
+/// A procedural macro that includes a font from a URL at compile time, expanding to its
+/// bytes as a `&'static [u8]`, optionally subset to the characters the program displays.
+///
+/// TrueType and OpenType fonts are checked to parse, and WOFF and WOFF2 fonts are
+/// recognized by their signature and embedded as they are. Subsetting keeps the glyph ids
+/// and character map of the font but removes the outlines of the other glyphs, along with
+/// the layout tables such as kerning, which makes embedding large CJK fonts practical.
+/// WOFF and WOFF2 fonts are decoded to be subset, and subsets are TrueType or OpenType
+/// fonts unless `format` asks for another container. Requires the `font` feature.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_font_url;
+///
+/// const LATO: &[u8] = include_font_url!(
+///     "https://raw.githubusercontent.com/google/fonts/main/ofl/lato/Lato-Regular.ttf",
+///     chars = "0123456789:",
+///     ranges = ["U+0020-007E"],
+/// );
+/// ```
+///
+/// # Options
+///
+/// Besides the [options shared by every macro](crate#options):
+///
+/// * `chars = "..."` - Subset the font to the characters of this string.
+/// * `ranges = ["U+4E00-9FFF"]` - Subset the font to these ranges of code points, in the
+///   syntax of the CSS `unicode-range` descriptor. Combined with `chars` if both are
+///   given.
+/// * `format = "woff2"` - Embed the font in this container, as named by the CSS `format()`
+///   function: `truetype` or `opentype`, `woff` or `woff2`. WOFF2 fonts are encoded
+///   without transforming their tables.
+///
+/// Characters the font has no glyph for are skipped. When skipped through `skip_under`,
+/// the macro expands to an empty slice.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The URL is invalid or the content cannot be fetched
+/// * The content is not a TrueType, OpenType, WOFF or WOFF2 font
+/// * A subset is requested and none of its characters are in the font
+#[cfg(feature = "font")]
+#[proc_macro]
+pub fn include_font_url(input: TokenStream) -> TokenStream {
+    let parser = |input: ParseStream| UrlInput::parse_with(input, &["chars", "ranges", "format"]);
+    let (UrlInput { url, mut options }, extra) = parse_macro_input!(input with parser);
+    let mut chars: Option<std::collections::BTreeSet<char>> = None;
+    let mut format = None;
+    for (key, value) in extra {
+        let parsed = match key.to_string().as_str() {
+            "chars" => syn::parse2(quote! { #value })
+                .map(|value: LitStr| chars.get_or_insert_default().extend(value.value().chars())),
+            "format" => syn::parse2(quote! { #value }).and_then(|value: LitStr| {
+                let kind = font::FontKind::from_format(&value.value());
+                format = Some(kind.map_err(|err| syn::Error::new_spanned(&value, err))?);
+                Ok(())
+            }),
+            _ => string_list(&value).and_then(|ranges| {
+                for range in ranges {
+                    let (start, end) = font::unicode_range(&range)
+                        .map_err(|err| syn::Error::new_spanned(&value, err))?;
+                    chars.get_or_insert_default().extend(start..=end);
+                }
+                Ok(())
+            }),
+        };
+        if let Err(err) = parsed {
+            return err.to_compile_error().into();
+        }
+    }
+    if skip_requested(&options.skip_under) {
+        return quote! { b"" }.into();
+    }
+
+    let bytes = reject_bytes_options(&options, "include_font_url")
+        .and_then(|_| cached_url_content(&url.value(), CompressKind::None, &mut options.fetch))
+        .and_then(|path| {
+            let content =
+                std::fs::read(&path).map_err(|e| format!("Failed to open cache file: {}", e))?;
+            let kind = font::validate(&content)?;
+            let font = match (&chars, format) {
+                (None, None) => return embed_bytes(&path, None),
+                (None, Some(format)) if format == kind => return embed_bytes(&path, None),
+                (None, Some(format)) => {
+                    font::encode(font::to_sfnt(&content)?.into_owned(), format)?
+                }
+                (Some(chars), format) => {
+                    let subset = font::subset(&content, chars)?;
+                    font::encode(subset, format.unwrap_or(font::FontKind::Sfnt))?
+                }
+            };
+            let font = proc_macro2::Literal::byte_string(&font);
+            Ok(quote! { #font })
+        });
+    match bytes {
+        Ok(bytes) => {
+            let tracking = env_tracking(&options.fetch);
+            quote! {{
+                #tracking
+                #bytes
+            }}
+            .into()
+        }
+        Err(err) => compile_error(err),
+    }
+}
+
+/// A procedural macro that includes an SVG image from a URL at compile time, expanding to
+/// its markup as a `&'static str`.
+///
+/// The image must be well-formed XML with an `<svg>` root element. With `optimize = true`,
+/// it is cleaned up losslessly before it is embedded, in the spirit of SVGO: the XML
+/// declaration, comments, `<metadata>` and the elements and attributes of editors such as
+/// Inkscape and Illustrator are removed, whitespace between elements and in path data is
+/// collapsed, groups without attributes are unwrapped, group transforms are moved onto
+/// their only child and unused namespace declarations are dropped. Requires the `svg`
+/// feature.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_svg_url;
+///
+/// const LOGO: &str = include_svg_url!("https://example.com/logo.svg", optimize = true);
+/// ```
+///
+/// # Options
+///
+/// Besides the [options shared by every macro](crate#options):
+///
+/// * `optimize = true` - Clean up the image before embedding it. Defaults to `false`.
+///
+/// When skipped through `skip_under`, the macro expands to an empty string.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The URL is invalid or the content cannot be fetched
+/// * The response is not well-formed XML or its root element is not `<svg>`
+#[cfg(feature = "svg")]
+#[proc_macro]
+pub fn include_svg_url(input: TokenStream) -> TokenStream {
+    let parser = |input: ParseStream| UrlInput::parse_with(input, &["optimize"]);
+    let (UrlInput { url, mut options }, extra) = parse_macro_input!(input with parser);
+    let optimize = match extra
+        .last()
+        .map(|(_, value)| syn::parse2(quote! { #value }).map(|value: LitBool| value.value))
+        .transpose()
+    {
+        Ok(optimize) => optimize.unwrap_or(false),
+        Err(err) => return err.to_compile_error().into(),
+    };
+    if skip_requested(&options.skip_under) {
+        return quote! { "" }.into();
+    }
+
+    let svg = reject_bytes_options(&options, "include_svg_url")
+        .and_then(|_| read_cached_text(&url, &mut options))
+        .and_then(|content| match optimize {
+            true => svg::optimize(&content),
+            false => svg::validate(&content).map(|_| content),
+        });
+    match svg {
+        Ok(svg) => {
+            let tracking = env_tracking(&options.fetch);
+            quote! {{
+                #tracking
+                #svg
+            }}
+            .into()
+        }
+        Err(err) => compile_error(err),
+    }
+}
+
+/// A procedural macro that includes a Fluent (`.ftl`) localization resource from a URL at
+/// compile time, expanding to its text as a `&'static str`.
+///
+/// The resource is parsed during expansion, so syntax errors in a translation fail the
+/// build instead of surfacing when the resource is loaded at runtime. Messages and terms
+/// defined more than once are rejected too, as `fluent-bundle` refuses to add them.
+/// Requires the `fluent` feature.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_fluent_url;
+///
+/// const MAIN_FTL: &str = include_fluent_url!("https://translate.example.com/de/main.ftl");
+///
+/// let resource = fluent_bundle::FluentResource::try_new(MAIN_FTL.to_string()).unwrap();
+/// ```
+///
+/// See the [crate level documentation](crate#options) for the supported options. When
+/// skipped through `skip_under`, the macro expands to an empty string.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The URL is invalid or the content cannot be fetched
+/// * The resource has syntax errors, reported with their line
+/// * A message or term is defined more than once
+#[cfg(feature = "fluent")]
+#[proc_macro]
+pub fn include_fluent_url(input: TokenStream) -> TokenStream {
+    let UrlInput { url, mut options } = parse_macro_input!(input as UrlInput);
+    if skip_requested(&options.skip_under) {
+        return quote! { "" }.into();
+    }
+
+    let content = reject_bytes_options(&options, "include_fluent_url")
+        .and_then(|_| read_cached_text(&url, &mut options))
+        .and_then(|content| formats::validate_fluent(&content).map(|_| content));
+    match content {
+        Ok(content) => {
+            let tracking = env_tracking(&options.fetch);
+            quote! {{
+                #tracking
+                #content
+            }}
+            .into()
+        }
+        Err(err) => compile_error(err),
+    }
+}
+
+/// A procedural macro that fetches a Markdown document from a URL at compile time and
+/// expands to the HTML rendered by `pulldown-cmark` as a `&'static str`.
+///
+/// Tables, footnotes, strikethrough and task lists are enabled by default. HTML in the
+/// document is passed through as it is unless `sanitize = true` is given, so only render
+/// documents from sources you trust without it. Requires the `markdown` feature.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_md_url;
+///
+/// const README: &str = include_md_url!(
+///     "https://raw.githubusercontent.com/serde-rs/serde/master/README.md",
+///     extensions = ["tables", "smart_punctuation"],
+///     sanitize = true,
+/// );
+/// ```
+///
+/// # Options
+///
+/// Besides the [options shared by every macro](crate#options):
+///
+/// * `extensions = ["tables", "footnotes"]` - Enable only the listed extensions, out of
+///   `tables`, `footnotes`, `strikethrough`, `tasklists`, `smart_punctuation`,
+///   `heading_attributes`, `definition_lists` and `math`. An empty list renders plain
+///   CommonMark.
+/// * `sanitize = true` - Clean the rendered HTML with `ammonia`, removing scripts, event
+///   handlers and other markup that is unsafe to display.
+///
+/// When skipped through `skip_under`, the macro expands to an empty string.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The URL is invalid or the content cannot be fetched
+/// * The content is not valid UTF-8
+/// * An unknown extension is listed
+#[cfg(feature = "markdown")]
+#[proc_macro]
+pub fn include_md_url(input: TokenStream) -> TokenStream {
+    let parser = |input: ParseStream| UrlInput::parse_with(input, &["extensions", "sanitize"]);
+    let (UrlInput { url, mut options }, extra) = parse_macro_input!(input with parser);
+    let mut markdown_options = formats::markdown_options(formats::DEFAULT_MARKDOWN_EXTENSIONS)
+        .expect("the default extensions are known");
+    let mut sanitize = false;
+    for (key, value) in extra {
+        let parsed = match key.to_string().as_str() {
+            "extensions" => string_list(&value).and_then(|extensions| {
+                formats::markdown_options(&extensions)
+                    .map(|parsed| markdown_options = parsed)
+                    .map_err(|err| syn::Error::new_spanned(&value, err))
+            }),
+            _ => syn::parse2(quote! { #value }).map(|value: LitBool| sanitize = value.value),
+        };
+        if let Err(err) = parsed {
+            return err.to_compile_error().into();
+        }
+    }
+    if skip_requested(&options.skip_under) {
+        return quote! { "" }.into();
+    }
+
+    let html = reject_bytes_options(&options, "include_md_url")
+        .and_then(|_| read_cached_text(&url, &mut options))
+        .map(|content| formats::render_markdown(&content, markdown_options, sanitize));
+    match html {
+        Ok(html) => {
+            let tracking = env_tracking(&options.fetch);
+            quote! {{
+                #tracking
+                #html
+            }}
+            .into()
+        }
+        Err(err) => compile_error(err),
+    }
+}
+
+/// A procedural macro that fetches an HTML page at compile time and expands to the
+/// elements matching a CSS selector as a `&'static str`, instead of the whole page.
+///
+/// Every matching element is embedded with its outer HTML, one per line, or with its text
+/// content when `text = true` is given, in which case runs of whitespace are collapsed
+/// into single spaces as a browser would display them. Requires the `html` feature.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_html_url;
+///
+/// const VERSIONS: &str =
+///     include_html_url!("https://example.com/downloads.html", select = "table#versions");
+/// const LATEST: &str = include_html_url!(
+///     "https://example.com/downloads.html",
+///     select = "table#versions tr:first-child td",
+///     text = true,
+/// );
+/// ```
+///
+/// # Options
+///
+/// Besides the [options shared by every macro](crate#options):
+///
+/// * `select = "table#versions"` - The CSS selector of the elements to embed, which is
+///   required.
+/// * `text = true` - Embed the text content of the elements instead of their HTML.
+///
+/// When skipped through `skip_under`, the macro expands to an empty string.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The URL is invalid or the content cannot be fetched
+/// * `select` is missing or not a valid CSS selector
+/// * No element of the page matches the selector
+#[cfg(feature = "html")]
+#[proc_macro]
+pub fn include_html_url(input: TokenStream) -> TokenStream {
+    let parser = |input: ParseStream| UrlInput::parse_with(input, &["select", "text"]);
+    let (UrlInput { url, mut options }, extra) = parse_macro_input!(input with parser);
+    let mut selector = None;
+    let mut text = false;
+    for (key, value) in extra {
+        let parsed = match key.to_string().as_str() {
+            "select" => syn::parse2(quote! { #value }).and_then(|value: LitStr| {
+                formats::css_selector(&value.value())
+                    .map(|parsed| selector = Some(parsed))
+                    .map_err(|err| syn::Error::new(value.span(), err))
+            }),
+            _ => syn::parse2(quote! { #value }).map(|value: LitBool| text = value.value),
+        };
+        if let Err(err) = parsed {
+            return err.to_compile_error().into();
+        }
+    }
+    let Some(selector) = selector else {
+        let err = "`include_html_url!` requires a `select = \"...\"` option";
+        return syn::Error::new(url.span(), err).to_compile_error().into();
+    };
+    if skip_requested(&options.skip_under) {
+        return quote! { "" }.into();
+    }
+
+    let html = reject_bytes_options(&options, "include_html_url")
+        .and_then(|_| read_cached_text(&url, &mut options))
+        .and_then(|content| formats::select_html(&content, &selector, text));
+    match html {
+        Ok(html) => {
+            let tracking = env_tracking(&options.fetch);
+            quote! {{
+                #tracking
+                #html
+            }}
+            .into()
+        }
+        Err(err) => compile_error(err),
+    }
+}
+
+/// A procedural macro that includes a GeoJSON document from a URL at compile time,
+/// validating it against RFC 7946.
+///
+/// Every object must have the members its type requires, positions must be longitudes and
+/// latitudes in range, line strings need at least two positions and polygon rings must be
+/// closed with at least four. The macro expands to the validated text as a
+/// `&'static str`, or deserializes it into the given type with `serde_json::from_str`,
+/// such as `geojson::GeoJson` when the calling crate depends on `geojson`.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_geojson_url;
+///
+/// const BORDERS: &str = include_geojson_url!("https://example.com/borders.geojson");
+///
+/// let borders = include_geojson_url!("https://example.com/borders.geojson", geojson::GeoJson);
+///
+/// const OUTLINES: &[&[[f64; 2]]] = include_geojson_url!(
+///     "https://example.com/borders.geojson",
+///     coordinates = true,
+///     simplify = 0.01,
+/// );
+/// ```
+///
+/// # Options
+///
+/// Besides the [options shared by every macro](crate#options) and the `with` conversion of
+/// [`include_json_url!`]:
+///
+/// * `coordinates = true` - Embed the coordinates as `&'static [&'static [[f64; 2]]]`
+///   instead, one `[longitude, latitude]` path per line string, polygon ring and
+///   multi-point, with points as paths of one position. Altitudes are dropped.
+/// * `simplify = 0.01` - Simplify the embedded paths with the Douglas-Peucker algorithm,
+///   dropping positions closer than the tolerance, in degrees, to the simplified path.
+///   Rings that would collapse are kept as they are. Requires `coordinates = true`.
+///
+/// When skipped through `skip_under`, the macro expands to `Default::default()` of the
+/// type, an empty string or an empty slice.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The URL is invalid or the content cannot be fetched
+/// * The response is not valid JSON or not valid GeoJSON, reported with the path of the
+///   offending member
+/// * `coordinates` is combined with a type or `with`, or `simplify` is given without it
+#[proc_macro]
+pub fn include_geojson_url(input: TokenStream) -> TokenStream {
+    let parser =
+        |input: ParseStream| TypedUrlInput::parse_with(input, &["coordinates", "simplify"]);
+    let (input, extra) = parse_macro_input!(input with parser);
+    let mut coordinates = false;
+    let mut simplify = None;
+    for (key, value) in extra {
+        let parsed = match key.to_string().as_str() {
+            "coordinates" => {
+                syn::parse2(quote! { #value }).map(|value: LitBool| coordinates = value.value)
+            }
+            _ => syn::parse2(quote! { #value })
+                .and_then(|value: syn::LitFloat| value.base10_parse::<f64>())
+                .or_else(|_| {
+                    syn::parse2(quote! { #value }).and_then(|value: LitInt| value.base10_parse())
+                })
+                .map(|value| simplify = Some(value)),
+        };
+        if let Err(err) = parsed {
+            return err.to_compile_error().into();
+        }
+    }
+    if !coordinates {
+        if simplify.is_some() {
+            let err = "`simplify` requires `coordinates = true`";
+            return syn::Error::new(input.url.span(), err)
+                .to_compile_error()
+                .into();
+        }
+        return expand_text_format(
+            input,
+            TextFormat {
+                macro_name: "include_geojson_url",
+                name: "GeoJSON",
+                validate: geo::validate,
+                normalize: None,
+                from_str: quote! { serde_json::from_str },
+                value_ty: None,
+            },
+        );
+    }
+
+    let TypedUrlInput {
+        url,
+        ty,
+        with,
+        csv,
+        mut options,
+    } = input;
+    if ty.is_some() || with.is_some() {
+        let err = "`coordinates` cannot be combined with a type or `with`";
+        return syn::Error::new(url.span(), err).to_compile_error().into();
+    }
+    if skip_requested(&options.skip_under) {
+        return quote! { &[] }.into();
+    }
+
+    let paths = reject_bytes_options(&options, "include_geojson_url")
+        .and_then(|_| match csv == CsvOptions::default() {
+            true => Ok(()),
+            false => Err(
+                "`delimiter` and `check_headers` are not supported by `include_geojson_url!`"
+                    .to_string(),
+            ),
+        })
+        .and_then(|_| read_cached_text(&url, &mut options))
+        .and_then(|content| geo::paths(&content));
+    match paths {
+        Ok(paths) => {
+            let paths = paths.iter().map(|path| {
+                let path = match simplify {
+                    Some(tolerance) => geo::simplify(path, tolerance),
+                    None => path.clone(),
+                };
+                let positions = path.iter().map(|[longitude, latitude]| {
+                    let longitude = proc_macro2::Literal::f64_unsuffixed(*longitude);
+                    let latitude = proc_macro2::Literal::f64_unsuffixed(*latitude);
+                    quote! { [#longitude, #latitude] }
+                });
+                quote! { &[#(#positions),*] }
+            });
+            let tracking = env_tracking(&options.fetch);
+            quote! {{
+                #tracking
+                const PATHS: &[&[[f64; 2]]] = &[#(#paths),*];
+                PATHS
+            }}
+            .into()
+        }
+        Err(err) => compile_error(err),
+    }
+}
+
+/// A procedural macro that includes a PEM bundle, such as Mozilla's `cacert.pem`, from a
+/// URL at compile time, expanding to its certificates as `&'static [&'static str]`.
+///
+/// The bundle is validated during expansion: every block must be well-formed and at least
+/// one must be a certificate. Text between blocks is dropped and each certificate is
+/// embedded as a normalized PEM block. Requires the `pem` feature.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_pem_url;
+///
+/// const ROOTS: &[&str] = include_pem_url!("https://curl.se/ca/cacert.pem");
+/// const ROOTS_DER: &[&[u8]] = include_pem_url!("https://curl.se/ca/cacert.pem", der = true);
+/// ```
+///
+/// # Options
+///
+/// Besides the [options shared by every macro](crate#options):
+///
+/// * `der = true` - Embed the DER bytes of each block as `&'static [&'static [u8]]`.
+/// * `label = "PUBLIC KEY"` - Embed the blocks with this label instead of certificates.
+///
+/// When skipped through `skip_under`, the macro expands to an empty slice.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The URL is invalid or the content cannot be fetched
+/// * A PEM block is malformed or not terminated
+/// * The bundle has no block with the requested label
+#[cfg(feature = "pem")]
+#[proc_macro]
+pub fn include_pem_url(input: TokenStream) -> TokenStream {
+    let parser = |input: ParseStream| UrlInput::parse_with(input, &["der", "label"]);
+    let (UrlInput { url, mut options }, extra) = parse_macro_input!(input with parser);
+    let mut der = false;
+    let mut label = "CERTIFICATE".to_string();
+    for (key, value) in extra {
+        let parsed = match key.to_string().as_str() {
+            "der" => syn::parse2(quote! { #value }).map(|value: LitBool| der = value.value),
+            _ => syn::parse2(quote! { #value }).map(|value: LitStr| label = value.value()),
+        };
+        if let Err(err) = parsed {
+            return err.to_compile_error().into();
+        }
+    }
+    if skip_requested(&options.skip_under) {
+        return quote! { &[] }.into();
+    }
+
+    let blocks = reject_bytes_options(&options, "include_pem_url")
+        .and_then(|_| read_cached_text(&url, &mut options))
+        .and_then(|content| formats::pem_blocks(&content, &label));
+    match blocks {
+        Ok(blocks) => {
+            let tracking = env_tracking(&options.fetch);
+            let config = pem::EncodeConfig::new().set_line_ending(pem::LineEnding::LF);
+            let blocks = blocks.iter().map(|block| match der {
+                true => {
+                    let bytes = proc_macro2::Literal::byte_string(block.contents());
+                    quote! { #bytes as &[u8] }
+                }
+                false => {
+                    let text = pem::encode_config(block, config);
+                    quote! { #text }
+                }
+            });
+            quote! {{
+                #tracking
+                &[#(#blocks),*]
+            }}
+            .into()
+        }
+        Err(err) => compile_error(err),
+    }
+}
+
+/// Expands an INI or properties file into a module of string constants.
+fn expand_ini(input: ModuleUrlInput, macro_name: &str, properties: bool) -> TokenStream {
+    let ModuleUrlInput {
+        vis,
+        name,
+        input: UrlInput { url, mut options },
+    } = input;
+    let sections = reject_bytes_options(&options, macro_name)
+        .and_then(|_| reject_skip_under(&options, macro_name))
+        .and_then(|_| read_cached_text(&url, &mut options))
+        .and_then(|content| formats::parse_ini(&content, properties));
+    let sections = match sections {
+        Ok(sections) => sections,
+        Err(err) => return compile_error(err),
+    };
+
+    let mut modules = Vec::new();
+    let mut root = None;
+    for section in sections {
+        let mut names = std::collections::BTreeSet::from(["ENTRIES".to_string()]);
+        let mut consts = Vec::new();
+        for (key, value) in &section.entries {
+            let Some(ident) = const_ident(key).filter(|ident| names.insert(ident.to_string()))
+            else {
+                let err = format!("The key `{}` does not map to a unique constant name", key);
+                return syn::Error::new(url.span(), err).to_compile_error().into();
+            };
+            consts.push(quote! { pub const #ident: &str = #value; });
+        }
+        let keys = section.entries.iter().map(|(key, _)| key);
+        let values = section.entries.iter().map(|(_, value)| value);
+        let items = quote! {
+            #(#consts)*
+            /// Every entry of the section, in file order.
+            pub const ENTRIES: &[(&str, &str)] = &[#((#keys, #values)),*];
+        };
+        match &section.name {
+            None => root = Some(items),
+            Some(section) => {
+                let Some(ident) = snake_ident(section) else {
+                    let err = format!("The section `{}` does not map to a module name", section);
+                    return syn::Error::new(url.span(), err).to_compile_error().into();
+                };
+                modules.push(quote! {
+                    pub mod #ident {
+                        #items
+                    }
+                });
+            }
+        }
+    }
+
+    let tracking = env_tracking(&options.fetch);
+    let output = quote! {
+        #vis mod #name {
+            #tracking
+            #root
+            #(#modules)*
+        }
+    };
+    output.into()
+}
+
+/// A procedural macro that fetches an INI file at compile time and expands it into a
+/// module of `pub const` string items.
+///
+/// Keys become `SCREAMING_SNAKE_CASE` constants and every `[section]` becomes a nested
+/// `snake_case` module. Each module also has an `ENTRIES` slice of the original key and
+/// value pairs, for lookups by name. Values wrapped in double quotes are unquoted.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_ini_url;
+///
+/// include_ini_url!(pub mod legacy, "https://config.example.com/app.ini");
+///
+/// // host = localhost in the [database] section
+/// assert_eq!(legacy::database::HOST, "localhost");
+/// ```
+///
+/// See the [crate level documentation](crate#options) for the supported options,
+/// except `skip_under` as the generated items would be missing.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The URL is invalid or the content cannot be fetched
+/// * A line is neither a comment, a section header nor a `key = value` entry
+/// * Two keys of a section map to the same constant name
+#[proc_macro]
+pub fn include_ini_url(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as ModuleUrlInput);
+    expand_ini(input, "include_ini_url", false)
+}
+
+/// A procedural macro that fetches a Java `.properties` file at compile time and expands
+/// it into a module of `pub const` string items.
+///
+/// Keys become `SCREAMING_SNAKE_CASE` constants, so `server.port` is available as
+/// `SERVER_PORT`, and the module has an `ENTRIES` slice of the original key and value
+/// pairs. Line continuations and backslash escapes are resolved.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_properties_url;
+///
+/// include_properties_url!(mod messages, "https://config.example.com/messages.properties");
+///
+/// println!("{}", messages::GREETING);
+/// ```
+///
+/// See the [crate level documentation](crate#options) for the supported options,
+/// except `skip_under` as the generated items would be missing.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The URL is invalid or the content cannot be fetched
+/// * Two keys map to the same constant name
+#[proc_macro]
+pub fn include_properties_url(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as ModuleUrlInput);
+    expand_ini(input, "include_properties_url", true)
+}
+
+/// A procedural macro that fetches a dotenv file at compile time and expands to a module
+/// of its entries as `pub const KEY: &str` items.
+///
+/// Constants are named after their key in `SCREAMING_SNAKE_CASE`, and `ENTRIES` lists every
+/// embedded entry in file order. `${VAR}` references are embedded as they are. Entries that
+/// look like secrets, by a key such as `DB_PASSWORD` or a value shaped like a token, private
+/// key or URL with credentials, are refused unless listed in `allow_secrets`, as anything
+/// embedded can be read from the binary.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_env_url;
+///
+/// include_env_url!(
+///     pub mod config,
+///     "https://config.example.com/app/production.env",
+///     keys = ["API_URL", "REGION"],
+/// );
+///
+/// println!("{} in {}", config::API_URL, config::REGION);
+/// ```
+///
+/// # Options
+///
+/// Besides the [options shared by every macro](crate#options), except `skip_under` as the
+/// generated items would be missing:
+///
+/// * `keys = ["API_URL", "REGION"]` - Embed only the listed keys, which must be present.
+/// * `allow_secrets = ["PUBLIC_TOKEN"]` - Embed the listed keys even if they look like
+///   secrets.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The URL is invalid or the content cannot be fetched
+/// * A line is malformed, reported with its number
+/// * A key listed in `keys` is missing
+/// * An embedded entry looks like a secret and is not listed in `allow_secrets`
+#[proc_macro]
+pub fn include_env_url(input: TokenStream) -> TokenStream {
+    let parser = ModuleUrlInput::parser(&["keys", "allow_secrets"]);
+    let (
+        ModuleUrlInput {
+            vis,
+            name,
+            input: UrlInput { url, mut options },
+        },
+        extra,
+    ) = parse_macro_input!(input with parser);
+    let mut keys = None;
+    let mut allow_secrets = Vec::new();
+    for (key, value) in extra {
+        match string_list(&value) {
+            Ok(list) if key == "keys" => keys = Some(list),
+            Ok(list) => allow_secrets = list,
+            Err(err) => return err.to_compile_error().into(),
+        }
+    }
+
+    let entries = reject_bytes_options(&options, "include_env_url")
+        .and_then(|_| reject_skip_under(&options, "include_env_url"))
+        .and_then(|_| read_cached_text(&url, &mut options))
+        .and_then(|content| formats::parse_dotenv(&content))
+        .and_then(|mut entries| {
+            if let Some(keys) = &keys {
+                if let Some(missing) = keys
+                    .iter()
+                    .find(|key| !entries.iter().any(|(k, _)| k == *key))
+                {
+                    return Err(format!("The dotenv file has no key `{}`", missing));
+                }
+                entries.retain(|(key, _)| keys.contains(key));
+            }
+            for (key, value) in &entries {
+                if allow_secrets.contains(key) {
+                    continue;
+                }
+                if let Some(reason) = formats::secret_reason(key, value) {
+                    return Err(format!(
+                        "Refusing to embed `{}` as {}, list it in `allow_secrets` to embed it \
+                         anyway",
+                        key, reason
+                    ));
+                }
+            }
+            Ok(entries)
+        });
+    let entries = match entries {
+        Ok(entries) => entries,
+        Err(err) => return compile_error(err),
+    };
+
+    let mut names = std::collections::BTreeSet::from(["ENTRIES".to_string()]);
+    let mut consts = Vec::new();
+    for (key, value) in &entries {
+        let Some(ident) = const_ident(key).filter(|ident| names.insert(ident.to_string())) else {
+            let err = format!("The key `{}` does not map to a unique constant name", key);
+            return syn::Error::new(url.span(), err).to_compile_error().into();
+        };
+        consts.push(quote! { pub const #ident: &str = #value; });
+    }
+    let keys = entries.iter().map(|(key, _)| key);
+    let values = entries.iter().map(|(_, value)| value);
+    let tracking = env_tracking(&options.fetch);
+    let output = quote! {
+        #vis mod #name {
+            #tracking
+            #(#consts)*
+            /// Every embedded entry, in file order.
+            pub const ENTRIES: &[(&str, &str)] = &[#((#keys, #values)),*];
+        }
+    };
+    output.into()
+}
+
+/// A procedural macro that fetches a gettext `.po` catalog at compile time and compiles it
+/// into a module of static lookup tables.
+///
+/// Like `msgfmt`, fuzzy, obsolete and untranslated entries are left out. The module holds
+/// the `HEADER` fields and the sorted `MESSAGES`, and provides `gettext`, `pgettext`,
+/// `ngettext` and `npgettext` functions that fall back to the untranslated text. The C
+/// expression of the `Plural-Forms` header is compiled into the module's `plural`
+/// function.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_po_url;
+///
+/// include_po_url!(pub mod de, "https://translate.example.com/app/de.po");
+///
+/// println!("{}", de::gettext("Hello"));
+/// println!("{}", de::ngettext("One file", "{n} files", 3));
+/// ```
+///
+/// See the [crate level documentation](crate#options) for the supported options,
+/// except `skip_under` as the generated items would be missing.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The URL is invalid or the content cannot be fetched
+/// * An entry is malformed, reported with its line
+/// * A message is translated more than once or has the wrong number of plural forms
+/// * The `Plural-Forms` header is invalid
+#[proc_macro]
+pub fn include_po_url(input: TokenStream) -> TokenStream {
+    let ModuleUrlInput {
+        vis,
+        name,
+        input: UrlInput { url, mut options },
+    } = parse_macro_input!(input as ModuleUrlInput);
+    let catalog = reject_bytes_options(&options, "include_po_url")
+        .and_then(|_| reject_skip_under(&options, "include_po_url"))
+        .and_then(|_| read_cached_text(&url, &mut options))
+        .and_then(|content| gettext::parse(&content));
+
+    match catalog {
+        Ok(catalog) => {
+            let tracking = env_tracking(&options.fetch);
+            let items = gettext::generate(&catalog);
+            quote! {
+                #vis mod #name {
+                    #tracking
+                    #items
+                }
+            }
+            .into()
+        }
+        Err(err) => compile_error(err),
+    }
+}
+
+/// A procedural macro that fetches an iCalendar (`.ics`) file at compile time and expands
+/// to a module embedding its events.
+///
+/// The calendar is checked against the RFC 5545 syntax: content lines need a name and a
+/// value, components must be nested properly inside a `VCALENDAR`, dates must be
+/// well-formed and every event needs a `UID` and a `DTSTART`. The module holds an `Event`
+/// struct and the events as `EVENTS`, in file order, with their text unescaped and dates
+/// in ISO 8601. Recurrence rules are embedded as they are and local times keep their
+/// `TZID`, without expanding either.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_ical_url;
+///
+/// include_ical_url!(pub mod holidays, "https://calendars.example.com/holidays/cz.ics");
+///
+/// for holiday in holidays::EVENTS {
+///     println!("{}: {}", holiday.start, holiday.summary);
+/// }
+/// ```
+///
+/// See the [crate level documentation](crate#options) for the supported options,
+/// except `skip_under` as the generated items would be missing.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The URL is invalid or the content cannot be fetched
+/// * A content line, component or date is malformed, reported with its line
+/// * An event has no `UID` or `DTSTART`
+#[proc_macro]
+pub fn include_ical_url(input: TokenStream) -> TokenStream {
+    let ModuleUrlInput {
+        vis,
+        name,
+        input: UrlInput { url, mut options },
+    } = parse_macro_input!(input as ModuleUrlInput);
+    let events = reject_bytes_options(&options, "include_ical_url")
+        .and_then(|_| reject_skip_under(&options, "include_ical_url"))
+        .and_then(|_| read_cached_text(&url, &mut options))
+        .and_then(|content| ical::parse(&content));
+
+    match events {
+        Ok(events) => {
+            let tracking = env_tracking(&options.fetch);
+            let items = ical::generate(&events);
+            quote! {
+                #vis mod #name {
+                    #tracking
+                    #items
+                }
+            }
+            .into()
+        }
+        Err(err) => compile_error(err),
+    }
+}
+
+/// A procedural macro that fetches the [Public Suffix List](https://publicsuffix.org/) at
+/// compile time and compiles it into a module of static lookup tables.
+///
+/// The module holds the sorted `SUFFIXES`, `WILDCARDS` and `EXCEPTIONS` rules and provides
+/// `public_suffix(domain: &str) -> Option<&str>` and `registrable_domain(domain: &str) ->
+/// Option<&str>`, which follow the algorithm of the list without any parsing at runtime.
+/// Internationalized rules match both in Unicode and in Punycode.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_public_suffix_url;
+///
+/// include_public_suffix_url!(
+///     pub mod psl,
+///     "https://publicsuffix.org/list/public_suffix_list.dat",
+/// );
+///
+/// assert_eq!(psl::public_suffix("www.example.co.uk"), Some("co.uk"));
+/// assert_eq!(psl::registrable_domain("www.example.co.uk"), Some("example.co.uk"));
+/// ```
+///
+/// # Options
+///
+/// Besides the [options shared by every macro](crate#options), except `skip_under` as the
+/// generated items would be missing:
+///
+/// * `private_domains = false` - Leave out the private domains section, such as
+///   `github.io`, and only embed the suffixes operated by registries.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The URL is invalid or the content cannot be fetched
+/// * The list has no ICANN section or a rule is malformed, reported with its line
+#[proc_macro]
+pub fn include_public_suffix_url(input: TokenStream) -> TokenStream {
+    let parser = ModuleUrlInput::parser(&["private_domains"]);
+    let (
+        ModuleUrlInput {
+            vis,
+            name,
+            input: UrlInput { url, mut options },
+        },
+        extra,
+    ) = parse_macro_input!(input with parser);
+    let private_domains = match extra
+        .last()
+        .map(|(_, value)| syn::parse2(quote! { #value }).map(|value: LitBool| value.value))
+        .transpose()
+    {
+        Ok(private_domains) => private_domains.unwrap_or(true),
+        Err(err) => return err.to_compile_error().into(),
+    };
+    let rules = reject_bytes_options(&options, "include_public_suffix_url")
+        .and_then(|_| reject_skip_under(&options, "include_public_suffix_url"))
+        .and_then(|_| read_cached_text(&url, &mut options))
+        .and_then(|content| psl::parse(&content, private_domains));
+
+    match rules {
+        Ok(rules) => {
+            let tracking = env_tracking(&options.fetch);
+            let items = psl::generate(&rules);
+            quote! {
+                #vis mod #name {
+                    #tracking
+                    #items
+                }
+            }
+            .into()
+        }
+        Err(err) => compile_error(err),
+    }
+}
+
+/// A procedural macro that fetches a MIME type database at compile time and compiles it
+/// into a module of lookup tables.
+///
+/// Both the Apache `mime.types` format and the `db.json` of the `mime-db` package are
+/// read. The module holds the sorted `EXTENSIONS` and `TYPES` tables and provides
+/// `from_extension`, `from_path` and `extensions` lookups, which ignore ASCII case. An
+/// extension claimed by several media types maps to the first one in the database.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_mime_db_url;
+///
+/// include_mime_db_url!(
+///     pub mod mime_types,
+///     "https://svn.apache.org/repos/asf/httpd/httpd/trunk/docs/conf/mime.types",
+/// );
+///
+/// assert_eq!(mime_types::from_extension("css"), Some("text/css"));
+/// assert_eq!(mime_types::from_path("/static/logo.PNG"), Some("image/png"));
+/// assert_eq!(mime_types::extensions("image/jpeg")[0], "jpeg");
+/// ```
+///
+/// See the [crate level documentation](crate#options) for the supported options,
+/// except `skip_under` as the generated items would be missing.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The URL is invalid or the content cannot be fetched
+/// * A media type or extension is malformed, reported with its line
+/// * The database has no media types
+#[proc_macro]
+pub fn include_mime_db_url(input: TokenStream) -> TokenStream {
+    let ModuleUrlInput {
+        vis,
+        name,
+        input: UrlInput { url, mut options },
+    } = parse_macro_input!(input as ModuleUrlInput);
+    let types = reject_bytes_options(&options, "include_mime_db_url")
+        .and_then(|_| reject_skip_under(&options, "include_mime_db_url"))
+        .and_then(|_| read_cached_text(&url, &mut options))
+        .and_then(|content| mime_db::parse(&content));
+
+    match types {
+        Ok(types) => {
+            let tracking = env_tracking(&options.fetch);
+            let items = mime_db::generate(&types);
+            quote! {
+                #vis mod #name {
+                    #tracking
+                    #items
+                }
+            }
+            .into()
+        }
+        Err(err) => compile_error(err),
+    }
+}
+
+/// A procedural macro that fetches a Unicode Character Database file, such as
+/// `UnicodeData.txt` or `emoji-data.txt`, at compile time and compiles it into a module of
+/// range tables.
+///
+/// The code points are grouped by the value of one of the `;` separated fields, the
+/// property name in files like `emoji-data.txt` or `PropList.txt` by default. For every
+/// value, the module holds a sorted `&[(char, char)]` table of inclusive ranges named
+/// after it in `SCREAMING_SNAKE_CASE`, and an `is_` function checking whether a `char` is
+/// in it with a binary search. `<..., First>` and `<..., Last>` entries of
+/// `UnicodeData.txt` are joined into ranges and surrogates are left out.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_unicode_data_url;
+///
+/// include_unicode_data_url!(
+///     pub mod emoji,
+///     "https://www.unicode.org/Public/16.0.0/ucd/emoji/emoji-data.txt",
+///     values = ["Emoji_Presentation", "Extended_Pictographic"],
+/// );
+///
+/// assert!(emoji::is_emoji_presentation('😀'));
+///
+/// // The general categories are the third field of `UnicodeData.txt`
+/// include_unicode_data_url!(
+///     pub mod categories,
+///     "https://www.unicode.org/Public/16.0.0/ucd/UnicodeData.txt",
+///     field = 2,
+///     values = ["Lu", "Ll"],
+/// );
+///
+/// assert!(categories::is_lu('Ä'));
+/// ```
+///
+/// # Options
+///
+/// Besides the [options shared by every macro](crate#options), except `skip_under` as the
+/// generated items would be missing:
+///
+/// * `field = 2` - The index of the field to group by, counting the code points as field
+///   0. Defaults to 1.
+/// * `values = ["Lu", "Ll"]` - Generate tables only for the listed values, which must be
+///   present. Without it, a table is generated for every value of the field.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The URL is invalid or the content cannot be fetched
+/// * A line has an invalid code point or lacks the field, reported with its number
+/// * A value listed in `values` has no code points
+/// * Two values map to the same constant name
+#[proc_macro]
+pub fn include_unicode_data_url(input: TokenStream) -> TokenStream {
+    let parser = ModuleUrlInput::parser(&["field", "values"]);
+    let (
+        ModuleUrlInput {
+            vis,
+            name,
+            input: UrlInput { url, mut options },
+        },
+        extra,
+    ) = parse_macro_input!(input with parser);
+    let mut field = 1;
+    let mut values = None;
+    for (key, value) in extra {
+        let parsed = match key.to_string().as_str() {
+            "field" => syn::parse2(quote! { #value })
+                .and_then(|value: LitInt| value.base10_parse())
+                .map(|value| field = value),
+            _ => string_list(&value).map(|list| values = Some(list)),
+        };
+        if let Err(err) = parsed {
+            return err.to_compile_error().into();
+        }
+    }
+    let items = reject_bytes_options(&options, "include_unicode_data_url")
+        .and_then(|_| reject_skip_under(&options, "include_unicode_data_url"))
+        .and_then(|_| read_cached_text(&url, &mut options))
+        .and_then(|content| ucd::parse(&content, field, values.as_deref()))
+        .and_then(|tables| ucd::generate(&tables));
+
+    match items {
+        Ok(items) => {
+            let tracking = env_tracking(&options.fetch);
+            quote! {
+                #vis mod #name {
+                    #tracking
+                    #items
+                }
+            }
+            .into()
+        }
+        Err(err) => compile_error(err),
+    }
+}
+
+/// A procedural macro that fetches a word list at compile time and compiles it into a
+/// module with a static set of its words.
+///
+/// The list has one word per line, such as a dictionary, a list of stop words or a
+/// blocklist. Surrounding whitespace and blank lines are ignored. The module holds the
+/// sorted `WORDS` slice and a `contains` function that binary searches it.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_wordlist_url;
+///
+/// include_wordlist_url!(
+///     pub mod stop_words,
+///     "https://example.com/stop-words.txt",
+///     lowercase = true,
+/// );
+///
+/// assert!(stop_words::contains("The"));
+/// ```
+///
+/// # Options
+///
+/// * `lowercase = true` lowercases the words, and makes `contains` lowercase the word it
+///   looks up, so the set ignores case. Defaults to `false`.
+/// * `dedup = false` keeps duplicate words in `WORDS`. Defaults to `true`.
+///
+/// See the [crate level documentation](crate#options) for the other options, except
+/// `skip_under` as the generated items would be missing.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The URL is invalid or the content cannot be fetched
+/// * The list has no words
+#[proc_macro]
+pub fn include_wordlist_url(input: TokenStream) -> TokenStream {
+    let parser = ModuleUrlInput::parser(&["lowercase", "dedup"]);
+    let (
+        ModuleUrlInput {
+            vis,
+            name,
+            input: UrlInput { url, mut options },
+        },
+        extra,
+    ) = parse_macro_input!(input with parser);
+    let mut lowercase = false;
+    let mut dedup = true;
+    for (key, value) in extra {
+        let parsed =
+            syn::parse2(quote! { #value }).map(|value: LitBool| match key.to_string().as_str() {
+                "lowercase" => lowercase = value.value,
+                _ => dedup = value.value,
+            });
+        if let Err(err) = parsed {
+            return err.to_compile_error().into();
+        }
+    }
+    let words = reject_bytes_options(&options, "include_wordlist_url")
+        .and_then(|_| reject_skip_under(&options, "include_wordlist_url"))
+        .and_then(|_| read_cached_text(&url, &mut options))
+        .and_then(|content| wordlist::parse(&content, lowercase, dedup));
+
+    match words {
+        Ok(words) => {
+            let tracking = env_tracking(&options.fetch);
+            let items = wordlist::generate(&words, lowercase);
+            quote! {
+                #vis mod #name {
+                    #tracking
+                    #items
+                }
+            }
+            .into()
+        }
+        Err(err) => compile_error(err),
+    }
+}
+
+/// A procedural macro that fetches the IANA time zone database at compile time and
+/// compiles its zones into a module of static transition tables.
+///
+/// The URL points to a release archive such as `tzdata2025b.tar.gz`, gzip compressed or
+/// not, or to a single source file in the `zic` format. The module holds an `Offset` type,
+/// with the standard and daylight saving offsets and the abbreviation, and a `Zone` type
+/// whose `offset_at` method returns the offset in effect at a Unix timestamp. Every zone
+/// and link becomes a `Zone` constant named after it in `SCREAMING_SNAKE_CASE`, with the
+/// signs of offsets spelled out (`Etc/GMT-3` is `ETC_GMT_MINUS_3`), and `find` looks
+/// the zones up by name in the sorted `ZONES` table. Recurring rules are expanded up to the
+/// year 2100, after which the last offset stays in effect. `VERSION` holds the release of
+/// an archive with a `version` file. Requires the `tzdata` feature.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_tzdata_url;
+///
+/// include_tzdata_url!(
+///     pub mod tz,
+///     "https://data.iana.org/time-zones/releases/tzdata2025b.tar.gz",
+///     zones = ["Europe/Paris", "America/New_York", "US/Eastern"],
+/// );
+///
+/// let offset = tz::EUROPE_PARIS.offset_at(1_750_000_000);
+/// assert_eq!((offset.total(), offset.abbreviation), (7200, "CEST"));
+/// assert_eq!(tz::find("US/Eastern").map(|zone| zone.name()), Some("US/Eastern"));
+/// ```
+///
+/// # Options
+///
+/// Besides the [options shared by every macro](crate#options), except `skip_under` as the
+/// generated items would be missing:
+///
+/// * `zones = ["Europe/Paris"]` - Generate only the listed zones and links, which must be
+///   in the database. Every zone and link is generated by default.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The URL is invalid or the content cannot be fetched
+/// * A line of the database does not parse, reported with its file and number
+/// * A zone listed in `zones` is not in the database
+/// * Two zones map to the same constant name
+#[cfg(feature = "tzdata")]
+#[proc_macro]
+pub fn include_tzdata_url(input: TokenStream) -> TokenStream {
+    let parser = ModuleUrlInput::parser(&["zones"]);
+    let (
+        ModuleUrlInput {
+            vis,
+            name,
+            input: UrlInput { url, mut options },
+        },
+        extra,
+    ) = parse_macro_input!(input with parser);
+    let mut zones = None;
+    for (_, value) in extra {
+        match string_list(&value) {
+            Ok(list) => zones = Some(list),
+            Err(err) => return err.to_compile_error().into(),
+        }
+    }
+    let items = reject_bytes_options(&options, "include_tzdata_url")
+        .and_then(|_| reject_skip_under(&options, "include_tzdata_url"))
+        .and_then(|_| cached_url_content(&url.value(), CompressKind::None, &mut options.fetch))
+        .and_then(|path| {
+            std::fs::read(path).map_err(|e| format!("Failed to open cache file: {}", e))
+        })
+        .and_then(|content| tzdata::parse(&content))
+        .and_then(|database| tzdata::generate(&database, zones.as_deref()));
+
+    match items {
+        Ok(items) => {
+            let tracking = env_tracking(&options.fetch);
+            quote! {
+                #vis mod #name {
+                    #tracking
+                    #items
+                }
+            }
+            .into()
+        }
+        Err(err) => compile_error(err),
+    }
+}
+
+/// A procedural macro that fetches a `.proto` schema at compile time and expands to the
+/// message types generated for it by `prost-build`.
+///
+/// The schema is parsed without `protoc`. It may import the protobuf well-known types,
+/// which map to `prost-types`, but no other files. The generated code needs the calling
+/// crate to depend on `prost` (and `prost-types` when well-known types are used).
+/// Requires the `proto` feature.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// mod registry {
+///     include_url_macro::include_proto_url!("https://schemas.example.com/registry/v1.proto");
+/// }
+///
+/// let request = registry::LookupRequest { name: "demo".into() };
+/// ```
+///
+/// See the [crate level documentation](crate#options) for the supported options,
+/// except `skip_under` as the generated items would be missing.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The URL is invalid or the content cannot be fetched
+/// * The schema does not parse or type check, including unresolved imports
+#[cfg(feature = "proto")]
+#[proc_macro]
+pub fn include_proto_url(input: TokenStream) -> TokenStream {
+    let UrlInput { url, mut options } = parse_macro_input!(input as UrlInput);
+    let url_str = url.value();
+    let file_name = url_str
+        .rsplit('/')
+        .next()
+        .filter(|name| name.ends_with(".proto"))
+        .unwrap_or("schema.proto");
+    let code = reject_bytes_options(&options, "include_proto_url")
+        .and_then(|_| reject_skip_under(&options, "include_proto_url"))
+        .and_then(|_| {
+            options.fetch.text = true;
+            cached_url_content(&url_str, CompressKind::None, &mut options.fetch)
+        })
+        .and_then(|path| proto::generate(&path, file_name))
+        .and_then(|code| {
+            code.parse::<proc_macro2::TokenStream>()
+                .map_err(|e| format!("Failed to parse generated protobuf code: {}", e))
+        });
+
+    match code {
+        Ok(code) => {
+            let tracking = env_tracking(&options.fetch);
+            quote! {
+                #tracking
+                #code
+            }
+            .into()
+        }
+        Err(err) => compile_error(err),
+    }
+}
+
+/// A procedural macro that fetches a GraphQL schema in SDL form at compile time and
+/// expands to a module of serde types for it.
+///
+/// Objects, interfaces and input objects become structs with `snake_case` fields renamed
+/// to their GraphQL names, enums become `PascalCase` enums, unions become enums tagged by
+/// `__typename` and custom scalars become `String` aliases. Nullable fields are `Option`s
+/// and fields of composite types are boxed. The calling crate needs to depend on `serde`
+/// with the `derive` feature. Requires the `graphql` feature.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_graphql_schema_url;
+///
+/// include_graphql_schema_url!(
+///     pub mod github,
+///     "https://docs.github.com/public/fpt/schema.docs.graphql"
+/// );
+///
+/// let response: github::Repository = serde_json::from_str(body)?;
+/// ```
+///
+/// See the [crate level documentation](crate#options) for the supported options,
+/// except `skip_under` as the generated items would be missing.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The URL is invalid or the content cannot be fetched
+/// * The schema does not parse or refers to an undefined type
+#[cfg(feature = "graphql")]
+#[proc_macro]
+pub fn include_graphql_schema_url(input: TokenStream) -> TokenStream {
+    let ModuleUrlInput {
+        vis,
+        name,
+        input: UrlInput { url, mut options },
+    } = parse_macro_input!(input as ModuleUrlInput);
+    let items = reject_bytes_options(&options, "include_graphql_schema_url")
+        .and_then(|_| reject_skip_under(&options, "include_graphql_schema_url"))
+        .and_then(|_| read_cached_text(&url, &mut options))
+        .and_then(|sdl| graphql::generate(&sdl));
+
+    match items {
+        Ok(items) => {
+            let tracking = env_tracking(&options.fetch);
+            quote! {
+                #vis mod #name {
+                    #tracking
+                    #items
+                }
+            }
+            .into()
+        }
+        Err(err) => compile_error(err),
+    }
+}
+
+/// A procedural macro that fetches an OpenAPI 3.x specification at compile time and
+/// expands to a module of the request and response types it defines.
+///
+/// Every schema under `components.schemas` becomes a serde struct or enum, generated by
+/// `typify`. The module also contains an `operations` module with a `(method, path)`
+/// constant for every operation with an `operationId`, so a removed or moved endpoint
+/// fails the build. The calling crate needs to depend on `serde`, and on `chrono`, `uuid`
+/// or `regress` when the schemas use the matching formats or patterns. Requires the
+/// `openapi` feature.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_openapi_url;
+///
+/// include_openapi_url!(pub mod gateway, "https://gateway.example.com/openapi.yaml");
+///
+/// let (method, path) = gateway::operations::GET_PET;
+/// let pet: gateway::Pet = client.request(method, path).send()?.json()?;
+/// ```
+///
+/// See the [crate level documentation](crate#options) for the supported options,
+/// except `skip_under` as the generated items would be missing.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The URL is invalid or the content cannot be fetched
+/// * The response is not an OpenAPI 3.x specification in JSON or YAML
+/// * A schema cannot be converted into Rust types
+#[cfg(feature = "openapi")]
+#[proc_macro]
+pub fn include_openapi_url(input: TokenStream) -> TokenStream {
+    let ModuleUrlInput {
+        vis,
+        name,
+        input: UrlInput { url, mut options },
+    } = parse_macro_input!(input as ModuleUrlInput);
+    let items = reject_bytes_options(&options, "include_openapi_url")
+        .and_then(|_| reject_skip_under(&options, "include_openapi_url"))
+        .and_then(|_| read_cached_text(&url, &mut options))
+        .and_then(|spec| schema::generate_openapi(&spec));
+
+    match items {
+        Ok(items) => {
+            let tracking = env_tracking(&options.fetch);
+            quote! {
+                #vis mod #name {
+                    #tracking
+                    #items
+                }
             }
-            bytes::Bytes::from(buffer)
+            .into()
         }
-    };
-
-    let mut file = OpenOptions::new()
-        .write(true)
-        .create(true)
-        .truncate(true)
-        .open(&cache_file)
-        .map_err(|e| format!("Failed to open cache file: {}", e))?;
-
-    file.write_all(&content)
-        .map_err(|e| format!("Failed to write cache file: {}", e))?;
-    Ok(cache_file)
+        Err(err) => compile_error(err),
+    }
 }
 
-/// A procedural macro that includes content from a URL as a static string at compile time.
+/// A procedural macro that fetches a JSON Schema document at compile time and expands to
+/// a module of the serde types it describes.
+///
+/// The root schema becomes a type named after its `title`, or after the module in
+/// `PascalCase` when it has none, and every schema under `definitions` or `$defs` becomes a
+/// type of the same name. The types are generated by `typify`. The calling crate needs to
+/// depend on `serde`, and on `chrono`, `uuid` or `regress` when the schema uses the matching
+/// formats or patterns. Requires the `jsonschema` feature.
 ///
 /// # Usage
 ///
-/// ```rust
-/// use include_url_macro::include_url;
+/// ```rust,ignore
+/// use include_url_macro::include_jsonschema_url;
+///
+/// include_jsonschema_url!(pub mod deploy_config, "https://schemas.example.com/deploy.json");
 ///
-/// const STATIC_CONTENT: &str = include_url!("https://example.com/static/content.txt");
+/// let config: deploy_config::DeployConfig = serde_json::from_str(text)?;
 /// ```
 ///
+/// See the [crate level documentation](crate#options) for the supported options,
+/// except `skip_under` as the generated items would be missing.
+///
 /// # Errors
 ///
 /// This macro will fail at compile time if:
-/// * The URL is invalid
-/// * The URL scheme is not HTTP or HTTPS
-/// * The content cannot be fetched
-/// * The response is not valid UTF-8
+/// * The URL is invalid or the content cannot be fetched
+/// * The response is not a JSON Schema object
+/// * A schema cannot be converted into Rust types
+#[cfg(feature = "jsonschema")]
 #[proc_macro]
-pub fn include_url(input: TokenStream) -> TokenStream {
-    let url_str = parse_macro_input!(input as LitStr).value();
+pub fn include_jsonschema_url(input: TokenStream) -> TokenStream {
+    let ModuleUrlInput {
+        vis,
+        name,
+        input: UrlInput { url, mut options },
+    } = parse_macro_input!(input as ModuleUrlInput);
+    let root_name = const_ident(&name.to_string())
+        .map(|ident| {
+            ident
+                .to_string()
+                .split('_')
+                .map(|word| word[..1].to_string() + &word[1..].to_lowercase())
+                .collect::<String>()
+        })
+        .unwrap_or_else(|| "Root".to_string());
+    let items = reject_bytes_options(&options, "include_jsonschema_url")
+        .and_then(|_| reject_skip_under(&options, "include_jsonschema_url"))
+        .and_then(|_| read_cached_text(&url, &mut options))
+        .and_then(|text| schema::generate_jsonschema(&text, &root_name));
 
-    match cached_url_content(&url_str, CompressKind::None) {
-        Ok(path) => {
-            let path_str = path.display().to_string();
-            let output = quote! { include_str!(#path_str) };
-            output.into()
+    match items {
+        Ok(items) => {
+            let tracking = env_tracking(&options.fetch);
+            quote! {
+                #vis mod #name {
+                    #tracking
+                    #items
+                }
+            }
+            .into()
         }
-        Err(err) => syn::Error::new(proc_macro2::Span::call_site(), err)
-            .to_compile_error()
-            .into(),
+        Err(err) => compile_error(err),
     }
 }
 
+/// A procedural macro that fetches an Apache Avro schema or object container file at
+/// compile time and expands to a module of serde types for it.
+///
+/// An `.avsc` schema becomes the types of its records, enums and fixed types. For an
+/// object container file, the types are generated from the schema in its header and the
+/// records are decoded during expansion, embedded as JSON and returned by the module's
+/// `records()` function. Fields are renamed to `snake_case`, `["null", T]` unions become
+/// `Option`s, other unions become `serde_json::Value`s and recursive references are
+/// boxed. The calling crate needs to depend on `serde`, and on `serde_json` for container
+/// files or general unions. The `null` and `deflate` codecs are supported. Requires the
+/// `avro` feature.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_avro_url;
+///
+/// include_avro_url!(pub mod stations, "https://data.example.com/stations.avro");
+///
+/// for station in stations::records() {
+///     println!("{}", station.name);
+/// }
+/// ```
+///
+/// See the [crate level documentation](crate#options) for the supported options,
+/// except `skip_under` as the generated items would be missing.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The URL is invalid or the content cannot be fetched
+/// * The response is neither a valid schema nor a valid container file
+/// * The container file uses an unsupported codec
+#[cfg(feature = "avro")]
 #[proc_macro]
-pub fn include_url_bytes(input: TokenStream) -> TokenStream {
-    let url_str = parse_macro_input!(input as LitStr).value();
+pub fn include_avro_url(input: TokenStream) -> TokenStream {
+    let ModuleUrlInput {
+        vis,
+        name,
+        input: UrlInput { url, mut options },
+    } = parse_macro_input!(input as ModuleUrlInput);
+    let generated = reject_bytes_options(&options, "include_avro_url")
+        .and_then(|_| reject_skip_under(&options, "include_avro_url"))
+        .and_then(|_| cached_url_content(&url.value(), CompressKind::None, &mut options.fetch))
+        .and_then(|path| {
+            std::fs::read(path).map_err(|e| format!("Failed to open cache file: {}", e))
+        })
+        .and_then(|content| avro::generate(&content));
 
-    match cached_url_content(&url_str, CompressKind::None) {
-        Ok(path) => {
-            let path_str = path.display().to_string();
-            let output = quote! { include_bytes!(#path_str) };
-            output.into()
+    match generated {
+        Ok(avro::Generated {
+            items,
+            root,
+            records,
+        }) => {
+            let tracking = env_tracking(&options.fetch);
+            let records = records.map(|records| {
+                quote! {
+                    /// The records of the container file.
+                    pub fn records() -> ::std::vec::Vec<#root> {
+                        ::serde_json::from_str(#records)
+                            .expect("Failed to parse the embedded Avro records")
+                    }
+                }
+            });
+            quote! {
+                #vis mod #name {
+                    #tracking
+                    #(#items)*
+                    #records
+                }
+            }
+            .into()
         }
-        Err(err) => syn::Error::new(proc_macro2::Span::call_site(), err)
-            .to_compile_error()
-            .into(),
+        Err(err) => compile_error(err),
     }
 }
 
-#[cfg(feature = "brotli")]
+/// A procedural macro that fetches a Parquet file at compile time and expands to a module
+/// embedding its columns as static typed arrays.
+///
+/// The module holds a `ROWS` constant and a constant slice per column, named after the
+/// column in `SCREAMING_SNAKE_CASE`. Integer, floating point and boolean columns keep their
+/// width, strings and decimals become `&str`, binary columns `&[u8]`, dates the number of
+/// days and timestamps the number of milli- or microseconds since the Unix epoch. Optional
+/// columns become slices of `Option`s. Only top-level primitive columns can be embedded.
+/// Requires the `parquet` feature.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_parquet_url;
+///
+/// include_parquet_url!(
+///     pub mod trips,
+///     "https://data.example.com/trips.parquet",
+///     columns = ["id", "distance"],
+/// );
+///
+/// let total: f64 = trips::DISTANCE.iter().sum();
+/// ```
+///
+/// # Options
+///
+/// Besides the [options shared by every macro](crate#options), except `skip_under` as the
+/// generated items would be missing:
+///
+/// * `columns = ["id", "name"]` - Embed only the listed columns, in the given order.
+///   Every column is embedded by default.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The URL is invalid or the content cannot be fetched
+/// * The response is not a valid Parquet file
+/// * A listed column does not exist or is nested
+#[cfg(feature = "parquet")]
 #[proc_macro]
-pub fn include_url_bytes_with_brotli(input: TokenStream) -> TokenStream {
-    let url_str = parse_macro_input!(input as LitStr).value();
+pub fn include_parquet_url(input: TokenStream) -> TokenStream {
+    let parser = ModuleUrlInput::parser(&["columns"]);
+    let (
+        ModuleUrlInput {
+            vis,
+            name,
+            input: UrlInput { url, mut options },
+        },
+        extra,
+    ) = parse_macro_input!(input with parser);
+    let columns = match extra
+        .last()
+        .map(|(_, value)| string_list(value))
+        .transpose()
+    {
+        Ok(columns) => columns,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    let items = reject_bytes_options(&options, "include_parquet_url")
+        .and_then(|_| reject_skip_under(&options, "include_parquet_url"))
+        .and_then(|_| cached_url_content(&url.value(), CompressKind::None, &mut options.fetch))
+        .and_then(|path| {
+            std::fs::read(path).map_err(|e| format!("Failed to open cache file: {}", e))
+        })
+        .and_then(|content| parquet::generate(content.into(), columns.as_deref()));
 
-    match cached_url_content(&url_str, CompressKind::Brotli) {
-        Ok(path) => {
-            let path_str = path.display().to_string();
-            let output = quote! { include_bytes!(#path_str) };
-            output.into()
+    match items {
+        Ok(items) => {
+            let tracking = env_tracking(&options.fetch);
+            quote! {
+                #vis mod #name {
+                    #tracking
+                    #items
+                }
+            }
+            .into()
         }
-        Err(err) => syn::Error::new(proc_macro2::Span::call_site(), err)
-            .to_compile_error()
-            .into(),
+        Err(err) => compile_error(err),
     }
 }
 
-/// Parser for the `include_json_url` macro's input.
+/// A procedural macro that fetches a SQLite database at compile time and expands to a
+/// module embedding either the database file or the result of a query.
 ///
-/// Handles both the URL and optional type specification.
-struct JsonUrlInput {
-    url: LitStr,
-    ty: Option<Type>,
-}
-
-impl Parse for JsonUrlInput {
-    fn parse(input: ParseStream) -> syn::Result<Self> {
-        let url = input.parse()?;
-
-        // Check if there's a type specification after a comma
-        let ty = if input.peek(Token![,]) {
-            input.parse::<Token![,]>()?;
-            Some(input.parse()?)
-        } else {
-            None
+/// Without a `query`, the module holds the database file as `DATABASE: &[u8]`, after
+/// checking its integrity. With a `query`, it holds a row struct with a field per result
+/// column, named in `snake_case`, and the rows as `ROWS`. Integer columns become `i64`,
+/// real columns `f64`, text `&str` and blobs `&[u8]`, and columns returning a `NULL` become
+/// `Option`s. Requires the `sqlite` feature, which builds a bundled SQLite.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_sqlite_url;
+///
+/// include_sqlite_url!(
+///     pub mod airports,
+///     "https://data.example.com/airports.sqlite",
+///     query = "SELECT code, name, elevation FROM airports ORDER BY code",
+///     row = Airport,
+/// );
+///
+/// let highest = airports::ROWS.iter().max_by_key(|airport| airport.elevation);
+/// ```
+///
+/// # Options
+///
+/// Besides the [options shared by every macro](crate#options), except `skip_under` as the
+/// generated items would be missing:
+///
+/// * `query = "SELECT ..."` - Embed the result of the query instead of the database file.
+/// * `row = Name` - The name of the row struct (defaults to `Row`).
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The URL is invalid or the content cannot be fetched
+/// * The response is not an intact SQLite database
+/// * The query is invalid or returns values of mixed types in a column
+#[cfg(feature = "sqlite")]
+#[proc_macro]
+pub fn include_sqlite_url(input: TokenStream) -> TokenStream {
+    let parser = ModuleUrlInput::parser(&["query", "row"]);
+    let (
+        ModuleUrlInput {
+            vis,
+            name,
+            input: UrlInput { url, mut options },
+        },
+        extra,
+    ) = parse_macro_input!(input with parser);
+    let mut query = None;
+    let mut row_type = None;
+    for (key, value) in extra {
+        let parsed = match key.to_string().as_str() {
+            "query" => syn::parse2(quote! { #value }).map(|value: LitStr| query = Some(value)),
+            _ => syn::parse2(quote! { #value }).map(|value: Ident| row_type = Some(value)),
         };
+        if let Err(err) = parsed {
+            return err.to_compile_error().into();
+        }
+    }
+    if let (None, Some(row_type)) = (&query, &row_type) {
+        return syn::Error::new(row_type.span(), "`row` requires a `query`")
+            .to_compile_error()
+            .into();
+    }
 
-        Ok(JsonUrlInput { url, ty })
+    let items = reject_bytes_options(&options, "include_sqlite_url")
+        .and_then(|_| reject_skip_under(&options, "include_sqlite_url"))
+        .and_then(|_| cached_url_content(&url.value(), CompressKind::None, &mut options.fetch))
+        .and_then(|path| match &query {
+            Some(query) => {
+                let row_type = row_type.unwrap_or_else(|| format_ident!("Row"));
+                sqlite::generate(&path, &query.value(), &row_type)
+            }
+            None => sqlite::validate(&path).map(|_| {
+                let path = path.display().to_string();
+                quote! {
+                    /// The database file.
+                    pub const DATABASE: &[u8] = include_bytes!(#path);
+                }
+            }),
+        });
+
+    match items {
+        Ok(items) => {
+            let tracking = env_tracking(&options.fetch);
+            quote! {
+                #vis mod #name {
+                    #tracking
+                    #items
+                }
+            }
+            .into()
+        }
+        Err(err) => compile_error(err),
     }
 }
 
-/// A procedural macro that includes and parses JSON content from a URL at compile time.
+/// A procedural macro that fetches an RSS or Atom feed at compile time and expands to a
+/// module embedding a snapshot of its entries.
 ///
-/// This macro can either return a generic `serde_json::Value` or parse the JSON into
-/// a specific type that implements `serde::Deserialize`.
+/// The module holds the `TITLE` and `LINK` of the feed, an `Entry` struct with the
+/// `title`, `link`, `date` and `timestamp` of an entry, and the entries as `ENTRIES` in
+/// feed order. Dates are the publication date, or the last update if there is none, in
+/// RFC 3339 and as seconds since the Unix epoch. RSS 0.9 and 1.0 and JSON Feed are read
+/// too, and relative links are resolved against the feed URL. Requires the `feed`
+/// feature.
 ///
 /// # Usage
 ///
-/// Basic usage (returns `serde_json::Value`):
-/// ```rust
-/// use include_url_macro::include_json_url;
+/// ```rust,ignore
+/// use include_url_macro::include_rss_url;
 ///
-/// let json = include_json_url!("https://jsonplaceholder.typicode.com/posts");
+/// include_rss_url!(pub mod blog, "https://blog.rust-lang.org/feed.xml", limit = 5);
+///
+/// for post in blog::ENTRIES {
+///     println!("{}: {}", post.date.unwrap_or("undated"), post.title);
+/// }
 /// ```
 ///
-/// Usage with a specific type:
-/// ```rust
-/// use include_url_macro::include_json_url;
+/// # Options
+///
+/// Besides the [options shared by every macro](crate#options), except `skip_under` as the
+/// generated items would be missing:
+///
+/// * `limit = 5` - Embed only the first entries of the feed.
+///
+/// # Errors
+///
+/// This macro will fail at compile time if:
+/// * The URL is invalid or the content cannot be fetched
+/// * The response is not an RSS, Atom or JSON feed
+#[cfg(feature = "feed")]
+#[proc_macro]
+pub fn include_rss_url(input: TokenStream) -> TokenStream {
+    let parser = ModuleUrlInput::parser(&["limit"]);
+    let (
+        ModuleUrlInput {
+            vis,
+            name,
+            input: UrlInput { url, mut options },
+        },
+        extra,
+    ) = parse_macro_input!(input with parser);
+    let limit = match extra
+        .last()
+        .map(|(_, value)| {
+            syn::parse2(quote! { #value }).and_then(|value: LitInt| value.base10_parse())
+        })
+        .transpose()
+    {
+        Ok(limit) => limit,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    let items = reject_bytes_options(&options, "include_rss_url")
+        .and_then(|_| reject_skip_under(&options, "include_rss_url"))
+        .and_then(|_| cached_url_content(&url.value(), CompressKind::None, &mut options.fetch))
+        .and_then(|path| {
+            std::fs::read(path).map_err(|e| format!("Failed to open cache file: {}", e))
+        })
+        .and_then(|content| feed::generate(&content, &url.value(), limit));
+
+    match items {
+        Ok(items) => {
+            let tracking = env_tracking(&options.fetch);
+            quote! {
+                #vis mod #name {
+                    #tracking
+                    #items
+                }
+            }
+            .into()
+        }
+        Err(err) => compile_error(err),
+    }
+}
+
+/// A procedural macro that includes and parses CSV content from a URL at compile time,
+/// expanding to a `Vec` of the given row type.
+///
+/// The CSV is validated during expansion and the header row must name exactly the fields
+/// of the row struct, so an upstream column change fails the build. The rows are
+/// deserialized with `csv::Reader::deserialize`, so the calling crate needs to depend on
+/// `csv`. Requires the `csv` feature.
+///
+/// # Usage
+///
+/// ```rust,ignore
+/// use include_url_macro::include_csv_url;
 /// use serde::Deserialize;
 ///
 /// #[derive(Deserialize)]
-/// struct Post {
-///   userId: i32,
-///   id: i32,
-///   title: String,
-///   body: String,
+/// struct Airport {
+///     code: String,
+///     name: String,
+///     elevation: i32,
 /// }
 ///
-/// let post: Post = include_json_url!("https://jsonplaceholder.typicode.com/posts/1", Post);
+/// let airports: Vec<Airport> = include_csv_url!("https://example.com/airports.csv", Airport);
 /// ```
 ///
+/// # Options
+///
+/// Besides the [options shared by every macro](crate#options):
+///
+/// * `delimiter = ';'` - The field delimiter (defaults to `,`).
+/// * `check_headers = false` - Skip the header check, for example when the row struct
+///   renames its fields through serde attributes.
+///
 /// # Errors
 ///
 /// This macro will fail at compile time if:
-/// * The URL is invalid
-/// * The URL scheme is not HTTP or HTTPS
-/// * The content cannot be fetched
-/// * The response is not valid JSON
-/// * The JSON cannot be parsed into the specified type (if a type is provided)
+/// * The URL is invalid or the content cannot be fetched
+/// * The response is not valid CSV or its rows have differing lengths
+/// * The header columns do not match the fields of the row struct
+#[cfg(feature = "csv")]
 #[proc_macro]
-pub fn include_json_url(input: TokenStream) -> TokenStream {
-    let JsonUrlInput { url, ty } = parse_macro_input!(input as JsonUrlInput);
-    let url_str = url.value();
-
-    match cached_url_content(&url_str, CompressKind::None) {
-        Ok(path) => {
-            let content = match std::fs::read_to_string(path)
-                .map_err(|e| format!("Failed to open cache file: {}", e))
-            {
-                Ok(content) => content,
-                Err(e) => {
-                    return syn::Error::new(proc_macro2::Span::call_site(), e)
-                        .to_compile_error()
-                        .into()
-                }
-            };
-            match serde_json::from_str::<serde_json::Value>(&content) {
-                Ok(_) => {
-                    // JSON is valid, proceed with the original logic
-                    let output = match ty {
-                        Some(ty) => quote! {{
-                            let json_str = #content;
-                            serde_json::from_str::<#ty>(&json_str)
-                                .expect("Failed to parse JSON into the specified type")
-                        }},
-                        None => quote! {{
-                            let json_str = #content;
-                            serde_json::from_str::<serde_json::Value>(&json_str)
-                                .expect("Failed to parse JSON")
-                        }},
-                    };
-                    output.into()
-                }
-                Err(json_err) => {
-                    // Return a compile error if JSON is invalid
-                    syn::Error::new(
-                        proc_macro2::Span::call_site(),
-                        format!("Invalid JSON content from URL: {}", json_err),
-                    )
-                    .to_compile_error()
-                    .into()
-                }
-            }
-        }
-        Err(err) => syn::Error::new(proc_macro2::Span::call_site(), err)
+pub fn include_csv_url(input: TokenStream) -> TokenStream {
+    let TypedUrlInput {
+        url,
+        ty,
+        with,
+        csv,
+        mut options,
+    } = parse_macro_input!(input as TypedUrlInput);
+    let Some(ty) = ty else {
+        return syn::Error::new(url.span(), "`include_csv_url!` requires a row type")
+            .to_compile_error()
+            .into();
+    };
+    if skip_requested(&options.skip_under) {
+        return quote! { ::std::vec::Vec::<#ty>::new() }.into();
+    }
+    if with.is_some() {
+        return syn::Error::new(url.span(), "`with` is not supported by `include_csv_url!`")
             .to_compile_error()
-            .into(),
+            .into();
     }
+
+    let headers = reject_bytes_options(&options, "include_csv_url")
+        .and_then(|_| read_cached_text(&url, &mut options))
+        .and_then(|content| {
+            formats::csv_headers(&content, csv.delimiter).map(|headers| (content, headers))
+        });
+    let (content, headers) = match headers {
+        Ok(headers) => headers,
+        Err(err) => return compile_error(err),
+    };
+
+    let header_check = match (&ty, csv.check_headers) {
+        (Type::Path(path), true) => {
+            let fields = headers
+                .iter()
+                .map(|header| {
+                    syn::parse_str::<syn::Member>(header)
+                        .or_else(|_| syn::parse_str(&format!("r#{}", header)))
+                })
+                .collect::<Result<Vec<syn::Member>, _>>();
+            let Ok(fields) = fields else {
+                return syn::Error::new(
+                    url.span(),
+                    "The CSV headers are not valid field names, use `check_headers = false`",
+                )
+                .to_compile_error()
+                .into();
+            };
+            let path = &path.path;
+            // An exhaustive struct pattern fails to compile unless the headers name exactly
+            // the fields of the row struct
+            quote! {
+                let _ = |row: &#ty| {
+                    let #path { #(#fields: _),* } = row;
+                };
+            }
+        }
+        _ => quote! {},
+    };
+    let delimiter = csv.delimiter;
+    let tracking = env_tracking(&options.fetch);
+
+    let output = quote! {{
+        #tracking
+        #header_check
+        let text = #content;
+        csv::ReaderBuilder::new()
+            .delimiter(#delimiter)
+            .from_reader(text.as_bytes())
+            .deserialize::<#ty>()
+            .collect::<::core::result::Result<::std::vec::Vec<#ty>, _>>()
+            .expect("Failed to parse CSV into the specified row type")
+    }};
+    output.into()
 }
 
 #[cfg(test)]
@@ -325,25 +7642,506 @@ mod tests {
     /// Test that valid URLs can be fetched
     #[test]
     fn test_fetch_url_content() {
-        let result = fetch_url_content("https://example.com");
+        let result = fetch_url_content("https://example.com", &FetchOptions::default());
         assert!(result.is_ok());
     }
 
     /// Test that invalid URL schemes are rejected
     #[test]
     fn test_invalid_scheme() {
-        let result = fetch_url_content("ftp://example.com");
-        assert!(result.is_err());
-        assert!(result
-            .unwrap_err()
-            .contains("Only HTTP and HTTPS URLs are supported"));
+        let result = fetch_url_content("gopher://example.com", &FetchOptions::default());
+        let error = result.unwrap_err();
+        assert!(
+            error.starts_with("Unsupported URL scheme `gopher`"),
+            "{}",
+            error
+        );
+        assert!(error.contains("`https`, `data`, `file`") && error.contains("`oci`"));
+    }
+
+    /// Test that the report lists every URL only when asked to
+    #[test]
+    fn test_report_input() {
+        assert!(!syn::parse_str::<ReportInput>("").unwrap().details);
+        assert!(
+            syn::parse_str::<ReportInput>("details = true")
+                .unwrap()
+                .details
+        );
+        assert!(syn::parse_str::<ReportInput>("verbose = true").is_err());
+    }
+
+    /// Test that options without timeouts fall back to the defaults
+    #[test]
+    fn test_timeout_defaults() {
+        let options = FetchOptions::default();
+        assert_eq!(options.timeout_or_default(), Duration::from_secs(300));
+        assert_eq!(
+            options.connect_timeout_or_default(),
+            Duration::from_secs(30)
+        );
+        let options = FetchOptions {
+            timeout: Some(5),
+            ..FetchOptions::default()
+        };
+        assert_eq!(options.timeout_or_default(), Duration::from_secs(5));
     }
 
     /// Test that invalid URLs are rejected
     #[test]
     fn test_invalid_url() {
-        let result = fetch_url_content("not-a-url");
+        let result = fetch_url_content("not-a-url", &FetchOptions::default());
         assert!(result.is_err());
         assert!(result.unwrap_err().contains("Invalid URL"));
     }
+
+    /// Test that the next page is picked out of a `Link` header
+    #[test]
+    fn test_next_link() {
+        let header = r#"<https://api.github.com/repositories/1/releases?page=2>; rel="next", <https://api.github.com/repositories/1/releases?page=5>; rel="last""#;
+        assert_eq!(
+            next_link(header).as_deref(),
+            Some("https://api.github.com/repositories/1/releases?page=2")
+        );
+        assert_eq!(
+            next_link(r#"<https://api.github.com/repositories/1/releases?page=1>; rel="prev""#),
+            None
+        );
+        // Commas in targets and quoted parameters, and relations with several values
+        let header = r#"</items?ids=1,2>; title="a, b"; rel="prev first", </items?ids=3,4>; REL="last next""#;
+        assert_eq!(next_link(header).as_deref(), Some("/items?ids=3,4"));
+        assert_eq!(next_link("<?page=2>;rel=next").as_deref(), Some("?page=2"));
+    }
+
+    /// Test that pagination options are parsed after the type
+    #[test]
+    fn test_json_url_input_options() {
+        let input: TypedUrlInput = syn::parse_str(
+            r#""https://example.com/items", Vec<u32>, paginate = link_header, max_pages = 5"#,
+        )
+        .unwrap();
+        assert!(input.ty.is_some());
+        assert_eq!(input.options.fetch.paginate, Paginate::LinkHeader);
+        assert_eq!(input.options.fetch.max_pages, Some(5));
+
+        let input: TypedUrlInput =
+            syn::parse_str(r#""https://example.com/items", paginate = none"#).unwrap();
+        assert!(input.ty.is_none());
+        assert_eq!(input.options.fetch.paginate, Paginate::None);
+
+        let input: TypedUrlInput = syn::parse_str(r#""https://example.com", retries = 3"#).unwrap();
+        assert_eq!(input.options.fetch.retries, Some(3));
+        assert!(syn::parse_str::<TypedUrlInput>(r#""https://example.com", retry = 3"#).is_err());
+        let input: UrlInput =
+            syn::parse_str(r#""https://example.com", retry_any_method = true"#).unwrap();
+        assert!(input.options.fetch.retry_any_method);
+
+        let input: TypedUrlInput =
+            syn::parse_str(r#""https://example.com", Config, with = config::from_value"#).unwrap();
+        let with = input.with.unwrap();
+        assert_eq!(quote!(#with).to_string(), "config :: from_value");
+    }
+
+    /// Test that the shared options are parsed for every macro
+    #[test]
+    fn test_macro_options() {
+        let input: UrlInput = syn::parse_str(
+            r#""https://example.com", header = "Accept: text/plain", timeout = 30,
+               connect_timeout = 5, decompress = false,
+               sha256 = "E3B0C44298FC1C149AFBF4C8996FB92427AE41E4649B934CA495991B7852B855",
+               compression = none,"#,
+        )
+        .unwrap();
+        assert_eq!(
+            input.options.fetch.headers,
+            [("Accept".to_string(), "text/plain".to_string())]
+        );
+        assert_eq!(input.options.fetch.timeout, Some(30));
+        assert_eq!(input.options.fetch.connect_timeout, Some(5));
+        assert!(input.options.fetch.keep_encoding);
+        assert_eq!(
+            input.options.fetch.sha256.as_deref(),
+            Some("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855")
+        );
+        assert_eq!(input.options.compression, Some(CompressKind::None));
+
+        assert!(syn::parse_str::<UrlInput>(r#""https://example.com", sha256 = "abc""#).is_err());
+        assert!(syn::parse_str::<UrlInput>(r#""https://example.com", header = "Accept""#).is_err());
+        assert!(syn::parse_str::<UrlInput>(r#""https://example.com", with = f"#).is_err());
+
+        let input: UrlInput =
+            syn::parse_str(r#""https://example.com", allow_status = [404, 410]"#).unwrap();
+        assert_eq!(input.options.fetch.allow_status, [404, 410]);
+        assert!(syn::parse_str::<UrlInput>(r#""https://example.com", allow_status = 42"#).is_err());
+
+        let input: UrlInput = syn::parse_str(
+            r#""https://example.com", headers = { "Accept" = "text/csv", "X-Api-Key" = env("KEY") }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            input.options.fetch.headers,
+            [
+                ("Accept".to_string(), "text/csv".to_string()),
+                ("X-Api-Key".to_string(), "${KEY}".to_string())
+            ]
+        );
+        assert!(
+            syn::parse_str::<UrlInput>(r#""https://example.com", headers = { "A B" = "c" }"#)
+                .is_err()
+        );
+        assert!(syn::parse_str::<UrlInput>(
+            r#""https://example.com", headers = { "A" = var("B") }"#
+        )
+        .is_err());
+
+        let input: UrlInput = syn::parse_str(
+            r#""https://example.com", cookie = "consent=yes", cookie = env("SESSION")"#,
+        )
+        .unwrap();
+        assert_eq!(input.options.fetch.cookies, ["consent=yes", "${SESSION}"]);
+        assert!(syn::parse_str::<UrlInput>(r#""https://example.com", cookie = "a=\n""#).is_err());
+
+        let input: UrlInput = syn::parse_str(
+            r#""https://example.com", ca_cert = "certs/ca.pem", ca_cert = env("CA_PEM")"#,
+        )
+        .unwrap();
+        assert_eq!(input.options.fetch.ca_certs, ["certs/ca.pem", "${CA_PEM}"]);
+
+        let input: UrlInput = syn::parse_str(
+            r#""https://example.com", client_cert = "client.p12",
+               client_cert_password = env("CLIENT_PASSWORD")"#,
+        )
+        .unwrap();
+        assert_eq!(
+            input.options.fetch.client_cert.as_deref(),
+            Some("client.p12")
+        );
+        assert_eq!(
+            input.options.fetch.client_cert_password.as_deref(),
+            Some("${CLIENT_PASSWORD}")
+        );
+
+        let input: UrlInput = syn::parse_str(
+            r#""https://example.com", ip_version = 4, doh = "https://dns.google/resolve",
+               resolve = { "example.com" = "10.0.0.1", "example.org" = "::1" }"#,
+        )
+        .unwrap();
+        assert_eq!(input.options.fetch.ip_version, Some(4));
+        assert_eq!(
+            input.options.fetch.hosts,
+            [
+                ("example.com".to_string(), "10.0.0.1".parse().unwrap()),
+                ("example.org".to_string(), "::1".parse().unwrap())
+            ]
+        );
+        assert!(syn::parse_str::<UrlInput>(r#""https://example.com", ip_version = 5"#).is_err());
+        assert!(syn::parse_str::<UrlInput>(
+            r#""https://example.com", doh = "http://127.0.0.1/dns-query""#
+        )
+        .is_err());
+        assert!(syn::parse_str::<UrlInput>(
+            r#""https://example.com", resolve = { "example.com" = "10.0.0" }"#
+        )
+        .is_err());
+
+        let input: UrlInput = syn::parse_str(
+            r#""https://example.com", proxy = "http://${PROXY_HOST}:3128",
+               auth = basic_env("USER", "PASSWORD")"#,
+        )
+        .unwrap();
+        assert_eq!(
+            input.options.fetch.proxy.as_deref(),
+            Some("http://${PROXY_HOST}:3128")
+        );
+        assert_eq!(
+            input.options.fetch.auth,
+            Some(Auth::Basic("USER".to_string(), "PASSWORD".to_string()))
+        );
+        assert!(syn::parse_str::<UrlInput>(
+            r#""https://example.com", auth = bearer_env("A", "B")"#
+        )
+        .is_err());
+
+        let input: UrlInput = syn::parse_str(
+            r#""https://example.com/search", method = "put", body = "{}",
+               content_type = "application/json""#,
+        )
+        .unwrap();
+        assert_eq!(input.options.fetch.method, Some(Method::PUT));
+        assert_eq!(input.options.fetch.body.as_deref(), Some("{}"));
+        assert!(
+            syn::parse_str::<UrlInput>(r#""https://example.com", method = "GET POST""#).is_err()
+        );
+
+        let input: UrlInput = syn::parse_str(
+            r#"mirrors = ["https://a.example.com/f", "https://b.example.com/f"], verify_mirrors = true"#,
+        )
+        .unwrap();
+        assert_eq!(input.url.value(), "https://a.example.com/f");
+        assert_eq!(input.options.fetch.mirrors, ["https://b.example.com/f"]);
+        assert!(input.options.fetch.verify_mirrors);
+        let input: TypedUrlInput =
+            syn::parse_str(r#""https://a.example.com/f", mirrors = ["https://b.example.com/f"]"#)
+                .unwrap();
+        assert_eq!(input.options.fetch.mirrors, ["https://b.example.com/f"]);
+        assert!(syn::parse_str::<UrlInput>("mirrors = []").is_err());
+        assert!(syn::parse_str::<UrlInput>(r#"retries = 3, "https://example.com""#).is_err());
+
+        let input: UrlInput =
+            syn::parse_str(r#""https://example.com", charset = "Shift_JIS""#).unwrap();
+        assert_eq!(input.options.fetch.charset.as_deref(), Some("Shift_JIS"));
+        assert!(syn::parse_str::<UrlInput>(r#""https://example.com", charset = "utf-9""#).is_err());
+    }
+
+    /// Test that retries back off exponentially and honor `Retry-After`
+    #[test]
+    fn test_retry_delays() {
+        for attempt in 0..3 {
+            let delay = backoff(attempt);
+            let full = RETRY_BASE_DELAY * (1 << attempt);
+            assert!(delay >= full / 2 && delay <= full, "{:?}", delay);
+        }
+        assert!(backoff(30) <= MAX_RETRY_DELAY);
+        assert_eq!(retry_after(" 120"), Some(Duration::from_secs(120)));
+        assert_eq!(
+            retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(retry_after("soon"), None);
+    }
+
+    /// Test that requests which are not idempotent are only retried when asked to
+    #[test]
+    fn test_retry_methods() {
+        let attempts = |options: &FetchOptions| {
+            let mut attempts = 0;
+            let result = with_retries::<()>("retry-methods.test", options, || {
+                attempts += 1;
+                Err(Failure {
+                    message: "Connection reset".to_string(),
+                    transient: true,
+                    retry_after: Some(Duration::ZERO),
+                })
+            });
+            assert!(result.is_err());
+            attempts
+        };
+        let mut options = FetchOptions {
+            retries: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(attempts(&options), 3);
+        options.method = Some(Method::DELETE);
+        assert_eq!(attempts(&options), 3);
+        options.method = None;
+        options.body = Some("{}".to_string());
+        assert_eq!(attempts(&options), 1);
+        options.retry_any_method = true;
+        assert_eq!(attempts(&options), 3);
+    }
+
+    /// Test that error responses are quoted with collapsed whitespace and truncated
+    #[test]
+    fn test_body_snippet() {
+        assert_eq!(
+            body_snippet("<html>\n  <h1>Not Found</h1>\n</html>\n"),
+            "<html> <h1>Not Found</h1> </html>"
+        );
+        assert_eq!(body_snippet(" \n "), "");
+        let snippet = body_snippet(&"é".repeat(500));
+        assert_eq!(snippet, format!("{}...", "é".repeat(ERROR_SNIPPET_CHARS)));
+    }
+
+    /// Test that `${NAME}` references are substituted and recorded
+    #[test]
+    fn test_substitute_env() {
+        env::set_var("INCLUDE_URL_TEST_HOST", "assets.example.com");
+        let mut options = FetchOptions::default();
+        let url = substitute_env("https://${INCLUDE_URL_TEST_HOST}/a.json", &mut options);
+        assert_eq!(url.unwrap(), "https://assets.example.com/a.json");
+        assert_eq!(
+            options.env,
+            [(
+                "INCLUDE_URL_TEST_HOST".to_string(),
+                Some("assets.example.com".to_string())
+            )]
+        );
+
+        let missing = substitute_env("${INCLUDE_URL_TEST_MISSING}", &mut options);
+        assert!(missing.unwrap_err().contains("is not set"));
+        assert!(substitute_env("${INCLUDE_URL_TEST_HOST", &mut options).is_err());
+    }
+
+    /// Test that key order and whitespace do not affect canonical JSON
+    #[test]
+    fn test_canonicalize_json() {
+        let a = canonicalize_json(br#"{ "b": [1, 2.50], "a": { "d": null, "c": true } }"#);
+        let b = canonicalize_json(br#"{"a":{"c":true,"d":null},"b":[1,2.5]}"#);
+        assert_eq!(a.unwrap(), b.unwrap());
+        assert_eq!(
+            canonicalize_json(br#"{"z":1,"y":2}"#).unwrap(),
+            &br#"{"y":2,"z":1}"#[..]
+        );
+        assert!(canonicalize_json(b"not json").is_err());
+    }
+
+    /// Test that sizes are parsed with and without units
+    #[test]
+    fn test_size_from_str() {
+        assert_eq!(size_from_str("1024"), Some(1024));
+        assert_eq!(size_from_str("512KiB"), Some(512 * 1024));
+        assert_eq!(size_from_str("5 MB"), Some(5_000_000));
+        assert_eq!(size_from_str("2gib"), Some(2 << 30));
+        assert_eq!(size_from_str("MB"), None);
+        assert_eq!(size_from_str("5 parsecs"), None);
+
+        let input: UrlInput =
+            syn::parse_str(r#""https://example.com", chunk_size = "1MiB""#).unwrap();
+        assert_eq!(input.options.chunk_size, Some(1 << 20));
+
+        let input: TypedUrlInput =
+            syn::parse_str(r#""https://example.com", Config, max_size = "5MB""#).unwrap();
+        assert_eq!(input.options.fetch.max_size, Some(5_000_000));
+    }
+
+    /// Test that chunked content is included as one reference per chunk file
+    #[test]
+    fn test_embed_bytes_chunks() {
+        let path = std::env::temp_dir().join(format!("include_url_chunks_{}", std::process::id()));
+        std::fs::write(&path, b"0123456789").unwrap();
+
+        let tokens = embed_bytes(&path, Some(4)).unwrap().to_string();
+        assert!(tokens.contains("static CHUNKS : [& [u8] ; 3usize]"));
+        assert_eq!(tokens.matches("include_bytes !").count(), 3);
+        assert!(!tokens.contains("unsafe"));
+        let chunk_paths = |tokens: &str| -> Vec<String> {
+            tokens
+                .split("include_bytes ! (\"")
+                .skip(1)
+                .map(|rest| rest.split('"').next().unwrap().to_string())
+                .collect()
+        };
+        let old_paths = chunk_paths(&tokens);
+        for (chunk_path, chunk) in old_paths.iter().zip(["0123", "4567", "89"]) {
+            assert_eq!(std::fs::read(chunk_path).unwrap(), chunk.as_bytes());
+        }
+
+        // Refreshed content is embedded instead of the chunks of the former content
+        std::fs::write(&path, b"0123abcdefg").unwrap();
+        let tokens = embed_bytes(&path, Some(4)).unwrap().to_string();
+        let new_paths = chunk_paths(&tokens);
+        assert_eq!(new_paths[0], old_paths[0]);
+        for (chunk_path, chunk) in new_paths.iter().zip(["0123", "abcd", "efg"]) {
+            assert_eq!(std::fs::read(chunk_path).unwrap(), chunk.as_bytes());
+        }
+        assert!(!std::path::Path::new(&old_paths[1]).exists());
+        assert!(!std::path::Path::new(&old_paths[2]).exists());
+
+        for chunk_path in new_paths {
+            std::fs::remove_file(chunk_path).unwrap();
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+
+    /// Test that durations are parsed with and without units
+    #[test]
+    fn test_seconds_from_str() {
+        assert_eq!(seconds_from_str("3600"), Some(3600));
+        assert_eq!(seconds_from_str("30m"), Some(30 * 60));
+        assert_eq!(seconds_from_str("7 d"), Some(7 * 24 * 60 * 60));
+        assert_eq!(seconds_from_str("d"), None);
+        assert_eq!(seconds_from_str("2 fortnights"), None);
+
+        let input: UrlInput = syn::parse_str(r#""https://example.com", ttl = "12h""#).unwrap();
+        assert_eq!(input.options.fetch.ttl, Some(12 * 60 * 60));
+        assert!(syn::parse_str::<UrlInput>(r#""https://example.com", ttl = "soon""#).is_err());
+    }
+
+    /// Test that `include_urls!` takes a list of URLs or a manifest
+    #[test]
+    fn test_url_list_input() {
+        let input: UrlListInput = syn::parse_str(
+            r#"["https://example.com/a", "https://example.com/b"], bytes = true, concurrency = 4,
+               timeout = 60"#,
+        )
+        .unwrap();
+        assert_eq!(
+            input.urls,
+            ["https://example.com/a", "https://example.com/b"]
+        );
+        assert!(input.bytes && input.manifest.is_none());
+        assert_eq!(input.concurrency, Some(4));
+        assert_eq!(input.options.fetch.timeout, Some(60));
+
+        let input: UrlListInput = syn::parse_str(r#"manifest = "urls.txt""#).unwrap();
+        assert_eq!(input.manifest.unwrap().value(), "urls.txt");
+        assert!(syn::parse_str::<UrlListInput>(r#"url = "urls.txt""#).is_err());
+        assert!(
+            syn::parse_str::<UrlListInput>(r#"["https://example.com"], concurrency = 0"#).is_err()
+        );
+    }
+
+    /// Test that `file_name` is separated from the shared options
+    #[test]
+    fn test_path_url_input() {
+        let input: PathUrlInput = syn::parse_str(
+            r#""https://example.com/model", file_name = "model.bin", timeout = 600"#,
+        )
+        .unwrap();
+        assert_eq!(input.file_name.unwrap().value(), "model.bin");
+        assert_eq!(input.options.fetch.timeout, Some(600));
+    }
+
+    /// Test that the module name is parsed ahead of the URL and options
+    #[test]
+    fn test_module_url_input() {
+        use syn::parse::Parser as _;
+
+        let input: ModuleUrlInput =
+            syn::parse_str(r#"pub mod config, "https://example.com/app.ini", timeout = 5"#)
+                .unwrap();
+        assert_eq!(input.name, "config");
+        assert_eq!(input.input.options.fetch.timeout, Some(5));
+        assert!(syn::parse_str::<ModuleUrlInput>(r#""https://example.com/app.ini""#).is_err());
+
+        let source = r#"mod data, "https://example.com/data.parquet", columns = ["id"]"#;
+        let (_, extra) = ModuleUrlInput::parser(&["columns"])
+            .parse_str(source)
+            .unwrap();
+        assert_eq!(extra[0].0, "columns");
+        assert!(syn::parse_str::<ModuleUrlInput>(source).is_err());
+    }
+
+    /// Test that keys and sections are converted into identifiers
+    #[test]
+    fn test_const_ident() {
+        let name = |key| const_ident(key).map(|ident| ident.to_string());
+        assert_eq!(name("server.port").as_deref(), Some("SERVER_PORT"));
+        assert_eq!(name("maxConnections").as_deref(), Some("MAX_CONNECTIONS"));
+        assert_eq!(name("2fa-enabled").as_deref(), Some("_2FA_ENABLED"));
+        assert_eq!(name("--"), None);
+        assert_eq!(snake_ident("Database Replica").unwrap(), "database_replica");
+        assert_eq!(snake_ident("type").unwrap(), "type_");
+    }
+
+    /// Test that `skip_under` accepts a single cfg or a list
+    #[test]
+    fn test_skip_under() {
+        let input: UrlInput =
+            syn::parse_str(r#""https://example.com", skip_under = [test, clippy]"#).unwrap();
+        assert_eq!(input.options.skip_under, ["test", "clippy"]);
+
+        let input: TypedUrlInput =
+            syn::parse_str(r#""https://example.com", Config, skip_under = docsrs"#).unwrap();
+        assert_eq!(input.options.skip_under, ["docsrs"]);
+
+        let args: Vec<String> = ["rustc", "--test", "--cfg", "docsrs", "--cfg=loom"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        assert!(cfg_in_args("test", &args));
+        assert!(cfg_in_args("docsrs", &args));
+        assert!(cfg_in_args("loom", &args));
+        assert!(!cfg_in_args("miri", &args));
+    }
 }
@@ -0,0 +1,294 @@
+//! The `include_url.lock` file of the workspace, pinning what moving references, such as
+//! the `latest` release of a repository, resolved to when first built, and the digest of
+//! the content of every URL when first fetched.
+//!
+//! The file sits next to the manifest of the workspace, or of the crate outside of a
+//! workspace, and is meant to be committed. A reference pinned in it is not resolved again,
+//! and content fetched again, into another cache or once its cache entry expired, has to
+//! match its digest, so every checkout builds the same content, until the file is edited or
+//! the build runs with `INCLUDE_URL_UPDATE_LOCK=1`, which resolves every reference again
+//! and takes the digest of the content as it is. Concurrent builds of the crates of a
+//! workspace lock the file while they update it.
+
+use std::{
+    collections::BTreeMap,
+    env,
+    io::{Read, Seek, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use sha2::{Digest, Sha256};
+
+use crate::{data_url, file_url, revalidate::Metadata, FetchOptions};
+
+/// Name of the lockfile.
+const FILE_NAME: &str = "include_url.lock";
+
+/// Environment variable asking for every pinned reference to be resolved again.
+pub(crate) const UPDATE_VAR: &str = "INCLUDE_URL_UPDATE_LOCK";
+
+const HEADER: &str = "# Pins of the content included by include_url_macro, commit this file.\n\
+                      # Build with INCLUDE_URL_UPDATE_LOCK=1 to update it.\n\n";
+
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct Lockfile {
+    /// What references resolved to, by reference.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pins: BTreeMap<String, String>,
+    /// The content of URLs when first fetched, by URL.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    content: BTreeMap<String, Content>,
+}
+
+/// What the content of a URL was when it was first fetched.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Content {
+    /// Lowercase hex SHA-256 digest, as checked by the `sha256` option.
+    sha256: String,
+    /// Size in bytes.
+    length: u64,
+    /// Entity tag of the response, for reference.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    etag: Option<String>,
+}
+
+/// Returns the value `reference` is pinned to, or resolves it with `resolve` and pins the
+/// result. The lockfile is tracked so that editing it re-expands the macro.
+pub(crate) fn pinned(
+    reference: &str,
+    options: &mut FetchOptions,
+    resolve: impl FnOnce(&mut FetchOptions) -> Result<String, String>,
+) -> Result<String, String> {
+    let Some(path) = path() else {
+        return resolve(options);
+    };
+    if !update_requested() {
+        if let Some(pin) = read(&path)?.pins.get(reference) {
+            track(&path, options);
+            return Ok(pin.clone());
+        }
+    }
+    let value = resolve(options)?;
+    update(&path, |lockfile| {
+        lockfile.pins.insert(reference.to_string(), value.clone());
+    })?;
+    track(&path, options);
+    Ok(value)
+}
+
+/// Checks the digest of fetched or cached content against the one it was locked with, or
+/// locks it. Local content, content checked by a `sha256` or `integrity` option, content
+/// asked to be left out with `locked = false` and content cached before its digest was
+/// kept are not locked.
+pub(crate) fn check_content(
+    url: &str,
+    metadata: &Metadata,
+    options: &mut FetchOptions,
+) -> Result<(), String> {
+    let (Some(sha256), Some(length)) = (&metadata.sha256, metadata.length) else {
+        return Ok(());
+    };
+    let local = data_url::is_data_url(url) || file_url::is_file_url(url);
+    let checked = options.sha256.is_some() || options.integrity.is_some();
+    if local || options.unlocked || checked {
+        return Ok(());
+    }
+    let Some(path) = path() else {
+        return Ok(());
+    };
+    let key = content_key(url, options);
+    if !update_requested() {
+        if let Some(locked) = read(&path)?.content.get(&key) {
+            if locked.sha256 != *sha256 {
+                return Err(format!(
+                    "Content changed upstream: `{}` is locked in `{}` with SHA-256 {} ({} \
+                     bytes), but is now {} ({} bytes). Build with {}=1 to accept the new content",
+                    key,
+                    path.display(),
+                    locked.sha256,
+                    locked.length,
+                    sha256,
+                    length,
+                    UPDATE_VAR
+                ));
+            }
+            track(&path, options);
+            return Ok(());
+        }
+    }
+    let content = Content {
+        sha256: sha256.clone(),
+        length,
+        etag: metadata.etag.clone(),
+    };
+    update(&path, |lockfile| {
+        lockfile.content.insert(key, content);
+    })?;
+    track(&path, options);
+    Ok(())
+}
+
+/// Key of content in the lockfile, its URL as written, along with the digest of the values
+/// of the `${NAME}` references of the URL, which select what is fetched without ending up in
+/// the file, and the digest of the request body for requests that send one, such as the
+/// queries to a GraphQL endpoint.
+fn content_key(url: &str, options: &FetchOptions) -> String {
+    let mut key = url.to_string();
+    let names: Vec<&str> = (url.split("${").skip(1))
+        .filter_map(|rest| rest.split_once('}').map(|(name, _)| name))
+        .collect();
+    if !names.is_empty() {
+        let mut hasher = Sha256::new();
+        for name in names {
+            let value = options.env.iter().find(|(recorded, _)| recorded == name);
+            hasher.update(name);
+            hasher.update(b"=");
+            hasher.update(value.and_then(|(_, value)| value.as_deref()).unwrap_or(""));
+            hasher.update(b"\0");
+        }
+        key.push_str(&format!(
+            " env:{}",
+            &format!("{:x}", hasher.finalize())[..16]
+        ));
+    }
+    if let Some(body) = &options.body {
+        let digest = format!("{:x}", Sha256::digest(body));
+        key.push_str(&format!(" body:{}", &digest[..16]));
+    }
+    key
+}
+
+/// Tells whether the pins are to be resolved again.
+fn update_requested() -> bool {
+    env::var(UPDATE_VAR).is_ok_and(|value| matches!(value.trim(), "1" | "true"))
+}
+
+fn track(path: &Path, options: &mut FetchOptions) {
+    if !options.files.iter().any(|file| file == path) {
+        options.files.push(path.to_path_buf());
+    }
+}
+
+/// Finds the lockfile next to the manifest of the workspace of the crate being compiled.
+fn path() -> Option<PathBuf> {
+    let crate_dir = PathBuf::from(env::var_os("CARGO_MANIFEST_DIR")?);
+    let workspace = crate_dir.ancestors().find(|dir| {
+        std::fs::read_to_string(dir.join("Cargo.toml"))
+            .ok()
+            .and_then(|manifest| manifest.parse::<toml::Table>().ok())
+            .is_some_and(|manifest| manifest.contains_key("workspace"))
+    });
+    Some(workspace.unwrap_or(&crate_dir).join(FILE_NAME))
+}
+
+fn read(path: &Path) -> Result<Lockfile, String> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => parse(&content, path),
+        Err(_) => Ok(Lockfile::default()),
+    }
+}
+
+fn parse(content: &str, path: &Path) -> Result<Lockfile, String> {
+    toml::from_str(content).map_err(|e| format!("Invalid `{}`: {}", path.display(), e))
+}
+
+/// Applies a change to the lockfile, holding a lock on it so that the changes of
+/// concurrent builds are not lost.
+fn update(path: &Path, change: impl FnOnce(&mut Lockfile)) -> Result<(), String> {
+    let failed = |e: std::io::Error| format!("Failed to update `{}`: {}", path.display(), e);
+    let mut file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .map_err(failed)?;
+    file.lock().map_err(failed)?;
+    let mut content = String::new();
+    file.read_to_string(&mut content).map_err(failed)?;
+    let mut lockfile = parse(&content, path)?;
+    change(&mut lockfile);
+    let content = format!(
+        "{}{}",
+        HEADER,
+        toml::to_string(&lockfile).map_err(|e| e.to_string())?
+    );
+    file.rewind().map_err(failed)?;
+    file.set_len(0).map_err(failed)?;
+    file.write_all(content.as_bytes()).map_err(failed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that pins are written under the header and read back
+    #[test]
+    fn test_update() {
+        let path = env::temp_dir().join("include_url_lockfile_test.lock");
+        let _ = std::fs::remove_file(&path);
+        update(&path, |lockfile| {
+            lockfile
+                .pins
+                .insert("github-release:acme/tool@latest".into(), "v1.4.2".into());
+        })
+        .unwrap();
+        update(&path, |lockfile| {
+            lockfile.pins.insert("other".into(), "value".into());
+        })
+        .unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with(HEADER));
+        assert!(content.contains("\"github-release:acme/tool@latest\" = \"v1.4.2\""));
+        assert_eq!(read(&path).unwrap().pins.len(), 2);
+        assert!(!content.contains("[content]"));
+
+        let locked = Content {
+            sha256: "ab".repeat(32),
+            length: 42,
+            etag: Some("\"v1\"".into()),
+        };
+        update(&path, |lockfile| {
+            lockfile
+                .content
+                .insert("https://example.com/data.json".into(), locked.clone());
+        })
+        .unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.contains("[content.\"https://example.com/data.json\"]"));
+        assert_eq!(
+            read(&path).unwrap().content["https://example.com/data.json"],
+            locked
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    /// Test that content is locked separately for every value of the variables of its URL
+    #[test]
+    fn test_content_key() {
+        let url = "https://${INCLUDE_URL_TEST_LOCK_HOST}/data.json";
+        let mut options = FetchOptions::default();
+        assert_eq!(
+            content_key("https://example.com/data.json", &options),
+            "https://example.com/data.json"
+        );
+
+        let mut keys = Vec::new();
+        for host in ["staging.example.com", "example.com"] {
+            options.env = vec![("INCLUDE_URL_TEST_LOCK_HOST".into(), Some(host.into()))];
+            let key = content_key(url, &options);
+            assert!(key.starts_with(&format!("{} env:", url)));
+            assert!(!key.contains(host));
+            keys.push(key);
+        }
+        assert_ne!(keys[0], keys[1]);
+        assert_eq!(content_key(url, &options), keys[1]);
+
+        options.body = Some("{}".into());
+        let key = content_key(url, &options);
+        assert!(key.starts_with(&keys[1]));
+        assert!(key.contains(" body:"));
+    }
+}
@@ -0,0 +1,189 @@
+//! Compilation of the MIME type databases included by `include_mime_db_url!`.
+//!
+//! Both the Apache `mime.types` format, a media type followed by its extensions on each
+//! line, and the JSON of the `mime-db` package are read. The mappings are embedded as
+//! slices sorted for binary search in both directions.
+
+use std::collections::BTreeMap;
+
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// The media types with their extensions, in the order of the database.
+pub(crate) type MimeTypes = Vec<(String, Vec<String>)>;
+
+/// Returns whether `name` is a `type/subtype` media type.
+fn is_media_type(name: &str) -> bool {
+    let is_token = |token: &str| {
+        !token.is_empty()
+            && token
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b"!#$&-^_.+".contains(&b))
+    };
+    name.split_once('/')
+        .is_some_and(|(ty, subtype)| is_token(ty) && is_token(subtype))
+}
+
+/// Parses a database in the Apache `mime.types` format or the JSON format of `mime-db`.
+///
+/// Media types are lowercased, and types without extensions are kept for the reverse
+/// lookup.
+pub(crate) fn parse(content: &str) -> Result<MimeTypes, String> {
+    let mut types = MimeTypes::new();
+    if content.trim_start().starts_with('{') {
+        let db: BTreeMap<String, serde_json::Value> = serde_json::from_str(content)
+            .map_err(|e| format!("Invalid MIME database from URL: {}", e))?;
+        for (name, entry) in db {
+            let extensions = match entry.get("extensions") {
+                None => Vec::new(),
+                Some(extensions) => serde_json::from_value(extensions.clone()).map_err(|_| {
+                    format!(
+                        "Invalid MIME database from URL: the extensions of `{}` are not a list \
+                         of strings",
+                        name
+                    )
+                })?,
+            };
+            add(&mut types, &name, extensions)
+                .map_err(|e| format!("Invalid MIME database from URL: {}", e))?;
+        }
+    } else {
+        for (index, line) in content.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default();
+            let mut words = line.split_whitespace();
+            let Some(name) = words.next() else {
+                continue;
+            };
+            add(&mut types, name, words.map(str::to_string).collect()).map_err(|e| {
+                format!("Invalid MIME database from URL: line {}: {}", index + 1, e)
+            })?;
+        }
+    }
+    match types.is_empty() {
+        true => Err("Invalid MIME database from URL: no media types found".to_string()),
+        false => Ok(types),
+    }
+}
+
+fn add(types: &mut MimeTypes, name: &str, extensions: Vec<String>) -> Result<(), String> {
+    if !is_media_type(name) {
+        return Err(format!("invalid media type `{}`", name));
+    }
+    let name = name.to_ascii_lowercase();
+    let index = match types.iter().position(|(known, _)| *known == name) {
+        Some(index) => index,
+        None => {
+            types.push((name, Vec::new()));
+            types.len() - 1
+        }
+    };
+    let (name, entry) = &mut types[index];
+    for extension in extensions {
+        let extension = extension.to_ascii_lowercase();
+        if extension.is_empty() || extension.contains(['.', '/']) {
+            return Err(format!("invalid extension `{}` of `{}`", extension, name));
+        }
+        if !entry.contains(&extension) {
+            entry.push(extension);
+        }
+    }
+    Ok(())
+}
+
+/// Generates the `EXTENSIONS` and `TYPES` tables and their lookup functions.
+///
+/// An extension claimed by several media types maps to the first one in the database, in
+/// alphabetical order for the JSON format.
+pub(crate) fn generate(types: &MimeTypes) -> TokenStream {
+    let mut extensions: BTreeMap<&str, &str> = BTreeMap::new();
+    for (name, type_extensions) in types {
+        for extension in type_extensions {
+            extensions.entry(extension).or_insert(name);
+        }
+    }
+    let extensions = extensions
+        .into_iter()
+        .map(|(extension, name)| quote! { (#extension, #name) });
+    let types: BTreeMap<&str, &[String]> = types
+        .iter()
+        .map(|(name, extensions)| (name.as_str(), extensions.as_slice()))
+        .collect();
+    let types = types.iter().map(|(name, extensions)| {
+        quote! { (#name, &[#(#extensions),*]) }
+    });
+    quote! {
+        /// The media type of every extension, sorted by extension.
+        pub const EXTENSIONS: &[(&str, &str)] = &[#(#extensions),*];
+        /// The extensions of every media type, sorted by media type.
+        pub const TYPES: &[(&str, &[&str])] = &[#(#types),*];
+
+        /// Compares `value` to the lowercase `key`, ignoring ASCII case.
+        fn compare(key: &str, value: &str) -> ::core::cmp::Ordering {
+            key.bytes().cmp(value.bytes().map(|b| b.to_ascii_lowercase()))
+        }
+
+        /// Returns the media type of a file extension, such as `text/css` for `css`.
+        ///
+        /// A leading `.` is ignored and ASCII letters match in any case.
+        pub fn from_extension(extension: &str) -> ::core::option::Option<&'static str> {
+            let extension = extension.strip_prefix('.').unwrap_or(extension);
+            EXTENSIONS
+                .binary_search_by(|(key, _)| compare(key, extension))
+                .ok()
+                .map(|index| EXTENSIONS[index].1)
+        }
+
+        /// Returns the media type of a path or URL by the extension of its file name.
+        pub fn from_path(path: &str) -> ::core::option::Option<&'static str> {
+            let name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+            let (_, extension) = name.rsplit_once('.')?;
+            from_extension(extension)
+        }
+
+        /// Returns the extensions of a media type, the preferred one first, or an empty
+        /// slice if it is unknown.
+        pub fn extensions(media_type: &str) -> &'static [&'static str] {
+            TYPES
+                .binary_search_by(|(key, _)| compare(key, media_type))
+                .map_or(&[], |index| TYPES[index].1)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that both database formats are read and shared extensions keep the first type
+    #[test]
+    fn test_parse() {
+        let apache = "# MIME type\t\tExtensions\n\
+                      text/css\t\t\tcss\n\
+                      application/javascript\t\tjs mjs\n\
+                      text/javascript\t\t\tjs\n\
+                      application/x-empty\n";
+        let types = parse(apache).unwrap();
+        assert_eq!(
+            types[1],
+            (
+                "application/javascript".to_string(),
+                vec!["js".to_string(), "mjs".to_string()]
+            )
+        );
+        assert!(types[3].1.is_empty());
+        let code = generate(&types).to_string();
+        assert!(code.contains("(\"js\" , \"application/javascript\")"));
+
+        let json = r#"{"application/json": {"source": "iana", "extensions": ["json", "map"]},
+                       "text/plain": {"extensions": ["txt"]}, "image/x-icon": {}}"#;
+        let types = parse(json).unwrap();
+        assert_eq!(types[0].0, "application/json");
+        assert_eq!(types[0].1, ["json", "map"]);
+        assert!(types[1].1.is_empty());
+
+        let error = parse("text/css css\nnot-a-type txt\n").unwrap_err();
+        assert!(error.contains("line 2"), "{}", error);
+        assert!(parse(r#"{"text/plain": {"extensions": "txt"}}"#).is_err());
+        assert!(parse("# only comments\n").is_err());
+    }
+}
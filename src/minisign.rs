@@ -0,0 +1,128 @@
+//! minisign and signify signatures, `signature_url = "...minisig"` with `minisign_pub`,
+//! checked against the trusted public key before the content is cached.
+//!
+//! Signatures of the BLAKE2b digest of the content, which `minisign` makes by default, and
+//! legacy signatures of the content itself are verified along with their trusted comment.
+//! OpenBSD `signify` shares the key format, and its signatures, which have no trusted
+//! comment, are verified as signatures of the content.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use minisign_verify::{PublicKey, Signature};
+use ring::signature;
+
+use crate::FetchOptions;
+
+/// Prefix of the comment lines of keys and signatures.
+const UNTRUSTED_COMMENT: &str = "untrusted comment:";
+
+/// Reads the trusted key, given as the base64 key, the content of a `.pub` file or the path
+/// to one relative to the manifest directory, which is tracked for re-expansion.
+pub(crate) fn load_key(value: &str, options: &mut FetchOptions) -> Result<String, String> {
+    let value = value.trim();
+    let inline = value.contains(UNTRUSTED_COMMENT) || decode_key(value).is_some();
+    let content = match inline {
+        true => value.to_string(),
+        false => String::from_utf8(crate::read_key_file(value, options)?)
+            .map_err(|_| format!("`{}` is not a minisign public key", value))?,
+    };
+    content
+        .lines()
+        .map(str::trim)
+        .rev()
+        .find(|line| !line.is_empty() && !line.starts_with(UNTRUSTED_COMMENT))
+        .map(str::to_string)
+        .ok_or_else(|| "`minisign_pub` holds no public key".to_string())
+}
+
+/// Fails unless `signature`, a `.minisig` or `.sig` file, was made over `content` by the
+/// base64 encoded public `key`.
+pub(crate) fn verify(content: &[u8], signature: &[u8], key: &str) -> Result<(), String> {
+    let public_key = PublicKey::from_base64(key)
+        .map_err(|_| format!("Invalid minisign public key `{}`", key))?;
+    let signature = std::str::from_utf8(signature)
+        .map_err(|_| "The signature file is not a minisign signature".to_string())?;
+    let lines: Vec<_> = signature
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+    match lines[..] {
+        [comment, encoded] if comment.starts_with(UNTRUSTED_COMMENT) => {
+            verify_signify(content, encoded, key)
+        }
+        _ => {
+            let signature = Signature::decode(&lines.join("\n"))
+                .map_err(|e| format!("Invalid minisign signature: {}", e))?;
+            public_key
+                .verify(content, &signature, true)
+                .map_err(|e| e.to_string())
+        }
+    }
+}
+
+/// Checks a signify signature, an Ed25519 signature of the content itself.
+fn verify_signify(content: &[u8], encoded: &str, key: &str) -> Result<(), String> {
+    let signature = decode(encoded, 74).ok_or("Invalid signify signature")?;
+    let key = decode_key(key).ok_or("Invalid signify public key")?;
+    if signature[2..10] != key[2..10] {
+        return Err(
+            "The signature was created with a different key than the one provided".to_string(),
+        );
+    }
+    signature::UnparsedPublicKey::new(&signature::ED25519, &key[10..])
+        .verify(content, &signature[10..])
+        .map_err(|_| "The signature verification failed".to_string())
+}
+
+/// Decodes a base64 public key: the `Ed` algorithm, the key ID and the Ed25519 key.
+fn decode_key(encoded: &str) -> Option<Vec<u8>> {
+    decode(encoded, 42)
+}
+
+/// Decodes base64 data of the `Ed` algorithm, expected to be `length` bytes long.
+fn decode(encoded: &str, length: usize) -> Option<Vec<u8>> {
+    STANDARD
+        .decode(encoded)
+        .ok()
+        .filter(|bytes| bytes.len() == length && bytes.starts_with(b"Ed"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "RWQBI0VniavN7wOhB7/zzhC+HXDdGOdLwJln5NYwm6UNXx3chmQSVTG4";
+
+    const MINISIG: &str = "untrusted comment: signature from minisign secret key
+RUQBI0VniavN7+vhVK/G5qGUvVgNXiv12dRhXAW3WbxNBKzT77qhzGk+1ViMyLDygzJVCNPQLp7pU2sG8npTTFRfURnrUaD//w4=
+trusted comment: timestamp:1760000000\tfile:hello.txt
+GIsFgf4MKLDfm4VPOW9ZgoHa4pBDHt9p+Q+FFo1ZabNhO+oCQprF6gYyzXNh9VsX4MXJmvLZNoQXl7283peRBQ==
+";
+
+    const SIGNIFY: &str = "untrusted comment: verify with release.pub
+RWQBI0VniavN74HEQzwYxgiYVVxtyzoWziYfKO6RY/ewGCMfp+rSzl49cS5CWppuJRTZvEvqdzeya4eKF3UEaCcL/KAp2t3YBww=
+";
+
+    /// Test that minisign and signify signatures are checked against the public key
+    #[test]
+    fn test_verify() {
+        let content = b"Hello, world.\n";
+        assert_eq!(verify(content, MINISIG.as_bytes(), KEY), Ok(()));
+        assert_eq!(verify(content, SIGNIFY.as_bytes(), KEY), Ok(()));
+        assert_eq!(
+            verify(b"Hello, world!\n", MINISIG.as_bytes(), KEY),
+            Err("The signature verification failed".to_string())
+        );
+        assert!(verify(b"Hello, world!\n", SIGNIFY.as_bytes(), KEY).is_err());
+
+        let other = "RWQAAAAAAAAAAAOhB7/zzhC+HXDdGOdLwJln5NYwm6UNXx3chmQSVTG4";
+        assert!(verify(content, MINISIG.as_bytes(), other).is_err());
+        assert!(verify(content, SIGNIFY.as_bytes(), other).is_err());
+
+        let mut options = FetchOptions::default();
+        let file = format!("untrusted comment: minisign public key\n{}\n", KEY);
+        assert_eq!(load_key(&file, &mut options).as_deref(), Ok(KEY));
+        assert_eq!(load_key(KEY, &mut options).as_deref(), Ok(KEY));
+        assert!(options.files.is_empty());
+    }
+}
@@ -0,0 +1,425 @@
+//! Artifacts of OCI registries, `oci://ghcr.io/org/artifact:tag`.
+//!
+//! The manifest of the reference is fetched, the layer of the requested media type is
+//! picked out of it, and its blob is fetched and checked against its digest. Both go
+//! through the cache as `oci://registry/v2/repository/...` URLs, which are requested from
+//! the registry API over HTTPS, or HTTP for `localhost`. Registries answering with a bearer
+//! challenge are given a pull token of the repository, anonymous or obtained with the
+//! credentials of `auth = basic_env(...)`, while `auth = bearer_env(...)` is sent as is.
+
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use reqwest::{
+    header::{ACCEPT, AUTHORIZATION, WWW_AUTHENTICATE},
+    StatusCode,
+};
+use serde::Deserialize;
+use url::Url;
+
+use crate::{
+    cached_url_content, check_status, credential, error_chain, http_client, Auth, CompressKind,
+    FetchOptions,
+};
+
+/// Manifest formats accepted from registries.
+const MANIFEST_TYPES: &str = "application/vnd.oci.image.manifest.v1+json, \
+     application/vnd.docker.distribution.manifest.v2+json, \
+     application/vnd.oci.image.index.v1+json, \
+     application/vnd.docker.distribution.manifest.list.v2+json";
+
+/// Annotation holding the file name of a layer, set by `oras push`.
+const TITLE_ANNOTATION: &str = "org.opencontainers.image.title";
+
+/// A reference to an artifact, `registry/repository:tag` or `registry/repository@digest`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Reference {
+    registry: String,
+    repository: String,
+    /// Tag or digest of the manifest.
+    reference: String,
+}
+
+impl Reference {
+    fn parse(value: &str) -> Result<Self, String> {
+        let invalid = || {
+            format!(
+                "Expected `oci://registry/repository:tag` or `oci://registry/repository@digest`, \
+                 got `{}`",
+                value
+            )
+        };
+        let (registry, path) = (value.strip_prefix("oci://"))
+            .and_then(|rest| rest.split_once('/'))
+            .ok_or_else(invalid)?;
+        let (repository, reference) = match path.split_once('@') {
+            Some((repository, digest)) => (repository, digest),
+            None => match path.rsplit_once(':') {
+                Some((repository, tag)) if !tag.contains('/') => (repository, tag),
+                _ => (path, "latest"),
+            },
+        };
+        if registry.is_empty() || repository.is_empty() || reference.is_empty() {
+            return Err(invalid());
+        }
+        // Docker Hub serves its API from another host, and official images under `library/`
+        let (registry, repository) = match registry {
+            "docker.io" | "index.docker.io" if !repository.contains('/') => {
+                ("registry-1.docker.io", format!("library/{}", repository))
+            }
+            "docker.io" | "index.docker.io" => ("registry-1.docker.io", repository.to_string()),
+            _ => (registry, repository.to_string()),
+        };
+        Ok(Reference {
+            registry: registry.to_string(),
+            repository,
+            reference: reference.to_string(),
+        })
+    }
+
+    fn manifest_url(&self) -> String {
+        format!(
+            "oci://{}/v2/{}/manifests/{}",
+            self.registry, self.repository, self.reference
+        )
+    }
+
+    fn blob_url(&self, digest: &str) -> String {
+        format!(
+            "oci://{}/v2/{}/blobs/{}",
+            self.registry, self.repository, digest
+        )
+    }
+}
+
+#[derive(Deserialize)]
+struct Manifest {
+    #[serde(default)]
+    layers: Vec<Descriptor>,
+    /// Manifests of an image index.
+    #[serde(default)]
+    manifests: Vec<Descriptor>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Descriptor {
+    media_type: String,
+    digest: String,
+    #[serde(default)]
+    annotations: HashMap<String, String>,
+}
+
+impl Descriptor {
+    fn describe(&self) -> String {
+        match self.annotations.get(TITLE_ANNOTATION) {
+            Some(title) => format!("`{}` ({}, {})", title, self.media_type, self.digest),
+            None => format!("{} ({})", self.media_type, self.digest),
+        }
+    }
+}
+
+/// Fetches the blob of the layer of the artifact with the given media type, or its only
+/// layer, into the cache.
+pub(crate) fn fetch_blob(
+    reference_str: &str,
+    media_type: Option<&str>,
+    options: &mut FetchOptions,
+) -> Result<PathBuf, String> {
+    let reference = Reference::parse(reference_str)?;
+    let mut manifest_options = FetchOptions {
+        sha256: None,
+        integrity: None,
+        signature_url: None,
+        pgp_key: None,
+        minisign_pub: None,
+        sigstore_identity: None,
+        zsync: None,
+        ..options.clone()
+    };
+    manifest_options
+        .headers
+        .push((ACCEPT.to_string(), MANIFEST_TYPES.to_string()));
+    let manifest = cached_url_content(
+        &reference.manifest_url(),
+        CompressKind::None,
+        &mut manifest_options,
+    )?;
+    for recorded in manifest_options.env {
+        if !options.env.contains(&recorded) {
+            options.env.push(recorded);
+        }
+    }
+    // The manifest of a tag is locked, the layer is then checked by its digest
+    options.files.extend(manifest_options.files);
+    let manifest =
+        std::fs::read(manifest).map_err(|e| format!("Failed to open cache file: {}", e))?;
+
+    let digest = select_layer(&manifest, media_type, reference_str)?;
+    let Some(hex) = digest.strip_prefix("sha256:") else {
+        return Err(format!(
+            "Unsupported digest `{}` of the layer of `{}`",
+            digest, reference_str
+        ));
+    };
+    options
+        .sha256
+        .get_or_insert_with(|| hex.to_ascii_lowercase());
+    cached_url_content(&reference.blob_url(&digest), CompressKind::None, options)
+}
+
+/// Returns the digest of the layer with the given media type, or of the only layer.
+fn select_layer(
+    manifest: &[u8],
+    media_type: Option<&str>,
+    reference: &str,
+) -> Result<String, String> {
+    let manifest: Manifest = serde_json::from_slice(manifest)
+        .map_err(|e| format!("Invalid manifest of `{}`: {}", reference, e))?;
+    if !manifest.manifests.is_empty() {
+        let manifests: Vec<_> = manifest
+            .manifests
+            .iter()
+            .map(Descriptor::describe)
+            .collect();
+        return Err(format!(
+            "`{}` is an index of {} manifests, reference one of them by digest: {}",
+            reference,
+            manifests.len(),
+            manifests.join(", ")
+        ));
+    }
+    let layers: Vec<_> = (manifest.layers.iter())
+        .filter(|layer| media_type.is_none_or(|media_type| layer.media_type == media_type))
+        .collect();
+    let described = || {
+        let layers: Vec<_> = manifest.layers.iter().map(Descriptor::describe).collect();
+        layers.join(", ")
+    };
+    match (layers.as_slice(), media_type) {
+        ([layer], _) => Ok(layer.digest.clone()),
+        ([], Some(media_type)) => Err(format!(
+            "`{}` has no `{}` layer, its layers are: {}",
+            reference,
+            media_type,
+            described()
+        )),
+        ([], None) => Err(format!("`{}` has no layers", reference)),
+        (_, _) => Err(format!(
+            "`{}` has {} matching layers, pick one with `media_type`: {}",
+            reference,
+            layers.len(),
+            described()
+        )),
+    }
+}
+
+/// Translates an `oci://registry/v2/...` URL into the request to the registry API, with a
+/// pull token of the repository when the registry asks for one.
+pub(crate) fn request(url_str: &str, options: &mut FetchOptions) -> Result<Url, String> {
+    let url = Url::parse(url_str).map_err(|e| format!("Invalid URL: {}", e))?;
+    // Registries on the local machine are commonly served without TLS
+    let scheme = match url.host_str() {
+        Some("localhost" | "127.0.0.1" | "[::1]") => "http",
+        _ => "https",
+    };
+    let request = Url::parse(&url_str.replacen("oci", scheme, 1))
+        .map_err(|e| format!("Invalid URL: {}", e))?;
+    let repository = (url.path().strip_prefix("/v2/"))
+        .and_then(|path| {
+            path.rsplit_once("/manifests/")
+                .or_else(|| path.rsplit_once("/blobs/"))
+        })
+        .map(|(repository, _)| repository)
+        .ok_or_else(|| format!("Unexpected registry URL `{}`", url_str))?;
+
+    if !matches!(options.auth, Some(Auth::Bearer(_))) {
+        if let Some(token) = pull_token(&request, repository, options)? {
+            options.auth = None;
+            options
+                .headers
+                .push((AUTHORIZATION.to_string(), format!("Bearer {}", token)));
+        }
+    }
+    Ok(request)
+}
+
+/// Returns a token allowing to pull from the repository, reused until shortly before it
+/// expires, or `None` when the registry does not ask for one.
+fn pull_token(
+    request: &Url,
+    repository: &str,
+    options: &FetchOptions,
+) -> Result<Option<String>, String> {
+    static TOKENS: OnceLock<Mutex<HashMap<String, (String, Instant)>>> = OnceLock::new();
+    let tokens = TOKENS.get_or_init(Default::default);
+    let lock = || {
+        tokens
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    };
+    let base = request
+        .join("/v2/")
+        .map_err(|e| format!("Invalid URL: {}", e))?;
+    let key = format!("{}{}", base, repository);
+    if let Some((token, expires)) = lock().get(&key) {
+        if Instant::now() < *expires {
+            return Ok(Some(token.clone()));
+        }
+    }
+
+    let registry = base.host_str().unwrap_or_default().to_string();
+    let response = http_client(&base, options)?
+        .get(base)
+        .header("User-Agent", "include_url_macro")
+        .send()
+        .map_err(|e| {
+            format!(
+                "Failed to reach registry `{}`: {}",
+                registry,
+                error_chain(&e)
+            )
+        })?;
+    let challenge = (response.headers().get(WWW_AUTHENTICATE))
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    // Registries accepting the request, or credentials as is, need no token
+    let bearer = challenge
+        .get(..7)
+        .is_some_and(|scheme| scheme.eq_ignore_ascii_case("bearer "));
+    if response.status() != StatusCode::UNAUTHORIZED || !bearer {
+        return Ok(None);
+    }
+    let parameters = challenge_parameters(&challenge[7..]);
+    let realm = parameters
+        .get("realm")
+        .ok_or_else(|| format!("Registry `{}` names no token service", registry))?;
+    let mut token_url =
+        Url::parse(realm).map_err(|e| format!("Invalid token service `{}`: {}", realm, e))?;
+    if let Some(service) = parameters.get("service") {
+        token_url.query_pairs_mut().append_pair("service", service);
+    }
+    token_url
+        .query_pairs_mut()
+        .append_pair("scope", &format!("repository:{}:pull", repository));
+
+    let mut token_request = http_client(&token_url, options)?
+        .get(token_url)
+        .header("User-Agent", "include_url_macro");
+    if let Some(Auth::Basic(user, password)) = &options.auth {
+        token_request = token_request.basic_auth(credential(user)?, Some(credential(password)?));
+    }
+    #[derive(Deserialize)]
+    struct Token {
+        token: Option<String>,
+        access_token: Option<String>,
+        expires_in: Option<u64>,
+    }
+    let token: Token = token_request
+        .send()
+        .map_err(|e| error_chain(&e))
+        .and_then(|response| check_status(response, options).map_err(|e| e.message))
+        .and_then(|response| response.json().map_err(|e| e.to_string()))
+        .map_err(|e| {
+            format!(
+                "Failed to get a pull token of `{}` from `{}`: {}",
+                repository, realm, e
+            )
+        })?;
+    let value = (token.token.or(token.access_token))
+        .ok_or_else(|| format!("The token service `{}` returned no token", realm))?;
+
+    // Tokens last 60 seconds unless told otherwise
+    let lifetime = Duration::from_secs(token.expires_in.unwrap_or(60).saturating_sub(10));
+    lock().insert(key, (value.clone(), Instant::now() + lifetime));
+    Ok(Some(value))
+}
+
+/// Reads the `name="value"` parameters of a `WWW-Authenticate` challenge.
+fn challenge_parameters(challenge: &str) -> HashMap<String, String> {
+    let mut parameters = HashMap::new();
+    let mut rest = challenge;
+    while let Some((name, value)) = rest.split_once('=') {
+        let name = name.trim_matches(|c: char| c == ',' || c.is_whitespace());
+        let (value, next) = match value.strip_prefix('"') {
+            Some(quoted) => {
+                let end = quoted.find('"').unwrap_or(quoted.len());
+                (&quoted[..end], quoted.get(end + 1..).unwrap_or_default())
+            }
+            None => {
+                let end = value.find(',').unwrap_or(value.len());
+                (&value[..end], &value[end..])
+            }
+        };
+        parameters.insert(name.to_ascii_lowercase(), value.to_string());
+        rest = next;
+    }
+    parameters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that references are split into registry, repository and tag or digest
+    #[test]
+    fn test_reference() {
+        let reference = Reference::parse("oci://ghcr.io/org/schemas:v1").unwrap();
+        assert_eq!(
+            reference.manifest_url(),
+            "oci://ghcr.io/v2/org/schemas/manifests/v1"
+        );
+        let reference = Reference::parse("oci://localhost:5000/models@sha256:ab12").unwrap();
+        assert_eq!(reference.repository, "models");
+        assert_eq!(reference.reference, "sha256:ab12");
+        assert_eq!(
+            reference.blob_url("sha256:cd34"),
+            "oci://localhost:5000/v2/models/blobs/sha256:cd34"
+        );
+        let reference = Reference::parse("oci://docker.io/alpine").unwrap();
+        assert_eq!(reference.registry, "registry-1.docker.io");
+        assert_eq!(reference.repository, "library/alpine");
+        assert_eq!(reference.reference, "latest");
+        assert!(Reference::parse("oci://ghcr.io").is_err());
+        assert!(Reference::parse("https://ghcr.io/org/schemas").is_err());
+    }
+
+    /// Test that the layer is picked by its media type
+    #[test]
+    fn test_select_layer() {
+        let manifest = br#"{"schemaVersion": 2, "layers": [
+            {"mediaType": "application/schema+json", "digest": "sha256:aa", "size": 10,
+             "annotations": {"org.opencontainers.image.title": "order.json"}},
+            {"mediaType": "application/vnd.onnx", "digest": "sha256:bb", "size": 20}
+        ]}"#;
+        let select = |media_type| select_layer(manifest, media_type, "oci://ghcr.io/org/a:v1");
+        assert_eq!(select(Some("application/vnd.onnx")).unwrap(), "sha256:bb");
+        let error = select(None).unwrap_err();
+        assert!(error.contains("`order.json` (application/schema+json, sha256:aa)"));
+        assert!(select(Some("text/plain"))
+            .unwrap_err()
+            .contains("no `text/plain` layer"));
+
+        let index = br#"{"manifests": [{"mediaType": "application/vnd.oci.image.manifest.v1+json",
+                         "digest": "sha256:cc", "size": 30}]}"#;
+        assert!(select_layer(index, None, "oci://ghcr.io/org/a:v1")
+            .unwrap_err()
+            .contains("index of 1 manifests"));
+    }
+
+    /// Test that the parameters of a bearer challenge are read
+    #[test]
+    fn test_challenge_parameters() {
+        let parameters = challenge_parameters(
+            r#"realm="https://ghcr.io/token",service="ghcr.io",scope="repository:org/a:pull,push""#,
+        );
+        assert_eq!(parameters["realm"], "https://ghcr.io/token");
+        assert_eq!(parameters["service"], "ghcr.io");
+        assert_eq!(parameters["scope"], "repository:org/a:pull,push");
+        assert_eq!(challenge_parameters("realm=x, error=y")["error"], "y");
+    }
+}
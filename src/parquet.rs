@@ -0,0 +1,314 @@
+//! Column extraction for the Parquet files included by `include_parquet_url!`.
+//!
+//! Every selected column becomes a constant slice of its values. The element type follows
+//! the values the reader decodes, so logical types such as `INT_8` or `DATE` keep their
+//! width, and optional columns become slices of `Option`s.
+
+use ::parquet::basic::{Repetition, Type as PhysicalType};
+use ::parquet::file::reader::{FileReader, SerializedFileReader};
+use ::parquet::record::Field;
+use proc_macro2::{Literal, TokenStream};
+use quote::{quote, ToTokens as _};
+
+use crate::{const_ident, float_literal};
+
+/// The Rust element type of a column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColumnType {
+    Bool,
+    I8,
+    I16,
+    I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
+    F32,
+    F64,
+    Str,
+    Bytes,
+}
+
+impl ColumnType {
+    /// Returns the type and literal of a non-null primitive value.
+    fn of(field: &Field) -> Option<(ColumnType, TokenStream)> {
+        Some(match field {
+            Field::Bool(value) => (ColumnType::Bool, quote! { #value }),
+            Field::Byte(value) => (
+                ColumnType::I8,
+                Literal::i8_unsuffixed(*value).into_token_stream(),
+            ),
+            Field::Short(value) => (
+                ColumnType::I16,
+                Literal::i16_unsuffixed(*value).into_token_stream(),
+            ),
+            Field::Int(value) | Field::Date(value) => (
+                ColumnType::I32,
+                Literal::i32_unsuffixed(*value).into_token_stream(),
+            ),
+            Field::Long(value) | Field::TimestampMillis(value) | Field::TimestampMicros(value) => (
+                ColumnType::I64,
+                Literal::i64_unsuffixed(*value).into_token_stream(),
+            ),
+            Field::UByte(value) => (
+                ColumnType::U8,
+                Literal::u8_unsuffixed(*value).into_token_stream(),
+            ),
+            Field::UShort(value) => (
+                ColumnType::U16,
+                Literal::u16_unsuffixed(*value).into_token_stream(),
+            ),
+            Field::UInt(value) => (
+                ColumnType::U32,
+                Literal::u32_unsuffixed(*value).into_token_stream(),
+            ),
+            Field::ULong(value) => (
+                ColumnType::U64,
+                Literal::u64_unsuffixed(*value).into_token_stream(),
+            ),
+            Field::Float16(value) => (
+                ColumnType::F32,
+                float_literal(f64::from(*value), quote! { f32 }),
+            ),
+            Field::Float(value) => (
+                ColumnType::F32,
+                float_literal(f64::from(*value), quote! { f32 }),
+            ),
+            Field::Double(value) => (ColumnType::F64, float_literal(*value, quote! { f64 })),
+            Field::Decimal(_) => {
+                let value = field.to_string();
+                (ColumnType::Str, quote! { #value })
+            }
+            Field::Str(value) => (ColumnType::Str, quote! { #value }),
+            Field::Bytes(value) => {
+                let value = Literal::byte_string(value.data());
+                (ColumnType::Bytes, quote! { #value })
+            }
+            Field::Null | Field::Group(_) | Field::ListInternal(_) | Field::MapInternal(_) => {
+                return None
+            }
+        })
+    }
+
+    /// Returns the type of a column without values from its physical type.
+    fn of_physical(ty: PhysicalType) -> ColumnType {
+        match ty {
+            PhysicalType::BOOLEAN => ColumnType::Bool,
+            PhysicalType::INT32 => ColumnType::I32,
+            PhysicalType::INT64 | PhysicalType::INT96 => ColumnType::I64,
+            PhysicalType::FLOAT => ColumnType::F32,
+            PhysicalType::DOUBLE => ColumnType::F64,
+            PhysicalType::BYTE_ARRAY | PhysicalType::FIXED_LEN_BYTE_ARRAY => ColumnType::Bytes,
+        }
+    }
+
+    fn rust_type(self) -> TokenStream {
+        match self {
+            ColumnType::Bool => quote! { bool },
+            ColumnType::I8 => quote! { i8 },
+            ColumnType::I16 => quote! { i16 },
+            ColumnType::I32 => quote! { i32 },
+            ColumnType::I64 => quote! { i64 },
+            ColumnType::U8 => quote! { u8 },
+            ColumnType::U16 => quote! { u16 },
+            ColumnType::U32 => quote! { u32 },
+            ColumnType::U64 => quote! { u64 },
+            ColumnType::F32 => quote! { f32 },
+            ColumnType::F64 => quote! { f64 },
+            ColumnType::Str => quote! { &str },
+            ColumnType::Bytes => quote! { &[u8] },
+        }
+    }
+}
+
+/// Generates a `ROWS` constant and a constant slice for each of the named columns of a
+/// Parquet file, or for every column if `columns` is `None`.
+///
+/// Columns are named after their Parquet name in `SCREAMING_SNAKE_CASE`. Only top-level
+/// primitive columns can be embedded.
+pub(crate) fn generate(
+    content: bytes::Bytes,
+    columns: Option<&[String]>,
+) -> Result<TokenStream, String> {
+    let reader = SerializedFileReader::new(content)
+        .map_err(|e| format!("Invalid Parquet file from URL: {}", e))?;
+    let schema = reader
+        .metadata()
+        .file_metadata()
+        .schema_descr()
+        .root_schema();
+    let fields = schema.get_fields();
+    let names: Vec<String> = match columns {
+        Some(columns) => columns.to_vec(),
+        None => fields
+            .iter()
+            .map(|field| field.name().to_string())
+            .collect(),
+    };
+
+    let mut selected = Vec::new();
+    for name in &names {
+        let field = fields
+            .iter()
+            .find(|field| field.name() == name)
+            .ok_or_else(|| {
+                let available: Vec<&str> = fields.iter().map(|field| field.name()).collect();
+                format!(
+                    "The Parquet file has no column `{}`, expected one of: {}",
+                    name,
+                    available.join(", ")
+                )
+            })?;
+        let info = field.get_basic_info();
+        if !field.is_primitive() || info.repetition() == Repetition::REPEATED {
+            return Err(format!(
+                "The Parquet column `{}` is nested, only primitive columns can be embedded",
+                name
+            ));
+        }
+        selected.push((
+            name.as_str(),
+            info.repetition() == Repetition::OPTIONAL,
+            ColumnType::of_physical(field.get_physical_type()),
+        ));
+    }
+
+    let mut values: Vec<Vec<Option<TokenStream>>> = vec![Vec::new(); selected.len()];
+    let mut types: Vec<Option<ColumnType>> = vec![None; selected.len()];
+    let mut row_count = 0usize;
+    let rows = reader
+        .get_row_iter(None)
+        .map_err(|e| format!("Failed to read Parquet rows: {}", e))?;
+    for row in rows {
+        let row = row.map_err(|e| format!("Failed to read Parquet rows: {}", e))?;
+        row_count += 1;
+        for (index, (name, _, _)) in selected.iter().enumerate() {
+            let field = row
+                .get_column_iter()
+                .find(|(column, _)| column == name)
+                .map(|(_, field)| field)
+                .ok_or_else(|| format!("A row is missing the Parquet column `{}`", name))?;
+            let value = match ColumnType::of(field) {
+                Some((ty, value)) => {
+                    if types[index].is_some_and(|known| known != ty) {
+                        return Err(format!("The Parquet column `{}` has mixed types", name));
+                    }
+                    types[index] = Some(ty);
+                    Some(value)
+                }
+                None if matches!(field, Field::Null) => None,
+                None => {
+                    return Err(format!(
+                        "The Parquet column `{}` is nested, only primitive columns can be \
+                         embedded",
+                        name
+                    ))
+                }
+            };
+            values[index].push(value);
+        }
+    }
+
+    let mut idents = vec![const_ident("rows")];
+    let mut constants = Vec::new();
+    for (((name, optional, physical), values), ty) in selected.into_iter().zip(values).zip(types) {
+        let ident = const_ident(name)
+            .filter(|ident| !idents.contains(&Some(ident.clone())))
+            .ok_or_else(|| {
+                format!(
+                    "The Parquet column `{}` does not map to a unique constant name",
+                    name
+                )
+            })?;
+        idents.push(Some(ident.clone()));
+        let element = ty.unwrap_or(physical).rust_type();
+        let doc = format!("The `{}` column.", name);
+        let constant = match optional {
+            true => {
+                let values = values.into_iter().map(|value| match value {
+                    Some(value) => quote! { ::core::option::Option::Some(#value) },
+                    None => quote! { ::core::option::Option::None },
+                });
+                quote! {
+                    #[doc = #doc]
+                    pub const #ident: &[::core::option::Option<#element>] = &[#(#values),*];
+                }
+            }
+            false => {
+                let values = values
+                    .into_iter()
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| format!("The required Parquet column `{}` has nulls", name))?;
+                quote! {
+                    #[doc = #doc]
+                    pub const #ident: &[#element] = &[#(#values),*];
+                }
+            }
+        };
+        constants.push(constant);
+    }
+
+    Ok(quote! {
+        /// The number of rows.
+        pub const ROWS: usize = #row_count;
+        #(#constants)*
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use ::parquet::data_type::{ByteArray, ByteArrayType, Int64Type};
+    use ::parquet::file::writer::SerializedFileWriter;
+    use ::parquet::schema::parser::parse_message_type;
+
+    use super::*;
+
+    /// Writes a file with a required `id` column and an optional `name` column.
+    fn sample() -> bytes::Bytes {
+        let schema = parse_message_type(
+            "message sample { required int64 id; optional binary name (UTF8); }",
+        )
+        .unwrap();
+        let mut content = Vec::new();
+        let mut writer =
+            SerializedFileWriter::new(&mut content, Arc::new(schema), Default::default()).unwrap();
+        let mut row_group = writer.next_row_group().unwrap();
+        let mut column = row_group.next_column().unwrap().unwrap();
+        column
+            .typed::<Int64Type>()
+            .write_batch(&[1, 2], None, None)
+            .unwrap();
+        column.close().unwrap();
+        let mut column = row_group.next_column().unwrap().unwrap();
+        column
+            .typed::<ByteArrayType>()
+            .write_batch(&[ByteArray::from("Ferris")], Some(&[1, 0]), None)
+            .unwrap();
+        column.close().unwrap();
+        row_group.close().unwrap();
+        writer.close().unwrap();
+        content.into()
+    }
+
+    /// Test that columns are projected and typed after their values
+    #[test]
+    fn test_generate() {
+        let code = generate(sample(), None).unwrap().to_string();
+        assert!(code.contains("pub const ROWS : usize = 2usize"));
+        assert!(code.contains("pub const ID : & [i64] = & [1 , 2]"));
+        assert!(code.contains(
+            "pub const NAME : & [:: core :: option :: Option < & str >] = & [:: core :: option \
+             :: Option :: Some (\"Ferris\") , :: core :: option :: Option :: None]"
+        ));
+
+        let code = generate(sample(), Some(&["name".to_string()]))
+            .unwrap()
+            .to_string();
+        assert!(!code.contains("pub const ID"));
+        assert!(generate(sample(), Some(&["missing".to_string()])).is_err());
+        assert!(generate(bytes::Bytes::from_static(b"PAR1"), None).is_err());
+    }
+}
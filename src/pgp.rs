@@ -0,0 +1,448 @@
+//! Detached OpenPGP signatures, `signature_url = "...asc"` with `pgp_key`, checked against
+//! the trusted public keys before the content is cached.
+//!
+//! Version 4 signatures of binary or text documents by RSA and Ed25519 keys are verified,
+//! hashed with SHA-256, SHA-384 or SHA-512. The keys are trusted as they are given: every
+//! primary key and subkey of the key block may have made the signature, and their binding
+//! signatures, expiry and revocation are not looked at.
+
+use std::borrow::Cow;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ring::{digest, signature};
+
+use crate::FetchOptions;
+
+/// Object identifier of the Ed25519 curve of legacy EdDSA keys.
+const ED25519_OID: &[u8] = &[0x2B, 0x06, 0x01, 0x04, 0x01, 0xDA, 0x47, 0x0F, 0x01];
+
+/// Public key algorithms.
+const RSA: u8 = 1;
+const RSA_SIGN_ONLY: u8 = 3;
+const EDDSA_LEGACY: u8 = 22;
+const ED25519: u8 = 27;
+
+#[derive(Debug)]
+enum KeyMaterial {
+    Rsa { n: Vec<u8>, e: Vec<u8> },
+    Ed25519(Vec<u8>),
+}
+
+/// A primary key or subkey of the trusted key block.
+#[derive(Debug)]
+struct PublicKey {
+    key_id: [u8; 8],
+    material: KeyMaterial,
+}
+
+/// A version 4 signature packet.
+#[derive(Debug)]
+struct Signature {
+    /// `0x00` for binary documents, `0x01` for text.
+    kind: u8,
+    algorithm: u8,
+    hash: u8,
+    /// The signature packet from its version to the end of the hashed subpackets.
+    hashed: Vec<u8>,
+    issuer: Option<[u8; 8]>,
+    /// The MPIs of RSA and legacy EdDSA signatures, or the raw Ed25519 signature.
+    values: Vec<Vec<u8>>,
+}
+
+/// Reads the trusted keys, given as an armored key block or the path to a key file relative
+/// to the manifest directory, which is tracked for re-expansion.
+pub(crate) fn load_keys(value: &str, options: &mut FetchOptions) -> Result<Vec<u8>, String> {
+    match value.contains("-----BEGIN PGP") {
+        true => Ok(value.as_bytes().to_vec()),
+        false => crate::read_key_file(value, options),
+    }
+}
+
+/// Fails unless one of the signatures of `signature` was made over `content` by one of the
+/// keys of `keys`. Both may be armored or binary.
+pub(crate) fn verify(content: &[u8], signature: &[u8], keys: &[u8]) -> Result<(), String> {
+    let key_data = dearmor(keys)?;
+    let mut trusted = Vec::new();
+    for (tag, body) in packets(&key_data).map_err(|e| format!("Invalid `pgp_key`: {}", e))? {
+        if matches!(tag, 6 | 14) {
+            trusted.extend(parse_key(body)?);
+        }
+    }
+    if trusted.is_empty() {
+        return Err("`pgp_key` holds no RSA or Ed25519 public key".to_string());
+    }
+
+    let signature_data = dearmor(signature)?;
+    let signatures = packets(&signature_data)
+        .map_err(|e| format!("Invalid OpenPGP signature: {}", e))?
+        .into_iter()
+        .filter(|(tag, _)| *tag == 2)
+        .map(|(_, body)| parse_signature(body))
+        .collect::<Result<Vec<_>, _>>()?;
+    if signatures.is_empty() {
+        return Err("The signature file holds no OpenPGP signature".to_string());
+    }
+    for signature in &signatures {
+        let candidates: Vec<_> = match signature.issuer {
+            Some(issuer) => trusted.iter().filter(|key| key.key_id == issuer).collect(),
+            None => trusted.iter().collect(),
+        };
+        for key in candidates {
+            if verify_with(signature, key, content)? {
+                return Ok(());
+            }
+        }
+    }
+    let issuers: Option<Vec<_>> = signatures
+        .iter()
+        .map(|signature| signature.issuer)
+        .collect();
+    match issuers {
+        Some(issuers)
+            if !issuers
+                .iter()
+                .any(|id| trusted.iter().any(|key| key.key_id == *id)) =>
+        {
+            Err(format!(
+                "The content is signed by key {}, which is not in `pgp_key`",
+                hex(&issuers[0])
+            ))
+        }
+        _ => Err("The OpenPGP signature does not match the content".to_string()),
+    }
+}
+
+/// Checks a signature with one key, telling whether it matches.
+fn verify_with(signature: &Signature, key: &PublicKey, content: &[u8]) -> Result<bool, String> {
+    let message = match signature.kind {
+        0x00 => Cow::Borrowed(content),
+        0x01 => Cow::Owned(canonical_text(content)),
+        kind => {
+            return Err(format!(
+                "Unsupported OpenPGP signature type 0x{:02x}, expected a document signature",
+                kind
+            ))
+        }
+    };
+    let mut trailer = signature.hashed.clone();
+    trailer.extend([0x04, 0xFF]);
+    trailer.extend((signature.hashed.len() as u32).to_be_bytes());
+    let unsupported_hash = || {
+        format!(
+            "Unsupported OpenPGP hash algorithm {}, expected SHA-256, SHA-384 or SHA-512",
+            signature.hash
+        )
+    };
+    match (&key.material, signature.algorithm) {
+        (KeyMaterial::Rsa { n, e }, RSA | RSA_SIGN_ONLY) => {
+            let parameters = match signature.hash {
+                8 => &signature::RSA_PKCS1_2048_8192_SHA256,
+                9 => &signature::RSA_PKCS1_2048_8192_SHA384,
+                10 => &signature::RSA_PKCS1_2048_8192_SHA512,
+                _ => return Err(unsupported_hash()),
+            };
+            let mut signed = Vec::with_capacity(message.len() + trailer.len());
+            signed.extend_from_slice(&message);
+            signed.extend_from_slice(&trailer);
+            let value = left_pad(&signature.values[0], n.len());
+            let key = signature::RsaPublicKeyComponents { n, e };
+            Ok(key.verify(parameters, &signed, &value).is_ok())
+        }
+        (KeyMaterial::Ed25519(public), EDDSA_LEGACY | ED25519) => {
+            let algorithm = match signature.hash {
+                8 => &digest::SHA256,
+                9 => &digest::SHA384,
+                10 => &digest::SHA512,
+                _ => return Err(unsupported_hash()),
+            };
+            let mut context = digest::Context::new(algorithm);
+            context.update(&message);
+            context.update(&trailer);
+            let hashed = context.finish();
+            let value = match signature.algorithm {
+                EDDSA_LEGACY => [
+                    left_pad(&signature.values[0], 32),
+                    left_pad(&signature.values[1], 32),
+                ]
+                .concat(),
+                _ => signature.values[0].clone(),
+            };
+            let key = signature::UnparsedPublicKey::new(&signature::ED25519, public);
+            Ok(key.verify(hashed.as_ref(), &value).is_ok())
+        }
+        _ => Ok(false),
+    }
+}
+
+/// Decodes the ASCII armored blocks of `data`, or returns binary data as it is.
+fn dearmor(data: &[u8]) -> Result<Cow<'_, [u8]>, String> {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return Ok(Cow::Borrowed(data));
+    };
+    if !text.contains("-----BEGIN PGP") {
+        return Ok(Cow::Borrowed(data));
+    }
+    let mut decoded = Vec::new();
+    let mut lines = text.lines().map(str::trim);
+    while lines.any(|line| line.starts_with("-----BEGIN PGP")) {
+        let mut encoded = String::new();
+        let mut in_headers = true;
+        for line in lines.by_ref() {
+            if line.starts_with("-----END PGP") || (line.starts_with('=') && line.len() == 5) {
+                break;
+            }
+            // Armor headers, such as `Version: ...`, end at a blank line
+            if in_headers && (line.is_empty() || line.contains(": ")) {
+                in_headers = !line.is_empty();
+                continue;
+            }
+            in_headers = false;
+            encoded.push_str(line);
+        }
+        let block = STANDARD
+            .decode(&encoded)
+            .map_err(|e| format!("Invalid OpenPGP armor: {}", e))?;
+        decoded.extend(block);
+    }
+    Ok(Cow::Owned(decoded))
+}
+
+/// Splits OpenPGP data into the tags and bodies of its packets.
+fn packets(mut data: &[u8]) -> Result<Vec<(u8, &[u8])>, String> {
+    let mut packets = Vec::new();
+    while let Some(&header) = data.first() {
+        data = &data[1..];
+        if header & 0x80 == 0 {
+            return Err("not an OpenPGP packet".to_string());
+        }
+        let (tag, length) = if header & 0x40 != 0 {
+            let first = take(&mut data, 1)?[0] as usize;
+            let length = match first {
+                0..=191 => first,
+                192..=223 => ((first - 192) << 8) + take(&mut data, 1)?[0] as usize + 192,
+                255 => be_number(take(&mut data, 4)?),
+                _ => return Err("partial packet lengths are not supported".to_string()),
+            };
+            (header & 0x3F, length)
+        } else {
+            let length = match header & 0x03 {
+                0 => be_number(take(&mut data, 1)?),
+                1 => be_number(take(&mut data, 2)?),
+                2 => be_number(take(&mut data, 4)?),
+                _ => data.len(),
+            };
+            ((header >> 2) & 0x0F, length)
+        };
+        packets.push((tag, take(&mut data, length)?));
+    }
+    Ok(packets)
+}
+
+/// Parses a public key or public subkey packet, skipping keys of other versions and
+/// algorithms.
+fn parse_key(body: &[u8]) -> Result<Option<PublicKey>, String> {
+    let invalid = |e: String| format!("Invalid public key in `pgp_key`: {}", e);
+    let mut rest = body;
+    if take(&mut rest, 1).map_err(invalid)?[0] != 4 {
+        return Ok(None);
+    }
+    let _created = take(&mut rest, 4).map_err(invalid)?;
+    let material = match take(&mut rest, 1).map_err(invalid)?[0] {
+        RSA | RSA_SIGN_ONLY => KeyMaterial::Rsa {
+            n: mpi(&mut rest).map_err(invalid)?.to_vec(),
+            e: mpi(&mut rest).map_err(invalid)?.to_vec(),
+        },
+        EDDSA_LEGACY => {
+            let oid_length = take(&mut rest, 1).map_err(invalid)?[0] as usize;
+            if take(&mut rest, oid_length).map_err(invalid)? != ED25519_OID {
+                return Ok(None);
+            }
+            match mpi(&mut rest).map_err(invalid)? {
+                [0x40, point @ ..] if point.len() == 32 => KeyMaterial::Ed25519(point.to_vec()),
+                _ => return Err(invalid("expected a native Ed25519 point".to_string())),
+            }
+        }
+        ED25519 => KeyMaterial::Ed25519(take(&mut rest, 32).map_err(invalid)?.to_vec()),
+        _ => return Ok(None),
+    };
+    // The fingerprint of version 4 keys is the SHA-1 digest of the framed key packet
+    let mut framed = vec![0x99];
+    framed.extend((body.len() as u16).to_be_bytes());
+    framed.extend(body);
+    let fingerprint = digest::digest(&digest::SHA1_FOR_LEGACY_USE_ONLY, &framed);
+    let mut key_id = [0; 8];
+    key_id.copy_from_slice(&fingerprint.as_ref()[12..]);
+    Ok(Some(PublicKey { key_id, material }))
+}
+
+/// Parses a signature packet.
+fn parse_signature(body: &[u8]) -> Result<Signature, String> {
+    let invalid = |e: String| format!("Invalid OpenPGP signature: {}", e);
+    let mut rest = body;
+    let version = take(&mut rest, 1).map_err(invalid)?[0];
+    if version != 4 {
+        return Err(format!(
+            "Unsupported OpenPGP signature version {}, expected 4",
+            version
+        ));
+    }
+    let header = take(&mut rest, 3).map_err(invalid)?;
+    let (kind, algorithm, hash) = (header[0], header[1], header[2]);
+    let hashed_length = be_number(take(&mut rest, 2).map_err(invalid)?);
+    let hashed_subpackets = take(&mut rest, hashed_length).map_err(invalid)?;
+    let hashed = body[..body.len() - rest.len()].to_vec();
+    let unhashed_length = be_number(take(&mut rest, 2).map_err(invalid)?);
+    let unhashed_subpackets = take(&mut rest, unhashed_length).map_err(invalid)?;
+    let _hash_prefix = take(&mut rest, 2).map_err(invalid)?;
+    let values = match algorithm {
+        RSA | RSA_SIGN_ONLY => vec![mpi(&mut rest).map_err(invalid)?.to_vec()],
+        EDDSA_LEGACY => vec![
+            mpi(&mut rest).map_err(invalid)?.to_vec(),
+            mpi(&mut rest).map_err(invalid)?.to_vec(),
+        ],
+        ED25519 => vec![take(&mut rest, 64).map_err(invalid)?.to_vec()],
+        _ => {
+            return Err(format!(
+                "Unsupported OpenPGP signature algorithm {}, expected RSA or Ed25519",
+                algorithm
+            ))
+        }
+    };
+    let mut issuer = None;
+    for subpackets in [hashed_subpackets, unhashed_subpackets] {
+        for (kind, data) in subpackets_of(subpackets).map_err(invalid)? {
+            match (kind, data) {
+                (16, [..]) if data.len() == 8 => issuer = issuer.or(data.try_into().ok()),
+                // Issuer fingerprint, of which version 4 keys take the last 8 bytes as ID
+                (33, [4, fingerprint @ ..]) if fingerprint.len() == 20 => {
+                    issuer = issuer.or(fingerprint[12..].try_into().ok())
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(Signature {
+        kind,
+        algorithm,
+        hash,
+        hashed,
+        issuer,
+        values,
+    })
+}
+
+/// Splits signature subpackets into their types, without the critical bit, and data.
+fn subpackets_of(mut data: &[u8]) -> Result<Vec<(u8, &[u8])>, String> {
+    let mut subpackets = Vec::new();
+    while !data.is_empty() {
+        let first = take(&mut data, 1)?[0] as usize;
+        let length = match first {
+            0..=191 => first,
+            192..=254 => ((first - 192) << 8) + take(&mut data, 1)?[0] as usize + 192,
+            _ => be_number(take(&mut data, 4)?),
+        };
+        let subpacket = take(&mut data, length)?;
+        let (kind, content) = subpacket
+            .split_first()
+            .ok_or_else(|| "empty subpacket".to_string())?;
+        subpackets.push((kind & 0x7F, content));
+    }
+    Ok(subpackets)
+}
+
+/// Normalizes the line endings of a text document to CRLF, as text signatures are made.
+fn canonical_text(content: &[u8]) -> Vec<u8> {
+    let mut text = Vec::with_capacity(content.len());
+    for (index, &byte) in content.iter().enumerate() {
+        if byte == b'\n' && (index == 0 || content[index - 1] != b'\r') {
+            text.push(b'\r');
+        }
+        text.push(byte);
+    }
+    text
+}
+
+/// Takes the next `length` bytes.
+fn take<'a>(data: &mut &'a [u8], length: usize) -> Result<&'a [u8], String> {
+    if data.len() < length {
+        return Err("unexpected end of data".to_string());
+    }
+    let (taken, rest) = data.split_at(length);
+    *data = rest;
+    Ok(taken)
+}
+
+/// Takes a multiprecision integer, returning its big-endian bytes.
+fn mpi<'a>(data: &mut &'a [u8]) -> Result<&'a [u8], String> {
+    let bits = be_number(take(data, 2)?);
+    take(data, bits.div_ceil(8))
+}
+
+fn be_number(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .fold(0, |number, &byte| (number << 8) | byte as usize)
+}
+
+/// Pads a big-endian number with leading zeros to `length` bytes.
+fn left_pad(value: &[u8], length: usize) -> Vec<u8> {
+    let mut padded = vec![0; length.saturating_sub(value.len())];
+    padded.extend_from_slice(value);
+    padded
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02X}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "-----BEGIN PGP PUBLIC KEY BLOCK-----
+
+mDMEatJA/RYJKwYBBAHaRw8BAQdAY1Y0cWSDFih9RjUIaXJCtd7+wTnfTfhGoaQO
+e8gZ4Ty0HVJlbGVhc2UgPHJlbGVhc2VAZXhhbXBsZS5jb20+iJAEExYIADgWIQQq
+mvTnZUwclSsITkZxhcZfUHm/DgUCatJA/QIbAwULCQgHAgYVCgkICwIEFgIDAQIe
+AQIXgAAKCRBxhcZfUHm/DjfXAP9Fcl2bLFE63pBEQVVz+dL0AoN1Hpqfoklvnzu5
+Kj4CIwD9GrFXv8BD/zIHJG5fGJvprnQTWIA317EAAwknSJp6pgA=
+=LLd4
+-----END PGP PUBLIC KEY BLOCK-----
+";
+
+    const SIGNATURE: &str = "-----BEGIN PGP SIGNATURE-----
+
+iIoEABYIADIWIQQqmvTnZUwclSsITkZxhcZfUHm/DgUCatJA/RQccmVsZWFzZUBl
+eGFtcGxlLmNvbQAKCRBxhcZfUHm/DgPSAQCZHn/hgKX5nlsNACVYNenRSm0n1NIO
+gjZyZPcugOjxCwD/dwoF8AnBroghaQz5mJsauMPOhFpktbc+ckjTEONLZAY=
+=kwK+
+-----END PGP SIGNATURE-----
+";
+
+    /// Test that a detached Ed25519 signature is checked against the key block
+    #[test]
+    fn test_verify() {
+        let content = b"Hello, world.\n";
+        assert_eq!(
+            verify(content, SIGNATURE.as_bytes(), KEY.as_bytes()),
+            Ok(())
+        );
+        let binary = dearmor(SIGNATURE.as_bytes()).unwrap();
+        assert_eq!(verify(content, &binary, KEY.as_bytes()), Ok(()));
+        assert_eq!(
+            verify(b"Hello, world!\n", SIGNATURE.as_bytes(), KEY.as_bytes()),
+            Err("The OpenPGP signature does not match the content".to_string())
+        );
+
+        let mut other = dearmor(KEY.as_bytes()).unwrap().into_owned();
+        other[30] ^= 1;
+        assert_eq!(
+            verify(content, SIGNATURE.as_bytes(), &other),
+            Err(
+                "The content is signed by key 7185C65F5079BF0E, which is not in `pgp_key`"
+                    .to_string()
+            )
+        );
+        assert_eq!(canonical_text(b"a\nb\r\nc"), b"a\r\nb\r\nc");
+    }
+}
@@ -0,0 +1,93 @@
+//! Code generation for protobuf schemas included by `include_proto_url!`.
+//!
+//! The schema is parsed by the pure Rust parser of `protobuf-parse`, so no `protoc`
+//! binary is needed, and the resulting descriptors are handed to `prost-build`.
+
+use std::path::Path;
+
+use prost::Message as _;
+use protobuf::Message as _;
+
+/// Generates the prost message types for the cached schema at `schema`.
+///
+/// `file_name` is the name the schema is parsed under, which appears in error messages.
+/// Only the well-known types can be imported.
+pub(crate) fn generate(schema: &Path, file_name: &str) -> Result<String, String> {
+    let dir = schema.with_extension(format!("{}.proto", std::process::id()));
+    let result = generate_in(schema, file_name, &dir);
+    let _ = std::fs::remove_dir_all(&dir);
+    result
+}
+
+fn generate_in(schema: &Path, file_name: &str, dir: &Path) -> Result<String, String> {
+    let out_dir = dir.join("out");
+    std::fs::create_dir_all(&out_dir)
+        .map_err(|e| format!("Failed to create protobuf directory: {}", e))?;
+    let input = dir.join(file_name);
+    std::fs::copy(schema, &input).map_err(|e| format!("Failed to copy protobuf schema: {}", e))?;
+
+    let parsed = protobuf_parse::Parser::new()
+        .pure()
+        .include(dir)
+        .input(&input)
+        .parse_and_typecheck()
+        .map_err(|e| format!("Invalid protobuf schema from URL: {:#}", e))?;
+    let mut files = protobuf::descriptor::FileDescriptorSet::new();
+    files.file = parsed.file_descriptors;
+    let bytes = files
+        .write_to_bytes()
+        .map_err(|e| format!("Failed to encode protobuf descriptors: {}", e))?;
+    let files = prost_types::FileDescriptorSet::decode(bytes.as_slice())
+        .map_err(|e| format!("Failed to decode protobuf descriptors: {}", e))?;
+
+    prost_build::Config::new()
+        .out_dir(&out_dir)
+        .compile_fds(files)
+        .map_err(|e| format!("Failed to generate protobuf code: {}", e))?;
+
+    let mut outputs = std::fs::read_dir(&out_dir)
+        .map_err(|e| format!("Failed to read generated protobuf code: {}", e))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read generated protobuf code: {}", e))?;
+    outputs.sort();
+    let mut code = String::new();
+    for output in outputs {
+        code += &std::fs::read_to_string(output)
+            .map_err(|e| format!("Failed to read generated protobuf code: {}", e))?;
+    }
+    Ok(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that messages are generated and well-known types resolve to prost-types
+    #[test]
+    fn test_generate() {
+        let dir = std::env::temp_dir().join(format!("include_url_proto_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let schema = dir.join("schema");
+        std::fs::write(
+            &schema,
+            "syntax = \"proto3\";\n\
+             package demo.v1;\n\
+             import \"google/protobuf/timestamp.proto\";\n\
+             message Ping {\n  string id = 1;\n  google.protobuf.Timestamp sent_at = 2;\n}\n",
+        )
+        .unwrap();
+
+        let code = generate(&schema, "ping.proto").unwrap();
+        assert!(code.contains("pub struct Ping"));
+        assert!(code.contains("::prost_types::Timestamp"));
+
+        std::fs::write(
+            &schema,
+            "syntax = \"proto3\";\nmessage Ping { string id = }\n",
+        )
+        .unwrap();
+        assert!(generate(&schema, "ping.proto").is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
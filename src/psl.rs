@@ -0,0 +1,184 @@
+//! Compilation of the Public Suffix List included by `include_public_suffix_url!`.
+//!
+//! The rules are split into normal, wildcard and exception rules, each embedded as a sorted
+//! static slice that the generated `public_suffix` function binary searches, following the
+//! algorithm at <https://publicsuffix.org/list/>. Internationalized rules are embedded in
+//! both their Unicode and their Punycode form, so domains match in either.
+
+use std::collections::BTreeSet;
+
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// The rules of a Public Suffix List, without the leading `*.` of wildcard rules and the
+/// `!` of exception rules.
+#[derive(Debug, Default)]
+pub(crate) struct Rules {
+    suffixes: BTreeSet<String>,
+    wildcards: BTreeSet<String>,
+    exceptions: BTreeSet<String>,
+}
+
+/// Parses and checks a Public Suffix List, leaving out the private domains section unless
+/// `private` is set.
+pub(crate) fn parse(content: &str, private: bool) -> Result<Rules, String> {
+    let mut rules = Rules::default();
+    let mut icann = false;
+    let mut in_private = false;
+    for (index, line) in content.lines().enumerate() {
+        let error = |message: &str| {
+            format!(
+                "Invalid Public Suffix List from URL: line {}: {}",
+                index + 1,
+                message
+            )
+        };
+        let line = line.trim();
+        if let Some(comment) = line.strip_prefix("//") {
+            match comment.trim() {
+                "===BEGIN ICANN DOMAINS===" => icann = true,
+                "===BEGIN PRIVATE DOMAINS===" => in_private = true,
+                "===END PRIVATE DOMAINS===" => in_private = false,
+                _ => {}
+            }
+            continue;
+        }
+        // Only the first whitespace separated word of a line is the rule
+        let Some(rule) = line.split_whitespace().next() else {
+            continue;
+        };
+        if in_private && !private {
+            continue;
+        }
+        let (set, name) = match (rule.strip_prefix('!'), rule.strip_prefix("*.")) {
+            (Some(name), _) => (&mut rules.exceptions, name),
+            (None, Some(name)) => (&mut rules.wildcards, name),
+            (None, None) => (&mut rules.suffixes, rule),
+        };
+        if name
+            .split('.')
+            .any(|label| label.is_empty() || label.contains(['*', '!']))
+        {
+            return Err(error(&format!("invalid rule `{}`", rule)));
+        }
+        let name = name.to_lowercase();
+        let ascii = match url::Host::parse(&name) {
+            Ok(url::Host::Domain(ascii)) => ascii,
+            _ => return Err(error(&format!("invalid rule `{}`", rule))),
+        };
+        set.insert(ascii);
+        set.insert(name);
+    }
+    match icann && !rules.suffixes.is_empty() {
+        true => Ok(rules),
+        false => Err(
+            "Invalid Public Suffix List from URL: no `===BEGIN ICANN DOMAINS===` section with \
+             rules found"
+                .to_string(),
+        ),
+    }
+}
+
+/// Generates the rule tables and the `public_suffix` and `registrable_domain` functions.
+pub(crate) fn generate(rules: &Rules) -> TokenStream {
+    let Rules {
+        suffixes,
+        wildcards,
+        exceptions,
+    } = rules;
+    quote! {
+        /// The normal rules, sorted.
+        pub const SUFFIXES: &[&str] = &[#(#suffixes),*];
+        /// The wildcard rules without their leading `*.`, sorted.
+        pub const WILDCARDS: &[&str] = &[#(#wildcards),*];
+        /// The exception rules without their leading `!`, sorted.
+        pub const EXCEPTIONS: &[&str] = &[#(#exceptions),*];
+
+        /// Returns whether the sorted `rules` contain `name`, ignoring ASCII case.
+        fn contains(rules: &[&str], name: &str) -> bool {
+            rules
+                .binary_search_by(|rule| {
+                    rule.bytes().cmp(name.bytes().map(|b| b.to_ascii_lowercase()))
+                })
+                .is_ok()
+        }
+
+        /// Returns the public suffix of a domain, such as `co.uk` for `www.example.co.uk`.
+        ///
+        /// A trailing dot is ignored and ASCII letters match in any case. Domains without a
+        /// matching rule fall back to their last label. Returns `None` for an empty domain
+        /// or one with empty labels.
+        pub fn public_suffix(domain: &str) -> ::core::option::Option<&str> {
+            let domain = domain.strip_suffix('.').unwrap_or(domain);
+            if domain.split('.').any(str::is_empty) {
+                return ::core::option::Option::None;
+            }
+            let mut candidate = domain;
+            loop {
+                let parent = candidate.split_once('.').map(|(_, parent)| parent);
+                if contains(EXCEPTIONS, candidate) {
+                    return parent;
+                }
+                if contains(SUFFIXES, candidate)
+                    || parent.is_some_and(|parent| contains(WILDCARDS, parent))
+                {
+                    return ::core::option::Option::Some(candidate);
+                }
+                match parent {
+                    ::core::option::Option::Some(parent) => candidate = parent,
+                    ::core::option::Option::None => {
+                        return ::core::option::Option::Some(candidate)
+                    }
+                }
+            }
+        }
+
+        /// Returns the registrable domain of a domain, its public suffix and one more label,
+        /// such as `example.co.uk` for `www.example.co.uk`.
+        ///
+        /// Returns `None` if the domain is a public suffix itself.
+        pub fn registrable_domain(domain: &str) -> ::core::option::Option<&str> {
+            let domain = domain.strip_suffix('.').unwrap_or(domain);
+            let suffix = public_suffix(domain)?;
+            let prefix = domain[..domain.len() - suffix.len()].strip_suffix('.')?;
+            let start = prefix.rfind('.').map_or(0, |dot| dot + 1);
+            ::core::option::Option::Some(&domain[start..])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIST: &str = "// ===BEGIN ICANN DOMAINS===\n\
+                        com\n\
+                        co.uk\n\
+                        uk\n\
+                        *.ck\n\
+                        !www.ck\n\
+                        中国\n\
+                        // ===END ICANN DOMAINS===\n\
+                        // ===BEGIN PRIVATE DOMAINS===\n\
+                        github.io\n\
+                        // ===END PRIVATE DOMAINS===\n";
+
+    /// Test that rules are sorted into their kind, with Punycode forms added
+    #[test]
+    fn test_parse() {
+        let rules = parse(LIST, true).unwrap();
+        assert!(rules.suffixes.contains("co.uk"));
+        assert!(rules.suffixes.contains("xn--fiqs8s"));
+        assert!(rules.suffixes.contains("github.io"));
+        assert!(rules.wildcards.contains("ck"));
+        assert!(rules.exceptions.contains("www.ck"));
+        assert!(!parse(LIST, false).unwrap().suffixes.contains("github.io"));
+
+        let error = parse("// ===BEGIN ICANN DOMAINS===\ncom\nfoo..bar\n", true).unwrap_err();
+        assert!(error.contains("line 3"), "{}", error);
+        assert!(parse("<html><body>Not found</body></html>", true).is_err());
+
+        let code = generate(&rules).to_string();
+        assert!(code.contains("pub const EXCEPTIONS : & [& str] = & [\"www.ck\"]"));
+    }
+}
@@ -0,0 +1,322 @@
+//! Pixel data generation for the images included by `include_image_url!`.
+//!
+//! PNG, JPEG and GIF images are decoded with the `image` crate during expansion, and their
+//! pixels are embedded in a raw layout, so the program needs no image decoder. Only the
+//! first frame of an animated GIF is kept. Images can also be downscaled and re-encoded,
+//! to embed them at the size they are displayed at.
+
+use image::DynamicImage;
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// A layout of the embedded pixel data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PixelFormat {
+    /// Red, green, blue and alpha bytes.
+    Rgba8,
+    /// Red, green and blue bytes.
+    Rgb8,
+    /// One luminance byte.
+    Luma8,
+    /// A luminance and an alpha byte.
+    LumaA8,
+    /// 5 bits of red, 6 of green and 5 of blue in a big endian `u16`, the default byte
+    /// order of `embedded-graphics` raw images.
+    Rgb565,
+    /// The same in a little endian `u16`.
+    Rgb565Le,
+    /// One bit per pixel, most significant bit first, set for pixels with a luminance of at
+    /// least half. Every row starts on a new byte.
+    Mono1,
+}
+
+impl PixelFormat {
+    const ALL: [PixelFormat; 7] = [
+        PixelFormat::Rgba8,
+        PixelFormat::Rgb8,
+        PixelFormat::Luma8,
+        PixelFormat::LumaA8,
+        PixelFormat::Rgb565,
+        PixelFormat::Rgb565Le,
+        PixelFormat::Mono1,
+    ];
+
+    /// Parses the name of a pixel format, such as `rgba8`.
+    pub(crate) fn parse(name: &str) -> Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|format| format.name() == name)
+            .ok_or_else(|| {
+                let names: Vec<String> = Self::ALL
+                    .iter()
+                    .map(|format| format!("`{}`", format.name()))
+                    .collect();
+                format!(
+                    "Unsupported pixel format `{}`, expected one of {}",
+                    name,
+                    names.join(", ")
+                )
+            })
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            PixelFormat::Rgba8 => "rgba8",
+            PixelFormat::Rgb8 => "rgb8",
+            PixelFormat::Luma8 => "luma8",
+            PixelFormat::LumaA8 => "lumaa8",
+            PixelFormat::Rgb565 => "rgb565",
+            PixelFormat::Rgb565Le => "rgb565le",
+            PixelFormat::Mono1 => "mono1",
+        }
+    }
+
+    fn bits_per_pixel(self) -> usize {
+        match self {
+            PixelFormat::Rgba8 => 32,
+            PixelFormat::Rgb8 => 24,
+            PixelFormat::Luma8 => 8,
+            PixelFormat::LumaA8 | PixelFormat::Rgb565 | PixelFormat::Rgb565Le => 16,
+            PixelFormat::Mono1 => 1,
+        }
+    }
+
+    /// Returns the number of bytes of every row of an image `width` pixels wide.
+    fn stride(self, width: u32) -> usize {
+        (width as usize * self.bits_per_pixel()).div_ceil(8)
+    }
+
+    /// Converts the pixels of an image into this layout.
+    fn convert(self, image: &DynamicImage) -> Vec<u8> {
+        let rgb565 = |to_bytes: fn(u16) -> [u8; 2]| {
+            image
+                .to_rgb8()
+                .pixels()
+                .flat_map(|pixel| {
+                    let [r, g, b] = pixel.0.map(u16::from);
+                    to_bytes((r >> 3) << 11 | (g >> 2) << 5 | b >> 3)
+                })
+                .collect()
+        };
+        match self {
+            PixelFormat::Rgba8 => image.to_rgba8().into_raw(),
+            PixelFormat::Rgb8 => image.to_rgb8().into_raw(),
+            PixelFormat::Luma8 => image.to_luma8().into_raw(),
+            PixelFormat::LumaA8 => image.to_luma_alpha8().into_raw(),
+            PixelFormat::Rgb565 => rgb565(u16::to_be_bytes),
+            PixelFormat::Rgb565Le => rgb565(u16::to_le_bytes),
+            PixelFormat::Mono1 => image
+                .to_luma8()
+                .rows()
+                .flat_map(|row| {
+                    let row: Vec<u8> = row.map(|pixel| pixel.0[0]).collect();
+                    row.chunks(8)
+                        .map(|pixels| {
+                            pixels.iter().enumerate().fold(0, |byte, (index, luma)| {
+                                byte | (u8::from(*luma >= 128) << (7 - index))
+                            })
+                        })
+                        .collect::<Vec<u8>>()
+                })
+                .collect(),
+        }
+    }
+}
+
+/// A file format images are re-encoded in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FileFormat {
+    Png,
+    /// JPEG with a quality from 1 to 100.
+    Jpeg(u8),
+    Gif,
+}
+
+impl FileFormat {
+    /// The quality of JPEG images when none is given, the default of the `image` encoder.
+    pub(crate) const DEFAULT_QUALITY: u8 = 75;
+
+    /// Parses the name of a file format, `png`, `jpeg` or `gif`.
+    pub(crate) fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "png" => Ok(FileFormat::Png),
+            "jpeg" | "jpg" => Ok(FileFormat::Jpeg(Self::DEFAULT_QUALITY)),
+            "gif" => Ok(FileFormat::Gif),
+            _ => Err(format!(
+                "Unsupported image format `{}`, expected one of `png`, `jpeg`, `gif`",
+                name
+            )),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            FileFormat::Png => "png",
+            FileFormat::Jpeg(_) => "jpeg",
+            FileFormat::Gif => "gif",
+        }
+    }
+
+    /// Encodes an image in this format, dropping the alpha channel for JPEG.
+    fn encode(self, image: &DynamicImage) -> Result<Vec<u8>, String> {
+        let mut encoded = std::io::Cursor::new(Vec::new());
+        match self {
+            FileFormat::Png => image.write_to(&mut encoded, image::ImageFormat::Png),
+            FileFormat::Jpeg(quality) => image.write_with_encoder(
+                image::codecs::jpeg::JpegEncoder::new_with_quality(&mut encoded, quality),
+            ),
+            FileFormat::Gif => image.write_to(&mut encoded, image::ImageFormat::Gif),
+        }
+        .map_err(|e| format!("Failed to encode the image as {}: {}", self.name(), e))?;
+        Ok(encoded.into_inner())
+    }
+}
+
+/// What the module generated for an image embeds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Output {
+    /// The raw pixels in a layout.
+    Pixels(PixelFormat),
+    /// The image re-encoded in a file format.
+    Encoded(FileFormat),
+}
+
+/// Decodes a PNG, JPEG or GIF image, detecting the format from its content.
+pub(crate) fn decode(content: &[u8]) -> Result<DynamicImage, String> {
+    image::load_from_memory(content).map_err(|e| format!("Invalid image from URL: {}", e))
+}
+
+/// Downscales an image to fit within `width` by `height` pixels, keeping its aspect ratio.
+///
+/// Images that already fit are left as they are rather than enlarged.
+pub(crate) fn fit(image: DynamicImage, width: u32, height: u32) -> DynamicImage {
+    match image.width() <= width && image.height() <= height {
+        true => image,
+        false => image.resize(width, height, image::imageops::FilterType::Lanczos3),
+    }
+}
+
+/// Generates the `WIDTH`, `HEIGHT` and `FORMAT` constants of an image, with its `PIXELS` and
+/// `BYTES_PER_PIXEL` in a layout or its encoded `BYTES`.
+pub(crate) fn generate(image: &DynamicImage, output: Output) -> Result<TokenStream, String> {
+    let width = image.width();
+    let height = image.height();
+    let data = match output {
+        Output::Pixels(format) => {
+            let name = format.name();
+            let bits_per_pixel = format.bits_per_pixel();
+            let bytes_per_pixel = (bits_per_pixel % 8 == 0).then(|| {
+                let bytes_per_pixel = bits_per_pixel / 8;
+                quote! {
+                    /// The number of bytes of every pixel.
+                    pub const BYTES_PER_PIXEL: usize = #bytes_per_pixel;
+                }
+            });
+            let stride = format.stride(width);
+            let pixels = proc_macro2::Literal::byte_string(&format.convert(image));
+            quote! {
+                /// The layout of `PIXELS`, such as `rgba8`.
+                pub const FORMAT: &str = #name;
+                /// The number of bits of every pixel.
+                pub const BITS_PER_PIXEL: usize = #bits_per_pixel;
+                #bytes_per_pixel
+                /// The number of bytes of every row of `PIXELS`.
+                pub const STRIDE: usize = #stride;
+
+                /// Aligns the pixels for direct memory access.
+                #[repr(C, align(4))]
+                struct Aligned<T: ?Sized>(T);
+
+                /// The pixels, row by row from the top left corner, starting at an address
+                /// aligned to 4 bytes.
+                pub const PIXELS: &[u8] = &Aligned(*#pixels).0;
+            }
+        }
+        Output::Encoded(format) => {
+            let name = format.name();
+            let bytes = proc_macro2::Literal::byte_string(&format.encode(image)?);
+            quote! {
+                /// The file format of `BYTES`, such as `png`.
+                pub const FORMAT: &str = #name;
+                /// The encoded image.
+                pub const BYTES: &[u8] = #bytes;
+            }
+        }
+    };
+    Ok(quote! {
+        /// The width of the image in pixels.
+        pub const WIDTH: u32 = #width;
+        /// The height of the image in pixels.
+        pub const HEIGHT: u32 = #height;
+        #data
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that decoded pixels are converted into every layout
+    #[test]
+    fn test_generate() {
+        let mut png = Vec::new();
+        image::RgbaImage::from_raw(2, 1, vec![255, 0, 0, 255, 0, 0, 255, 128])
+            .unwrap()
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let image = decode(&png).unwrap();
+        assert_eq!(PixelFormat::Rgb8.convert(&image), [255, 0, 0, 0, 0, 255]);
+        assert_eq!(PixelFormat::LumaA8.convert(&image).len(), 4);
+        // Red is 0xF800 and blue 0x001F
+        assert_eq!(PixelFormat::Rgb565.convert(&image), [0xF8, 0, 0, 0x1F]);
+        assert_eq!(PixelFormat::Rgb565Le.convert(&image), [0, 0xF8, 0x1F, 0]);
+
+        let code = generate(&image, Output::Pixels(PixelFormat::parse("rgba8").unwrap()))
+            .unwrap()
+            .to_string();
+        assert!(code.contains("pub const WIDTH : u32 = 2u32"));
+        let pixels = proc_macro2::Literal::byte_string(&[255, 0, 0, 255, 0, 0, 255, 128]);
+        assert!(code.contains(&format!(
+            "pub const PIXELS : & [u8] = & Aligned (* {}) . 0",
+            pixels
+        )));
+        assert!(PixelFormat::parse("bgr8").is_err());
+        assert!(decode(b"<html></html>").is_err());
+    }
+
+    /// Test that monochrome rows are packed most significant bit first and padded to bytes
+    #[test]
+    fn test_mono1() {
+        let luma = [255, 0, 0, 255, 255, 0, 0, 0, 255, 0];
+        let image = DynamicImage::ImageLuma8(
+            image::GrayImage::from_raw(10, 2, [luma, luma].concat()).unwrap(),
+        );
+        assert_eq!(PixelFormat::Mono1.stride(10), 2);
+        assert_eq!(
+            PixelFormat::Mono1.convert(&image),
+            [0b1001_1000, 0b1000_0000, 0b1001_1000, 0b1000_0000]
+        );
+        let code = generate(&image, Output::Pixels(PixelFormat::Mono1))
+            .unwrap()
+            .to_string();
+        assert!(code.contains("pub const STRIDE : usize = 2usize"));
+        assert!(!code.contains("BYTES_PER_PIXEL"));
+    }
+
+    /// Test that images are only downscaled and survive re-encoding
+    #[test]
+    fn test_fit_and_encode() {
+        let image = DynamicImage::new_rgba8(64, 32);
+        let small = fit(image.clone(), 16, 16);
+        assert_eq!((small.width(), small.height()), (16, 8));
+        let same = fit(image, 128, 128);
+        assert_eq!((same.width(), same.height()), (64, 32));
+
+        for format in ["png", "jpeg", "gif"] {
+            let encoded = FileFormat::parse(format).unwrap().encode(&small).unwrap();
+            let decoded = decode(&encoded).unwrap();
+            assert_eq!((decoded.width(), decoded.height()), (16, 8));
+        }
+        assert!(FileFormat::parse("webp").is_err());
+    }
+}
@@ -0,0 +1,116 @@
+//! Per-host rate limiting shared by every macro expanded by the compiler process.
+//!
+//! Requests to a host are spaced by the interval of the `rate_limit` option, and a host
+//! that asked to slow down, with `Retry-After` or the `X-RateLimit-Remaining` and
+//! `X-RateLimit-Reset` headers of GitHub and many other APIs, is paused for every macro
+//! rather than only for the request that was limited.
+
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use reqwest::header::HeaderMap;
+
+/// The earliest instant the next request to each host may be sent.
+fn schedule() -> std::sync::MutexGuard<'static, HashMap<String, Instant>> {
+    static SCHEDULE: OnceLock<Mutex<HashMap<String, Instant>>> = OnceLock::new();
+    SCHEDULE
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Waits until a request to `host` may be sent, reserving the slot of the request when at
+/// most `per_second` requests are allowed every second.
+pub(crate) fn wait(host: &str, per_second: Option<u32>) {
+    let now = Instant::now();
+    let start = {
+        let mut schedule = schedule();
+        let start = schedule.get(host).map_or(now, |next| (*next).max(now));
+        if let Some(per_second) = per_second.filter(|per_second| *per_second > 0) {
+            schedule.insert(
+                host.to_string(),
+                start + Duration::from_secs(1) / per_second,
+            );
+        }
+        start
+    };
+    std::thread::sleep(start - now);
+}
+
+/// Holds back the requests to `host` for `delay`.
+pub(crate) fn pause(host: &str, delay: Duration) {
+    let until = Instant::now() + delay;
+    let mut schedule = schedule();
+    let next = schedule.entry(host.to_string()).or_insert(until);
+    *next = (*next).max(until);
+}
+
+/// Whether the response says that no request is left until the limit resets.
+pub(crate) fn exhausted(headers: &HeaderMap) -> bool {
+    header(headers, "x-ratelimit-remaining") == Some(0)
+}
+
+/// Returns the delay until the limit resets, from `X-RateLimit-Reset` given either as a
+/// Unix timestamp, as GitHub does, or as a number of seconds.
+pub(crate) fn reset_delay(headers: &HeaderMap) -> Option<Duration> {
+    let reset = header(headers, "x-ratelimit-reset")?;
+    // Timestamps are far larger than any sensible delay
+    if reset < 1_000_000_000 {
+        return Some(Duration::from_secs(reset));
+    }
+    let reset = UNIX_EPOCH + Duration::from_secs(reset);
+    Some(reset.duration_since(SystemTime::now()).unwrap_or_default())
+}
+
+/// Reads a header holding a number.
+fn header(headers: &HeaderMap, name: &str) -> Option<u64> {
+    headers.get(name)?.to_str().ok()?.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that the rate limit headers are read
+    #[test]
+    fn test_rate_limit_headers() {
+        let mut headers = HeaderMap::new();
+        assert!(!exhausted(&headers));
+        assert_eq!(reset_delay(&headers), None);
+
+        headers.insert("x-ratelimit-remaining", "0".parse().unwrap());
+        headers.insert("x-ratelimit-reset", "30".parse().unwrap());
+        assert!(exhausted(&headers));
+        assert_eq!(reset_delay(&headers), Some(Duration::from_secs(30)));
+
+        let reset = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            + 120;
+        headers.insert("x-ratelimit-reset", reset.to_string().parse().unwrap());
+        let delay = reset_delay(&headers).unwrap();
+        assert!(delay > Duration::from_secs(110) && delay <= Duration::from_secs(120));
+        headers.insert("x-ratelimit-reset", "1000000000".parse().unwrap());
+        assert_eq!(reset_delay(&headers), Some(Duration::ZERO));
+    }
+
+    /// Test that requests to a host are spaced and paused
+    #[test]
+    fn test_wait() {
+        let start = Instant::now();
+        wait("spaced.test", Some(20));
+        wait("spaced.test", Some(20));
+        wait("spaced.test", Some(20));
+        assert!(start.elapsed() >= Duration::from_millis(100));
+
+        pause("paused.test", Duration::from_millis(50));
+        let start = Instant::now();
+        wait("paused.test", None);
+        wait("other.test", None);
+        assert!(start.elapsed() >= Duration::from_millis(40));
+    }
+}
@@ -0,0 +1,293 @@
+//! Records of the remote content included by each compilation.
+//!
+//! Every macro expansion appends a line to the report of the target being compiled, while
+//! it is compiled, so the report never describes an earlier compilation or another target.
+//! Expired content used in place of a fresh copy is always reported as a warning, and every
+//! inclusion is when `INCLUDE_URL_REPORT` is set. `report_url_inclusions!`, expanded last,
+//! sums up the report of the compilation.
+
+use std::{
+    env,
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+    process,
+    time::Duration,
+};
+
+use sha2::{Digest, Sha256};
+
+/// Environment variable listing every inclusion as a warning.
+pub(crate) const REPORT_VAR: &str = "INCLUDE_URL_REPORT";
+
+/// How the content of an inclusion was obtained.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CacheStatus {
+    /// Served from the cache without touching the network.
+    Hit,
+    /// Downloaded during this compilation.
+    Miss,
+    /// Served from the cache after the server confirmed it is current.
+    Revalidated,
+    /// Served from the cache although it expired, as it could not be revalidated.
+    Stale,
+}
+
+impl CacheStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            CacheStatus::Hit => "hit",
+            CacheStatus::Miss => "miss",
+            CacheStatus::Revalidated => "revalidated",
+            CacheStatus::Stale => "stale",
+        }
+    }
+}
+
+/// Returns the report file of the target being compiled.
+///
+/// The library, binaries and tests of a package share its `OUT_DIR`, so each kind of target
+/// gets a file of its own, named after the crate. Packages without a build script have no
+/// `OUT_DIR`, their reports are kept in the cache directory.
+pub(crate) fn report_path() -> PathBuf {
+    let args: Vec<String> = env::args().collect();
+    let kind = if crate::cfg_in_args("test", &args) {
+        "test"
+    } else if env::var_os("CARGO_BIN_NAME").is_some() {
+        "bin"
+    } else {
+        "lib"
+    };
+    let crate_name = env::var("CARGO_CRATE_NAME").unwrap_or_else(|_| "unknown".into());
+    let dir = match env::var_os("OUT_DIR") {
+        Some(out_dir) => PathBuf::from(out_dir).join("include_url_report"),
+        None => {
+            let package = env::var("CARGO_PKG_NAME").unwrap_or_else(|_| "unknown".into());
+            let hash = Sha256::digest(package.as_bytes());
+            Path::new(env!("INCLUDE_URL_CACHE_DIR"))
+                .join("reports")
+                .join(format!("{:x}", hash))
+        }
+    };
+    dir.join(format!("{}-{}.tsv", crate_name, kind))
+}
+
+/// Returns whether every inclusion is listed as a warning.
+fn report_requested() -> bool {
+    env::var(REPORT_VAR).is_ok_and(|value| !matches!(value.trim(), "" | "0" | "false"))
+}
+
+/// Records an inclusion for the target being compiled, and reports it in `warnings` if it
+/// is stale or was asked for.
+///
+/// Each line is `<pid>\t<status>\t<bytes>\t<milliseconds>\t<url>`, where the time is
+/// spent looking up the cache and fetching the content. A report written by another
+/// compiler process is replaced, so the file always describes the current compilation.
+/// Failures to write it are ignored since the report is informational only.
+pub(crate) fn record(
+    url: &str,
+    bytes: u64,
+    status: CacheStatus,
+    elapsed: Duration,
+    warnings: &mut Vec<String>,
+) {
+    if status == CacheStatus::Stale {
+        warnings.push(format!(
+            "The expired content of `{}` is used, as it could not be revalidated",
+            url
+        ));
+    } else if report_requested() {
+        warnings.push(format!(
+            "include_url: {} {} bytes in {:.2}s {}",
+            status.as_str(),
+            bytes,
+            elapsed.as_secs_f64(),
+            url
+        ));
+    }
+
+    let path = report_path();
+    let pid = process::id().to_string();
+    let same_session = std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|content| {
+            content
+                .lines()
+                .next()
+                .map(|line| line.starts_with(&format!("{}\t", pid)))
+        })
+        .unwrap_or(false);
+    if path
+        .parent()
+        .is_some_and(|dir| std::fs::create_dir_all(dir).is_err())
+    {
+        return;
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(same_session)
+        .truncate(!same_session)
+        .open(&path);
+    if let Ok(mut file) = file {
+        let _ = writeln!(
+            file,
+            "{}\t{}\t{}\t{}\t{}",
+            pid,
+            status.as_str(),
+            bytes,
+            elapsed.as_millis(),
+            url
+        );
+    }
+}
+
+/// Totals of the inclusions recorded by a compilation.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct Summary {
+    /// The inclusions as `(milliseconds, status, bytes, url)`, the slowest first.
+    pub entries: Vec<(u64, String, u64, String)>,
+    /// Included bytes.
+    pub bytes: u64,
+    /// Inclusions served from the cache, revalidated or not.
+    pub hits: usize,
+    /// Time spent looking up the cache and fetching, in milliseconds.
+    pub millis: u64,
+}
+
+impl Summary {
+    /// Sums up the lines of `report` written by the compiler process `pid`, skipping the
+    /// ones of an earlier compilation and malformed lines.
+    pub(crate) fn parse(report: &str, pid: u32) -> Summary {
+        let pid = pid.to_string();
+        let mut summary = Summary::default();
+        for line in report.lines() {
+            let mut fields = line.splitn(5, '\t');
+            if fields.next() != Some(pid.as_str()) {
+                continue;
+            }
+            let (Some(status), Some(Ok(bytes)), Some(Ok(millis)), Some(url)) = (
+                fields.next(),
+                fields.next().map(str::parse::<u64>),
+                fields.next().map(str::parse::<u64>),
+                fields.next(),
+            ) else {
+                continue;
+            };
+            summary.bytes += bytes;
+            summary.millis += millis;
+            if let "hit" | "revalidated" = status {
+                summary.hits += 1;
+            }
+            summary
+                .entries
+                .push((millis, status.to_string(), bytes, url.to_string()));
+        }
+        summary
+            .entries
+            .sort_by_key(|entry| std::cmp::Reverse(entry.0));
+        summary
+    }
+
+    /// Returns the lines of the summary: the totals, followed by every inclusion with
+    /// `details`, or otherwise by the stale ones.
+    pub(crate) fn lines(&self, details: bool) -> Vec<String> {
+        let mut lines = vec![format!(
+            "include_url: {} URLs, {} bytes, {}/{} cache hits, {:.2}s fetching",
+            self.entries.len(),
+            self.bytes,
+            self.hits,
+            self.entries.len(),
+            self.millis as f64 / 1000.0
+        )];
+        for (millis, status, bytes, url) in &self.entries {
+            if details {
+                lines.push(format!(
+                    "include_url: {} {} bytes in {:.2}s {}",
+                    status,
+                    bytes,
+                    *millis as f64 / 1000.0,
+                    url
+                ));
+            } else if status == CacheStatus::Stale.as_str() {
+                lines.push(format!("include_url: stale entry used for {}", url));
+            }
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that records of the same process are appended and stale entries are reported
+    #[test]
+    fn test_record() {
+        let mut warnings = Vec::new();
+        record(
+            "https://example.com/a",
+            10,
+            CacheStatus::Hit,
+            Duration::ZERO,
+            &mut warnings,
+        );
+        record(
+            "https://example.com/b",
+            20,
+            CacheStatus::Stale,
+            Duration::from_millis(1500),
+            &mut warnings,
+        );
+
+        let content = std::fs::read_to_string(report_path()).unwrap();
+        let pid = process::id();
+        assert!(content.contains(&format!("{}\thit\t10\t0\thttps://example.com/a\n", pid)));
+        assert!(content.contains(&format!(
+            "{}\tstale\t20\t1500\thttps://example.com/b\n",
+            pid
+        )));
+        assert_eq!(
+            warnings,
+            [
+                "The expired content of `https://example.com/b` is used, as it could not be \
+              revalidated"
+            ]
+        );
+    }
+
+    /// Test that the summary adds up the inclusions of the current compilation only
+    #[test]
+    fn test_summary() {
+        let report = "41\tmiss\t100\t900\thttps://example.com/old\n\
+                      42\thit\t1000\t1\thttps://example.com/a\n\
+                      42\tmiss\t2500\t420\thttps://example.com/b\n\
+                      42\trevalidated\t500\t80\thttps://example.com/c\n\
+                      42\tstale\t24\t3000\thttps://example.com/d\n\
+                      42\tmiss\tmany\t0\thttps://example.com/e\n";
+        let summary = Summary::parse(report, 42);
+        assert_eq!(summary.entries.len(), 4);
+        assert_eq!(summary.bytes, 4024);
+        assert_eq!(summary.hits, 2);
+        assert_eq!(summary.millis, 3501);
+        assert_eq!(
+            summary.lines(false),
+            [
+                "include_url: 4 URLs, 4024 bytes, 2/4 cache hits, 3.50s fetching",
+                "include_url: stale entry used for https://example.com/d",
+            ]
+        );
+        let details = summary.lines(true);
+        assert_eq!(details.len(), 5);
+        assert_eq!(
+            details[1],
+            "include_url: stale 24 bytes in 3.00s https://example.com/d"
+        );
+        assert_eq!(
+            details[4],
+            "include_url: hit 1000 bytes in 0.00s https://example.com/a"
+        );
+
+        assert_eq!(Summary::parse("", 42), Summary::default());
+    }
+}
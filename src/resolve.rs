@@ -0,0 +1,194 @@
+//! Host name resolution controls of the HTTP client.
+//!
+//! By default the client resolves hosts with the system resolver and connects to IPv6 and
+//! IPv4 addresses alike. The resolver of this module restricts the addresses to one IP
+//! version, for networks where the other one is broken, and can send the queries to a DNS
+//! over HTTPS server instead, using the JSON API offered by public resolvers such as
+//! `https://cloudflare-dns.com/dns-query` and `https://dns.google/resolve`.
+
+use std::{
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
+
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use serde::Deserialize;
+
+/// DNS record type of IPv4 addresses.
+const TYPE_A: u16 = 1;
+/// DNS record type of IPv6 addresses.
+const TYPE_AAAA: u16 = 28;
+
+/// Resolves hosts with the system resolver or a DNS over HTTPS server, keeping the
+/// addresses of the requested IP version.
+#[derive(Debug, Clone)]
+pub(crate) struct Resolver {
+    /// `4` or `6` to only connect over one IP version.
+    pub ip_version: Option<u8>,
+    /// URL of a DNS over HTTPS server with a JSON API.
+    pub doh: Option<String>,
+    /// Limit for the queries sent to the DNS over HTTPS server.
+    pub timeout: Duration,
+}
+
+impl Resolver {
+    /// Whether an address is of the requested IP version.
+    fn accepts(&self, ip: &IpAddr) -> bool {
+        match self.ip_version {
+            Some(4) => ip.is_ipv4(),
+            Some(6) => ip.is_ipv6(),
+            _ => true,
+        }
+    }
+
+    /// The record types to query over HTTPS.
+    fn record_types(&self) -> &'static [u16] {
+        match self.ip_version {
+            Some(4) => &[TYPE_A],
+            Some(6) => &[TYPE_AAAA],
+            _ => &[TYPE_A, TYPE_AAAA],
+        }
+    }
+}
+
+impl Resolve for Resolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let resolver = self.clone();
+        Box::pin(async move {
+            let host = name.as_str();
+            let ips = match &resolver.doh {
+                Some(server) => {
+                    let record_types = resolver.record_types();
+                    query_doh(server, host, record_types, resolver.timeout).await?
+                }
+                None => tokio::net::lookup_host((host, 0))
+                    .await?
+                    .map(|address| address.ip())
+                    .collect(),
+            };
+            let addresses: Vec<SocketAddr> = ips
+                .into_iter()
+                .filter(|ip| resolver.accepts(ip))
+                .map(|ip| SocketAddr::new(ip, 0))
+                .collect();
+            if addresses.is_empty() {
+                let version = resolver
+                    .ip_version
+                    .map(|version| format!(" IPv{}", version));
+                return Err(format!(
+                    "No{} address found for `{}`",
+                    version.unwrap_or_default(),
+                    host
+                )
+                .into());
+            }
+            Ok(Box::new(addresses.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// A response of the JSON API of a DNS over HTTPS server.
+#[derive(Deserialize)]
+struct DohResponse {
+    #[serde(rename = "Status")]
+    status: u16,
+    #[serde(rename = "Answer", default)]
+    answer: Vec<DohAnswer>,
+}
+
+#[derive(Deserialize)]
+struct DohAnswer {
+    #[serde(rename = "type")]
+    record_type: u16,
+    data: String,
+}
+
+/// Queries the addresses of `host` from a DNS over HTTPS server.
+async fn query_doh(
+    server: &str,
+    host: &str,
+    record_types: &[u16],
+    timeout: Duration,
+) -> Result<Vec<IpAddr>, Box<dyn std::error::Error + Send + Sync>> {
+    let client = reqwest::Client::builder().timeout(timeout).build()?;
+    let mut ips = Vec::new();
+    for record_type in record_types {
+        let response = client
+            .get(server)
+            .query(&[("name", host), ("type", &record_type.to_string())])
+            .header("Accept", "application/dns-json")
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        ips.extend(answer_ips(&response, *record_type)?);
+    }
+    Ok(ips)
+}
+
+/// Reads the addresses of the given record type from a DNS over HTTPS response, skipping
+/// the aliases leading to them.
+fn answer_ips(response: &[u8], record_type: u16) -> Result<Vec<IpAddr>, String> {
+    let response: DohResponse = serde_json::from_slice(response)
+        .map_err(|e| format!("Invalid DNS over HTTPS response: {}", e))?;
+    // NXDOMAIN is an empty answer, other errors are reported
+    if response.status != 0 && response.status != 3 {
+        return Err(format!(
+            "The DNS over HTTPS server failed with status {}",
+            response.status
+        ));
+    }
+    response
+        .answer
+        .iter()
+        .filter(|answer| answer.record_type == record_type)
+        .map(|answer| {
+            answer
+                .data
+                .parse()
+                .map_err(|_| format!("Invalid address `{}` in DNS answer", answer.data))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that addresses are read from DNS over HTTPS answers
+    #[test]
+    fn test_answer_ips() {
+        let response = br#"{"Status": 0, "Answer": [
+            {"name": "www.example.com", "type": 5, "TTL": 60, "data": "example.com."},
+            {"name": "example.com", "type": 1, "TTL": 60, "data": "93.184.215.14"}
+        ]}"#;
+        assert_eq!(
+            answer_ips(response, TYPE_A),
+            Ok(vec!["93.184.215.14".parse().unwrap()])
+        );
+        assert_eq!(answer_ips(br#"{"Status": 3}"#, TYPE_AAAA), Ok(vec![]));
+        assert!(answer_ips(br#"{"Status": 2}"#, TYPE_A).is_err());
+        assert!(answer_ips(b"<html></html>", TYPE_A).is_err());
+    }
+
+    /// Test that addresses are filtered by IP version
+    #[test]
+    fn test_ip_version() {
+        let v4: IpAddr = "127.0.0.1".parse().unwrap();
+        let v6: IpAddr = "::1".parse().unwrap();
+        let resolver = Resolver {
+            ip_version: Some(4),
+            doh: None,
+            timeout: Duration::from_secs(30),
+        };
+        assert!(resolver.accepts(&v4) && !resolver.accepts(&v6));
+        assert_eq!(resolver.record_types(), [TYPE_A]);
+        let resolver = Resolver {
+            ip_version: None,
+            doh: None,
+            timeout: Duration::from_secs(30),
+        };
+        assert!(resolver.accepts(&v4) && resolver.accepts(&v6));
+    }
+}
@@ -0,0 +1,273 @@
+//! Revalidation of cached content.
+//!
+//! The `ETag` and `Last-Modified` headers of a response are kept in a metadata file next to
+//! its cache file, along with the time the content was fetched and the time it expires, as
+//! told by `Cache-Control: max-age` or `Expires`. An entry that expired or is older than its
+//! `ttl`, which takes precedence, or every entry when `INCLUDE_URL_REFRESH` is set, is
+//! revalidated with a conditional request, and a server answering `304 Not Modified` keeps
+//! the cached content without sending it again. Entries without validators are downloaded
+//! again, and entries without an expiration time are kept until their `ttl`, if any.
+
+use std::{
+    env,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use reqwest::{
+    blocking::RequestBuilder,
+    header::{
+        HeaderMap, AGE, CACHE_CONTROL, ETAG, EXPIRES, IF_MODIFIED_SINCE, IF_NONE_MATCH,
+        LAST_MODIFIED,
+    },
+};
+use serde::{Deserialize, Serialize};
+
+/// Environment variable requesting the revalidation of every cached entry.
+pub(crate) const REFRESH_VAR: &str = "INCLUDE_URL_REFRESH";
+
+/// What is known about the response a cache file was written from.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Metadata {
+    /// Entity tag of the response, sent in `If-None-Match`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    /// Date the content was last modified, sent in `If-Modified-Since`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+    /// When the content was fetched or last revalidated, in seconds since the Unix epoch.
+    pub fetched: u64,
+    /// When the content expires, in seconds since the Unix epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires: Option<u64>,
+    /// Lowercase hex SHA-256 digest of the content, as checked by the `sha256` option.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// Size of the content in bytes, as hashed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub length: Option<u64>,
+}
+
+impl Metadata {
+    /// Reads the validators of a response fetched now.
+    pub(crate) fn from_headers(headers: &HeaderMap) -> Self {
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let fetched = now();
+        Metadata {
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+            fetched,
+            expires: freshness_lifetime(headers).map(|lifetime| fetched + lifetime),
+            ..Metadata::default()
+        }
+    }
+
+    /// Marks the content as current after a `304 Not Modified` response, which may carry
+    /// updated validators and expiration. Without the latter, the content stays fresh as
+    /// long as it was before.
+    pub(crate) fn revalidated(self, headers: &HeaderMap) -> Self {
+        let updated = Metadata::from_headers(headers);
+        let lifetime = self
+            .expires
+            .map(|expires| expires.saturating_sub(self.fetched));
+        Metadata {
+            etag: updated.etag.or(self.etag),
+            last_modified: updated.last_modified.or(self.last_modified),
+            fetched: updated.fetched,
+            expires: (updated.expires).or(lifetime.map(|lifetime| updated.fetched + lifetime)),
+            ..self
+        }
+    }
+
+    /// Whether a conditional request can be sent for the content.
+    pub(crate) fn has_validators(&self) -> bool {
+        self.etag.is_some() || self.last_modified.is_some()
+    }
+
+    /// Whether the content has to be checked for changes, being older than `ttl` seconds,
+    /// or else expired, or a refresh being requested.
+    pub(crate) fn is_stale(&self, ttl: Option<u64>) -> bool {
+        let now = now();
+        refresh_requested()
+            || match ttl {
+                Some(ttl) => now.saturating_sub(self.fetched) >= ttl,
+                None => self.expires.is_some_and(|expires| now >= expires),
+            }
+    }
+
+    /// Makes a request conditional on the content having changed.
+    pub(crate) fn conditional(&self, mut request: RequestBuilder) -> RequestBuilder {
+        if let Some(etag) = &self.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &self.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+        request
+    }
+}
+
+/// The metadata file next to a cache file.
+fn metadata_path(cache_file: &Path) -> PathBuf {
+    cache_file.with_extension("meta")
+}
+
+/// Loads the metadata of a cache file. Entries cached without metadata are dated by the
+/// modification time of their file and have no validators.
+pub(crate) fn load(cache_file: &Path) -> Metadata {
+    if let Some(metadata) = std::fs::read(metadata_path(cache_file))
+        .ok()
+        .and_then(|content| serde_json::from_slice(&content).ok())
+    {
+        return metadata;
+    }
+    let modified = std::fs::metadata(cache_file).and_then(|metadata| metadata.modified());
+    Metadata {
+        fetched: modified.map_or(0, seconds_since_epoch),
+        ..Metadata::default()
+    }
+}
+
+/// Writes the metadata of a cache file.
+pub(crate) fn store(cache_file: &Path, metadata: &Metadata) -> Result<(), String> {
+    let content = serde_json::to_vec(metadata)
+        .map_err(|e| format!("Failed to serialize cache metadata: {}", e))?;
+    std::fs::write(metadata_path(cache_file), content)
+        .map_err(|e| format!("Failed to write cache metadata: {}", e))
+}
+
+/// Returns for how many seconds a response stays fresh, from the `max-age` of its
+/// `Cache-Control` header, minus its `Age`, or else from its `Expires` header. Responses
+/// that must not be reused without revalidation are stale right away.
+fn freshness_lifetime(headers: &HeaderMap) -> Option<u64> {
+    let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+    let directives = header(CACHE_CONTROL)
+        .into_iter()
+        .flat_map(|value| value.split(','));
+    for directive in directives {
+        let directive = directive.trim().to_ascii_lowercase();
+        match directive.split_once('=') {
+            None if directive == "no-cache" || directive == "no-store" => return Some(0),
+            Some(("max-age", seconds)) => {
+                let age: u64 = header(AGE)
+                    .and_then(|age| age.trim().parse().ok())
+                    .unwrap_or(0);
+                return Some(
+                    seconds
+                        .trim_matches('"')
+                        .parse::<u64>()
+                        .ok()?
+                        .saturating_sub(age),
+                );
+            }
+            _ => {}
+        }
+    }
+    // Invalid dates, such as `0`, mean that the response already expired
+    let expires = header(EXPIRES)?;
+    let expires = httpdate::parse_http_date(expires).unwrap_or(UNIX_EPOCH);
+    Some(seconds_since_epoch(expires).saturating_sub(now()))
+}
+
+/// Whether `INCLUDE_URL_REFRESH` asks for every cached entry to be revalidated.
+pub(crate) fn refresh_requested() -> bool {
+    env::var(REFRESH_VAR).is_ok_and(|value| !matches!(value.trim(), "" | "0" | "false"))
+}
+
+fn now() -> u64 {
+    seconds_since_epoch(SystemTime::now())
+}
+
+fn seconds_since_epoch(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .as_ref()
+        .map_or(0, Duration::as_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that the validators are kept and updated by a revalidation
+    #[test]
+    fn test_metadata() {
+        let mut headers = HeaderMap::new();
+        headers.insert(ETAG, "\"v1\"".parse().unwrap());
+        let metadata = Metadata::from_headers(&headers);
+        assert_eq!(metadata.etag.as_deref(), Some("\"v1\""));
+        assert!(metadata.has_validators() && !metadata.is_stale(Some(60)));
+        assert!(!Metadata::from_headers(&HeaderMap::new()).has_validators());
+
+        let old = Metadata {
+            fetched: now() - 120,
+            ..metadata
+        };
+        let expired = Metadata {
+            expires: Some(now() - 60),
+            ..old.clone()
+        };
+        assert!(expired.is_stale(None) && !expired.is_stale(Some(3600)));
+        let revalidated = expired.revalidated(&HeaderMap::new());
+        assert_eq!(revalidated.expires, Some(revalidated.fetched + 60));
+        assert!(old.is_stale(Some(60)) && !old.is_stale(None));
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            LAST_MODIFIED,
+            "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap(),
+        );
+        let revalidated = old.revalidated(&headers);
+        assert_eq!(revalidated.etag.as_deref(), Some("\"v1\""));
+        assert!(revalidated.last_modified.is_some() && !revalidated.is_stale(Some(60)));
+    }
+
+    /// Test that the freshness of a response is read from its caching headers
+    #[test]
+    fn test_freshness_lifetime() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(freshness_lifetime(&headers), None);
+        headers.insert(EXPIRES, "0".parse().unwrap());
+        assert_eq!(freshness_lifetime(&headers), Some(0));
+        headers.insert(CACHE_CONTROL, "public, max-age=300".parse().unwrap());
+        headers.insert(AGE, "100".parse().unwrap());
+        assert_eq!(freshness_lifetime(&headers), Some(200));
+        headers.insert(CACHE_CONTROL, "no-cache".parse().unwrap());
+        assert_eq!(freshness_lifetime(&headers), Some(0));
+
+        let mut headers = HeaderMap::new();
+        let expires = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(3600));
+        headers.insert(EXPIRES, expires.parse().unwrap());
+        let lifetime = freshness_lifetime(&headers).unwrap();
+        assert!((3590..=3600).contains(&lifetime), "{}", lifetime);
+    }
+
+    /// Test that metadata round-trips through the file next to the cache file
+    #[test]
+    fn test_store() {
+        let cache_file = std::env::temp_dir().join(format!(
+            "include_url_macro_test_meta_{}",
+            std::process::id()
+        ));
+        std::fs::write(&cache_file, b"content").unwrap();
+        assert!(!load(&cache_file).has_validators());
+        assert!(load(&cache_file).fetched > 0);
+
+        let metadata = Metadata {
+            etag: Some("\"v2\"".to_string()),
+            last_modified: None,
+            fetched: 1_700_000_000,
+            expires: Some(1_700_000_300),
+            sha256: Some("ab".repeat(32)),
+            length: Some(7),
+        };
+        store(&cache_file, &metadata).unwrap();
+        assert_eq!(load(&cache_file), metadata);
+
+        let _ = std::fs::remove_file(metadata_path(&cache_file));
+        let _ = std::fs::remove_file(&cache_file);
+    }
+}
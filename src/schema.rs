@@ -0,0 +1,182 @@
+//! Rust type generation for the OpenAPI specifications and JSON Schemas included by
+//! `include_openapi_url!` and `include_jsonschema_url!`.
+//!
+//! Schemas are converted into structs and enums by `typify`, which resolves references by
+//! the last segment of their path, so `#/components/schemas/Pet` refers to the `Pet` type.
+
+use proc_macro2::TokenStream;
+#[cfg(feature = "openapi")]
+use quote::quote;
+
+#[cfg(feature = "openapi")]
+use crate::const_ident;
+
+/// Generates types for schema definitions given as JSON values, keyed by type name.
+fn generate_types(
+    definitions: impl IntoIterator<Item = (String, serde_json::Value)>,
+) -> Result<TokenStream, String> {
+    let definitions = definitions
+        .into_iter()
+        .map(|(name, schema)| {
+            serde_json::from_value::<schemars::schema::Schema>(schema)
+                .map(|schema| (name.clone(), schema))
+                .map_err(|e| format!("Invalid schema `{}`: {}", name, e))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut types =
+        typify::TypeSpace::new(typify::TypeSpaceSettings::default().with_struct_builder(false));
+    types
+        .add_ref_types(definitions)
+        .map_err(|e| format!("Failed to generate types: {}", e))?;
+    Ok(types.to_stream())
+}
+
+/// Generates the types of the component schemas of an OpenAPI 3.x specification in JSON or
+/// YAML form, and an `operations` module holding the method and path of every operation
+/// with an `operationId`.
+#[cfg(feature = "openapi")]
+pub(crate) fn generate_openapi(spec: &str) -> Result<TokenStream, String> {
+    let spec: openapiv3::OpenAPI = serde_yaml::from_str(spec)
+        .map_err(|e| format!("Invalid OpenAPI specification from URL: {}", e))?;
+    if !spec.openapi.starts_with("3.") {
+        return Err(format!(
+            "Unsupported OpenAPI version `{}`, expected 3.x",
+            spec.openapi
+        ));
+    }
+
+    let schemas = spec
+        .components
+        .iter()
+        .flat_map(|components| &components.schemas)
+        .map(|(name, schema)| {
+            serde_json::to_value(schema)
+                .map(|schema| (name.clone(), schema))
+                .map_err(|e| format!("Invalid schema `{}`: {}", name, e))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let types = generate_types(schemas)
+        .map_err(|e| format!("Invalid OpenAPI specification from URL: {}", e))?;
+
+    let mut operations = Vec::new();
+    for (path, method, operation) in spec.operations() {
+        let Some(id) = &operation.operation_id else {
+            continue;
+        };
+        let name =
+            const_ident(id).ok_or_else(|| format!("The operation `{}` has no Rust name", id))?;
+        let method = method.to_uppercase();
+        let doc = operation
+            .summary
+            .as_ref()
+            .map(|summary| quote! { #[doc = #summary] });
+        operations.push(quote! {
+            #doc
+            pub const #name: (&str, &str) = (#method, #path);
+        });
+    }
+
+    Ok(quote! {
+        #types
+
+        /// The method and path of every operation, named after its `operationId`.
+        pub mod operations {
+            #(#operations)*
+        }
+    })
+}
+
+/// Generates the types of a JSON Schema document: the root schema, named after its
+/// `title` or `default_name`, and everything under `definitions` or `$defs`.
+#[cfg(feature = "jsonschema")]
+pub(crate) fn generate_jsonschema(schema: &str, default_name: &str) -> Result<TokenStream, String> {
+    let mut root: serde_json::Value =
+        serde_json::from_str(schema).map_err(|e| format!("Invalid JSON Schema from URL: {}", e))?;
+    let Some(object) = root.as_object_mut() else {
+        return Err("Invalid JSON Schema from URL: the root is not an object".to_string());
+    };
+    let mut definitions = Vec::new();
+    for key in ["definitions", "$defs"] {
+        if let Some(serde_json::Value::Object(defs)) = object.remove(key) {
+            definitions.extend(defs);
+        }
+    }
+    let name = match object.get("title").and_then(|title| title.as_str()) {
+        Some(title) => title.to_string(),
+        None => default_name.to_string(),
+    };
+    definitions.push((name, root));
+    generate_types(definitions).map_err(|e| format!("Invalid JSON Schema from URL: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that component schemas and operations are generated
+    #[cfg(feature = "openapi")]
+    #[test]
+    fn test_generate_openapi() {
+        let code = generate_openapi(
+            r##"
+            openapi: 3.0.3
+            info: {title: Pets, version: "1"}
+            paths:
+              /pets/{id}:
+                get:
+                  operationId: getPet
+                  responses:
+                    "200":
+                      description: A pet
+                      content:
+                        application/json:
+                          schema: {$ref: "#/components/schemas/Pet"}
+            components:
+              schemas:
+                Pet:
+                  type: object
+                  required: [id]
+                  properties:
+                    id: {type: integer, format: int64}
+                    owner: {$ref: "#/components/schemas/Owner"}
+                Owner:
+                  type: object
+                  properties:
+                    name: {type: string}
+            "##,
+        )
+        .unwrap();
+        let code = code.to_string();
+        assert!(code.contains("pub struct Pet"));
+        assert!(code.contains("pub owner : :: std :: option :: Option < Owner >"));
+        assert!(code.contains(r#"pub const GET_PET : (& str , & str) = ("GET" , "/pets/{id}")"#));
+
+        assert!(generate_openapi("swagger: \"2.0\"").is_err());
+    }
+
+    /// Test that the root schema and its definitions are generated
+    #[cfg(feature = "jsonschema")]
+    #[test]
+    fn test_generate_jsonschema() {
+        let code = generate_jsonschema(
+            r##"{
+                "type": "object",
+                "required": ["name"],
+                "properties": {
+                    "name": {"type": "string"},
+                    "limits": {"$ref": "#/$defs/Limits"}
+                },
+                "$defs": {
+                    "Limits": {"type": "object", "properties": {"cpu": {"type": "number"}}}
+                }
+            }"##,
+            "Config",
+        )
+        .unwrap();
+        let code = code.to_string();
+        assert!(code.contains("pub struct Config"));
+        assert!(code.contains("pub limits : :: std :: option :: Option < Limits >"));
+
+        assert!(generate_jsonschema("[]", "Config").is_err());
+    }
+}
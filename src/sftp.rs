@@ -0,0 +1,307 @@
+//! Files of SSH servers, `sftp://user@host/path`.
+//!
+//! The server must be listed in `~/.ssh/known_hosts` with the key it presents, the way
+//! `ssh-keyscan host >> ~/.ssh/known_hosts` adds it, since an unknown key cannot be
+//! confirmed during a build. The session authenticates with the password of the
+//! `auth = basic_env("USER", "PASSWORD")` credentials when they are given, and otherwise as
+//! the user of the URL, or the local user, with the keys of the SSH agent or the
+//! unencrypted default keys of `~/.ssh`. Paths are absolute, `sftp://host/~/file` names a
+//! file of the home directory.
+
+use std::{
+    env,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use ssh2::{CheckResult, KnownHostFileKind, Session};
+use url::Url;
+
+use crate::{
+    check_size, credential,
+    ftp::{connect_host, transient},
+    Auth, Failure, FetchOptions,
+};
+
+const DEFAULT_PORT: u16 = 22;
+
+/// Private keys tried when no agent holds an accepted key, as `ssh` tries them.
+const DEFAULT_KEYS: &[&str] = &["id_ed25519", "id_ecdsa", "id_rsa"];
+
+/// The file named by an `sftp://` URL, and the credentials to read it with.
+#[derive(Debug, PartialEq, Eq)]
+struct Target {
+    host: String,
+    port: u16,
+    /// The absolute path, or the path relative to the home directory.
+    path: String,
+    user: String,
+    password: Option<String>,
+}
+
+/// Reads the server, the path and the credentials of an `sftp://` URL.
+fn target(url_str: &str, options: &FetchOptions) -> Result<Target, String> {
+    let url = Url::parse(url_str).map_err(|e| format!("Invalid URL: {}", e))?;
+    let host = url.host_str().unwrap_or_default();
+    let path = percent_encoding::percent_decode_str(url.path())
+        .decode_utf8()
+        .map_err(|e| format!("Invalid path in `{}`: {}", url_str, e))?;
+    let path = path.strip_prefix("/~/").unwrap_or(&path);
+    if host.is_empty() || path.trim_start_matches('/').is_empty() {
+        return Err(format!("Expected `sftp://host/path`, got `{}`", url_str));
+    }
+    let (user, password) = match &options.auth {
+        Some(Auth::Basic(user, password)) => (credential(user)?, Some(credential(password)?)),
+        Some(Auth::Bearer(_)) => {
+            return Err("SSH servers only accept `auth = basic_env(...)`".to_string())
+        }
+        None if !url.username().is_empty() => (url.username().to_string(), None),
+        None => (
+            env::var("USER")
+                .or_else(|_| env::var("USERNAME"))
+                .map_err(|_| format!("No user name in `{}`", url_str))?,
+            None,
+        ),
+    };
+    Ok(Target {
+        host: host.to_string(),
+        port: url.port().unwrap_or(DEFAULT_PORT),
+        path: path.to_string(),
+        user,
+        password,
+    })
+}
+
+/// Downloads the file named by an `sftp://` URL.
+pub(crate) fn fetch(url_str: &str, options: &FetchOptions) -> Result<bytes::Bytes, String> {
+    let target = target(url_str, options)?;
+    crate::with_retries(&target.host, options, || {
+        let session = connect(&target.host, target.port, options)?;
+        authenticate(&session, &target.user, target.password.as_deref())?;
+        let sftp = session.sftp().map_err(ssh_failure)?;
+        let path = Path::new(&target.path);
+        let stat = sftp
+            .stat(path)
+            .map_err(|e| format!("Failed to open `{}`: {}", path.display(), e.message()))?;
+        if let Some(size) = stat.size {
+            check_size(size, options)?;
+        }
+        let file = sftp.open(path).map_err(ssh_failure)?;
+        let limit = options.max_size.map_or(u64::MAX, |max| max as u64 + 1);
+        let mut content = Vec::new();
+        (file.take(limit))
+            .read_to_end(&mut content)
+            .map_err(|e| transient(format!("SFTP transfer failed: {}", e)))?;
+        if let Some(max) = options.max_size.filter(|&max| content.len() > max) {
+            return Err(format!("Content exceeds the `max_size` of {} bytes", max).into());
+        }
+        Ok(content.into())
+    })
+}
+
+/// Opens a session with the server, checking its key against `~/.ssh/known_hosts`.
+fn connect(host: &str, port: u16, options: &FetchOptions) -> Result<Session, Failure> {
+    let stream = connect_host(host, port, options)?;
+    let mut session = Session::new().map_err(ssh_failure)?;
+    session.set_tcp_stream(stream);
+    let timeout = options.timeout_or_default().as_millis();
+    session.set_timeout(timeout.try_into().unwrap_or(u32::MAX));
+    session.handshake().map_err(ssh_failure)?;
+
+    let known_hosts_file = ssh_dir()
+        .map(|dir| dir.join("known_hosts"))
+        .ok_or_else(|| "No home directory to find `~/.ssh/known_hosts` in".to_string())?;
+    let (key, _) = session
+        .host_key()
+        .ok_or_else(|| "The SSH server sent no host key".to_string())?;
+    check_host_key(&session, host, port, key, &known_hosts_file)?;
+    Ok(session)
+}
+
+/// Checks the key presented by `host` against the keys of the known hosts file.
+fn check_host_key(
+    session: &Session,
+    host: &str,
+    port: u16,
+    key: &[u8],
+    known_hosts_file: &Path,
+) -> Result<(), Failure> {
+    let mut known_hosts = session.known_hosts().map_err(ssh_failure)?;
+    // A missing file leaves the list empty, and the host unknown
+    let _ = known_hosts.read_file(known_hosts_file, KnownHostFileKind::OpenSSH);
+    match known_hosts.check_port(host, port, key) {
+        CheckResult::Match => Ok(()),
+        CheckResult::NotFound => Err(format!(
+            "`{}` is not a known host, add its key to `{}` with `ssh-keyscan`",
+            host,
+            known_hosts_file.display()
+        )
+        .into()),
+        CheckResult::Mismatch => Err(format!(
+            "The key of `{}` does not match the one in `{}`",
+            host,
+            known_hosts_file.display()
+        )
+        .into()),
+        CheckResult::Failure => Err(format!("Failed to check the key of `{}`", host).into()),
+    }
+}
+
+fn authenticate(session: &Session, user: &str, password: Option<&str>) -> Result<(), Failure> {
+    if let Some(password) = password {
+        session
+            .userauth_password(user, password)
+            .map_err(|e| format!("Failed to authenticate as `{}`: {}", user, e.message()))?;
+        return Ok(());
+    }
+    if session.userauth_agent(user).is_ok() {
+        return Ok(());
+    }
+    for key in ssh_dir().map(|dir| default_keys(&dir)).unwrap_or_default() {
+        if session.userauth_pubkey_file(user, None, &key, None).is_ok() {
+            return Ok(());
+        }
+    }
+    Err(format!(
+        "Failed to authenticate as `{}` with the SSH agent or the keys of `~/.ssh`",
+        user
+    )
+    .into())
+}
+
+/// Returns the default private keys found in `ssh_dir`, in the order they are tried.
+fn default_keys(ssh_dir: &Path) -> Vec<PathBuf> {
+    (DEFAULT_KEYS.iter())
+        .map(|key| ssh_dir.join(key))
+        .filter(|key| key.exists())
+        .collect()
+}
+
+fn ssh_dir() -> Option<PathBuf> {
+    let home = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE"))?;
+    Some(PathBuf::from(home).join(".ssh"))
+}
+
+/// Connection failures are temporary, the errors of the server are not.
+fn ssh_failure(error: ssh2::Error) -> Failure {
+    let message = format!("SSH session failed: {}", error.message());
+    match error.code() {
+        ssh2::ErrorCode::Session(_) => transient(message),
+        ssh2::ErrorCode::SFTP(_) => message.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+
+    use super::*;
+
+    /// Test that the host, port, path and user are read from the URL, and the credentials
+    /// of `auth` take precedence over its user
+    #[test]
+    fn test_target() {
+        let options = FetchOptions::default();
+        assert_eq!(
+            target(
+                "sftp://deploy@example.com:2222/srv/data%20set.json",
+                &options
+            ),
+            Ok(Target {
+                host: "example.com".to_string(),
+                port: 2222,
+                path: "/srv/data set.json".to_string(),
+                user: "deploy".to_string(),
+                password: None,
+            })
+        );
+        let home = target("sftp://deploy@example.com/~/data.json", &options).unwrap();
+        assert_eq!((home.port, home.path.as_str()), (22, "data.json"));
+        assert_eq!(
+            target("sftp://deploy@example.com/", &options),
+            Err("Expected `sftp://host/path`, got `sftp://deploy@example.com/`".to_string())
+        );
+
+        env::set_var("INCLUDE_URL_SFTP_TEST_USER", "builder");
+        env::set_var("INCLUDE_URL_SFTP_TEST_PASSWORD", "secret");
+        let options = FetchOptions {
+            auth: Some(Auth::Basic(
+                "INCLUDE_URL_SFTP_TEST_USER".to_string(),
+                "INCLUDE_URL_SFTP_TEST_PASSWORD".to_string(),
+            )),
+            ..Default::default()
+        };
+        let target_with_auth = target("sftp://deploy@example.com/data.json", &options).unwrap();
+        assert_eq!(target_with_auth.user, "builder");
+        assert_eq!(target_with_auth.password.as_deref(), Some("secret"));
+        let options = FetchOptions {
+            auth: Some(Auth::Bearer("INCLUDE_URL_SFTP_TEST_USER".to_string())),
+            ..Default::default()
+        };
+        assert_eq!(
+            target("sftp://example.com/data.json", &options),
+            Err("SSH servers only accept `auth = basic_env(...)`".to_string())
+        );
+    }
+
+    /// Test that only the default keys present are tried, in the order of `ssh`
+    #[test]
+    fn test_default_keys() {
+        let dir = env::temp_dir().join(format!("include_url_sftp_keys_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("id_rsa"), "").unwrap();
+        std::fs::write(dir.join("id_ed25519"), "").unwrap();
+        std::fs::write(dir.join("id_dsa"), "").unwrap();
+        assert_eq!(
+            default_keys(&dir),
+            [dir.join("id_ed25519"), dir.join("id_rsa")]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Test that the key of a host has to be the one of the known hosts file
+    #[test]
+    fn test_check_host_key() {
+        // The wire encoding of an Ed25519 public key
+        let key = |byte: u8| {
+            let mut key = b"\0\0\0\x0bssh-ed25519\0\0\0\x20".to_vec();
+            key.extend_from_slice(&[byte; 32]);
+            key
+        };
+        let file = env::temp_dir().join(format!(
+            "include_url_sftp_known_hosts_{}",
+            std::process::id()
+        ));
+        std::fs::write(
+            &file,
+            format!(
+                "example.com ssh-ed25519 {}\n[example.com]:2222 ssh-ed25519 {}\n",
+                STANDARD.encode(key(1)),
+                STANDARD.encode(key(2))
+            ),
+        )
+        .unwrap();
+        let session = Session::new().unwrap();
+        let check = |host: &str, port: u16, key: &[u8]| {
+            check_host_key(&session, host, port, key, &file).map_err(|e| e.message)
+        };
+
+        assert_eq!(check("example.com", 22, &key(1)), Ok(()));
+        assert_eq!(check("example.com", 2222, &key(2)), Ok(()));
+        assert_eq!(
+            check("example.com", 22, &key(2)),
+            Err(format!(
+                "The key of `example.com` does not match the one in `{}`",
+                file.display()
+            ))
+        );
+        assert_eq!(
+            check("example.org", 22, &key(1)),
+            Err(format!(
+                "`example.org` is not a known host, add its key to `{}` with `ssh-keyscan`",
+                file.display()
+            ))
+        );
+        std::fs::remove_file(&file).unwrap();
+    }
+}
@@ -0,0 +1,836 @@
+//! Sigstore bundles, `signature_url = "...sigstore.json"` with `sigstore_identity` and
+//! `sigstore_issuer`, checked before the content is cached as `cosign verify-blob` does.
+//!
+//! The short-lived certificate of the bundle has to chain to a certificate authority of the
+//! trusted root, `sigstore_root`, and name the expected identity and OIDC issuer. The
+//! signature of the content is checked with its key, and the entry of the bundle in the
+//! transparency log is checked with the key of the log, by its signed entry timestamp or
+//! its inclusion proof, at the time of which the certificate has to be valid. The entry
+//! has to record the digest of the content, the signature and the certificate.
+//!
+//! Bundles of the Sigstore format and the earlier format of `cosign sign-blob --bundle` are
+//! read. Only `hashedrekord` entries of message signatures are supported, not DSSE
+//! attestations, and the signed certificate timestamps of the certificate are not checked.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ring::{digest, signature};
+use serde::Deserialize;
+use serde_json::Value;
+use x509_parser::{
+    certificate::X509Certificate,
+    extensions::GeneralName,
+    oid_registry::{OID_EC_P256, OID_KEY_TYPE_EC_PUBLIC_KEY, OID_NIST_EC_P384, OID_SIG_ED25519},
+    prelude::FromDer,
+    x509::SubjectPublicKeyInfo,
+};
+
+use crate::FetchOptions;
+
+/// The OIDC issuer extension of Fulcio certificates, a DER encoded string.
+const ISSUER_V2: &str = "1.3.6.1.4.1.57264.1.8";
+/// The deprecated OIDC issuer extension, holding the raw string.
+const ISSUER_V1: &str = "1.3.6.1.4.1.57264.1.1";
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Bundle {
+    verification_material: VerificationMaterial,
+    message_signature: Option<MessageSignature>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VerificationMaterial {
+    /// The certificate of version 0.3 bundles.
+    certificate: Option<RawBytes>,
+    /// The certificate chain of earlier bundles, starting with the certificate.
+    x509_certificate_chain: Option<CertificateChain>,
+    #[serde(default)]
+    tlog_entries: Vec<TlogEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawBytes {
+    raw_bytes: String,
+}
+
+#[derive(Deserialize)]
+struct CertificateChain {
+    certificates: Vec<RawBytes>,
+}
+
+#[derive(Deserialize)]
+struct MessageSignature {
+    signature: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TlogEntry {
+    log_index: Value,
+    log_id: LogId,
+    integrated_time: Value,
+    inclusion_promise: Option<InclusionPromise>,
+    inclusion_proof: Option<InclusionProof>,
+    canonicalized_body: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LogId {
+    key_id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InclusionPromise {
+    signed_entry_timestamp: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InclusionProof {
+    log_index: Value,
+    root_hash: String,
+    tree_size: Value,
+    hashes: Vec<String>,
+    checkpoint: Checkpoint,
+}
+
+#[derive(Deserialize)]
+struct Checkpoint {
+    envelope: String,
+}
+
+/// A bundle of the format `cosign` wrote before the Sigstore bundle format.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CosignBundle {
+    base64_signature: String,
+    /// The base64 encoded PEM of the certificate.
+    cert: String,
+    rekor_bundle: RekorBundle,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct RekorBundle {
+    signed_entry_timestamp: String,
+    payload: RekorPayload,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RekorPayload {
+    body: String,
+    integrated_time: Value,
+    log_index: Value,
+    /// The hex encoded ID of the log.
+    #[serde(rename = "logID")]
+    log_id: String,
+}
+
+impl CosignBundle {
+    fn into_bundle(self) -> Result<Bundle, String> {
+        let certificate = decode(&self.cert, "certificate")
+            .ok()
+            .and_then(|pem| pem_der(&pem))
+            .ok_or("Invalid certificate in the cosign bundle")?;
+        let payload = self.rekor_bundle.payload;
+        let log_id = (0..payload.log_id.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(payload.log_id.get(i..i + 2)?, 16).ok())
+            .collect::<Option<Vec<_>>>()
+            .ok_or("Invalid log ID in the cosign bundle")?;
+        Ok(Bundle {
+            verification_material: VerificationMaterial {
+                certificate: Some(RawBytes {
+                    raw_bytes: STANDARD.encode(certificate),
+                }),
+                x509_certificate_chain: None,
+                tlog_entries: vec![TlogEntry {
+                    log_index: payload.log_index,
+                    log_id: LogId {
+                        key_id: STANDARD.encode(log_id),
+                    },
+                    integrated_time: payload.integrated_time,
+                    inclusion_promise: Some(InclusionPromise {
+                        signed_entry_timestamp: self.rekor_bundle.signed_entry_timestamp,
+                    }),
+                    inclusion_proof: None,
+                    canonicalized_body: payload.body,
+                }],
+            },
+            message_signature: Some(MessageSignature {
+                signature: self.base64_signature,
+            }),
+        })
+    }
+}
+
+/// The `trusted_root.json` of a Sigstore instance.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TrustedRoot {
+    tlogs: Vec<TransparencyLog>,
+    certificate_authorities: Vec<CertificateAuthority>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TransparencyLog {
+    public_key: RawBytes,
+    log_id: LogId,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CertificateAuthority {
+    cert_chain: CertificateChain,
+}
+
+/// Fails unless the Sigstore `bundle` holds a signature of `content` by the identity of
+/// `sigstore_identity` and `sigstore_issuer`, logged in the transparency log.
+pub(crate) fn verify(
+    content: &[u8],
+    bundle: &[u8],
+    options: &mut FetchOptions,
+) -> Result<(), String> {
+    let identity = options.sigstore_identity.clone().unwrap_or_default();
+    let issuer = (options.sigstore_issuer.clone())
+        .ok_or("`sigstore_identity` requires `sigstore_issuer`, the OIDC issuer of the identity")?;
+    let root_path = match options.sigstore_root.clone() {
+        Some(root) => root,
+        None => options.config()?.sigstore_root.clone().ok_or(
+            "`sigstore_identity` requires `sigstore_root`, the path to the `trusted_root.json` \
+             of the Sigstore instance",
+        )?,
+    };
+    let root = crate::read_key_file(&root_path, options)?;
+    let root: TrustedRoot = serde_json::from_slice(&root)
+        .map_err(|e| format!("Invalid Sigstore trusted root `{}`: {}", root_path, e))?;
+    let bundle = match serde_json::from_slice::<CosignBundle>(bundle) {
+        Ok(cosign) => cosign.into_bundle()?,
+        Err(_) => serde_json::from_slice::<Bundle>(bundle)
+            .map_err(|e| format!("Invalid Sigstore bundle: {}", e))?,
+    };
+
+    let material = &bundle.verification_material;
+    let certificate = (material.certificate.as_ref())
+        .or_else(|| {
+            material
+                .x509_certificate_chain
+                .as_ref()?
+                .certificates
+                .first()
+        })
+        .ok_or("The Sigstore bundle holds no certificate, signatures by keys are not supported")?;
+    let certificate_der = decode(&certificate.raw_bytes, "certificate")?;
+    let (_, certificate) = X509Certificate::from_der(&certificate_der)
+        .map_err(|e| format!("Invalid certificate in the Sigstore bundle: {}", e))?;
+    let signature = bundle.message_signature.ok_or(
+        "The Sigstore bundle holds no message signature, DSSE envelopes are not supported",
+    )?;
+    let signature = decode(&signature.signature, "signature")?;
+
+    check_identity(&certificate, &identity, &issuer)?;
+    if !verify_with(certificate.public_key(), content, &signature) {
+        return Err("The signature of the Sigstore bundle does not match the content".to_string());
+    }
+    if material.tlog_entries.is_empty() {
+        return Err("The Sigstore bundle holds no transparency log entry".to_string());
+    }
+    for entry in &material.tlog_entries {
+        let integrated_time = number(&entry.integrated_time, "integratedTime")?;
+        check_entry(entry, &root, content, &signature, &certificate_der)?;
+        check_chain(&certificate, &root, integrated_time)?;
+    }
+    Ok(())
+}
+
+/// Checks the subject alternative name and the OIDC issuer named by the certificate.
+fn check_identity(
+    certificate: &X509Certificate,
+    identity: &str,
+    issuer: &str,
+) -> Result<(), String> {
+    let names = certificate
+        .subject_alternative_name()
+        .ok()
+        .flatten()
+        .map(|extension| &extension.value.general_names[..])
+        .unwrap_or_default();
+    let subjects: Vec<_> = names
+        .iter()
+        .filter_map(|name| match name {
+            GeneralName::RFC822Name(name) | GeneralName::URI(name) => Some(*name),
+            _ => None,
+        })
+        .collect();
+    if !subjects.contains(&identity) {
+        return Err(format!(
+            "The Sigstore certificate is issued to {}, not to `{}`",
+            match subjects[..] {
+                [] => "no identity".to_string(),
+                _ => format!("`{}`", subjects.join("`, `")),
+            },
+            identity
+        ));
+    }
+    let mut issuers = certificate.extensions().iter().filter_map(|extension| {
+        match extension.oid.to_id_string().as_str() {
+            ISSUER_V2 => match extension.value {
+                [0x0C, length, string @ ..] if string.len() == *length as usize => {
+                    std::str::from_utf8(string).ok()
+                }
+                [0x0C, 0x81, length, string @ ..] if string.len() == *length as usize => {
+                    std::str::from_utf8(string).ok()
+                }
+                _ => None,
+            },
+            ISSUER_V1 => std::str::from_utf8(extension.value).ok(),
+            _ => None,
+        }
+    });
+    match issuers.next() {
+        Some(actual) if actual == issuer => Ok(()),
+        Some(actual) => Err(format!(
+            "The Sigstore certificate is issued for tokens of `{}`, not of `{}`",
+            actual, issuer
+        )),
+        None => Err("The Sigstore certificate names no OIDC issuer".to_string()),
+    }
+}
+
+/// Checks that the certificate was valid at the time it was logged, for code signing, and
+/// issued by a certificate authority of the trusted root.
+fn check_chain(certificate: &X509Certificate, root: &TrustedRoot, time: i64) -> Result<(), String> {
+    let valid_at = |certificate: &X509Certificate| {
+        let validity = certificate.validity();
+        (validity.not_before.timestamp()..=validity.not_after.timestamp()).contains(&time)
+    };
+    if !valid_at(certificate) {
+        return Err(
+            "The Sigstore certificate was not valid when the signature was logged".to_string(),
+        );
+    }
+    let code_signing = certificate.extended_key_usage().ok().flatten();
+    if !code_signing.is_some_and(|usage| usage.value.code_signing) {
+        return Err("The Sigstore certificate is not issued for code signing".to_string());
+    }
+    for authority in &root.certificate_authorities {
+        let chain = authority
+            .cert_chain
+            .certificates
+            .iter()
+            .map(|certificate| decode(&certificate.raw_bytes, "trusted root certificate"))
+            .collect::<Result<Vec<_>, _>>()?;
+        let chain = chain
+            .iter()
+            .map(|der| X509Certificate::from_der(der).map(|(_, certificate)| certificate))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Invalid certificate in the Sigstore trusted root: {}", e))?;
+        let mut issued = certificate;
+        let chained = chain.iter().all(|authority| {
+            let signed = issued.issuer().as_raw() == authority.subject().as_raw()
+                && authority.is_ca()
+                && valid_at(authority)
+                && issued
+                    .verify_signature(Some(authority.public_key()))
+                    .is_ok();
+            issued = authority;
+            signed
+        });
+        if chained && !chain.is_empty() {
+            return Ok(());
+        }
+    }
+    Err(
+        "The Sigstore certificate is not issued by a certificate authority of the trusted root"
+            .to_string(),
+    )
+}
+
+/// Checks a transparency log entry: the log signed it, and it records the content, the
+/// signature and the certificate.
+fn check_entry(
+    entry: &TlogEntry,
+    root: &TrustedRoot,
+    content: &[u8],
+    signature: &[u8],
+    certificate: &[u8],
+) -> Result<(), String> {
+    let log_id = decode(&entry.log_id.key_id, "log ID")?;
+    let log = root
+        .tlogs
+        .iter()
+        .find(|log| decode(&log.log_id.key_id, "log ID").is_ok_and(|id| id == log_id))
+        .ok_or(
+            "The Sigstore bundle is logged in a transparency log missing from the trusted root",
+        )?;
+    let log_key_der = decode(&log.public_key.raw_bytes, "log key")?;
+    let (_, log_key) = SubjectPublicKeyInfo::from_der(&log_key_der)
+        .map_err(|e| format!("Invalid transparency log key in the trusted root: {}", e))?;
+    let body = decode(&entry.canonicalized_body, "log entry")?;
+
+    let logged =
+        match (&entry.inclusion_promise, &entry.inclusion_proof) {
+            (Some(promise), _) => {
+                // The log signs the canonical JSON of the entry, with sorted keys
+                let payload = format!(
+                    r#"{{"body":"{}","integratedTime":{},"logID":"{}","logIndex":{}}}"#,
+                    entry.canonicalized_body,
+                    number(&entry.integrated_time, "integratedTime")?,
+                    hex(&log_id),
+                    number(&entry.log_index, "logIndex")?
+                );
+                let timestamp = decode(&promise.signed_entry_timestamp, "signed entry timestamp")?;
+                verify_with(&log_key, payload.as_bytes(), &timestamp)
+            }
+            (None, Some(proof)) => {
+                let index = number(&proof.log_index, "logIndex")? as u64;
+                let size = number(&proof.tree_size, "treeSize")? as u64;
+                let root_hash = decode(&proof.root_hash, "root hash")?;
+                let hashes = (proof.hashes.iter())
+                    .map(|hash| decode(hash, "proof hash"))
+                    .collect::<Result<Vec<_>, _>>()?;
+                inclusion_root(&body, index, size, &hashes).is_some_and(|root| root == root_hash)
+                    && verify_checkpoint(&proof.checkpoint.envelope, &log_key, size, &root_hash)
+            }
+            (None, None) => return Err(
+                "The transparency log entry of the Sigstore bundle carries no proof of inclusion"
+                    .to_string(),
+            ),
+        };
+    if !logged {
+        return Err(
+            "The transparency log entry of the Sigstore bundle is not signed by the log"
+                .to_string(),
+        );
+    }
+
+    let body: Value = serde_json::from_slice(&body)
+        .map_err(|e| format!("Invalid transparency log entry: {}", e))?;
+    if body["kind"] != "hashedrekord" {
+        return Err(format!(
+            "Unsupported transparency log entry of kind {}, expected `hashedrekord`",
+            body["kind"]
+        ));
+    }
+    let spec = &body["spec"];
+    let digest = hex(digest::digest(&digest::SHA256, content).as_ref());
+    let logged_key = spec["signature"]["publicKey"]["content"]
+        .as_str()
+        .and_then(|pem| STANDARD.decode(pem).ok())
+        .and_then(|pem| pem_der(&pem));
+    let matches = spec["data"]["hash"]["algorithm"] == "sha256"
+        && spec["data"]["hash"]["value"] == digest.as_str()
+        && spec["signature"]["content"]
+            .as_str()
+            .and_then(|s| STANDARD.decode(s).ok())
+            == Some(signature.to_vec())
+        && logged_key.as_deref() == Some(certificate);
+    match matches {
+        true => Ok(()),
+        false => Err(
+            "The transparency log entry of the Sigstore bundle is for another signature"
+                .to_string(),
+        ),
+    }
+}
+
+/// Computes the root hash of a Merkle tree of `size` leaves from the inclusion proof of the
+/// leaf at `index`, as in RFC 9162.
+fn inclusion_root(leaf: &[u8], index: u64, size: u64, hashes: &[Vec<u8>]) -> Option<Vec<u8>> {
+    if index >= size {
+        return None;
+    }
+    let hash = |parts: &[&[u8]]| {
+        let mut context = digest::Context::new(&digest::SHA256);
+        parts.iter().for_each(|part| context.update(part));
+        context.finish().as_ref().to_vec()
+    };
+    let (mut node, mut last) = (index, size - 1);
+    let mut root = hash(&[&[0x00], leaf]);
+    for sibling in hashes {
+        if last == 0 {
+            return None;
+        }
+        if node & 1 == 1 || node == last {
+            root = hash(&[&[0x01], sibling, &root]);
+            while node & 1 == 0 && node != 0 {
+                node >>= 1;
+                last >>= 1;
+            }
+        } else {
+            root = hash(&[&[0x01], &root, sibling]);
+        }
+        node >>= 1;
+        last >>= 1;
+    }
+    (last == 0).then_some(root)
+}
+
+/// Checks a checkpoint, a signed note of the size and root hash of the log.
+fn verify_checkpoint(
+    envelope: &str,
+    key: &SubjectPublicKeyInfo,
+    size: u64,
+    root_hash: &[u8],
+) -> bool {
+    let Some((note, signatures)) = envelope.split_once("\n\n") else {
+        return false;
+    };
+    let mut lines = note.lines().skip(1);
+    let checkpoint_size = lines.next().and_then(|line| line.parse::<u64>().ok());
+    let checkpoint_root = lines.next().and_then(|line| STANDARD.decode(line).ok());
+    if checkpoint_size != Some(size) || checkpoint_root.as_deref() != Some(root_hash) {
+        return false;
+    }
+    let message = format!("{}\n", note);
+    signatures.lines().any(|line| {
+        // Signature lines are `— origin base64`, the key hint followed by the signature
+        let signature = line
+            .rsplit(' ')
+            .next()
+            .and_then(|s| STANDARD.decode(s).ok());
+        signature.is_some_and(|signature| {
+            signature.len() > 4 && verify_with(key, message.as_bytes(), &signature[4..])
+        })
+    })
+}
+
+/// Checks a signature by a P-256, P-384 or Ed25519 key, with ECDSA signatures encoded in
+/// ASN.1.
+fn verify_with(key: &SubjectPublicKeyInfo, message: &[u8], signature: &[u8]) -> bool {
+    let algorithm: &dyn signature::VerificationAlgorithm =
+        if key.algorithm.algorithm == OID_SIG_ED25519 {
+            &signature::ED25519
+        } else if key.algorithm.algorithm == OID_KEY_TYPE_EC_PUBLIC_KEY {
+            let curve = key
+                .algorithm
+                .parameters
+                .as_ref()
+                .and_then(|p| p.as_oid().ok());
+            match curve {
+                Some(curve) if curve == OID_EC_P256 => &signature::ECDSA_P256_SHA256_ASN1,
+                Some(curve) if curve == OID_NIST_EC_P384 => &signature::ECDSA_P384_SHA384_ASN1,
+                _ => return false,
+            }
+        } else {
+            return false;
+        };
+    signature::UnparsedPublicKey::new(algorithm, &key.subject_public_key.data)
+        .verify(message, signature)
+        .is_ok()
+}
+
+/// Decodes the DER of the first block of a PEM file.
+fn pem_der(pem: &[u8]) -> Option<Vec<u8>> {
+    let pem = std::str::from_utf8(pem).ok()?;
+    let encoded: String = pem
+        .lines()
+        .skip_while(|line| !line.starts_with("-----BEGIN"))
+        .skip(1)
+        .take_while(|line| !line.starts_with("-----END"))
+        .map(str::trim)
+        .collect();
+    STANDARD.decode(encoded).ok()
+}
+
+fn decode(encoded: &str, what: &str) -> Result<Vec<u8>, String> {
+    STANDARD
+        .decode(encoded)
+        .map_err(|e| format!("Invalid base64 {} in the Sigstore bundle: {}", what, e))
+}
+
+/// Reads a 64-bit number, encoded as a string in JSON.
+fn number(value: &Value, name: &str) -> Result<i64, String> {
+    value
+        .as_i64()
+        .or_else(|| value.as_str()?.parse().ok())
+        .ok_or_else(|| format!("Invalid `{}` in the Sigstore bundle", name))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use ring::{
+        rand::SystemRandom,
+        signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING},
+    };
+    use serde_json::json;
+
+    use super::*;
+
+    /// A self-signed certificate authority, valid from 2026 to 2126.
+    const CA_CERTIFICATE: &str = "\
+        MIIBozCCAUqgAwIBAgIBATAKBggqhkjOPQQDAjAoMRQwEgYDVQQKDAtleGFtcGxlLmNvbTEQMA4GA1UEAwwHdGVz\
+        dC1jYTAgFw0yNjEwMTYxNzE0NDNaGA8yMTI2MDkyMjE3MTQ0M1owKDEUMBIGA1UECgwLZXhhbXBsZS5jb20xEDAO\
+        BgNVBAMMB3Rlc3QtY2EwWTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAARcJyGvBqUWvNgENCGC3uFFygJ/zCbVK9oS\
+        HDFGQ2v+c3VoX4D4A4SUmOuVowj/pHy62UTRO+OwaWNiPjOGqNAQo2MwYTAdBgNVHQ4EFgQUH8t5P80aenD3PJ4U\
+        gg/Ak1YNfdgwHwYDVR0jBBgwFoAUH8t5P80aenD3PJ4Ugg/Ak1YNfdgwDwYDVR0TAQH/BAUwAwEB/zAOBgNVHQ8B\
+        Af8EBAMCAgQwCgYIKoZIzj0EAwIDRwAwRAIgH+y82GBbvbdvdgonxN61pkD84HNpMojwK9w7jQj9PP0CIAWsPLiT\
+        ae5iaNIrpdIGnytB0mAkYcnhwNfdH6BEFOIF";
+    /// A code signing certificate of `CA_CERTIFICATE` for `signer@example.com`, with tokens
+    /// of `https://issuer.example.com`.
+    const CERTIFICATE: &str = "\
+        MIIBzTCCAXOgAwIBAgIBAjAKBggqhkjOPQQDAjAoMRQwEgYDVQQKDAtleGFtcGxlLmNvbTEQMA4GA1UEAwwHdGVz\
+        dC1jYTAgFw0yNjEwMTYxNzE0NDNaGA8yMTI2MDkyMjE3MTQ0M1owADBZMBMGByqGSM49AgEGCCqGSM49AwEHA0IA\
+        BOBKUXblFtGfR2fsd9RFDe+ldWvgB08lV51SDqoyj/3ekQzvGgVNpfk5n7JV3aZaXb/+TIXyD8w0Sf49jnlaOK+j\
+        gbMwgbAwHQYDVR0RBBYwFIESc2lnbmVyQGV4YW1wbGUuY29tMBMGA1UdJQQMMAoGCCsGAQUFBwMDMA4GA1UdDwEB\
+        /wQEAwIHgDAqBgorBgEEAYO/MAEIBBwMGmh0dHBzOi8vaXNzdWVyLmV4YW1wbGUuY29tMB0GA1UdDgQWBBSyIgdR\
+        1MXHkX5kGwyLSEijyInQTTAfBgNVHSMEGDAWgBQfy3k/zRp6cPc8nhSCD8CTVg192DAKBggqhkjOPQQDAgNIADBF\
+        AiEA/QMukE8QZGt+W8a4QlK59lFKZFXKK2R3UcW35fuJkyMCIHIDZuDdXkT+QBzST4Nx3lCzByD08RXnwxIFtVFV\
+        7SoX";
+    /// The PKCS#8 key of `CERTIFICATE`.
+    const CERTIFICATE_KEY: &str = "\
+        MIGHAgEAMBMGByqGSM49AgEGCCqGSM49AwEHBG0wawIBAQQgcVpyxaITHs/2l9cUv0/nB2lBHk3i6lp1d1khprgJ\
+        anKhRANCAATgSlF25RbRn0dn7HfURQ3vpXVr4AdPJVedUg6qMo/93pEM7xoFTaX5OZ+yVd2mWl2//kyF8g/MNEn+\
+        PY55Wjiv";
+    /// The time the test signatures are logged, in 2027.
+    const INTEGRATED_TIME: i64 = 1800000000;
+
+    fn sign(key: &EcdsaKeyPair, message: &[u8]) -> Vec<u8> {
+        let signature = key.sign(&SystemRandom::new(), message).unwrap();
+        signature.as_ref().to_vec()
+    }
+
+    /// Signs `content` with `CERTIFICATE` and logs it in a new transparency log, by a signed
+    /// entry timestamp or an inclusion proof in a tree of two entries. Returns the trusted
+    /// root of the log and `CA_CERTIFICATE`, and the bundle.
+    fn sign_bundle(content: &[u8], inclusion_proof: bool) -> (Value, Value) {
+        let rng = SystemRandom::new();
+        let key = STANDARD.decode(CERTIFICATE_KEY).unwrap();
+        let key = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &key, &rng).unwrap();
+        let log_key = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng).unwrap();
+        let log_key =
+            EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, log_key.as_ref(), &rng)
+                .unwrap();
+        // The P-256 subject public key info prefix, followed by the point
+        let mut log_spki = from_hex("3059301306072a8648ce3d020106082a8648ce3d030107034200");
+        log_spki.extend_from_slice(log_key.public_key().as_ref());
+        let log_id = digest::digest(&digest::SHA256, &log_spki).as_ref().to_vec();
+
+        let signature = sign(&key, content);
+        let pem = format!(
+            "-----BEGIN CERTIFICATE-----\n{}\n-----END CERTIFICATE-----\n",
+            CERTIFICATE
+        );
+        let body = json!({
+            "apiVersion": "0.0.1",
+            "kind": "hashedrekord",
+            "spec": {
+                "data": {
+                    "hash": {
+                        "algorithm": "sha256",
+                        "value": hex(digest::digest(&digest::SHA256, content).as_ref()),
+                    },
+                },
+                "signature": {
+                    "content": STANDARD.encode(&signature),
+                    "publicKey": { "content": STANDARD.encode(pem) },
+                },
+            },
+        });
+        let body = STANDARD.encode(body.to_string());
+        let mut entry = json!({
+            "logIndex": "1",
+            "logId": { "keyId": STANDARD.encode(&log_id) },
+            "integratedTime": INTEGRATED_TIME.to_string(),
+            "canonicalizedBody": body,
+        });
+        if inclusion_proof {
+            let leaf = |data: &[u8]| digest::digest(&digest::SHA256, &[&[0x00], data].concat());
+            let sibling = leaf(b"entry 0").as_ref().to_vec();
+            let body = STANDARD.decode(&body).unwrap();
+            let root_hash = [&[0x01], &sibling[..], leaf(&body).as_ref()].concat();
+            let root_hash = digest::digest(&digest::SHA256, &root_hash);
+            let note = format!("rekor.example.com - 1\n2\n{}\n", STANDARD.encode(root_hash));
+            let checkpoint = [&log_id[..4], &sign(&log_key, note.as_bytes())].concat();
+            entry["inclusionProof"] = json!({
+                "logIndex": "1",
+                "rootHash": STANDARD.encode(root_hash),
+                "treeSize": "2",
+                "hashes": [STANDARD.encode(sibling)],
+                "checkpoint": {
+                    "envelope": format!(
+                        "{}\n\u{2014} rekor.example.com {}\n",
+                        note,
+                        STANDARD.encode(checkpoint)
+                    ),
+                },
+            });
+        } else {
+            let payload = format!(
+                r#"{{"body":"{}","integratedTime":{},"logID":"{}","logIndex":1}}"#,
+                body,
+                INTEGRATED_TIME,
+                hex(&log_id)
+            );
+            entry["inclusionPromise"] = json!({
+                "signedEntryTimestamp": STANDARD.encode(sign(&log_key, payload.as_bytes())),
+            });
+        }
+
+        let root = json!({
+            "tlogs": [{
+                "publicKey": { "rawBytes": STANDARD.encode(&log_spki) },
+                "logId": { "keyId": STANDARD.encode(&log_id) },
+            }],
+            "certificateAuthorities": [{
+                "certChain": { "certificates": [{ "rawBytes": CA_CERTIFICATE }] },
+            }],
+        });
+        let bundle = json!({
+            "mediaType": "application/vnd.dev.sigstore.bundle.v0.3+json",
+            "verificationMaterial": {
+                "certificate": { "rawBytes": CERTIFICATE },
+                "tlogEntries": [entry],
+            },
+            "messageSignature": { "signature": STANDARD.encode(&signature) },
+        });
+        (root, bundle)
+    }
+
+    /// Verifies `bundle` for `content` with the trusted root `root`.
+    fn verify_bundle(
+        content: &[u8],
+        root: &Value,
+        bundle: &Value,
+        identity: &str,
+        issuer: &str,
+    ) -> Result<(), String> {
+        let path = std::env::temp_dir().join(format!(
+            "include_url_sigstore_root_{}_{}.json",
+            std::process::id(),
+            hex(digest::digest(&digest::SHA256, root.to_string().as_bytes()).as_ref())
+        ));
+        std::fs::write(&path, root.to_string()).unwrap();
+        let mut options = FetchOptions {
+            sigstore_identity: Some(identity.to_string()),
+            sigstore_issuer: Some(issuer.to_string()),
+            sigstore_root: Some(path.to_string_lossy().into_owned()),
+            ..Default::default()
+        };
+        let verified = verify(content, bundle.to_string().as_bytes(), &mut options);
+        std::fs::remove_file(&path).unwrap();
+        verified
+    }
+
+    /// Test that bundles are verified by the identity of the certificate, the content and
+    /// the transparency log entry, logged by a signed entry timestamp or an inclusion proof
+    #[test]
+    fn test_verify() {
+        let content = b"signed content";
+        let identity = "signer@example.com";
+        let issuer = "https://issuer.example.com";
+        for inclusion_proof in [false, true] {
+            let (root, bundle) = sign_bundle(content, inclusion_proof);
+            assert_eq!(
+                verify_bundle(content, &root, &bundle, identity, issuer),
+                Ok(())
+            );
+
+            assert_eq!(
+                verify_bundle(content, &root, &bundle, "other@example.com", issuer),
+                Err(
+                    "The Sigstore certificate is issued to `signer@example.com`, not to \
+                     `other@example.com`"
+                        .to_string()
+                )
+            );
+            assert_eq!(
+                verify_bundle(
+                    content,
+                    &root,
+                    &bundle,
+                    identity,
+                    "https://other.example.com"
+                ),
+                Err("The Sigstore certificate is issued for tokens of \
+                     `https://issuer.example.com`, not of `https://other.example.com`"
+                    .to_string())
+            );
+            assert_eq!(
+                verify_bundle(b"tampered content", &root, &bundle, identity, issuer),
+                Err("The signature of the Sigstore bundle does not match the content".to_string())
+            );
+        }
+
+        // An inclusion proof that does not lead to the root hash signed by the log
+        let (root, mut bundle) = sign_bundle(content, true);
+        let proof = &mut bundle["verificationMaterial"]["tlogEntries"][0]["inclusionProof"];
+        proof["hashes"][0] = json!(STANDARD.encode([0u8; 32]));
+        assert_eq!(
+            verify_bundle(content, &root, &bundle, identity, issuer),
+            Err(
+                "The transparency log entry of the Sigstore bundle is not signed by the log"
+                    .to_string()
+            )
+        );
+    }
+
+    fn from_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    /// Test that inclusion proofs are folded into the root hash of the log
+    #[test]
+    fn test_inclusion_root() {
+        let root = from_hex("98c97f0ba3175cd08b031dd084b9dc4e649b64d1a28e6ea694646503173ab587");
+        let proofs = [
+            (
+                5,
+                vec![
+                    "485335db7cfec965f15ff745fc625c41d5ea2646936930165828f73dd4b68854",
+                    "be15781b628a28414c1c8a11b86db8422fa1041215fe0d7c4496d23cda1e4142",
+                    "9799f307517ef517c2205df9b67762bf34756b20099fb7dfcce76bcebd273b2e",
+                ],
+            ),
+            (
+                6,
+                vec![
+                    "dd0379d83ac7f164e7eea30cdcefb57508254c48f766afccd3d976365e328ccc",
+                    "9799f307517ef517c2205df9b67762bf34756b20099fb7dfcce76bcebd273b2e",
+                ],
+            ),
+            (
+                0,
+                vec![
+                    "2dfb36c6f66cac361429cf46df868ab8242d3a6441f1099c8fb3f98ec5d108a4",
+                    "ddbde80fccdeb3198ef69b8e1f0934fbce296c6babfb738574a4f62972266793",
+                    "94afb8a2ca051c05458ea39dcd19b1bd68e7e33529f10b439d2a615a51f636e2",
+                ],
+            ),
+        ];
+        for (index, hashes) in proofs {
+            let hashes: Vec<_> = hashes.into_iter().map(from_hex).collect();
+            let leaf = format!("entry {}", index);
+            assert_eq!(
+                inclusion_root(leaf.as_bytes(), index, 7, &hashes),
+                Some(root.clone())
+            );
+            assert_ne!(
+                inclusion_root(b"entry 3", index, 7, &hashes),
+                Some(root.clone())
+            );
+            assert_eq!(
+                inclusion_root(leaf.as_bytes(), index, 8, &hashes[..1]),
+                None
+            );
+        }
+        assert_eq!(
+            number(&Value::from("1760000000"), "integratedTime"),
+            Ok(1760000000)
+        );
+        assert_eq!(number(&Value::from(42), "logIndex"), Ok(42));
+    }
+}
@@ -0,0 +1,148 @@
+//! Pages listed by sitemaps, for [`include_sitemap_url!`](crate::include_sitemap_url).
+//!
+//! The `<loc>` entries of a sitemap are read as they are, and the ones of a sitemap index
+//! are fetched in turn as nested sitemaps. Sitemaps are fetched through the cache, so
+//! offline builds read them from the cache, and pages are keyed by the path of their URL.
+
+use std::collections::HashSet;
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use url::Url;
+
+use crate::{dir, FetchOptions, Paginate};
+
+/// Elements whose content is kept as it is by [`minify`].
+const RAW_ELEMENTS: [&str; 4] = ["pre", "textarea", "script", "style"];
+
+/// Lists the page URLs of a sitemap, following the sitemaps of a sitemap index.
+pub(crate) fn urls(url_str: &str, options: &mut FetchOptions) -> Result<Vec<String>, String> {
+    let url_str = crate::resolve_env(url_str, options)?;
+    let max_pages = options.max_pages.unwrap_or(crate::MAX_PAGES);
+    let mut urls = Vec::new();
+    let mut visited = HashSet::new();
+    let mut pending = vec![url_str];
+    while let Some(sitemap) = pending.pop() {
+        if !visited.insert(sitemap.clone()) {
+            continue;
+        }
+        if visited.len() > max_pages {
+            return Err(format!(
+                "The sitemap index lists more than the limit of {} sitemaps",
+                max_pages
+            ));
+        }
+        let sitemap_options = FetchOptions {
+            paginate: Paginate::None,
+            text: true,
+            ..options.clone()
+        };
+        let content = dir::fetch_listing(&sitemap, sitemap_options, options)?;
+        let content = String::from_utf8_lossy(&content);
+        let locs = dir::xml_elements(&content, "loc");
+        if content.contains("<sitemapindex") {
+            pending.extend(locs.into_iter().rev().map(|loc| loc.trim().to_string()));
+        } else if content.contains("<urlset") {
+            urls.extend(locs.into_iter().map(|loc| loc.trim().to_string()));
+        } else {
+            return Err(format!("`{}` is not a sitemap", sitemap));
+        }
+    }
+    Ok(urls)
+}
+
+/// Returns the path of a page URL, its key in the generated map.
+pub(crate) fn page_path(url_str: &str) -> Result<String, String> {
+    let url = Url::parse(url_str).map_err(|e| format!("Invalid URL `{}`: {}", url_str, e))?;
+    Ok(url.path().to_string())
+}
+
+/// Collapses the runs of whitespace of an HTML page into single spaces and drops its
+/// comments, leaving the content of `pre`, `textarea`, `script` and `style` elements as
+/// it is.
+pub(crate) fn minify(html: &str) -> String {
+    let mut minified = String::with_capacity(html.len());
+    let mut rest = html;
+    while !rest.is_empty() {
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        let raw = RAW_ELEMENTS.iter().find(|name| starts_element(rest, name));
+        if let Some(name) = raw {
+            let close = format!("</{}", name);
+            let end = rest.to_ascii_lowercase()[1..]
+                .find(&close)
+                .map_or(rest.len(), |end| end + 1);
+            minified.push_str(&rest[..end]);
+            rest = &rest[end..];
+            continue;
+        }
+        let c = rest.chars().next().expect("the rest is not empty");
+        if c.is_whitespace() {
+            rest = rest.trim_start();
+            let separated = minified.is_empty() || minified.ends_with(' ');
+            if !separated && !rest.is_empty() {
+                minified.push(' ');
+            }
+            continue;
+        }
+        minified.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    minified
+}
+
+/// Tells whether `html` starts with the opening tag of the element `name`.
+fn starts_element(html: &str, name: &str) -> bool {
+    let Some(tag) = html.strip_prefix('<') else {
+        return false;
+    };
+    tag.len() > name.len()
+        && tag[..name.len()].eq_ignore_ascii_case(name)
+        && tag[name.len()..].starts_with(|c: char| c == '>' || c == '/' || c.is_whitespace())
+}
+
+/// Generates the `PAGES` constant and the `get` function of the pages of a sitemap, sorted
+/// by path.
+pub(crate) fn generate(pages: &[(String, String)]) -> TokenStream {
+    let paths = pages.iter().map(|(path, _)| path);
+    let contents = pages.iter().map(|(_, content)| content);
+    quote! {
+        /// The pages of the sitemap by the path of their URL, sorted by path.
+        pub const PAGES: &[(&str, &str)] = &[#((#paths, #contents)),*];
+
+        /// Returns the page at the path of a URL, such as `/guide/install`.
+        pub fn get(path: &str) -> ::core::option::Option<&'static str> {
+            PAGES
+                .binary_search_by(|(key, _)| (*key).cmp(path))
+                .ok()
+                .map(|index| PAGES[index].1)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that whitespace and comments are minified outside of raw elements
+    #[test]
+    fn test_minify() {
+        let html = "<html>\n  <body>\n    <!-- nav -->\n    <p>Some   <b>bold</b>\n text</p>\n\
+                    <PRE>  keep\n  this </PRE>\n  <pretty>a  b</pretty>\n</body>\n</html>\n";
+        assert_eq!(
+            minify(html),
+            "<html> <body> <p>Some <b>bold</b> text</p> <PRE>  keep\n  this </PRE> \
+             <pretty>a b</pretty> </body> </html>"
+        );
+        assert_eq!(
+            minify("<script>if (a  <b) {}</script>"),
+            "<script>if (a  <b) {}</script>"
+        );
+        assert_eq!(
+            page_path("https://docs.example.com/guide/install?lang=en").unwrap(),
+            "/guide/install"
+        );
+    }
+}
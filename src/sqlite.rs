@@ -0,0 +1,219 @@
+//! Query evaluation for the SQLite databases included by `include_sqlite_url!`.
+//!
+//! The database is opened read-only from the cache and the result of the query becomes a
+//! struct with one field per result column and a constant slice of its rows. As SQLite
+//! columns are dynamically typed, field types follow the values the query returns and
+//! fall back to the declared type of the column when it returns none.
+
+use std::path::Path;
+
+use proc_macro2::{Ident, Literal, TokenStream};
+use quote::{quote, ToTokens as _};
+use rusqlite::{types::Value, Connection, OpenFlags};
+
+use crate::{float_literal, snake_ident};
+
+/// The Rust type of a result column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColumnType {
+    Integer,
+    Real,
+    Text,
+    Blob,
+}
+
+impl ColumnType {
+    /// Returns the type of a column from its declared type, following the SQLite rules for
+    /// column affinity.
+    fn of_declared(declared: Option<&str>) -> ColumnType {
+        let declared = declared.unwrap_or_default().to_ascii_uppercase();
+        if declared.contains("INT") {
+            ColumnType::Integer
+        } else if ["CHAR", "CLOB", "TEXT"]
+            .iter()
+            .any(|name| declared.contains(name))
+        {
+            ColumnType::Text
+        } else if ["REAL", "FLOA", "DOUB"]
+            .iter()
+            .any(|name| declared.contains(name))
+        {
+            ColumnType::Real
+        } else {
+            ColumnType::Blob
+        }
+    }
+
+    fn rust_type(self) -> TokenStream {
+        match self {
+            ColumnType::Integer => quote! { i64 },
+            ColumnType::Real => quote! { f64 },
+            ColumnType::Text => quote! { &'static str },
+            ColumnType::Blob => quote! { &'static [u8] },
+        }
+    }
+}
+
+/// Opens the cached database and checks its integrity.
+fn open(path: &Path) -> Result<Connection, String> {
+    let connection = Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .map_err(|e| format!("Failed to open SQLite database: {}", e))?;
+    let status: String = connection
+        .query_row("PRAGMA quick_check", [], |row| row.get(0))
+        .map_err(|e| format!("Invalid SQLite database from URL: {}", e))?;
+    match status.as_str() {
+        "ok" => Ok(connection),
+        _ => Err(format!("Invalid SQLite database from URL: {}", status)),
+    }
+}
+
+/// Checks that the cached file is an intact SQLite database.
+pub(crate) fn validate(path: &Path) -> Result<(), String> {
+    open(path).map(|_| ())
+}
+
+/// Runs `query` against the cached database and generates the `row_type` struct and a `ROWS`
+/// constant holding the result.
+///
+/// Fields are named after the result columns in `snake_case`, and columns returning a
+/// `NULL` become `Option`s. Integer and real values in one column are widened to `f64`.
+pub(crate) fn generate(path: &Path, query: &str, row_type: &Ident) -> Result<TokenStream, String> {
+    let connection = open(path)?;
+    let mut statement = connection
+        .prepare(query)
+        .map_err(|e| format!("Invalid SQLite query: {}", e))?;
+    let columns: Vec<(String, ColumnType)> = statement
+        .columns()
+        .iter()
+        .map(|column| {
+            (
+                column.name().to_string(),
+                ColumnType::of_declared(column.decl_type()),
+            )
+        })
+        .collect();
+
+    let mut values = Vec::new();
+    let mut rows = statement
+        .query([])
+        .map_err(|e| format!("Failed to run SQLite query: {}", e))?;
+    while let Some(row) = rows
+        .next()
+        .map_err(|e| format!("Failed to run SQLite query: {}", e))?
+    {
+        let row = (0..columns.len())
+            .map(|index| row.get::<_, Value>(index))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to run SQLite query: {}", e))?;
+        values.push(row);
+    }
+
+    let mut names: Vec<Ident> = Vec::new();
+    let mut types = Vec::new();
+    for (index, (name, declared)) in columns.iter().enumerate() {
+        let field = snake_ident(name)
+            .filter(|field| !names.contains(field))
+            .ok_or_else(|| format!("The column `{}` does not map to a unique field name", name))?;
+        let mut ty = None;
+        let mut nullable = false;
+        for row in &values {
+            let value_ty = match row[index] {
+                Value::Null => {
+                    nullable = true;
+                    continue;
+                }
+                Value::Integer(_) => ColumnType::Integer,
+                Value::Real(_) => ColumnType::Real,
+                Value::Text(_) => ColumnType::Text,
+                Value::Blob(_) => ColumnType::Blob,
+            };
+            ty = Some(match (ty, value_ty) {
+                (None, value_ty) => value_ty,
+                (Some(ty), value_ty) if ty == value_ty => ty,
+                (Some(ColumnType::Integer | ColumnType::Real), ColumnType::Integer)
+                | (Some(ColumnType::Integer), ColumnType::Real) => ColumnType::Real,
+                _ => return Err(format!("The column `{}` has mixed types", name)),
+            });
+        }
+        names.push(field);
+        types.push((ty.unwrap_or(*declared), nullable));
+    }
+
+    let mut literals = Vec::new();
+    for row in &values {
+        let fields = row.iter().zip(&types).map(|(value, (ty, nullable))| {
+            let literal = match (value, ty) {
+                (Value::Null, _) => return quote! { ::core::option::Option::None },
+                (Value::Integer(value), ColumnType::Integer) => {
+                    Literal::i64_unsuffixed(*value).into_token_stream()
+                }
+                (Value::Integer(value), _) => float_literal(*value as f64, quote! { f64 }),
+                (Value::Real(value), _) => float_literal(*value, quote! { f64 }),
+                (Value::Text(value), _) => quote! { #value },
+                (Value::Blob(value), _) => Literal::byte_string(value).into_token_stream(),
+            };
+            match nullable {
+                true => quote! { ::core::option::Option::Some(#literal) },
+                false => literal,
+            }
+        });
+        literals.push(quote! { #row_type { #(#names: #fields),* } });
+    }
+
+    let types = types.iter().map(|(ty, nullable)| {
+        let ty = ty.rust_type();
+        match nullable {
+            true => quote! { ::core::option::Option<#ty> },
+            false => ty,
+        }
+    });
+    Ok(quote! {
+        /// A row returned by the query.
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        pub struct #row_type {
+            #(pub #names: #types,)*
+        }
+
+        /// The rows returned by the query.
+        pub const ROWS: &[#row_type] = &[#(#literals),*];
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use quote::format_ident;
+
+    use super::*;
+
+    /// Test that query results become typed rows and invalid databases are rejected
+    #[test]
+    fn test_generate() {
+        let path =
+            std::env::temp_dir().join(format!("include_url_sqlite_{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        Connection::open(&path)
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE airports (code TEXT NOT NULL, elevation INTEGER, area REAL);
+                 INSERT INTO airports VALUES ('PRG', 380, 11.5), ('AMS', NULL, 27);",
+            )
+            .unwrap();
+
+        let code = generate(&path, "SELECT * FROM airports", &format_ident!("Airport"))
+            .unwrap()
+            .to_string();
+        assert!(code.contains("pub code : & 'static str"));
+        assert!(code.contains("pub elevation : :: core :: option :: Option < i64 >"));
+        assert!(code.contains("pub area : f64"));
+        assert!(code.contains("Airport { code : \"AMS\""));
+        assert!(generate(&path, "SELECT * FROM missing", &format_ident!("Row")).is_err());
+        validate(&path).unwrap();
+
+        std::fs::write(&path, b"not a database").unwrap();
+        assert!(validate(&path).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,391 @@
+//! Validation and optimization of the SVG images included by `include_svg_url!`.
+//!
+//! Optimization is a set of lossless cleanups in the spirit of SVGO: the prolog, comments,
+//! `<metadata>` and the elements and attributes of editors such as Inkscape are removed,
+//! whitespace between elements and in path data is collapsed, groups without attributes
+//! are unwrapped and group transforms are moved onto their only child. Attribute values and
+//! text are copied as written, so entities and escapes are preserved.
+
+use quick_xml::events::{BytesStart, Event};
+
+/// The namespaces of editor specific elements and attributes, which renderers ignore.
+const EDITOR_NAMESPACES: &[&str] = &[
+    "http://www.inkscape.org/namespaces/inkscape",
+    "http://sodipodi.sourceforge.net/DTD/sodipodi-0.dtd",
+    "http://www.bohemiancoding.com/sketch/ns",
+    "http://www.serif.com/",
+    "http://ns.adobe.com/",
+];
+
+/// Elements whose whitespace-only text is rendered.
+const TEXT_ELEMENTS: &[&str] = &["text", "tspan", "textPath"];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Element(Element),
+    /// Text as written, with its entities and character references.
+    Text(String),
+    CData(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Element {
+    name: String,
+    /// The attributes with their values as written.
+    attributes: Vec<(String, String)>,
+    children: Vec<Node>,
+}
+
+impl Element {
+    fn from_start(start: &BytesStart) -> Result<Self, String> {
+        let attributes = start
+            .attributes()
+            .map(|attribute| {
+                let attribute = attribute.map_err(|e| e.to_string())?;
+                Ok((
+                    String::from_utf8_lossy(attribute.key.as_ref()).into_owned(),
+                    String::from_utf8_lossy(&attribute.value).into_owned(),
+                ))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Element {
+            name: String::from_utf8_lossy(start.name().as_ref()).into_owned(),
+            attributes,
+            children: Vec::new(),
+        })
+    }
+
+    fn local_name(&self) -> &str {
+        local_name(&self.name)
+    }
+
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+fn local_name(name: &str) -> &str {
+    name.rsplit_once(':').map_or(name, |(_, local)| local)
+}
+
+fn prefix(name: &str) -> Option<&str> {
+    name.split_once(':').map(|(prefix, _)| prefix)
+}
+
+/// Parses an SVG document into the tree of its root `<svg>` element, leaving out comments,
+/// processing instructions and the prolog.
+fn parse(content: &str) -> Result<Element, String> {
+    let mut reader = quick_xml::Reader::from_str(content);
+    let mut stack: Vec<Element> = Vec::new();
+    let mut root = None;
+    loop {
+        let event = reader.read_event().map_err(|e| {
+            format!(
+                "Invalid SVG image from URL at byte {}: {}",
+                reader.error_position(),
+                e
+            )
+        });
+        let error = |e: String| {
+            format!(
+                "Invalid SVG image from URL at byte {}: {}",
+                reader.buffer_position(),
+                e
+            )
+        };
+        let node = match event? {
+            Event::Start(start) => {
+                stack.push(Element::from_start(&start).map_err(error)?);
+                continue;
+            }
+            Event::Empty(start) => Node::Element(Element::from_start(&start).map_err(error)?),
+            Event::End(_) => Node::Element(stack.pop().expect("end tags are matched")),
+            Event::Text(text) => Node::Text(String::from_utf8_lossy(&text).into_owned()),
+            Event::GeneralRef(reference) => {
+                Node::Text(format!("&{};", String::from_utf8_lossy(&reference)))
+            }
+            Event::CData(data) => Node::CData(String::from_utf8_lossy(&data).into_owned()),
+            Event::Eof => break,
+            _ => continue,
+        };
+        match (stack.last_mut(), node) {
+            (Some(parent), node) => parent.children.push(node),
+            (None, Node::Element(element)) if root.is_none() => root = Some(element),
+            (None, Node::Element(_)) => {
+                return Err("Invalid SVG image from URL: multiple root elements".to_string())
+            }
+            (None, _) => {}
+        }
+    }
+    match root {
+        _ if !stack.is_empty() => Err("Invalid SVG image from URL: unclosed element".to_string()),
+        Some(root) if root.local_name() == "svg" => Ok(root),
+        Some(root) => Err(format!(
+            "Invalid SVG image from URL: the root element is `<{}>` instead of `<svg>`",
+            root.name
+        )),
+        None => Err("Invalid SVG image from URL: no root element".to_string()),
+    }
+}
+
+/// Checks that `content` is a well-formed XML document with an `<svg>` root element.
+pub(crate) fn validate(content: &str) -> Result<(), String> {
+    parse(content).map(|_| ())
+}
+
+/// Optimizes an SVG document, returning the serialized `<svg>` element.
+pub(crate) fn optimize(content: &str) -> Result<String, String> {
+    let mut root = parse(content)?;
+    let editor_prefixes: Vec<String> = root
+        .attributes
+        .iter()
+        .filter_map(|(key, value)| {
+            let prefix = key.strip_prefix("xmlns:")?;
+            EDITOR_NAMESPACES
+                .iter()
+                .any(|namespace| value.starts_with(namespace))
+                .then(|| prefix.to_string())
+        })
+        .collect();
+    strip(&mut root, &editor_prefixes, false);
+    collapse_groups(&mut root);
+    remove_unused_namespaces(&mut root);
+    let mut output = String::with_capacity(content.len());
+    serialize(&root, &mut output);
+    Ok(output)
+}
+
+/// Removes metadata, editor data and insignificant whitespace, and collapses the
+/// whitespace of path data.
+fn strip(element: &mut Element, editor_prefixes: &[String], preserve: bool) {
+    let is_editor =
+        |name: &str| prefix(name).is_some_and(|p| editor_prefixes.iter().any(|e| e == p));
+    element.attributes.retain(|(key, _)| {
+        !is_editor(key)
+            && !key
+                .strip_prefix("xmlns:")
+                .is_some_and(|p| editor_prefixes.iter().any(|e| e == p))
+    });
+    for (key, value) in &mut element.attributes {
+        if key == "d" || key == "points" {
+            *value = minify_path(value);
+        }
+    }
+    let preserve = match element.attribute("xml:space") {
+        Some(space) => space == "preserve",
+        None => preserve || TEXT_ELEMENTS.contains(&element.local_name()),
+    };
+    element.children.retain_mut(|child| match child {
+        Node::Element(child) => {
+            if child.local_name() == "metadata" || is_editor(&child.name) {
+                return false;
+            }
+            strip(child, editor_prefixes, preserve);
+            true
+        }
+        Node::Text(text) => preserve || !text.trim().is_empty(),
+        Node::CData(_) => true,
+    });
+}
+
+/// Collapses the whitespace and commas separating the numbers and commands of path data or
+/// a list of points, keeping a space only between two numbers.
+fn minify_path(value: &str) -> String {
+    let is_command = |c: char| c.is_ascii_alphabetic() && c != 'e' && c != 'E';
+    let mut minified = String::with_capacity(value.len());
+    let mut separated = false;
+    for c in value.chars() {
+        if c.is_ascii_whitespace() || c == ',' {
+            separated = true;
+            continue;
+        }
+        let follows_command = minified.chars().next_back().is_some_and(is_command);
+        if separated && !minified.is_empty() && !follows_command && !is_command(c) && c != '-' {
+            minified.push(' ');
+        }
+        separated = false;
+        minified.push(c);
+    }
+    minified
+}
+
+/// Unwraps groups without attributes and moves the transform of a group onto its only
+/// child, then removes empty groups and definitions.
+///
+/// The children of a `<switch>` are left alone, as it renders only one of them.
+fn collapse_groups(element: &mut Element) {
+    let switch = element.local_name() == "switch";
+    let children = std::mem::take(&mut element.children);
+    for child in children {
+        let Node::Element(mut child) = child else {
+            element.children.push(child);
+            continue;
+        };
+        collapse_groups(&mut child);
+        if switch || child.local_name() != "g" {
+            if !(child.local_name() == "defs" && child.children.is_empty()) {
+                element.children.push(Node::Element(child));
+            }
+            continue;
+        }
+        let has_id = child.attribute("id").is_some();
+        let transform = match child.attributes.as_slice() {
+            [(key, transform)] if key == "transform" => Some(transform.clone()),
+            _ => None,
+        };
+        match (
+            child.attributes.is_empty(),
+            transform,
+            child.children.as_mut_slice(),
+        ) {
+            (true, _, _) => element.children.append(&mut child.children),
+            (false, _, []) if !has_id => {}
+            (false, Some(transform), [Node::Element(only)])
+                if only.attribute("id").is_none()
+                    && !only.local_name().starts_with("animate")
+                    && only.local_name() != "set" =>
+            {
+                match only
+                    .attributes
+                    .iter_mut()
+                    .find(|(key, _)| key == "transform")
+                {
+                    Some((_, inner)) => *inner = format!("{} {}", transform, inner),
+                    None => only.attributes.push(("transform".to_string(), transform)),
+                }
+                element.children.append(&mut child.children);
+            }
+            _ => element.children.push(Node::Element(child)),
+        }
+    }
+}
+
+/// Removes the `xmlns:` declarations of prefixes no element or attribute uses.
+fn remove_unused_namespaces(root: &mut Element) {
+    fn collect<'a>(element: &'a Element, used: &mut Vec<&'a str>) {
+        used.extend(prefix(&element.name));
+        used.extend(
+            element
+                .attributes
+                .iter()
+                .filter(|(key, _)| !key.starts_with("xmlns"))
+                .filter_map(|(key, _)| prefix(key)),
+        );
+        for child in &element.children {
+            if let Node::Element(child) = child {
+                collect(child, used);
+            }
+        }
+    }
+
+    let mut used = Vec::new();
+    collect(root, &mut used);
+    let used: Vec<String> = used.into_iter().map(str::to_string).collect();
+    root.attributes.retain(|(key, _)| {
+        key.strip_prefix("xmlns:")
+            .is_none_or(|prefix| used.iter().any(|used| used == prefix))
+    });
+}
+
+fn serialize(element: &Element, output: &mut String) {
+    output.push('<');
+    output.push_str(&element.name);
+    for (key, value) in &element.attributes {
+        // Values are copied as written, so one that contains a double quote was written
+        // between single quotes
+        let quote = if value.contains('"') { '\'' } else { '"' };
+        output.push(' ');
+        output.push_str(key);
+        output.push('=');
+        output.push(quote);
+        output.push_str(value);
+        output.push(quote);
+    }
+    if element.children.is_empty() {
+        output.push_str("/>");
+        return;
+    }
+    output.push('>');
+    for child in &element.children {
+        match child {
+            Node::Element(child) => serialize(child, output),
+            Node::Text(text) => output.push_str(text),
+            Node::CData(data) => {
+                output.push_str("<![CDATA[");
+                output.push_str(data);
+                output.push_str("]]>");
+            }
+        }
+    }
+    output.push_str("</");
+    output.push_str(&element.name);
+    output.push('>');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that editor data is stripped and groups are collapsed
+    #[test]
+    fn test_optimize() {
+        let svg = r##"<?xml version="1.0" encoding="UTF-8"?>
+            <!-- Created with Inkscape -->
+            <svg xmlns="http://www.w3.org/2000/svg"
+                 xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape"
+                 xmlns:sodipodi="http://sodipodi.sourceforge.net/DTD/sodipodi-0.dtd"
+                 xmlns:dc="http://purl.org/dc/elements/1.1/"
+                 viewBox="0 0 24 24" inkscape:version="1.3">
+              <sodipodi:namedview pagecolor="#ffffff"/>
+              <metadata><dc:title>Icon</dc:title></metadata>
+              <defs/>
+              <g>
+                <g transform="translate(1, 2)">
+                  <path transform="scale(2)" d="M 10,20 L 30 , -40 Z" inkscape:label="a"/>
+                </g>
+              </g>
+              <text x="0"> A &amp; B <tspan> </tspan></text>
+            </svg>"##;
+        assert_eq!(
+            optimize(svg).unwrap(),
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 24 24\">\
+             <path transform=\"translate(1, 2) scale(2)\" d=\"M10 20L30-40Z\"/>\
+             <text x=\"0\"> A &amp; B <tspan> </tspan></text></svg>"
+        );
+    }
+
+    /// Test that path data keeps the separators between numbers
+    #[test]
+    fn test_minify_path() {
+        assert_eq!(minify_path(" M 1.5e-3,2 l -1 .5 "), "M1.5e-3 2l-1 .5");
+        assert_eq!(minify_path("a1 1 0 0 0 2 2"), "a1 1 0 0 0 2 2");
+        assert_eq!(minify_path("0,0 10,0 10,10"), "0 0 10 0 10 10");
+    }
+
+    /// Test that groups with an id or several children are kept
+    #[test]
+    fn test_collapse_groups() {
+        let svg = r#"<svg><g id="a" transform="rotate(45)"><path d="M0 0"/></g><g transform="scale(2)"><path id="b" d="M0 0"/></g><g transform="scale(2)"><rect/><rect/></g><g id="empty"/><g fill="red"/></svg>"#;
+        assert_eq!(
+            optimize(svg).unwrap(),
+            "<svg><g id=\"a\" transform=\"rotate(45)\"><path d=\"M0 0\"/></g>\
+             <g transform=\"scale(2)\"><path id=\"b\" d=\"M0 0\"/></g>\
+             <g transform=\"scale(2)\"><rect/><rect/></g><g id=\"empty\"/></svg>"
+        );
+        let svg = "<svg><switch><g><rect/></g><g><circle/></g></switch></svg>";
+        assert_eq!(optimize(svg).unwrap(), svg);
+    }
+
+    /// Test that documents without an `<svg>` root are rejected
+    #[test]
+    fn test_validate() {
+        assert!(validate("<svg xmlns='http://www.w3.org/2000/svg'/>").is_ok());
+        assert!(validate("<svg:svg xmlns:svg='http://www.w3.org/2000/svg'/>").is_ok());
+        assert!(validate("<html><body/></html>").is_err());
+        assert!(validate("<svg><g></svg>").is_err());
+        assert!(validate("").is_err());
+    }
+}
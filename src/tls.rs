@@ -0,0 +1,115 @@
+//! TLS settings of the HTTP client.
+//!
+//! Servers with certificates issued by a private CA are trusted by adding the certificate of
+//! the CA to the roots of the client, and servers requiring mutual TLS are sent a client
+//! certificate. Certificates are given as PEM, either inline, which is handy for a CI
+//! secret held in an environment variable, or as the path to a file, relative to the
+//! manifest directory of the crate being compiled.
+//!
+//! Client certificates in PKCS#12 archives can only be loaded by the native TLS backend,
+//! enabled by the `pkcs12` feature, which the client then switches to. Every other client
+//! uses `rustls`.
+//!
+//! Verification stays enabled. A self-signed certificate can only be trusted if it is the
+//! certificate of a CA signing the one of the server, as the verifier of `rustls` rejects
+//! a CA certificate presented by a server as well as a self-signed server certificate.
+
+use std::{env, path::Path};
+
+use reqwest::{blocking::ClientBuilder, Certificate, Identity};
+
+use crate::error_chain;
+
+/// Reads the PEM given inline, or the content of a file, with a description of where it
+/// came from.
+fn read_source(value: &str) -> Result<(Vec<u8>, String), String> {
+    if value.contains("-----BEGIN") {
+        return Ok((value.as_bytes().to_vec(), "inline PEM".to_string()));
+    }
+    let path = match env::var_os("CARGO_MANIFEST_DIR") {
+        Some(dir) => Path::new(&dir).join(value),
+        None => Path::new(value).to_path_buf(),
+    };
+    std::fs::read(&path)
+        .map(|pem| (pem, format!("`{}`", path.display())))
+        .map_err(|e| format!("Failed to read `{}`: {}", path.display(), e))
+}
+
+/// Parses the extra root certificates, every value holding one or more PEM certificates.
+pub(crate) fn root_certificates(values: &[String]) -> Result<Vec<Certificate>, String> {
+    let mut certificates = Vec::new();
+    for value in values {
+        let (pem, source) = read_source(value)?;
+        let bundle = Certificate::from_pem_bundle(&pem)
+            .map_err(|e| format!("Invalid CA certificate in {}: {}", source, error_chain(&e)))?;
+        if bundle.is_empty() {
+            return Err(format!("No CA certificate found in {}", source));
+        }
+        certificates.extend(bundle);
+    }
+    Ok(certificates)
+}
+
+/// Sends a client certificate, given as PEM holding the certificate chain and the private
+/// key, or as a PKCS#12 archive opened with `password`, switching to the native TLS
+/// backend for the latter.
+#[cfg_attr(not(feature = "pkcs12"), allow(unused_variables))]
+pub(crate) fn client_identity(
+    builder: ClientBuilder,
+    value: &str,
+    password: Option<&str>,
+) -> Result<ClientBuilder, String> {
+    let (content, source) = read_source(value)?;
+    if content.windows(10).any(|window| window == b"-----BEGIN") {
+        return Identity::from_pem(&content)
+            .map(|identity| builder.identity(identity))
+            .map_err(|e| {
+                format!(
+                    "Invalid client certificate in {}: {}",
+                    source,
+                    error_chain(&e)
+                )
+            });
+    }
+    #[cfg(feature = "pkcs12")]
+    return Identity::from_pkcs12_der(&content, password.unwrap_or_default())
+        .map(|identity| builder.use_native_tls().identity(identity))
+        .map_err(|e| {
+            format!(
+                "Invalid PKCS#12 client certificate in {}: {}",
+                source,
+                error_chain(&e)
+            )
+        });
+    #[cfg(not(feature = "pkcs12"))]
+    Err(format!(
+        "The client certificate in {} is not PEM, PKCS#12 archives require the `pkcs12` feature",
+        source
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that missing and malformed certificates are reported
+    #[test]
+    fn test_root_certificates() {
+        assert!(root_certificates(&[]).unwrap().is_empty());
+        let err = root_certificates(&["missing/ca.pem".to_string()]).unwrap_err();
+        assert!(err.contains("missing/ca.pem"), "{}", err);
+        let err = root_certificates(&["-----BEGIN PUBLIC KEY-----\n".to_string()]).unwrap_err();
+        assert!(err.contains("inline PEM"), "{}", err);
+    }
+
+    /// Test that a client certificate without its private key is rejected
+    #[test]
+    fn test_client_identity() {
+        let builder = || ClientBuilder::new();
+        let err = client_identity(builder(), "-----BEGIN CERTIFICATE-----\n", None)
+            .err()
+            .unwrap();
+        assert!(err.contains("inline PEM"), "{}", err);
+        assert!(client_identity(builder(), "missing/client.p12", Some("secret")).is_err());
+    }
+}
@@ -0,0 +1,270 @@
+//! Struct inference for JSON samples included by `include_json_url_typed!`.
+//!
+//! The shape of a sample is inferred the way `json_typegen` does: arrays unify the shapes
+//! of their elements, keys missing from some objects and `null`s become `Option`s, and
+//! values that cannot be unified fall back to their JSON text. The generated types borrow
+//! `'static` data so the sample itself can be emitted as a constant.
+
+use proc_macro2::{Literal, TokenStream};
+use quote::{format_ident, quote};
+use serde_json::Value;
+
+use crate::{const_ident, snake_ident};
+
+/// The inferred shape of a JSON value.
+#[derive(Debug, Clone, PartialEq)]
+enum Shape {
+    /// No value seen yet, such as the elements of an empty array.
+    Unknown,
+    /// Values whose shapes conflict, kept as JSON text.
+    Any,
+    Bool,
+    Int,
+    Float,
+    Str,
+    Optional(Box<Shape>),
+    Array(Box<Shape>),
+    Object(Vec<(String, Shape)>),
+}
+
+impl Shape {
+    fn of(value: &Value) -> Shape {
+        match value {
+            Value::Null => Shape::Unknown.optional(),
+            Value::Bool(_) => Shape::Bool,
+            Value::Number(number) if number.is_i64() => Shape::Int,
+            Value::Number(_) => Shape::Float,
+            Value::String(_) => Shape::Str,
+            Value::Array(values) => Shape::Array(Box::new(
+                values
+                    .iter()
+                    .map(Shape::of)
+                    .fold(Shape::Unknown, Shape::unify),
+            )),
+            Value::Object(map) => Shape::Object(
+                map.iter()
+                    .map(|(key, value)| (key.clone(), Shape::of(value)))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Makes the shape optional, unless it already is.
+    fn optional(self) -> Shape {
+        match self {
+            Shape::Optional(_) => self,
+            shape => Shape::Optional(Box::new(shape)),
+        }
+    }
+
+    /// Combines two shapes into one that describes values of either.
+    fn unify(self, other: Shape) -> Shape {
+        match (self, other) {
+            (Shape::Unknown, shape) | (shape, Shape::Unknown) => shape,
+            (Shape::Optional(a), Shape::Optional(b)) => Shape::Optional(Box::new(a.unify(*b))),
+            (Shape::Optional(a), b) | (b, Shape::Optional(a)) => {
+                Shape::Optional(Box::new(a.unify(b)))
+            }
+            (Shape::Int, Shape::Float) | (Shape::Float, Shape::Int) => Shape::Float,
+            (Shape::Array(a), Shape::Array(b)) => Shape::Array(Box::new(a.unify(*b))),
+            (Shape::Object(mut a), Shape::Object(b)) => {
+                for (key, shape) in &mut a {
+                    let other = b
+                        .iter()
+                        .find(|(other, _)| other == key)
+                        .map_or(Shape::Unknown.optional(), |(_, shape)| shape.clone());
+                    *shape = std::mem::replace(shape, Shape::Unknown).unify(other);
+                }
+                for (key, shape) in b {
+                    if !a.iter().any(|(other, _)| *other == key) {
+                        a.push((key, shape.optional()));
+                    }
+                }
+                Shape::Object(a)
+            }
+            (a, b) if a == b => a,
+            _ => Shape::Any,
+        }
+    }
+}
+
+/// Generates the structs describing `value` and a constant holding it.
+///
+/// The root must be an object or an array of objects. The root struct is called `name`,
+/// nested structs are named after their path, and the constant is `name` in
+/// `SCREAMING_SNAKE_CASE`.
+pub(crate) fn generate(value: &Value, name: &str) -> Result<TokenStream, String> {
+    let shape = Shape::of(value);
+    let root = match &shape {
+        Shape::Object(_) => &shape,
+        Shape::Array(element) if matches!(**element, Shape::Object(_)) => element,
+        _ => return Err("The JSON sample must be an object or an array of objects".to_string()),
+    };
+    let mut generator = Generator::default();
+    let ty = generator.rust_type(root, name)?;
+    let ty = match &shape {
+        Shape::Array(_) => quote! { &'static [#ty] },
+        _ => ty,
+    };
+    let expr = generator.value(value, &shape, name)?;
+    let constant =
+        const_ident(name).ok_or_else(|| format!("`{}` is not a valid type name", name))?;
+    let structs = generator.structs;
+    Ok(quote! {
+        #(#structs)*
+        /// The JSON sample the types were inferred from.
+        pub const #constant: #ty = #expr;
+    })
+}
+
+#[derive(Default)]
+struct Generator {
+    structs: Vec<TokenStream>,
+}
+
+impl Generator {
+    /// Returns the Rust type of a shape, generating the structs it needs.
+    fn rust_type(&mut self, shape: &Shape, path: &str) -> Result<TokenStream, String> {
+        Ok(match shape {
+            Shape::Unknown | Shape::Any | Shape::Str => quote! { &'static str },
+            Shape::Bool => quote! { bool },
+            Shape::Int => quote! { i64 },
+            Shape::Float => quote! { f64 },
+            Shape::Optional(shape) => {
+                let ty = self.rust_type(shape, path)?;
+                quote! { ::core::option::Option<#ty> }
+            }
+            Shape::Array(shape) => {
+                let ty = self.rust_type(shape, path)?;
+                quote! { &'static [#ty] }
+            }
+            Shape::Object(fields) => {
+                let name = format_ident!("{}", path);
+                let mut names = Vec::new();
+                let mut types = Vec::new();
+                for (key, shape) in fields {
+                    let field = snake_ident(key)
+                        .filter(|field| !names.contains(field))
+                        .ok_or_else(|| {
+                            format!("The key `{}` does not map to a unique field name", key)
+                        })?;
+                    types.push(self.rust_type(shape, &format!("{}{}", path, pascal_case(key)))?);
+                    names.push(field);
+                }
+                self.structs.push(quote! {
+                    #[derive(Debug, Clone, Copy, PartialEq)]
+                    pub struct #name {
+                        #(pub #names: #types,)*
+                    }
+                });
+                quote! { #name }
+            }
+        })
+    }
+
+    /// Returns an expression building `value`, which has the given shape.
+    fn value(&self, value: &Value, shape: &Shape, path: &str) -> Result<TokenStream, String> {
+        Ok(match (shape, value) {
+            (Shape::Optional(_), Value::Null) => quote! { ::core::option::Option::None },
+            (Shape::Optional(shape), value) => {
+                let value = self.value(value, shape, path)?;
+                quote! { ::core::option::Option::Some(#value) }
+            }
+            (Shape::Bool, Value::Bool(value)) => quote! { #value },
+            (Shape::Int, Value::Number(number)) => {
+                let number = Literal::i64_unsuffixed(number.as_i64().unwrap_or_default());
+                quote! { #number }
+            }
+            (Shape::Float, Value::Number(number)) => {
+                let number = Literal::f64_suffixed(number.as_f64().unwrap_or_default());
+                quote! { #number }
+            }
+            (Shape::Str, Value::String(value)) => quote! { #value },
+            (Shape::Array(shape), Value::Array(values)) => {
+                let values = values
+                    .iter()
+                    .map(|value| self.value(value, shape, path))
+                    .collect::<Result<Vec<_>, _>>()?;
+                quote! { &[#(#values),*] }
+            }
+            (Shape::Object(fields), Value::Object(map)) => {
+                let name = format_ident!("{}", path);
+                let mut values = Vec::new();
+                for (key, shape) in fields {
+                    let path = format!("{}{}", path, pascal_case(key));
+                    values.push(match map.get(key) {
+                        Some(value) => self.value(value, shape, &path)?,
+                        None => quote! { ::core::option::Option::None },
+                    });
+                }
+                let names = fields.iter().filter_map(|(key, _)| snake_ident(key));
+                quote! { #name { #(#names: #values),* } }
+            }
+            (_, value) => {
+                let json = value.to_string();
+                quote! { #json }
+            }
+        })
+    }
+}
+
+/// Converts a JSON key into a `PascalCase` struct name segment.
+pub(crate) fn pascal_case(key: &str) -> String {
+    let Some(ident) = const_ident(key) else {
+        return String::new();
+    };
+    ident
+        .to_string()
+        .trim_start_matches('_')
+        .split('_')
+        .filter(|word| !word.is_empty())
+        .map(|word| word[..1].to_string() + &word[1..].to_lowercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that array elements are unified into optional and widened fields
+    #[test]
+    fn test_unify() {
+        let value: Value = serde_json::from_str(
+            r#"[{"id": 1, "score": 1, "tag": "a"}, {"id": 2, "score": 2.5, "extra": null}]"#,
+        )
+        .unwrap();
+        let Shape::Array(element) = Shape::of(&value) else {
+            panic!("expected an array");
+        };
+        let Shape::Object(fields) = *element else {
+            panic!("expected an object");
+        };
+        let field = |name: &str| {
+            fields
+                .iter()
+                .find(|(key, _)| key == name)
+                .unwrap()
+                .1
+                .clone()
+        };
+        assert_eq!(field("id"), Shape::Int);
+        assert_eq!(field("score"), Shape::Float);
+        assert_eq!(field("tag"), Shape::Optional(Box::new(Shape::Str)));
+        assert_eq!(field("extra"), Shape::Unknown.optional());
+    }
+
+    /// Test that nested structs and the constant are generated
+    #[test]
+    fn test_generate() {
+        let value: Value =
+            serde_json::from_str(r#"{"userId": 1, "author": {"name": "Ferris"}, "tags": []}"#)
+                .unwrap();
+        let code = generate(&value, "Post").unwrap();
+        let file: syn::File = syn::parse2(code).unwrap();
+        let code = quote! { #file }.to_string();
+        assert!(code.contains("pub struct PostAuthor"));
+        assert!(code.contains("pub user_id : i64"));
+        assert!(code.contains("pub const POST : Post"));
+        assert!(generate(&Value::Bool(true), "Post").is_err());
+    }
+}
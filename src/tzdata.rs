@@ -0,0 +1,395 @@
+//! Compilation of the IANA time zone database included by `include_tzdata_url!`.
+//!
+//! The database is read either from a release archive, such as `tzdata2025b.tar.gz`, or
+//! from a single source file in the `zic` format. The zones are compiled into their
+//! transitions with `parse-zoneinfo`, which expands recurring rules up to the year 2100.
+//! The distinct offsets of every zone are stored once, and every zone keeps a sorted slice
+//! of transition times with the index of the offset each one switches to.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Read;
+
+use parse_zoneinfo::line::{Line, LineParser};
+use parse_zoneinfo::table::{Table, TableBuilder};
+use parse_zoneinfo::transitions::TableTransitions;
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+use crate::const_ident;
+
+/// The files of a release archive holding the zones and the links to them.
+const SOURCE_FILES: &[&str] = &[
+    "africa",
+    "antarctica",
+    "asia",
+    "australasia",
+    "etcetera",
+    "europe",
+    "northamerica",
+    "southamerica",
+    "backward",
+];
+
+/// The parsed database and the release it comes from, if known.
+pub(crate) struct Database {
+    pub table: Table,
+    pub version: Option<String>,
+}
+
+/// Reads the source files of a release archive, optionally gzip compressed, or a single
+/// source file, and parses the zones, rules and links they define.
+pub(crate) fn parse(content: &[u8]) -> Result<Database, String> {
+    let content = match content.starts_with(&[0x1F, 0x8B]) {
+        true => {
+            let mut decompressed = Vec::new();
+            flate2::read::GzDecoder::new(content)
+                .read_to_end(&mut decompressed)
+                .map_err(|e| format!("Failed to decompress the time zone database: {}", e))?;
+            decompressed
+        }
+        false => content.to_vec(),
+    };
+    let mut sources = Vec::new();
+    let mut version = None;
+    if content.get(257..262) == Some(b"ustar") {
+        let mut archive = tar::Archive::new(content.as_slice());
+        let entries = archive
+            .entries()
+            .map_err(|e| format!("Invalid time zone archive from URL: {}", e))?;
+        for entry in entries {
+            let mut entry =
+                entry.map_err(|e| format!("Invalid time zone archive from URL: {}", e))?;
+            let path = entry
+                .path()
+                .map_err(|e| format!("Invalid time zone archive from URL: {}", e))?;
+            let Some(file) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            let file = file.to_string();
+            if file != "version" && !SOURCE_FILES.contains(&file.as_str()) {
+                continue;
+            }
+            let mut text = String::new();
+            entry.read_to_string(&mut text).map_err(|e| {
+                format!(
+                    "Failed to read `{}` from the time zone archive: {}",
+                    file, e
+                )
+            })?;
+            match file.as_str() {
+                "version" => version = Some(text.trim().to_string()),
+                _ => sources.push((Some(file), text)),
+            }
+        }
+        if sources.is_empty() {
+            return Err("The time zone archive has no zone files".to_string());
+        }
+        sources.sort_by_key(|(file, _)| {
+            SOURCE_FILES
+                .iter()
+                .position(|f| Some(*f) == file.as_deref())
+        });
+    } else {
+        let text = String::from_utf8(content)
+            .map_err(|e| format!("Invalid time zone database from URL: {}", e))?;
+        sources.push((None, text));
+    }
+    let table = build(&sources)?;
+    if table.zonesets.is_empty() {
+        return Err("Invalid time zone database from URL: no zones found".to_string());
+    }
+    Ok(Database { table, version })
+}
+
+/// Builds the table of a set of source files.
+///
+/// A zone may only refer to rules that were added before it, so the rules of every file are
+/// added first and the zones and links in a second pass.
+fn build(sources: &[(Option<String>, String)]) -> Result<Table, String> {
+    let parser = LineParser::default();
+    let mut lines = Vec::new();
+    for (file, text) in sources {
+        for (index, line) in text.lines().enumerate() {
+            let error = move |message: String| match file {
+                Some(file) => format!(
+                    "Invalid time zone database from URL: {} line {}: {}",
+                    file,
+                    index + 1,
+                    message
+                ),
+                None => format!(
+                    "Invalid time zone database from URL: line {}: {}",
+                    index + 1,
+                    message
+                ),
+            };
+            let line = line.split('#').next().unwrap_or_default();
+            match parser.parse_str(line) {
+                Ok(Line::Space) => {}
+                Ok(line) => lines.push((line, error)),
+                Err(err) => return Err(error(err.to_string())),
+            }
+        }
+    }
+    let mut builder = TableBuilder::new();
+    for (line, error) in &lines {
+        if let Line::Rule(rule) = line {
+            builder
+                .add_rule_line(*rule)
+                .map_err(|e| error(e.to_string()))?;
+        }
+    }
+    for (line, error) in lines {
+        let added = match line {
+            Line::Zone(zone) => builder.add_zone_line(zone),
+            Line::Continuation(info) => builder.add_continuation_line(info),
+            Line::Link(link) => builder.add_link_line(link),
+            Line::Rule(_) | Line::Space => Ok(()),
+        };
+        added.map_err(|e| error(e.to_string()))?;
+    }
+    Ok(builder.build())
+}
+
+/// Resolves a zone or link name to the name of its zone.
+fn resolve<'a>(table: &'a Table, mut name: &'a str) -> Option<&'a str> {
+    // Links point to zones, but guard against chains and cycles anyway
+    for _ in 0..8 {
+        if table.zonesets.contains_key(name) {
+            return Some(name);
+        }
+        name = table.links.get(name)?;
+    }
+    None
+}
+
+/// Spells out the sign of the offsets in names such as `Etc/GMT-3`, which would otherwise
+/// map to the same constant as `Etc/GMT+3`.
+fn constant_name(name: &str) -> String {
+    let mut spelled = String::new();
+    let mut chars = name.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('+', _) => spelled.push_str("_plus_"),
+            ('-', Some(next)) if next.is_ascii_digit() => spelled.push_str("_minus_"),
+            _ => spelled.push(c),
+        }
+    }
+    spelled
+}
+
+/// Generates the `Offset` and `Zone` types, a constant for every zone and link in `zones`,
+/// or in the database if not given, and the `ZONES` table with its `find` lookup.
+pub(crate) fn generate(
+    database: &Database,
+    zones: Option<&[String]>,
+) -> Result<TokenStream, String> {
+    let table = &database.table;
+    let names: BTreeSet<&str> = match zones {
+        Some(zones) => zones.iter().map(String::as_str).collect(),
+        None => table
+            .zonesets
+            .keys()
+            .chain(table.links.keys())
+            .map(String::as_str)
+            .collect(),
+    };
+    let mut offsets: Vec<(i32, i32, String)> = Vec::new();
+    let mut offset_indices = BTreeMap::new();
+    let mut transitions = BTreeMap::new();
+    let mut constants = BTreeSet::new();
+    let mut idents = Vec::new();
+    let mut items = Vec::new();
+    let mut zone_names = Vec::new();
+    for name in &names {
+        let zone = resolve(table, name)
+            .ok_or_else(|| format!("The time zone database has no zone `{}`", name))?;
+        if !transitions.contains_key(zone) {
+            let timespans = table
+                .timespans(zone)
+                .ok_or_else(|| format!("The time zone database has no zone `{}`", zone))?;
+            let mut times = Vec::new();
+            let mut indices = Vec::new();
+            let spans = std::iter::once((i64::MIN, timespans.first)).chain(timespans.rest);
+            for (time, span) in spans {
+                let offset = match (
+                    i32::try_from(span.utc_offset),
+                    i32::try_from(span.dst_offset),
+                ) {
+                    (Ok(utc_offset), Ok(dst_offset)) => (utc_offset, dst_offset, span.name),
+                    _ => return Err(format!("The zone `{}` has an offset out of range", zone)),
+                };
+                let next = offsets.len();
+                let offset = *offset_indices.entry(offset.clone()).or_insert_with(|| {
+                    offsets.push(offset);
+                    next
+                });
+                times.push(time);
+                indices.push(
+                    u16::try_from(offset)
+                        .map_err(|_| "The time zone database has too many offsets".to_string())?,
+                );
+            }
+            let id = transitions.len();
+            let times_ident = format_ident!("TIMES_{}", id);
+            let offsets_ident = format_ident!("OFFSETS_{}", id);
+            items.push(quote! {
+                pub(super) const #times_ident: &[i64] = &[#(#times),*];
+                pub(super) const #offsets_ident: &[u16] = &[#(#indices),*];
+            });
+            transitions.insert(zone, (times_ident, offsets_ident));
+        }
+        let (times, offsets) = &transitions[zone];
+        let constant = const_ident(&constant_name(name))
+            .ok_or_else(|| format!("The zone `{}` does not map to an identifier", name))?;
+        if !constants.insert(constant.to_string()) {
+            return Err(format!(
+                "The zone `{}` does not map to a unique constant name",
+                name
+            ));
+        }
+        let doc = match *name == zone {
+            true => format!("The time zone `{}`.", name),
+            false => format!("The time zone `{}`, a link to `{}`.", name, zone),
+        };
+        idents.push(constant.clone());
+        zone_names.push(quote! {
+            #[doc = #doc]
+            pub const #constant: Zone = Zone {
+                name: #name,
+                times: transitions::#times,
+                offsets: transitions::#offsets,
+            };
+        });
+    }
+    let offsets = offsets
+        .iter()
+        .map(|(utc_offset, dst_offset, abbreviation)| {
+            quote! {
+                Offset {
+                    utc_offset: #utc_offset,
+                    dst_offset: #dst_offset,
+                    abbreviation: #abbreviation,
+                }
+            }
+        });
+    let version = match &database.version {
+        Some(version) => quote! { Some(#version) },
+        None => quote! { None },
+    };
+
+    Ok(quote! {
+        /// The release of the time zone database, such as `2025b`, if the archive has a
+        /// `version` file.
+        pub const VERSION: Option<&str> = #version;
+
+        /// An offset from UTC in effect during a span of time.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub struct Offset {
+            /// The standard offset from UTC, in seconds.
+            pub utc_offset: i32,
+            /// The daylight saving time added to the standard offset, in seconds.
+            pub dst_offset: i32,
+            /// The abbreviation of the offset, such as `CEST` or `-03`.
+            pub abbreviation: &'static str,
+        }
+
+        impl Offset {
+            /// Returns the total offset from UTC, in seconds.
+            pub const fn total(&self) -> i32 {
+                self.utc_offset + self.dst_offset
+            }
+        }
+
+        /// A time zone of the database.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub struct Zone {
+            name: &'static str,
+            times: &'static [i64],
+            offsets: &'static [u16],
+        }
+
+        impl Zone {
+            /// Returns the name of the time zone, such as `Europe/Paris`.
+            pub const fn name(&self) -> &'static str {
+                self.name
+            }
+
+            /// Returns the offset in effect at `timestamp`, in seconds since the Unix epoch.
+            pub fn offset_at(&self, timestamp: i64) -> Offset {
+                // The first transition is at `i64::MIN`, so one always precedes `timestamp`
+                let index = self.times.partition_point(|time| *time <= timestamp) - 1;
+                OFFSETS[self.offsets[index] as usize]
+            }
+        }
+
+        const OFFSETS: &[Offset] = &[#(#offsets),*];
+
+        mod transitions {
+            #(#items)*
+        }
+
+        #(#zone_names)*
+
+        /// Every time zone of the module, sorted by name.
+        pub const ZONES: &[Zone] = &[#(#idents),*];
+
+        /// Returns the time zone named `name`, such as `Europe/Paris`.
+        pub fn find(name: &str) -> Option<Zone> {
+            ZONES
+                .binary_search_by(|zone| zone.name.cmp(name))
+                .ok()
+                .map(|index| ZONES[index])
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "\
+# Central European Time, simplified
+Rule\tEU\t1981\tmax\t-\tMar\tlastSun\t 1:00u\t1:00\tS
+Rule\tEU\t1996\tmax\t-\tOct\tlastSun\t 1:00u\t0\t-
+Zone\tEurope/Paris\t0:09:21 -\tLMT\t1891 Mar 16
+\t\t\t1:00\tEU\tCE%sT
+Link\tEurope/Paris\tEurope/Monaco
+Zone\tEtc/GMT-3\t3\t-\t+03
+Zone\tEtc/GMT+3\t-3\t-\t-03
+";
+
+    /// Test that zones and links are compiled into constants with shared transitions
+    #[test]
+    fn test_generate() {
+        let database = parse(SOURCE.as_bytes()).unwrap();
+        assert_eq!(database.version, None);
+        let code = generate(&database, None).unwrap().to_string();
+        for constant in [
+            "EUROPE_PARIS",
+            "EUROPE_MONACO",
+            "ETC_GMT_MINUS_3",
+            "ETC_GMT_PLUS_3",
+        ] {
+            assert!(code.contains(&format!("pub const {} : Zone", constant)));
+        }
+        assert_eq!(code.matches("const TIMES_").count(), 3);
+        assert!(code.contains("abbreviation : \"CEST\""));
+
+        let code = generate(&database, Some(&["Europe/Monaco".to_string()])).unwrap();
+        assert!(!code.to_string().contains("EUROPE_PARIS"));
+        assert!(generate(&database, Some(&["Europe/Berlin".to_string()])).is_err());
+    }
+
+    /// Test that lines that do not parse are reported with their number
+    #[test]
+    fn test_parse_errors() {
+        let err = parse(b"Zone\tEurope/Paris\t1:00\tEU\tCE%sT\n")
+            .err()
+            .unwrap();
+        assert!(err.contains("line 1"), "{}", err);
+        let err = parse(b"Zone\tEurope/Paris\tone\t-\tCET\n").err().unwrap();
+        assert!(err.contains("line 1"), "{}", err);
+        assert!(parse(b"# no zones\n").is_err());
+    }
+}
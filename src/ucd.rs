@@ -0,0 +1,224 @@
+//! Table generation for the Unicode Character Database files included by
+//! `include_unicode_data_url!`.
+//!
+//! UCD files have one entry per line with `;` separated fields, the first being a code
+//! point or a range such as `1F600..1F64F`. `UnicodeData.txt` spells large ranges as a
+//! pair of `<..., First>` and `<..., Last>` entries instead. The code points of every value
+//! of the selected field are merged into sorted ranges, leaving out surrogates as they are
+//! not `char`s.
+
+use std::collections::BTreeMap;
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+use crate::{const_ident, snake_ident};
+
+/// The code point ranges of every value of a field, keyed by value.
+pub(crate) type Tables = BTreeMap<String, Vec<(u32, u32)>>;
+
+/// Parses a code point or a range of code points.
+fn parse_range(value: &str) -> Option<(u32, u32)> {
+    let code_point = |value: &str| {
+        u32::from_str_radix(value, 16)
+            .ok()
+            .filter(|code_point| *code_point <= 0x10FFFF)
+    };
+    let (start, end) = match value.split_once("..") {
+        Some((start, end)) => (code_point(start)?, code_point(end)?),
+        None => (code_point(value)?, code_point(value)?),
+    };
+    (start <= end).then_some((start, end))
+}
+
+/// Parses a UCD file and groups the code points by the value of the field at `field`,
+/// keeping only the values in `values` if given.
+pub(crate) fn parse(
+    content: &str,
+    field: usize,
+    values: Option<&[String]>,
+) -> Result<Tables, String> {
+    let mut tables = Tables::new();
+    // The start of a `<..., First>` range and its line
+    let mut first: Option<(u32, usize)> = None;
+    for (index, line) in content.lines().enumerate() {
+        let number = index + 1;
+        let error = |message: &str| {
+            format!(
+                "Invalid Unicode data file from URL: line {}: {}",
+                number, message
+            )
+        };
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(';').map(str::trim).collect();
+        let (mut start, end) = parse_range(fields[0])
+            .ok_or_else(|| error(&format!("invalid code point `{}`", fields[0])))?;
+        let name = fields.get(1).copied().unwrap_or_default();
+        if name.starts_with('<') && name.ends_with(", First>") {
+            first = Some((start, number));
+            continue;
+        }
+        if name.starts_with('<') && name.ends_with(", Last>") {
+            start = first
+                .take()
+                .map(|(start, _)| start)
+                .ok_or_else(|| error("`Last>` entry without a `First>` entry"))?;
+        } else if let Some((_, line)) = first {
+            return Err(error(&format!(
+                "the `First>` entry on line {} is not followed by a `Last>` entry",
+                line
+            )));
+        }
+        let value = fields
+            .get(field)
+            .ok_or_else(|| error(&format!("no field {}", field)))?;
+        if value.is_empty() || values.is_some_and(|values| !values.iter().any(|v| v == value)) {
+            continue;
+        }
+        tables
+            .entry(value.to_string())
+            .or_default()
+            .push((start, end));
+    }
+    if let Some(values) = values {
+        if let Some(missing) = values.iter().find(|value| !tables.contains_key(*value)) {
+            return Err(format!(
+                "The Unicode data file has no code points with the value `{}`",
+                missing
+            ));
+        }
+    }
+    if tables.is_empty() {
+        return Err("Invalid Unicode data file from URL: no code points found".to_string());
+    }
+    for ranges in tables.values_mut() {
+        *ranges = merge(std::mem::take(ranges));
+    }
+    Ok(tables)
+}
+
+/// Sorts and merges overlapping or adjacent ranges and removes the surrogates.
+fn merge(mut ranges: Vec<(u32, u32)>) -> Vec<(u32, u32)> {
+    ranges.sort_unstable();
+    let mut merged: Vec<(u32, u32)> = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1.saturating_add(1) => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+        .into_iter()
+        .flat_map(|(start, end)| {
+            [(start, end.min(0xD7FF)), (start.max(0xE000), end)]
+                .into_iter()
+                .filter(|(start, end)| start <= end)
+        })
+        .collect()
+}
+
+/// Generates a range table and an `is_` function for every value.
+pub(crate) fn generate(tables: &Tables) -> Result<TokenStream, String> {
+    let mut names = std::collections::BTreeSet::new();
+    let mut items = Vec::new();
+    for (value, ranges) in tables {
+        let (Some(table), Some(function)) = (const_ident(value), snake_ident(value)) else {
+            return Err(format!(
+                "The value `{}` does not map to an identifier",
+                value
+            ));
+        };
+        if !names.insert(table.to_string()) {
+            return Err(format!(
+                "The value `{}` does not map to a unique constant name",
+                value
+            ));
+        }
+        let function = format_ident!("is_{}", function);
+        let ranges = ranges.iter().map(|(start, end)| {
+            let start = char::from_u32(*start).expect("surrogates are removed");
+            let end = char::from_u32(*end).expect("surrogates are removed");
+            quote! { (#start, #end) }
+        });
+        let table_doc = format!(
+            "The ranges of code points with the value `{}`, sorted.",
+            value
+        );
+        let function_doc = format!("Returns whether `c` has the value `{}`.", value);
+        items.push(quote! {
+            #[doc = #table_doc]
+            pub const #table: &[(char, char)] = &[#(#ranges),*];
+
+            #[doc = #function_doc]
+            pub fn #function(c: char) -> bool {
+                contains(#table, c)
+            }
+        });
+    }
+    Ok(quote! {
+        /// Returns whether one of the sorted `ranges` contains `c`.
+        fn contains(ranges: &[(char, char)], c: char) -> bool {
+            ranges
+                .binary_search_by(|&(start, end)| {
+                    if c < start {
+                        ::core::cmp::Ordering::Greater
+                    } else if c > end {
+                        ::core::cmp::Ordering::Less
+                    } else {
+                        ::core::cmp::Ordering::Equal
+                    }
+                })
+                .is_ok()
+        }
+
+        #(#items)*
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that property files and `UnicodeData.txt` ranges are merged per value
+    #[test]
+    fn test_parse() {
+        let emoji = "# emoji-data.txt\n\
+                     231A..231B    ; Emoji_Presentation   # 1.1  [2] (⌚..⌛)\n\
+                     23E9..23EC    ; Emoji_Presentation   # 6.0  [4]\n\
+                     23F0          ; Emoji_Presentation\n\
+                     0023          ; Emoji\n";
+        let tables = parse(emoji, 1, None).unwrap();
+        assert_eq!(
+            tables["Emoji_Presentation"],
+            [(0x231A, 0x231B), (0x23E9, 0x23EC), (0x23F0, 0x23F0)]
+        );
+        assert_eq!(tables["Emoji"], [(0x23, 0x23)]);
+        let selected = parse(emoji, 1, Some(&["Emoji".to_string()])).unwrap();
+        assert_eq!(selected.len(), 1);
+        assert!(parse(emoji, 1, Some(&["Emoji_Modifier".to_string()])).is_err());
+
+        let data = "0041;LATIN CAPITAL LETTER A;Lu;0;L;;;;;N;;;;0061;\n\
+                    0042;LATIN CAPITAL LETTER B;Lu;0;L;;;;;N;;;;0062;\n\
+                    D800;<Non Private Use High Surrogate, First>;Cs;0;L;;;;;N;;;;;\n\
+                    DB7F;<Non Private Use High Surrogate, Last>;Cs;0;L;;;;;N;;;;;\n\
+                    4E00;<CJK Ideograph, First>;Lo;0;L;;;;;N;;;;;\n\
+                    9FFF;<CJK Ideograph, Last>;Lo;0;L;;;;;N;;;;;\n";
+        let tables = parse(data, 2, None).unwrap();
+        assert_eq!(tables["Lu"], [(0x41, 0x42)]);
+        assert_eq!(tables["Lo"], [(0x4E00, 0x9FFF)]);
+        assert!(tables["Cs"].is_empty());
+
+        let error = parse("0041;A\nXYZ;B\n", 1, None).unwrap_err();
+        assert!(error.contains("line 2"), "{}", error);
+        assert!(parse("0041;A\n", 5, None).is_err());
+        assert!(parse("4E00;<CJK Ideograph, First>;Lo\n0041;A;Lu\n", 2, None).is_err());
+
+        let code = generate(&parse(emoji, 1, None).unwrap())
+            .unwrap()
+            .to_string();
+        assert!(code.contains("pub fn is_emoji_presentation (c : char) -> bool"));
+    }
+}
@@ -0,0 +1,939 @@
+//! Decoding and encoding of the WOFF and WOFF2 containers of web fonts.
+//!
+//! Both wrap the tables of a TrueType or OpenType font. WOFF compresses every table with
+//! zlib, and WOFF2 compresses all of them together with Brotli, after transforming the
+//! `glyf`, `loca` and `hmtx` tables into a more compressible form. Decoding rebuilds the
+//! tables and the checksums of the font, so that it can be parsed and subset like any other.
+//! Encoding never transforms the tables. Font collections are not supported.
+
+use std::io::{Read, Write};
+
+/// Tags of the tables WOFF2 refers to by their index.
+const KNOWN_TAGS: [&[u8; 4]; 63] = [
+    b"cmap", b"head", b"hhea", b"hmtx", b"maxp", b"name", b"OS/2", b"post", b"cvt ", b"fpgm",
+    b"glyf", b"loca", b"prep", b"CFF ", b"VORG", b"EBDT", b"EBLC", b"gasp", b"hdmx", b"kern",
+    b"LTSH", b"PCLT", b"VDMX", b"vhea", b"vmtx", b"BASE", b"GDEF", b"GPOS", b"GSUB", b"EBSC",
+    b"JSTF", b"MATH", b"CBDT", b"CBLC", b"COLR", b"CPAL", b"SVG ", b"sbix", b"acnt", b"avar",
+    b"bdat", b"bloc", b"bsln", b"cvar", b"fdsc", b"feat", b"fmtx", b"fvar", b"gvar", b"hsty",
+    b"just", b"lcar", b"mort", b"morx", b"opbd", b"prop", b"trak", b"Zapf", b"Silf", b"Glat",
+    b"Gloc", b"Feat", b"Sill",
+];
+
+/// Flavor of font collections.
+const COLLECTION: u32 = u32::from_be_bytes(*b"ttcf");
+
+/// A table of a font, by tag.
+type Table = ([u8; 4], Vec<u8>);
+
+/// Reads the big-endian values of a font.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Reader { data, pos: 0 }
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let bytes = (self.pos.checked_add(len))
+            .and_then(|end| self.data.get(self.pos..end))
+            .ok_or("Invalid web font: unexpected end of data")?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_be_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn i16(&mut self) -> Result<i16, String> {
+        Ok(i16::from_be_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_be_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn tag(&mut self) -> Result<[u8; 4], String> {
+        Ok(self.bytes(4)?.try_into().unwrap())
+    }
+
+    /// Reads a `UIntBase128`, the variable length integer of the WOFF2 table directory.
+    fn base128(&mut self) -> Result<u32, String> {
+        let mut value = 0u32;
+        for index in 0..5 {
+            let byte = self.u8()?;
+            if (index == 0 && byte == 0x80) || value & 0xFE00_0000 != 0 {
+                return Err("Invalid WOFF2 font: malformed table length".to_string());
+            }
+            value = (value << 7) | u32::from(byte & 0x7F);
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("Invalid WOFF2 font: malformed table length".to_string())
+    }
+
+    /// Reads a `255UInt16`, the variable length integer of the transformed `glyf` table.
+    fn uint255(&mut self) -> Result<u16, String> {
+        Ok(match self.u8()? {
+            253 => self.u16()?,
+            254 => u16::from(self.u8()?) + 253 * 2,
+            255 => u16::from(self.u8()?) + 253,
+            code => u16::from(code),
+        })
+    }
+}
+
+/// Writes a `UIntBase128`.
+fn write_base128(output: &mut Vec<u8>, value: u32) {
+    let len = (1..5).find(|len| value >> (7 * len) == 0).unwrap_or(5);
+    for index in (0..len).rev() {
+        let byte = ((value >> (7 * index)) & 0x7F) as u8;
+        output.push(if index == 0 { byte } else { byte | 0x80 });
+    }
+}
+
+/// Returns the checksum of a table, the sum of its big-endian 32-bit words.
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, word| {
+        let mut padded = [0; 4];
+        padded[..word.len()].copy_from_slice(word);
+        sum.wrapping_add(u32::from_be_bytes(padded))
+    })
+}
+
+/// Returns the checksum of a table as recorded in the table directory, which leaves out
+/// the `checkSumAdjustment` of the `head` table.
+fn table_checksum(tag: &[u8; 4], data: &[u8]) -> u32 {
+    match (tag, data.get(8..12)) {
+        (b"head", Some(adjustment)) => {
+            checksum(data).wrapping_sub(u32::from_be_bytes(adjustment.try_into().unwrap()))
+        }
+        _ => checksum(data),
+    }
+}
+
+fn padded_len(len: usize) -> usize {
+    len.div_ceil(4) * 4
+}
+
+/// Returns the size of the TrueType or OpenType font made of `tables`.
+fn sfnt_size(tables: &[Table]) -> u32 {
+    let tables = tables.iter().map(|(_, data)| 16 + padded_len(data.len()));
+    (12 + tables.sum::<usize>()) as u32
+}
+
+/// Reads the flavor and the tables of a TrueType or OpenType font.
+fn read_sfnt(sfnt: &[u8]) -> Result<(u32, Vec<Table>), String> {
+    let mut reader = Reader::new(sfnt);
+    let flavor = reader.u32()?;
+    if flavor == COLLECTION {
+        return Err("Font collections cannot be encoded as web fonts".to_string());
+    }
+    let num_tables = reader.u16()?;
+    reader.bytes(6)?;
+    let mut tables = Vec::new();
+    for _ in 0..num_tables {
+        let tag = reader.tag()?;
+        let _checksum = reader.u32()?;
+        let offset = reader.u32()? as usize;
+        let len = reader.u32()? as usize;
+        let data = Reader::new(sfnt.get(offset..).unwrap_or_default()).bytes(len)?;
+        tables.push((tag, data.to_vec()));
+    }
+    Ok((flavor, tables))
+}
+
+/// Assembles a TrueType or OpenType font from its tables, computing the checksums.
+fn write_sfnt(flavor: u32, mut tables: Vec<Table>) -> Vec<u8> {
+    tables.sort_by_key(|(tag, _)| *tag);
+    let num_tables = tables.len() as u16;
+    let entry_selector = num_tables.max(1).ilog2() as u16;
+    let search_range: u16 = (1 << entry_selector) * 16;
+    let mut sfnt = Vec::new();
+    sfnt.extend(flavor.to_be_bytes());
+    sfnt.extend(num_tables.to_be_bytes());
+    sfnt.extend(search_range.to_be_bytes());
+    sfnt.extend(entry_selector.to_be_bytes());
+    sfnt.extend((num_tables * 16).saturating_sub(search_range).to_be_bytes());
+
+    let mut offset = 12 + 16 * tables.len();
+    let mut head = None;
+    for (tag, data) in &mut tables {
+        if tag == b"head" && data.len() >= 12 {
+            data[8..12].fill(0);
+            head = Some(offset);
+        }
+        sfnt.extend(tag.iter());
+        sfnt.extend(checksum(data).to_be_bytes());
+        sfnt.extend((offset as u32).to_be_bytes());
+        sfnt.extend((data.len() as u32).to_be_bytes());
+        offset += padded_len(data.len());
+    }
+    for (_, data) in &tables {
+        sfnt.extend(data);
+        sfnt.resize(padded_len(sfnt.len()), 0);
+    }
+    if let Some(head) = head {
+        let adjustment = 0xB1B0_AFBAu32.wrapping_sub(checksum(&sfnt));
+        sfnt[head + 8..head + 12].copy_from_slice(&adjustment.to_be_bytes());
+    }
+    sfnt
+}
+
+/// Decodes a WOFF font into a TrueType or OpenType font.
+pub(crate) fn decode_woff(woff: &[u8]) -> Result<Vec<u8>, String> {
+    let mut reader = Reader::new(woff);
+    reader.bytes(4)?;
+    let flavor = reader.u32()?;
+    reader.u32()?;
+    let num_tables = reader.u16()?;
+    reader.bytes(30)?;
+    let mut tables = Vec::new();
+    for _ in 0..num_tables {
+        let tag = reader.tag()?;
+        let offset = reader.u32()? as usize;
+        let compressed_len = reader.u32()? as usize;
+        let len = reader.u32()? as usize;
+        reader.u32()?;
+        let data = Reader::new(woff.get(offset..).unwrap_or_default()).bytes(compressed_len)?;
+        let data = match compressed_len.cmp(&len) {
+            std::cmp::Ordering::Equal => data.to_vec(),
+            std::cmp::Ordering::Less => {
+                let mut table = Vec::with_capacity(len);
+                flate2::read::ZlibDecoder::new(data)
+                    .take(len as u64 + 1)
+                    .read_to_end(&mut table)
+                    .map_err(|e| format!("Invalid WOFF font: {}", e))?;
+                if table.len() != len {
+                    return Err("Invalid WOFF font: wrong table length".to_string());
+                }
+                table
+            }
+            std::cmp::Ordering::Greater => {
+                return Err("Invalid WOFF font: wrong table length".to_string())
+            }
+        };
+        tables.push((tag, data));
+    }
+    Ok(write_sfnt(flavor, tables))
+}
+
+/// Encodes a TrueType or OpenType font as a WOFF font, compressing the tables with zlib.
+pub(crate) fn encode_woff(sfnt: &[u8]) -> Result<Vec<u8>, String> {
+    let (flavor, mut tables) = read_sfnt(sfnt)?;
+    tables.sort_by_key(|(tag, _)| *tag);
+
+    let mut directory = Vec::new();
+    let mut data = Vec::new();
+    let mut offset = 44 + 20 * tables.len();
+    for (tag, table) in &tables {
+        let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::best());
+        let compressed = encoder
+            .write_all(table)
+            .and_then(|_| encoder.finish())
+            .map_err(|e| format!("Failed to compress the font: {}", e))?;
+        let stored = match compressed.len() < table.len() {
+            true => &compressed,
+            false => table,
+        };
+        directory.extend(tag.iter());
+        directory.extend((offset as u32).to_be_bytes());
+        directory.extend((stored.len() as u32).to_be_bytes());
+        directory.extend((table.len() as u32).to_be_bytes());
+        directory.extend(table_checksum(tag, table).to_be_bytes());
+        data.extend(stored);
+        data.resize(padded_len(data.len()), 0);
+        offset = 44 + 20 * tables.len() + data.len();
+    }
+
+    let mut woff = Vec::with_capacity(offset);
+    woff.extend(b"wOFF");
+    woff.extend(flavor.to_be_bytes());
+    woff.extend((offset as u32).to_be_bytes());
+    woff.extend((tables.len() as u16).to_be_bytes());
+    woff.extend([0; 2]);
+    woff.extend(sfnt_size(&tables).to_be_bytes());
+    woff.extend([0, 1, 0, 0]);
+    woff.extend([0; 20]);
+    woff.extend(directory);
+    woff.extend(data);
+    Ok(woff)
+}
+
+/// An entry of the WOFF2 table directory.
+struct Woff2Entry {
+    tag: [u8; 4],
+    /// Length of the table in the compressed stream.
+    stored_len: usize,
+    transformed: bool,
+}
+
+/// Decodes a WOFF2 font into a TrueType or OpenType font, reversing the transforms of the
+/// `glyf`, `loca` and `hmtx` tables.
+pub(crate) fn decode_woff2(woff2: &[u8]) -> Result<Vec<u8>, String> {
+    let mut reader = Reader::new(woff2);
+    reader.bytes(4)?;
+    let flavor = reader.u32()?;
+    if flavor == COLLECTION {
+        return Err("WOFF2 font collections are not supported".to_string());
+    }
+    reader.u32()?;
+    let num_tables = reader.u16()?;
+    reader.bytes(6)?;
+    let compressed_len = reader.u32()? as usize;
+    reader.bytes(24)?;
+
+    let mut entries = Vec::new();
+    for _ in 0..num_tables {
+        let flags = reader.u8()?;
+        let tag = match flags & 0x3F {
+            63 => reader.tag()?,
+            index => *KNOWN_TAGS[index as usize],
+        };
+        let version = flags >> 6;
+        let len = reader.base128()? as usize;
+        let transformed = match &tag {
+            b"glyf" | b"loca" => version == 0,
+            _ => version != 0,
+        };
+        let stored_len = match transformed {
+            true => reader.base128()? as usize,
+            false => len,
+        };
+        entries.push(Woff2Entry {
+            tag,
+            stored_len,
+            transformed,
+        });
+    }
+
+    let total: usize = entries.iter().map(|entry| entry.stored_len).sum();
+    let mut stream = Vec::with_capacity(total);
+    brotli::Decompressor::new(reader.bytes(compressed_len)?, 4096)
+        .take(total as u64 + 1)
+        .read_to_end(&mut stream)
+        .map_err(|e| format!("Invalid WOFF2 font: {}", e))?;
+    if stream.len() != total {
+        return Err("Invalid WOFF2 font: wrong length of the compressed tables".to_string());
+    }
+
+    let mut stream = Reader::new(&stream);
+    let mut tables = Vec::new();
+    let mut transformed = Vec::new();
+    for entry in &entries {
+        let data = stream.bytes(entry.stored_len)?;
+        match entry.transformed {
+            true => transformed.push((entry, data)),
+            false => tables.push((entry.tag, data.to_vec())),
+        }
+    }
+
+    let mut x_mins = None;
+    for (_, data) in transformed
+        .iter()
+        .filter(|(entry, _)| &entry.tag == b"glyf")
+    {
+        let decoded = decode_glyf(data)?;
+        if !transformed.iter().any(|(entry, _)| &entry.tag == b"loca") {
+            return Err("Invalid WOFF2 font: transformed `glyf` without `loca`".to_string());
+        }
+        if let Some((_, head)) = tables.iter_mut().find(|(tag, _)| tag == b"head") {
+            if let Some(format) = head.get_mut(50..52) {
+                format.copy_from_slice(&decoded.index_format.to_be_bytes());
+            }
+        }
+        tables.push((*b"glyf", decoded.glyf));
+        tables.push((*b"loca", decoded.loca));
+        x_mins = Some(decoded.x_mins);
+    }
+    for (entry, data) in &transformed {
+        match &entry.tag {
+            b"glyf" => {}
+            b"loca" if x_mins.is_some() => {}
+            b"hmtx" => {
+                let x_mins = (x_mins.as_ref())
+                    .ok_or("Invalid WOFF2 font: transformed `hmtx` without `glyf`")?;
+                let hhea = tables.iter().find(|(tag, _)| tag == b"hhea");
+                let hhea = hhea.ok_or("Invalid WOFF2 font: no `hhea`")?;
+                let num_metrics = Reader::new(hhea.1.get(34..).unwrap_or_default()).u16()?;
+                tables.push((*b"hmtx", decode_hmtx(data, num_metrics, x_mins)?));
+            }
+            tag => {
+                return Err(format!(
+                    "Unsupported WOFF2 transform of the `{}` table",
+                    String::from_utf8_lossy(tag)
+                ))
+            }
+        }
+    }
+    Ok(write_sfnt(flavor, tables))
+}
+
+/// Decodes the coordinate deltas of a point of the transformed `glyf` table, whose flag
+/// selects how many bytes of `glyphs` encode them and with which signs.
+fn decode_triplet(flag: u8, glyphs: &mut Reader) -> Result<(i32, i32), String> {
+    let with_sign = |flag: u8, value: i32| if flag & 1 != 0 { value } else { -value };
+    let code = i32::from(flag);
+    Ok(match flag {
+        0..=9 => (
+            0,
+            with_sign(flag, ((code & 14) << 7) + i32::from(glyphs.u8()?)),
+        ),
+        10..=19 => {
+            let dx = (((code - 10) & 14) << 7) + i32::from(glyphs.u8()?);
+            (with_sign(flag, dx), 0)
+        }
+        20..=83 => {
+            let (code, byte) = (code - 20, i32::from(glyphs.u8()?));
+            (
+                with_sign(flag, 1 + (code & 0x30) + (byte >> 4)),
+                with_sign(flag >> 1, 1 + ((code & 0x0C) << 2) + (byte & 0x0F)),
+            )
+        }
+        84..=119 => {
+            let code = code - 84;
+            let (x, y) = (i32::from(glyphs.u8()?), i32::from(glyphs.u8()?));
+            (
+                with_sign(flag, 1 + ((code / 12) << 8) + x),
+                with_sign(flag >> 1, 1 + (((code % 12) >> 2) << 8) + y),
+            )
+        }
+        120..=123 => {
+            let bytes = glyphs.bytes(3)?;
+            let [b0, b1, b2] = [0, 1, 2].map(|index| i32::from(bytes[index]));
+            (
+                with_sign(flag, (b0 << 4) + (b1 >> 4)),
+                with_sign(flag >> 1, ((b1 & 0x0F) << 8) + b2),
+            )
+        }
+        _ => {
+            let (x, y) = (glyphs.u16()?, glyphs.u16()?);
+            (
+                with_sign(flag, i32::from(x)),
+                with_sign(flag >> 1, i32::from(y)),
+            )
+        }
+    })
+}
+
+/// The `glyf` and `loca` tables decoded from the transformed `glyf` table.
+struct Glyf {
+    glyf: Vec<u8>,
+    loca: Vec<u8>,
+    /// Minimum x coordinate of every glyph.
+    x_mins: Vec<i16>,
+    /// `indexToLocFormat` of `loca`.
+    index_format: u16,
+}
+
+/// Decodes the transformed `glyf` table.
+fn decode_glyf(data: &[u8]) -> Result<Glyf, String> {
+    let mut header = Reader::new(data);
+    header.u16()?;
+    let options = header.u16()?;
+    let num_glyphs = header.u16()? as usize;
+    let index_format = header.u16()?;
+    let mut rest = Reader::new(&data[36.min(data.len())..]);
+    let mut stream = || -> Result<Reader, String> {
+        let len = header.u32()? as usize;
+        Ok(Reader::new(rest.bytes(len)?))
+    };
+    let mut contours = stream()?;
+    let mut points = stream()?;
+    let mut flags = stream()?;
+    let mut glyphs = stream()?;
+    let mut composites = stream()?;
+    let mut bboxes = stream()?;
+    let mut instructions = stream()?;
+    let overlaps = match options & 1 {
+        0 => None,
+        _ => Some(rest.bytes(num_glyphs.div_ceil(8))?),
+    };
+    let bitmap = bboxes.bytes(4 * num_glyphs.div_ceil(32))?;
+    let bit = |bitmap: &[u8], index: usize| bitmap[index >> 3] & (0x80 >> (index & 7)) != 0;
+    let invalid = |what: &str| format!("Invalid WOFF2 font: {}", what);
+
+    let mut glyf = Vec::new();
+    let mut offsets = vec![0];
+    let mut x_mins = Vec::with_capacity(num_glyphs);
+    for index in 0..num_glyphs {
+        let num_contours = contours.i16()?;
+        let has_bbox = bit(bitmap, index);
+        let mut x_min = 0;
+        if num_contours < 0 {
+            if !has_bbox {
+                return Err(invalid("composite glyph without bounding box"));
+            }
+            let bbox = bboxes.bytes(8)?;
+            x_min = Reader::new(bbox).i16()?;
+            let start = composites.pos;
+            let mut has_instructions = false;
+            loop {
+                let flags = composites.u16()?;
+                composites.u16()?;
+                let args = if flags & 0x0001 != 0 { 4 } else { 2 };
+                let scale = match flags {
+                    _ if flags & 0x0008 != 0 => 2,
+                    _ if flags & 0x0040 != 0 => 4,
+                    _ if flags & 0x0080 != 0 => 8,
+                    _ => 0,
+                };
+                composites.bytes(args + scale)?;
+                has_instructions |= flags & 0x0100 != 0;
+                if flags & 0x0020 == 0 {
+                    break;
+                }
+            }
+            glyf.extend(num_contours.to_be_bytes());
+            glyf.extend(bbox);
+            glyf.extend(&composites.data[start..composites.pos]);
+            if has_instructions {
+                let len = glyphs.uint255()?;
+                glyf.extend(len.to_be_bytes());
+                glyf.extend(instructions.bytes(len.into())?);
+            }
+        } else if num_contours > 0 {
+            let mut end_points = Vec::new();
+            let mut num_points = 0u16;
+            for _ in 0..num_contours {
+                num_points = (num_points.checked_add(points.uint255()?))
+                    .ok_or_else(|| invalid("too many points in a glyph"))?;
+                let end = num_points.checked_sub(1);
+                end_points.push(end.ok_or_else(|| invalid("empty contour"))?);
+            }
+            let (mut x, mut y) = (0i32, 0i32);
+            let mut outline = Vec::with_capacity(num_points.into());
+            for _ in 0..num_points {
+                let flag = flags.u8()?;
+                let (dx, dy) = decode_triplet(flag & 0x7F, &mut glyphs)?;
+                x += dx;
+                y += dy;
+                outline.push((x, y, flag & 0x80 == 0));
+            }
+            let instruction_len = glyphs.uint255()?;
+
+            let bbox = match has_bbox {
+                true => bboxes.bytes(8)?.to_vec(),
+                false => {
+                    let xs = outline.iter().map(|point| point.0);
+                    let ys = outline.iter().map(|point| point.1);
+                    let bbox = [xs.clone().min(), ys.clone().min(), xs.max(), ys.max()];
+                    let bbox = bbox.map(|value| i16::try_from(value.unwrap_or(0)));
+                    let bbox = bbox.into_iter().collect::<Result<Vec<i16>, _>>();
+                    let bbox = bbox.map_err(|_| invalid("glyph coordinates out of range"))?;
+                    bbox.iter().flat_map(|value| value.to_be_bytes()).collect()
+                }
+            };
+            x_min = Reader::new(&bbox).i16()?;
+            glyf.extend(num_contours.to_be_bytes());
+            glyf.extend(bbox);
+            glyf.extend(end_points.iter().flat_map(|end| end.to_be_bytes()));
+            glyf.extend(instruction_len.to_be_bytes());
+            glyf.extend(instructions.bytes(instruction_len.into())?);
+
+            let (mut point_flags, mut xs, mut ys) = (Vec::new(), Vec::new(), Vec::new());
+            let (mut last_x, mut last_y) = (0, 0);
+            for (point, (x, y, on_curve)) in outline.into_iter().enumerate() {
+                let overlap = point == 0 && overlaps.is_some_and(|bitmap| bit(bitmap, index));
+                let mut flag = u8::from(on_curve) | if overlap { 0x40 } else { 0 };
+                for (delta, output, short, same) in [
+                    (x - last_x, &mut xs, 0x02, 0x10),
+                    (y - last_y, &mut ys, 0x04, 0x20),
+                ] {
+                    match delta {
+                        0 => flag |= same,
+                        -255..=255 => {
+                            flag |= short | if delta > 0 { same } else { 0 };
+                            output.push(delta.unsigned_abs() as u8);
+                        }
+                        _ => {
+                            let delta = i16::try_from(delta)
+                                .map_err(|_| invalid("glyph coordinates out of range"))?;
+                            output.extend(delta.to_be_bytes());
+                        }
+                    }
+                }
+                point_flags.push(flag);
+                (last_x, last_y) = (x, y);
+            }
+            glyf.extend(point_flags);
+            glyf.extend(xs);
+            glyf.extend(ys);
+        } else if has_bbox {
+            return Err(invalid("empty glyph with a bounding box"));
+        }
+        glyf.resize(padded_len(glyf.len()), 0);
+        offsets.push(glyf.len());
+        x_mins.push(x_min);
+    }
+
+    let loca = match index_format {
+        0 => offsets
+            .iter()
+            .map(|offset| u16::try_from(offset / 2).map(u16::to_be_bytes))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| invalid("`glyf` too large for a short `loca`"))?
+            .concat(),
+        _ => offsets
+            .iter()
+            .flat_map(|offset| (*offset as u32).to_be_bytes())
+            .collect(),
+    };
+    Ok(Glyf {
+        glyf,
+        loca,
+        x_mins,
+        index_format,
+    })
+}
+
+/// Decodes the transformed `hmtx` table, whose omitted left side bearings are the minimum x
+/// coordinates of the glyphs.
+fn decode_hmtx(data: &[u8], num_metrics: u16, x_mins: &[i16]) -> Result<Vec<u8>, String> {
+    let num_metrics = usize::from(num_metrics);
+    if num_metrics == 0 || num_metrics > x_mins.len() {
+        return Err("Invalid WOFF2 font: wrong number of horizontal metrics".to_string());
+    }
+    let mut reader = Reader::new(data);
+    let flags = reader.u8()?;
+    let advances = (0..num_metrics)
+        .map(|_| reader.u16())
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut hmtx = Vec::new();
+    for (index, x_min) in x_mins.iter().enumerate() {
+        let omitted = match index < num_metrics {
+            true => flags & 1 != 0,
+            false => flags & 2 != 0,
+        };
+        let bearing = match omitted {
+            true => *x_min,
+            false => reader.i16()?,
+        };
+        if let Some(advance) = advances.get(index) {
+            hmtx.extend(advance.to_be_bytes());
+        }
+        hmtx.extend(bearing.to_be_bytes());
+    }
+    Ok(hmtx)
+}
+
+/// Encodes a TrueType or OpenType font as a WOFF2 font, compressing the tables with
+/// Brotli without transforming them.
+pub(crate) fn encode_woff2(sfnt: &[u8]) -> Result<Vec<u8>, String> {
+    let (flavor, mut tables) = read_sfnt(sfnt)?;
+    // `loca` follows `glyf`, as decoders of transformed tables expect
+    tables.sort_by_key(|(tag, _)| match tag {
+        b"loca" => *b"glyg",
+        tag => *tag,
+    });
+
+    let mut directory = Vec::new();
+    let mut stream = Vec::new();
+    for (tag, data) in &tables {
+        let index = KNOWN_TAGS.iter().position(|known| *known == tag);
+        let flags = index.unwrap_or(63) as u8;
+        // Version 3 is the null transform of `glyf` and `loca`
+        let version = if matches!(tag, b"glyf" | b"loca") {
+            0xC0
+        } else {
+            0
+        };
+        directory.push(flags | version);
+        if index.is_none() {
+            directory.extend(tag.iter());
+        }
+        write_base128(&mut directory, data.len() as u32);
+        stream.extend(data);
+    }
+    let mut compressed = Vec::new();
+    {
+        let mut encoder = brotli::CompressorWriter::new(&mut compressed, 4096, 11, 22);
+        encoder
+            .write_all(&stream)
+            .map_err(|e| format!("Failed to compress the font: {}", e))?;
+    }
+
+    let len = padded_len(48 + directory.len() + compressed.len());
+    let mut woff2 = Vec::with_capacity(len);
+    woff2.extend(b"wOF2");
+    woff2.extend(flavor.to_be_bytes());
+    woff2.extend((len as u32).to_be_bytes());
+    woff2.extend((tables.len() as u16).to_be_bytes());
+    woff2.extend([0; 2]);
+    woff2.extend(sfnt_size(&tables).to_be_bytes());
+    woff2.extend((compressed.len() as u32).to_be_bytes());
+    woff2.extend([0, 1, 0, 0]);
+    woff2.extend([0; 20]);
+    woff2.extend(directory);
+    woff2.extend(compressed);
+    woff2.resize(len, 0);
+    Ok(woff2)
+}
+
+/// The points of a contour, as `(x, y, on_curve)`.
+#[cfg(test)]
+pub(crate) type Contour = Vec<(i16, i16, bool)>;
+
+/// Builds a TrueType font of three glyphs, an empty `.notdef`, `A` made of a triangle with
+/// an off-curve point and a square, and `B`, a composite of `A` moved to the right, along
+/// with the outlines of `A`.
+#[cfg(test)]
+pub(crate) fn sample_font() -> (Vec<u8>, Vec<Contour>) {
+    let be = |values: &[i32]| -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|value| (*value as i16).to_be_bytes())
+            .collect()
+    };
+    let contours = vec![
+        vec![(50, 0, true), (350, 700, false), (650, 0, true)],
+        vec![
+            (200, 100, true),
+            (300, 100, true),
+            (300, 200, true),
+            (200, 200, true),
+        ],
+    ];
+
+    let mut glyph_a = be(&[2, 50, 0, 650, 700, 2, 6, 2]);
+    glyph_a.extend([0xB0, 0x01]);
+    let points: Vec<_> = contours.iter().flatten().collect();
+    glyph_a.extend(points.iter().map(|point| u8::from(point.2)));
+    let mut last = (0i16, 0i16);
+    let mut ys = Vec::new();
+    for (x, y, _) in &points {
+        glyph_a.extend((x - last.0).to_be_bytes());
+        ys.extend((y - last.1).to_be_bytes());
+        last = (*x, *y);
+    }
+    glyph_a.extend(ys);
+    let glyph_b = be(&[-1, 750, 0, 1350, 700, 0x0003, 1, 700, 0]);
+    let mut glyf = glyph_a.clone();
+    glyf.resize(padded_len(glyf.len()), 0);
+    let b_offset = glyf.len();
+    glyf.extend(&glyph_b);
+    let loca = be(&[0, 0, b_offset as i32 / 2, glyf.len() as i32 / 2]);
+
+    let mut head = vec![0, 1, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0x5F, 0x0F, 0x3C, 0xF5];
+    head.extend(be(&[0, 1000, 0, 0, 0, 0, 0, 0, 0, 0]));
+    head.extend(be(&[0, 0, 1350, 700, 0, 8, 2, 0, 0]));
+    let mut hhea = vec![0, 1, 0, 0];
+    hhea.extend(be(&[
+        800, -200, 0, 1400, 0, 0, 1350, 1, 0, 0, 0, 0, 0, 0, 0, 2,
+    ]));
+    let hmtx = be(&[500, 0, 700, 50, 750]);
+    let mut maxp = vec![0, 1, 0, 0];
+    maxp.extend(be(&[3, 7, 2, 7, 2, 2, 0, 0, 0, 0, 0, 2, 1, 1]));
+    let mut cmap = be(&[0, 1, 3, 1, 0, 12]);
+    cmap.extend(be(&[
+        4, 32, 0, 4, 4, 1, 0, 0x42, -1, 0, 0x41, -1, -64, 1, 0, 0,
+    ]));
+    let mut post = vec![0, 3, 0, 0, 0, 0, 0, 0];
+    post.extend(be(&[-100, 50]));
+    post.extend([0; 20]);
+
+    let tables = vec![
+        (*b"cmap", cmap),
+        (*b"glyf", glyf),
+        (*b"head", head),
+        (*b"hhea", hhea),
+        (*b"hmtx", hmtx),
+        (*b"loca", loca),
+        (*b"maxp", maxp),
+        (*b"post", post),
+    ];
+    (write_sfnt(0x0001_0000, tables), contours)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Records the outline of a glyph as text.
+    #[derive(Default)]
+    struct Outline(Vec<String>);
+
+    impl ttf_parser::OutlineBuilder for Outline {
+        fn move_to(&mut self, x: f32, y: f32) {
+            self.0.push(format!("M {} {}", x, y));
+        }
+        fn line_to(&mut self, x: f32, y: f32) {
+            self.0.push(format!("L {} {}", x, y));
+        }
+        fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+            self.0.push(format!("Q {} {} {} {}", x1, y1, x, y));
+        }
+        fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+            self.0
+                .push(format!("C {} {} {} {} {} {}", x1, y1, x2, y2, x, y));
+        }
+        fn close(&mut self) {
+            self.0.push("Z".to_string());
+        }
+    }
+
+    /// The outline, bounding box, advance and left side bearing of a glyph.
+    type Glyph = (
+        Vec<String>,
+        Option<ttf_parser::Rect>,
+        Option<u16>,
+        Option<i16>,
+    );
+
+    /// Describes the glyphs of a font.
+    fn glyphs(font: &[u8]) -> Vec<Glyph> {
+        let face = ttf_parser::Face::parse(font, 0).unwrap();
+        (0..face.number_of_glyphs())
+            .map(|id| {
+                let id = ttf_parser::GlyphId(id);
+                let mut outline = Outline::default();
+                let bbox = face.outline_glyph(id, &mut outline);
+                let advance = face.glyph_hor_advance(id);
+                (outline.0, bbox, advance, face.glyph_hor_side_bearing(id))
+            })
+            .collect()
+    }
+
+    /// Test that the checksums of assembled fonts add up
+    #[test]
+    fn test_write_sfnt() {
+        let (font, _) = sample_font();
+        assert_eq!(checksum(&font), 0xB1B0_AFBA);
+        let (flavor, tables) = read_sfnt(&font).unwrap();
+        assert_eq!(flavor, 0x0001_0000);
+        assert_eq!(tables.len(), 8);
+        let face = ttf_parser::Face::parse(&font, 0).unwrap();
+        assert_eq!(face.glyph_index('B'), Some(ttf_parser::GlyphId(2)));
+        assert_eq!(glyphs(&font)[2].0.len(), glyphs(&font)[1].0.len());
+    }
+
+    /// Test that fonts survive an encoding as WOFF and WOFF2
+    #[test]
+    fn test_round_trip() {
+        let (font, _) = sample_font();
+        let woff = encode_woff(&font).unwrap();
+        assert_eq!(&woff[..4], b"wOFF");
+        assert_eq!(
+            woff.len() as u32,
+            u32::from_be_bytes(woff[8..12].try_into().unwrap())
+        );
+        assert_eq!(decode_woff(&woff).unwrap(), font);
+
+        let woff2 = encode_woff2(&font).unwrap();
+        assert_eq!(&woff2[..4], b"wOF2");
+        assert_eq!(woff2.len() % 4, 0);
+        assert_eq!(decode_woff2(&woff2).unwrap(), font);
+
+        assert!(decode_woff2(&woff2[..60]).is_err());
+        assert!(decode_woff(&woff[..50]).is_err());
+    }
+
+    /// Test that variable length integers are read back
+    #[test]
+    fn test_integers() {
+        for value in [0, 63, 127, 128, 16_383, 16_384, u32::MAX >> 4] {
+            let mut output = Vec::new();
+            write_base128(&mut output, value);
+            assert_eq!(Reader::new(&output).base128(), Ok(value));
+        }
+        assert!(Reader::new(&[0x80, 0x01]).base128().is_err());
+        let values = [7u8, 253, 0x01, 0x2C, 255, 10, 254, 0];
+        let mut reader = Reader::new(&values);
+        let decoded: Vec<u16> = (0..4).map(|_| reader.uint255().unwrap()).collect();
+        assert_eq!(decoded, [7, 300, 263, 506]);
+    }
+
+    /// Test that the transformed `glyf`, `loca` and `hmtx` tables of WOFF2 are decoded
+    #[test]
+    fn test_decode_transformed() {
+        let (font, contours) = sample_font();
+        let (flavor, tables) = read_sfnt(&font).unwrap();
+
+        // Every point is encoded with the 4 byte triplet, whose flag carries the signs
+        let mut num_points = Vec::new();
+        let (mut flags, mut glyph_stream) = (Vec::new(), Vec::new());
+        let mut last = (0i16, 0i16);
+        for contour in &contours {
+            num_points.push(contour.len() as u8);
+            for (x, y, on_curve) in contour {
+                let (dx, dy) = (x - last.0, y - last.1);
+                let signs = u8::from(dx >= 0) | u8::from(dy >= 0) << 1;
+                flags.push(124 + signs + if *on_curve { 0 } else { 0x80 });
+                glyph_stream.extend(dx.unsigned_abs().to_be_bytes());
+                glyph_stream.extend(dy.unsigned_abs().to_be_bytes());
+                last = (*x, *y);
+            }
+        }
+        glyph_stream.push(2);
+        let composites = [0, 3, 0, 1, 2, 188, 0, 0];
+        let mut bboxes = vec![0x20, 0, 0, 0];
+        bboxes.extend(
+            [750i16, 0, 1350, 700]
+                .iter()
+                .flat_map(|value| value.to_be_bytes()),
+        );
+        let streams: [&[u8]; 7] = [
+            &[0, 0, 0, 2, 0xFF, 0xFF],
+            &num_points,
+            &flags,
+            &glyph_stream,
+            &composites,
+            &bboxes,
+            &[0xB0, 0x01],
+        ];
+        let mut glyf = vec![0, 0, 0, 0, 0, 3, 0, 0];
+        for stream in streams {
+            glyf.extend((stream.len() as u32).to_be_bytes());
+        }
+        glyf.extend(streams.concat());
+        // Both left side bearings are omitted
+        let hmtx = vec![3, 0x01, 0xF4, 0x02, 0xBC];
+
+        let mut directory = Vec::new();
+        let mut stream = Vec::new();
+        for (tag, data) in &tables {
+            let index = KNOWN_TAGS.iter().position(|known| *known == tag).unwrap();
+            let data = match tag {
+                b"glyf" => &glyf,
+                b"loca" => &Vec::new(),
+                b"hmtx" => &hmtx,
+                _ => data,
+            };
+            directory.push(index as u8 | if tag == b"hmtx" { 0x40 } else { 0 });
+            let len = tables.iter().find(|(name, _)| name == tag).unwrap().1.len();
+            write_base128(&mut directory, len as u32);
+            if matches!(tag, b"glyf" | b"loca" | b"hmtx") {
+                write_base128(&mut directory, data.len() as u32);
+            }
+            stream.extend(data);
+        }
+        let mut compressed = Vec::new();
+        brotli::CompressorWriter::new(&mut compressed, 4096, 5, 22)
+            .write_all(&stream)
+            .unwrap();
+        let mut woff2 = b"wOF2".to_vec();
+        woff2.extend(flavor.to_be_bytes());
+        woff2.extend([0; 4]);
+        woff2.extend((tables.len() as u16).to_be_bytes());
+        woff2.extend([0; 6]);
+        woff2.extend((compressed.len() as u32).to_be_bytes());
+        woff2.extend([0; 24]);
+        woff2.extend(directory);
+        woff2.extend(compressed);
+
+        let decoded = decode_woff2(&woff2).unwrap();
+        assert_eq!(checksum(&decoded), 0xB1B0_AFBA);
+        assert_eq!(glyphs(&decoded), glyphs(&font));
+        let (_, decoded_tables) = read_sfnt(&decoded).unwrap();
+        let hmtx = |tables: &[Table]| tables.iter().find(|(tag, _)| tag == b"hmtx").cloned();
+        assert_eq!(hmtx(&decoded_tables), hmtx(&tables));
+    }
+}
@@ -0,0 +1,80 @@
+//! Set generation for the word lists included by `include_wordlist_url!`.
+//!
+//! A word list has one word per line, such as a dictionary, a list of stop words or a
+//! blocklist. The words are embedded as a sorted static slice that the generated
+//! `contains` function binary searches.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Reads the words of a list, one per line with surrounding whitespace and blank lines
+/// ignored, and sorts them.
+///
+/// Words are lowercased if `lowercase` is set, and duplicates are removed if `dedup` is.
+pub(crate) fn parse(content: &str, lowercase: bool, dedup: bool) -> Result<Vec<String>, String> {
+    let mut words: Vec<String> = content
+        .lines()
+        .map(str::trim)
+        .filter(|word| !word.is_empty())
+        .map(|word| match lowercase {
+            true => word.to_lowercase(),
+            false => word.to_string(),
+        })
+        .collect();
+    if words.is_empty() {
+        return Err("Invalid word list from URL: no words found".to_string());
+    }
+    words.sort_unstable();
+    if dedup {
+        words.dedup();
+    }
+    Ok(words)
+}
+
+/// Generates the `WORDS` slice and its `contains` function, which lowercases the word it
+/// looks up if the list was lowercased.
+pub(crate) fn generate(words: &[String], lowercase: bool) -> TokenStream {
+    let (doc, compare) = match lowercase {
+        true => (
+            "Returns whether the list contains `word`, ignoring case.",
+            quote! { key.chars().cmp(word.chars().flat_map(char::to_lowercase)) },
+        ),
+        false => (
+            "Returns whether the list contains `word`.",
+            quote! { key.cmp(&word) },
+        ),
+    };
+    quote! {
+        /// The words of the list, sorted.
+        pub const WORDS: &[&str] = &[#(#words),*];
+
+        #[doc = #doc]
+        pub fn contains(word: &str) -> bool {
+            WORDS.binary_search_by(|key| #compare).is_ok()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that words are trimmed, sorted and optionally lowercased and deduplicated
+    #[test]
+    fn test_parse() {
+        let list = "the\n  And \n\nÉTÉ\nthe\nand\n";
+        assert_eq!(
+            parse(list, false, true).unwrap(),
+            ["And", "and", "the", "ÉTÉ"]
+        );
+        assert_eq!(parse(list, true, true).unwrap(), ["and", "the", "été"]);
+        assert_eq!(
+            parse(list, true, false).unwrap(),
+            ["and", "and", "the", "the", "été"]
+        );
+        assert!(parse("\n  \n", false, true).is_err());
+
+        let code = generate(&parse(list, true, true).unwrap(), true).to_string();
+        assert!(code.contains("pub const WORDS : & [& str] = & [\"and\" , \"the\" , \"été\"]"));
+    }
+}
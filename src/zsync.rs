@@ -0,0 +1,499 @@
+//! Delta refresh of large content described by a zsync control file.
+//!
+//! A `.zsync` control file, as written by `zsyncmake`, holds the length and SHA-1 digest of
+//! the content and a weak rolling checksum and a truncated MD4 checksum of each of its
+//! blocks. When cached content given `zsync = "..."` is due for revalidation, the control
+//! file is fetched instead of the content. Unchanged content is kept as is. Otherwise the
+//! blocks of the new content found anywhere in the cached content are copied from it, and
+//! only the missing ones are requested from the URL of the content with `Range` requests.
+//! The result has to match the SHA-1 digest of the control file, and is then checked and
+//! cached like a download.
+//!
+//! Control files of compressed content (`Z-URL`) are not supported.
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufWriter, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use md4::{Digest, Md4};
+use reqwest::{
+    blocking::Client,
+    header::{HeaderMap, AGE, CACHE_CONTROL, EXPIRES, RANGE},
+    Method, StatusCode,
+};
+use url::Url;
+
+use crate::{Body, CompressKind, Failure, FetchOptions, Fetched, Paginate};
+
+/// Bytes of the cached content read at a time while looking for blocks.
+const READ_SIZE: usize = 1 << 20;
+
+/// The block checksums of the new content, from a control file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ControlFile {
+    /// Size of the blocks in bytes, the last one is padded with zeros.
+    pub block_size: usize,
+    /// Size of the content in bytes.
+    pub length: u64,
+    /// Number of consecutive blocks that have to match for a block to be used.
+    pub seq_matches: usize,
+    /// Number of bytes of the rolling checksum kept for each block.
+    pub rsum_bytes: usize,
+    /// Number of bytes of the MD4 checksum kept for each block.
+    pub checksum_bytes: usize,
+    /// Lowercase hex SHA-1 digest of the content.
+    pub sha1: String,
+    /// Rolling checksum of each block, as masked by `rsum_bytes`.
+    pub rsums: Vec<u32>,
+    /// Truncated MD4 checksum of each block.
+    pub checksums: Vec<Vec<u8>>,
+}
+
+impl ControlFile {
+    /// Parses a control file, its `Key: value` header lines and then its block checksums.
+    pub(crate) fn parse(content: &[u8]) -> Result<Self, String> {
+        let end = (content.windows(2).position(|bytes| bytes == b"\n\n"))
+            .ok_or("The control file has no block checksums")?;
+        let header = std::str::from_utf8(&content[..end])
+            .map_err(|_| "The header of the control file is not UTF-8".to_string())?;
+        let fields: HashMap<&str, &str> = (header.lines())
+            .filter_map(|line| line.split_once(':'))
+            .map(|(key, value)| (key.trim(), value.trim()))
+            .collect();
+        let field = |name: &str| {
+            (fields.get(name).copied())
+                .ok_or_else(|| format!("The control file has no `{}` field", name))
+        };
+        let number = |name: &str| {
+            field(name)?
+                .parse::<u64>()
+                .map_err(|_| format!("Invalid `{}` in the control file", name))
+        };
+        field("zsync")?;
+        let block_size = number("Blocksize")? as usize;
+        let length = number("Length")?;
+        let lengths: Vec<usize> = (field("Hash-Lengths")?.split(','))
+            .map(|length| length.trim().parse().ok())
+            .collect::<Option<_>>()
+            .ok_or("Invalid `Hash-Lengths` in the control file")?;
+        let [seq_matches, rsum_bytes, checksum_bytes] = lengths[..] else {
+            return Err("Invalid `Hash-Lengths` in the control file".to_string());
+        };
+        if block_size == 0 || !(1..=4).contains(&rsum_bytes) || !(1..=16).contains(&checksum_bytes)
+        {
+            return Err("Invalid block checksum sizes in the control file".to_string());
+        }
+        let sha1 = field("SHA-1")?.to_ascii_lowercase();
+        if sha1.len() != 40 || !sha1.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err("Invalid `SHA-1` in the control file".to_string());
+        }
+
+        let blocks = length.div_ceil(block_size as u64) as usize;
+        let sums = &content[end + 2..];
+        let size = rsum_bytes + checksum_bytes;
+        if sums.len() != blocks * size {
+            return Err(format!(
+                "The control file has {} bytes of block checksums, expected {}",
+                sums.len(),
+                blocks * size
+            ));
+        }
+        let (rsums, checksums) = sums
+            .chunks(size)
+            .map(|sum| {
+                let rsum =
+                    (sum[..rsum_bytes].iter()).fold(0, |rsum, byte| rsum << 8 | *byte as u32);
+                (rsum, sum[rsum_bytes..].to_vec())
+            })
+            .unzip();
+        Ok(ControlFile {
+            block_size,
+            length,
+            seq_matches,
+            rsum_bytes,
+            checksum_bytes,
+            sha1,
+            rsums,
+            checksums,
+        })
+    }
+
+    /// Keeps the bytes of a rolling checksum that the control file holds.
+    fn mask(&self, rsum: u32) -> u32 {
+        match self.rsum_bytes {
+            4 => rsum,
+            bytes => rsum & ((1 << (bytes * 8)) - 1),
+        }
+    }
+
+    /// Whether `block` holds the data of the block `index`.
+    fn matches(&self, index: usize, block: &[u8]) -> bool {
+        self.checksums[index][..] == Md4::digest(block)[..self.checksum_bytes]
+    }
+
+    /// Finds the blocks of the new content in `old`, returning the offset each block was
+    /// found at.
+    pub(crate) fn find_blocks(&self, old: impl Read) -> Result<Vec<Option<u64>>, String> {
+        let mut by_rsum: HashMap<u32, Vec<usize>> = HashMap::new();
+        for (index, rsum) in self.rsums.iter().enumerate() {
+            by_rsum.entry(*rsum).or_default().push(index);
+        }
+        let mut found = vec![None; self.rsums.len()];
+        let size = self.block_size;
+        let mut window = Window::new(old, size);
+        let mut position = 0;
+        let mut rsum = None;
+        loop {
+            window.fill(position + 2 * size)?;
+            let data = &window.data;
+            if position + size > data.len() {
+                return Ok(found);
+            }
+            let (a, b) = rsum.unwrap_or_else(|| rolling_checksum(&data[position..position + size]));
+            let candidates = by_rsum.get(&self.mask((a as u32) << 16 | b as u32));
+            let mut matched = false;
+            for &index in candidates.into_iter().flatten() {
+                if found[index].is_some() || !self.matches(index, &data[position..][..size]) {
+                    continue;
+                }
+                // Short checksums are confirmed by the block that follows
+                let next = data.get(position + size..position + 2 * size);
+                let confirmed = self.seq_matches < 2
+                    || index + 1 == found.len()
+                    || next.is_some_and(|next| self.matches(index + 1, next));
+                if confirmed {
+                    found[index] = Some(window.offset + position as u64);
+                    matched = true;
+                }
+            }
+            if matched {
+                position += size;
+                rsum = None;
+            } else if position + size < data.len() {
+                let (old, new) = (data[position], data[position + size]);
+                let a = a.wrapping_sub(old as u16).wrapping_add(new as u16);
+                let b = (b.wrapping_sub((size as u16).wrapping_mul(old as u16))).wrapping_add(a);
+                rsum = Some((a, b));
+                position += 1;
+            } else {
+                return Ok(found);
+            }
+            position = window.discard(position);
+        }
+    }
+}
+
+/// The part of the cached content being looked at, followed by a block of zeros once all
+/// of it was read, as the last block of the content is padded.
+struct Window<R> {
+    reader: R,
+    data: Vec<u8>,
+    /// Offset of the start of `data` in the content.
+    offset: u64,
+    block_size: usize,
+    done: bool,
+}
+
+impl<R: Read> Window<R> {
+    fn new(reader: R, block_size: usize) -> Self {
+        Window {
+            reader,
+            data: Vec::new(),
+            offset: 0,
+            block_size,
+            done: false,
+        }
+    }
+
+    /// Reads until `end` bytes are available, or the content ends.
+    fn fill(&mut self, end: usize) -> Result<(), String> {
+        while self.data.len() < end && !self.done {
+            let start = self.data.len();
+            self.data.resize(start + READ_SIZE, 0);
+            let read = (self.reader.read(&mut self.data[start..]))
+                .map_err(|e| format!("Failed to read the cached content: {}", e))?;
+            self.data.truncate(start + read);
+            if read == 0 {
+                self.data.resize(start + self.block_size, 0);
+                self.done = true;
+            }
+        }
+        Ok(())
+    }
+
+    /// Drops the data before `position` once enough of it was looked at, returning where
+    /// `position` is then.
+    fn discard(&mut self, position: usize) -> usize {
+        if position < READ_SIZE {
+            return position;
+        }
+        self.data.drain(..position);
+        self.offset += position as u64;
+        0
+    }
+}
+
+/// The rolling checksum of a block as computed by zsync, the sum of its bytes and the sum
+/// of its bytes weighted by their distance from its end.
+fn rolling_checksum(block: &[u8]) -> (u16, u16) {
+    let mut a = 0u16;
+    let mut b = 0u16;
+    for (index, byte) in block.iter().enumerate() {
+        a = a.wrapping_add(*byte as u16);
+        b = b.wrapping_add(((block.len() - index) as u16).wrapping_mul(*byte as u16));
+    }
+    (a, b)
+}
+
+/// Refreshes the cached content of `url_str` in `cache_file` from the control file at
+/// `control_url`, returning the patched content, or [`Fetched::NotModified`] if it did not
+/// change.
+pub(crate) fn refresh(
+    url_str: &str,
+    control_url: &str,
+    cache_file: &Path,
+    compress_kind: &CompressKind,
+    options: &FetchOptions,
+) -> Result<Fetched, String> {
+    if *compress_kind != CompressKind::None {
+        return Err("compressed content cannot be patched".to_string());
+    }
+    if options.canonicalize || options.text || options.charset.is_some() {
+        return Err("only content embedded as it is sent can be patched".to_string());
+    }
+    if crate::request_method(options) != Method::GET {
+        return Err("only content fetched with `GET` can be patched".to_string());
+    }
+    let url = crate::parse_url(url_str)?;
+
+    let control_options = FetchOptions {
+        sha256: None,
+        integrity: None,
+        signature_url: None,
+        pgp_key: None,
+        minisign_pub: None,
+        sigstore_identity: None,
+        zsync: None,
+        paginate: Paginate::None,
+        partial: None,
+        revalidate: None,
+        ..options.clone()
+    };
+    let (headers, control) = match crate::fetch_url_content(control_url, &control_options)? {
+        Fetched::Content(headers, body) => (headers, body.into_bytes()?),
+        Fetched::NotModified(_) => unreachable!("the control file request is not conditional"),
+    };
+    let control = ControlFile::parse(&control)
+        .map_err(|e| format!("Invalid control file `{}`: {}", control_url, e))?;
+    crate::check_size(control.length, options).map_err(|failure| failure.message)?;
+    // The validators of the control file do not apply to the content
+    let mut kept = HeaderMap::new();
+    for name in [CACHE_CONTROL, EXPIRES, AGE] {
+        if let Some(value) = headers.get(&name) {
+            kept.insert(name, value.clone());
+        }
+    }
+
+    let open = || File::open(cache_file).map_err(|e| format!("Failed to open cache file: {}", e));
+    if sha1(open()?)? == control.sha1 {
+        return Ok(Fetched::NotModified(kept));
+    }
+    let found = control.find_blocks(std::io::BufReader::new(open()?))?;
+    let patched = cache_file.with_extension("patch");
+    let written = (|| {
+        // The ranges are fetched like the content, with the crate wide defaults
+        let options = crate::with_config_defaults(options)?;
+        let client = crate::http_client(&url, &options)?;
+        let mut output = BufWriter::new(
+            File::create(&patched)
+                .map_err(|e| format!("Failed to create the patched file: {}", e))?,
+        );
+        write_blocks(&control, &found, &mut open()?, &mut output, |start, end| {
+            fetch_range(&client, &url, start, end, &options)
+        })?;
+        (output.into_inner())
+            .map_err(|e| format!("Failed to write the patched file: {}", e.error()))?
+            .set_len(control.length)
+            .map_err(|e| format!("Failed to write the patched file: {}", e))?;
+        let digest = sha1(File::open(&patched).map_err(|e| e.to_string())?)?;
+        match digest == control.sha1 {
+            true => Ok(()),
+            false => Err(format!(
+                "SHA-1 mismatch of the patched content: expected {}, got {}",
+                control.sha1, digest
+            )),
+        }
+    })();
+    match written {
+        Ok(()) => Ok(Fetched::Content(kept, Body::File(patched))),
+        Err(e) => {
+            let _ = std::fs::remove_file(&patched);
+            Err(e)
+        }
+    }
+}
+
+/// Writes the blocks of the new content to `output` in order, copying the blocks found in
+/// `old` and fetching the runs of missing blocks, given as inclusive byte ranges, with
+/// `fetch_range`.
+fn write_blocks(
+    control: &ControlFile,
+    found: &[Option<u64>],
+    old: &mut (impl Read + Seek),
+    output: &mut impl Write,
+    mut fetch_range: impl FnMut(u64, u64) -> Result<Vec<u8>, String>,
+) -> Result<(), String> {
+    let size = control.block_size;
+    let write_error = |e: std::io::Error| format!("Failed to write the patched file: {}", e);
+    let mut block = vec![0; size];
+    let mut index = 0;
+    while index < found.len() {
+        if let Some(offset) = found[index] {
+            // Blocks at the end of the cached content are padded like the last block
+            block.fill(0);
+            old.seek(SeekFrom::Start(offset))
+                .map_err(|e| format!("Failed to read the cached content: {}", e))?;
+            let mut filled = 0;
+            while filled < size {
+                match old.read(&mut block[filled..]) {
+                    Ok(0) => break,
+                    Ok(read) => filled += read,
+                    Err(e) => return Err(format!("Failed to read the cached content: {}", e)),
+                }
+            }
+            output.write_all(&block).map_err(write_error)?;
+            index += 1;
+            continue;
+        }
+        let end = (index..found.len())
+            .find(|index| found[*index].is_some())
+            .unwrap_or(found.len());
+        let start = (index * size) as u64;
+        let last = ((end * size) as u64).min(control.length) - 1;
+        let content = fetch_range(start, last)?;
+        output.write_all(&content).map_err(write_error)?;
+        // The last block is padded until the file is cut to its length
+        let padding = (end - index) * size - content.len();
+        output.write_all(&vec![0; padding]).map_err(write_error)?;
+        index = end;
+    }
+    Ok(())
+}
+
+/// Requests the inclusive byte range from `start` to `end` of the content.
+fn fetch_range(
+    client: &Client,
+    url: &Url,
+    start: u64,
+    end: u64,
+    options: &FetchOptions,
+) -> Result<Vec<u8>, String> {
+    let host = url.host_str().unwrap_or_default();
+    crate::with_retries(host, options, || {
+        let response = crate::build_request(client, Method::GET, url.clone(), options)?
+            .header(RANGE, format!("bytes={}-{}", start, end))
+            .send()
+            .map_err(|e| crate::request_error(e, options, "Failed to fetch a range"))?;
+        let mut response = crate::check_status(response, options)?;
+        if response.status() != StatusCode::PARTIAL_CONTENT {
+            return Err(Failure::from(format!(
+                "`{}` answered a range request with {}",
+                url,
+                response.status()
+            )));
+        }
+        let mut content = Vec::new();
+        response
+            .read_to_end(&mut content)
+            .map_err(|e| format!("Failed to read a range: {}", e))?;
+        match content.len() as u64 == end - start + 1 {
+            true => Ok(content),
+            false => Err(Failure {
+                message: format!("Range {}-{} of `{}` was cut short", start, end, url),
+                transient: true,
+                retry_after: None,
+            }),
+        }
+    })
+}
+
+/// Returns the lowercase hex SHA-1 digest of the content read from `reader`.
+fn sha1(mut reader: impl Read) -> Result<String, String> {
+    let mut context = ring::digest::Context::new(&ring::digest::SHA1_FOR_LEGACY_USE_ONLY);
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => context.update(&buffer[..read]),
+            Err(e) => return Err(format!("Failed to read the cached content: {}", e)),
+        }
+    }
+    let digest = context.finish();
+    Ok(digest
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes the control file of `content` as `zsyncmake` does.
+    fn control_file(content: &[u8], block_size: usize, rsum_bytes: usize) -> Vec<u8> {
+        let mut control = format!(
+            "zsync: 0.6.2\nFilename: content.bin\nBlocksize: {}\nLength: {}\n\
+             Hash-Lengths: 2,{},6\nURL: content.bin\nSHA-1: {}\n\n",
+            block_size,
+            content.len(),
+            rsum_bytes,
+            sha1(content).unwrap()
+        )
+        .into_bytes();
+        for block in content.chunks(block_size) {
+            let mut block = block.to_vec();
+            block.resize(block_size, 0);
+            let (a, b) = rolling_checksum(&block);
+            let rsum = [a.to_be_bytes(), b.to_be_bytes()].concat();
+            control.extend(&rsum[4 - rsum_bytes..]);
+            control.extend(&Md4::digest(&block)[..6]);
+        }
+        control
+    }
+
+    /// Test that the blocks shared with the cached content are copied and the rest fetched
+    #[test]
+    fn test_patch() {
+        let old: Vec<u8> = (0..20_000u32)
+            .flat_map(|i| i.wrapping_mul(2_654_435_761).to_le_bytes())
+            .collect();
+        let mut new = old.clone();
+        new.splice(1000..1000, *b"inserted");
+        new[50_000] ^= 1;
+        new.truncate(70_003);
+
+        let control = ControlFile::parse(&control_file(&new, 1024, 3)).unwrap();
+        assert_eq!((control.length, control.rsums.len()), (70_003, 69));
+        let found = control.find_blocks(&old[..]).unwrap();
+        let mut fetched = 0;
+        let mut output = Vec::new();
+        let mut cached = std::io::Cursor::new(&old);
+        write_blocks(&control, &found, &mut cached, &mut output, |start, end| {
+            fetched += end - start + 1;
+            Ok(new[start as usize..=end as usize].to_vec())
+        })
+        .unwrap();
+        output.truncate(new.len());
+        assert_eq!(output, new);
+        assert_eq!(sha1(&output[..]).unwrap(), control.sha1);
+        // The first block, the changed one, the short last one, and the blocks before the
+        // latter two, as a match is confirmed by the next block
+        assert_eq!(fetched, 4 * 1024 + 371);
+
+        assert!(ControlFile::parse(b"zsync: 0.6.2\nBlocksize: 1024\n\n").is_err());
+    }
+}
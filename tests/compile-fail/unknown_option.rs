@@ -0,0 +1,5 @@
+use include_url_macro::include_url;
+
+fn main() {
+    let _content = include_url!("https://example.com", unknown = 3);
+}
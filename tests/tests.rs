@@ -69,6 +69,15 @@ mod tests {
         assert!(json["body"].as_str().unwrap().len() > 0);
     }
 
+    // Test that compressed responses are decoded before they are embedded
+    #[test]
+    fn test_include_compressed_response() {
+        let gzip = include_json_url!("https://httpbin.org/gzip");
+        assert_eq!(gzip["gzipped"], true);
+        let brotli = include_json_url!("https://httpbin.org/brotli");
+        assert_eq!(brotli["brotli"], true);
+    }
+
     // Test for parsing into a specific type
     #[derive(Deserialize, Debug, PartialEq)]
     struct Post {
@@ -86,4 +95,640 @@ mod tests {
         assert!(!post.title.is_empty());
         assert!(!post.body.is_empty());
     }
+
+    mod posts {
+        pub fn title_from_value(value: serde_json::Value) -> Result<String, String> {
+            value["title"]
+                .as_str()
+                .map(str::to_string)
+                .ok_or_else(|| "missing title".to_string())
+        }
+    }
+
+    #[test]
+    fn test_include_json_url_with() {
+        let title: String = include_json_url!(
+            "https://jsonplaceholder.typicode.com/posts/1",
+            String,
+            with = posts::title_from_value
+        );
+        assert!(!title.is_empty());
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_include_yaml_url() {
+        let workflow = include_url_macro::include_yaml_url!(
+            "https://raw.githubusercontent.com/rust-lang/rust/master/.github/workflows/ci.yml"
+        );
+        assert!(workflow["jobs"].is_mapping());
+    }
+
+    #[derive(Deserialize)]
+    struct Manifest {
+        package: Package,
+    }
+
+    #[derive(Deserialize)]
+    struct Package {
+        name: String,
+    }
+
+    #[test]
+    fn test_include_toml_url() {
+        let manifest = include_url_macro::include_toml_url!(
+            "https://raw.githubusercontent.com/serde-rs/serde/master/serde/Cargo.toml",
+            Manifest
+        );
+        assert_eq!(manifest.package.name, "serde");
+    }
+
+    #[cfg(feature = "csv")]
+    #[derive(Deserialize)]
+    struct Country {
+        #[allow(dead_code)]
+        name: String,
+        code: String,
+    }
+
+    #[cfg(feature = "csv")]
+    #[test]
+    fn test_include_csv_url() {
+        let countries = include_url_macro::include_csv_url!(
+            "https://raw.githubusercontent.com/datasets/country-list/main/data.csv",
+            Country
+        );
+        assert!(countries.iter().any(|country| country.code == "NL"));
+    }
+
+    #[cfg(feature = "xml")]
+    #[derive(Deserialize)]
+    struct Rss {
+        channel: Channel,
+    }
+
+    #[cfg(feature = "xml")]
+    #[derive(Deserialize)]
+    struct Channel {
+        title: String,
+    }
+
+    #[cfg(feature = "xml")]
+    #[test]
+    fn test_include_xml_url() {
+        let xml = include_url_macro::include_xml_url!("https://blog.rust-lang.org/feed.xml");
+        assert!(xml.contains("<feed"));
+
+        let rss = include_url_macro::include_xml_url!("https://this-week-in-rust.org/rss.xml", Rss);
+        assert!(rss.channel.title.contains("Rust"));
+    }
+
+    #[cfg(any(feature = "msgpack", feature = "cbor"))]
+    #[derive(Deserialize)]
+    struct Named {
+        name: String,
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn test_include_msgpack_url() {
+        // httpbin decodes the path, serving the MessagePack map {"name": "demo"}
+        let bytes =
+            include_url_macro::include_msgpack_url!("https://httpbin.org/base64/gaRuYW1lpGRlbW8=");
+        assert_eq!(bytes, b"\x81\xa4name\xa4demo");
+
+        let named = include_url_macro::include_msgpack_url!(
+            "https://httpbin.org/base64/gaRuYW1lpGRlbW8=",
+            Named
+        );
+        assert_eq!(named.name, "demo");
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn test_include_cbor_url() {
+        // httpbin decodes the path, serving the CBOR map {"name": "demo"}
+        let value =
+            include_url_macro::include_cbor_url!("https://httpbin.org/base64/oWRuYW1lZGRlbW8=");
+        assert!(value.is_map());
+
+        let named = include_url_macro::include_cbor_url!(
+            "https://httpbin.org/base64/oWRuYW1lZGRlbW8=",
+            Named
+        );
+        assert_eq!(named.name, "demo");
+    }
+
+    #[test]
+    fn test_include_wasm_url() {
+        // httpbin decodes the path, serving an empty WebAssembly module
+        const MODULE: &[u8] =
+            include_url_macro::include_wasm_url!("https://httpbin.org/base64/AGFzbQEAAAA=");
+        assert_eq!(MODULE, b"\0asm\x01\0\0\0");
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn test_include_wasm_url_validated() {
+        let module = include_url_macro::include_wasm_url!(
+            "https://httpbin.org/base64/AGFzbQEAAAA=",
+            validate = true
+        );
+        assert_eq!(module.len(), 8);
+    }
+
+    // httpbin decodes the path, serving a white 2x2 PNG
+    #[cfg(feature = "image")]
+    include_url_macro::include_image_url!(
+        mod white,
+        "https://httpbin.org/base64/iVBORw0KGgoAAAANSUhEUgAAAAIAAAACCAYAAABytg0kAAAADklEQVR4nGP4DwUMMAYAj4IP8TylVlEAAAAASUVORK5CYII=",
+        pixels = "rgb8",
+    );
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_include_image_url() {
+        assert_eq!((white::WIDTH, white::HEIGHT), (2, 2));
+        assert_eq!(white::FORMAT, "rgb8");
+        assert_eq!(white::BYTES_PER_PIXEL, 3);
+        assert_eq!(white::PIXELS, [255; 12]);
+    }
+
+    #[cfg(feature = "image")]
+    include_url_macro::include_image_url!(
+        mod white_mono,
+        "https://httpbin.org/base64/iVBORw0KGgoAAAANSUhEUgAAAAIAAAACCAYAAABytg0kAAAADklEQVR4nGP4DwUMMAYAj4IP8TylVlEAAAAASUVORK5CYII=",
+        pixels = "mono1",
+    );
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_include_image_url_mono1() {
+        assert_eq!(white_mono::BITS_PER_PIXEL, 1);
+        assert_eq!(white_mono::STRIDE, 1);
+        assert_eq!(white_mono::PIXELS, [0b1100_0000; 2]);
+        assert_eq!(white_mono::PIXELS.as_ptr() as usize % 4, 0);
+    }
+
+    #[cfg(feature = "image")]
+    include_url_macro::include_image_url!(
+        mod white_icon,
+        "https://httpbin.org/base64/iVBORw0KGgoAAAANSUhEUgAAAAIAAAACCAYAAABytg0kAAAADklEQVR4nGP4DwUMMAYAj4IP8TylVlEAAAAASUVORK5CYII=",
+        resize = (1, 1),
+        format = "png",
+    );
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_include_image_url_resized() {
+        assert_eq!((white_icon::WIDTH, white_icon::HEIGHT), (1, 1));
+        assert_eq!(white_icon::FORMAT, "png");
+        assert!(white_icon::BYTES.starts_with(b"\x89PNG"));
+    }
+
+    #[cfg(feature = "font")]
+    #[test]
+    fn test_include_font_url() {
+        const DIGITS: &[u8] = include_url_macro::include_font_url!(
+            "https://raw.githubusercontent.com/google/fonts/main/ofl/lato/Lato-Regular.ttf",
+            chars = "0123456789",
+        );
+        const FULL: &[u8] = include_url_macro::include_font_url!(
+            "https://raw.githubusercontent.com/google/fonts/main/ofl/lato/Lato-Regular.ttf"
+        );
+        assert!(DIGITS.len() < FULL.len() / 2);
+    }
+
+    #[cfg(feature = "svg")]
+    #[test]
+    fn test_include_svg_url() {
+        // httpbin decodes the URL-safe base64 path, serving an indented `<svg>` with a
+        // comment and a `<g>` around a `<rect>`
+        const ICON: &str = include_url_macro::include_svg_url!(
+            "https://httpbin.org/base64/PHN2ZyB2aWV3Qm94PSIwIDAgMSAxIj48IS0tIGljb24gLS0-CiAgPGc-CiAgICA8cmVjdCB3aWR0aD0iMSIgaGVpZ2h0PSIxIi8-CiAgPC9nPgo8L3N2Zz4K",
+            optimize = true,
+        );
+        assert_eq!(
+            ICON,
+            "<svg viewBox=\"0 0 1 1\"><rect width=\"1\" height=\"1\"/></svg>"
+        );
+    }
+
+    #[cfg(feature = "ron")]
+    #[test]
+    fn test_include_ron_url() {
+        let value = include_url_macro::include_ron_url!(
+            "https://raw.githubusercontent.com/ron-rs/ron/master/examples/example.ron"
+        );
+        assert!(matches!(value, ron::Value::Map(_)));
+    }
+
+    #[cfg(feature = "json5")]
+    #[test]
+    fn test_include_json5_url() {
+        // httpbin decodes the path, serving "{\n  // comment\n  \"tabSize\": 4,\n}\n"
+        let settings = include_url_macro::include_json5_url!(
+            "https://httpbin.org/base64/ewogIC8vIGNvbW1lbnQKICAidGFiU2l6ZSI6IDQsCn0K"
+        );
+        assert_eq!(settings["tabSize"], 4);
+    }
+
+    #[test]
+    fn test_include_graphql_url() {
+        let data = include_url_macro::include_graphql_url!(
+            "https://countries.trevorblades.com/",
+            query = "query Country($code: ID!) { country(code: $code) { name } }",
+            variables = r#"{"code": "CZ"}"#,
+        );
+        assert_eq!(data["country"]["name"], "Czechia");
+    }
+
+    include_url_macro::include_json_url_typed!(
+        "https://jsonplaceholder.typicode.com/posts/1",
+        name = "TypedPost"
+    );
+
+    #[test]
+    fn test_include_json_url_typed_named() {
+        assert_eq!(TYPED_POST.id, 1);
+        assert_eq!(TYPED_POST.user_id, 1);
+        assert!(!TYPED_POST.title.is_empty());
+    }
+
+    #[derive(Deserialize)]
+    struct Event {
+        id: u32,
+    }
+
+    #[test]
+    fn test_include_ndjson_url() {
+        // httpbin decodes the path, serving "{\"id\":1}\n{\"id\":2}\n"
+        let events = include_url_macro::include_ndjson_url!(
+            "https://httpbin.org/base64/eyJpZCI6MX0KeyJpZCI6Mn0K",
+            Event
+        );
+        assert_eq!(
+            events.iter().map(|event| event.id).collect::<Vec<_>>(),
+            [1, 2]
+        );
+
+        let values = include_url_macro::include_ndjson_url!(
+            "https://httpbin.org/base64/eyJpZCI6MX0KeyJpZCI6Mn0K"
+        );
+        assert_eq!(values[1]["id"], 2);
+    }
+
+    // httpbin decodes the path, serving "name = demo\n[database]\nhost = localhost\n"
+    include_url_macro::include_ini_url!(
+        mod app_ini,
+        "https://httpbin.org/base64/bmFtZSA9IGRlbW8KW2RhdGFiYXNlXQpob3N0ID0gbG9jYWxob3N0Cg=="
+    );
+
+    #[test]
+    fn test_include_ini_url() {
+        assert_eq!(app_ini::NAME, "demo");
+        assert_eq!(app_ini::database::HOST, "localhost");
+        assert_eq!(app_ini::database::ENTRIES, [("host", "localhost")]);
+    }
+
+    // httpbin decodes the path, serving "server.port=8080\ngreeting=Hello, \\\n  world\n"
+    include_url_macro::include_properties_url!(
+        mod app_properties,
+        "https://httpbin.org/base64/c2VydmVyLnBvcnQ9ODA4MApncmVldGluZz1IZWxsbywgXAogIHdvcmxkCg=="
+    );
+
+    #[test]
+    fn test_include_properties_url() {
+        assert_eq!(app_properties::SERVER_PORT, "8080");
+        assert_eq!(app_properties::GREETING, "Hello, world");
+    }
+
+    // httpbin decodes the path, serving "export API_URL=https://api.example.com\nREGION=eu\n"
+    include_url_macro::include_env_url!(
+        mod app_env,
+        "https://httpbin.org/base64/ZXhwb3J0IEFQSV9VUkw9aHR0cHM6Ly9hcGkuZXhhbXBsZS5jb20KUkVHSU9OPWV1Cg==",
+        keys = ["REGION"],
+    );
+
+    #[test]
+    fn test_include_env_url() {
+        assert_eq!(app_env::REGION, "eu");
+        assert_eq!(app_env::ENTRIES, [("REGION", "eu")]);
+    }
+
+    // httpbin decodes the path, serving "msgid \"Hello\"\nmsgstr \"Hallo\"\n"
+    include_url_macro::include_po_url!(
+        mod de_po,
+        "https://httpbin.org/base64/bXNnaWQgIkhlbGxvIgptc2dzdHIgIkhhbGxvIgo="
+    );
+
+    #[test]
+    fn test_include_po_url() {
+        assert_eq!(de_po::gettext("Hello"), "Hallo");
+        assert_eq!(de_po::gettext("World"), "World");
+        assert_eq!(de_po::ngettext("file", "files", 2), "files");
+    }
+
+    #[test]
+    fn test_include_geojson_url() {
+        // httpbin decodes the path, serving
+        // {"type":"LineString","coordinates":[[0,0],[1,0.01],[2,0],[3,1]]}
+        const LINE: &str = include_url_macro::include_geojson_url!(
+            "https://httpbin.org/base64/eyJ0eXBlIjoiTGluZVN0cmluZyIsImNvb3JkaW5hdGVzIjpbWzAsMF0sWzEsMC4wMV0sWzIsMF0sWzMsMV1dfQ=="
+        );
+        const PATHS: &[&[[f64; 2]]] = include_url_macro::include_geojson_url!(
+            "https://httpbin.org/base64/eyJ0eXBlIjoiTGluZVN0cmluZyIsImNvb3JkaW5hdGVzIjpbWzAsMF0sWzEsMC4wMV0sWzIsMF0sWzMsMV1dfQ==",
+            coordinates = true,
+            simplify = 0.1,
+        );
+        assert!(LINE.starts_with(r#"{"type":"LineString""#));
+        assert_eq!(PATHS, [[[0.0, 0.0], [2.0, 0.0], [3.0, 1.0]]]);
+    }
+
+    // httpbin decodes the path, serving a calendar with one all-day event on 2024-12-25
+    // with the UID "1" and the summary "Christmas"
+    include_url_macro::include_ical_url!(
+        mod holidays,
+        "https://httpbin.org/base64/QkVHSU46VkNBTEVOREFSCkJFR0lOOlZFVkVOVApVSUQ6MQpEVFNUQVJUO1ZBTFVFPURBVEU6MjAyNDEyMjUKU1VNTUFSWTpDaHJpc3RtYXMKRU5EOlZFVkVOVApFTkQ6VkNBTEVOREFSCg=="
+    );
+
+    #[test]
+    fn test_include_ical_url() {
+        assert_eq!(holidays::EVENTS.len(), 1);
+        assert_eq!(holidays::EVENTS[0].summary, "Christmas");
+        assert_eq!(holidays::EVENTS[0].start, "2024-12-25");
+        assert!(holidays::EVENTS[0].all_day);
+    }
+
+    // httpbin decodes the path, serving an ICANN section with the rules
+    // "uk", "co.uk", "*.ck" and "!www.ck"
+    include_url_macro::include_public_suffix_url!(
+        mod psl,
+        "https://httpbin.org/base64/Ly8gPT09QkVHSU4gSUNBTk4gRE9NQUlOUz09PQp1awpjby51awoqLmNrCiF3d3cuY2sK"
+    );
+
+    #[test]
+    fn test_include_public_suffix_url() {
+        assert_eq!(psl::public_suffix("www.Example.CO.uk."), Some("CO.uk"));
+        assert_eq!(psl::public_suffix("shop.example.ck"), Some("example.ck"));
+        assert_eq!(psl::public_suffix("www.ck"), Some("ck"));
+        assert_eq!(psl::public_suffix("example.org"), Some("org"));
+        assert_eq!(psl::public_suffix("example..org"), None);
+        assert_eq!(
+            psl::registrable_domain("www.example.co.uk"),
+            Some("example.co.uk")
+        );
+        assert_eq!(psl::registrable_domain("co.uk"), None);
+    }
+
+    // httpbin decodes the path, serving the mime.types lines "text/css css",
+    // "application/javascript js mjs", "text/javascript js" and "image/jpeg jpeg jpg"
+    include_url_macro::include_mime_db_url!(
+        mod mime_types,
+        "https://httpbin.org/base64/dGV4dC9jc3MgY3NzCmFwcGxpY2F0aW9uL2phdmFzY3JpcHQganMgbWpzCnRleHQvamF2YXNjcmlwdCBqcwppbWFnZS9qcGVnIGpwZWcganBnCg=="
+    );
+
+    #[test]
+    fn test_include_mime_db_url() {
+        assert_eq!(mime_types::from_extension(".CSS"), Some("text/css"));
+        assert_eq!(
+            mime_types::from_path("https://example.com/app.js"),
+            Some("application/javascript")
+        );
+        assert_eq!(mime_types::from_path("README"), None);
+        assert_eq!(mime_types::extensions("image/JPEG"), ["jpeg", "jpg"]);
+        assert!(mime_types::extensions("text/html").is_empty());
+    }
+
+    // httpbin decodes the path, serving "0041..005A ; Upper\n0061..007A ; Lower\n"
+    include_url_macro::include_unicode_data_url!(
+        mod letters,
+        "https://httpbin.org/base64/MDA0MS4uMDA1QSA7IFVwcGVyCjAwNjEuLjAwN0EgOyBMb3dlcgo=",
+        values = ["Upper"],
+    );
+
+    #[test]
+    fn test_include_unicode_data_url() {
+        assert_eq!(letters::UPPER, [('A', 'Z')]);
+        assert!(letters::is_upper('Q'));
+        assert!(!letters::is_upper('q'));
+    }
+
+    // httpbin decodes the path, serving the lines "the", "And", "of" and "the"
+    include_url_macro::include_wordlist_url!(
+        mod stop_words,
+        "https://httpbin.org/base64/dGhlCkFuZApvZgp0aGUK",
+        lowercase = true,
+    );
+
+    #[test]
+    fn test_include_wordlist_url() {
+        assert_eq!(stop_words::WORDS, ["and", "of", "the"]);
+        assert!(stop_words::contains("THE"));
+        assert!(stop_words::contains("and"));
+        assert!(!stop_words::contains("a"));
+    }
+
+    // httpbin decodes the path, serving the zic lines "Zone Asia/Tokyo 9:00 - JST" and
+    // "Link Asia/Tokyo Japan"
+    #[cfg(feature = "tzdata")]
+    include_url_macro::include_tzdata_url!(
+        mod tz,
+        "https://httpbin.org/base64/Wm9uZQlBc2lhL1Rva3lvCTk6MDAJLQlKU1QKTGluawlBc2lhL1Rva3lvCUphcGFuCg=="
+    );
+
+    #[cfg(feature = "tzdata")]
+    #[test]
+    fn test_include_tzdata_url() {
+        let offset = tz::ASIA_TOKYO.offset_at(1_750_000_000);
+        assert_eq!((offset.total(), offset.abbreviation), (9 * 3600, "JST"));
+        assert_eq!(tz::find("Japan"), Some(tz::JAPAN));
+        assert_eq!(tz::JAPAN.name(), "Japan");
+        assert_eq!(tz::find("Asia/Seoul"), None);
+    }
+
+    #[cfg(feature = "proto")]
+    mod addressbook {
+        include_url_macro::include_proto_url!(
+            "https://raw.githubusercontent.com/protocolbuffers/protobuf/main/examples/addressbook.proto"
+        );
+    }
+
+    #[cfg(feature = "proto")]
+    #[test]
+    fn test_include_proto_url() {
+        use prost::Message;
+
+        let person = addressbook::Person {
+            name: "Ferris".to_string(),
+            ..Default::default()
+        };
+        let decoded = addressbook::Person::decode(person.encode_to_vec().as_slice()).unwrap();
+        assert_eq!(decoded.name, "Ferris");
+    }
+
+    // httpbin decodes the path, serving "type User {\n  login: String!\n  followers: [User!]!\n}\n"
+    #[cfg(feature = "graphql")]
+    include_url_macro::include_graphql_schema_url!(
+        mod social,
+        "https://httpbin.org/base64/dHlwZSBVc2VyIHsKICBsb2dpbjogU3RyaW5nIQogIGZvbGxvd2VyczogW1VzZXIhXSEKfQo="
+    );
+
+    #[cfg(feature = "graphql")]
+    #[test]
+    fn test_include_graphql_schema_url() {
+        let user: social::User = serde_json::from_str(
+            r#"{"login": "ferris", "followers": [{"login": "corro", "followers": []}]}"#,
+        )
+        .unwrap();
+        assert_eq!(user.followers[0].login, "corro");
+    }
+
+    #[cfg(feature = "openapi")]
+    include_url_macro::include_openapi_url!(
+        mod petstore,
+        "https://petstore3.swagger.io/api/v3/openapi.json"
+    );
+
+    #[cfg(feature = "openapi")]
+    #[test]
+    fn test_include_openapi_url() {
+        assert_eq!(petstore::operations::GET_PET_BY_ID, ("GET", "/pet/{petId}"));
+        let pet: petstore::Pet =
+            serde_json::from_str(r#"{"name": "doggie", "photoUrls": []}"#).unwrap();
+        assert_eq!(pet.name, "doggie");
+    }
+
+    // httpbin decodes the path, serving a schema for an object with a required `name`
+    #[cfg(feature = "jsonschema")]
+    include_url_macro::include_jsonschema_url!(
+        mod named_schema,
+        "https://httpbin.org/base64/eyJ0eXBlIjoib2JqZWN0IiwicmVxdWlyZWQiOlsibmFtZSJdLCJwcm9wZXJ0aWVzIjp7Im5hbWUiOnsidHlwZSI6InN0cmluZyJ9fX0="
+    );
+
+    #[cfg(feature = "jsonschema")]
+    #[test]
+    fn test_include_jsonschema_url() {
+        let named: named_schema::NamedSchema = serde_json::from_str(r#"{"name": "demo"}"#).unwrap();
+        assert_eq!(named.name, "demo");
+        assert!(serde_json::from_str::<named_schema::NamedSchema>("{}").is_err());
+    }
+
+    #[cfg(feature = "avro")]
+    include_url_macro::include_avro_url!(
+        mod weather,
+        "https://raw.githubusercontent.com/apache/avro/main/share/test/data/weather-deflate.avro"
+    );
+
+    #[cfg(feature = "avro")]
+    #[test]
+    fn test_include_avro_url() {
+        let records = weather::records();
+        assert!(!records.is_empty());
+        assert!(records.iter().all(|record| !record.station.is_empty()));
+    }
+
+    #[cfg(feature = "parquet")]
+    include_url_macro::include_parquet_url!(
+        mod alltypes,
+        "https://raw.githubusercontent.com/apache/parquet-testing/master/data/alltypes_plain.parquet",
+        columns = ["id", "bool_col", "double_col"],
+    );
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn test_include_parquet_url() {
+        assert_eq!(alltypes::ROWS, 8);
+        assert_eq!(alltypes::ID.len(), alltypes::ROWS);
+        let ids: Vec<i32> = alltypes::ID.iter().flatten().copied().collect();
+        assert!(ids.contains(&0) && ids.contains(&7));
+        let _: &[Option<bool>] = alltypes::BOOL_COL;
+        let _: &[Option<f64>] = alltypes::DOUBLE_COL;
+    }
+
+    #[cfg(feature = "sqlite")]
+    include_url_macro::include_sqlite_url!(
+        mod chinook,
+        "https://raw.githubusercontent.com/lerocha/chinook-database/master/ChinookDatabase/DataSources/Chinook_Sqlite.sqlite",
+        query = "SELECT GenreId, Name FROM Genre ORDER BY GenreId",
+        row = Genre,
+    );
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_include_sqlite_url() {
+        let first: &chinook::Genre = &chinook::ROWS[0];
+        assert_eq!(first.genre_id, 1);
+        assert_eq!(first.name, "Rock");
+    }
+
+    #[cfg(feature = "pem")]
+    #[test]
+    fn test_include_pem_url() {
+        const ROOTS: &[&str] = include_url_macro::include_pem_url!("https://curl.se/ca/cacert.pem");
+        const ROOTS_DER: &[&[u8]] =
+            include_url_macro::include_pem_url!("https://curl.se/ca/cacert.pem", der = true);
+        assert!(ROOTS.len() > 100);
+        assert_eq!(ROOTS.len(), ROOTS_DER.len());
+        assert!(ROOTS[0].starts_with("-----BEGIN CERTIFICATE-----\n"));
+    }
+
+    #[cfg(feature = "fluent")]
+    #[test]
+    fn test_include_fluent_url() {
+        const FTL: &str = include_url_macro::include_fluent_url!(
+            "https://httpbin.org/base64/aGVsbG8gPSBIZWxsbywgeyAkbmFtZSB9IQo="
+        );
+        assert_eq!(FTL, "hello = Hello, { $name }!\n");
+    }
+
+    #[cfg(feature = "markdown")]
+    #[test]
+    fn test_include_md_url() {
+        // httpbin decodes the path, serving "# Hi\n\n| a |\n|---|\n| 1 |\n"
+        const HTML: &str = include_url_macro::include_md_url!(
+            "https://httpbin.org/base64/IyBIaQoKfCBhIHwKfC0tLXwKfCAxIHwK"
+        );
+        const PLAIN: &str = include_url_macro::include_md_url!(
+            "https://httpbin.org/base64/IyBIaQoKfCBhIHwKfC0tLXwKfCAxIHwK",
+            extensions = [],
+        );
+        assert!(HTML.starts_with("<h1>Hi</h1>\n<table>"));
+        assert!(!PLAIN.contains("<table>"));
+    }
+
+    #[cfg(feature = "html")]
+    #[test]
+    fn test_include_html_url() {
+        // httpbin decodes the path, serving "<div><p>x</p><p class=\"v\">1.0  rc</p></div>"
+        const HTML: &str = include_url_macro::include_html_url!(
+            "https://httpbin.org/base64/PGRpdj48cD54PC9wPjxwIGNsYXNzPSJ2Ij4xLjAgIHJjPC9wPjwvZGl2Pg==",
+            select = "p.v",
+        );
+        const TEXT: &str = include_url_macro::include_html_url!(
+            "https://httpbin.org/base64/PGRpdj48cD54PC9wPjxwIGNsYXNzPSJ2Ij4xLjAgIHJjPC9wPjwvZGl2Pg==",
+            select = "p.v",
+            text = true,
+        );
+        assert_eq!(HTML, "<p class=\"v\">1.0  rc</p>");
+        assert_eq!(TEXT, "1.0 rc");
+    }
+
+    // httpbin decodes the path, serving an RSS channel titled "Blog" with one item "Hi"
+    // linking to https://example.com/1
+    #[cfg(feature = "feed")]
+    include_url_macro::include_rss_url!(
+        mod blog,
+        "https://httpbin.org/base64/PHJzcyB2ZXJzaW9uPSIyLjAiPjxjaGFubmVsPjx0aXRsZT5CbG9nPC90aXRsZT48aXRlbT48dGl0bGU+SGk8L3RpdGxlPjxsaW5rPmh0dHBzOi8vZXhhbXBsZS5jb20vMTwvbGluaz48L2l0ZW0+PC9jaGFubmVsPjwvcnNzPg=="
+    );
+
+    #[cfg(feature = "feed")]
+    #[test]
+    fn test_include_rss_url() {
+        assert_eq!(blog::TITLE, "Blog");
+        assert_eq!(blog::ENTRIES.len(), 1);
+        assert_eq!(blog::ENTRIES[0].title, "Hi");
+        assert_eq!(blog::ENTRIES[0].link, "https://example.com/1");
+        assert_eq!(blog::ENTRIES[0].date, None);
+    }
 }